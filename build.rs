@@ -0,0 +1,44 @@
+use std::process::Command;
+
+fn main() {
+    let git_commit = Command::new("git")
+        .args(["rev-parse", "--short", "HEAD"])
+        .output()
+        .ok()
+        .filter(|output| output.status.success())
+        .and_then(|output| String::from_utf8(output.stdout).ok())
+        .map(|commit| commit.trim().to_string())
+        .unwrap_or_else(|| "unknown".to_string());
+    println!("cargo:rustc-env=INTMAX_GIT_COMMIT={git_commit}");
+
+    // e.g., "0.1.0 (51c62fa)"
+    let rollup_interface_version = std::fs::read_to_string("Cargo.lock")
+        .ok()
+        .and_then(|lock_file| {
+            let package = lock_file
+                .split("[[package]]")
+                .find(|package| package.contains("name = \"intmax-rollup-interface\""))?
+                .to_string();
+            let version = package
+                .lines()
+                .find_map(|line| line.strip_prefix("version = "))?
+                .trim_matches('"')
+                .to_string();
+            let rev = package
+                .lines()
+                .find_map(|line| line.strip_prefix("source = "))
+                .and_then(|source| source.trim_matches('"').split('#').nth(1))
+                .map(|rev| rev.chars().take(7).collect::<String>());
+
+            Some(match rev {
+                Some(rev) => format!("{version} ({rev})"),
+                None => version,
+            })
+        })
+        .unwrap_or_else(|| "unknown".to_string());
+    println!("cargo:rustc-env=INTMAX_ROLLUP_INTERFACE_VERSION={rollup_interface_version}");
+
+    println!("cargo:rerun-if-changed=Cargo.lock");
+    println!("cargo:rerun-if-changed=.git/HEAD");
+    println!("cargo:rerun-if-changed=.git/refs");
+}
@@ -0,0 +1,83 @@
+//! Register a distributor and a few recipients, and mint tokens to all the recipients at once,
+//! which is what `tx bulk-mint` does with a CSV file.
+//! The outputs are packed into as few transactions as the rollup allows.
+//!
+//! ```sh
+//! INTMAX_AGGREGATOR_URL=http://localhost:8080 cargo run --release --example airdrop
+//! ```
+
+use intmax::{
+    intmax_rollup_interface::intmax_zkp_core::{
+        sparse_merkle_tree::goldilocks_poseidon::WrappedHashOut,
+        transaction::asset::{ContributedAsset, TokenKind},
+        zkdsa::account::Account,
+    },
+    service::{builder::ServiceBuilder, bulk::RecipientOutcome, functions::bulk_mint},
+    utils::{
+        config::DEFAULT_AGGREGATOR_URL,
+        config_env::AGGREGATOR_URL_ENV,
+        key_management::{memory::WalletOnMemory, types::Wallet},
+    },
+};
+
+const N_RECIPIENTS: usize = 5;
+
+#[tokio::main]
+async fn main() -> anyhow::Result<()> {
+    let aggregator_url =
+        std::env::var(AGGREGATOR_URL_ENV).unwrap_or_else(|_| DEFAULT_AGGREGATOR_URL.to_string());
+    let service = ServiceBuilder::new(&aggregator_url);
+    service.check_health().await?;
+
+    // The wallet is written into a temporary directory instead of the one of the CLI.
+    let wallet_dir = std::env::temp_dir().join(format!("intmax-example-{}", std::process::id()));
+    std::fs::create_dir_all(&wallet_dir)?;
+    let mut wallet = WalletOnMemory::new(wallet_dir.join("wallet"), String::new());
+
+    let distributor = Account::new(*WrappedHashOut::rand());
+    service.register_account(distributor.public_key).await?;
+    wallet.add_account(distributor)?;
+
+    // The recipients only need to be registered. Their keys are not kept.
+    let mut distribution = vec![];
+    for i in 0..N_RECIPIENTS {
+        let recipient = Account::new(*WrappedHashOut::rand());
+        service.register_account(recipient.public_key).await?;
+        distribution.push(ContributedAsset {
+            receiver_address: recipient.address,
+            kind: TokenKind {
+                contract_address: distributor.address,
+                variable_index: 0u8.into(),
+            },
+            amount: 10 * (i as u64 + 1),
+        });
+    }
+    // The registrations are approved before the tokens are sent.
+    service.trigger_propose_block().await?;
+    service.trigger_approve_block().await?;
+
+    // `read_distribution_from_csv` reads the same list from the CSV file of `tx bulk-mint`.
+    let recipients = bulk_mint(
+        &service,
+        &mut wallet,
+        distributor.address,
+        distribution,
+        true,
+    )
+    .await?;
+    wallet.backup()?;
+
+    for recipient in recipients {
+        let outcome = match recipient.outcome {
+            RecipientOutcome::Delivered { .. } => "delivered".to_string(),
+            RecipientOutcome::Pending { tx_hash } => format!("pending in {tx_hash}"),
+            RecipientOutcome::Failed { reason, .. } => format!("failed: {reason}"),
+        };
+        println!(
+            "{} {}: {outcome}",
+            recipient.asset.amount, recipient.asset.receiver_address
+        );
+    }
+
+    Ok(())
+}
@@ -0,0 +1,73 @@
+//! Register two accounts, mint a token to the first one and send a part of it to the second one,
+//! which is what `account add`, `tx mint` and `tx send` do.
+//!
+//! ```sh
+//! INTMAX_AGGREGATOR_URL=http://localhost:8080 cargo run --release --example mint_and_send
+//! ```
+
+use intmax::{
+    intmax_rollup_interface::intmax_zkp_core::{
+        sparse_merkle_tree::goldilocks_poseidon::WrappedHashOut,
+        transaction::asset::{ContributedAsset, TokenKind},
+        zkdsa::account::Account,
+    },
+    service::{builder::ServiceBuilder, functions::transfer},
+    utils::{
+        config::DEFAULT_AGGREGATOR_URL,
+        config_env::AGGREGATOR_URL_ENV,
+        key_management::{memory::WalletOnMemory, types::Wallet},
+    },
+};
+
+#[tokio::main]
+async fn main() -> anyhow::Result<()> {
+    let aggregator_url =
+        std::env::var(AGGREGATOR_URL_ENV).unwrap_or_else(|_| DEFAULT_AGGREGATOR_URL.to_string());
+    let service = ServiceBuilder::new(&aggregator_url);
+    service.check_health().await?;
+
+    // The wallet is written into a temporary directory instead of the one of the CLI.
+    let wallet_dir = std::env::temp_dir().join(format!("intmax-example-{}", std::process::id()));
+    std::fs::create_dir_all(&wallet_dir)?;
+    let mut wallet = WalletOnMemory::new(wallet_dir.join("wallet"), String::new());
+
+    let mut accounts = vec![];
+    for _ in 0..2 {
+        let account = Account::new(*WrappedHashOut::rand());
+        service.register_account(account.public_key).await?;
+        wallet.add_account(account)?;
+        accounts.push(account);
+    }
+    let (alice, bob) = (accounts[0].address, accounts[1].address);
+
+    // Only the tokens whose token address is the address of the minter can be minted.
+    let kind = TokenKind {
+        contract_address: alice,
+        variable_index: 0u8.into(),
+    };
+    let deposit = ContributedAsset {
+        receiver_address: alice,
+        kind,
+        amount: 100,
+    };
+    service.deposit_assets(alice, vec![deposit]).await?;
+    service.resolve_server_health_issue().await?;
+    service.trigger_propose_block().await?;
+    service.trigger_approve_block().await?;
+    wallet.backup()?;
+
+    let output = ContributedAsset {
+        receiver_address: bob,
+        kind,
+        amount: 30,
+    };
+    let tx_hash = transfer(&service, &mut wallet, alice, &[output], true, true).await?;
+    wallet.backup()?;
+
+    if let Some(tx_hash) = tx_hash {
+        println!("sent 30 tokens from {alice} to {bob} in {tx_hash}");
+    }
+    println!("the wallet was written into {}", wallet_dir.display());
+
+    Ok(())
+}
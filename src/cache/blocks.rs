@@ -0,0 +1,137 @@
+//! The approved blocks and their details, kept in `<wallet dir>/blocks/` and keyed by block number.
+//! An approved block never changes, so a cached block is used without asking the aggregator.
+//! A file which cannot be read is removed and the block is fetched again.
+
+use std::{
+    fs::{create_dir_all, File},
+    io::{BufReader, BufWriter, Write},
+    path::{Path, PathBuf},
+};
+
+use anyhow::Context;
+use intmax_rollup_interface::{
+    interface::BlockDetails,
+    intmax_zkp_core::{
+        plonky2::plonk::config::{GenericConfig, PoseidonGoldilocksConfig},
+        rollup::block::BlockInfo,
+    },
+};
+use serde::{de::DeserializeOwned, Serialize};
+
+const D: usize = 2;
+type C = PoseidonGoldilocksConfig;
+type F = <C as GenericConfig<D>>::F;
+
+pub const BLOCK_CACHE_DIR_NAME: &str = "blocks";
+
+#[derive(Clone, Debug)]
+pub struct BlockStore {
+    dir: PathBuf,
+}
+
+impl BlockStore {
+    pub fn new(dir: PathBuf) -> Self {
+        Self { dir }
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    fn block_path(&self, block_number: u32) -> PathBuf {
+        self.dir.join(format!("{block_number}.json"))
+    }
+
+    fn block_details_path(&self, block_number: u32) -> PathBuf {
+        self.dir.join(format!("{block_number}.details.json"))
+    }
+
+    pub fn contains_block(&self, block_number: u32) -> bool {
+        self.block_path(block_number).is_file()
+    }
+
+    pub fn read_block(&self, block_number: u32) -> Option<BlockInfo<F>> {
+        let block: BlockInfo<F> = read_cached_file(&self.block_path(block_number))?;
+        if block.header.block_number != block_number {
+            let _ = std::fs::remove_file(self.block_path(block_number));
+            return None;
+        }
+
+        Some(block)
+    }
+
+    pub fn write_block(&self, block: &BlockInfo<F>) -> anyhow::Result<()> {
+        write_cached_file(&self.block_path(block.header.block_number), block)
+    }
+
+    pub fn read_block_details(&self, block_number: u32) -> Option<BlockDetails> {
+        read_cached_file(&self.block_details_path(block_number))
+    }
+
+    pub fn write_block_details(
+        &self,
+        block_number: u32,
+        block_details: &BlockDetails,
+    ) -> anyhow::Result<()> {
+        write_cached_file(&self.block_details_path(block_number), block_details)
+    }
+}
+
+/// Returns `None` if the file does not exist or is broken. A broken file is removed.
+fn read_cached_file<T: DeserializeOwned>(file_path: &Path) -> Option<T> {
+    let file = File::open(file_path).ok()?;
+    match serde_json::from_reader(BufReader::new(file)) {
+        Ok(value) => Some(value),
+        Err(err) => {
+            eprintln!(
+                "WARNING: removed the broken cache {}: {err}",
+                file_path.display()
+            );
+            let _ = std::fs::remove_file(file_path);
+
+            None
+        }
+    }
+}
+
+/// The file is renamed into place after it is written, so an interrupted write leaves no broken file.
+fn write_cached_file(file_path: &Path, value: &impl Serialize) -> anyhow::Result<()> {
+    let dir = file_path.parent().unwrap();
+    create_dir_all(dir).with_context(|| format!("fail to create {}", dir.display()))?;
+
+    let tmp_path = file_path.with_extension("tmp");
+    let file = File::create(&tmp_path)
+        .with_context(|| format!("fail to create {}", tmp_path.display()))?;
+    let mut writer = BufWriter::new(file);
+    serde_json::to_writer(&mut writer, value)?;
+    writer.flush()?;
+    drop(writer);
+    std::fs::rename(&tmp_path, file_path)
+        .with_context(|| format!("fail to write {}", file_path.display()))?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::test_utils::temp_dir;
+
+    #[test]
+    fn test_cached_file() {
+        let temp_dir = temp_dir();
+        let dir = temp_dir.path().join("block-cache");
+        let store = BlockStore::new(dir.join(BLOCK_CACHE_DIR_NAME));
+        let file_path = store.block_path(3);
+        assert_eq!(read_cached_file::<Vec<u32>>(&file_path), None);
+
+        write_cached_file(&file_path, &vec![1u32, 2, 3]).unwrap();
+        assert!(store.contains_block(3));
+        assert_eq!(read_cached_file(&file_path), Some(vec![1u32, 2, 3]));
+        assert!(!file_path.with_extension("tmp").exists());
+
+        std::fs::write(&file_path, "{").unwrap();
+        assert_eq!(read_cached_file::<Vec<u32>>(&file_path), None);
+        assert!(!store.contains_block(3));
+    }
+}
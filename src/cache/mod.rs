@@ -0,0 +1,3 @@
+//! Data fetched from the aggregator and kept on disk, so that it is not downloaded again by the next command.
+
+pub mod blocks;
@@ -1,59 +1,218 @@
 use std::{
-    fs::{create_dir, File},
-    io::{Read, Write},
-    path::PathBuf,
+    collections::HashSet,
+    fs::{create_dir_all, File},
+    path::{Path, PathBuf},
     str::FromStr,
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
 };
 
 use anyhow::Context;
-use dialoguer::Confirm;
 use intmax_interoperability_plugin::ethers::{
-    prelude::k256::ecdsa::SigningKey,
     types::{H160, U256},
     utils::secret_key_to_address,
 };
-use intmax_rollup_interface::intmax_zkp_core::{
-    plonky2::{
-        field::{goldilocks_field::GoldilocksField, types::Field},
-        plonk::config::{GenericConfig, GenericHashOut, PoseidonGoldilocksConfig},
-    },
-    rollup::gadgets::deposit_block::VariableIndex,
-    sparse_merkle_tree::goldilocks_poseidon::WrappedHashOut,
-    transaction::asset::{ContributedAsset, TokenKind},
-    zkdsa::account::{Account, Address},
+use intmax_rollup_interface::{
+    constants::ROLLUP_CONSTANTS,
+    intmax_zkp_core::{
+        plonky2::{
+            field::{goldilocks_field::GoldilocksField, types::Field},
+            plonk::config::{GenericConfig, GenericHashOut, PoseidonGoldilocksConfig},
+        },
+        rollup::gadgets::deposit_block::VariableIndex,
+        sparse_merkle_tree::goldilocks_poseidon::WrappedHashOut,
+        transaction::asset::{ContributedAsset, TokenKind},
+        zkdsa::account::{Account, Address},
+    },
 };
 use num_bigint::BigUint;
-use structopt::StructOpt;
-
+use structopt::{clap::AppSettings, StructOpt};
+
+#[cfg(feature = "remote-prover")]
+use crate::prover::backend::{register_prover, RemoteProver};
+#[cfg(feature = "dev")]
+use crate::service::replay::replay_block;
+#[cfg(feature = "verbose")]
+use crate::utils::debug_format::DebugFormat;
+#[cfg(feature = "dev")]
+use crate::utils::fixtures::generate_fixtures;
 use crate::{
+    cache::blocks::BLOCK_CACHE_DIR_NAME,
+    progress,
     service::{
+        batch::{BatchSpec, SendTarget},
+        block_stats::BlockStats,
+        block_view::{BlockList, BlockSummary, BlockView, DEFAULT_LISTED_BLOCKS},
         builder::*,
-        ethereum::{get_network_config, gwei_to_wei},
-        functions::{bulk_mint, create_transaction_proof, merge, parse_address, transfer},
+        bulk::{BulkReport, RecipientOutcome},
+        capability::Capability,
+        distribution_template,
+        error::NothingToDo,
+        error_code::{find_error_code, ERROR_CODES},
+        ethereum::{
+            get_network_config_with_rpc_url, get_rpc_status, get_rpc_url, gwei_to_wei,
+            resolve_network_config, wei_to_gwei, GasPolicy,
+        },
+        export::{export_blocks, ExportFormat},
+        external_signer::{read_json, write_json, SignedRoot, UnsignedTransaction},
+        faucet::{fund_from_faucet, get_balance, get_faucet_url},
+        functions::{
+            bulk_mint, check_recipients_registration, confirm_delivery, create_transaction_proof,
+            ensure_assets_held, ensure_recipients_registered, merge, parse_address,
+            parse_recipient, resolve_token_address, simulate_merge, transfer,
+            transfer_from_accounts, wait_for_approval, warn_unmerged_assets_summary,
+            DefaultTokenAddress,
+        },
+        header_proof::BlockHeaderProof,
+        history::{build_tx_history, calc_assets_at_block},
         interoperability::{
-            activate_offer, get_offer, get_token_metadata, is_token_allowed, lock_offer,
-            register_transfer, unlock_offer, MakerTransferInfo, NetworkName, TakerTransferInfo,
+            activate_offer, check_unlock_witness, encode_intmax_account, get_offer,
+            get_offer_events, get_token_metadata, is_token_allowed, lock_offer, unlock_offer,
+            MakerTransferInfo, NetworkName, TakerTransferInfo,
+        },
+        offer_events::{offer_events_to_csv, OfferEventFilter, OfferEventsFormat},
+        planner::{compose_transfers, into_rounds, TransferIntent, TransferLimits},
+        prompt::{confirm_by_typing, confirm_distribution, select_payment_method},
+        proof_batch::{list_proof_files, verify_proof_artifact, verify_proof_files},
+        read_distribution_file,
+        receipt::{verify_receipt, PaymentClaim},
+        register_intent::{
+            get_temporary_receiver_address, resume_register_intents, verify_registered_offer,
+            RegisterIntent, RegisterIntentStatus, RegisterIntents, REGISTER_INTENTS_FILE_NAME,
         },
-        prompt::select_payment_method,
-        read_distribution_from_csv,
+        statement::{build_statement, statement_to_csv},
+        validate_distribution_token_addresses,
     },
     utils::{
-        key_management::{memory::WalletOnMemory, types::Wallet},
-        nickname::{NicknameTable, ReservedNicknameTable},
+        aggregator_url::{canonicalize_aggregator_url, resolve_aggregator_host},
+        audit::{read_audit_log, verify_audit_log, AuditRecorder, AUDIT_LOG_FILE_NAME},
+        backup::{
+            read_backup_password, read_existing_backup_password, write_encrypted_backup,
+            BackupArchive, BACKUP_DIR_NAME,
+        },
+        bech32::encode_intmax_address,
+        config::{edit_config_file, Config, HooksConfig},
+        config_env::{EnvConfig, LogLevel},
+        hex_string::{
+            is_address_literal, parse_h160, parse_hash_out, parse_intmax_address,
+            parse_private_key_env, read_private_key_env,
+        },
+        human_format::{format_duration, format_size},
+        key_management::{
+            encryption::{
+                prompt_new_wallet_password, read_encrypted_file, read_new_wallet_password,
+                read_wallet_password, read_wallet_password_env, write_encrypted_file,
+                WALLET_PASSWORD_ENV,
+            },
+            memory::{list_account_files, SerializableUserState, WalletOnMemory},
+            mnemonic::{derive_private_key, generate_mnemonic, mnemonic_to_seed, read_mnemonic},
+            read_private_key_from_stdin,
+            recent_sends::DEFAULT_DUPLICATE_WINDOW,
+            stats::{format_date, parse_date, TransferDirection},
+            storage::{warn_wallet_size, WalletStorageStats, DEFAULT_WALLET_SIZE_WARNING},
+            types::{MergePolicy, TransactionStatus, TxHash, Wallet},
+        },
+        nickname::{
+            address_to_nickname, nickname_to_address,
+            store::{NicknameStore, NICKNAME_FILE_NAME},
+            NicknameTable, ReservedNicknameTable, TOKEN_ADDRESS_MINE, TOKEN_ADDRESS_RECEIVER,
+        },
+        proof_format::{decode_proof_artifact, encode_proof_artifact, ProofArtifact},
+        prover::{ProverConfig, ProvingTimes, PROVING_TIMES_FILE_NAME},
+        qr::{encode_payment_request, render_qr_code, resolve_qr_image_argument, save_qr_code},
+        short_address::AddressRenderer,
+        signer::{
+            add_signer, read_signer_password, read_signing_key_from_stdin, remove_signer,
+            resolve_signer_secret_key, SignerTable, SIGNERS_DIR_NAME,
+        },
+        terminal::TerminalOptions,
+        version::{feature_not_compiled_error, VersionInfo, FEATURES},
+        wallet_dir::{
+            find_duplicate_wallet_dirs, find_source_wallet_dir, migrate_wallet_dir,
+            resolve_wallet_dir, wallet_dir_name, WALLET_FILE_NAME,
+        },
     },
 };
 
+mod watch;
+
+use watch::watch;
+
 const D: usize = 2;
 type C = PoseidonGoldilocksConfig;
 type F = <C as GenericConfig<D>>::F;
 
-const DEFAULT_AGGREGATOR_URL: &str = "http://localhost:8080";
+/// How long `account notify` asks the aggregator to hold each poll.
+const NOTIFY_LONG_POLL_WAIT: Duration = Duration::from_secs(30);
 
 #[derive(Debug, StructOpt)]
-#[structopt(name = "intmax")]
+#[structopt(name = "intmax", global_settings = &[AppSettings::DisableVersion])]
 pub struct Command {
+    /// Display the version, the supported aggregator versions and the enabled features.
+    #[structopt(long, short = "V")]
+    pub version: bool,
+
+    /// Print the results in JSON instead of tables, e.g., for scripts. Progress messages are suppressed.
+    #[structopt(long, global = true)]
+    pub json: bool,
+
+    /// Profile in the config file. If omitted, `default_profile` is used.
+    #[structopt(long, global = true)]
+    pub profile: Option<String>,
+
+    /// Disable colored output. This is also enabled by NO_COLOR or TERM=dumb.
+    #[structopt(long = "no-color", global = true)]
+    pub no_color: bool,
+
+    /// Use only ASCII characters in output. This is also enabled by TERM=dumb.
+    #[structopt(long, global = true)]
+    pub ascii: bool,
+
+    /// Never wait for input. Every prompt fails unless the flag to skip it
+    /// (e.g., --yes, --taker-token) is given. This is also enabled by CI.
+    #[structopt(long = "non-interactive", alias = "no-input", global = true)]
+    pub non_interactive: bool,
+
+    /// The number of threads used for proving. [default: all cores]
+    #[structopt(long = "prove-threads", alias = "threads", global = true)]
+    pub prove_threads: Option<usize>,
+
+    /// Upper limit of memory used for proving in MiB.
+    /// The number of proving threads is reduced to fit in it.
+    #[structopt(long = "prove-memory-budget", global = true)]
+    pub prove_memory_budget: Option<u64>,
+
+    /// Display the size of each response from the aggregator.
+    #[structopt(long, global = true)]
+    pub stats: bool,
+
+    /// Answer yes to every confirmation except the one of `account reset`,
+    /// which needs `--confirm RESET` in the non-interactive mode.
+    #[structopt(long = "yes", short = "y", global = true)]
+    pub assume_yes: bool,
+
+    /// Suppress progress messages and print only the results, warnings and errors.
+    #[structopt(long, global = true)]
+    pub quiet: bool,
+
+    /// Print complete addresses in tables instead of truncated ones, e.g., 0x714b…7e6d.
+    #[structopt(long = "full-addresses", global = true)]
+    pub full_addresses: bool,
+
+    /// Use this RPC endpoint instead of the one of the selected network in `io` commands.
+    #[structopt(long = "rpc-url", global = true)]
+    pub rpc_url: Option<String>,
+
+    /// Write the witnesses and payloads of the transactions into this directory for bug reports.
+    #[structopt(long = "debug-dump", global = true, parse(from_os_str))]
+    pub debug_dump: Option<PathBuf>,
+
+    /// Sign Ethereum transactions in `io` and `bridge` commands with the key added by `io signer add`
+    /// instead of PRIVATE_KEY.
+    #[structopt(long, global = true)]
+    pub signer: Option<String>,
+
     #[structopt(subcommand)]
-    pub sub_command: SubCommand,
+    pub sub_command: Option<SubCommand>,
 }
 
 #[derive(Debug, StructOpt)]
@@ -76,6 +235,12 @@ pub enum SubCommand {
         #[structopt(subcommand)]
         tx_command: TransactionCommand,
     },
+    /// commands for the distribution files of `tx bulk-mint` and `tx bulk-transfer`
+    #[structopt(name = "airdrop")]
+    Airdrop {
+        #[structopt(subcommand)]
+        airdrop_command: AirdropCommand,
+    },
     /// commands for blocks on intmax
     #[structopt(name = "block")]
     Block {
@@ -96,6 +261,103 @@ pub enum SubCommand {
         #[structopt(subcommand)]
         bridge_command: BridgeCommand,
     },
+    /// commands for developers
+    #[cfg(feature = "dev")]
+    #[structopt(name = "dev")]
+    Dev {
+        #[structopt(subcommand)]
+        dev_command: DevCommand,
+    },
+    /// [not compiled in] commands for interoperability. Rebuild with `--features interoperability`.
+    #[cfg(not(feature = "interoperability"))]
+    #[structopt(
+        name = "io",
+        settings = &[AppSettings::TrailingVarArg, AppSettings::AllowLeadingHyphen]
+    )]
+    InteroperabilityNotCompiled {
+        #[structopt(hidden = true)]
+        args: Vec<String>,
+    },
+    /// [not compiled in] commands for bridge. Rebuild with `--features bridge`.
+    #[cfg(not(feature = "bridge"))]
+    #[structopt(
+        name = "bridge",
+        settings = &[AppSettings::TrailingVarArg, AppSettings::AllowLeadingHyphen]
+    )]
+    BridgeNotCompiled {
+        #[structopt(hidden = true)]
+        args: Vec<String>,
+    },
+    /// [not compiled in] commands for developers. Rebuild with `--features dev`.
+    #[cfg(not(feature = "dev"))]
+    #[structopt(
+        name = "dev",
+        settings = &[AppSettings::TrailingVarArg, AppSettings::AllowLeadingHyphen]
+    )]
+    DevNotCompiled {
+        #[structopt(hidden = true)]
+        args: Vec<String>,
+    },
+    /// Build all proof circuits ahead of time and display the time taken for each of them.
+    /// The prover settings in the config file and the global options are applied.
+    #[structopt(name = "warmup")]
+    Warmup {},
+    /// Sign the proposed blocks including your pending transactions as soon as they are proposed,
+    /// so that they are not reverted for lack of your signature. It runs until interrupted.
+    #[structopt(name = "watch")]
+    Watch {
+        /// Watch only this account. [default: all accounts]
+        #[structopt(long, short = "u")]
+        user_address: Option<String>,
+        /// seconds between polls
+        #[structopt(long, default_value = "10")]
+        interval: u64,
+        /// Sign the blocks proposed now and exit, e.g., in a cron job.
+        #[structopt(long)]
+        once: bool,
+    },
+    /// List the features compiled in this binary and the commands they provide.
+    #[structopt(name = "features")]
+    Features {},
+    /// commands for the log of state-changing operations
+    #[structopt(name = "audit")]
+    Audit {
+        #[structopt(subcommand)]
+        audit_command: AuditCommand,
+    },
+    /// Display the causes of an error and how to recover from it.
+    /// The code is printed with the error, e.g., EAGG_502. If omitted, all codes are listed.
+    #[structopt(name = "explain")]
+    Explain { code: Option<String> },
+    /// commands for the proof files exported by `account transaction-proof` and `block header-proof`
+    #[structopt(name = "proof")]
+    Proof {
+        #[structopt(subcommand)]
+        proof_command: ProofCommand,
+    },
+}
+
+#[derive(Debug, StructOpt)]
+pub enum ProofCommand {
+    /// Verify a proof file. Its type is detected from the file.
+    #[structopt(name = "verify")]
+    Verify {
+        #[structopt(long, short = "f", parse(from_os_str))]
+        file: PathBuf,
+        /// the network of the verifier contract, which is needed for inclusion proofs
+        #[structopt(long = "network", short = "n")]
+        network_name: Option<String>,
+    },
+    /// Verify every proof file in a directory in parallel and display the result of each file.
+    /// Fails if any of them is invalid.
+    #[structopt(name = "verify-batch")]
+    VerifyBatch {
+        #[structopt(long, short = "d", parse(from_os_str))]
+        dir: PathBuf,
+        /// the network of the verifier contract, which is needed for inclusion proofs
+        #[structopt(long = "network", short = "n")]
+        network_name: Option<String>,
+    },
 }
 
 #[derive(Debug, StructOpt)]
@@ -105,7 +367,41 @@ pub enum ConfigCommand {
     AggregatorUrl {
         /// aggregator URL
         aggregator_url: Option<String>,
+        /// Allow plain HTTP to a host other than localhost.
+        #[structopt(long)]
+        allow_insecure: bool,
+    },
+    /// Open the config file with $EDITOR. The file is validated before it is saved.
+    #[structopt(name = "edit")]
+    Edit {},
+    /// Set the default profile. If omitted, the profiles are displayed.
+    #[structopt(name = "profile")]
+    Profile {
+        /// profile name
+        profile_name: Option<String>,
+    },
+    /// Enable or disable the privacy mode of the profile. If omitted, the current setting is displayed.
+    /// Transfers consume randomly chosen leaves and received assets are merged in random order,
+    /// which makes the change harder to trace but consumes more leaves per transfer.
+    #[structopt(name = "privacy")]
+    Privacy {
+        /// true or false
+        enabled: Option<bool>,
+    },
+}
+
+#[derive(Debug, StructOpt)]
+pub enum AuditCommand {
+    /// Display the operations recorded in ~/.intmax/audit.log.
+    #[structopt(name = "show")]
+    Show {
+        /// Display only the last N entries.
+        #[structopt(long, short = "n")]
+        limit: Option<usize>,
     },
+    /// Check that no entry of the audit log was modified or removed.
+    #[structopt(name = "verify")]
+    Verify {},
 }
 
 #[derive(Debug, StructOpt)]
@@ -113,16 +409,53 @@ pub enum AccountCommand {
     /// [danger operation] Initializing your wallet and delete your all accounts and nicknames.
     #[structopt(name = "reset")]
     Reset {
-        #[structopt(short = "y", long = "yes")]
-        assume_yes: bool,
+        /// Type `RESET` or the aggregator host in advance instead of the prompt.
+        #[structopt(long)]
+        confirm: Option<String>,
+        /// Write an encrypted backup of the wallet and nicknames into ~/.intmax/backups before resetting.
+        /// The password is read from INTMAX_BACKUP_PASSWORD or prompted.
+        #[structopt(long)]
+        backup_first: bool,
+    },
+    /// Merge the wallet and nicknames kept for another aggregator URL into the current one (or --to-url),
+    /// e.g., after the aggregator was renamed. Accounts and nicknames which already exist are never overwritten.
+    #[structopt(name = "migrate-data")]
+    MigrateData {
+        /// the aggregator URL, or the name of its directory in ~/.intmax
+        #[structopt(long)]
+        from_url: String,
+        /// [default: the current aggregator URL]
+        #[structopt(long)]
+        to_url: Option<String>,
+        /// Remove the source directory if all of its data was merged.
+        #[structopt(long)]
+        remove_source: bool,
     },
     /// Add your account.
     #[structopt(name = "add")]
     Add {
         /// Specify private key. If not specified, it is chosen at random.
+        /// NOTICE: The key given here is left in your shell history. Use --private-key-stdin instead.
         #[structopt(long)]
         private_key: Option<WrappedHashOut<F>>,
 
+        /// Read private key from stdin, e.g., `intmax account add --private-key-stdin < key.txt`.
+        #[structopt(long, conflicts_with = "private-key")]
+        private_key_stdin: bool,
+
+        /// Derive the account from a mnemonic, which is prompted or read from stdin.
+        /// Run it again with the same mnemonic and --count to recreate your accounts.
+        #[structopt(long, conflicts_with_all = &["private-key", "private-key-stdin"])]
+        mnemonic: bool,
+
+        /// Index of the first account derived from the mnemonic [default: 0]
+        #[structopt(long)]
+        index: Option<u32>,
+
+        /// Number of accounts derived from the mnemonic [default: 1]
+        #[structopt(long)]
+        count: Option<u32>,
+
         /// Add nickname
         #[structopt(long)]
         nickname: Option<String>,
@@ -131,20 +464,159 @@ pub enum AccountCommand {
         #[structopt(long = "default")]
         is_default: bool,
     },
+    /// Generate a mnemonic to derive accounts from with `account add --mnemonic`.
+    /// Write it down and keep it offline. It is not stored anywhere.
+    #[structopt(name = "new-mnemonic")]
+    NewMnemonic {},
+    /// Write one account, including its assets and sent transactions, into an encrypted file
+    /// to move it into another wallet with `account import`.
+    /// The password is read from INTMAX_BACKUP_PASSWORD or prompted.
+    #[structopt(name = "export")]
+    Export {
+        /// user address [default: default account]
+        #[structopt(long = "address", short = "u")]
+        user_address: Option<String>,
+        #[structopt(long, parse(from_os_str))]
+        file: PathBuf,
+    },
+    /// Add the account written by `account export`. An account which is already in your wallet is never overwritten.
+    #[structopt(name = "import")]
+    Import {
+        #[structopt(long, parse(from_os_str))]
+        file: PathBuf,
+        /// Set as default account.
+        #[structopt(long = "default")]
+        is_default: bool,
+    },
     /// List your addresses.
     #[structopt(name = "list")]
-    List {},
+    List {
+        /// Display the addresses in bech32, e.g., intmax1w99acmecj3lx6sfqt4w.
+        #[structopt(long)]
+        bech32: bool,
+    },
     /// Sets the default user account used when --user-address attribute is omitted in other commands.
     #[structopt(name = "set-default")]
     SetDefault {
         /// default user address
         user_address: Option<String>,
     },
+    /// Encrypt the private keys in the wallet with a new password, or change it.
+    /// The password of an encrypted wallet is read from INTMAX_WALLET_PASSWORD or prompted by every command.
+    #[structopt(name = "set-password")]
+    SetPassword {
+        /// Decrypt the private keys and keep them in plaintext.
+        #[structopt(long)]
+        remove: bool,
+    },
+    /// Display or set when the received assets are merged before sending a transaction.
+    /// `aggressive` merges them as much as possible, which may send several merge transactions first.
+    /// `lazy` merges only if the assets to send are not available without merging.
+    /// `manual` never merges automatically, so run `tx merge` in advance.
+    #[structopt(name = "merge-policy")]
+    MergePolicy {
+        /// aggressive, lazy or manual
+        merge_policy: Option<MergePolicy>,
+    },
+    /// Display your address as a QR code.
+    /// The address arguments of other commands can be given as a path to the QR image.
+    #[structopt(name = "qr")]
+    Qr {
+        /// user address [default: default account]
+        #[structopt(long = "address")]
+        user_address: Option<String>,
+        /// Include a payment request of the token.
+        #[structopt(long = "token-address", short = "a")]
+        contract_address: Option<String>,
+        /// the token id of the payment request
+        #[structopt(long = "token-id", short = "i")]
+        token_id: Option<VariableIndex<F>>,
+        /// the amount of the payment request
+        #[structopt(long, short = "q")]
+        amount: Option<u64>,
+        /// Write the QR code into the PNG file.
+        #[structopt(long = "output", short = "o")]
+        output_path: Option<PathBuf>,
+    },
     /// Display your assets.
     #[structopt(name = "assets")]
     Assets {
         #[structopt(long, short = "u")]
         user_address: Option<String>,
+        /// Display the assets held at this block instead, reconstructed from the cached transfer history.
+        #[structopt(long = "at-block")]
+        at_block: Option<u32>,
+    },
+    /// Display only the amount of a token you hold, including the unmerged assets.
+    /// It fails if the token is not held.
+    #[structopt(name = "balance")]
+    Balance {
+        #[structopt(long, short = "u")]
+        user_address: Option<String>,
+        /// token address [default: your own token]
+        #[structopt(long = "token-address", short = "a")]
+        contract_address: Option<String>,
+        /// [default: 0x00]
+        #[structopt(long = "token-id", short = "i")]
+        token_id: Option<VariableIndex<F>>,
+        /// Read the local wallet without synchronizing it with the aggregator.
+        #[structopt(long = "no-sync")]
+        no_sync: bool,
+    },
+    /// Wait until a new asset is received, and display it in JSON.
+    /// Assets received after the last sync of your wallet are regarded as new.
+    #[structopt(name = "notify")]
+    Notify {
+        #[structopt(long, short = "u")]
+        user_address: Option<String>,
+        /// Fail if no asset is received in the given seconds. [default: wait forever]
+        #[structopt(long)]
+        timeout: Option<u64>,
+    },
+    /// Display the totals of the tokens you sent and received for each month and counterparty.
+    /// The totals are updated when your wallet is synchronized.
+    #[structopt(name = "stats")]
+    Stats {
+        #[structopt(long, short = "u")]
+        user_address: Option<String>,
+        /// Display only the token of the given address or nickname.
+        #[structopt(long = "token")]
+        contract_address: Option<String>,
+        /// Display only the token id. It requires --token.
+        #[structopt(long = "token-id", short = "i")]
+        token_id: Option<VariableIndex<F>>,
+    },
+    /// Export a CSV statement of the transfers in a period with the running balance of each token.
+    /// Received transfers are dated when your wallet was synchronized.
+    #[structopt(name = "statement")]
+    Statement {
+        #[structopt(long, short = "u")]
+        user_address: Option<String>,
+        /// the first day of the period in UTC, e.g., 2024-01-01
+        #[structopt(long)]
+        from: String,
+        /// the last day of the period in UTC, e.g., 2024-03-31
+        #[structopt(long)]
+        to: String,
+        /// [default: stdout]
+        #[structopt(long, short = "o", parse(from_os_str))]
+        output: Option<PathBuf>,
+    },
+    /// Display how many bytes of your wallet file each account and the asset tree nodes take.
+    #[structopt(name = "storage-stats")]
+    StorageStats {},
+    /// Sign the message exported by `tx export-unsigned` with your private key.
+    /// This does not connect to the aggregator, so it can be run on an offline machine.
+    #[structopt(name = "sign-root")]
+    SignRoot {
+        #[structopt(long, short = "u")]
+        user_address: Option<String>,
+        /// the proposed world state root to sign
+        #[structopt(long)]
+        message: String,
+        /// Write the signature into the file instead of stdout.
+        #[structopt(long = "output", short = "o", parse(from_os_str))]
+        output_path: Option<PathBuf>,
     },
     /// commands for account nicknames.
     #[structopt(name = "nickname")]
@@ -168,6 +640,9 @@ pub enum AccountCommand {
         /// choose "scroll" (Scroll Alpha)
         #[structopt(long = "network", short = "n")]
         network_name: Option<String>,
+        /// Write the proof in the `intmax-proof` JSON format instead of displaying the witness.
+        #[structopt(long, short = "o", parse(from_os_str))]
+        output: Option<PathBuf>,
     },
 }
 
@@ -185,6 +660,14 @@ pub enum NicknameCommand {
     /// Display nicknames.
     #[structopt(name = "list")]
     List {},
+    /// Display the address of the nickname, including the reserved ones.
+    /// Exit with an error if it is not found.
+    #[structopt(name = "resolve")]
+    Resolve { nickname: String },
+    /// Display the nickname of the address, including the reserved ones.
+    /// Exit with an error if it is not found.
+    #[structopt(name = "of")]
+    Of { address: String },
 }
 
 #[derive(Debug, StructOpt)]
@@ -214,8 +697,13 @@ pub enum TransactionCommand {
         user_address: Option<String>,
         /// destination of a token
         #[structopt(long, short = "r")]
-        receiver_address: String,
-        /// token address
+        receiver_address: Option<String>,
+        /// Send to several recipients in one transaction, e.g., `--to alice:100 --to bob:50:0x714bdc6f38947e6d`.
+        /// Give `<recipient>:<amount>[:<token-address>[:<token-id>]]` for each recipient
+        /// instead of --receiver-address and --amount. The omitted token is given by --token-address and --token-id.
+        #[structopt(long = "to", number_of_values = 1)]
+        to: Vec<SendTarget>,
+        /// token address, nickname, `mine` (your own token) or `receiver` (the token of the recipient) [default: mine]
         #[structopt(long = "token-address", short = "a")]
         contract_address: Option<String>,
         /// the token id can be selected from 0x00 to 0xff
@@ -227,6 +715,43 @@ pub enum TransactionCommand {
         /// send NFT (an alias of `--amount 1`)
         #[structopt(long = "nft")]
         is_nft: bool,
+        /// Send even if the recipient may not be registered with the aggregator.
+        #[structopt(long = "allow-unregistered")]
+        allow_unregistered: bool,
+        /// Wait until the block including the transaction is approved.
+        #[structopt(long)]
+        wait: bool,
+        /// Leave the proposal block unsigned to sign it on another machine holding the key.
+        /// See `tx export-unsigned`.
+        #[structopt(long = "no-sign")]
+        no_sign: bool,
+        /// Send from every account in the wallet holding `--amount-per-account` of the token,
+        /// sharing one proposal block. `--token-address` is required.
+        #[structopt(long = "from-all", conflicts_with = "user-address")]
+        from_all: bool,
+        /// the amount sent from each account with `--from-all`
+        #[structopt(long = "amount-per-account", requires = "from-all")]
+        amount_per_account: Option<u64>,
+        /// Send even if the same transfer was sent within `limits.duplicate_window` seconds.
+        #[structopt(long = "allow-duplicate")]
+        allow_duplicate: bool,
+    },
+    /// Send the transfers from several accounts in the wallet listed in the YAML file,
+    /// sharing one proposal block.
+    ///
+    /// transfers:
+    ///   - { from: hot1, to: treasury, token_address: 0x714bdc6f38947e6d, amount: 100 }
+    #[structopt(name = "batch-send")]
+    BatchSend {
+        /// YAML file path
+        #[structopt(long = "file", short = "f", parse(from_os_str))]
+        spec_path: PathBuf,
+        /// Send even if some recipients may not be registered with the aggregator.
+        #[structopt(long = "allow-unregistered")]
+        allow_unregistered: bool,
+        /// Wait until the block including the transactions is approved.
+        #[structopt(long)]
+        wait: bool,
     },
     /// [advanced command] Merge received your token.
     /// This is usually performed automatically before you send the transaction.
@@ -235,6 +760,9 @@ pub enum TransactionCommand {
     Merge {
         #[structopt(long, short = "u")]
         user_address: Option<String>,
+        /// Show how many proofs merging takes and how long it is expected to take, without merging.
+        #[structopt(long)]
+        dry_run: bool,
     },
     /// You can issue new token according to the contents of the file.
     /// Up to 16 tokens can be sent together.
@@ -245,11 +773,18 @@ pub enum TransactionCommand {
         #[structopt(long, short = "u")]
         user_address: Option<String>,
 
-        /// CSV file path
+        /// distribution file path. `.json`, `.yaml` and `.yml` files are read as a list of named fields, and the others as CSV.
         #[structopt(long = "file", short = "f")]
         csv_path: PathBuf,
         // #[structopt(long)]
         // json: Vec<ContributedAsset<F>>,
+        /// Refuse the file if it includes token addresses that are neither yours nor registered.
+        #[structopt(long)]
+        strict: bool,
+
+        /// Write the outcome for each recipient into this file. [default: <file>.report.json]
+        #[structopt(long, parse(from_os_str))]
+        report: Option<PathBuf>,
     },
     /// You can transfer owned tokens according to the contents of the file.
     /// Up to 8 tokens can be sent together.
@@ -260,23 +795,124 @@ pub enum TransactionCommand {
         #[structopt(long, short = "u")]
         user_address: Option<String>,
 
-        /// CSV file path
+        /// distribution file path. `.json`, `.yaml` and `.yml` files are read as a list of named fields, and the others as CSV.
         #[structopt(long = "file", short = "f")]
         csv_path: PathBuf,
         // #[structopt(long)]
         // json: Vec<ContributedAsset<F>>,
+        /// Refuse the file if it includes token addresses that are neither yours nor registered.
+        #[structopt(long)]
+        strict: bool,
+
+        /// Write the outcome for each recipient into this file. [default: <file>.report.json]
+        #[structopt(long, parse(from_os_str))]
+        report: Option<PathBuf>,
+
+        /// Send even if some recipients may not be registered with the aggregator.
+        #[structopt(long = "allow-unregistered")]
+        allow_unregistered: bool,
+    },
+    /// Display the outcome for each recipient of `bulk-mint` or `bulk-transfer`,
+    /// updating the pending ones. With `--resume`, send to the failed recipients again.
+    #[structopt(name = "bulk-status")]
+    BulkStatus {
+        /// the report file written by `bulk-mint` or `bulk-transfer`
+        #[structopt(long, parse(from_os_str))]
+        report: PathBuf,
+
+        #[structopt(long)]
+        resume: bool,
+    },
+    /// Write the messages to sign for your pending transactions in JSON,
+    /// so that they are signed by `account sign-root` on another machine holding the key.
+    #[structopt(name = "export-unsigned")]
+    ExportUnsigned {
+        #[structopt(long, short = "u")]
+        user_address: Option<String>,
+        /// Export only the given pending transaction. It can be specified more than once.
+        #[structopt(long = "tx-hash", short = "t")]
+        tx_hashes: Vec<TxHash<F>>,
+        /// Write into the file instead of stdout.
+        #[structopt(long = "output", short = "o", parse(from_os_str))]
+        output_path: Option<PathBuf>,
+    },
+    /// Send the signature written by `account sign-root` for your pending transaction.
+    #[structopt(name = "import-signature")]
+    ImportSignature {
+        #[structopt(long, short = "u")]
+        user_address: Option<String>,
+        #[structopt(long = "tx-hash", short = "t")]
+        tx_hash: TxHash<F>,
+        /// the file written by `account sign-root`
+        #[structopt(long = "signature", parse(from_os_str))]
+        signature_path: PathBuf,
+        /// Wait until the block including the transaction is approved.
+        #[structopt(long)]
+        wait: bool,
+    },
+    /// List the transfers sent from and received by your account in chronological order.
+    #[structopt(name = "history")]
+    History {
+        #[structopt(long, short = "u")]
+        user_address: Option<String>,
+    },
+    /// Verify the inclusion proof given by someone claiming to have paid you,
+    /// i.e., the file written by `account transaction-proof --output`.
+    /// It must send the claimed asset to your account in a transaction included in an approved block.
+    #[structopt(name = "verify-receipt")]
+    VerifyReceipt {
+        /// the account receiving the payment
+        #[structopt(long, short = "u")]
+        user_address: Option<String>,
+        /// the proof file
+        #[structopt(long, short = "f", parse(from_os_str))]
+        file: PathBuf,
+        /// the claimed token address or nickname. If omitted, the token is not checked.
+        #[structopt(long = "token-address", short = "a")]
+        contract_address: Option<String>,
+        /// the claimed token id
+        #[structopt(long = "token-id", short = "i")]
+        token_id: Option<VariableIndex<F>>,
+        /// the claimed amount
+        #[structopt(long, short = "q")]
+        amount: Option<u64>,
+        /// Check the witness with the verifier contract of the network as well,
+        /// which does not rely on the aggregator.
+        #[structopt(long = "network", short = "n")]
+        network_name: Option<String>,
     },
     /// [upcoming features] Exchange tokens with a specified user.
     #[structopt(name = "swap")]
     Swap {},
 }
 
+#[derive(Debug, StructOpt)]
+pub enum AirdropCommand {
+    /// Write a sample distribution file with the correct header.
+    /// The format is selected by the extension: `.json`, `.yaml` or `.yml` for named fields, and CSV otherwise.
+    #[structopt(name = "template")]
+    Template {
+        #[structopt(long, short = "o", parse(from_os_str))]
+        output: PathBuf,
+    },
+}
+
 #[derive(Debug, StructOpt)]
 pub enum BlockCommand {
     /// [advanced command] Trigger to propose a block.
     #[cfg(feature = "advanced")]
     #[structopt(name = "propose")]
     Propose {},
+    /// [not compiled in] Rebuild with `--features advanced`.
+    #[cfg(not(feature = "advanced"))]
+    #[structopt(
+        name = "propose",
+        settings = &[AppSettings::TrailingVarArg, AppSettings::AllowLeadingHyphen]
+    )]
+    ProposeNotCompiled {
+        #[structopt(hidden = true)]
+        args: Vec<String>,
+    },
     /// [advanced command] Sign to the proposal block.
     /// It is usually performed automatically after the transaction has been executed.
     /// If you do not sign the proposal block containing your transaction by the deadline,
@@ -285,11 +921,45 @@ pub enum BlockCommand {
     Sign {
         #[structopt(long, short = "u")]
         user_address: Option<String>,
+        /// Sign only for the given pending transaction. It can be specified more than once.
+        #[structopt(long = "tx-hash", short = "t")]
+        tx_hashes: Vec<TxHash<F>>,
+        /// Wait until the blocks including your signed transactions are approved.
+        #[structopt(long)]
+        wait: bool,
+    },
+    /// Display the pending transactions which have not been approved yet, including the expired ones.
+    #[structopt(name = "pending")]
+    Pending {
+        #[structopt(long, short = "u")]
+        user_address: Option<String>,
+    },
+    /// Estimate how often the aggregator approves blocks and how many transactions they include,
+    /// which tells how long a transaction takes to be approved.
+    /// The interval between blocks is measured by watching for new blocks.
+    #[structopt(name = "stats")]
+    Stats {
+        /// the number of recent blocks whose transactions are counted
+        #[structopt(long, default_value = "20")]
+        blocks: u32,
+        /// how long to watch for new blocks in seconds
+        #[structopt(long, default_value = "60")]
+        duration: u64,
     },
     /// [advanced command] Trigger to approve a block.
     #[cfg(feature = "advanced")]
     #[structopt(name = "approve")]
     Approve {},
+    /// [not compiled in] Rebuild with `--features advanced`.
+    #[cfg(not(feature = "advanced"))]
+    #[structopt(
+        name = "approve",
+        settings = &[AppSettings::TrailingVarArg, AppSettings::AllowLeadingHyphen]
+    )]
+    ApproveNotCompiled {
+        #[structopt(hidden = true)]
+        args: Vec<String>,
+    },
     /// [advanced command] Verify a approved block.
     #[cfg(feature = "advanced")]
     #[structopt(name = "verify")]
@@ -297,6 +967,59 @@ pub enum BlockCommand {
         #[structopt(long, short = "n")]
         block_number: Option<u32>,
     },
+    /// [not compiled in] Rebuild with `--features advanced`.
+    #[cfg(not(feature = "advanced"))]
+    #[structopt(
+        name = "verify",
+        settings = &[AppSettings::TrailingVarArg, AppSettings::AllowLeadingHyphen]
+    )]
+    VerifyNotCompiled {
+        #[structopt(hidden = true)]
+        args: Vec<String>,
+    },
+    /// List the approved blocks with the numbers of their transactions and deposits.
+    #[structopt(name = "list")]
+    List {
+        /// List the blocks after this block number. [default: 10 blocks before --until]
+        #[structopt(long)]
+        since: Option<u32>,
+        /// the last block number to list [default: the latest block]
+        #[structopt(long)]
+        until: Option<u32>,
+    },
+    /// Display the header, the transactions and the deposits of a block.
+    #[structopt(name = "show")]
+    Show { block_number: u32 },
+    /// Export block headers, transaction lists and deposit lists for analytics.
+    #[structopt(name = "export")]
+    Export {
+        /// the first block number to export [default: 1]
+        #[structopt(long)]
+        from: Option<u32>,
+
+        /// the last block number to export [default: the latest block]
+        #[structopt(long)]
+        to: Option<u32>,
+
+        /// output format (only `csv` is supported now)
+        #[structopt(long, default_value = "csv")]
+        format: ExportFormat,
+
+        /// output directory
+        #[structopt(long, short = "o", parse(from_os_str))]
+        output: PathBuf,
+    },
+    /// Export the proof that the block extends the header chain, which consists of
+    /// the prior block header and the inclusion proof of its hash in the block headers digest.
+    #[structopt(name = "header-proof")]
+    HeaderProof {
+        #[structopt(long = "block", short = "n")]
+        block_number: u32,
+
+        /// output JSON file [default: stdout]
+        #[structopt(long, short = "o", parse(from_os_str))]
+        output: Option<PathBuf>,
+    },
 }
 
 #[cfg(feature = "interoperability")]
@@ -309,7 +1032,7 @@ pub enum InteroperabilityCommand {
         /// destination of a token
         #[structopt(long, short = "r")]
         receiver_address: String,
-        /// token address
+        /// token address, nickname, `mine` (your own token) or `receiver` (the token of the recipient) [default: mine]
         #[structopt(long = "token-address", short = "a")]
         contract_address: Option<String>,
         /// the token id can be selected from 0x00 to 0xff
@@ -334,6 +1057,18 @@ pub enum InteroperabilityCommand {
         #[structopt(long)]
         max_gas_price: Option<f64>,
     },
+    /// Complete the registrations interrupted after the intmax transfer.
+    #[structopt(name = "repair")]
+    Repair {
+        #[structopt(long, short = "u")]
+        user_address: Option<String>,
+        /// the offer which was registered, but not recorded in the wallet
+        #[structopt(long)]
+        offer_id: Option<usize>,
+        /// choose "scroll" (Scroll Alpha)
+        #[structopt(long = "network", short = "n")]
+        network_name: String,
+    },
     #[structopt(name = "activate")]
     Activate {
         // #[structopt(long, short = "u")]
@@ -343,6 +1078,19 @@ pub enum InteroperabilityCommand {
         /// choose "scroll" (Scroll Alpha)
         #[structopt(long = "network", short = "n")]
         network_name: String,
+        /// Request test ETH to the faucet first if the balance is zero.
+        #[structopt(long)]
+        faucet: bool,
+    },
+    /// Request test ETH to the faucet and wait until the balance increases.
+    #[structopt(name = "faucet")]
+    Faucet {
+        /// choose "scroll" (Scroll Alpha)
+        #[structopt(long = "network", short = "n")]
+        network_name: String,
+        /// the address funded by the faucet [default: the address of --signer or PRIVATE_KEY]
+        #[structopt(long)]
+        address: Option<String>,
     },
     #[structopt(name = "lock")]
     Lock {
@@ -351,7 +1099,7 @@ pub enum InteroperabilityCommand {
         /// destination of a token
         #[structopt(long, short = "r")]
         receiver_address: String,
-        /// token address [Default: receiver_address]
+        /// token address, nickname, `mine` (your own token) or `receiver` (the token of the recipient) [default: receiver]
         #[structopt(long = "token-address", short = "a")]
         contract_address: Option<String>,
         /// the token id can be selected from 0x00 to 0xff
@@ -388,6 +1136,10 @@ pub enum InteroperabilityCommand {
         /// If you already sent transaction on intmax, you can use its hash.
         #[structopt(long = "tx-hash", short = "t")]
         tx_hash: Option<String>,
+        /// Stop after verification without sending any transaction.
+        /// The witness is verified only if --tx-hash is given.
+        #[structopt(long)]
+        dry_run: bool,
     },
     #[structopt(name = "view")]
     View {
@@ -401,6 +1153,112 @@ pub enum InteroperabilityCommand {
         #[structopt(long = "reverse-offer", short = "r")]
         is_reverse_offer: bool,
     },
+    /// Export the Register and Activate events of the offers for reconciliation.
+    #[structopt(name = "events")]
+    Events {
+        /// choose "scroll" (Scroll Alpha)
+        #[structopt(long = "network", short = "n")]
+        network_name: String,
+        #[structopt(long)]
+        from_block: u64,
+        /// [default: the latest block]
+        #[structopt(long)]
+        to_block: Option<u64>,
+        /// Ethereum address of the maker
+        #[structopt(long)]
+        maker: Option<String>,
+        /// Ethereum address of the taker
+        #[structopt(long)]
+        taker: Option<String>,
+        /// intmax address or nickname of either the maker or the taker
+        #[structopt(long)]
+        intmax_address: Option<String>,
+        /// "json" or "csv"
+        #[structopt(long, default_value = "json")]
+        format: OfferEventsFormat,
+        /// output file [default: stdout]
+        #[structopt(long, short = "o", parse(from_os_str))]
+        output: Option<PathBuf>,
+    },
+    #[structopt(name = "network")]
+    Network {
+        #[structopt(subcommand)]
+        network_command: NetworkCommand,
+    },
+    /// commands for the Ethereum secret keys selected by --signer.
+    #[structopt(name = "signer")]
+    Signer {
+        #[structopt(subcommand)]
+        signer_command: SignerCommand,
+    },
+}
+
+#[cfg(feature = "interoperability")]
+#[derive(Debug, StructOpt)]
+pub enum SignerCommand {
+    /// Encrypt an Ethereum secret key with a password and store it under the name.
+    /// The key is read from the first line of stdin.
+    #[structopt(name = "add")]
+    Add {
+        name: String,
+        /// Read the key from PRIVATE_KEY instead of stdin.
+        #[structopt(long = "from-env")]
+        from_env: bool,
+    },
+    /// Display the names and the addresses of the signers.
+    #[structopt(name = "list")]
+    List {},
+    /// Remove the signer and its encrypted key.
+    #[structopt(name = "remove")]
+    Remove { name: String },
+}
+
+#[cfg(feature = "interoperability")]
+#[derive(Debug, StructOpt)]
+pub enum NetworkCommand {
+    /// Display the RPC endpoint, its latency and the current gas price of each network.
+    /// Also checks that the endpoint serves the expected chain.
+    #[structopt(name = "status")]
+    Status {
+        /// choose "scroll" (Scroll Alpha) or "polygon" (Polygon zkEVM testnet) [default: all networks]
+        #[structopt(long = "network", short = "n")]
+        network_name: Option<String>,
+    },
+}
+
+#[cfg(feature = "dev")]
+#[derive(Debug, StructOpt)]
+pub enum DevCommand {
+    /// Write wallet, nickname and config files in each known schema for compatibility tests.
+    #[structopt(name = "gen-wallet-fixtures")]
+    GenWalletFixtures {
+        #[structopt(long, short = "o", parse(from_os_str))]
+        output_dir: PathBuf,
+    },
+    /// Create many accounts for load testing and output their addresses and private keys as CSV.
+    /// The accounts are registered one by one and approved in a single block at the end.
+    #[structopt(name = "create-accounts")]
+    CreateAccounts {
+        /// the number of accounts to create
+        #[structopt(long)]
+        count: usize,
+        /// Give the accounts nicknames `<prefix>1`, `<prefix>2`, ...
+        #[structopt(long)]
+        prefix: Option<String>,
+        /// the interval between account registrations in milliseconds
+        #[structopt(long, default_value = "100")]
+        interval: u64,
+        /// Write the CSV into the file instead of stdout.
+        #[structopt(long, short = "o", parse(from_os_str))]
+        output: Option<PathBuf>,
+    },
+    /// Rebuild the witnesses of a block and compare each intermediate digest with its header
+    /// to find where a block proof diverges.
+    #[structopt(name = "replay-block")]
+    ReplayBlock {
+        #[structopt(long = "block", short = "n")]
+        block_number: u32,
+    },
 }
 
 #[cfg(feature = "bridge")]
@@ -433,7 +1291,7 @@ pub enum BridgeCommand {
     Burn {
         #[structopt(long, short = "u")]
         user_address: Option<String>,
-        /// token address
+        /// token address, nickname or `mine` (your own token) [default: mine]
         #[structopt(long = "token-address", short = "a")]
         contract_address: Option<String>,
         /// the token id can be selected from 0x00 to 0xff
@@ -451,119 +1309,710 @@ pub enum BridgeCommand {
     },
 }
 
-impl Command {
-    pub async fn invoke(self) -> anyhow::Result<()> {
-        invoke_command(self).await
-    }
-}
+impl SubCommand {
+    /// Returns the name and the arguments of a state-changing command, which are recorded in the audit log.
+    /// Private keys are never included in the arguments.
+    fn audit_operation(&self) -> Option<(String, String)> {
+        let name = match self {
+            SubCommand::Config { config_command } => match config_command {
+                ConfigCommand::AggregatorUrl {
+                    aggregator_url: Some(_),
+                    ..
+                } => "config aggregator-url",
+                ConfigCommand::Edit {} => "config edit",
+                ConfigCommand::Profile {
+                    profile_name: Some(_),
+                } => "config profile",
+                ConfigCommand::Privacy { enabled: Some(_) } => "config privacy",
+                _ => return None,
+            },
+            SubCommand::Account { account_command } => match account_command {
+                AccountCommand::Reset { .. } => "account reset",
+                AccountCommand::MigrateData { .. } => "account migrate-data",
+                AccountCommand::Add {
+                    private_key,
+                    private_key_stdin,
+                    mnemonic,
+                    index,
+                    count,
+                    nickname,
+                    is_default,
+                } => {
+                    let arguments = format!(
+                        "Add {{ private_key: {}, private_key_stdin: {private_key_stdin}, mnemonic: {mnemonic}, index: {index:?}, count: {count:?}, nickname: {nickname:?}, is_default: {is_default} }}",
+                        if private_key.is_some() { "<redacted>" } else { "None" }
+                    );
 
-pub async fn invoke_command(command: Command) -> anyhow::Result<()> {
-    let mut intmax_dir = dirs::home_dir().expect("fail to get home directory");
-    intmax_dir.push(".intmax");
+                    return Some(("account add".to_string(), arguments));
+                }
+                AccountCommand::Import { .. } => "account import",
+                AccountCommand::SetDefault { .. } => "account set-default",
+                AccountCommand::SetPassword { .. } => "account set-password",
+                AccountCommand::MergePolicy {
+                    merge_policy: Some(_),
+                } => "account merge-policy",
+                AccountCommand::Nickname {
+                    nickname_command: NicknameCommand::Set { .. },
+                } => "account nickname set",
+                AccountCommand::Nickname {
+                    nickname_command: NicknameCommand::Remove { .. },
+                } => "account nickname remove",
+                _ => return None,
+            },
+            SubCommand::Transaction { tx_command } => match tx_command {
+                TransactionCommand::Mint { .. } => "tx mint",
+                TransactionCommand::Send { .. } => "tx send",
+                TransactionCommand::BatchSend { .. } => "tx batch-send",
+                TransactionCommand::Merge { dry_run: true, .. } => return None,
+                TransactionCommand::Merge { .. } => "tx merge",
+                TransactionCommand::BulkMint { .. } => "tx bulk-mint",
+                TransactionCommand::BulkTransfer { .. } => "tx bulk-transfer",
+                TransactionCommand::BulkStatus { resume: true, .. } => "tx bulk-status",
+                TransactionCommand::BulkStatus { .. } => return None,
+                TransactionCommand::ExportUnsigned { .. } => return None,
+                TransactionCommand::ImportSignature { .. } => "tx import-signature",
+                TransactionCommand::History { .. } => return None,
+                TransactionCommand::VerifyReceipt { .. } => return None,
+                TransactionCommand::Swap {} => return None,
+            },
+            SubCommand::Block { block_command } => match block_command {
+                #[cfg(feature = "advanced")]
+                BlockCommand::Propose {} => "block propose",
+                BlockCommand::Sign { .. } => "block sign",
+                #[cfg(feature = "advanced")]
+                BlockCommand::Approve {} => "block approve",
+                _ => return None,
+            },
+            SubCommand::Watch { .. } => "watch",
+            #[cfg(feature = "dev")]
+            SubCommand::Dev {
+                dev_command: DevCommand::CreateAccounts { .. },
+            } => "dev create-accounts",
+            #[cfg(feature = "interoperability")]
+            SubCommand::Interoperability { io_command } => match io_command {
+                InteroperabilityCommand::Register { .. } => "io register",
+                InteroperabilityCommand::Repair { .. } => "io repair",
+                InteroperabilityCommand::Activate { .. } => "io activate",
+                InteroperabilityCommand::Faucet { .. } => "io faucet",
+                InteroperabilityCommand::Lock { .. } => "io lock",
+                InteroperabilityCommand::Unlock { .. } => "io unlock",
+                InteroperabilityCommand::View { .. } => return None,
+                InteroperabilityCommand::Events { .. } => return None,
+                InteroperabilityCommand::Network { .. } => return None,
+                InteroperabilityCommand::Signer {
+                    signer_command: SignerCommand::Add { .. },
+                } => "io signer add",
+                InteroperabilityCommand::Signer {
+                    signer_command: SignerCommand::Remove { .. },
+                } => "io signer remove",
+                InteroperabilityCommand::Signer { .. } => return None,
+            },
+            #[cfg(feature = "bridge")]
+            SubCommand::Bridge { bridge_command } => match bridge_command {
+                BridgeCommand::Deposit { .. } => "bridge deposit",
+                BridgeCommand::Burn { .. } => "bridge exit",
+            },
+            _ => return None,
+        };
 
-    if File::open(intmax_dir.clone()).is_err() {
-        create_dir(intmax_dir.clone()).unwrap();
-        println!("make directory: {}", intmax_dir.to_string_lossy());
+        Some((name.to_string(), format!("{self:?}")))
     }
 
-    let mut config_file_path = intmax_dir.clone();
-    config_file_path.push("config");
+    /// Returns the user address of a command which reads or updates only that account,
+    /// so that the other accounts are not loaded from the wallet.
+    fn single_account_user_address(&self) -> Option<Option<String>> {
+        let user_address = match self {
+            SubCommand::Account { account_command } => match account_command {
+                AccountCommand::Qr { user_address, .. }
+                | AccountCommand::Assets { user_address, .. }
+                | AccountCommand::Balance { user_address, .. }
+                | AccountCommand::Notify { user_address, .. }
+                | AccountCommand::Stats { user_address, .. }
+                | AccountCommand::Statement { user_address, .. }
+                | AccountCommand::SignRoot { user_address, .. }
+                | AccountCommand::Export { user_address, .. } => user_address,
+                _ => return None,
+            },
+            SubCommand::Transaction { tx_command } => match tx_command {
+                TransactionCommand::Mint { user_address, .. }
+                | TransactionCommand::Merge { user_address, .. }
+                | TransactionCommand::History { user_address }
+                | TransactionCommand::VerifyReceipt { user_address, .. } => user_address,
+                TransactionCommand::Send {
+                    user_address,
+                    from_all: false,
+                    ..
+                } => user_address,
+                _ => return None,
+            },
+            SubCommand::Watch {
+                user_address: user_address @ Some(_),
+                ..
+            } => user_address,
+            _ => return None,
+        };
 
-    let mut service = if let Ok(mut file) = File::open(config_file_path.clone()) {
-        let mut encoded_service = String::new();
-        file.read_to_string(&mut encoded_service)?;
-        serde_json::from_str(&encoded_service).unwrap()
-    } else {
-        ServiceBuilder::new(DEFAULT_AGGREGATOR_URL)
-    };
+        Some(user_address.clone())
+    }
 
-    let mut wallet_dir_path = intmax_dir.clone();
-    let aggregator_url = service
-        .aggregator_api_url("")
-        .split("://")
-        .last()
-        .unwrap()
-        .to_string();
-    assert!(!aggregator_url.is_empty());
-    wallet_dir_path.push(aggregator_url);
+    /// Returns the optional features of the aggregator which the command depends on,
+    /// so that it fails before sending anything to an aggregator without them.
+    fn required_capabilities(&self) -> &'static [Capability] {
+        match self {
+            SubCommand::Account { account_command } => match account_command {
+                AccountCommand::Notify { .. } => &[Capability::ReceivedAssets],
+                AccountCommand::TransactionProof { .. } => &[Capability::TransactionProof],
+                _ => &[],
+            },
+            SubCommand::Transaction {
+                tx_command: TransactionCommand::VerifyReceipt { .. },
+            } => &[Capability::TransactionProof],
+            #[cfg(feature = "interoperability")]
+            SubCommand::Interoperability { io_command } => match io_command {
+                InteroperabilityCommand::Register { .. }
+                | InteroperabilityCommand::Repair { .. }
+                | InteroperabilityCommand::Unlock { .. } => &[Capability::TransactionProof],
+                _ => &[],
+            },
+            _ => &[],
+        }
+    }
 
-    let mut nickname_file_path = wallet_dir_path.clone();
-    nickname_file_path.push("nickname");
+    /// Returns the name of the command and the feature it requires
+    /// if the command is not compiled in this binary.
+    // Only the last arm is left if all features are enabled.
+    #[allow(clippy::match_single_binding)]
+    fn missing_feature(&self) -> Option<(&'static str, &'static str)> {
+        match self {
+            #[cfg(not(feature = "interoperability"))]
+            SubCommand::InteroperabilityNotCompiled { .. } => Some(("io", "interoperability")),
+            #[cfg(not(feature = "bridge"))]
+            SubCommand::BridgeNotCompiled { .. } => Some(("bridge", "bridge")),
+            #[cfg(not(feature = "dev"))]
+            SubCommand::DevNotCompiled { .. } => Some(("dev", "dev")),
+            #[cfg(not(feature = "advanced"))]
+            SubCommand::Block { block_command } => match block_command {
+                BlockCommand::ProposeNotCompiled { .. } => Some(("block propose", "advanced")),
+                BlockCommand::ApproveNotCompiled { .. } => Some(("block approve", "advanced")),
+                BlockCommand::VerifyNotCompiled { .. } => Some(("block verify", "advanced")),
+                _ => None,
+            },
+            _ => None,
+        }
+    }
+}
 
-    let mut nickname_table = if let Ok(mut file) = File::open(nickname_file_path.clone()) {
-        let mut encoded_nickname_table = String::new();
-        file.read_to_string(&mut encoded_nickname_table)?;
-        serde_json::from_str(&encoded_nickname_table).unwrap()
-    } else {
-        NicknameTable::default()
-    };
+impl Command {
+    pub async fn invoke(self) -> anyhow::Result<()> {
+        let mut audit_recorder = AuditRecorder::new(
+            self.sub_command
+                .as_ref()
+                .and_then(SubCommand::audit_operation),
+        );
+        let result = invoke_command(self, &mut audit_recorder).await;
+        if let Err(error) = audit_recorder.finish(&result) {
+            if result.is_ok() {
+                return Err(error);
+            }
 
-    let mut wallet_file_path = wallet_dir_path.clone();
-    wallet_file_path.push("wallet");
+            eprintln!("{error:#}");
+        }
 
-    let password = "password"; // unused
-    if let SubCommand::Account {
-        account_command: AccountCommand::Reset { assume_yes },
-    } = command.sub_command
+        result
+    }
+}
+
+pub async fn invoke_command(
+    command: Command,
+    audit_recorder: &mut AuditRecorder,
+) -> anyhow::Result<()> {
+    let json = command.json;
+    if command.version {
+        let version_info = VersionInfo::current();
+        if json {
+            println!("{}", serde_json::to_string_pretty(&version_info)?);
+        } else {
+            println!("{version_info}");
+        }
+
+        return Ok(());
+    }
+
+    let sub_command = if let Some(sub_command) = command.sub_command {
+        sub_command
+    } else {
+        Command::clap().print_help()?;
+        println!();
+
+        return Ok(());
+    };
+
+    if let Some((command_name, feature)) = sub_command.missing_feature() {
+        return Err(feature_not_compiled_error(command_name, feature));
+    }
+
+    if let SubCommand::Features {} = sub_command {
+        if json {
+            println!("{}", serde_json::to_string_pretty(&FEATURES)?);
+        } else {
+            let separator = "--------------------------------------------------------------------------------------";
+            println!("{}", separator);
+            for feature in FEATURES {
+                println!(
+                    "  {:20} | {:8} | {}",
+                    feature.name,
+                    if feature.enabled {
+                        "enabled"
+                    } else {
+                        "disabled"
+                    },
+                    feature.commands.join(", ")
+                );
+            }
+            println!("{}", separator);
+        }
+
+        return Ok(());
+    }
+
+    if let SubCommand::Explain { code } = &sub_command {
+        if let Some(code) = code {
+            let error_code = find_error_code(code).with_context(|| {
+                format!("unknown error code: {code}. Run `intmax explain` to list the codes")
+            })?;
+            println!("{error_code}");
+        } else {
+            for error_code in ERROR_CODES {
+                println!("{:16} {}", error_code.code, error_code.summary);
+            }
+        }
+
+        return Ok(());
+    }
+
+    if let SubCommand::Airdrop {
+        airdrop_command: AirdropCommand::Template { output },
+    } = &sub_command
     {
-        if !assume_yes {
-            let response = Confirm::new()
-                .with_prompt(
-                    "This operation cannot be undone. Do you really want to reset the wallet?",
-                )
-                .interact()
-                .unwrap();
+        if output.exists() {
+            anyhow::bail!("{} already exists", output.display());
+        }
+        std::fs::write(output, distribution_template(output)?)
+            .with_context(|| format!("fail to write {}", output.display()))?;
+        println!(
+            "the template was written into {}. Replace the recipients before sending.",
+            output.display()
+        );
+
+        return Ok(());
+    }
+
+    // The flags take precedence over the environment variables.
+    let env_config = EnvConfig::from_env()?;
+    let non_interactive = command.non_interactive || env_config.non_interactive.unwrap_or(false);
+    let quiet = command.quiet || json || env_config.log_level == Some(LogLevel::Quiet);
+    let stats = command.stats || env_config.log_level == Some(LogLevel::Debug);
+    let profile_name = command
+        .profile
+        .clone()
+        .or_else(|| env_config.profile.clone());
+    let rpc_url = command.rpc_url.clone();
+    let signer = command.signer.clone();
+
+    let terminal = TerminalOptions::new(command.no_color, command.ascii, non_interactive)
+        .with_assume_yes(command.assume_yes)
+        .with_quiet(quiet)
+        .with_full_addresses(command.full_addresses);
+
+    let intmax_dir = env_config.data_dir()?;
+
+    if File::open(intmax_dir.clone()).is_err() {
+        create_dir_all(intmax_dir.clone()).unwrap();
+        progress!("make directory: {}", intmax_dir.to_string_lossy());
+    }
+
+    let audit_log_path = intmax_dir.join(AUDIT_LOG_FILE_NAME);
+    if let SubCommand::Audit { audit_command } = &sub_command {
+        return invoke_audit_command(audit_command, &audit_log_path, json);
+    }
+    audit_recorder.set_audit_log_path(audit_log_path);
+
+    let signers_dir = intmax_dir.join(SIGNERS_DIR_NAME);
+    #[cfg(feature = "interoperability")]
+    if let SubCommand::Interoperability {
+        io_command: InteroperabilityCommand::Signer { signer_command },
+    } = &sub_command
+    {
+        return invoke_signer_command(signer_command, &signers_dir, &terminal);
+    }
+
+    let mut config_file_path = intmax_dir.clone();
+    config_file_path.push("config.toml");
+
+    let mut legacy_config_file_path = intmax_dir.clone();
+    legacy_config_file_path.push("config");
+
+    if let SubCommand::Config {
+        config_command: ConfigCommand::Edit {},
+    } = sub_command
+    {
+        return edit_config_file(&config_file_path, &legacy_config_file_path, &terminal);
+    }
+
+    #[cfg(feature = "dev")]
+    if let SubCommand::Dev {
+        dev_command: DevCommand::GenWalletFixtures { output_dir },
+    } = &sub_command
+    {
+        for file_path in generate_fixtures(output_dir)? {
+            println!("write {}", file_path.display());
+        }
+
+        return Ok(());
+    }
 
-            if !response {
-                eprintln!("Wallet was not reset");
+    let mut config = Config::read_from_file(&config_file_path, &legacy_config_file_path)?;
+    let mut profile = config.get_profile(profile_name.as_deref())?.clone();
+    env_config.apply_to_profile(&mut profile);
+    profile
+        .validate(profile_name.as_deref().unwrap_or(&config.default_profile))
+        .context("invalid INTMAX_* environment variable")?;
+
+    let prover_config = ProverConfig {
+        threads: command.prove_threads.or(profile.prover.threads),
+        memory_budget: command.prove_memory_budget.or(profile.prover.memory_budget),
+        url: profile.prover.url.clone(),
+    };
+    prover_config.validate()?;
+    #[cfg(feature = "remote-prover")]
+    if let Some(prover_url) = &prover_config.url {
+        register_prover(std::sync::Arc::new(RemoteProver::new(prover_url)?));
+    }
+    prover_config.apply_on_first_proof();
+
+    if let SubCommand::Warmup {} = sub_command {
+        let end = warm_up_circuits();
+        println!("all circuits were built in {}", format_duration(end));
+
+        return Ok(());
+    }
+
+    if let SubCommand::Proof { proof_command } = &sub_command {
+        let resolve_verifier_network = |network_name: &str| {
+            let network_name = network_name.parse::<NetworkName>();
+            let rpc_url = rpc_url.clone();
+            let rpc_config = profile.rpc.clone();
+            async move {
+                let network_name = network_name.context("invalid network name")?;
+                #[cfg(not(feature = "enable-polygon-zkevm"))]
+                if network_name == NetworkName::PolygonZkEvmTest {
+                    anyhow::bail!("Polygon ZKEVM testnet cannot be selected now");
+                }
 
-                return Ok(());
+                resolve_network_config(network_name, rpc_url.as_deref(), &rpc_config).await
+            }
+        };
+
+        match proof_command {
+            ProofCommand::Verify { file, network_name } => {
+                let encoded_proof = std::fs::read_to_string(file)
+                    .with_context(|| format!("fail to read {}", file.display()))?;
+                let proof = decode_proof_artifact(&encoded_proof)?;
+                let network_config = match (&proof, network_name) {
+                    (ProofArtifact::Inclusion(_), Some(network_name)) => {
+                        Some(resolve_verifier_network(network_name).await?)
+                    }
+                    (ProofArtifact::Header(_), Some(_)) => {
+                        eprintln!("WARNING: --network is ignored for header proofs");
+                        None
+                    }
+                    _ => None,
+                };
+                verify_proof_artifact(&proof, network_config.as_ref()).await?;
+                println!("the {} proof is valid", proof.type_name());
+            }
+            ProofCommand::VerifyBatch { dir, network_name } => {
+                let files = list_proof_files(dir)?;
+                if files.is_empty() {
+                    anyhow::bail!("no proof files were found in {}", dir.display());
+                }
+                let network_config = if let Some(network_name) = network_name {
+                    Some(resolve_verifier_network(network_name).await?)
+                } else {
+                    None
+                };
+
+                progress!("verifying {} proof files", files.len());
+                let report = verify_proof_files(dir, &files, network_config.as_ref());
+                if json {
+                    println!("{}", serde_json::to_string_pretty(&report)?);
+                } else {
+                    println!("{report}");
+                }
+                if report.failed != 0 {
+                    anyhow::bail!("{} of {} proofs are invalid", report.failed, report.total);
+                }
             }
         }
 
-        let wallet = WalletOnMemory::new(wallet_file_path, password.to_string());
+        return Ok(());
+    }
+
+    let mut service =
+        ServiceBuilder::new(&canonicalize_aggregator_url(&profile.aggregator_url, true)?);
+    service.set_timeout(profile.http.timeout.map(Duration::from_secs));
+    service.set_stats(stats);
+    service.set_wire_format(profile.http.wire_format.unwrap_or_default());
+    service.set_retry_policy(profile.http.retry_policy());
+    service.set_proving_times_path(Some(intmax_dir.join(PROVING_TIMES_FILE_NAME)));
+    service.set_debug_dump_dir(command.debug_dump.clone());
+    service.set_privacy_mode(profile.privacy.enabled);
+    service.set_pending_ttl(profile.limits.pending_ttl.map(Duration::from_secs));
+    service.set_policy(profile.policy.clone());
+
+    let aggregator_url = wallet_dir_name(&service.aggregator_api_url(""));
+    assert!(!aggregator_url.is_empty());
+    let wallet_dir_path = resolve_wallet_dir(&intmax_dir, &service.aggregator_api_url(""));
+    service.set_block_cache_dir(Some(wallet_dir_path.join(BLOCK_CACHE_DIR_NAME)));
+
+    if let SubCommand::Account {
+        account_command:
+            AccountCommand::MigrateData {
+                from_url,
+                to_url,
+                remove_source,
+            },
+    } = &sub_command
+    {
+        let from_dir = find_source_wallet_dir(&intmax_dir, from_url);
+        let to_dir = if let Some(to_url) = to_url {
+            intmax_dir.join(wallet_dir_name(to_url))
+        } else {
+            intmax_dir.join(&aggregator_url)
+        };
+        let prompt = format!(
+            "Merge the wallet and nicknames in {} into {}?",
+            from_dir.display(),
+            to_dir.display()
+        );
+        if !terminal.confirm(&prompt)? {
+            eprintln!("Data was not migrated");
+
+            return Ok(());
+        }
+
+        let password = read_wallet_password_env()?;
+        let report = migrate_wallet_dir(
+            &from_dir,
+            &to_dir,
+            *remove_source,
+            password.as_ref().map(|password| password.as_str()),
+        )?;
+        println!(
+            "{} accounts, {} nicknames and {} other files were merged into {}",
+            report.moved_accounts,
+            report.moved_nicknames,
+            report.copied_files.len(),
+            to_dir.display()
+        );
+        for address in report.skipped_accounts {
+            eprintln!("WARNING: account {address} was skipped since it already exists");
+        }
+        for nickname in report.skipped_nicknames {
+            eprintln!("WARNING: nickname {nickname} was skipped since the nickname or its address is already used");
+        }
+        for file_name in report.skipped_files {
+            eprintln!("WARNING: {file_name} was skipped since it already exists");
+        }
+        if *remove_source {
+            println!("removed {}", from_dir.display());
+        }
+
+        return Ok(());
+    }
+
+    if wallet_dir_path != intmax_dir.join(&aggregator_url) {
+        eprintln!(
+            "WARNING: the wallet of this aggregator is in {}. Move it with `intmax account migrate-data --from-url {} --remove-source`",
+            wallet_dir_path.display(),
+            service.aggregator_api_url("")
+        );
+    }
+    for duplicate_dir in find_duplicate_wallet_dirs(&intmax_dir, &wallet_dir_path)? {
+        eprintln!(
+            "WARNING: {} seems to hold another wallet of the same aggregator. Merge it with `intmax account migrate-data --from-url {}`",
+            duplicate_dir.display(),
+            duplicate_dir.file_name().unwrap_or_default().to_string_lossy()
+        );
+    }
+
+    let nickname_file_path = wallet_dir_path.join(NICKNAME_FILE_NAME);
+
+    let wallet_file_path = wallet_dir_path.join(WALLET_FILE_NAME);
+    let is_reset = matches!(
+        sub_command,
+        SubCommand::Account {
+            account_command: AccountCommand::Reset { .. }
+        }
+    );
+    // A broken wallet must not be overwritten with an empty one, except by `account reset`.
+    let stored_wallet = if wallet_file_path.exists() && !is_reset {
+        Some(WalletOnMemory::read_index_from_file(
+            wallet_file_path.clone(),
+        )?)
+    } else {
+        None
+    };
+    let wallet_password = match &stored_wallet {
+        Some(wallet) if wallet.encrypted => Some(read_wallet_password(&terminal)?),
+        // A plaintext wallet is encrypted if INTMAX_WALLET_PASSWORD is set.
+        Some(_) => read_wallet_password_env()?,
+        None if is_reset => None,
+        None => read_new_wallet_password(&terminal)?,
+    };
+    audit_recorder.set_aggregator_url(&aggregator_url);
+
+    if let SubCommand::Account {
+        account_command:
+            AccountCommand::Reset {
+                confirm,
+                backup_first,
+            },
+    } = &sub_command
+    {
+        let confirmed = confirm_by_typing(
+            "This operation cannot be undone. All accounts, private keys and nicknames will be deleted.",
+            "RESET",
+            &aggregator_url,
+            confirm.as_deref(),
+            &terminal,
+        )?;
+        if !confirmed {
+            eprintln!("Wallet was not reset");
+
+            return Ok(());
+        }
+
+        if *backup_first {
+            let mut file_paths = vec![wallet_file_path.clone(), nickname_file_path.clone()];
+            file_paths.append(&mut list_account_files(&wallet_file_path)?);
+            let archive = BackupArchive::read_from_files(&file_paths)?;
+            let password = read_backup_password(&terminal)?;
+            let backup_file_name = format!(
+                "reset-{}.json",
+                SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs()
+            );
+            let backup_file_path = write_encrypted_backup(
+                &intmax_dir.join(BACKUP_DIR_NAME),
+                &backup_file_name,
+                &archive,
+                &password,
+            )?;
+            println!("write backup: {}", backup_file_path.display());
+        }
+
+        let password = read_new_wallet_password(&terminal)?;
+        let wallet = WalletOnMemory::new(
+            wallet_file_path,
+            password
+                .map(|password| password.to_string())
+                .unwrap_or_default(),
+        );
 
         wallet.backup()?;
 
-        let nickname_table = NicknameTable::default();
-        let encoded_nickname_table = serde_json::to_string(&nickname_table).unwrap();
-        std::fs::create_dir(wallet_dir_path.clone()).unwrap_or(());
-        let mut file = File::create(nickname_file_path.clone())?;
-        write!(file, "{}", encoded_nickname_table)?;
-        file.flush()?;
+        NicknameStore::reset(nickname_file_path)?;
 
         println!("Wallet initialized");
 
         return Ok(());
     }
 
-    let mut wallet = {
-        let result = WalletOnMemory::read_from_file(wallet_file_path.clone());
-        if let Ok(wallet) = result {
-            wallet
-        } else {
-            let wallet = WalletOnMemory::new(wallet_file_path, password.to_string());
+    let mut nickname_table = NicknameStore::open(nickname_file_path)?;
 
-            wallet.backup()?;
+    let mut wallet = if let Some(mut wallet) = stored_wallet {
+        if let Some(password) = wallet_password {
+            if wallet.encrypted {
+                wallet.unlock(password.to_string())?;
+            } else {
+                // All accounts are rewritten so that no plaintext account file is left.
+                wallet.load_all_accounts()?;
+                wallet.set_password(Some(password));
+                wallet.backup()?;
+                progress!(
+                    "The private keys in {} were encrypted with {WALLET_PASSWORD_ENV}",
+                    wallet_dir_path.display()
+                );
+            }
+        }
 
-            println!("Wallet initialized");
+        wallet
+    } else {
+        let wallet = WalletOnMemory::new(
+            wallet_file_path,
+            wallet_password
+                .map(|password| password.to_string())
+                .unwrap_or_default(),
+        );
 
-            wallet
-        }
+        wallet.backup()?;
+
+        println!("Wallet initialized");
+
+        wallet
     };
+    wallet.set_sent_tx_hash_sink(audit_recorder.sent_tx_hash_sink());
+
+    if let Some(user_address) = sub_command.single_account_user_address() {
+        let user_address = parse_address(&wallet, &nickname_table, user_address)?;
+        wallet.load_account(&user_address)?;
+    } else {
+        wallet.load_all_accounts()?;
+    }
+
+    warn_wallet_size(
+        &wallet,
+        profile
+            .limits
+            .wallet_size_warning
+            .unwrap_or(DEFAULT_WALLET_SIZE_WARNING),
+    )?;
 
-    if let SubCommand::Config { config_command: _ } = command.sub_command {
+    if let SubCommand::Config { config_command: _ } = sub_command {
         // nothing to do
+    } else if let SubCommand::Account {
+        account_command: AccountCommand::SignRoot { .. },
+    } = sub_command
+    {
+        // The signer may be offline.
+    } else if let SubCommand::Account {
+        account_command: AccountCommand::NewMnemonic {},
+    } = sub_command
+    {
+        // The mnemonic is generated offline.
+    } else if let SubCommand::Account {
+        account_command: AccountCommand::Export { .. } | AccountCommand::Import { .. },
+    } = sub_command
+    {
+        // The account is moved between local wallets.
+    } else if let SubCommand::Account {
+        account_command: AccountCommand::Balance { no_sync: true, .. },
+    } = sub_command
+    {
+        // Only the local wallet is read.
     } else {
         check_compatibility_with_server(&service).await?;
+        for capability in sub_command.required_capabilities() {
+            service.ensure_capability(*capability).await?;
+        }
     }
 
-    let set_nickname = |nickname_table: &mut NicknameTable,
+    let set_nickname = |nickname_table: &mut NicknameStore,
                         address: Address<F>,
                         nickname: String|
      -> anyhow::Result<()> {
-        if nickname.starts_with("0x") {
-            anyhow::bail!("nickname must not start with 0x");
+        if is_address_literal(&nickname) {
+            anyhow::bail!("nickname must not start with 0x or intmax1");
         }
 
         if nickname.len() > 12 {
@@ -578,6 +2027,10 @@ pub async fn invoke_command(command: Command) -> anyhow::Result<()> {
             anyhow::bail!("given nickname is reserved");
         }
 
+        if nickname == TOKEN_ADDRESS_MINE || nickname == TOKEN_ADDRESS_RECEIVER {
+            anyhow::bail!("given nickname is reserved for --token-address");
+        }
+
         if reserved_nickname_table
             .address_to_nickname
             .contains_key(&address)
@@ -586,77 +2039,237 @@ pub async fn invoke_command(command: Command) -> anyhow::Result<()> {
         }
 
         nickname_table.insert(address, nickname)?;
-
-        let encoded_nickname_table = serde_json::to_string(&nickname_table).unwrap();
-        std::fs::create_dir(wallet_dir_path.clone()).unwrap_or(());
-        let mut file = File::create(nickname_file_path.clone())?;
-        write!(file, "{}", encoded_nickname_table)?;
-        file.flush()?;
-
-        Ok(())
+        nickname_table.flush()
     };
 
-    match command.sub_command {
+    match sub_command {
         SubCommand::Config { config_command } => match config_command {
-            ConfigCommand::AggregatorUrl { aggregator_url } => {
+            ConfigCommand::AggregatorUrl {
+                aggregator_url,
+                allow_insecure,
+            } => {
+                // Only the given URL is written, not the one of INTMAX_AGGREGATOR_URL.
+                let is_given = aggregator_url.is_some();
+                let aggregator_url = aggregator_url
+                    .map(|aggregator_url| {
+                        let aggregator_url =
+                            canonicalize_aggregator_url(&aggregator_url, allow_insecure)?;
+                        resolve_aggregator_host(&aggregator_url)?;
+
+                        anyhow::Ok(aggregator_url)
+                    })
+                    .transpose()?;
                 service.set_aggregator_url(aggregator_url).await?;
 
-                let encoded_service = serde_json::to_string(&service).unwrap();
-                let mut file = File::create(config_file_path)?;
-                write!(file, "{}", encoded_service)?;
-                file.flush()?;
+                if is_given {
+                    config
+                        .get_profile_mut(profile_name.as_deref())?
+                        .aggregator_url = service.aggregator_api_url("");
+                    config.write_to_file(&config_file_path)?;
+                }
+            }
+            ConfigCommand::Edit {} => {}
+            ConfigCommand::Profile { profile_name } => {
+                if let Some(profile_name) = profile_name {
+                    if !config.profiles.contains_key(&profile_name) {
+                        anyhow::bail!("profile `{profile_name}` was not found. Please add it with `config edit`.");
+                    }
+
+                    config.default_profile = profile_name.clone();
+                    config.write_to_file(&config_file_path)?;
+                    println!("set default profile: {profile_name}");
+                } else {
+                    for profile_name in config.profiles.keys() {
+                        if profile_name == &config.default_profile {
+                            println!("{profile_name} (default)");
+                        } else {
+                            println!("{profile_name}");
+                        }
+                    }
+                }
+            }
+            ConfigCommand::Privacy { enabled } => {
+                let enabled = if let Some(enabled) = enabled {
+                    config
+                        .get_profile_mut(profile_name.as_deref())?
+                        .privacy
+                        .enabled = enabled;
+                    config.write_to_file(&config_file_path)?;
+
+                    enabled
+                } else {
+                    profile.privacy.enabled
+                };
+
+                println!(
+                    "privacy mode: {}",
+                    if enabled { "enabled" } else { "disabled" }
+                );
             }
         },
         SubCommand::Account { account_command } => match account_command {
-            AccountCommand::Reset { .. } => {}
+            AccountCommand::Reset { .. } | AccountCommand::MigrateData { .. } => {}
             AccountCommand::Add {
                 private_key,
+                private_key_stdin,
+                mnemonic,
+                index,
+                count,
                 nickname,
                 is_default,
             } => {
-                let private_key = private_key
-                    // .map(|v| WrappedHashOut::from_str(&v).expect("fail to parse user address"))
-                    .unwrap_or_else(WrappedHashOut::rand);
-                let account = Account::new(*private_key);
-                service.register_account(account.public_key).await.unwrap();
-                wallet.add_account(account)?;
+                if !mnemonic && (index.is_some() || count.is_some()) {
+                    anyhow::bail!("--index and --count are only used with --mnemonic");
+                }
+                let count = count.unwrap_or(1);
+                if count == 0 {
+                    anyhow::bail!("--count must be a positive integer");
+                }
+                if count > 1 && (nickname.is_some() || is_default) {
+                    anyhow::bail!("--nickname and --default cannot be used with --count");
+                }
 
-                println!("new account added: {}", account.address);
+                let private_keys = if mnemonic {
+                    let phrase = read_mnemonic(&terminal)?;
+                    let seed = mnemonic_to_seed(&phrase)?;
+                    let index = index.unwrap_or(0);
+                    let last_index = index
+                        .checked_add(count - 1)
+                        .context("--index and --count are too large")?;
+
+                    (index..=last_index)
+                        .map(|index| derive_private_key(&seed, index))
+                        .collect::<Vec<_>>()
+                } else if private_key_stdin {
+                    vec![read_private_key_from_stdin()?]
+                } else {
+                    if private_key.is_some() {
+                        eprintln!("WARNING: --private-key is visible in your shell history and process list. Use --private-key-stdin instead.");
+                    }
 
-                if is_default {
-                    wallet.set_default_account(Some(account.address));
-                    println!("set the above account as default");
+                    vec![private_key
+                        // .map(|v| WrappedHashOut::from_str(&v).expect("fail to parse user address"))
+                        .unwrap_or_else(WrappedHashOut::rand)]
+                };
+
+                let mut added_accounts = vec![];
+                for private_key in private_keys {
+                    let account = Account::new(*private_key);
+                    if wallet.data.contains_key(&account.address) {
+                        // The accounts recreated before are skipped.
+                        println!("account already in wallet: {}", account.address);
+                        continue;
+                    }
+
+                    // An account recreated from the mnemonic may be registered already.
+                    if service.is_registered_account(account.address).await? != Some(true) {
+                        service.register_account(account.public_key).await?;
+                    }
+                    wallet.add_account(account)?;
+                    added_accounts.push(account);
+
+                    println!("new account added: {}", account.address);
+                }
+
+                if let Some(account) = added_accounts.first() {
+                    if is_default {
+                        wallet.set_default_account(Some(account.address));
+                        println!("set the above account as default");
+                    }
                 }
 
                 wallet.backup()?;
 
-                if let Some(nickname) = nickname {
+                if let (Some(account), Some(nickname)) = (added_accounts.first(), nickname) {
                     set_nickname(&mut nickname_table, account.address, nickname.clone())?;
                     println!("the above account appears replaced by {nickname}");
                 }
 
-                service.resolve_server_health_issue().await.unwrap();
-                service.trigger_propose_block().await.unwrap();
-                service.trigger_approve_block().await.unwrap();
+                if added_accounts.is_empty() {
+                    return Ok(());
+                }
+
+                service.resolve_server_health_issue().await?;
+                service.trigger_propose_block().await?;
+                service.trigger_approve_block().await?;
             }
-            AccountCommand::List {} => {
+            AccountCommand::NewMnemonic {} => {
+                let phrase = generate_mnemonic()?;
+                eprintln!("WARNING: Anyone who knows this mnemonic controls the accounts derived from it. Write it down and keep it offline.");
+                println!("{}", *phrase);
+            }
+            AccountCommand::Export { user_address, file } => {
+                let user_address = parse_address(&wallet, &nickname_table, user_address)?;
+                if file.exists() {
+                    anyhow::bail!("{} already exists", file.display());
+                }
+
+                let raw = wallet.export_account(&user_address)?;
+                let password = read_backup_password(&terminal)?;
+                write_encrypted_file(
+                    &file,
+                    zeroize::Zeroizing::new(serde_json::to_string(&raw)?),
+                    &password,
+                )?;
+                eprintln!("WARNING: {} has the private key of {user_address}. Remove it after importing the account.", file.display());
+                println!("export {user_address}: {}", file.display());
+            }
+            AccountCommand::Import { file, is_default } => {
+                let password = read_existing_backup_password(&terminal)?;
+                let encoded_value = read_encrypted_file(&file, &password)?;
+                let raw: SerializableUserState = serde_json::from_str(&encoded_value)
+                    .with_context(|| format!("{} is not an exported account", file.display()))?;
+                let address = wallet.import_account(raw)?;
+                println!("account imported: {address}");
+                if is_default {
+                    wallet.set_default_account(Some(address));
+                    println!("set the above account as default");
+                }
+
+                wallet.backup()?;
+            }
+            AccountCommand::List { bech32 } => {
                 let mut account_list = wallet.data.keys().collect::<Vec<_>>();
                 account_list.sort_by_key(|v| v.to_string());
+                if json {
+                    let encoded_accounts = account_list
+                        .iter()
+                        .map(|address| {
+                            serde_json::json!({
+                                "address": address.to_string(),
+                                "bech32_address": encode_intmax_address(address),
+                                "nickname": nickname_table.address_to_nickname.get(address),
+                                "default": Some(**address) == wallet.get_default_account(),
+                            })
+                        })
+                        .collect::<Vec<_>>();
+                    println!("{}", serde_json::to_string_pretty(&encoded_accounts)?);
+
+                    return Ok(());
+                }
+
+                let address_renderer =
+                    known_address_renderer(&terminal, &wallet, &nickname_table, &[]);
 
                 let mut is_empty = true;
                 for address in account_list {
                     is_empty = false;
 
+                    let encoded_address = if bech32 {
+                        encode_intmax_address(address)
+                    } else {
+                        address_renderer.render(&address.to_string())
+                    };
                     if Some(*address) == wallet.get_default_account() {
                         if let Some(nickname) = nickname_table.address_to_nickname.get(address) {
-                            println!("{address} [{nickname}] (default)",);
+                            println!("{encoded_address} [{nickname}] (default)",);
                         } else {
-                            println!("{address} (default)");
+                            println!("{encoded_address} (default)");
                         }
                     } else if let Some(nickname) = nickname_table.address_to_nickname.get(address) {
-                        println!("{address} [{nickname}]",);
+                        println!("{encoded_address} [{nickname}]",);
                     } else {
-                        println!("{address}");
+                        println!("{encoded_address}");
                     }
                 }
 
@@ -671,8 +2284,8 @@ pub async fn invoke_command(command: Command) -> anyhow::Result<()> {
                 if let Some(user_address) = user_address {
                     let user_address = if user_address.is_empty() {
                         anyhow::bail!("empty user address");
-                    } else if user_address.starts_with("0x") {
-                        Address::from_str(&user_address)?
+                    } else if is_address_literal(&user_address) {
+                        parse_intmax_address("user address", &user_address)?
                     } else if let Some(user_address) =
                         nickname_table.nickname_to_address.get(&user_address)
                     {
@@ -694,8 +2307,139 @@ pub async fn invoke_command(command: Command) -> anyhow::Result<()> {
 
                 wallet.backup()?;
             }
+            AccountCommand::SetPassword { remove } => {
+                if remove {
+                    wallet.set_password(None);
+                    wallet.backup()?;
+                    println!("The private keys are no longer encrypted");
+                } else {
+                    let password = prompt_new_wallet_password(&terminal)?;
+                    wallet.set_password(Some(password));
+                    wallet.backup()?;
+                    println!("The private keys were encrypted with the new password");
+                }
+            }
+            AccountCommand::MergePolicy { merge_policy } => {
+                if let Some(merge_policy) = merge_policy {
+                    wallet.merge_policy = merge_policy;
+                    wallet.backup()?;
+                }
+
+                println!("merge policy: {}", wallet.merge_policy);
+            }
 
-            AccountCommand::Assets { user_address } => {
+            AccountCommand::Qr {
+                user_address,
+                contract_address,
+                token_id: variable_index,
+                amount,
+                output_path,
+            } => {
+                let user_address = parse_address(&wallet, &nickname_table, user_address)?;
+
+                let requested_asset = if let Some(amount) = amount {
+                    let contract_address = if let Some(contract_address) = contract_address {
+                        if contract_address.is_empty() {
+                            anyhow::bail!("empty token address");
+                        } else if is_address_literal(&contract_address) {
+                            parse_intmax_address("token address", &contract_address)?
+                        } else if let Some(contract_address) =
+                            nickname_table.nickname_to_address.get(&contract_address)
+                        {
+                            *contract_address
+                        } else {
+                            anyhow::bail!("unregistered nickname: token address");
+                        }
+                    } else {
+                        user_address
+                    };
+                    let kind = TokenKind {
+                        contract_address,
+                        variable_index: variable_index.unwrap_or_else(|| 0u8.into()),
+                    };
+
+                    Some((kind, amount))
+                } else {
+                    if contract_address.is_some() || variable_index.is_some() {
+                        anyhow::bail!("you cannot omit --amount attribute with a payment request");
+                    }
+
+                    None
+                };
+
+                let payload = encode_payment_request(user_address, requested_asset);
+                println!("{}", render_qr_code(&payload, &terminal)?);
+                println!("{payload}");
+
+                if let Some(output_path) = output_path {
+                    save_qr_code(&payload, &output_path)?;
+                    println!("write QR code: {}", output_path.to_string_lossy());
+                }
+            }
+            AccountCommand::Balance {
+                user_address,
+                contract_address,
+                token_id,
+                no_sync,
+            } => {
+                let user_address = parse_address(&wallet, &nickname_table, user_address)?;
+                let contract_address = if let Some(contract_address) = contract_address {
+                    if contract_address.is_empty() {
+                        anyhow::bail!("empty token address");
+                    } else if is_address_literal(&contract_address) {
+                        parse_intmax_address("token address", &contract_address)?
+                    } else if let Some(contract_address) =
+                        nickname_table.nickname_to_address.get(&contract_address)
+                    {
+                        *contract_address
+                    } else {
+                        anyhow::bail!("unregistered nickname: token address");
+                    }
+                } else {
+                    user_address
+                };
+                let variable_index = token_id.unwrap_or_else(|| 0u8.into());
+
+                if !no_sync {
+                    let user_state = wallet
+                        .data
+                        .get_mut(&user_address)
+                        .expect("user address was not found in wallet");
+
+                    service
+                        .sync_sent_transaction(user_state, user_address)
+                        .await;
+
+                    wallet.backup()?;
+                }
+
+                let user_state = wallet
+                    .data
+                    .get(&user_address)
+                    .expect("user address was not found in wallet");
+                let total_amount_map = calc_unmerged_assets(user_state).assets.calc_total_amount();
+                let amount = total_amount_map
+                    .get(&(contract_address.to_string(), variable_index.to_string()))
+                    .with_context(|| {
+                        format!("you do not hold the token {contract_address} with token ID {variable_index}")
+                    })?;
+
+                if json {
+                    let encoded_balance = serde_json::json!({
+                        "user_address": user_address.to_string(),
+                        "token_address": contract_address.to_string(),
+                        "token_id": variable_index.to_string(),
+                        "amount": amount.to_string(),
+                    });
+                    println!("{}", serde_json::to_string_pretty(&encoded_balance)?);
+                } else {
+                    println!("{amount}");
+                }
+            }
+            AccountCommand::Assets {
+                user_address,
+                at_block,
+            } => {
                 let user_address = parse_address(&wallet, &nickname_table, user_address)?;
                 {
                     let user_state = wallet
@@ -712,22 +2456,80 @@ pub async fn invoke_command(command: Command) -> anyhow::Result<()> {
 
                 let user_state = wallet
                     .data
-                    .get_mut(&user_address)
+                    .get(&user_address)
                     .expect("user address was not found in wallet");
 
-                // NOTICE: Changes to `user_state` here are not saved to file.
-                calc_merge_witnesses(user_state, user_state.rest_received_assets.clone()).await;
+                let summary = calc_unmerged_assets(user_state);
+                warn_unmerged_assets_summary(&summary);
 
-                let total_amount_map = user_state.assets.calc_total_amount();
+                let mut total_amount_map = summary.assets.calc_total_amount();
+                if let Some(at_block) = at_block {
+                    if at_block > user_state.last_seen_block_number {
+                        anyhow::bail!(
+                            "block {at_block} has not been synchronized yet (last seen: {})",
+                            user_state.last_seen_block_number
+                        );
+                    }
 
+                    let assets = calc_assets_at_block(
+                        &total_amount_map,
+                        &user_state.sent_transactions,
+                        &user_state.stats,
+                        at_block,
+                    )?;
+                    if assets.undated_transfers != 0 {
+                        eprintln!(
+                            "WARNING: {} transfers have no block number and are assumed to be included at or before block {at_block}",
+                            assets.undated_transfers
+                        );
+                    }
+                    total_amount_map = assets.total_amount_map;
+                }
+
+                if json {
+                    let encoded_assets = total_amount_map
+                        .iter()
+                        .map(|((contract_address, variable_index), total_amount)| {
+                            serde_json::json!({
+                                "token_address": contract_address,
+                                "token_id": variable_index,
+                                "amount": total_amount.to_string(),
+                            })
+                        })
+                        .collect::<Vec<_>>();
+                    let mut encoded_assets = serde_json::json!({
+                        "user_address": user_address.to_string(),
+                        "assets": encoded_assets,
+                    });
+                    if let Some(at_block) = at_block {
+                        encoded_assets["block_number"] = at_block.into();
+                    }
+                    println!("{}", serde_json::to_string_pretty(&encoded_assets)?);
+
+                    return Ok(());
+                }
+
+                let address_renderer = known_address_renderer(
+                    &terminal,
+                    &wallet,
+                    &nickname_table,
+                    &total_amount_map
+                        .keys()
+                        .map(|(contract_address, _)| contract_address.clone())
+                        .collect::<Vec<_>>(),
+                );
                 let separator = "--------------------------------------------------------------------------------------";
                 {
+                    let rendered_user_address = address_renderer.render(&user_address.to_string());
                     if let Some(user_nickname) =
                         nickname_table.address_to_nickname.get(&user_address)
                     {
-                        println!("User: {} ({})", user_nickname, user_address);
+                        println!("User: {} ({})", user_nickname, rendered_user_address);
                     } else {
-                        println!("User: {}", user_address);
+                        println!("User: {}", rendered_user_address);
+                    }
+                    if let Some(at_block) = at_block {
+                        println!("Block: {}", at_block);
                     }
                 }
                 println!("{}", separator);
@@ -744,10 +2546,14 @@ pub async fn invoke_command(command: Command) -> anyhow::Result<()> {
                         {
                             println!(
                                 "  Token Address | {} [{}]",
-                                decoded_contract_address, contract_nickname
+                                address_renderer.render(&contract_address),
+                                contract_nickname
                             );
                         } else {
-                            println!("  Token Address | {}", decoded_contract_address);
+                            println!(
+                                "  Token Address | {}",
+                                address_renderer.render(&contract_address)
+                            );
                         }
                         println!("  Token ID      | {}", variable_index);
                         println!("  Amount        | {}", total_amount);
@@ -755,18 +2561,270 @@ pub async fn invoke_command(command: Command) -> anyhow::Result<()> {
                     }
                 }
 
-                #[cfg(feature = "verbose")]
-                println!(
-                    "raw data: {}",
-                    serde_json::to_string(&user_state.assets).unwrap()
-                );
+                #[cfg(feature = "verbose")]
+                println!(
+                    "raw data: {}",
+                    serde_json::to_string(&summary.assets).unwrap()
+                );
+            }
+            AccountCommand::SignRoot {
+                user_address,
+                message,
+                output_path,
+            } => {
+                let user_address = parse_address(&wallet, &nickname_table, user_address)?;
+                let message = parse_hash_out("--message", &message)?;
+                let user_state = wallet
+                    .data
+                    .get(&user_address)
+                    .expect("user address was not found in wallet");
+
+                progress!("signing {message} by {user_address}");
+                let signature = spawn_signer(user_state.account).process(*message)?;
+
+                write_json(
+                    &SignedRoot {
+                        user_address,
+                        message,
+                        signature,
+                    },
+                    output_path.as_deref(),
+                )?;
+            }
+            AccountCommand::Stats {
+                user_address,
+                contract_address,
+                token_id,
+            } => {
+                let user_address = parse_address(&wallet, &nickname_table, user_address)?;
+                let contract_address = if let Some(contract_address) = contract_address {
+                    if contract_address.is_empty() {
+                        anyhow::bail!("empty token address");
+                    } else if is_address_literal(&contract_address) {
+                        Some(parse_intmax_address("token address", &contract_address)?)
+                    } else if let Some(contract_address) =
+                        nickname_table.nickname_to_address.get(&contract_address)
+                    {
+                        Some(*contract_address)
+                    } else {
+                        anyhow::bail!("unregistered nickname: token address");
+                    }
+                } else {
+                    if token_id.is_some() {
+                        anyhow::bail!("--token-id requires --token");
+                    }
+
+                    None
+                };
+
+                let user_state = wallet
+                    .data
+                    .get_mut(&user_address)
+                    .expect("user address was not found in wallet");
+
+                service
+                    .sync_sent_transaction(user_state, user_address)
+                    .await;
+
+                wallet.backup()?;
+
+                let user_state = wallet
+                    .data
+                    .get(&user_address)
+                    .expect("user address was not found in wallet");
+                let totals = user_state.stats.filter(contract_address, token_id);
+
+                let address_renderer = known_address_renderer(
+                    &terminal,
+                    &wallet,
+                    &nickname_table,
+                    &totals
+                        .iter()
+                        .flat_map(|total| {
+                            [Some(total.kind.contract_address), total.counterparty]
+                                .into_iter()
+                                .flatten()
+                                .map(|address| address.to_string())
+                        })
+                        .collect::<Vec<_>>(),
+                );
+                let format_address = |address: Address<F>| {
+                    let rendered_address = address_renderer.render(&address.to_string());
+                    if let Some(nickname) = nickname_table.address_to_nickname.get(&address) {
+                        format!("{rendered_address} [{nickname}]")
+                    } else {
+                        rendered_address
+                    }
+                };
+                let separator = "--------------------------------------------------------------------------------------";
+                println!("User: {}", format_address(user_address));
+                println!("{separator}");
+                if totals.is_empty() {
+                    println!("  No transfers recorded");
+                    println!("{separator}");
+                } else {
+                    let mut last_month = None;
+                    for total in totals {
+                        if last_month.as_ref() != Some(&total.month) {
+                            if last_month.is_some() {
+                                println!("{separator}");
+                            }
+                            println!("  {}", total.month);
+                            last_month = Some(total.month.clone());
+                        }
+
+                        let counterparty = if let Some(counterparty) = total.counterparty {
+                            format_address(counterparty)
+                        } else if total.direction == TransferDirection::Received {
+                            "deposit".to_string()
+                        } else {
+                            "unknown".to_string()
+                        };
+                        let preposition = match total.direction {
+                            TransferDirection::Sent => "to",
+                            TransferDirection::Received => "from",
+                        };
+                        println!(
+                            "    {:<8} | {} | {} | {} {preposition} {counterparty} ({} times)",
+                            total.direction.to_string(),
+                            format_address(total.kind.contract_address),
+                            total.kind.variable_index,
+                            total.amount,
+                            total.count
+                        );
+                    }
+                    println!("{separator}");
+                }
+            }
+            AccountCommand::Statement {
+                user_address,
+                from,
+                to,
+                output,
+            } => {
+                let user_address = parse_address(&wallet, &nickname_table, user_address)?;
+                let from = parse_date(&from).context("--from")?;
+                let to = parse_date(&to).context("--to")?;
+
+                let user_state = wallet
+                    .data
+                    .get_mut(&user_address)
+                    .expect("user address was not found in wallet");
+
+                service
+                    .sync_sent_transaction(user_state, user_address)
+                    .await;
+
+                wallet.backup()?;
+
+                let user_state = wallet
+                    .data
+                    .get(&user_address)
+                    .expect("user address was not found in wallet");
+                let assets = calc_unmerged_assets(user_state).assets;
+                let rows =
+                    build_statement(&user_state.stats.history, &assets, user_address, from, to)?;
+                let csv = statement_to_csv(&rows);
+                if let Some(output) = output {
+                    std::fs::write(&output, csv)?;
+                    progress!("write {}", output.display());
+                } else {
+                    print!("{csv}");
+                }
+            }
+            AccountCommand::Notify {
+                user_address,
+                timeout,
+            } => {
+                let user_address = parse_address(&wallet, &nickname_table, user_address)?;
+                let user_state = wallet
+                    .data
+                    .get_mut(&user_address)
+                    .expect("user address was not found in wallet");
+
+                let start = Instant::now();
+                let mut since = user_state.last_seen_block_number;
+                let received_assets = loop {
+                    let (raw_merge_witnesses, latest_block_number, poll_interval) = service
+                        .poll_received_assets(user_address, since, NOTIFY_LONG_POLL_WAIT)
+                        .await?;
+                    let received_assets = get_received_asset_notifications(&raw_merge_witnesses);
+                    if !received_assets.is_empty() {
+                        break received_assets;
+                    }
+
+                    since = since.max(latest_block_number);
+                    let poll_interval =
+                        poll_interval.unwrap_or(DEFAULT_RECEIVED_ASSETS_POLLING_INTERVAL);
+                    if let Some(timeout) = timeout {
+                        if start.elapsed() + poll_interval > Duration::from_secs(timeout) {
+                            anyhow::bail!("no assets were received in {timeout} seconds");
+                        }
+                    }
+
+                    tokio::time::sleep(poll_interval).await;
+                };
+
+                // The received assets are recorded in the wallet,
+                // so the next `account notify` waits for another asset.
+                service
+                    .sync_sent_transaction(user_state, user_address)
+                    .await;
+                wallet.backup()?;
+
+                let encoded_notification = serde_json::json!({
+                    "user_address": user_address,
+                    "received_assets": received_assets,
+                });
+                println!("{}", serde_json::to_string_pretty(&encoded_notification)?);
+            }
+            AccountCommand::StorageStats {} => {
+                let stats = WalletStorageStats::calculate(&wallet)?;
+                let address_renderer =
+                    known_address_renderer(&terminal, &wallet, &nickname_table, &[]);
+
+                let separator = "--------------------------------------------------------------------------------------";
+                println!("Wallet: {}", wallet.wallet_file_path.display());
+                println!("{}", separator);
+                println!("  File Size     | {}", format_size(stats.file_size));
+                println!(
+                    "  Tree Nodes    | {} ({} nodes)",
+                    format_size(stats.tree_nodes as u64),
+                    stats.n_tree_nodes
+                );
+                println!("{}", separator);
+                for account in stats.accounts.iter() {
+                    let rendered_address = address_renderer.render(&account.address.to_string());
+                    if let Some(nickname) = nickname_table.address_to_nickname.get(&account.address)
+                    {
+                        println!("  Account       | {} [{}]", rendered_address, nickname);
+                    } else {
+                        println!("  Account       | {}", rendered_address);
+                    }
+                    println!(
+                        "  Received      | {} (unmerged asset proofs)",
+                        format_size(account.received_asset_proofs as u64)
+                    );
+                    println!(
+                        "  Sent          | {}",
+                        format_size(account.sent_transactions as u64)
+                    );
+                    println!("  Others        | {}", format_size(account.others as u64));
+                    println!("  Total         | {}", format_size(account.total() as u64));
+                    println!("{}", separator);
+                }
+
+                let (category, bytes) = stats.largest_categories()[0];
+                if bytes > 0 {
+                    println!(
+                        "The largest part is {category}. To reduce it, {}.",
+                        category.remediation()
+                    );
+                }
             }
             AccountCommand::Nickname { nickname_command } => match nickname_command {
                 NicknameCommand::Set { address, nickname } => {
-                    if address.len() != 18 {
-                        anyhow::bail!("address must be 8 bytes hex string with 0x-prefix");
-                    }
-                    let address = Address::from_str(&address)?;
+                    let address = parse_intmax_address("address", &address)?;
 
                     set_nickname(&mut nickname_table, address, nickname)?;
 
@@ -775,20 +2833,12 @@ pub async fn invoke_command(command: Command) -> anyhow::Result<()> {
                 NicknameCommand::Remove { nicknames } => {
                     // Process all given nicknames before displaying an error.
                     nicknames.into_iter().for_each(|nickname| {
-                        match nickname_table.remove(nickname.clone()).and_then(|_| {
-                            let encoded_nickname_table =
-                                serde_json::to_string(&nickname_table).unwrap();
-                            std::fs::create_dir(wallet_dir_path.clone()).unwrap_or(());
-                            let mut file = File::create(nickname_file_path.clone())?;
-                            write!(file, "{}", encoded_nickname_table)?;
-                            file.flush()?;
-
-                            Ok(())
-                        }) {
+                        match nickname_table.remove(nickname.clone()) {
                             Ok(_) => println!("{nickname} is removed"),
                             Err(error) => eprintln!("{error}"),
                         }
                     });
+                    nickname_table.flush()?;
 
                     println!("Done!");
                 }
@@ -804,6 +2854,21 @@ pub async fn invoke_command(command: Command) -> anyhow::Result<()> {
                         println!("{nickname} = {address}");
                     }
                 }
+                NicknameCommand::Resolve { nickname } => {
+                    if let Some(address) = nickname_to_address(&nickname_table, &nickname) {
+                        println!("{address}");
+                    } else {
+                        anyhow::bail!("nickname not found: {nickname}");
+                    }
+                }
+                NicknameCommand::Of { address } => {
+                    let address = parse_intmax_address("address", &address)?;
+                    if let Some(nickname) = address_to_nickname(&nickname_table, &address) {
+                        println!("{nickname}");
+                    } else {
+                        anyhow::bail!("no nickname is given to {address}");
+                    }
+                }
             },
             AccountCommand::PossessionProof { .. } => {
                 anyhow::bail!("This is a upcoming feature.");
@@ -816,18 +2881,15 @@ pub async fn invoke_command(command: Command) -> anyhow::Result<()> {
                 tx_hash,
                 receiver_address,
                 network_name,
-                ..
+                output,
             } => {
                 // let user_address = parse_address(&wallet, &nickname_table, user_address)?;
                 let reserved_nickname_table = ReservedNicknameTable::new();
+                let receiver_address = resolve_qr_image_argument(receiver_address)?;
                 let receiver_address = if receiver_address.is_empty() {
                     anyhow::bail!("empty recipient");
-                } else if receiver_address.starts_with("0x") {
-                    if receiver_address.len() != 18 {
-                        anyhow::bail!("recipient must be 8 bytes hex string with 0x-prefix");
-                    }
-
-                    Address::from_str(&receiver_address)?
+                } else if is_address_literal(&receiver_address) {
+                    parse_intmax_address("recipient", &receiver_address)?
                 } else if let Some(receiver_address) = reserved_nickname_table
                     .nickname_to_address
                     .get(&receiver_address)
@@ -849,18 +2911,32 @@ pub async fn invoke_command(command: Command) -> anyhow::Result<()> {
                         anyhow::bail!("Polygon ZKEVM testnet cannot be selected now");
                     }
 
-                    Some(get_network_config(network_name))
+                    Some(
+                        resolve_network_config(network_name, rpc_url.as_deref(), &profile.rpc)
+                            .await?,
+                    )
                 } else {
                     None
                 };
 
-                let tx_hash =
-                    WrappedHashOut::from_str(&tx_hash).expect("tx hash is invalid: {tx_hash}");
-                let witness =
-                    create_transaction_proof(&service, network_name, *tx_hash, receiver_address)
+                let tx_hash = TxHash::<F>::from(parse_hash_out("tx hash", &tx_hash)?);
+                let proof =
+                    create_transaction_proof(&service, network_name, *tx_hash.0, receiver_address)
                         .await?;
 
-                println!("{witness}");
+                if let Some(output) = output {
+                    std::fs::write(
+                        &output,
+                        encode_proof_artifact(&ProofArtifact::Inclusion(proof))?,
+                    )
+                    .with_context(|| format!("fail to write {}", output.display()))?;
+                    println!(
+                        "the transaction proof was written into {}",
+                        output.display()
+                    );
+                } else {
+                    println!("{}", proof.witness);
+                }
             }
         },
         SubCommand::Transaction { tx_command } => {
@@ -919,11 +2995,14 @@ pub async fn invoke_command(command: Command) -> anyhow::Result<()> {
                         .deposit_assets(user_address, vec![deposit_info])
                         .await?;
 
-                    service.resolve_server_health_issue().await.unwrap();
-                    service.trigger_propose_block().await.unwrap();
-                    service.trigger_approve_block().await.unwrap();
+                    service.resolve_server_health_issue().await?;
+                    service.trigger_propose_block().await?;
+                    service.trigger_approve_block().await?;
                 }
-                TransactionCommand::Merge { user_address } => {
+                TransactionCommand::Merge {
+                    user_address,
+                    dry_run,
+                } => {
                     let user_address = parse_address(&wallet, &nickname_table, user_address)?;
 
                     {
@@ -939,138 +3018,874 @@ pub async fn invoke_command(command: Command) -> anyhow::Result<()> {
                         wallet.backup()?;
                     }
 
-                    ctrlc::set_handler(|| {}).expect("Error setting Ctrl-C handler");
+                    if dry_run {
+                        let proving_times = ProvingTimes::read_from_file(
+                            &intmax_dir.join(PROVING_TIMES_FILE_NAME),
+                        )?;
+                        let simulation = simulate_merge(&wallet, user_address, 0, &proving_times);
+
+                        let separator = "--------------------------------------------------------------------------------------";
+                        println!("{}", separator);
+                        println!("  Unmerged      | {}", simulation.n_unmerged);
+                        println!("  Merge Proofs  | {}", simulation.n_merge_proofs);
+                        println!(
+                            "  Iterations    | {} (up to {} merges each)",
+                            simulation.n_iterations, ROLLUP_CONSTANTS.n_merges
+                        );
+                        if let Some(proving_time) = simulation.proving_time {
+                            println!(
+                                "  Proving Time  | {} (average of the recent {} proofs)",
+                                format_duration(proving_time),
+                                proving_times.recent.len()
+                            );
+                        } else {
+                            println!("  Proving Time  | unknown (nothing has been proved yet)");
+                        }
+                        println!(
+                            "  Fragments     | {} -> {}",
+                            simulation.n_fragments_before, simulation.n_fragments_after
+                        );
+                        println!("{}", separator);
+
+                        return Ok(());
+                    }
+
+                    let n_unmerged = wallet
+                        .data
+                        .get(&user_address)
+                        .expect("user address was not found in wallet")
+                        .rest_received_assets
+                        .len();
+                    if n_unmerged == 0 {
+                        if json {
+                            println!(
+                                "{}",
+                                serde_json::json!({ "status": "nothing_to_do", "merged": 0 })
+                            );
+                        }
+
+                        return Err(NothingToDo.into());
+                    }
+
+                    ctrlc::set_handler(|| {}).expect("Error setting Ctrl-C handler");
+
+                    merge(&service, &mut wallet, user_address, 0).await?;
+
+                    if json {
+                        println!(
+                            "{}",
+                            serde_json::json!({ "status": "merged", "merged": n_unmerged })
+                        );
+                    }
+                }
+                TransactionCommand::Send {
+                    user_address,
+                    receiver_address,
+                    to,
+                    contract_address,
+                    token_id: variable_index,
+                    amount,
+                    is_nft,
+                    allow_unregistered,
+                    wait,
+                    no_sign,
+                    from_all,
+                    amount_per_account,
+                    allow_duplicate,
+                } => {
+                    let duplicate_window = profile
+                        .limits
+                        .duplicate_window
+                        .unwrap_or(DEFAULT_DUPLICATE_WINDOW);
+
+                    if wait && no_sign {
+                        anyhow::bail!("--wait cannot be used with --no-sign");
+                    }
+
+                    if from_all {
+                        if no_sign {
+                            anyhow::bail!("--from-all cannot be used with --no-sign");
+                        }
+                        if !to.is_empty() {
+                            anyhow::bail!("--from-all cannot be used with --to");
+                        }
+
+                        let receiver_address = receiver_address
+                            .context("--receiver-address is required with --from-all")?;
+                        let receiver_address = parse_recipient(
+                            &nickname_table,
+                            &resolve_qr_image_argument(receiver_address)?,
+                        )?;
+                        let contract_address = contract_address
+                            .context("--token-address is required with --from-all")?;
+                        let contract_address =
+                            parse_address(&wallet, &nickname_table, Some(contract_address))?;
+                        let amount = amount_per_account
+                            .context("--amount-per-account is required with --from-all")?;
+                        if amount == 0 || amount >= 1u64 << 56 {
+                            anyhow::bail!(
+                                "`amount-per-account` must be a positive integer less than 2^56"
+                            );
+                        }
+                        if let Some(max_amount) = profile.limits.max_amount {
+                            if amount > max_amount {
+                                anyhow::bail!(
+                                    "`amount-per-account` exceeds the limit in the config file: {max_amount}"
+                                );
+                            }
+                        }
+
+                        let output_asset = ContributedAsset {
+                            receiver_address,
+                            kind: TokenKind {
+                                contract_address,
+                                variable_index: variable_index.unwrap_or_else(|| 0u8.into()),
+                            },
+                            amount,
+                        };
+
+                        let mut account_list = wallet.data.keys().cloned().collect::<Vec<_>>();
+                        account_list.sort_by_key(|v| v.to_string());
+                        let mut transfers = vec![];
+                        for sender_address in account_list {
+                            if sender_address == receiver_address {
+                                continue;
+                            }
+
+                            let user_state = wallet
+                                .data
+                                .get(&sender_address)
+                                .expect("user address was not found in wallet");
+                            let assets = calc_unmerged_assets(user_state).assets;
+                            if ensure_assets_held(&assets, std::slice::from_ref(&output_asset))
+                                .is_ok()
+                            {
+                                transfers.push((sender_address, vec![output_asset.clone()]));
+                            } else {
+                                progress!("skip {sender_address}, which holds less than {amount}");
+                            }
+                        }
+                        if transfers.is_empty() {
+                            anyhow::bail!("no account holds {amount} of the token");
+                        }
+                        if !allow_duplicate {
+                            let now = SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs();
+                            for (sender_address, assets) in transfers.iter() {
+                                ensure_not_duplicate(
+                                    &wallet,
+                                    *sender_address,
+                                    assets,
+                                    now,
+                                    duplicate_window,
+                                )?;
+                            }
+                        }
+
+                        let sent_tx_hashes = collect_sent_tx_hashes(&wallet);
+                        let result = send_from_accounts(
+                            &service,
+                            &mut wallet,
+                            &transfers,
+                            allow_unregistered,
+                            wait,
+                            &profile.hooks,
+                        )
+                        .await;
+                        if let Err(err) = result {
+                            record_sent_transfers(
+                                &mut wallet,
+                                &transfers,
+                                &sent_tx_hashes,
+                                duplicate_window,
+                            );
+
+                            return Err(err);
+                        }
+
+                        let now = SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs();
+                        for (sender_address, assets) in transfers.iter() {
+                            if let Some(user_state) = wallet.data.get_mut(sender_address) {
+                                user_state
+                                    .recent_sends
+                                    .record(assets, None, now, duplicate_window);
+                            }
+                        }
+                        wallet.backup()?;
+
+                        return Ok(());
+                    }
+
+                    let user_address = parse_address(&wallet, &nickname_table, user_address)?;
+
+                    let output_assets = if !to.is_empty() {
+                        if receiver_address.is_some() || amount.is_some() || is_nft {
+                            anyhow::bail!(
+                                "--to cannot be used with --receiver-address, --amount or --nft"
+                            );
+                        }
+
+                        let intents = to
+                            .iter()
+                            .map(|target| {
+                                let output = target.resolve(
+                                    &wallet,
+                                    &nickname_table,
+                                    user_address,
+                                    contract_address.as_deref(),
+                                    variable_index,
+                                )?;
+
+                                Ok(TransferIntent {
+                                    sender_address: user_address,
+                                    output,
+                                })
+                            })
+                            .collect::<anyhow::Result<Vec<_>>>()?;
+                        // The outputs to the same recipient and token are summed up.
+                        let mut planned_txs =
+                            compose_transfers(intents, &TransferLimits::transfer())?;
+                        if planned_txs.len() != 1 {
+                            anyhow::bail!(
+                                "the recipients and tokens do not fit in one transaction, which has up to {} outputs including the change of each token. Use `tx batch-send` instead",
+                                ROLLUP_CONSTANTS.n_diffs
+                            );
+                        }
+
+                        planned_txs.remove(0).outputs
+                    } else {
+                        let receiver_address = receiver_address
+                            .context("give --receiver-address and --amount, or --to")?;
+                        let reserved_nickname_table = ReservedNicknameTable::new();
+                        let receiver_address = resolve_qr_image_argument(receiver_address)?;
+                        let receiver_address = if receiver_address.is_empty() {
+                            anyhow::bail!("empty recipient");
+                        } else if is_address_literal(&receiver_address) {
+                            parse_intmax_address("recipient", &receiver_address)?
+                        } else if let Some(receiver_address) = reserved_nickname_table
+                            .nickname_to_address
+                            .get(&receiver_address)
+                        {
+                            *receiver_address
+                        } else if let Some(receiver_address) =
+                            nickname_table.nickname_to_address.get(&receiver_address)
+                        {
+                            *receiver_address
+                        } else {
+                            anyhow::bail!("unregistered nickname: recipient");
+                        };
+
+                        if user_address == receiver_address {
+                            anyhow::bail!("cannot send asset to myself");
+                        }
+
+                        let contract_address = resolve_token_address(
+                            &nickname_table,
+                            contract_address,
+                            DefaultTokenAddress::Mine,
+                            user_address,
+                            Some(receiver_address),
+                        )?;
+
+                        if user_address == receiver_address {
+                            anyhow::bail!("cannot send asset to myself");
+                        }
+
+                        let variable_index = if let Some(variable_index) = variable_index {
+                            if is_nft && variable_index == 0u8.into() {
+                                anyhow::bail!("it is recommended that the NFT token ID be something other than 0x00");
+                            }
+
+                            variable_index
+                        } else {
+                            if is_nft {
+                                anyhow::bail!(
+                                    "you cannot omit --token-id attribute with --nft flag"
+                                );
+                            }
+
+                            0u8.into()
+                        };
+                        let amount = if let Some(amount) = amount {
+                            if is_nft {
+                                println!("--nft flag was ignored because of --amount attribute");
+                            }
+
+                            amount
+                        } else if is_nft {
+                            1
+                        } else {
+                            anyhow::bail!("you cannot omit --amount attribute without --nft flag");
+                        };
+
+                        if amount == 0 || amount >= 1u64 << 56 {
+                            anyhow::bail!("`amount` must be a positive integer less than 2^56");
+                        }
+
+                        // let variable_index = VariableIndex::from_str(&variable_index).unwrap();
+                        let output_asset = ContributedAsset {
+                            receiver_address,
+                            kind: TokenKind {
+                                contract_address,
+                                variable_index,
+                            },
+                            amount,
+                        };
+
+                        vec![output_asset]
+                    };
+
+                    for output_asset in output_assets.iter() {
+                        if let Some(max_amount) = profile.limits.max_amount {
+                            if output_asset.amount > max_amount {
+                                anyhow::bail!(
+                                    "`amount` exceeds the limit in the config file: {max_amount}"
+                                );
+                            }
+                        }
+                    }
+                    service.debug_dump("output_assets", &output_assets);
+
+                    if !allow_duplicate {
+                        ensure_not_duplicate(
+                            &wallet,
+                            user_address,
+                            &output_assets,
+                            SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs(),
+                            duplicate_window,
+                        )?;
+                    }
+
+                    let mut receiver_addresses = vec![];
+                    for output_asset in output_assets.iter() {
+                        if !receiver_addresses.contains(&output_asset.receiver_address) {
+                            receiver_addresses.push(output_asset.receiver_address);
+                        }
+                    }
+                    let recipient_statuses =
+                        check_recipients_registration(&service, &receiver_addresses).await;
+                    for (recipient, status) in recipient_statuses.iter() {
+                        println!("Recipient: {recipient} ({status})");
+                    }
+                    ensure_recipients_registered(&recipient_statuses, allow_unregistered)?;
+
+                    ctrlc::set_handler(|| {}).expect("Error setting Ctrl-C handler");
+
+                    let sent_tx_hashes = collect_sent_tx_hashes(&wallet);
+                    let result = transfer(
+                        &service,
+                        &mut wallet,
+                        user_address,
+                        &output_assets,
+                        wait,
+                        !no_sign,
+                    )
+                    .await;
+                    let tx_hash = match result {
+                        Ok(tx_hash) => tx_hash,
+                        Err(err) => {
+                            record_sent_transfers(
+                                &mut wallet,
+                                &[(user_address, output_assets)],
+                                &sent_tx_hashes,
+                                duplicate_window,
+                            );
+
+                            return Err(err);
+                        }
+                    };
+
+                    let user_state = wallet
+                        .data
+                        .get_mut(&user_address)
+                        .expect("user address was not found in wallet");
+                    user_state.recent_sends.record(
+                        &output_assets,
+                        tx_hash,
+                        SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs(),
+                        duplicate_window,
+                    );
+                    wallet.backup()?;
+
+                    if let Some(tx_hash) = tx_hash {
+                        profile.hooks.run_post_send(tx_hash.to_string())?;
+                    }
+                }
+                TransactionCommand::BatchSend {
+                    spec_path,
+                    allow_unregistered,
+                    wait,
+                } => {
+                    let spec = BatchSpec::read_from_file(&spec_path)?;
+                    let transfers = spec.resolve(&wallet, &nickname_table)?;
+                    if transfers.is_empty() {
+                        anyhow::bail!("no transfers are given");
+                    }
+
+                    send_from_accounts(
+                        &service,
+                        &mut wallet,
+                        &transfers,
+                        allow_unregistered,
+                        wait,
+                        &profile.hooks,
+                    )
+                    .await?;
+                }
+                TransactionCommand::BulkMint {
+                    user_address,
+                    csv_path,
+                    // json
+                    strict,
+                    report,
+                } => {
+                    let user_address = parse_address(&wallet, &nickname_table, user_address)?;
+                    let report_file_path =
+                        report.unwrap_or_else(|| BulkReport::default_file_path(&csv_path));
+
+                    let json = read_distribution_file(user_address, &csv_path)?;
+                    validate_distribution_token_addresses(
+                        user_address,
+                        &json,
+                        &wallet,
+                        &nickname_table,
+                        strict,
+                    )?;
+
+                    if !confirm_distribution(&json, &[], &nickname_table, &terminal)? {
+                        eprintln!("Tokens were not minted");
+
+                        return Ok(());
+                    }
+
+                    ctrlc::set_handler(|| {}).expect("Error setting Ctrl-C handler");
+
+                    let recipients =
+                        bulk_mint(&service, &mut wallet, user_address, json, true).await?;
+                    let report = BulkReport {
+                        sender_address: user_address,
+                        recipients,
+                    };
+                    report.save(&report_file_path)?;
+                }
+                TransactionCommand::BulkTransfer {
+                    user_address,
+                    csv_path,
+                    // json
+                    strict,
+                    allow_unregistered,
+                    report,
+                } => {
+                    let user_address = parse_address(&wallet, &nickname_table, user_address)?;
+                    let report_file_path =
+                        report.unwrap_or_else(|| BulkReport::default_file_path(&csv_path));
+
+                    let json = read_distribution_file(user_address, &csv_path)?;
+                    validate_distribution_token_addresses(
+                        user_address,
+                        &json,
+                        &wallet,
+                        &nickname_table,
+                        strict,
+                    )?;
+
+                    let recipients = json
+                        .iter()
+                        .map(|asset| asset.receiver_address)
+                        .collect::<Vec<_>>();
+                    let recipient_statuses =
+                        check_recipients_registration(&service, &recipients).await;
+                    ensure_recipients_registered(&recipient_statuses, allow_unregistered)?;
+
+                    if !confirm_distribution(
+                        &json,
+                        &recipient_statuses,
+                        &nickname_table,
+                        &terminal,
+                    )? {
+                        eprintln!("Tokens were not sent");
+
+                        return Ok(());
+                    }
+
+                    let recipients =
+                        bulk_mint(&service, &mut wallet, user_address, json, false).await?;
+                    let report = BulkReport {
+                        sender_address: user_address,
+                        recipients,
+                    };
+                    report.save(&report_file_path)?;
+                }
+                TransactionCommand::BulkStatus {
+                    report: report_file_path,
+                    resume,
+                } => {
+                    let mut report = BulkReport::read_from_file(&report_file_path)?;
+                    let user_address = report.sender_address;
+                    if !wallet.contains_account(&user_address) {
+                        anyhow::bail!(
+                            "the sender {user_address} of the report was not found in your wallet"
+                        );
+                    }
+
+                    // Update the pending recipients.
+                    let pending_tx_hashes = report
+                        .recipients
+                        .iter()
+                        .filter_map(|recipient| match recipient.outcome {
+                            RecipientOutcome::Pending { tx_hash } => Some(tx_hash),
+                            _ => None,
+                        })
+                        .collect::<HashSet<_>>();
+                    for tx_hash in pending_tx_hashes {
+                        let outcome =
+                            confirm_delivery(&service, &mut wallet, user_address, tx_hash).await;
+                        for recipient in report.recipients.iter_mut() {
+                            if recipient.outcome == (RecipientOutcome::Pending { tx_hash }) {
+                                recipient.outcome = outcome.clone();
+                            }
+                        }
+                    }
+                    report.write_to_file(&report_file_path)?;
+
+                    let (deposit_list, transfer_list) = report.failed_assets();
+                    if resume && !(deposit_list.is_empty() && transfer_list.is_empty()) {
+                        let failed_assets = [deposit_list.clone(), transfer_list.clone()].concat();
+                        if !confirm_distribution(&failed_assets, &[], &nickname_table, &terminal)? {
+                            eprintln!("Tokens were not sent");
+
+                            return Ok(());
+                        }
+
+                        ctrlc::set_handler(|| {}).expect("Error setting Ctrl-C handler");
+
+                        for (distribution_list, need_deposit) in
+                            [(deposit_list, true), (transfer_list, false)]
+                        {
+                            if distribution_list.is_empty() {
+                                continue;
+                            }
+
+                            let recipients = bulk_mint(
+                                &service,
+                                &mut wallet,
+                                user_address,
+                                distribution_list,
+                                need_deposit,
+                            )
+                            .await?;
+                            report.update(recipients);
+                            report.write_to_file(&report_file_path)?;
+                        }
+                    }
+
+                    report.save(&report_file_path)?;
+                }
+                TransactionCommand::ExportUnsigned {
+                    user_address,
+                    tx_hashes,
+                    output_path,
+                } => {
+                    let user_address = parse_address(&wallet, &nickname_table, user_address)?;
+                    let user_state = wallet
+                        .data
+                        .get_mut(&user_address)
+                        .expect("user address was not found in wallet");
+
+                    service
+                        .sync_sent_transaction(user_state, user_address)
+                        .await;
+
+                    let mut unsigned_tx_hashes = user_state
+                        .sent_transactions
+                        .iter()
+                        .filter(|(tx_hash, sent_transaction)| {
+                            sent_transaction.status == TransactionStatus::Pending
+                                && sent_transaction.proposed_block_number.is_none()
+                                && (tx_hashes.is_empty() || tx_hashes.contains(tx_hash))
+                        })
+                        .map(|(tx_hash, _)| *tx_hash)
+                        .collect::<Vec<_>>();
+                    unsigned_tx_hashes.sort_by_key(|tx_hash| tx_hash.to_string());
+                    for tx_hash in tx_hashes.iter() {
+                        if !unsigned_tx_hashes.contains(tx_hash) {
+                            anyhow::bail!(
+                                "transaction {tx_hash} is not waiting for your signature"
+                            );
+                        }
+                    }
+
+                    let mut unsigned_transactions = vec![];
+                    for tx_hash in unsigned_tx_hashes {
+                        let message = service.get_signing_message(user_address, tx_hash).await?;
+                        let expires_at = profile.limits.pending_ttl.and_then(|pending_ttl| {
+                            user_state.sent_transactions[&tx_hash]
+                                .created_at
+                                .map(|created_at| created_at + pending_ttl)
+                        });
+                        unsigned_transactions.push(UnsignedTransaction {
+                            user_address,
+                            tx_hash,
+                            message,
+                            expires_at,
+                        });
+                    }
+
+                    wallet.backup()?;
+
+                    write_json(&unsigned_transactions, output_path.as_deref())?;
+                }
+                TransactionCommand::ImportSignature {
+                    user_address,
+                    tx_hash,
+                    signature_path,
+                    wait,
+                } => {
+                    let user_address = parse_address(&wallet, &nickname_table, user_address)?;
+                    let signed_root: SignedRoot = read_json(&signature_path)?;
+                    if signed_root.user_address != user_address {
+                        anyhow::bail!(
+                            "the signature was made by {}, not {user_address}",
+                            signed_root.user_address
+                        );
+                    }
+
+                    let user_state = wallet
+                        .data
+                        .get_mut(&user_address)
+                        .expect("user address was not found in wallet");
+                    service.expire_stale_transactions(user_state);
+                    let is_expired = user_state
+                        .sent_transactions
+                        .get(&tx_hash)
+                        .map(|sent_transaction| {
+                            sent_transaction.status == TransactionStatus::Expired
+                        })
+                        .unwrap_or(false);
+                    if is_expired {
+                        wallet.backup()?;
+                        anyhow::bail!("transaction {tx_hash} has expired after limits.pending_ttl seconds. Send it again");
+                    }
+
+                    let user_state = wallet
+                        .data
+                        .get_mut(&user_address)
+                        .expect("user address was not found in wallet");
+                    let sent_transaction = user_state
+                        .sent_transactions
+                        .get_mut(&tx_hash)
+                        .filter(|sent_transaction| {
+                            sent_transaction.status == TransactionStatus::Pending
+                                && sent_transaction.proposed_block_number.is_none()
+                        })
+                        .ok_or_else(|| {
+                            anyhow::anyhow!(
+                                "transaction {tx_hash} is not waiting for your signature"
+                            )
+                        })?;
+
+                    // The proposal block may have been replaced since the message was exported.
+                    let message = service.get_signing_message(user_address, tx_hash).await?;
+                    if message != signed_root.message {
+                        anyhow::bail!("the signature is not for the current proposal block including transaction {tx_hash}. Export it again");
+                    }
+
+                    let latest_block = service.get_latest_block().await?;
+                    service
+                        .send_received_signature(signed_root.signature, tx_hash)
+                        .await?;
+                    sent_transaction.proposed_block_number =
+                        Some(latest_block.header.block_number + 1);
+
+                    wallet.backup()?;
+
+                    println!("Done!");
+
+                    if wait {
+                        wait_for_approval(&service, &mut wallet, user_address, tx_hash).await?;
+                    }
+                }
+                TransactionCommand::History { user_address } => {
+                    let user_address = parse_address(&wallet, &nickname_table, user_address)?;
+                    let user_state = wallet
+                        .data
+                        .get_mut(&user_address)
+                        .expect("user address was not found in wallet");
+
+                    service
+                        .sync_sent_transaction(user_state, user_address)
+                        .await;
+
+                    wallet.backup()?;
 
-                    merge(&service, &mut wallet, user_address, 0).await?;
+                    let user_state = wallet
+                        .data
+                        .get(&user_address)
+                        .expect("user address was not found in wallet");
+                    let entries =
+                        build_tx_history(&user_state.sent_transactions, &user_state.stats.history);
+
+                    let address_renderer = known_address_renderer(
+                        &terminal,
+                        &wallet,
+                        &nickname_table,
+                        &entries
+                            .iter()
+                            .flat_map(|entry| {
+                                [Some(entry.kind.contract_address), entry.counterparty]
+                                    .into_iter()
+                                    .flatten()
+                                    .map(|address| address.to_string())
+                            })
+                            .collect::<Vec<_>>(),
+                    );
+                    let format_address = |address: Address<F>| {
+                        let rendered_address = address_renderer.render(&address.to_string());
+                        if let Some(nickname) = nickname_table.address_to_nickname.get(&address) {
+                            format!("{rendered_address} [{nickname}]")
+                        } else {
+                            rendered_address
+                        }
+                    };
+                    let separator = "--------------------------------------------------------------------------------------";
+                    println!("User: {}", format_address(user_address));
+                    println!("{separator}");
+                    if entries.is_empty() {
+                        println!("  No transfers recorded");
+                    }
+                    for entry in entries {
+                        let date = entry
+                            .timestamp
+                            .map(format_date)
+                            .unwrap_or_else(|| "unknown".to_string());
+                        let block_number = entry
+                            .block_number
+                            .map(|block_number| block_number.to_string())
+                            .unwrap_or_else(|| "-".to_string());
+                        let counterparty = if let Some(counterparty) = entry.counterparty {
+                            format_address(counterparty)
+                        } else if entry.direction == TransferDirection::Received {
+                            "deposit".to_string()
+                        } else {
+                            "unknown".to_string()
+                        };
+                        let preposition = match entry.direction {
+                            TransferDirection::Sent => "to",
+                            TransferDirection::Received => "from",
+                        };
+                        let status = entry
+                            .status
+                            .map(|status| format!(" ({status})"))
+                            .unwrap_or_default();
+                        println!(
+                            "  {date} | block {block_number:>6} | {:<8} | {}",
+                            entry.direction.to_string(),
+                            entry.tx_hash
+                        );
+                        println!(
+                            "      {} | {} | {} {preposition} {counterparty}{status}",
+                            format_address(entry.kind.contract_address),
+                            entry.kind.variable_index,
+                            entry.amount
+                        );
+                    }
+                    println!("{separator}");
                 }
-                TransactionCommand::Send {
+                TransactionCommand::VerifyReceipt {
                     user_address,
-                    receiver_address,
+                    file,
                     contract_address,
-                    token_id: variable_index,
+                    token_id,
                     amount,
-                    is_nft,
+                    network_name,
                 } => {
                     let user_address = parse_address(&wallet, &nickname_table, user_address)?;
-
-                    let reserved_nickname_table = ReservedNicknameTable::new();
-                    let receiver_address = if receiver_address.is_empty() {
-                        anyhow::bail!("empty recipient");
-                    } else if receiver_address.starts_with("0x") {
-                        if receiver_address.len() != 18 {
-                            anyhow::bail!("recipient must be 8 bytes hex string with 0x-prefix");
-                        }
-
-                        Address::from_str(&receiver_address)?
-                    } else if let Some(receiver_address) = reserved_nickname_table
-                        .nickname_to_address
-                        .get(&receiver_address)
-                    {
-                        *receiver_address
-                    } else if let Some(receiver_address) =
-                        nickname_table.nickname_to_address.get(&receiver_address)
-                    {
-                        *receiver_address
-                    } else {
-                        anyhow::bail!("unregistered nickname: recipient");
-                    };
-
-                    if user_address == receiver_address {
-                        anyhow::bail!("cannot send asset to myself");
-                    }
-
-                    let contract_address = if let Some(contract_address) = contract_address {
+                    let token_address = if let Some(contract_address) = contract_address {
                         if contract_address.is_empty() {
                             anyhow::bail!("empty token address");
-                        } else if contract_address.starts_with("0x") {
-                            Address::from_str(&contract_address)?
+                        } else if is_address_literal(&contract_address) {
+                            Some(parse_intmax_address("token address", &contract_address)?)
                         } else if let Some(contract_address) =
                             nickname_table.nickname_to_address.get(&contract_address)
                         {
-                            *contract_address
+                            Some(*contract_address)
                         } else {
                             anyhow::bail!("unregistered nickname: token address");
                         }
                     } else {
-                        user_address
+                        None
                     };
 
-                    if user_address == receiver_address {
-                        anyhow::bail!("cannot send asset to myself");
-                    }
-
-                    let variable_index = if let Some(variable_index) = variable_index {
-                        if is_nft && variable_index == 0u8.into() {
-                            anyhow::bail!("it is recommended that the NFT token ID be something other than 0x00");
-                        }
-
-                        variable_index
-                    } else {
-                        if is_nft {
-                            anyhow::bail!("you cannot omit --token-id attribute with --nft flag");
-                        }
-
-                        0u8.into()
+                    let encoded_proof = std::fs::read_to_string(&file)
+                        .with_context(|| format!("fail to read {}", file.display()))?;
+                    let proof = match decode_proof_artifact(&encoded_proof)? {
+                        ProofArtifact::Inclusion(proof) => proof,
+                        proof => anyhow::bail!(
+                            "a receipt is an inclusion proof written by `account transaction-proof`, but {} has a {} proof",
+                            file.display(),
+                            proof.type_name()
+                        ),
                     };
-                    let amount = if let Some(amount) = amount {
-                        if is_nft {
-                            println!("--nft flag was ignored because of --amount attribute");
+
+                    let network_config = if let Some(network_name) = network_name {
+                        let network_name: NetworkName =
+                            network_name.parse().context("invalid network name")?;
+                        #[cfg(not(feature = "enable-polygon-zkevm"))]
+                        if network_name == NetworkName::PolygonZkEvmTest {
+                            anyhow::bail!("Polygon ZKEVM testnet cannot be selected now");
                         }
 
-                        amount
-                    } else if is_nft {
-                        1
+                        Some(
+                            resolve_network_config(network_name, rpc_url.as_deref(), &profile.rpc)
+                                .await?,
+                        )
                     } else {
-                        anyhow::bail!("you cannot omit --amount attribute without --nft flag");
+                        None
                     };
 
-                    if amount == 0 || amount >= 1u64 << 56 {
-                        anyhow::bail!("`amount` must be a positive integer less than 2^56");
-                    }
-
-                    // let variable_index = VariableIndex::from_str(&variable_index).unwrap();
-                    let output_asset = ContributedAsset {
-                        receiver_address,
-                        kind: TokenKind {
-                            contract_address,
-                            variable_index,
-                        },
+                    let claim = PaymentClaim {
+                        recipient: user_address,
+                        token_address,
+                        token_id,
                         amount,
                     };
-                    #[cfg(feature = "verbose")]
-                    dbg!(serde_json::to_string(&output_asset).unwrap());
-
-                    ctrlc::set_handler(|| {}).expect("Error setting Ctrl-C handler");
-
-                    transfer(&service, &mut wallet, user_address, &[output_asset]).await?;
-                }
-                TransactionCommand::BulkMint {
-                    user_address,
-                    csv_path,
-                    // json
-                } => {
-                    let user_address = parse_address(&wallet, &nickname_table, user_address)?;
-
-                    let file =
-                        File::open(csv_path).map_err(|_| anyhow::anyhow!("file was not found"))?;
-                    let json = read_distribution_from_csv(user_address, file)?;
-
-                    ctrlc::set_handler(|| {}).expect("Error setting Ctrl-C handler");
-
-                    bulk_mint(&service, &mut wallet, user_address, json, true).await?;
-                }
-                TransactionCommand::BulkTransfer {
-                    user_address,
-                    csv_path,
-                    // json
-                } => {
-                    let user_address = parse_address(&wallet, &nickname_table, user_address)?;
-
-                    let file =
-                        File::open(csv_path).map_err(|_| anyhow::anyhow!("file was not found"))?;
-                    let json = read_distribution_from_csv(user_address, file)?;
-
-                    bulk_mint(&service, &mut wallet, user_address, json, false).await?;
+                    let receipt =
+                        verify_receipt(&service, network_config.as_ref(), &proof, &claim).await?;
+
+                    if json {
+                        let encoded_receipt = serde_json::json!({
+                            "valid": true,
+                            "tx_hash": proof.tx_hash.to_string(),
+                            "block_number": receipt.block_number,
+                            "recipient": user_address.to_string(),
+                            "token_address": proof.token_address,
+                            "token_id": proof.token_id,
+                            "amount": proof.amount.to_string(),
+                            "verified_on_chain": receipt.verified_on_chain,
+                        });
+                        println!("{}", serde_json::to_string_pretty(&encoded_receipt)?);
+                    } else {
+                        if receipt.verified_on_chain {
+                            println!("the receipt is valid");
+                        } else {
+                            println!("the receipt matches the aggregator, but its witness was not verified");
+                        }
+                        println!("  transaction: {}", proof.tx_hash);
+                        println!("  block: {}", receipt.block_number);
+                        println!("  recipient: {user_address}");
+                        // The proof has only the hash of the token address.
+                        let encoded_token_address = token_address
+                            .map(|token_address| token_address.to_string())
+                            .unwrap_or_else(|| format!("{:?}", proof.token_address));
+                        println!("  token address: {encoded_token_address}");
+                        println!("  token id: {}", proof.token_id);
+                        println!("  amount: {}", proof.amount);
+                    }
+                    if !receipt.verified_on_chain {
+                        eprintln!("WARNING: the witness was not verified, so the transaction hash is not proved to commit to the assets. Add --network to check it with the verifier contract.");
+                    }
+                    if token_address.is_none() || token_id.is_none() || amount.is_none() {
+                        eprintln!("WARNING: the token or the amount was not checked. Compare them with the claim, or give --token-address, --token-id and --amount.");
+                    }
                 }
                 TransactionCommand::Swap { .. } => {
                     anyhow::bail!("This is a upcoming feature.");
@@ -1082,16 +3897,144 @@ pub async fn invoke_command(command: Command) -> anyhow::Result<()> {
             BlockCommand::Propose {} => {
                 service.trigger_propose_block().await?;
             }
-            BlockCommand::Sign { user_address } => {
+            BlockCommand::Sign {
+                user_address,
+                tx_hashes,
+                wait,
+            } => {
+                let user_address = parse_address(&wallet, &nickname_table, user_address)?;
+                let user_state = wallet
+                    .data
+                    .get_mut(&user_address)
+                    .expect("user address was not found in wallet");
+
+                for tx_hash in tx_hashes.iter() {
+                    let is_unsigned = user_state
+                        .sent_transactions
+                        .get(tx_hash)
+                        .map(|sent_transaction| {
+                            sent_transaction.status == TransactionStatus::Pending
+                                && sent_transaction.proposed_block_number.is_none()
+                        })
+                        .unwrap_or(false);
+                    if !is_unsigned {
+                        anyhow::bail!("transaction {tx_hash} is not waiting for your signature");
+                    }
+                }
+
+                let target_tx_hashes = if tx_hashes.is_empty() {
+                    None
+                } else {
+                    Some(tx_hashes.as_slice())
+                };
+                service
+                    .sign_proposed_block(user_state, user_address, target_tx_hashes, None)
+                    .await?;
+
+                wallet.backup()?;
+
+                if wait {
+                    let user_state = wallet
+                        .data
+                        .get(&user_address)
+                        .expect("user address was not found in wallet");
+                    let signed_transactions = user_state
+                        .sent_transactions
+                        .iter()
+                        .filter(|(tx_hash, sent_transaction)| {
+                            sent_transaction.status == TransactionStatus::Pending
+                                && sent_transaction.proposed_block_number.is_some()
+                                && (tx_hashes.is_empty() || tx_hashes.contains(tx_hash))
+                        })
+                        .map(|(tx_hash, _)| *tx_hash)
+                        .collect::<Vec<_>>();
+                    for tx_hash in signed_transactions {
+                        wait_for_approval(&service, &mut wallet, user_address, tx_hash).await?;
+                    }
+                }
+            }
+            BlockCommand::Stats {
+                blocks: n_blocks,
+                duration,
+            } => {
+                let latest_block_number = service.get_latest_block().await?.header.block_number;
+                let since = latest_block_number.saturating_sub(n_blocks);
+                let (mut blocks, _) = service
+                    .get_blocks(Some(since), Some(latest_block_number))
+                    .await?;
+                blocks.retain(|block| block.header.block_number > since);
+
+                let mut stats = BlockStats::new(latest_block_number, &blocks);
+                progress!("watch new blocks for {duration} seconds");
+                stats.watch(&service, Duration::from_secs(duration)).await?;
+
+                if json {
+                    println!("{}", serde_json::to_string_pretty(&stats)?);
+                } else {
+                    println!("{}", stats.display(&terminal));
+                }
+            }
+            BlockCommand::Pending { user_address } => {
                 let user_address = parse_address(&wallet, &nickname_table, user_address)?;
                 let user_state = wallet
                     .data
                     .get_mut(&user_address)
                     .expect("user address was not found in wallet");
 
-                service.sign_proposed_block(user_state, user_address).await;
+                service
+                    .sync_sent_transaction(user_state, user_address)
+                    .await;
+
+                // The expired transactions are listed so that they are not mistaken for lost ones.
+                let mut pending_transactions = user_state
+                    .sent_transactions
+                    .iter()
+                    .filter(|(_, sent_transaction)| {
+                        sent_transaction.status == TransactionStatus::Pending
+                            || sent_transaction.status == TransactionStatus::Expired
+                    })
+                    .map(|(tx_hash, sent_transaction)| (*tx_hash, sent_transaction.clone()))
+                    .collect::<Vec<_>>();
+                pending_transactions.sort_by_key(|(tx_hash, sent_transaction)| {
+                    (sent_transaction.created_at, tx_hash.to_string())
+                });
 
                 wallet.backup()?;
+
+                if json {
+                    let encoded_pending_transactions = pending_transactions
+                        .iter()
+                        .map(|(tx_hash, sent_transaction)| {
+                            serde_json::json!({
+                                "tx_hash": tx_hash,
+                                "status": sent_transaction.status,
+                                "signed": sent_transaction.proposed_block_number.is_some(),
+                                "proposed_block_number": sent_transaction.proposed_block_number,
+                                "created_at": sent_transaction.created_at,
+                            })
+                        })
+                        .collect::<Vec<_>>();
+                    println!(
+                        "{}",
+                        serde_json::to_string_pretty(&encoded_pending_transactions)?
+                    );
+                } else if pending_transactions.is_empty() {
+                    println!("No pending transactions");
+                } else {
+                    let separator = "--------------------------------------------------------------------------------------";
+                    println!("{}", separator);
+                    for (tx_hash, sent_transaction) in pending_transactions {
+                        println!("  Tx Hash       | {}", tx_hash);
+                        println!("  Status        | {}", sent_transaction.status);
+                        if let Some(proposed_block_number) = sent_transaction.proposed_block_number
+                        {
+                            println!("  Signed        | block {}", proposed_block_number);
+                        } else {
+                            println!("  Signed        | not yet");
+                        }
+                        println!("{}", separator);
+                    }
+                }
             }
             #[cfg(feature = "advanced")]
             BlockCommand::Approve {} => {
@@ -1108,8 +4051,123 @@ pub async fn invoke_command(command: Command) -> anyhow::Result<()> {
             }
             #[cfg(feature = "advanced")]
             BlockCommand::Verify { block_number } => {
-                service.verify_block(block_number).await?;
+                let block_number = if let Some(block_number) = block_number {
+                    block_number
+                } else {
+                    service.get_latest_block().await?.header.block_number
+                };
+                let result = service.verify_block(Some(block_number)).await;
+                if json {
+                    let encoded_result = serde_json::json!({
+                        "block_number": block_number,
+                        "valid": result.is_ok(),
+                        "reason": result.as_ref().err().map(|err| format!("{err:#}")),
+                    });
+                    println!("{}", serde_json::to_string_pretty(&encoded_result)?);
+                }
+                result?;
+            }
+            BlockCommand::List { since, until } => {
+                let latest_block_number = service.get_latest_block().await?.header.block_number;
+                let until = until
+                    .unwrap_or(latest_block_number)
+                    .min(latest_block_number);
+                let since = since.unwrap_or_else(|| until.saturating_sub(DEFAULT_LISTED_BLOCKS));
+                if since >= until {
+                    anyhow::bail!(
+                        "--since must be less than --until and the latest block {latest_block_number}"
+                    );
+                }
+
+                let (mut blocks, _) = service.get_blocks(Some(since), Some(until)).await?;
+                blocks.retain(|block| (since + 1..=until).contains(&block.header.block_number));
+                blocks.sort_by_key(|block| block.header.block_number);
+
+                let block_list = BlockList {
+                    latest_block_number,
+                    blocks: blocks.iter().map(BlockSummary::new).collect(),
+                };
+                if json {
+                    println!("{}", serde_json::to_string_pretty(&block_list)?);
+                } else {
+                    println!("{block_list}");
+                }
+            }
+            BlockCommand::Show { block_number } => {
+                let latest_block_number = service.get_latest_block().await?.header.block_number;
+                if block_number > latest_block_number {
+                    anyhow::bail!("block {block_number} has not been approved yet. The latest approved block is {latest_block_number}");
+                }
+
+                let block = service.get_cached_block(block_number).await?;
+                if json {
+                    let encoded_block = serde_json::json!({
+                        "approved": true,
+                        "block_hash": BlockSummary::new(&block).block_hash,
+                        "block": block,
+                    });
+                    println!("{}", serde_json::to_string_pretty(&encoded_block)?);
+                } else {
+                    println!("{}", BlockView(&block));
+                }
+            }
+            BlockCommand::Export {
+                from,
+                to,
+                format,
+                output,
+            } => {
+                let from = from.unwrap_or(1);
+                if let Some(to) = to {
+                    if from > to {
+                        anyhow::bail!("--from must not be greater than --to");
+                    }
+                }
+
+                let blocks = service.get_cached_blocks(from, to).await?;
+
+                for file_path in export_blocks(&blocks, &output, format)? {
+                    println!("write {}", file_path.display());
+                }
+                println!("{} blocks were exported", blocks.len());
+            }
+            BlockCommand::HeaderProof {
+                block_number,
+                output,
+            } => {
+                if block_number == 0 {
+                    anyhow::bail!("the genesis block has no prior header");
+                }
+
+                let (blocks, _) = service
+                    .get_blocks(Some(block_number - 1), Some(block_number))
+                    .await?;
+                let block = blocks
+                    .into_iter()
+                    .find(|block| block.header.block_number == block_number)
+                    .ok_or_else(|| anyhow::anyhow!("block {block_number} was not found"))?;
+                let block_details = service.get_block_details(block_number).await?;
+
+                let proof = BlockHeaderProof::new(
+                    block.header,
+                    block_details.prev_block_header,
+                    block_details.block_headers_proof_siblings,
+                );
+                proof.verify()?;
+
+                let encoded_proof = encode_proof_artifact(&ProofArtifact::Header(proof))?;
+                if let Some(output) = output {
+                    std::fs::write(&output, encoded_proof)
+                        .with_context(|| format!("fail to write {}", output.display()))?;
+                    println!("the header proof was written into {}", output.display());
+                } else {
+                    println!("{encoded_proof}");
+                }
             }
+            #[cfg(not(feature = "advanced"))]
+            BlockCommand::ProposeNotCompiled { .. }
+            | BlockCommand::ApproveNotCompiled { .. }
+            | BlockCommand::VerifyNotCompiled { .. } => {}
         },
         #[cfg(feature = "interoperability")]
         SubCommand::Interoperability { io_command } => match io_command {
@@ -1146,18 +4204,16 @@ pub async fn invoke_command(command: Command) -> anyhow::Result<()> {
                     anyhow::bail!("Polygon ZKEVM testnet cannot be selected now");
                 }
 
-                let network_config = get_network_config(network_name);
+                let network_config =
+                    resolve_network_config(network_name, rpc_url.as_deref(), &profile.rpc).await?;
                 let secret_key =
-                    std::env::var("PRIVATE_KEY").expect("PRIVATE_KEY must be set in .env file");
+                    resolve_signer_secret_key(&signers_dir, signer.as_deref(), &terminal)?;
 
+                let receiver_address = resolve_qr_image_argument(receiver_address)?;
                 let receiver_address = if receiver_address.is_empty() {
                     anyhow::bail!("empty recipient");
-                } else if receiver_address.starts_with("0x") {
-                    if receiver_address.len() != 18 {
-                        anyhow::bail!("recipient must be 8 bytes hex string with 0x-prefix");
-                    }
-
-                    Address::from_str(&receiver_address)?
+                } else if is_address_literal(&receiver_address) {
+                    parse_intmax_address("recipient", &receiver_address)?
                 } else if let Some(receiver_address) =
                     nickname_table.nickname_to_address.get(&receiver_address)
                 {
@@ -1166,21 +4222,13 @@ pub async fn invoke_command(command: Command) -> anyhow::Result<()> {
                     anyhow::bail!("unregistered nickname: recipient");
                 };
 
-                let contract_address = if let Some(contract_address) = contract_address {
-                    if contract_address.is_empty() {
-                        anyhow::bail!("empty token address");
-                    } else if contract_address.starts_with("0x") {
-                        Address::from_str(&contract_address)?
-                    } else if let Some(contract_address) =
-                        nickname_table.nickname_to_address.get(&contract_address)
-                    {
-                        *contract_address
-                    } else {
-                        anyhow::bail!("unregistered nickname: token address");
-                    }
-                } else {
-                    user_address
-                };
+                let contract_address = resolve_token_address(
+                    &nickname_table,
+                    contract_address,
+                    DefaultTokenAddress::Mine,
+                    user_address,
+                    Some(receiver_address),
+                )?;
 
                 let variable_index = if let Some(variable_index) = variable_index {
                     if is_nft && variable_index == 0u8.into() {
@@ -1212,15 +4260,7 @@ pub async fn invoke_command(command: Command) -> anyhow::Result<()> {
 
                 let payment_token_address =
                     if let Some(payment_token_address) = payment_token_address {
-                        let payment_token_address = if let Some(stripped_payment_token_address) =
-                            payment_token_address.strip_prefix("0x")
-                        {
-                            stripped_payment_token_address.to_string()
-                        } else {
-                            payment_token_address
-                        };
-
-                        Some(H160::from_str(&payment_token_address).unwrap())
+                        Some(parse_h160("--taker-token", &payment_token_address)?)
                     } else {
                         None
                     };
@@ -1235,13 +4275,29 @@ pub async fn invoke_command(command: Command) -> anyhow::Result<()> {
 
                         get_token_metadata(&network_config, payment_token_address).await?
                     } else {
-                        select_payment_method(&network_config, false)
+                        select_payment_method(&network_config, false, &terminal)
                             .await?
                             .context("stop operation")?
                     };
 
                 ctrlc::set_handler(|| {}).expect("Error setting Ctrl-C handler");
 
+                let mut intents_file_path = wallet_dir_path.clone();
+                intents_file_path.push(REGISTER_INTENTS_FILE_NAME);
+
+                // Complete the registrations interrupted before.
+                resume_register_intents(
+                    &service,
+                    &wallet,
+                    &intents_file_path,
+                    network_name,
+                    &network_config,
+                    &secret_key,
+                    user_address,
+                    &wallet_dir_path,
+                )
+                .await?;
+
                 merge(&service, &mut wallet, user_address, 0).await?;
 
                 let user_state = wallet
@@ -1259,78 +4315,193 @@ pub async fn invoke_command(command: Command) -> anyhow::Result<()> {
                     "transfer amount is too much"
                 );
 
-                let temporary_receiver_address = match network_name {
-                    NetworkName::ScrollAlpha => Address(F::from_canonical_u64(1)),
-                    NetworkName::PolygonZkEvmTest => Address(F::from_canonical_u64(2)),
-                };
                 let output_asset = ContributedAsset {
-                    receiver_address: temporary_receiver_address,
+                    receiver_address: get_temporary_receiver_address(network_name),
                     kind: TokenKind {
                         contract_address,
                         variable_index,
                     },
                     amount: maker_amount,
                 };
-                #[cfg(feature = "verbose")]
-                dbg!(serde_json::to_string(&output_asset).unwrap());
+                service.debug_dump("output_asset", &output_asset);
+
+                let taker_amount = U256::from_little_endian(
+                    &BigUint::from_str(&taker_amount).unwrap().to_bytes_le(),
+                );
 
-                let tx_hash =
-                    transfer(&service, &mut wallet, user_address, &[output_asset]).await?;
+                // Record the intent before sending the assets, so that the registration can be
+                // completed by the next `io register` or `io repair` if it is interrupted.
+                let mut intents = RegisterIntents::read_from_file(&intents_file_path)?;
+                intents.intents.push(RegisterIntent::new(
+                    network_name,
+                    user_address,
+                    receiver_address,
+                    output_asset.kind,
+                    maker_amount,
+                    payment_token_metadata.address,
+                    taker_amount,
+                    max_gas_price.or(profile.limits.max_gas_price),
+                )?);
+                let intent_index = intents.intents.len() - 1;
+                intents.write_to_file(&intents_file_path)?;
+
+                let tx_hash = transfer(
+                    &service,
+                    &mut wallet,
+                    user_address,
+                    &[output_asset],
+                    false,
+                    true,
+                )
+                .await?;
 
                 wallet.backup()?;
 
-                if tx_hash.is_none() {
+                let tx_hash = if let Some(tx_hash) = tx_hash {
+                    tx_hash
+                } else {
+                    intents.intents[intent_index].status = RegisterIntentStatus::Canceled;
+                    intents.write_to_file(&intents_file_path)?;
                     anyhow::bail!("exit transaction should exist");
+                };
+
+                intents.intents[intent_index].status =
+                    RegisterIntentStatus::Transferred { tx_hash };
+                intents.write_to_file(&intents_file_path)?;
+
+                let offer_id = intents.intents[intent_index]
+                    .register(
+                        &service,
+                        &network_config,
+                        &secret_key,
+                        tx_hash,
+                        &mut GasPolicy::default(),
+                    )
+                    .await
+                    .with_context(|| {
+                        format!(
+                            "the intmax transfer {tx_hash} was sent, but the offer was not registered. Run `io repair --network {network_name}` to complete it"
+                        )
+                    })?;
+                intents.intents[intent_index].status = RegisterIntentStatus::Registered {
+                    tx_hash: Some(tx_hash),
+                    offer_id,
+                };
+                intents.write_to_file(&intents_file_path)?;
+                println!("offer_id: {}", offer_id);
+
+                verify_registered_offer(
+                    &wallet,
+                    &network_config,
+                    &secret_key,
+                    &intents.intents[intent_index],
+                    offer_id,
+                    tx_hash,
+                    &wallet_dir_path,
+                )
+                .await?;
+            }
+            InteroperabilityCommand::Repair {
+                user_address,
+                offer_id,
+                network_name,
+            } => {
+                let user_address = parse_address(&wallet, &nickname_table, user_address)?;
+                {
+                    let user_state = wallet
+                        .data
+                        .get_mut(&user_address)
+                        .expect("user address was not found in wallet");
+
+                    service
+                        .sync_sent_transaction(user_state, user_address)
+                        .await;
+
+                    wallet.backup()?;
+                }
+
+                let network_name: NetworkName =
+                    network_name.parse().context("invalid network name")?;
+                #[cfg(not(feature = "enable-polygon-zkevm"))]
+                if network_name == NetworkName::PolygonZkEvmTest {
+                    anyhow::bail!("Polygon ZKEVM testnet cannot be selected now");
+                }
+
+                let network_config =
+                    resolve_network_config(network_name, rpc_url.as_deref(), &profile.rpc).await?;
+                let secret_key =
+                    resolve_signer_secret_key(&signers_dir, signer.as_deref(), &terminal)?;
+
+                let mut intents_file_path = wallet_dir_path.clone();
+                intents_file_path.push(REGISTER_INTENTS_FILE_NAME);
+
+                if let Some(offer_id) = offer_id {
+                    // The offer was registered, but the result was not recorded.
+                    let offer = get_offer(&network_config, offer_id.into(), false)
+                        .await
+                        .with_context(|| format!("offer {offer_id} was not found"))?;
+                    let mut intents = RegisterIntents::read_from_file(&intents_file_path)?;
+                    let i = intents
+                        .pending(user_address, network_name)
+                        .into_iter()
+                        .find(|i| intents.intents[*i].matches_offer(&offer))
+                        .with_context(|| {
+                            format!("no pending registration matches offer {offer_id}")
+                        })?;
+                    let tx_hash = intents.intents[i].tx_hash();
+                    intents.intents[i].status = RegisterIntentStatus::Registered {
+                        tx_hash,
+                        offer_id: offer_id.into(),
+                    };
+                    intents.write_to_file(&intents_file_path)?;
+
+                    if let Some(tx_hash) = tx_hash {
+                        verify_registered_offer(
+                            &wallet,
+                            &network_config,
+                            &secret_key,
+                            &intents.intents[i],
+                            offer_id.into(),
+                            tx_hash,
+                            &wallet_dir_path,
+                        )
+                        .await?;
+                    }
                 }
 
-                let tx_hash = tx_hash.unwrap();
-
-                let witness = create_transaction_proof(
+                resume_register_intents(
                     &service,
-                    Some(network_config.clone()),
-                    *tx_hash,
-                    output_asset.receiver_address,
-                )
-                .await?;
-
-                let signer_key =
-                    SigningKey::from_bytes(&hex::decode(&secret_key).unwrap()).unwrap();
-                let my_account = secret_key_to_address(&signer_key);
-                let sending_transfer_info = MakerTransferInfo {
-                    address: my_account,
-                    intmax_account: user_address,
-                    kind: TokenKind {
-                        contract_address,
-                        variable_index,
-                    },
-                    amount: maker_amount,
-                };
-                let taker_amount = U256::from_little_endian(
-                    &BigUint::from_str(&taker_amount).unwrap().to_bytes_le(),
-                );
-                let receiving_transfer_info = TakerTransferInfo {
-                    address: H160::default(), // anyone can activate
-                    intmax_account: receiver_address,
-                    token_address: payment_token_metadata.address,
-                    amount: taker_amount,
-                };
-
-                let offer_id = register_transfer(
+                    &wallet,
+                    &intents_file_path,
+                    network_name,
                     &network_config,
-                    secret_key,
-                    sending_transfer_info,
-                    receiving_transfer_info,
-                    max_gas_price.map(gwei_to_wei),
-                    witness,
+                    &secret_key,
+                    user_address,
+                    &wallet_dir_path,
                 )
                 .await?;
-                println!("offer_id: {}", offer_id);
+
+                let intents = RegisterIntents::read_from_file(&intents_file_path)?;
+                let network_name = network_name.to_string();
+                for intent in intents.intents.iter().filter(|intent| {
+                    intent.user_address == user_address && intent.network_name == network_name
+                }) {
+                    match &intent.status {
+                        RegisterIntentStatus::Registered { offer_id, .. } => {
+                            println!("{}: registered (offer_id: {})", intent.created_at, offer_id)
+                        }
+                        RegisterIntentStatus::Canceled => {
+                            println!("{}: canceled", intent.created_at)
+                        }
+                        _ => println!("{}: pending", intent.created_at),
+                    }
+                }
             }
             InteroperabilityCommand::Activate {
                 // user_address,
                 offer_id,
                 network_name,
-                ..
+                faucet,
             } => {
                 // let _user_address = parse_address(&wallet, &nickname_table, user_address)?;
                 // let user_state = wallet
@@ -1345,21 +4516,74 @@ pub async fn invoke_command(command: Command) -> anyhow::Result<()> {
                     anyhow::bail!("Polygon ZKEVM testnet cannot be selected now");
                 }
 
-                let network_config = get_network_config(network_name);
+                let network_config =
+                    resolve_network_config(network_name, rpc_url.as_deref(), &profile.rpc).await?;
                 let secret_key =
-                    std::env::var("PRIVATE_KEY").expect("PRIVATE_KEY must be set in .env file");
+                    resolve_signer_secret_key(&signers_dir, signer.as_deref(), &terminal)?;
+
+                if faucet {
+                    let signer_key = parse_private_key_env(&secret_key)?;
+                    let my_account = secret_key_to_address(&signer_key);
+                    if get_balance(&network_config, my_account).await?.is_zero() {
+                        fund_from_faucet(
+                            network_name,
+                            &network_config,
+                            get_faucet_url(&profile.faucet, network_name),
+                            my_account,
+                        )
+                        .await?;
+                    }
+                }
 
                 let offer_id: U256 = offer_id.into();
-                let is_activated = activate_offer(&network_config, secret_key, offer_id).await?;
+                let is_activated = activate_offer(
+                    &network_config,
+                    &secret_key,
+                    offer_id,
+                    &mut GasPolicy::default(),
+                    profile.limits.max_gas_price.map(gwei_to_wei),
+                )
+                .await?;
 
                 if !is_activated {
                     anyhow::bail!("The activation was succeeded, but it has not reflect yet. Please rerun `intmax io activate <offer-id>` after few minutes.");
                 }
 
                 // reflect to deposit tree
-                service.resolve_server_health_issue().await.unwrap();
-                service.trigger_propose_block().await.unwrap();
-                service.trigger_approve_block().await.unwrap();
+                service.resolve_server_health_issue().await?;
+                service.trigger_propose_block().await?;
+                service.trigger_approve_block().await?;
+            }
+            InteroperabilityCommand::Faucet {
+                network_name,
+                address,
+            } => {
+                let network_name: NetworkName =
+                    network_name.parse().context("invalid network name")?;
+                #[cfg(not(feature = "enable-polygon-zkevm"))]
+                if network_name == NetworkName::PolygonZkEvmTest {
+                    anyhow::bail!("Polygon ZKEVM testnet cannot be selected now");
+                }
+
+                let network_config =
+                    resolve_network_config(network_name, rpc_url.as_deref(), &profile.rpc).await?;
+                let address: H160 = if let Some(address) = address {
+                    parse_h160("--address", &address)?
+                } else {
+                    let secret_key =
+                        resolve_signer_secret_key(&signers_dir, signer.as_deref(), &terminal)?;
+                    let signer_key = parse_private_key_env(&secret_key)?;
+
+                    secret_key_to_address(&signer_key)
+                };
+
+                fund_from_faucet(
+                    network_name,
+                    &network_config,
+                    get_faucet_url(&profile.faucet, network_name),
+                    address,
+                )
+                .await?;
             }
             InteroperabilityCommand::Lock {
                 user_address,
@@ -1394,18 +4618,16 @@ pub async fn invoke_command(command: Command) -> anyhow::Result<()> {
                     anyhow::bail!("Polygon ZKEVM testnet cannot be selected now");
                 }
 
-                let network_config = get_network_config(network_name);
+                let network_config =
+                    resolve_network_config(network_name, rpc_url.as_deref(), &profile.rpc).await?;
                 let secret_key =
-                    std::env::var("PRIVATE_KEY").expect("PRIVATE_KEY must be set in .env file");
+                    resolve_signer_secret_key(&signers_dir, signer.as_deref(), &terminal)?;
 
+                let receiver_address = resolve_qr_image_argument(receiver_address)?;
                 let receiver_address = if receiver_address.is_empty() {
                     anyhow::bail!("empty recipient");
-                } else if receiver_address.starts_with("0x") {
-                    if receiver_address.len() != 18 {
-                        anyhow::bail!("recipient must be 8 bytes hex string with 0x-prefix");
-                    }
-
-                    Address::from_str(&receiver_address)?
+                } else if is_address_literal(&receiver_address) {
+                    parse_intmax_address("recipient", &receiver_address)?
                 } else if let Some(receiver_address) =
                     nickname_table.nickname_to_address.get(&receiver_address)
                 {
@@ -1414,25 +4636,16 @@ pub async fn invoke_command(command: Command) -> anyhow::Result<()> {
                     anyhow::bail!("unregistered nickname: recipient");
                 };
 
-                let contract_address = if let Some(contract_address) = contract_address {
-                    if contract_address.is_empty() {
-                        anyhow::bail!("empty token address");
-                    } else if contract_address.starts_with("0x") {
-                        Address::from_str(&contract_address)?
-                    } else if let Some(contract_address) =
-                        nickname_table.nickname_to_address.get(&contract_address)
-                    {
-                        *contract_address
-                    } else {
-                        anyhow::bail!("unregistered nickname: token address");
-                    }
-                } else {
-                    if receiver_address == Address::default() {
-                        anyhow::bail!("contract_address must be non-zero address");
-                    }
-
-                    receiver_address
-                };
+                let contract_address = resolve_token_address(
+                    &nickname_table,
+                    contract_address,
+                    DefaultTokenAddress::Receiver,
+                    user_address,
+                    Some(receiver_address),
+                )?;
+                if contract_address == Address::default() {
+                    anyhow::bail!("contract_address must be non-zero address");
+                }
 
                 let variable_index = if let Some(variable_index) = variable_index {
                     if is_nft && variable_index == 0u8.into() {
@@ -1450,12 +4663,7 @@ pub async fn invoke_command(command: Command) -> anyhow::Result<()> {
                     0u8.into()
                 };
 
-                let receiver = if let Some(stripped_receiver) = receiver.strip_prefix("0x") {
-                    stripped_receiver.to_string()
-                } else {
-                    receiver
-                };
-                let receiver = H160::from_str(&receiver).unwrap();
+                let receiver = parse_h160("--receiver", &receiver)?;
 
                 let maker_amount = if let Some(maker_amount) = maker_amount {
                     if is_nft {
@@ -1471,15 +4679,7 @@ pub async fn invoke_command(command: Command) -> anyhow::Result<()> {
 
                 let payment_token_address =
                     if let Some(payment_token_address) = payment_token_address {
-                        let payment_token_address = if let Some(stripped_payment_token_address) =
-                            payment_token_address.strip_prefix("0x")
-                        {
-                            stripped_payment_token_address.to_string()
-                        } else {
-                            payment_token_address
-                        };
-
-                        Some(H160::from_str(&payment_token_address).unwrap())
+                        Some(parse_h160("--taker-token", &payment_token_address)?)
                     } else {
                         None
                     };
@@ -1495,15 +4695,14 @@ pub async fn invoke_command(command: Command) -> anyhow::Result<()> {
 
                         get_token_metadata(&network_config, payment_token_address).await?
                     } else {
-                        select_payment_method(&network_config, true)
+                        select_payment_method(&network_config, true, &terminal)
                             .await?
                             .context("stop operation")?
                     };
 
                 ctrlc::set_handler(|| {}).expect("Error setting Ctrl-C handler");
 
-                let signer_key =
-                    SigningKey::from_bytes(&hex::decode(&secret_key).unwrap()).unwrap();
+                let signer_key = parse_private_key_env(&secret_key)?;
                 let my_account = secret_key_to_address(&signer_key);
                 let taker_amount = U256::from_little_endian(
                     &BigUint::from_str(&taker_amount).unwrap().to_bytes_le(),
@@ -1530,11 +4729,13 @@ pub async fn invoke_command(command: Command) -> anyhow::Result<()> {
 
                 let offer_id = lock_offer(
                     &network_config,
-                    secret_key,
+                    &secret_key,
                     sending_transfer_info,
                     receiving_transfer_info,
+                    &mut GasPolicy::default(),
+                    profile.limits.max_gas_price.map(gwei_to_wei),
                 )
-                .await;
+                .await?;
                 println!("offer_id: {}", offer_id);
             }
             InteroperabilityCommand::Unlock {
@@ -1542,6 +4743,7 @@ pub async fn invoke_command(command: Command) -> anyhow::Result<()> {
                 offer_id,
                 network_name,
                 tx_hash,
+                dry_run,
             } => {
                 let user_address = parse_address(&wallet, &nickname_table, user_address)?;
                 {
@@ -1564,9 +4766,10 @@ pub async fn invoke_command(command: Command) -> anyhow::Result<()> {
                     anyhow::bail!("Polygon ZKEVM testnet cannot be selected now");
                 }
 
-                let network_config = get_network_config(network_name);
+                let network_config =
+                    resolve_network_config(network_name, rpc_url.as_deref(), &profile.rpc).await?;
                 let secret_key =
-                    std::env::var("PRIVATE_KEY").expect("PRIVATE_KEY must be set in .env file");
+                    resolve_signer_secret_key(&signers_dir, signer.as_deref(), &terminal)?;
 
                 let offer = get_offer(&network_config, offer_id.into(), true).await;
 
@@ -1580,12 +4783,14 @@ pub async fn invoke_command(command: Command) -> anyhow::Result<()> {
                     return anyhow::Ok(());
                 }
 
-                let signer_key =
-                    SigningKey::from_bytes(&hex::decode(secret_key.clone()).unwrap()).unwrap();
+                let signer_key = parse_private_key_env(&secret_key)?;
                 let my_account = secret_key_to_address(&signer_key);
                 if offer.maker != my_account {
-                    dbg!(offer.maker, my_account);
-                    anyhow::bail!("Only the maker can unlock this offer");
+                    anyhow::bail!(
+                        "Only the maker can unlock this offer (maker: {:?}, PRIVATE_KEY: {:?})",
+                        offer.maker,
+                        my_account
+                    );
                 }
 
                 let maker_token_kind = {
@@ -1597,13 +4802,26 @@ pub async fn invoke_command(command: Command) -> anyhow::Result<()> {
                 };
                 let maker_amount = offer.maker_amount.as_u64();
 
-                merge(&service, &mut wallet, user_address, 0).await?;
+                let total_amount_map = if dry_run {
+                    let user_state = wallet
+                        .data
+                        .get(&user_address)
+                        .expect("user address was not found in wallet");
 
-                let user_state = wallet
-                    .data
-                    .get_mut(&user_address)
-                    .expect("user address was not found in wallet");
-                let total_amount_map = user_state.assets.calc_total_amount();
+                    let summary = calc_unmerged_assets(user_state);
+                    warn_unmerged_assets_summary(&summary);
+
+                    summary.assets.calc_total_amount()
+                } else {
+                    merge(&service, &mut wallet, user_address, 0).await?;
+
+                    let user_state = wallet
+                        .data
+                        .get_mut(&user_address)
+                        .expect("user address was not found in wallet");
+
+                    user_state.assets.calc_total_amount()
+                };
 
                 let balance = total_amount_map
                     .get(&(
@@ -1613,10 +4831,11 @@ pub async fn invoke_command(command: Command) -> anyhow::Result<()> {
                     .cloned()
                     .unwrap_or_default();
                 #[cfg(feature = "verbose")]
-                dbg!(
-                    maker_token_kind.contract_address.to_string(),
-                    maker_token_kind.variable_index.to_string(),
-                    &balance
+                progress!(
+                    "balance of token {} {}: {}",
+                    maker_token_kind.contract_address.debug_format(),
+                    maker_token_kind.variable_index.debug_format(),
+                    balance
                 );
                 anyhow::ensure!(
                     BigUint::from(maker_amount).le(&balance),
@@ -1643,35 +4862,104 @@ pub async fn invoke_command(command: Command) -> anyhow::Result<()> {
                     kind: maker_token_kind,
                     amount: maker_amount,
                 };
-                #[cfg(feature = "verbose")]
-                dbg!(serde_json::to_string(&output_asset).unwrap());
+                service.debug_dump("output_asset", &output_asset);
 
-                let tx_hash = if let Some(tx_hash) = tx_hash {
-                    tx_hash.parse().expect("given tx-hash is invalid")
+                let tx_hash: TxHash<F> = if let Some(tx_hash) = tx_hash {
+                    parse_hash_out("--tx-hash", &tx_hash)?.into()
+                } else if dry_run {
+                    println!("dry run: the offer can be unlocked after sending {maker_amount} tokens on intmax");
+                    return Ok(());
                 } else {
-                    transfer(&service, &mut wallet, user_address, &[output_asset])
-                        .await?
-                        .expect("no transaction was sent")
+                    transfer(
+                        &service,
+                        &mut wallet,
+                        user_address,
+                        &[output_asset],
+                        false,
+                        true,
+                    )
+                    .await?
+                    .expect("no transaction was sent")
                 };
 
                 let witness = create_transaction_proof(
                     &service,
                     Some(network_config.clone()),
-                    *tx_hash,
+                    *tx_hash.0,
                     output_asset.receiver_address,
                 )
                 .await?;
 
                 let offer_id: U256 = offer_id.into();
-                let _is_unlocked =
-                    unlock_offer(&network_config, secret_key, offer_id, witness).await?;
+                if dry_run {
+                    check_unlock_witness(&network_config, offer_id, witness).await?;
+                    println!("dry run: the witness is valid");
+                    return Ok(());
+                }
+
+                let _is_unlocked = unlock_offer(
+                    &network_config,
+                    &secret_key,
+                    offer_id,
+                    witness,
+                    &mut GasPolicy::default(),
+                    profile.limits.max_gas_price.map(gwei_to_wei),
+                )
+                .await?;
+            }
+            InteroperabilityCommand::Events {
+                network_name,
+                from_block,
+                to_block,
+                maker,
+                taker,
+                intmax_address,
+                format,
+                output,
+            } => {
+                let filter = OfferEventFilter {
+                    maker: maker.map(|maker| parse_h160("maker", &maker)).transpose()?,
+                    taker: taker.map(|taker| parse_h160("taker", &taker)).transpose()?,
+                    intmax_address: intmax_address
+                        .map(|intmax_address| {
+                            parse_address(&wallet, &nickname_table, Some(intmax_address))
+                                .map(|address| encode_intmax_account(address).into())
+                        })
+                        .transpose()?,
+                };
+                let network_config =
+                    resolve_network_config(network_name.parse()?, rpc_url.as_deref(), &profile.rpc)
+                        .await?;
+
+                let events = get_offer_events(&network_config, from_block, to_block)
+                    .await?
+                    .into_iter()
+                    .filter(|event| filter.matches(event))
+                    .collect::<Vec<_>>();
+                let encoded_events = match format {
+                    OfferEventsFormat::Json => serde_json::to_string_pretty(&events)? + "\n",
+                    OfferEventsFormat::Csv => offer_events_to_csv(&events),
+                };
+                if let Some(output) = output {
+                    std::fs::write(&output, encoded_events)
+                        .with_context(|| format!("fail to write {}", output.display()))?;
+                    println!(
+                        "{} events were written into {}",
+                        events.len(),
+                        output.display()
+                    );
+                } else {
+                    print!("{encoded_events}");
+                }
             }
             InteroperabilityCommand::View {
                 offer_id,
                 network_name,
                 is_reverse_offer,
             } => {
-                let network_config = get_network_config(network_name.parse()?);
+                let network_config =
+                    resolve_network_config(network_name.parse()?, rpc_url.as_deref(), &profile.rpc)
+                        .await?;
 
                 let offer = get_offer(&network_config, offer_id.into(), is_reverse_offer).await;
 
@@ -1679,6 +4967,56 @@ pub async fn invoke_command(command: Command) -> anyhow::Result<()> {
                     let mut maker_asset_id = [0u8; 32];
                     offer.maker_asset_id.to_little_endian(&mut maker_asset_id);
                     let maker_token_kind = TokenKind::<F>::from_bytes(&maker_asset_id);
+                    let maker_address = format!("0x{}", hex::encode(offer.maker.to_fixed_bytes()));
+                    let maker_intmax_address =
+                        format!("0x{}", hex::encode(offer.maker_intmax_address));
+                    let taker_address = format!("0x{}", hex::encode(offer.taker.to_fixed_bytes()));
+                    let taker_intmax_address =
+                        format!("0x{}", hex::encode(offer.taker_intmax_address));
+                    let taker_token_address = format!(
+                        "0x{}",
+                        hex::encode(offer.taker_token_address.to_fixed_bytes())
+                    );
+                    let address_renderer = known_address_renderer(
+                        &terminal,
+                        &wallet,
+                        &nickname_table,
+                        &[
+                            maker_address.clone(),
+                            maker_intmax_address.clone(),
+                            maker_token_kind.contract_address.to_string(),
+                            taker_address.clone(),
+                            taker_intmax_address.clone(),
+                            taker_token_address.clone(),
+                        ],
+                    );
+                    if json {
+                        let status = if offer.is_activated {
+                            "activated"
+                        } else {
+                            "not_activated"
+                        };
+                        let encoded_offer = serde_json::json!({
+                            "status": status,
+                            "maker": {
+                                "address": maker_address,
+                                "intmax_address": maker_intmax_address,
+                                "token_address": maker_token_kind.contract_address.to_string(),
+                                "token_id": maker_token_kind.variable_index.to_string(),
+                                "amount": offer.maker_amount.to_string(),
+                            },
+                            "taker": {
+                                "address": taker_address,
+                                "intmax_address": taker_intmax_address,
+                                "token_address": taker_token_address,
+                                "amount": offer.taker_amount.to_string(),
+                            },
+                        });
+                        println!("{}", serde_json::to_string_pretty(&encoded_offer)?);
+
+                        return Ok(());
+                    }
+
                     println!(
                         "Status       | {}",
                         if offer.is_activated {
@@ -1689,38 +5027,86 @@ pub async fn invoke_command(command: Command) -> anyhow::Result<()> {
                     );
                     println!("Maker        |");
                     println!(
-                        "  {:10} | 0x{}",
+                        "  {:10} | {}",
                         network_name,
-                        hex::encode(offer.maker.to_fixed_bytes())
+                        address_renderer.render(&maker_address)
                     );
                     println!(
-                        "  intmax     | 0x{}",
-                        hex::encode(offer.maker_intmax_address)
+                        "  intmax     | {}",
+                        address_renderer.render(&maker_intmax_address)
                     );
                     println!("  Asset      |");
-                    println!("    Address  | {}", maker_token_kind.contract_address);
+                    println!(
+                        "    Address  | {}",
+                        address_renderer.render(&maker_token_kind.contract_address.to_string())
+                    );
                     println!("    Token ID | {}", maker_token_kind.variable_index);
                     println!("  Amount     | {}", offer.maker_amount);
                     println!("Taker        |",);
                     println!(
-                        "  {:10} | 0x{}",
+                        "  {:10} | {}",
                         network_name,
-                        hex::encode(offer.taker.to_fixed_bytes())
+                        address_renderer.render(&taker_address)
                     );
                     println!(
-                        "  intmax     | 0x{}",
-                        hex::encode(offer.taker_intmax_address)
+                        "  intmax     | {}",
+                        address_renderer.render(&taker_intmax_address)
                     );
                     println!("  Asset      |");
                     println!(
-                        "    Address  | 0x{}",
-                        hex::encode(offer.taker_token_address.to_fixed_bytes())
+                        "    Address  | {}",
+                        address_renderer.render(&taker_token_address)
                     );
                     println!("  Amount     | {}", offer.taker_amount);
+                } else if json {
+                    println!(
+                        "{}",
+                        serde_json::to_string_pretty(
+                            &serde_json::json!({ "status": "not_registered" })
+                        )?
+                    );
                 } else {
                     println!("Status       | NOT REGISTERED");
                 }
             }
+            InteroperabilityCommand::Network {
+                network_command: NetworkCommand::Status { network_name },
+            } => {
+                let network_names = if let Some(network_name) = network_name {
+                    vec![network_name.parse().context("invalid network name")?]
+                } else {
+                    vec![NetworkName::ScrollAlpha, NetworkName::PolygonZkEvmTest]
+                };
+
+                let separator = "--------------------------------------------------------------------------------------";
+                println!("{}", separator);
+                for network_name in network_names {
+                    let rpc_url = rpc_url
+                        .as_deref()
+                        .or_else(|| get_rpc_url(&profile.rpc, network_name));
+                    let network_config = get_network_config_with_rpc_url(network_name, rpc_url);
+                    println!("  Network       | {}", network_name);
+                    println!("  RPC URL       | {}", network_config.rpc_url);
+                    match get_rpc_status(&network_config).await {
+                        Ok(status) => {
+                            let chain_id_status =
+                                if status.chain_id == network_config.chain_id.into() {
+                                    "ok".to_string()
+                                } else {
+                                    format!("WRONG NETWORK (expected {})", network_config.chain_id)
+                                };
+                            println!("  Chain ID      | {} ({chain_id_status})", status.chain_id);
+                            println!("  Latency       | {} ms", status.latency.as_millis());
+                            println!("  Block Number  | {}", status.block_number);
+                            println!("  Gas Price     | {} Gwei", wei_to_gwei(status.gas_price));
+                        }
+                        Err(err) => {
+                            println!("  Status        | UNREACHABLE: {err:#}");
+                        }
+                    }
+                    println!("{}", separator);
+                }
+            }
         },
         #[cfg(feature = "bridge")]
         SubCommand::Bridge { bridge_command } => {
@@ -1749,21 +5135,13 @@ pub async fn invoke_command(command: Command) -> anyhow::Result<()> {
                         anyhow::bail!("cannot send asset to myself");
                     }
 
-                    let contract_address = if let Some(contract_address) = contract_address {
-                        if contract_address.is_empty() {
-                            anyhow::bail!("empty token address");
-                        } else if contract_address.starts_with("0x") {
-                            Address::from_str(&contract_address)?
-                        } else if let Some(contract_address) =
-                            nickname_table.nickname_to_address.get(&contract_address)
-                        {
-                            *contract_address
-                        } else {
-                            anyhow::bail!("unregistered nickname: token address");
-                        }
-                    } else {
-                        user_address
-                    };
+                    let contract_address = resolve_token_address(
+                        &nickname_table,
+                        contract_address,
+                        DefaultTokenAddress::Mine,
+                        user_address,
+                        None,
+                    )?;
 
                     let variable_index = if let Some(variable_index) = variable_index {
                         if is_nft && variable_index == 0u8.into() {
@@ -1803,15 +5181,388 @@ pub async fn invoke_command(command: Command) -> anyhow::Result<()> {
                         },
                         amount,
                     };
-                    #[cfg(feature = "verbose")]
-                    dbg!(serde_json::to_string(&output_asset).unwrap());
+                    service.debug_dump("output_asset", &output_asset);
 
                     ctrlc::set_handler(|| {}).expect("Error setting Ctrl-C handler");
 
-                    transfer(&service, &mut wallet, user_address, &[output_asset]).await?;
+                    transfer(
+                        &service,
+                        &mut wallet,
+                        user_address,
+                        &[output_asset],
+                        false,
+                        true,
+                    )
+                    .await?;
+                }
+            }
+        }
+        #[cfg(feature = "dev")]
+        SubCommand::Dev { dev_command } => match dev_command {
+            DevCommand::CreateAccounts {
+                count,
+                prefix,
+                interval,
+                output,
+            } => {
+                if count == 0 {
+                    anyhow::bail!("--count must be a positive integer");
+                }
+
+                // Check the nicknames before registering any account.
+                let nicknames = (1..=count)
+                    .map(|i| prefix.as_ref().map(|prefix| format!("{prefix}{i}")))
+                    .collect::<Vec<_>>();
+                for nickname in nicknames.iter().flatten() {
+                    if nickname.len() > 12 {
+                        anyhow::bail!("choose a prefix so that the nicknames are less than or equal to 12 characters: {nickname}");
+                    }
+
+                    if nickname_to_address(&nickname_table, nickname).is_some() {
+                        anyhow::bail!("nickname {nickname} is already used");
+                    }
+                }
+
+                let mut records = vec![];
+                for (i, nickname) in nicknames.into_iter().enumerate() {
+                    if i != 0 {
+                        tokio::time::sleep(Duration::from_millis(interval)).await;
+                    }
+
+                    let private_key = WrappedHashOut::rand();
+                    let account = Account::new(*private_key);
+                    service.register_account(account.public_key).await?;
+                    wallet.add_account(account)?;
+                    wallet.backup()?;
+                    eprintln!("new account added ({}/{count}): {}", i + 1, account.address);
+
+                    if let Some(nickname) = &nickname {
+                        set_nickname(&mut nickname_table, account.address, nickname.clone())?;
+                    }
+
+                    records.push((account.address, private_key, nickname));
+                }
+
+                service.resolve_server_health_issue().await?;
+                service.trigger_propose_block().await?;
+                service.trigger_approve_block().await?;
+
+                let mut csv = "address,private_key,nickname\n".to_string();
+                for (address, private_key, nickname) in records {
+                    csv += &format!("{address},{private_key},{}\n", nickname.unwrap_or_default());
+                }
+                if let Some(output) = output {
+                    std::fs::write(&output, csv)?;
+                    eprintln!("write {}", output.display());
+                } else {
+                    print!("{csv}");
+                }
+            }
+            DevCommand::ReplayBlock { block_number } => {
+                if block_number == 0 {
+                    anyhow::bail!("the genesis block has no prior header");
+                }
+
+                let (blocks, _) = service
+                    .get_blocks(Some(block_number - 1), Some(block_number))
+                    .await?;
+                let block = blocks
+                    .into_iter()
+                    .find(|block| block.header.block_number == block_number)
+                    .ok_or_else(|| anyhow::anyhow!("block {block_number} was not found"))?;
+                let block_details = service.get_block_details(block_number).await?;
+
+                let replay = replay_block(&block, &block_details);
+                println!("{replay}");
+                if replay.first_divergence().is_some() {
+                    anyhow::bail!("the witnesses of block {block_number} do not match its header");
+                }
+            }
+            DevCommand::GenWalletFixtures { .. } => {}
+        },
+        #[cfg(not(feature = "interoperability"))]
+        SubCommand::InteroperabilityNotCompiled { .. } => {}
+        #[cfg(not(feature = "bridge"))]
+        SubCommand::BridgeNotCompiled { .. } => {}
+        #[cfg(not(feature = "dev"))]
+        SubCommand::DevNotCompiled { .. } => {}
+        SubCommand::Watch {
+            user_address,
+            interval,
+            once,
+        } => {
+            let user_addresses = if user_address.is_some() {
+                vec![parse_address(&wallet, &nickname_table, user_address)?]
+            } else {
+                let mut user_addresses = wallet.data.keys().copied().collect::<Vec<_>>();
+                user_addresses.sort_by_cached_key(|address| address.to_string());
+                user_addresses
+            };
+            if user_addresses.is_empty() {
+                anyhow::bail!("no accounts is in your wallet");
+            }
+            if interval == 0 {
+                anyhow::bail!("--interval must be a positive integer");
+            }
+
+            watch(
+                &service,
+                &mut wallet,
+                &user_addresses,
+                Duration::from_secs(interval),
+                once,
+            )
+            .await?;
+        }
+        SubCommand::Warmup {}
+        | SubCommand::Features { .. }
+        | SubCommand::Airdrop { .. }
+        | SubCommand::Audit { .. }
+        | SubCommand::Explain { .. }
+        | SubCommand::Proof { .. } => {}
+    }
+
+    Ok(())
+}
+
+/// Refuse to send the transfers which were sent from `user_address` within `window` seconds,
+/// e.g., when the same command is run twice by mistake.
+fn ensure_not_duplicate(
+    wallet: &WalletOnMemory,
+    user_address: Address<F>,
+    assets: &[ContributedAsset<F>],
+    now: u64,
+    window: u64,
+) -> anyhow::Result<()> {
+    let user_state = wallet
+        .data
+        .get(&user_address)
+        .expect("user address was not found in wallet");
+    for asset in assets {
+        if let Some(sent) = user_state.recent_sends.find_duplicate(asset, now, window) {
+            let tx_hash = sent
+                .tx_hash
+                .map(|tx_hash| format!(" in {tx_hash}"))
+                .unwrap_or_default();
+            anyhow::bail!(
+                "{} of {} was already sent from {user_address} to {} {} seconds ago{tx_hash}. Give --allow-duplicate to send it again",
+                asset.amount,
+                asset.kind.contract_address,
+                asset.receiver_address,
+                now.saturating_sub(sent.timestamp),
+            );
+        }
+    }
+
+    Ok(())
+}
+
+fn collect_sent_tx_hashes(wallet: &WalletOnMemory) -> HashSet<TxHash<F>> {
+    wallet
+        .data
+        .values()
+        .flat_map(|user_state| user_state.sent_transactions.keys())
+        .copied()
+        .collect()
+}
+
+/// Remember the transfers sent after `sent_tx_hashes` were collected, when the command failed later,
+/// e.g., in signing or waiting for the approval, so that running it again does not pay twice.
+fn record_sent_transfers(
+    wallet: &mut WalletOnMemory,
+    transfers: &[(Address<F>, Vec<ContributedAsset<F>>)],
+    sent_tx_hashes: &HashSet<TxHash<F>>,
+    window: u64,
+) {
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_secs())
+        .unwrap_or_default();
+    for (sender_address, assets) in transfers {
+        if let Some(user_state) = wallet.data.get_mut(sender_address) {
+            let tx_hash = user_state
+                .sent_transactions
+                .iter()
+                .find(|(tx_hash, sent_transaction)| {
+                    !sent_tx_hashes.contains(tx_hash)
+                        && assets.iter().all(|asset| {
+                            sent_transaction.outputs.contains(&(
+                                asset.receiver_address,
+                                asset.kind,
+                                asset.amount,
+                            ))
+                        })
+                })
+                .map(|(tx_hash, _)| *tx_hash);
+            if tx_hash.is_some() {
+                user_state.recent_sends.record(assets, tx_hash, now, window);
+            }
+        }
+    }
+
+    if let Err(err) = wallet.backup() {
+        eprintln!("WARNING: fail to record the sent transfers: {err:#}");
+    }
+}
+
+/// Send the transfers of `tx send --from-all` and `tx batch-send`, sharing one proposal block.
+async fn send_from_accounts(
+    service: &ServiceBuilder,
+    wallet: &mut WalletOnMemory,
+    transfers: &[(Address<F>, Vec<ContributedAsset<F>>)],
+    allow_unregistered: bool,
+    wait: bool,
+    hooks: &HooksConfig,
+) -> anyhow::Result<()> {
+    let mut recipients = transfers
+        .iter()
+        .flat_map(|(_, assets)| assets.iter().map(|asset| asset.receiver_address))
+        .collect::<Vec<_>>();
+    recipients.sort_by_key(|recipient| recipient.to_string());
+    recipients.dedup();
+    let recipient_statuses = check_recipients_registration(service, &recipients).await;
+    for (recipient, status) in recipient_statuses.iter() {
+        println!("Recipient: {recipient} ({status})");
+    }
+    ensure_recipients_registered(&recipient_statuses, allow_unregistered)?;
+
+    for (sender_address, assets) in transfers {
+        service.debug_dump("output_assets", assets);
+        progress!("sending {} transfers from {sender_address}", assets.len());
+    }
+
+    // The transfers exceeding the limits of a transaction are sent in later blocks.
+    let intents = transfers
+        .iter()
+        .flat_map(|(sender_address, assets)| {
+            assets.iter().map(|output| TransferIntent {
+                sender_address: *sender_address,
+                output: output.clone(),
+            })
+        })
+        .collect::<Vec<_>>();
+    let rounds = into_rounds(compose_transfers(intents, &TransferLimits::transfer())?);
+
+    ctrlc::set_handler(|| {}).expect("Error setting Ctrl-C handler");
+
+    for round in rounds {
+        let round = round
+            .into_iter()
+            .map(|planned_tx| (planned_tx.sender_address, planned_tx.outputs))
+            .collect::<Vec<_>>();
+        let sent_transactions = transfer_from_accounts(service, wallet, &round, wait).await?;
+        for (sender_address, tx_hash) in sent_transactions {
+            println!("{sender_address}: {tx_hash}");
+            hooks.run_post_send(tx_hash.to_string())?;
+        }
+    }
+
+    Ok(())
+}
+
+/// The addresses in the wallet and the nickname table are shown distinguishably,
+/// as well as the given ones.
+fn known_address_renderer(
+    terminal: &TerminalOptions,
+    wallet: &WalletOnMemory,
+    nickname_table: &NicknameTable,
+    addresses: &[String],
+) -> AddressRenderer {
+    let known_addresses = wallet
+        .data
+        .keys()
+        .chain(nickname_table.address_to_nickname.keys())
+        .map(|address| address.to_string())
+        .chain(addresses.iter().cloned());
+
+    AddressRenderer::new(terminal, known_addresses)
+}
+
+#[cfg(feature = "interoperability")]
+fn invoke_signer_command(
+    signer_command: &SignerCommand,
+    signers_dir: &Path,
+    terminal: &TerminalOptions,
+) -> anyhow::Result<()> {
+    match signer_command {
+        SignerCommand::Add { name, from_env } => {
+            let signing_key = if *from_env {
+                parse_private_key_env(&read_private_key_env()?)?
+            } else {
+                read_signing_key_from_stdin()?
+            };
+            let password = read_signer_password(terminal, true)?;
+            let address = add_signer(signers_dir, name, &signing_key, &password)?;
+            println!("add signer {name}: {address:?}");
+        }
+        SignerCommand::List {} => {
+            let table = SignerTable::read_from_dir(signers_dir)?;
+            if table.signers.is_empty() {
+                println!("no signers. Add one with `intmax io signer add <name>`");
+            }
+            for (name, address) in table.signers {
+                println!("{name:20} {address:?}");
+            }
+        }
+        SignerCommand::Remove { name } => {
+            let address = remove_signer(signers_dir, name)?;
+            println!("remove signer {name}: {address:?}");
+        }
+    }
+
+    Ok(())
+}
+
+fn invoke_audit_command(
+    audit_command: &AuditCommand,
+    audit_log_path: &Path,
+    json: bool,
+) -> anyhow::Result<()> {
+    let entries = read_audit_log(audit_log_path)?;
+    match audit_command {
+        AuditCommand::Show { limit } => {
+            let skipped = limit.map_or(0, |limit| entries.len().saturating_sub(limit));
+            let entries = &entries[skipped..];
+            if json {
+                println!("{}", serde_json::to_string_pretty(entries)?);
+            } else if entries.is_empty() {
+                println!("No operations were recorded");
+            } else {
+                let separator = "--------------------------------------------------------------------------------------";
+                println!("{}", separator);
+                for entry in entries {
+                    println!("  Index         | {}", entry.index);
+                    println!("  Timestamp     | {}", entry.timestamp);
+                    println!("  Command       | {}", entry.command);
+                    println!("  Arguments     | {}", entry.arguments);
+                    if let Some(aggregator_url) = &entry.aggregator_url {
+                        println!("  Aggregator    | {}", aggregator_url);
+                    }
+                    for tx_hash in &entry.tx_hashes {
+                        println!("  Tx Hash       | {}", tx_hash);
+                    }
+                    if let Some(error) = &entry.error {
+                        println!("  Result        | failed: {}", error);
+                    } else {
+                        println!("  Result        | succeeded");
+                    }
+                    println!("{}", separator);
                 }
             }
         }
+        AuditCommand::Verify {} => {
+            verify_audit_log(&entries)?;
+            if let Some(last_entry) = entries.last() {
+                println!(
+                    "{} entries are verified. The last hash is {}",
+                    entries.len(),
+                    last_entry.hash
+                );
+                println!("Keep the last hash elsewhere to detect removal of the latest entries.");
+            } else {
+                println!("No operations were recorded");
+            }
+        }
     }
 
     Ok(())
@@ -0,0 +1,119 @@
+//! `intmax watch`, which signs the proposed blocks including your pending transactions as soon as they are proposed.
+//! A transaction whose proposal is left unsigned is reverted, which is easy to miss when `block sign` is run by hand.
+
+use std::time::Duration;
+
+use intmax_rollup_interface::intmax_zkp_core::{
+    plonky2::plonk::config::{GenericConfig, PoseidonGoldilocksConfig},
+    zkdsa::account::Address,
+};
+
+use crate::{
+    progress,
+    service::builder::ServiceBuilder,
+    utils::key_management::{
+        memory::WalletOnMemory,
+        types::{TransactionStatus, TxHash},
+    },
+};
+
+const D: usize = 2;
+type C = PoseidonGoldilocksConfig;
+type F = <C as GenericConfig<D>>::F;
+
+/// Sign the proposed blocks including the unsigned transactions of the accounts.
+/// A transaction which has not been proposed yet is left for the next poll.
+/// Returns the signed transactions.
+pub async fn sign_proposed_transactions(
+    service: &ServiceBuilder,
+    wallet: &mut WalletOnMemory,
+    user_addresses: &[Address<F>],
+) -> anyhow::Result<Vec<(Address<F>, TxHash<F>)>> {
+    let mut signed_transactions = vec![];
+    for user_address in user_addresses {
+        let user_state = wallet
+            .data
+            .get_mut(user_address)
+            .expect("user address was not found in wallet");
+
+        // The transactions which were approved, reverted or expired meanwhile are not signed.
+        service
+            .sync_sent_transaction(user_state, *user_address)
+            .await;
+
+        let mut unsigned_tx_hashes = user_state
+            .sent_transactions
+            .iter()
+            .filter(|(_, sent_transaction)| {
+                sent_transaction.status == TransactionStatus::Pending
+                    && sent_transaction.proposed_block_number.is_none()
+            })
+            .map(|(tx_hash, _)| *tx_hash)
+            .collect::<Vec<_>>();
+        unsigned_tx_hashes.sort_by_cached_key(|tx_hash| tx_hash.to_string());
+
+        // The signing message is found only after the transaction is proposed.
+        let mut proposed_tx_hashes = vec![];
+        for tx_hash in unsigned_tx_hashes {
+            if service
+                .get_signing_message(*user_address, tx_hash)
+                .await
+                .is_ok()
+            {
+                proposed_tx_hashes.push(tx_hash);
+            }
+        }
+        if proposed_tx_hashes.is_empty() {
+            continue;
+        }
+
+        service
+            .sign_proposed_block(user_state, *user_address, Some(&proposed_tx_hashes), None)
+            .await?;
+        signed_transactions.extend(
+            proposed_tx_hashes
+                .into_iter()
+                .map(|tx_hash| (*user_address, tx_hash)),
+        );
+    }
+
+    wallet.backup()?;
+
+    Ok(signed_transactions)
+}
+
+/// Poll the proposed blocks every `interval` and sign the transactions of the accounts until interrupted.
+/// An error while polling is warned and retried at the next poll, unless `once` is true.
+pub async fn watch(
+    service: &ServiceBuilder,
+    wallet: &mut WalletOnMemory,
+    user_addresses: &[Address<F>],
+    interval: Duration,
+    once: bool,
+) -> anyhow::Result<()> {
+    if !once {
+        progress!(
+            "watching the proposed blocks for {} accounts every {} seconds. Press Ctrl+C to stop.",
+            user_addresses.len(),
+            interval.as_secs()
+        );
+    }
+
+    loop {
+        match sign_proposed_transactions(service, wallet, user_addresses).await {
+            Ok(signed_transactions) => {
+                for (user_address, tx_hash) in signed_transactions {
+                    println!("signed transaction {tx_hash} of {user_address}");
+                }
+            }
+            Err(err) if !once => eprintln!("WARNING: fail to sign the proposed blocks: {err:#}"),
+            Err(err) => return Err(err),
+        }
+
+        if once {
+            return Ok(());
+        }
+
+        tokio::time::sleep(interval).await;
+    }
+}
@@ -1,4 +1,6 @@
+pub mod cache;
 pub mod controller;
+pub mod prover;
 pub mod service;
 pub mod utils;
 
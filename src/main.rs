@@ -1,12 +1,28 @@
 use dotenv::dotenv;
-use intmax::controller::Command;
+use intmax::{
+    controller::Command,
+    service::{error::NothingToDo, error_code::classify_error},
+};
 use structopt::StructOpt;
 
 #[tokio::main]
-async fn main() -> Result<(), Box<dyn std::error::Error>> {
+async fn main() {
     let _ = dotenv().ok();
 
-    Command::from_args().invoke().await?;
+    if let Err(error) = Command::from_args().invoke().await {
+        // Scripts can tell "nothing to do" from a failure by the exit code.
+        if error.is::<NothingToDo>() {
+            eprintln!("{error}");
+            std::process::exit(NothingToDo::EXIT_CODE);
+        }
 
-    Ok(())
+        eprintln!("Error: {error:?}");
+        if let Some(error_code) = classify_error(&error) {
+            eprintln!(
+                "\nError code: {}. Run `intmax explain {}` for the causes and how to recover.",
+                error_code.code, error_code.code
+            );
+        }
+        std::process::exit(1);
+    }
 }
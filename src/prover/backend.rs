@@ -0,0 +1,258 @@
+//! The backend which generates the proofs of user transactions, signatures and blocks.
+//! `CpuProver` proves on this machine with the cached circuits, and is used unless another backend is registered.
+//! With the `custom-prover` feature, an accelerated prover can be registered by `register_prover`,
+//! and with `remote-prover`, the user transaction proofs can be generated by a prover service given by `prover.url`.
+//!
+//! A user transaction proof is verified on this machine, and its public inputs are compared with the ones
+//! computed from the witness, so a backend cannot make this CLI send an invalid proof or a proof of another transaction.
+//! Signatures are always proved locally, since they need the private key.
+
+use std::sync::{Arc, Mutex};
+
+use anyhow::Context;
+
+use intmax_rollup_interface::intmax_zkp_core::{
+    plonky2::{
+        hash::hash_types::HashOut,
+        iop::witness::PartialWitness,
+        plonk::{
+            config::{GenericConfig, PoseidonGoldilocksConfig},
+            proof::ProofWithPublicInputs,
+        },
+    },
+    rollup::circuits::BlockDetail,
+    sparse_merkle_tree::{
+        gadgets::process::process_smt::SmtProcessProof, goldilocks_poseidon::WrappedHashOut,
+    },
+    transaction::{
+        circuits::MergeAndPurgeTransitionProofWithPublicInputs, gadgets::merge::MergeProof,
+    },
+    zkdsa::{account::Address, circuits::SimpleSignatureProofWithPublicInputs},
+};
+use serde::Serialize;
+
+use super::circuits::{block_circuit, simple_signature_circuit, user_tx_circuit};
+#[cfg(feature = "remote-prover")]
+use crate::utils::aggregator_url::is_insecure_url;
+
+const D: usize = 2;
+type C = PoseidonGoldilocksConfig;
+type F = <C as GenericConfig<D>>::F;
+
+pub type UserTxProof = MergeAndPurgeTransitionProofWithPublicInputs<F, C, D>;
+pub type SignatureProof = SimpleSignatureProofWithPublicInputs<F, C, D>;
+pub type BlockProof = ProofWithPublicInputs<F, C, D>;
+
+type PurgeWitness = (SmtProcessProof<F>, SmtProcessProof<F>, SmtProcessProof<F>);
+
+/// The inputs of a user transaction proof. It has no private key.
+#[derive(Clone, Debug, Serialize)]
+pub struct UserTxWitness<'a> {
+    pub user_address: Address<F>,
+    pub merge_witnesses: &'a [MergeProof<F>],
+    pub purge_input_witnesses: &'a [PurgeWitness],
+    pub purge_output_witnesses: &'a [PurgeWitness],
+    pub nonce: WrappedHashOut<F>,
+    pub old_user_asset_root: WrappedHashOut<F>,
+}
+
+pub trait Prover: Send + Sync {
+    /// the name displayed when proving starts
+    fn name(&self) -> &str;
+
+    fn prove_user_tx(&self, witness: &UserTxWitness<'_>) -> anyhow::Result<UserTxProof>;
+
+    fn prove_signature(
+        &self,
+        private_key: HashOut<F>,
+        message: HashOut<F>,
+    ) -> anyhow::Result<SignatureProof>;
+
+    fn prove_block(
+        &self,
+        block_detail: &BlockDetail<F>,
+        default_user_tx_proof: &UserTxProof,
+        default_simple_signature_proof: &SignatureProof,
+    ) -> anyhow::Result<BlockProof>;
+}
+
+/// Proves with the circuits built on this machine, using the thread pool of `ProverConfig`.
+#[derive(Copy, Clone, Debug, Default)]
+pub struct CpuProver;
+
+impl Prover for CpuProver {
+    fn name(&self) -> &str {
+        "cpu"
+    }
+
+    fn prove_user_tx(&self, witness: &UserTxWitness<'_>) -> anyhow::Result<UserTxProof> {
+        let merge_and_purge_circuit = user_tx_circuit();
+        let mut pw = PartialWitness::new();
+        let _public_inputs = merge_and_purge_circuit.targets.set_witness(
+            &mut pw,
+            witness.user_address,
+            witness.merge_witnesses,
+            witness.purge_input_witnesses,
+            witness.purge_output_witnesses,
+            witness.nonce,
+            witness.old_user_asset_root,
+        );
+
+        merge_and_purge_circuit.prove(pw)
+    }
+
+    fn prove_signature(
+        &self,
+        private_key: HashOut<F>,
+        message: HashOut<F>,
+    ) -> anyhow::Result<SignatureProof> {
+        let simple_signature_circuit = simple_signature_circuit();
+        let mut pw = PartialWitness::new();
+        simple_signature_circuit
+            .targets
+            .set_witness(&mut pw, private_key, message);
+
+        simple_signature_circuit.prove(pw)
+    }
+
+    fn prove_block(
+        &self,
+        block_detail: &BlockDetail<F>,
+        default_user_tx_proof: &UserTxProof,
+        default_simple_signature_proof: &SignatureProof,
+    ) -> anyhow::Result<BlockProof> {
+        block_circuit().set_witness_and_prove(
+            block_detail,
+            default_user_tx_proof,
+            default_simple_signature_proof,
+        )
+    }
+}
+
+/// Check that the proof is valid and proves the transition given by the witness,
+/// since a valid proof of another transaction could be returned by a backend.
+pub fn verify_user_tx_proof(
+    witness: &UserTxWitness<'_>,
+    proof: &UserTxProof,
+) -> anyhow::Result<()> {
+    let merge_and_purge_circuit = user_tx_circuit();
+    let public_inputs = merge_and_purge_circuit.targets.set_witness(
+        &mut PartialWitness::new(),
+        witness.user_address,
+        witness.merge_witnesses,
+        witness.purge_input_witnesses,
+        witness.purge_output_witnesses,
+        witness.nonce,
+        witness.old_user_asset_root,
+    );
+    if proof.public_inputs != public_inputs {
+        anyhow::bail!("the user transaction proof is not for this transaction");
+    }
+
+    merge_and_purge_circuit
+        .verify(proof.clone())
+        .context("the user transaction proof is invalid")
+}
+
+static PROVER: Mutex<Option<Arc<dyn Prover>>> = Mutex::new(None);
+
+/// Use `prover` for the following proofs instead of `CpuProver`.
+#[cfg(feature = "custom-prover")]
+pub fn register_prover(prover: Arc<dyn Prover>) {
+    *PROVER.lock().unwrap() = Some(prover);
+}
+
+/// Returns the registered prover, or `CpuProver` if none is registered.
+pub fn prover() -> Arc<dyn Prover> {
+    PROVER
+        .lock()
+        .unwrap()
+        .clone()
+        .unwrap_or_else(|| Arc::new(CpuProver))
+}
+
+/// Sends the witness of each user transaction to a prover service, which responds the proof as JSON.
+/// The witness includes the assets of the account, so use a prover service you trust with them.
+/// Signatures and blocks are proved on this machine.
+#[cfg(feature = "remote-prover")]
+#[derive(Clone, Debug)]
+pub struct RemoteProver {
+    url: String,
+    client: reqwest::blocking::Client,
+}
+
+/// A proof takes minutes on a slow prover service, but one which does not respond is not waited for forever.
+#[cfg(feature = "remote-prover")]
+const REMOTE_PROVER_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(600);
+
+#[cfg(feature = "remote-prover")]
+impl RemoteProver {
+    /// Plain HTTP is refused unless the host is local, since the witness includes the assets of the account.
+    pub fn new(url: &str) -> anyhow::Result<Self> {
+        let parsed_url = reqwest::Url::parse(url)
+            .map_err(|err| anyhow::anyhow!("invalid prover URL `{url}`: {err}"))?;
+        if parsed_url.scheme() != "http" && parsed_url.scheme() != "https" {
+            anyhow::bail!("prover URL must start with http:// or https://");
+        }
+        if is_insecure_url(&parsed_url) {
+            anyhow::bail!("prover URL `{url}` is not encrypted. Use https://");
+        }
+
+        Ok(Self {
+            url: url.trim_end_matches('/').to_string(),
+            client: reqwest::blocking::Client::builder()
+                .timeout(REMOTE_PROVER_TIMEOUT)
+                .build()?,
+        })
+    }
+}
+
+#[cfg(feature = "remote-prover")]
+impl Prover for RemoteProver {
+    fn name(&self) -> &str {
+        &self.url
+    }
+
+    fn prove_user_tx(&self, witness: &UserTxWitness<'_>) -> anyhow::Result<UserTxProof> {
+        let api_url = format!("{}/prove/user-tx", self.url);
+        // A blocking client must not run on a thread of the async runtime.
+        std::thread::scope(|scope| {
+            scope
+                .spawn(|| {
+                    let resp = self.client.post(&api_url).json(witness).send()?;
+                    if !resp.status().is_success() {
+                        anyhow::bail!(
+                            "prover service responded {}: {}",
+                            resp.status(),
+                            resp.text().unwrap_or_default()
+                        );
+                    }
+
+                    Ok(resp.json::<UserTxProof>()?)
+                })
+                .join()
+                .map_err(|_| anyhow::anyhow!("fail to request {api_url}"))?
+        })
+    }
+
+    fn prove_signature(
+        &self,
+        private_key: HashOut<F>,
+        message: HashOut<F>,
+    ) -> anyhow::Result<SignatureProof> {
+        CpuProver.prove_signature(private_key, message)
+    }
+
+    fn prove_block(
+        &self,
+        block_detail: &BlockDetail<F>,
+        default_user_tx_proof: &UserTxProof,
+        default_simple_signature_proof: &SignatureProof,
+    ) -> anyhow::Result<BlockProof> {
+        CpuProver.prove_block(
+            block_detail,
+            default_user_tx_proof,
+            default_simple_signature_proof,
+        )
+    }
+}
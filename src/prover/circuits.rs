@@ -0,0 +1,86 @@
+//! The circuits are built on first use and shared by the later proofs in the same process,
+//! since building one takes seconds while it never changes for the same rollup constants.
+//! A circuit is built only once even if several threads ask for it at the same time.
+//! The thread pool for proving is configured before the first circuit is built.
+//!
+//! The circuits are not saved to disk. The plonky2 version used by the rollup cannot serialize
+//! the prover data of a circuit (its gates and witness generators), so a circuit read back from disk could not prove.
+//! Persisting them needs a plonky2 upgrade in the rollup first.
+
+use std::sync::{Arc, Mutex};
+
+use intmax_rollup_interface::{
+    constants::ROLLUP_CONSTANTS,
+    intmax_zkp_core::{
+        plonky2::plonk::{
+            circuit_data::CircuitConfig,
+            config::{GenericConfig, PoseidonGoldilocksConfig},
+        },
+        rollup::circuits::{make_block_proof_circuit, BlockProofCircuit},
+        transaction::circuits::{make_user_proof_circuit, MergeAndPurgeTransitionCircuit},
+        zkdsa::circuits::{make_simple_signature_circuit, SimpleSignatureCircuit},
+    },
+};
+
+use crate::utils::prover::apply_pending_prover_config;
+
+const D: usize = 2;
+type C = PoseidonGoldilocksConfig;
+type F = <C as GenericConfig<D>>::F;
+
+pub type UserTxCircuit = MergeAndPurgeTransitionCircuit<F, C, D>;
+pub type SignatureCircuit = SimpleSignatureCircuit<F, C, D>;
+pub type BlockCircuit = BlockProofCircuit<F, C, D>;
+
+static USER_TX_CIRCUIT: Mutex<Option<Arc<UserTxCircuit>>> = Mutex::new(None);
+static SIMPLE_SIGNATURE_CIRCUIT: Mutex<Option<Arc<SignatureCircuit>>> = Mutex::new(None);
+static BLOCK_CIRCUIT: Mutex<Option<Arc<BlockCircuit>>> = Mutex::new(None);
+
+/// The lock is held while building, so that the other threads wait for the circuit instead of building it again.
+fn get_or_build<T>(cache: &Mutex<Option<Arc<T>>>, build: impl FnOnce() -> T) -> Arc<T> {
+    let mut cache = cache.lock().unwrap();
+    if let Some(circuit) = cache.as_ref() {
+        return circuit.clone();
+    }
+
+    apply_pending_prover_config();
+    let circuit = Arc::new(build());
+    *cache = Some(circuit.clone());
+
+    circuit
+}
+
+pub fn user_tx_circuit() -> Arc<UserTxCircuit> {
+    get_or_build(&USER_TX_CIRCUIT, || {
+        make_user_proof_circuit::<F, C, D>(
+            CircuitConfig::standard_recursion_config(),
+            ROLLUP_CONSTANTS,
+        )
+    })
+}
+
+pub fn simple_signature_circuit() -> Arc<SignatureCircuit> {
+    get_or_build(&SIMPLE_SIGNATURE_CIRCUIT, || {
+        make_simple_signature_circuit(CircuitConfig::standard_recursion_config())
+    })
+}
+
+/// The block circuit recursively verifies the user transaction and simple signature proofs,
+/// so those two circuits are built first, in parallel.
+pub fn block_circuit() -> Arc<BlockCircuit> {
+    if let Some(circuit) = BLOCK_CIRCUIT.lock().unwrap().as_ref() {
+        return circuit.clone();
+    }
+
+    apply_pending_prover_config();
+    let (user_tx_circuit, simple_signature_circuit) =
+        rayon::join(user_tx_circuit, simple_signature_circuit);
+    get_or_build(&BLOCK_CIRCUIT, || {
+        make_block_proof_circuit::<F, C, D>(
+            CircuitConfig::standard_recursion_config(),
+            ROLLUP_CONSTANTS,
+            &user_tx_circuit,
+            &simple_signature_circuit,
+        )
+    })
+}
@@ -0,0 +1,4 @@
+//! Proving with the circuits of the rollup. The thread pool used for proving is configured by `utils::prover`.
+
+pub mod backend;
+pub mod circuits;
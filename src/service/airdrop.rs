@@ -1,23 +1,230 @@
 use std::{
+    collections::BTreeMap,
     io::{BufRead, BufReader},
+    path::Path,
     str::FromStr,
 };
 
+use anyhow::Context;
 use intmax_rollup_interface::intmax_zkp_core::{
     plonky2::plonk::config::{GenericConfig, PoseidonGoldilocksConfig},
     rollup::gadgets::deposit_block::VariableIndex,
     transaction::asset::{ContributedAsset, TokenKind},
     zkdsa::account::Address,
 };
+use num_bigint::BigUint;
+use serde::{Deserialize, Serialize};
+
+use crate::utils::{
+    hex_string::parse_intmax_address,
+    key_management::memory::WalletOnMemory,
+    nickname::{NicknameTable, ReservedNicknameTable},
+};
 
 const CSV_EXAMPLE_LINK: &str =
     "https://github.com/InternetMaximalism/intmax-rollup-cli/blob/main/tests/airdrop/README.md";
 const CSV_DELIMITER: &str = r"\s*,\s*"; // コンマ区切り
 
+/// The header of a distribution CSV file. The columns are identified by their position, not by this header.
+pub const CSV_HEADER: &str = "Token Address, Recipient, Fungibility, Token ID (NFT), Amount (FT)";
+
+/// The recipient written in the template, which should be replaced before sending.
+const EXAMPLE_RECIPIENT: &str =
+    "0x714bdc6f38947e6da5ee9596c50b2e06e4e01c8885f98cf29d9c2f656eb3b45d";
+
+/// The names of the fields of a JSON or YAML distribution file, in the order of the CSV columns.
+const FIELD_NAMES: [&str; 5] = [
+    "token_address",
+    "recipient",
+    "fungibility",
+    "token_id",
+    "amount",
+];
+
 const D: usize = 2;
 type C = PoseidonGoldilocksConfig;
 type F = <C as GenericConfig<D>>::F;
 
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "UPPERCASE")]
+pub enum Fungibility {
+    #[default]
+    Ft,
+    Nft,
+}
+
+/// An entry of a JSON or YAML distribution file. The fields are named, so they cannot be misordered.
+/// The omitted fields have the same defaults as the empty columns of a CSV file.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct DistributionEntry {
+    /// [default: your user address]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub token_address: Option<String>,
+    /// [default: your user address]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub recipient: Option<String>,
+    #[serde(default)]
+    pub fungibility: Fungibility,
+    /// It is required for NFTs. [default: 0x00]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub token_id: Option<String>,
+    /// It is required for fungible tokens. [default: 1]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub amount: Option<u64>,
+}
+
+impl DistributionEntry {
+    /// `location` returns where the field of the given index is written in the file, e.g., `row: 1, column 0`.
+    fn to_contributed_asset(
+        &self,
+        user_address: Address<F>,
+        location: impl Fn(usize) -> String,
+    ) -> anyhow::Result<ContributedAsset<F>> {
+        let contract_address = match &self.token_address {
+            Some(token_address) => parse_intmax_address("token address", token_address).map_err(|_| {
+                anyhow::anyhow!(
+                    "Given file included invalid token address ({}). See {CSV_EXAMPLE_LINK} for more information.",
+                    location(0)
+                )
+            })?,
+            None => user_address,
+        };
+        let receiver_address = match &self.recipient {
+            Some(recipient) => parse_intmax_address("recipient", recipient).map_err(|_| {
+                anyhow::anyhow!(
+                    "Given file included invalid recipient ({}). See {CSV_EXAMPLE_LINK} for more information.",
+                    location(1)
+                )
+            })?,
+            None => user_address,
+        };
+        let fungible = self.fungibility == Fungibility::Ft;
+        let variable_index = match &self.token_id {
+            Some(token_id) => VariableIndex::from_str(token_id).map_err(|_| {
+                anyhow::anyhow!(
+                    "Given file included invalid token ID ({}). See {CSV_EXAMPLE_LINK} for more information.",
+                    location(3)
+                )
+            })?,
+            None if fungible => 0u8.into(),
+            None => anyhow::bail!(
+                "NFT ID cannot be omitted ({}). See {CSV_EXAMPLE_LINK} for more information.",
+                location(3)
+            ),
+        };
+        let amount = match self.amount {
+            Some(amount) => amount,
+            None if fungible => anyhow::bail!(
+                "Fungible token amount cannot be omitted ({}). See {CSV_EXAMPLE_LINK} for more information.",
+                location(4)
+            ),
+            None => 1,
+        };
+
+        Ok(ContributedAsset {
+            kind: TokenKind {
+                contract_address,
+                variable_index,
+            },
+            receiver_address,
+            amount,
+        })
+    }
+}
+
+/// Read a distribution file. The format is selected by the extension:
+/// `.json`, `.yaml` or `.yml` for a list of `DistributionEntry`, and CSV otherwise.
+pub fn read_distribution_file(
+    user_address: Address<F>,
+    file_path: &Path,
+) -> anyhow::Result<Vec<ContributedAsset<F>>> {
+    let extension = file_path
+        .extension()
+        .and_then(|extension| extension.to_str())
+        .unwrap_or_default()
+        .to_lowercase();
+    let file = std::fs::File::open(file_path)
+        .with_context(|| format!("fail to open {}", file_path.display()))?;
+    let invalid_file = || {
+        format!(
+            "invalid distribution file: {}. See {CSV_EXAMPLE_LINK} for more information.",
+            file_path.display()
+        )
+    };
+    let entries: Vec<DistributionEntry> = match extension.as_str() {
+        "json" => serde_json::from_reader(BufReader::new(file)).with_context(invalid_file)?,
+        "yaml" | "yml" => {
+            serde_yaml::from_reader(BufReader::new(file)).with_context(invalid_file)?
+        }
+        _ => return read_distribution_from_csv(user_address, file),
+    };
+
+    entries
+        .iter()
+        .enumerate()
+        .map(|(i, entry)| {
+            entry.to_contributed_asset(user_address, |field| {
+                format!("entry: {i}, field: {}", FIELD_NAMES[field])
+            })
+        })
+        .collect()
+}
+
+/// Returns a sample distribution file in the format selected by the extension of `file_path` as `read_distribution_file`.
+/// The sample sends a fungible token and an NFT issued by yourself to `EXAMPLE_RECIPIENT`.
+pub fn distribution_template(file_path: &Path) -> anyhow::Result<String> {
+    let entries = [
+        DistributionEntry {
+            token_address: None,
+            recipient: Some(EXAMPLE_RECIPIENT.to_string()),
+            fungibility: Fungibility::Ft,
+            token_id: None,
+            amount: Some(100),
+        },
+        DistributionEntry {
+            token_address: None,
+            recipient: Some(EXAMPLE_RECIPIENT.to_string()),
+            fungibility: Fungibility::Nft,
+            token_id: Some("0x01".to_string()),
+            amount: None,
+        },
+    ];
+    let extension = file_path
+        .extension()
+        .and_then(|extension| extension.to_str())
+        .unwrap_or_default()
+        .to_lowercase();
+    let template = match extension.as_str() {
+        "json" => serde_json::to_string_pretty(&entries)? + "\n",
+        "yaml" | "yml" => serde_yaml::to_string(&entries)?,
+        _ => {
+            let mut template = format!("{CSV_HEADER}\n");
+            for entry in entries.iter() {
+                template += &format!(
+                    "{}, {}, {}, {}, {}\n",
+                    entry.token_address.as_deref().unwrap_or_default(),
+                    entry.recipient.as_deref().unwrap_or_default(),
+                    if entry.fungibility == Fungibility::Ft {
+                        "FT"
+                    } else {
+                        "NFT"
+                    },
+                    entry.token_id.as_deref().unwrap_or_default(),
+                    entry
+                        .amount
+                        .map(|amount| amount.to_string())
+                        .unwrap_or_default()
+                );
+            }
+
+            template
+        }
+    };
+
+    Ok(template)
+}
+
 pub fn read_distribution_from_csv(
     user_address: Address<F>,
     file: std::fs::File,
@@ -38,70 +245,157 @@ pub fn read_distribution_from_csv(
             );
         }
 
-        let contract_address = if data[0].is_empty() {
-            user_address
-        } else {
-            Address::from_str(data[0]).map_err(|_| {
-                anyhow::anyhow!(
-                    "Given file included invalid token address (row: {i}, column 0). See {CSV_EXAMPLE_LINK} for more information."
-                )
-            })?
-        };
-        let receiver_address = if data[1].is_empty() {
-            user_address
-        } else {
-            Address::from_str(data[1]).map_err(|_| {
-                anyhow::anyhow!(
-                    "Given file included invalid recipient (row: {i}, column 1). See {CSV_EXAMPLE_LINK} for more information."
-                )
-            })?
-        };
-        let fungible = if data[2].is_empty() || data[2] == "FT" {
-            true
+        let fungibility = if data[2].is_empty() || data[2] == "FT" {
+            Fungibility::Ft
         } else if data[2] == "NFT" {
-            false
+            Fungibility::Nft
         } else {
             anyhow::bail!("Given file included invalid fungibility (row: {i}, column 2). See {CSV_EXAMPLE_LINK} for more information.");
         };
-        let variable_index = if data[3].is_empty() {
-            if fungible {
-                0u8.into()
-            } else {
-                anyhow::bail!(
-                    "NFT ID cannot be omitted (row: {i}, column 3). See {CSV_EXAMPLE_LINK} for more information."
-                );
-            }
-        } else {
-            VariableIndex::from_str(data[3]).map_err(|_| {
-                anyhow::anyhow!(
-                    "Given file included invalid token ID (row: {i}, column 3). See {CSV_EXAMPLE_LINK} for more information."
-                )
-            })?
-        };
         let amount = if data[4].is_empty() {
-            if fungible {
-                anyhow::bail!(
-                    "Fungible token amount cannot be omitted (row: {i}, column 4). See {CSV_EXAMPLE_LINK} for more information."
-                );
-            } else {
-                1
-            }
+            None
         } else {
-            u64::from_str(data[4]).map_err(|_| {
+            Some(u64::from_str(data[4]).map_err(|_| {
                 anyhow::anyhow!(
                     "Given file included invalid amount (row: {i}, column 4). See {CSV_EXAMPLE_LINK} for more information."
                 )
-            })?
+            })?)
         };
-        distribution.push(ContributedAsset {
-            kind: TokenKind {
-                contract_address,
-                variable_index,
-            },
-            receiver_address,
+        let non_empty = |value: &str| (!value.is_empty()).then(|| value.to_string());
+        let entry = DistributionEntry {
+            token_address: non_empty(data[0]),
+            recipient: non_empty(data[1]),
+            fungibility,
+            token_id: non_empty(data[3]),
             amount,
-        });
+        };
+        distribution.push(
+            entry.to_contributed_asset(user_address, |column| {
+                format!("row: {i}, column {column}")
+            })?,
+        );
     }
 
     Ok(distribution)
 }
+
+/// Check that every token address in the distribution is known to this wallet,
+/// i.e. it is the sender's address, one of the accounts in the wallet or a registered nickname.
+/// Unknown token addresses are only reported unless `strict` is set.
+pub fn validate_distribution_token_addresses(
+    user_address: Address<F>,
+    distribution: &[ContributedAsset<F>],
+    wallet: &WalletOnMemory,
+    nickname_table: &NicknameTable,
+    strict: bool,
+) -> anyhow::Result<()> {
+    let reserved_nickname_table = ReservedNicknameTable::new();
+    let mut unknown_token_addresses = distribution
+        .iter()
+        .map(|asset| asset.kind.contract_address)
+        .filter(|contract_address| {
+            *contract_address != user_address
+                && !wallet.contains_account(contract_address)
+                && !nickname_table
+                    .address_to_nickname
+                    .contains_key(contract_address)
+                && !reserved_nickname_table
+                    .address_to_nickname
+                    .contains_key(contract_address)
+        })
+        .map(|contract_address| contract_address.to_string())
+        .collect::<Vec<_>>();
+    unknown_token_addresses.sort();
+    unknown_token_addresses.dedup();
+
+    if unknown_token_addresses.is_empty() {
+        return Ok(());
+    }
+
+    for contract_address in unknown_token_addresses.iter() {
+        eprintln!(
+            "WARNING: token address {contract_address} is neither your address nor a registered account or nickname"
+        );
+    }
+
+    if strict {
+        anyhow::bail!(
+            "Given file included unknown token addresses. Register them with `account nickname set` or remove --strict flag."
+        );
+    }
+
+    Ok(())
+}
+
+/// Sum the amount to be sent for each token kind.
+/// The keys are `(contract_address, variable_index)` as in `Assets::calc_total_amount`.
+pub fn calc_distribution_total_amount(
+    distribution: &[ContributedAsset<F>],
+) -> BTreeMap<(String, String), BigUint> {
+    let mut total_amount_map = BTreeMap::new();
+    for asset in distribution.iter() {
+        let encoded_token_kind = (
+            asset.kind.contract_address.to_string(),
+            asset.kind.variable_index.to_string(),
+        );
+        *total_amount_map
+            .entry(encoded_token_kind)
+            .or_insert_with(BigUint::default) += asset.amount;
+    }
+
+    total_amount_map
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_distribution_entry() {
+        let user_address = parse_intmax_address(
+            "user address",
+            "0xc71603f33a1144ca7953db0ab48808f4c4055e3364a246c33c18a9786cb0b359",
+        )
+        .unwrap();
+        let encoded_entries = r#"
+- recipient: "0x714bdc6f38947e6da5ee9596c50b2e06e4e01c8885f98cf29d9c2f656eb3b45d"
+  amount: 100
+- fungibility: NFT
+  token_id: "0x01"
+"#;
+        let entries: Vec<DistributionEntry> = serde_yaml::from_str(encoded_entries).unwrap();
+        let location = |field: usize| FIELD_NAMES[field].to_string();
+        let fungible_asset = entries[0]
+            .to_contributed_asset(user_address, location)
+            .unwrap();
+        assert_eq!(fungible_asset.kind.contract_address, user_address);
+        assert_ne!(fungible_asset.receiver_address, user_address);
+        assert_eq!(fungible_asset.amount, 100);
+        let nft = entries[1]
+            .to_contributed_asset(user_address, location)
+            .unwrap();
+        assert_eq!(nft.receiver_address, user_address);
+        assert_eq!(
+            nft.kind.variable_index.to_string(),
+            VariableIndex::<F>::from(1u8).to_string()
+        );
+        assert_eq!(nft.amount, 1);
+
+        // The fields are named, so a misspelled or a missing one is refused instead of shifting the columns.
+        assert!(serde_yaml::from_str::<Vec<DistributionEntry>>("- recipent: 0x01\n").is_err());
+        let error = DistributionEntry::default()
+            .to_contributed_asset(user_address, location)
+            .unwrap_err();
+        assert!(error.to_string().contains("(amount)"));
+
+        let template = distribution_template(Path::new("a.yaml")).unwrap();
+        let entries: Vec<DistributionEntry> = serde_yaml::from_str(&template).unwrap();
+        assert_eq!(entries.len(), 2);
+        let template = distribution_template(Path::new("a.json")).unwrap();
+        let entries: Vec<DistributionEntry> = serde_json::from_str(&template).unwrap();
+        assert_eq!(entries[1].fungibility, Fungibility::Nft);
+        let template = distribution_template(Path::new("a.csv")).unwrap();
+        assert!(template.starts_with(CSV_HEADER));
+        assert!(template.ends_with(", NFT, 0x01, \n"));
+    }
+}
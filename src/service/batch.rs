@@ -0,0 +1,266 @@
+use std::{fs::File, path::Path, str::FromStr};
+
+use anyhow::Context;
+use intmax_rollup_interface::intmax_zkp_core::{
+    plonky2::plonk::config::{GenericConfig, PoseidonGoldilocksConfig},
+    rollup::gadgets::deposit_block::VariableIndex,
+    transaction::asset::{ContributedAsset, TokenKind},
+    zkdsa::account::Address,
+};
+use serde::Deserialize;
+
+use super::functions::{parse_address, parse_recipient};
+use crate::utils::{
+    key_management::memory::WalletOnMemory,
+    nickname::{NicknameTable, TOKEN_ADDRESS_MINE, TOKEN_ADDRESS_RECEIVER},
+};
+
+const D: usize = 2;
+type C = PoseidonGoldilocksConfig;
+type F = <C as GenericConfig<D>>::F;
+
+/// A transfer in the YAML file given to `tx batch-send`.
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct BatchTransferSpec {
+    /// an account in the wallet given as an address or a nickname
+    pub from: String,
+    /// the recipient given as an address or a nickname
+    pub to: String,
+    /// [default: the token issued by `from`]
+    #[serde(default)]
+    pub token_address: Option<String>,
+    /// [default: 0x00]
+    #[serde(default)]
+    pub token_id: Option<String>,
+    pub amount: u64,
+}
+
+/// e.g.,
+///
+/// ```yaml
+/// transfers:
+///   - from: hot1
+///     to: treasury
+///     token_address: 0x714bdc6f38947e6d
+///     amount: 100
+/// ```
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct BatchSpec {
+    pub transfers: Vec<BatchTransferSpec>,
+}
+
+impl BatchSpec {
+    pub fn read_from_file(spec_file_path: &Path) -> anyhow::Result<Self> {
+        let file = File::open(spec_file_path)
+            .with_context(|| format!("fail to open {}", spec_file_path.display()))?;
+
+        serde_yaml::from_reader(file)
+            .with_context(|| format!("invalid batch spec: {}", spec_file_path.display()))
+    }
+
+    /// Returns the assets to send for each sender in the order of first appearance.
+    /// They are split into transactions by `compose_transfers` when sent.
+    pub fn resolve(
+        &self,
+        wallet: &WalletOnMemory,
+        nickname_table: &NicknameTable,
+    ) -> anyhow::Result<Vec<(Address<F>, Vec<ContributedAsset<F>>)>> {
+        let mut transfers: Vec<(Address<F>, Vec<ContributedAsset<F>>)> = vec![];
+        for (i, transfer) in self.transfers.iter().enumerate() {
+            let context = || format!("transfers[{i}]");
+            let sender_address = parse_address(wallet, nickname_table, Some(transfer.from.clone()))
+                .with_context(context)?;
+            if !wallet.contains_account(&sender_address) {
+                anyhow::bail!("{}: {sender_address} is not in your wallet", context());
+            }
+            let receiver_address =
+                parse_recipient(nickname_table, &transfer.to).with_context(context)?;
+            if sender_address == receiver_address {
+                anyhow::bail!("{}: cannot send asset to myself", context());
+            }
+            let contract_address = if let Some(token_address) = &transfer.token_address {
+                parse_address(wallet, nickname_table, Some(token_address.clone()))
+                    .with_context(context)?
+            } else {
+                sender_address
+            };
+            let variable_index = if let Some(token_id) = &transfer.token_id {
+                VariableIndex::from_str(token_id)
+                    .map_err(|_| anyhow::anyhow!("{}: invalid token_id", context()))?
+            } else {
+                0u8.into()
+            };
+            if transfer.amount == 0 || transfer.amount >= 1u64 << 56 {
+                anyhow::bail!(
+                    "{}: amount must be a positive integer less than 2^56",
+                    context()
+                );
+            }
+
+            let asset = ContributedAsset {
+                receiver_address,
+                kind: TokenKind {
+                    contract_address,
+                    variable_index,
+                },
+                amount: transfer.amount,
+            };
+            if let Some((_, assets)) = transfers
+                .iter_mut()
+                .find(|(address, _)| *address == sender_address)
+            {
+                assets.push(asset);
+            } else {
+                transfers.push((sender_address, vec![asset]));
+            }
+        }
+
+        Ok(transfers)
+    }
+}
+
+/// A transfer given by `tx send --to <recipient>:<amount>[:<token-address>[:<token-id>]]`.
+/// The omitted token is the one given by `--token-address` and `--token-id` of the command.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SendTarget {
+    /// the recipient given as an address or a nickname
+    pub to: String,
+    pub amount: u64,
+    /// an address, a nickname, `mine` or `receiver`
+    pub token_address: Option<String>,
+    pub token_id: Option<VariableIndex<F>>,
+}
+
+impl FromStr for SendTarget {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let fields = s.split(':').collect::<Vec<_>>();
+        if fields.len() < 2 || fields.len() > 4 {
+            anyhow::bail!(
+                "invalid --to: {s}. Give <recipient>:<amount>[:<token-address>[:<token-id>]]"
+            );
+        }
+
+        let amount = fields[1]
+            .parse()
+            .map_err(|_| anyhow::anyhow!("invalid amount in --to: {s}"))?;
+        let token_address = fields
+            .get(2)
+            .filter(|token_address| !token_address.is_empty())
+            .map(|token_address| token_address.to_string());
+        let token_id = if let Some(token_id) = fields.get(3) {
+            Some(
+                VariableIndex::from_str(token_id)
+                    .map_err(|_| anyhow::anyhow!("invalid token id in --to: {s}"))?,
+            )
+        } else {
+            None
+        };
+
+        Ok(Self {
+            to: fields[0].to_string(),
+            amount,
+            token_address,
+            token_id,
+        })
+    }
+}
+
+impl SendTarget {
+    /// Returns the asset sent from `sender_address`.
+    /// The token defaults to `default_token_address` and `default_token_id`, and then to the token of the sender.
+    pub fn resolve(
+        &self,
+        wallet: &WalletOnMemory,
+        nickname_table: &NicknameTable,
+        sender_address: Address<F>,
+        default_token_address: Option<&str>,
+        default_token_id: Option<VariableIndex<F>>,
+    ) -> anyhow::Result<ContributedAsset<F>> {
+        let context = || format!("--to {}", self.to);
+        let receiver_address = parse_recipient(nickname_table, &self.to).with_context(context)?;
+        if sender_address == receiver_address {
+            anyhow::bail!("{}: cannot send asset to myself", context());
+        }
+        let contract_address = match self.token_address.as_deref().or(default_token_address) {
+            None | Some(TOKEN_ADDRESS_MINE) => sender_address,
+            Some(TOKEN_ADDRESS_RECEIVER) => receiver_address,
+            Some(token_address) => {
+                parse_address(wallet, nickname_table, Some(token_address.to_string()))
+                    .with_context(context)?
+            }
+        };
+        if self.amount == 0 || self.amount >= 1u64 << 56 {
+            anyhow::bail!(
+                "{}: amount must be a positive integer less than 2^56",
+                context()
+            );
+        }
+
+        Ok(ContributedAsset {
+            receiver_address,
+            kind: TokenKind {
+                contract_address,
+                variable_index: self
+                    .token_id
+                    .or(default_token_id)
+                    .unwrap_or_else(|| 0u8.into()),
+            },
+            amount: self.amount,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_batch_spec() {
+        let encoded_spec = r#"
+transfers:
+  - from: hot1
+    to: treasury
+    token_address: "0x714bdc6f38947e6d"
+    amount: 100
+  - from: "0x0000000000000001"
+    to: treasury
+    token_id: "0x01"
+    amount: 1
+"#;
+        let spec: BatchSpec = serde_yaml::from_str(encoded_spec).unwrap();
+        assert_eq!(spec.transfers.len(), 2);
+        assert_eq!(
+            spec.transfers[0].token_address.as_deref(),
+            Some("0x714bdc6f38947e6d")
+        );
+        assert_eq!(spec.transfers[1].token_address, None);
+        assert_eq!(spec.transfers[1].token_id.as_deref(), Some("0x01"));
+
+        let encoded_spec =
+            "transfers:\n  - from: hot1\n    to: treasury\n    amount: 1\n    memo: x\n";
+        assert!(serde_yaml::from_str::<BatchSpec>(encoded_spec).is_err());
+    }
+
+    #[test]
+    fn test_parse_send_target() {
+        let target = SendTarget::from_str("bob:100").unwrap();
+        assert_eq!(target.to, "bob");
+        assert_eq!(target.amount, 100);
+        assert_eq!(target.token_address, None);
+        assert_eq!(target.token_id, None);
+
+        let target = SendTarget::from_str("0x714bdc6f38947e6d:1:receiver:0x01").unwrap();
+        assert_eq!(target.token_address.as_deref(), Some("receiver"));
+        assert_eq!(target.token_id, Some(1u8.into()));
+        let target = SendTarget::from_str("bob:1::0x01").unwrap();
+        assert_eq!(target.token_address, None);
+
+        assert!(SendTarget::from_str("bob").is_err());
+        assert!(SendTarget::from_str("bob:-1").is_err());
+        assert!(SendTarget::from_str("bob:1:mine:0x01:x").is_err());
+    }
+}
@@ -0,0 +1,281 @@
+//! `block stats`, which estimates how often the aggregator produces blocks and how full they are.
+//! Blocks have no timestamps, so the interval between blocks is measured by watching for new blocks.
+//! A block is seen only once it is approved, so the interval includes both its proposal and its approval.
+
+use std::time::{Duration, Instant};
+
+use intmax_rollup_interface::intmax_zkp_core::{
+    plonky2::plonk::config::{GenericConfig, PoseidonGoldilocksConfig},
+    rollup::block::BlockInfo,
+};
+use serde::Serialize;
+
+use super::builder::ServiceBuilder;
+use crate::utils::{
+    human_format::{format_count, format_seconds, NumberFormat},
+    terminal::TerminalOptions,
+};
+
+const D: usize = 2;
+type C = PoseidonGoldilocksConfig;
+type F = <C as GenericConfig<D>>::F;
+
+/// How often the latest block is fetched while watching.
+const POLL_INTERVAL: Duration = Duration::from_secs(2);
+
+const SPARKLINE_CHARS: [char; 8] = ['▁', '▂', '▃', '▄', '▅', '▆', '▇', '█'];
+
+/// the levels used instead of `SPARKLINE_CHARS` with `--ascii` or `TERM=dumb`
+const ASCII_SPARKLINE_CHARS: [char; 8] = ['_', '.', ':', '-', '=', '+', '*', '#'];
+
+/// Returns one bar for each value, scaled from the minimum to the maximum.
+pub fn sparkline(values: &[f64], terminal: &TerminalOptions) -> String {
+    let chars = if terminal.ascii {
+        ASCII_SPARKLINE_CHARS
+    } else {
+        SPARKLINE_CHARS
+    };
+    let min = values.iter().copied().fold(f64::INFINITY, f64::min);
+    let max = values.iter().copied().fold(f64::NEG_INFINITY, f64::max);
+
+    values
+        .iter()
+        .map(|value| {
+            let level = if max > min {
+                ((value - min) / (max - min) * (chars.len() - 1) as f64).round() as usize
+            } else {
+                0
+            };
+
+            chars[level.min(chars.len() - 1)]
+        })
+        .collect()
+}
+
+fn average(values: &[f64]) -> Option<f64> {
+    if values.is_empty() {
+        return None;
+    }
+
+    Some(values.iter().sum::<f64>() / values.len() as f64)
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct BlockStats {
+    pub latest_block_number: u32,
+    /// the numbers of the sampled blocks in ascending order
+    pub sampled_blocks: Vec<u32>,
+    /// the transactions of each sampled block
+    pub tx_counts: Vec<usize>,
+    /// the deposits of each sampled block
+    pub deposit_counts: Vec<usize>,
+    pub watched_seconds: f64,
+    /// the blocks approved while watching
+    pub new_blocks: u32,
+    /// the seconds between the blocks approved while watching
+    pub block_intervals: Vec<f64>,
+}
+
+impl BlockStats {
+    pub fn new(latest_block_number: u32, blocks: &[BlockInfo<F>]) -> Self {
+        let mut blocks = blocks.iter().collect::<Vec<_>>();
+        blocks.sort_by_key(|block| block.header.block_number);
+
+        Self {
+            latest_block_number,
+            sampled_blocks: blocks
+                .iter()
+                .map(|block| block.header.block_number)
+                .collect(),
+            tx_counts: blocks
+                .iter()
+                .map(|block| block.transactions.len())
+                .collect(),
+            deposit_counts: blocks
+                .iter()
+                .map(|block| block.deposit_list.len())
+                .collect(),
+            watched_seconds: 0.0,
+            new_blocks: 0,
+            block_intervals: vec![],
+        }
+    }
+
+    /// The seconds per block while watching. `None` if no block was approved.
+    pub fn average_block_interval(&self) -> Option<f64> {
+        if self.new_blocks == 0 {
+            return None;
+        }
+
+        Some(self.watched_seconds / self.new_blocks as f64)
+    }
+
+    pub fn average_tx_count(&self) -> Option<f64> {
+        average(
+            &self
+                .tx_counts
+                .iter()
+                .map(|count| *count as f64)
+                .collect::<Vec<_>>(),
+        )
+    }
+
+    /// Fetch the latest block until `watch_duration` elapses, and record when new blocks appear.
+    pub async fn watch(
+        &mut self,
+        service: &ServiceBuilder,
+        watch_duration: Duration,
+    ) -> anyhow::Result<()> {
+        let start = Instant::now();
+        let mut last_block_number = self.latest_block_number;
+        let mut last_block_seen_at = None;
+        while start.elapsed() < watch_duration {
+            tokio::time::sleep(POLL_INTERVAL.min(watch_duration.saturating_sub(start.elapsed())))
+                .await;
+
+            let block_number = service.get_latest_block().await?.header.block_number;
+            if block_number <= last_block_number {
+                continue;
+            }
+
+            let now = Instant::now();
+            self.new_blocks += block_number - last_block_number;
+            if let Some(last_block_seen_at) = last_block_seen_at {
+                // Blocks approved within one poll are regarded as evenly spaced.
+                let interval = now.duration_since(last_block_seen_at).as_secs_f64()
+                    / (block_number - last_block_number) as f64;
+                for _ in last_block_number..block_number {
+                    self.block_intervals.push(interval);
+                }
+            }
+            last_block_number = block_number;
+            last_block_seen_at = Some(now);
+        }
+        self.watched_seconds = start.elapsed().as_secs_f64();
+        self.latest_block_number = last_block_number;
+
+        Ok(())
+    }
+
+    /// The summary printed without `--json`.
+    pub fn display<'a>(&'a self, terminal: &'a TerminalOptions) -> BlockStatsDisplay<'a> {
+        BlockStatsDisplay {
+            stats: self,
+            terminal,
+        }
+    }
+}
+
+pub struct BlockStatsDisplay<'a> {
+    stats: &'a BlockStats,
+    terminal: &'a TerminalOptions,
+}
+
+impl std::fmt::Display for BlockStatsDisplay<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let BlockStatsDisplay { stats, terminal } = self;
+        writeln!(f, "latest block:      {}", stats.latest_block_number)?;
+        match stats.average_block_interval() {
+            Some(interval) => writeln!(
+                f,
+                "block interval:    {} on average ({} blocks in {})",
+                format_seconds(interval),
+                stats.new_blocks,
+                format_seconds(stats.watched_seconds)
+            )?,
+            None => writeln!(
+                f,
+                "block interval:    no block was approved in {}",
+                format_seconds(stats.watched_seconds)
+            )?,
+        }
+        if !stats.block_intervals.is_empty() {
+            let min = stats
+                .block_intervals
+                .iter()
+                .copied()
+                .fold(f64::INFINITY, f64::min);
+            let max = stats
+                .block_intervals
+                .iter()
+                .copied()
+                .fold(f64::NEG_INFINITY, f64::max);
+            writeln!(
+                f,
+                "                   {}  min {}, max {}",
+                sparkline(&stats.block_intervals, terminal),
+                format_seconds(min),
+                format_seconds(max)
+            )?;
+        }
+
+        if let (Some(first), Some(last)) =
+            (stats.sampled_blocks.first(), stats.sampled_blocks.last())
+        {
+            let tx_counts = stats
+                .tx_counts
+                .iter()
+                .map(|count| *count as f64)
+                .collect::<Vec<_>>();
+            writeln!(
+                f,
+                "transactions:      {} per block on average, max {} (blocks {first}-{last})",
+                NumberFormat::from_env().decimal(stats.average_tx_count().unwrap_or_default(), 1),
+                format_count(stats.tx_counts.iter().max().copied().unwrap_or_default() as u64)
+            )?;
+            writeln!(f, "                   {}", sparkline(&tx_counts, terminal))?;
+            writeln!(
+                f,
+                "deposits:          {} in {} blocks",
+                format_count(stats.deposit_counts.iter().sum::<usize>() as u64),
+                stats.sampled_blocks.len()
+            )?;
+        }
+
+        match stats.average_block_interval() {
+            Some(interval) => write!(
+                f,
+                "A transaction sent now is expected to be approved in about {}.",
+                format_seconds(interval.round())
+            ),
+            None => write!(
+                f,
+                "A transaction sent now may take more than {} to be approved.",
+                format_seconds(stats.watched_seconds)
+            ),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_block_stats() {
+        let terminal = TerminalOptions::default();
+        assert_eq!(sparkline(&[1.0, 8.0, 4.5], &terminal), "▁█▅");
+        assert_eq!(sparkline(&[3.0, 3.0], &terminal), "▁▁");
+        assert_eq!(sparkline(&[], &terminal), "");
+
+        let ascii_terminal = TerminalOptions {
+            ascii: true,
+            ..TerminalOptions::default()
+        };
+        assert_eq!(sparkline(&[1.0, 8.0, 4.5], &ascii_terminal), "_#=");
+        assert!(sparkline(&[1.0, 2.0, 3.0, 4.0, 5.0, 6.0, 7.0, 8.0], &ascii_terminal).is_ascii());
+
+        let mut stats = BlockStats::new(10, &[]);
+        assert_eq!(stats.average_block_interval(), None);
+        assert_eq!(stats.average_tx_count(), None);
+
+        stats.watched_seconds = 60.0;
+        stats.new_blocks = 4;
+        stats.block_intervals = vec![10.0, 20.0, 15.0];
+        assert_eq!(stats.average_block_interval(), Some(15.0));
+        assert!(stats.display(&terminal).to_string().ends_with(&format!(
+            "expected to be approved in about {}.",
+            format_seconds(15.0)
+        )));
+    }
+}
@@ -0,0 +1,179 @@
+//! `block list` and `block show`, which display the blocks given by the aggregator.
+//! The aggregator gives only approved blocks, so a block after the latest one has not been approved yet.
+
+use std::fmt::Display;
+
+use intmax_rollup_interface::intmax_zkp_core::{
+    plonky2::plonk::config::{GenericConfig, PoseidonGoldilocksConfig},
+    rollup::block::BlockInfo,
+    sparse_merkle_tree::goldilocks_poseidon::WrappedHashOut,
+    transaction::block_header::get_block_hash,
+};
+use serde::Serialize;
+
+const D: usize = 2;
+type C = PoseidonGoldilocksConfig;
+type F = <C as GenericConfig<D>>::F;
+
+/// The number of blocks listed by `block list` if `--since` is omitted.
+pub const DEFAULT_LISTED_BLOCKS: u32 = 10;
+
+const SEPARATOR: &str =
+    "--------------------------------------------------------------------------------------";
+
+fn encode_block_hash(block: &BlockInfo<F>) -> String {
+    WrappedHashOut::from(get_block_hash(&block.header)).to_string()
+}
+
+/// A row of `block list`.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct BlockSummary {
+    pub block_number: u32,
+    pub block_hash: String,
+    pub tx_count: usize,
+    /// the transactions whose senders signed the block
+    pub valid_tx_count: usize,
+    pub deposit_count: usize,
+}
+
+impl BlockSummary {
+    pub fn new(block: &BlockInfo<F>) -> Self {
+        Self {
+            block_number: block.header.block_number,
+            block_hash: encode_block_hash(block),
+            tx_count: block.transactions.len(),
+            valid_tx_count: block
+                .address_list
+                .iter()
+                .filter(|sender| sender.is_valid)
+                .count(),
+            deposit_count: block.deposit_list.len(),
+        }
+    }
+}
+
+/// The table of `block list`. All the listed blocks are approved.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct BlockList {
+    pub latest_block_number: u32,
+    pub blocks: Vec<BlockSummary>,
+}
+
+impl Display for BlockList {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "Latest approved block: {}", self.latest_block_number)?;
+        writeln!(
+            f,
+            "{:>8} | {:>16} | {:>8} | Block hash",
+            "Block", "Transactions", "Deposits"
+        )?;
+        writeln!(f, "{SEPARATOR}")?;
+        if self.blocks.is_empty() {
+            writeln!(f, "  No blocks")?;
+        }
+        for summary in self.blocks.iter() {
+            writeln!(
+                f,
+                "{:>8} | {:>16} | {:>8} | {}",
+                summary.block_number,
+                format!("{} ({} valid)", summary.tx_count, summary.valid_tx_count),
+                summary.deposit_count,
+                summary.block_hash
+            )?;
+        }
+        write!(f, "{SEPARATOR}")
+    }
+}
+
+/// The output of `block show`.
+pub struct BlockView<'a>(pub &'a BlockInfo<F>);
+
+impl Display for BlockView<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let block = self.0;
+        let header = &block.header;
+        writeln!(f, "Block: {} (approved)", header.block_number)?;
+        writeln!(f, "{SEPARATOR}")?;
+        writeln!(f, "  {:<28} {}", "block hash:", encode_block_hash(block))?;
+        for (name, digest) in [
+            ("prev block hash", header.prev_block_hash),
+            ("block headers digest", header.block_headers_digest),
+            ("transactions digest", header.transactions_digest),
+            ("deposit digest", header.deposit_digest),
+            (
+                "proposed world state digest",
+                header.proposed_world_state_digest,
+            ),
+            (
+                "approved world state digest",
+                header.approved_world_state_digest,
+            ),
+            ("latest account digest", header.latest_account_digest),
+        ] {
+            writeln!(
+                f,
+                "  {:<28} {}",
+                format!("{name}:"),
+                WrappedHashOut::from(digest)
+            )?;
+        }
+
+        writeln!(f, "{SEPARATOR}")?;
+        writeln!(f, "Transactions: {}", block.transactions.len())?;
+        for (index, (sender, tx_hash)) in block
+            .address_list
+            .iter()
+            .zip(block.transactions.iter())
+            .enumerate()
+        {
+            let validity = if sender.is_valid { "valid" } else { "invalid" };
+            writeln!(
+                f,
+                "  {index:>4} | {tx_hash} | from {} ({validity})",
+                sender.sender_address
+            )?;
+        }
+
+        writeln!(f, "{SEPARATOR}")?;
+        writeln!(f, "Deposits: {}", block.deposit_list.len())?;
+        for (index, deposit) in block.deposit_list.iter().enumerate() {
+            writeln!(
+                f,
+                "  {index:>4} | {} | {} | {} to {}",
+                deposit.kind.contract_address,
+                deposit.kind.variable_index,
+                deposit.amount,
+                deposit.receiver_address
+            )?;
+        }
+        write!(f, "{SEPARATOR}")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_block_list() {
+        let block_list = BlockList {
+            latest_block_number: 12,
+            blocks: vec![BlockSummary {
+                block_number: 12,
+                block_hash: "0x01".to_string(),
+                tx_count: 3,
+                valid_tx_count: 2,
+                deposit_count: 1,
+            }],
+        };
+        let rendered_list = block_list.to_string();
+        assert!(rendered_list.starts_with("Latest approved block: 12\n"));
+        assert!(rendered_list.contains("      12 |      3 (2 valid) |        1 | 0x01"));
+
+        let block_list = BlockList {
+            blocks: vec![],
+            ..block_list
+        };
+        assert!(block_list.to_string().contains("No blocks"));
+    }
+}
@@ -1,5 +1,15 @@
-use std::{collections::HashMap, time::Instant};
+use std::{
+    collections::{BTreeMap, HashMap, HashSet},
+    ops::RangeInclusive,
+    path::PathBuf,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex,
+    },
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+};
 
+use anyhow::Context;
 use intmax_interoperability_plugin::ethers::types::Bytes;
 use intmax_rollup_interface::{
     constants::*,
@@ -9,17 +19,9 @@ use intmax_rollup_interface::{
         plonky2::{
             field::types::{Field, PrimeField64},
             hash::{hash_types::HashOut, poseidon::PoseidonHash},
-            iop::witness::PartialWitness,
-            plonk::{
-                circuit_data::CircuitConfig,
-                config::{GenericConfig, Hasher, PoseidonGoldilocksConfig},
-            },
-        },
-        rollup::{
-            block::BlockInfo,
-            circuits::{make_block_proof_circuit, BlockDetail},
-            gadgets::deposit_block::VariableIndex,
+            plonk::config::{GenericConfig, Hasher, PoseidonGoldilocksConfig},
         },
+        rollup::{block::BlockInfo, circuits::BlockDetail, gadgets::deposit_block::VariableIndex},
         sparse_merkle_tree::{
             gadgets::{
                 process::process_smt::SmtProcessProof, verify::verify_smt::SmtInclusionProof,
@@ -34,30 +36,134 @@ use intmax_rollup_interface::{
         transaction::{
             asset::{Asset, ContributedAsset, ReceivedAssetProof, TokenKind},
             block_header::{get_block_hash, BlockHeader},
-            circuits::{make_user_proof_circuit, MergeAndPurgeTransitionPublicInputs},
+            circuits::MergeAndPurgeTransitionPublicInputs,
             gadgets::merge::MergeProof,
         },
         zkdsa::{
             account::{Account, Address, PublicKey},
-            circuits::{make_simple_signature_circuit, SimpleSignatureProofWithPublicInputs},
+            circuits::SimpleSignatureProofWithPublicInputs,
         },
     },
 };
-use reqwest::Client;
-use serde::{Deserialize, Serialize};
+use rand::{rngs::StdRng, seq::SliceRandom, SeedableRng};
+use reqwest::{Client, RequestBuilder, Response};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
 // use wasm_bindgen::prelude::*;
 
-use crate::utils::key_management::memory::UserState;
+use super::{
+    capability::{
+        is_supported_by_probe, unsupported_capability_error, AdvertisedCapabilities, Capability,
+        CapabilityCache,
+    },
+    clock::{clock_skew_warning, parse_http_date},
+    codec::{SpooledBody, WireFormat},
+    error::{AggregatorError, InvalidResponse, NothingToDo, TooManyFragments},
+    functions::verify_received_asset_proof,
+    planner::{select_inputs, InputSelection},
+    policy::{request_policy_approval, PolicyConfig},
+    response::{check_response, ValidateResponse},
+    retry::{send_with_retry, RetryPolicy},
+};
+#[cfg(feature = "verbose")]
+use crate::utils::debug_format::DebugFormat;
+use crate::{
+    cache::blocks::BlockStore,
+    progress,
+    prover::{
+        backend::{prover, verify_user_tx_proof, UserTxWitness},
+        circuits::{block_circuit, simple_signature_circuit, user_tx_circuit},
+    },
+    utils::{
+        debug_format::write_debug_dump,
+        human_format::{format_duration, format_size},
+        key_management::{
+            memory::UserState,
+            secret::SecretKey,
+            types::{AssetRoot, Assets, MergeKey, SentTransaction, TransactionStatus, TxHash},
+        },
+        prover::record_proving_time,
+        version::is_supported_aggregator_version,
+        worker::Worker,
+    },
+};
 
 const D: usize = 2;
 type C = PoseidonGoldilocksConfig;
 type F = <C as GenericConfig<D>>::F;
 
 const CONTENT_TYPE: &str = "Content-Type";
+const ACCEPT: &str = "Accept";
+const RETRY_AFTER: &str = "Retry-After";
+
+/// The interval between polls of `/asset/received` if the aggregator does not specify it.
+pub const DEFAULT_RECEIVED_ASSETS_POLLING_INTERVAL: Duration = Duration::from_secs(5);
+
+/// `/asset/received` with `wait`, which asks the aggregator to hold the request
+/// until an asset is received or `wait` seconds pass.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+struct RequestAssetReceivedLongPollQuery {
+    user_address: Address<F>,
+    since: u32,
+    wait: u64,
+}
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
+struct RequestAccountStatusQuery {
+    address: Address<F>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+struct ResponseAccountStatusQuery {
+    is_registered: bool,
+}
+
+impl ValidateResponse for ResponseAccountStatusQuery {}
+
+/// The client of the aggregator.
+/// Cloning it is cheap, and the clones share the HTTP connections and the caches,
+/// so one instance can be used by concurrent tasks. The caches are behind `Mutex`es,
+/// which are never held across `.await` to keep the futures `Send`.
+#[derive(Clone, Debug)]
 pub struct ServiceBuilder {
     aggregator_url: String,
+    timeout: Option<Duration>,
+    /// the HTTP client with `timeout`, which is rebuilt only when the timeout is changed
+    client: Client,
+    /// If true, the size of each response from the aggregator is written to stderr.
+    stats: bool,
+    wire_format: WireFormat,
+    /// The blocks fetched from the aggregator, which received asset proofs are checked against.
+    block_cache: Arc<Mutex<BTreeMap<u32, BlockInfo<F>>>>,
+    /// The blocks and block details kept on disk across commands. See `cache::blocks`.
+    block_store: Option<BlockStore>,
+    /// The file which the proving times of user transactions are recorded in.
+    proving_times_path: Option<PathBuf>,
+    /// The directory which the witnesses and payloads are written into with `--debug-dump`.
+    debug_dump_dir: Option<PathBuf>,
+    /// Whether the skew of the local clock was already warned, which is checked on each health check.
+    clock_skew_warned: Arc<AtomicBool>,
+    /// If true, the consumed leaves and the order of merging are randomized. See `privacy`.
+    privacy_mode: bool,
+    pending_ttl: Option<Duration>,
+    /// The endpoint which approves each transfer before it is proved. See `policy`.
+    policy: PolicyConfig,
+    retry_policy: RetryPolicy,
+    /// The optional features of the aggregator found so far. See `capability`.
+    capability_cache: Arc<Mutex<CapabilityCache>>,
+}
+
+/// Witnesses and block details can be megabytes of JSON,
+/// so compressed responses and HTTP/2 are accepted if the aggregator supports them.
+fn build_client(timeout: Option<Duration>) -> Client {
+    let mut client_builder = Client::builder()
+        .gzip(true)
+        .brotli(true)
+        .http2_adaptive_window(true);
+    if let Some(timeout) = timeout {
+        client_builder = client_builder.timeout(timeout);
+    }
+
+    client_builder.build().expect("fail to build HTTP client")
 }
 
 pub async fn check_compatibility_with_server(service: &ServiceBuilder) -> anyhow::Result<()> {
@@ -68,7 +174,7 @@ pub async fn check_compatibility_with_server(service: &ServiceBuilder) -> anyhow
                 anyhow::bail!("Given aggregator URL is invalid.");
             }
 
-            if !version_info.version.starts_with("v0.5") {
+            if !is_supported_aggregator_version(&version_info.version) {
                 anyhow::bail!("Given aggregator URL is valid but is an incompatible version. If you get this error, synchronizing this CLI to the latest version may solve the problem. For more information, see https://github.com/InternetMaximalism/intmax-rollup-cli#update .");
             }
         }
@@ -84,9 +190,188 @@ impl ServiceBuilder {
     pub fn new(aggregator_url: &str) -> Self {
         Self {
             aggregator_url: aggregator_url.to_string(),
+            timeout: None,
+            client: build_client(None),
+            stats: false,
+            wire_format: WireFormat::Json,
+            block_cache: Default::default(),
+            block_store: None,
+            proving_times_path: None,
+            debug_dump_dir: None,
+            clock_skew_warned: Default::default(),
+            privacy_mode: false,
+            pending_ttl: None,
+            policy: Default::default(),
+            retry_policy: Default::default(),
+            capability_cache: Default::default(),
+        }
+    }
+
+    /// Set the timeout applied to each request to the aggregator.
+    pub fn set_timeout(&mut self, timeout: Option<Duration>) {
+        self.timeout = timeout;
+        self.client = build_client(timeout);
+    }
+
+    pub fn set_stats(&mut self, stats: bool) {
+        self.stats = stats;
+    }
+
+    /// Record the proving time of each user transaction in the file,
+    /// which is used to estimate how long merging takes.
+    pub fn set_proving_times_path(&mut self, proving_times_path: Option<PathBuf>) {
+        self.proving_times_path = proving_times_path;
+    }
+
+    /// Keep the fetched blocks in the directory so that the next commands do not download them again.
+    pub fn set_block_cache_dir(&mut self, block_cache_dir: Option<PathBuf>) {
+        self.block_store = block_cache_dir.map(BlockStore::new);
+    }
+
+    pub fn set_debug_dump_dir(&mut self, debug_dump_dir: Option<PathBuf>) {
+        self.debug_dump_dir = debug_dump_dir;
+    }
+
+    /// Write `value` into the `--debug-dump` directory if it is given.
+    /// A failure is only warned, since the dump must not stop the command.
+    pub fn debug_dump(&self, name: &str, value: &impl Serialize) {
+        if let Some(debug_dump_dir) = &self.debug_dump_dir {
+            match write_debug_dump(debug_dump_dir, name, value) {
+                Ok(file_path) => progress!("debug dump: {}", file_path.display()),
+                Err(err) => eprintln!("WARNING: fail to write the debug dump of {name}: {err:#}"),
+            }
+        }
+    }
+
+    pub fn set_privacy_mode(&mut self, privacy_mode: bool) {
+        self.privacy_mode = privacy_mode;
+    }
+
+    /// Expire the transactions which have not been approved within `pending_ttl`. They never expire if `None`.
+    pub fn set_pending_ttl(&mut self, pending_ttl: Option<Duration>) {
+        self.pending_ttl = pending_ttl;
+    }
+
+    /// Mark the pending transactions older than `pending_ttl` as expired,
+    /// so that a transaction left by an aborted run is neither signed nor listed as pending weeks later.
+    /// The assets of an expired transaction are not restored, since it may still be approved.
+    pub fn expire_stale_transactions<
+        D: NodeData<WrappedHashOut<F>, WrappedHashOut<F>, WrappedHashOut<F>> + Clone,
+        R: RootData<WrappedHashOut<F>> + Clone,
+    >(
+        &self,
+        user_state: &mut UserState<D, R>,
+    ) {
+        let pending_ttl = if let Some(pending_ttl) = self.pending_ttl {
+            pending_ttl
+        } else {
+            return;
+        };
+
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|duration| duration.as_secs())
+            .unwrap_or_default();
+        for (tx_hash, sent_transaction) in user_state.sent_transactions.iter_mut() {
+            if sent_transaction.is_stale(now, pending_ttl.as_secs()) {
+                sent_transaction.status = TransactionStatus::Expired;
+                eprintln!(
+                    "WARNING: transaction {tx_hash} expired after {} seconds without approval",
+                    pending_ttl.as_secs()
+                );
+            }
         }
     }
 
+    pub fn set_policy(&mut self, policy: PolicyConfig) {
+        self.policy = policy;
+    }
+
+    /// Set how the requests failed temporarily, e.g., with 502, are retried.
+    pub fn set_retry_policy(&mut self, retry_policy: RetryPolicy) {
+        self.retry_policy = retry_policy;
+    }
+
+    /// Set the encoding of the payloads of heavy endpoints.
+    pub fn set_wire_format(&mut self, wire_format: WireFormat) {
+        self.wire_format = wire_format;
+    }
+
+    fn client(&self) -> &Client {
+        &self.client
+    }
+
+    /// Send a request to the aggregator, retrying it according to the retry policy.
+    async fn send(&self, api_path: &str, request: RequestBuilder) -> anyhow::Result<Response> {
+        send_with_retry(&self.retry_policy, api_path, request).await
+    }
+
+    /// Read the response body in the format given by its `Content-Type`.
+    /// The size of the decompressed body and the decoding time are logged with `--stats`.
+    /// A body which cannot be decoded or breaks the invariants of `T` is an `InvalidResponse`.
+    async fn read_response<T: DeserializeOwned + ValidateResponse>(
+        &self,
+        api_path: &str,
+        resp: Response,
+    ) -> anyhow::Result<T> {
+        let version = resp.version();
+        let wire_format = WireFormat::from_content_type(
+            resp.headers()
+                .get(CONTENT_TYPE)
+                .and_then(|content_type| content_type.to_str().ok()),
+        );
+        let body = resp.bytes().await?;
+        let start = Instant::now();
+        let value = check_response(api_path, wire_format.decode(&body))?;
+        if self.stats {
+            let end = start.elapsed();
+            eprintln!(
+                "stats: {api_path} responded {} of {} over {:?}, decoded in {}",
+                format_size(body.len() as u64),
+                wire_format.content_type(),
+                version,
+                format_duration(end)
+            );
+        }
+
+        Ok(value)
+    }
+
+    /// Like `read_response`, but the body is spooled into a temporary file as it arrives
+    /// and decoded from there, which avoids buffering the raw body.
+    /// This is used for the endpoints responding many proofs.
+    async fn read_large_response<T: DeserializeOwned + ValidateResponse>(
+        &self,
+        api_path: &str,
+        mut resp: Response,
+    ) -> anyhow::Result<T> {
+        let version = resp.version();
+        let wire_format = WireFormat::from_content_type(
+            resp.headers()
+                .get(CONTENT_TYPE)
+                .and_then(|content_type| content_type.to_str().ok()),
+        );
+        let mut body = SpooledBody::new()?;
+        while let Some(chunk) = resp.chunk().await? {
+            body.write_chunk(&chunk)?;
+        }
+        let body_size = body.size();
+        let start = Instant::now();
+        let value = check_response(api_path, body.decode(wire_format))?;
+        if self.stats {
+            let end = start.elapsed();
+            eprintln!(
+                "stats: {api_path} responded {} of {} over {:?}, decoded in {}",
+                format_size(body_size as u64),
+                wire_format.content_type(),
+                version,
+                format_duration(end)
+            );
+        }
+
+        Ok(value)
+    }
+
     pub fn aggregator_api_url(&self, api_path: &str) -> String {
         let mut base_url: String = self.aggregator_url.clone();
 
@@ -105,6 +390,10 @@ impl ServiceBuilder {
             check_compatibility_with_server(&ServiceBuilder::new(&new_url)).await?;
 
             let _ = std::mem::replace::<String>(&mut self.aggregator_url, new_url.clone());
+            // The caches belong to the previous aggregator.
+            self.block_cache = Default::default();
+            self.block_store = None;
+            self.capability_cache = Default::default();
             println!("The new aggregator URL is {new_url} .");
         } else {
             println!(
@@ -120,40 +409,78 @@ impl ServiceBuilder {
         let payload = RequestAccountRegisterBody {
             public_key: public_key.into(),
         };
-        let body = serde_json::to_string(&payload).expect("fail to encode");
+        let body = serde_json::to_string(&payload)?;
         let api_path = "/account/register";
         #[cfg(feature = "verbose")]
         let start = {
             println!("request {api_path}");
             Instant::now()
         };
-        let resp = Client::new()
+        let request = self
+            .client()
             .post(self.aggregator_api_url(api_path))
             .body(body)
-            .header(CONTENT_TYPE, "application/json")
-            .send()
-            .await
-            .expect("fail to post");
+            .header(CONTENT_TYPE, "application/json");
+        let resp = self.send(api_path, request).await?;
         #[cfg(feature = "verbose")]
         {
             let end = start.elapsed();
-            println!("respond: {}.{:03} sec", end.as_secs(), end.subsec_millis());
+            println!("respond: {}", format_duration(end));
         }
         if resp.status() != 200 {
             #[cfg(feature = "verbose")]
             dbg!(&resp);
+            let status = resp.status().as_u16();
             let error_message = resp.text().await?;
-            anyhow::bail!("unexpected response from {api_path}: {error_message}");
+            return Err(AggregatorError::new(api_path, status, &error_message).into());
         }
 
-        let resp = resp
-            .json::<ResponseAccountRegisterBody>()
-            .await
-            .expect("fail to parse JSON");
+        let resp = self
+            .read_response::<ResponseAccountRegisterBody>(api_path, resp)
+            .await?;
 
         Ok(resp.address)
     }
 
+    /// Returns whether the given address is registered with the aggregator,
+    /// or `None` if the aggregator does not provide the registration status.
+    pub async fn is_registered_account(&self, address: Address<F>) -> anyhow::Result<Option<bool>> {
+        let query = RequestAccountStatusQuery { address };
+
+        let api_path = "/account/status";
+        #[cfg(feature = "verbose")]
+        let start = {
+            println!("request {api_path}");
+            Instant::now()
+        };
+        let request = self
+            .client()
+            .get(self.aggregator_api_url(api_path))
+            .query(&query);
+        let resp = self.send(api_path, request).await?;
+        #[cfg(feature = "verbose")]
+        {
+            let end = start.elapsed();
+            println!("respond: {}", format_duration(end));
+        }
+        if resp.status() == 404 {
+            return Ok(None);
+        }
+        if resp.status() != 200 {
+            #[cfg(feature = "verbose")]
+            dbg!(&resp);
+            let status = resp.status().as_u16();
+            let error_message = resp.text().await?;
+            return Err(AggregatorError::new(api_path, status, &error_message).into());
+        }
+
+        let resp = self
+            .read_response::<ResponseAccountStatusQuery>(api_path, resp)
+            .await?;
+
+        Ok(Some(resp.is_registered))
+    }
+
     /// test function
     pub async fn deposit_assets(
         &self,
@@ -175,41 +502,40 @@ impl ServiceBuilder {
                 .map(|v| v.into())
                 .collect::<Vec<_>>(),
         };
-        let body = serde_json::to_string(&payload).expect("fail to encode");
+        let body = serde_json::to_string(&payload)?;
         let api_path = "/test/deposit/add";
         #[cfg(feature = "verbose")]
         let start = {
             println!("request {api_path}");
             Instant::now()
         };
-        let resp = Client::new()
+        let request = self
+            .client()
             .post(self.aggregator_api_url(api_path))
             .body(body)
-            .header(CONTENT_TYPE, "application/json")
-            .send()
-            .await
-            .expect("fail to post");
+            .header(CONTENT_TYPE, "application/json");
+        let resp = self.send(api_path, request).await?;
         #[cfg(feature = "verbose")]
         {
             let end = start.elapsed();
-            println!("respond: {}.{:03} sec", end.as_secs(), end.subsec_millis());
+            println!("respond: {}", format_duration(end));
         }
         if resp.status() != 200 {
             #[cfg(feature = "verbose")]
             dbg!(&resp);
+            let status = resp.status().as_u16();
             let error_message = resp.text().await?;
-            anyhow::bail!("unexpected response from {api_path}: {error_message}");
+            return Err(AggregatorError::new(api_path, status, &error_message).into());
         }
 
-        let resp = resp
-            .json::<ResponseDepositAddBody>()
-            .await
-            .expect("fail to parse JSON");
+        let resp = self
+            .read_response::<ResponseDepositAddBody>(api_path, resp)
+            .await?;
 
         if resp.ok {
-            println!("deposit successfully");
+            progress!("deposit successfully");
         } else {
-            panic!("fail to deposit");
+            anyhow::bail!("fail to deposit");
         }
 
         Ok(())
@@ -222,37 +548,34 @@ impl ServiceBuilder {
         purge_input_witnesses: &[(SmtProcessProof<F>, SmtProcessProof<F>, SmtProcessProof<F>)],
         purge_output_witnesses: &[(SmtProcessProof<F>, SmtProcessProof<F>, SmtProcessProof<F>)],
         nonce: WrappedHashOut<F>,
-        user_asset_root: WrappedHashOut<F>,
+        user_asset_root: AssetRoot<F>,
     ) -> anyhow::Result<MergeAndPurgeTransitionPublicInputs<F>> {
         let user_tx_proof = {
-            let config = CircuitConfig::standard_recursion_config();
-            let merge_and_purge_circuit =
-                make_user_proof_circuit::<F, C, D>(config, ROLLUP_CONSTANTS);
-
-            let mut pw = PartialWitness::new();
-            let _public_inputs = merge_and_purge_circuit.targets.set_witness(
-                &mut pw,
-                account.address,
+            let witness = UserTxWitness {
+                user_address: account.address,
                 merge_witnesses,
                 purge_input_witnesses,
                 purge_output_witnesses,
                 nonce,
-                user_asset_root,
-            );
-            // dbg!(serde_json::to_string(&public_inputs).unwrap());
+                old_user_asset_root: user_asset_root.0,
+            };
 
-            println!("start proving: user_tx_proof");
+            let prover = prover();
+            progress!("start proving: user_tx_proof ({})", prover.name());
             let start = Instant::now();
-            let user_tx_proof = merge_and_purge_circuit.prove(pw).unwrap();
+            let user_tx_proof = prover.prove_user_tx(&witness)?;
             let end = start.elapsed();
-            println!("prove: {}.{:03} sec", end.as_secs(), end.subsec_millis());
+            progress!("prove: {}", format_duration(end));
+            if let Some(proving_times_path) = &self.proving_times_path {
+                if let Err(err) = record_proving_time(proving_times_path, end) {
+                    eprintln!("WARNING: fail to record the proving time: {err:#}");
+                }
+            }
 
             // dbg!(&sender1_tx_proof.public_inputs);
 
-            match merge_and_purge_circuit.verify(user_tx_proof.clone()) {
-                Ok(()) => {}
-                Err(x) => println!("{}", x),
-            }
+            // The proof may come from another machine, so it is never sent without this check.
+            verify_user_tx_proof(&witness, &user_tx_proof)?;
 
             user_tx_proof
         };
@@ -261,38 +584,69 @@ impl ServiceBuilder {
         println!("transaction hash is {} (INTMAX)", transaction.tx_hash);
 
         let payload = RequestTxSendBody { user_tx_proof };
-        let body = serde_json::to_string(&payload).expect("fail to encode");
         let api_path = "/tx/send";
+        self.debug_dump("tx_send_payload", &payload);
         #[cfg(feature = "verbose")]
         let start = {
             println!("request {api_path}");
             Instant::now()
         };
-        let resp = Client::new()
-            .post(self.aggregator_api_url(api_path))
-            .body(body)
-            .header(CONTENT_TYPE, "application/json")
-            .send()
-            .await
-            .expect("fail to post");
+        let mut wire_format = self.wire_format;
+        let resp = loop {
+            let body = wire_format.encode(&payload)?;
+            if self.stats {
+                eprintln!(
+                    "stats: {api_path} sent {} of {}",
+                    format_size(body.len() as u64),
+                    wire_format.content_type()
+                );
+            }
+            let request = self
+                .client()
+                .post(self.aggregator_api_url(api_path))
+                .body(body)
+                .header(CONTENT_TYPE, wire_format.content_type())
+                .header(ACCEPT, wire_format.accept());
+            let resp = self.send(api_path, request).await?;
+
+            // The aggregator which does not support the binary codec rejects the body.
+            if resp.status() == 415 && wire_format != WireFormat::Json {
+                wire_format = WireFormat::Json;
+                continue;
+            }
+
+            break resp;
+        };
         #[cfg(feature = "verbose")]
         {
             let end = start.elapsed();
-            println!("respond: {}.{:03} sec", end.as_secs(), end.subsec_millis());
+            println!("respond: {}", format_duration(end));
         }
         if resp.status() != 200 {
             #[cfg(feature = "verbose")]
             dbg!(&resp);
+            let status = resp.status().as_u16();
             let error_message = resp.text().await?;
-            anyhow::bail!("unexpected response from {api_path}: {error_message}");
+            let error = AggregatorError::new(api_path, status, &error_message)
+                .with_tx_hash(TxHash(transaction.tx_hash));
+
+            return Err(error.into());
         }
 
-        let resp = resp
-            .json::<ResponseTxSendBody>()
-            .await
-            .expect("fail to parse JSON");
+        let resp = self
+            .read_response::<ResponseTxSendBody>(api_path, resp)
+            .await?;
 
-        assert_eq!(resp.tx_hash, transaction.tx_hash);
+        if resp.tx_hash != transaction.tx_hash {
+            return Err(InvalidResponse::new(
+                api_path,
+                format!(
+                    "the transaction hash differs from the sent transaction {}",
+                    transaction.tx_hash
+                ),
+            )
+            .into());
+        }
 
         Ok(transaction)
     }
@@ -385,22 +739,110 @@ impl ServiceBuilder {
 
     pub async fn check_health(&self) -> anyhow::Result<ResponseCheckHealth> {
         let api_path = "/";
-        let resp = Client::new()
-            .get(self.aggregator_api_url(api_path))
-            .send()
-            .await?;
+        let request = self.client().get(self.aggregator_api_url(api_path));
+        let resp = self.send(api_path, request).await?;
+        self.check_clock_skew(&resp);
         if resp.status() != 200 {
             #[cfg(feature = "verbose")]
             dbg!(&resp);
+            let status = resp.status().as_u16();
             let error_message = resp.text().await?;
-            anyhow::bail!("unexpected response from {api_path}: {error_message}");
+            return Err(AggregatorError::new(api_path, status, &error_message).into());
         }
 
-        let resp = resp.json::<ResponseCheckHealth>().await?;
+        let resp = self
+            .read_response::<ResponseCheckHealth>(api_path, resp)
+            .await?;
 
         Ok(resp)
     }
 
+    /// Warn once if the local clock differs from the `Date` header of the aggregator.
+    fn check_clock_skew(&self, resp: &Response) {
+        if self.clock_skew_warned.load(Ordering::Relaxed) {
+            return;
+        }
+
+        let server_time = resp
+            .headers()
+            .get(reqwest::header::DATE)
+            .and_then(|value| value.to_str().ok())
+            .and_then(parse_http_date);
+        let local_time = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|duration| duration.as_secs());
+        if let (Some(server_time), Ok(local_time)) = (server_time, local_time) {
+            if let Some(warning) = clock_skew_warning(local_time, server_time) {
+                eprintln!("WARNING: {warning}");
+                self.clock_skew_warned.store(true, Ordering::Relaxed);
+            }
+        }
+    }
+
+    async fn get_advertised_capabilities(&self) -> anyhow::Result<AdvertisedCapabilities> {
+        if let Some(advertised) = self.capability_cache.lock().unwrap().advertised.clone() {
+            return Ok(advertised);
+        }
+
+        let api_path = "/";
+        let request = self.client().get(self.aggregator_api_url(api_path));
+        let resp = self.send(api_path, request).await?;
+        if resp.status() != 200 {
+            let status = resp.status().as_u16();
+            let error_message = resp.text().await?;
+            return Err(AggregatorError::new(api_path, status, &error_message).into());
+        }
+        let advertised = self
+            .read_response::<AdvertisedCapabilities>(api_path, resp)
+            .await?;
+        self.capability_cache.lock().unwrap().advertised = Some(advertised.clone());
+
+        Ok(advertised)
+    }
+
+    /// Returns whether the aggregator provides the capability, from its health check or by probing the endpoint.
+    pub async fn supports(&self, capability: Capability) -> anyhow::Result<bool> {
+        let advertised = self.get_advertised_capabilities().await?;
+        if let Some(supported) = advertised.supports(capability) {
+            return Ok(supported);
+        }
+        if let Some(supported) = self
+            .capability_cache
+            .lock()
+            .unwrap()
+            .probed
+            .get(&capability)
+        {
+            return Ok(*supported);
+        }
+
+        let api_path = capability.api_path();
+        let request = self.client().get(self.aggregator_api_url(api_path));
+        let resp = self.send(api_path, request).await?;
+        let supported = is_supported_by_probe(resp.status().as_u16());
+        self.capability_cache
+            .lock()
+            .unwrap()
+            .probed
+            .insert(capability, supported);
+
+        Ok(supported)
+    }
+
+    /// Fails with the aggregator version required if the aggregator does not provide the capability,
+    /// so that a command stops before anything is sent.
+    pub async fn ensure_capability(&self, capability: Capability) -> anyhow::Result<()> {
+        if !self.supports(capability).await? {
+            let advertised = self.get_advertised_capabilities().await?;
+            return Err(unsupported_capability_error(
+                capability,
+                &advertised.version,
+            ));
+        }
+
+        Ok(())
+    }
+
     pub async fn resolve_server_health_issue(&self) -> anyhow::Result<()> {
         // If an error occurs in this section, there is a high likelihood that the server is down.
         self.check_health().await?;
@@ -429,7 +871,7 @@ impl ServiceBuilder {
         user_state: &mut UserState<D, R>,
         user_address: Address<F>,
     ) {
-        let (mut raw_merge_witnesses, last_seen_block_number) = self
+        let (raw_merge_witnesses, last_seen_block_number) = self
             .get_merge_transaction_witness(
                 user_address,
                 Some(user_state.last_seen_block_number),
@@ -437,7 +879,7 @@ impl ServiceBuilder {
             )
             .await
             .unwrap_or_else(|err| {
-                dbg!(err);
+                eprintln!("WARNING: fail to fetch the merge transaction witnesses: {err}");
 
                 (vec![], user_state.last_seen_block_number)
             });
@@ -448,11 +890,16 @@ impl ServiceBuilder {
             )
             .await
             .unwrap_or_else(|err| {
-                dbg!(err);
+                eprintln!("WARNING: fail to fetch the blocks: {err}");
 
                 (vec![], last_seen_block_number)
             });
 
+        // The proofs are verified against the blocks fetched above, instead of trusting the aggregator.
+        let mut raw_merge_witnesses = self
+            .verify_received_asset_proofs(user_address, raw_merge_witnesses)
+            .await;
+
         // The asset contained in the transaction you cancel is reflected in your balance.
         {
             let canceled_transactions = blocks
@@ -482,17 +929,17 @@ impl ServiceBuilder {
                 .filter_map(|(_, tx_hash)| {
                     // dbg!(tx_hash.to_string());
 
-                    user_state.sent_transactions.get(tx_hash)
+                    user_state.sent_transactions.get(&TxHash(**tx_hash))
                 })
                 .cloned()
                 .collect::<Vec<_>>();
-            for assets in recovered_assets {
-                for asset in assets.0 {
+            for sent_transaction in recovered_assets {
+                for asset in sent_transaction.removed_assets {
                     // Assets that have already been already recovered will not be recorded again.
                     let old_amount = user_state
                         .asset_tree
                         .find(
-                            &asset.2,
+                            &asset.2 .0,
                             &asset.0.contract_address.to_hash_out().into(),
                             &asset.0.variable_index.to_hash_out().into(),
                         )
@@ -506,7 +953,7 @@ impl ServiceBuilder {
                     user_state
                         .asset_tree
                         .set(
-                            asset.2,
+                            asset.2 .0,
                             asset.0.contract_address.to_hash_out().into(),
                             asset.0.variable_index.to_hash_out().into(),
                             HashOut::from_partial(&[F::from_canonical_u64(asset.1)]).into(),
@@ -516,25 +963,82 @@ impl ServiceBuilder {
                 }
             }
 
-            // Delete the cancelled transaction as it will not be signed later.
+            // The cancelled transaction will not be signed later.
             for (_, target_tx_hash) in canceled_transactions {
-                user_state.sent_transactions.remove(target_tx_hash);
+                if let Some(sent_transaction) = user_state
+                    .sent_transactions
+                    .get_mut(&TxHash(*target_tx_hash))
+                {
+                    sent_transaction.status = TransactionStatus::Reverted;
+                }
             }
 
-            // Delete those whose proposed_block_number is less than or equal to the `last_seen_block_number`.
-            user_state.sent_transactions.retain(|_, v| {
-                if let Some(proposed_block_number) = v.1 {
-                    proposed_block_number > last_seen_block_number
-                } else {
-                    true
+            // The transactions whose proposed_block_number is less than or equal to the `last_seen_block_number`
+            // and which were not cancelled have been approved, even if they have expired.
+            for sent_transaction in user_state.sent_transactions.values_mut() {
+                if sent_transaction.status != TransactionStatus::Pending
+                    && sent_transaction.status != TransactionStatus::Expired
+                {
+                    continue;
                 }
-            });
+
+                if let Some(proposed_block_number) = sent_transaction.proposed_block_number {
+                    if proposed_block_number <= last_seen_block_number {
+                        sent_transaction.status = TransactionStatus::Approved;
+                    }
+                }
+            }
+        }
+
+        // Count the transfers which have been settled since the last sync.
+        {
+            let now = SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|duration| duration.as_secs())
+                .unwrap_or_default();
+            for (tx_hash, sent_transaction) in user_state.sent_transactions.iter() {
+                if sent_transaction.status != TransactionStatus::Approved {
+                    continue;
+                }
+
+                user_state.stats.record_sent(
+                    *tx_hash,
+                    sent_transaction.created_at.unwrap_or(now),
+                    &sent_transaction.outputs_by_recipient(),
+                );
+            }
+
+            for witness in raw_merge_witnesses.iter() {
+                if is_canceled_transaction(witness) {
+                    continue;
+                }
+
+                let block_number = witness.diff_tree_inclusion_proof.0.block_number;
+                let sender = if witness.is_deposit {
+                    None
+                } else {
+                    let tx_hash = witness.diff_tree_inclusion_proof.1.value;
+                    self.get_cached_block(block_number)
+                        .await
+                        .ok()
+                        .and_then(|block| find_sender(&block, tx_hash))
+                };
+                user_state.stats.record_received(
+                    calc_merge_key(witness),
+                    now,
+                    sender,
+                    block_number,
+                    &witness.assets,
+                );
+            }
         }
 
         user_state
             .rest_received_assets
             .append(&mut raw_merge_witnesses);
         user_state.last_seen_block_number = last_seen_block_number;
+
+        self.expire_stale_transactions(user_state);
     }
 
     pub async fn merge_and_purge_asset<
@@ -546,20 +1050,38 @@ impl ServiceBuilder {
         user_address: Address<F>,
         purge_diffs: &[ContributedAsset<F>],
         broadcast: bool,
-    ) -> anyhow::Result<WrappedHashOut<F>> {
-        let old_user_asset_root = user_state.asset_tree.get_root().unwrap();
+    ) -> anyhow::Result<TxHash<F>> {
+        let old_user_asset_root = AssetRoot(user_state.asset_tree.get_root().unwrap());
         // dbg!(&old_user_asset_root);
 
         let n_txs = 1 << ROLLUP_CONSTANTS.log_n_txs;
         let dequeued_len = n_txs.min(user_state.rest_received_assets.len());
         #[cfg(feature = "verbose")]
-        dbg!(user_state.rest_received_assets.len());
+        progress!(
+            "merging {dequeued_len} of {} received assets of {}",
+            user_state.rest_received_assets.len(),
+            user_address.debug_format()
+        );
 
         if dequeued_len == 0 && purge_diffs.is_empty() {
-            anyhow::bail!("nothing to do");
+            return Err(NothingToDo.into());
+        }
+
+        // Merging only is never rejected, since it sends nothing to others.
+        if !purge_diffs.is_empty() {
+            request_policy_approval(&self.policy, user_address, purge_diffs).await?;
         }
 
+        // The received assets merged with a transfer are kept in order,
+        // since `merge_before_sending` checks that they cover the transfer.
+        if self.privacy_mode && purge_diffs.is_empty() {
+            user_state
+                .rest_received_assets
+                .shuffle(&mut rand::thread_rng());
+        }
         let raw_merge_witnesses = user_state.rest_received_assets[0..dequeued_len].to_vec();
+        self.debug_dump("merge_witnesses", &raw_merge_witnesses);
+        self.debug_dump("purge_diffs", &purge_diffs);
         let merge_witnesses = calc_merge_witnesses(user_state, raw_merge_witnesses.clone()).await;
 
         // let middle_user_asset_root = user_state.asset_tree.get_root().unwrap();
@@ -602,6 +1124,34 @@ impl ServiceBuilder {
             output_asset_map.insert(output_asset.kind, old_amount + output_asset.amount);
         }
 
+        let mut total_amounts = output_asset_map
+            .iter()
+            .map(|(kind, amount)| (*kind, *amount))
+            .collect::<Vec<_>>();
+        total_amounts.sort_by_key(|(kind, _)| {
+            (
+                kind.contract_address.to_string(),
+                kind.variable_index.to_string(),
+            )
+        });
+        let mut recipients = purge_diffs
+            .iter()
+            .map(|output_asset| output_asset.receiver_address)
+            .collect::<Vec<_>>();
+        recipients.sort_by_key(|recipient| recipient.to_string());
+        recipients.dedup();
+        let outputs = purge_diffs
+            .iter()
+            .map(|output_asset| {
+                (
+                    output_asset.receiver_address,
+                    output_asset.kind,
+                    output_asset.amount,
+                )
+            })
+            .collect::<Vec<_>>();
+
+        let mut privacy_rng = self.privacy_mode.then(StdRng::from_entropy);
         let mut removed_assets = vec![];
         for (kind, output_amount) in output_asset_map {
             let mut target_assets = user_state
@@ -610,36 +1160,27 @@ impl ServiceBuilder {
                 .0
                 .into_iter()
                 .collect::<Vec<_>>();
-
-            // The leaf with the largest amount is processed first.
-            // However, if there is a leaf with the same value as output_amount, it is given priority.
-            target_assets.sort_by(|a, b| {
-                (a.1 == output_amount, a.1)
-                    .partial_cmp(&(b.1 == output_amount, b.1))
-                    .unwrap()
-                    .reverse()
-            });
-
-            let mut input_assets = vec![];
-            let mut input_amount = 0;
-            for asset in target_assets {
-                input_amount += asset.1;
-                input_assets.push(asset);
-
-                if output_amount <= input_amount {
-                    break;
-                }
-            }
-
-            if output_amount > input_amount {
-                anyhow::bail!("output asset amount is too much");
-            }
-
-            // The difference between input (what you own) and output (what you give to others) is given to yourself.
-            if input_amount > output_amount {
+            // The assets are kept in a hash set, so they are sorted to make the choice reproducible.
+            target_assets.sort_by_key(|asset| (asset.1, asset.2 .0.to_string()));
+
+            let max_inputs = ROLLUP_CONSTANTS
+                .n_diffs
+                .saturating_sub(purge_input_witness.len());
+            let InputSelection {
+                inputs: mut input_assets,
+                change,
+            } = select_inputs(
+                target_assets,
+                |asset| asset.1,
+                output_amount,
+                max_inputs,
+                privacy_rng.as_mut(),
+            )?;
+
+            if change > 0 {
                 let rest_asset = Asset {
                     kind,
-                    amount: input_amount - output_amount,
+                    amount: change,
                 };
                 let rest_witness = tx_diff_tree
                     .set(
@@ -658,7 +1199,7 @@ impl ServiceBuilder {
                 let rest_amount = user_state
                     .asset_tree
                     .find(
-                        &input_asset.2, // merge_key
+                        &input_asset.2 .0, // merge_key
                         &input_asset.0.contract_address.to_hash_out().into(),
                         &input_asset.0.variable_index.to_hash_out().into(),
                     )
@@ -668,7 +1209,7 @@ impl ServiceBuilder {
                 let input_witness = user_state
                     .asset_tree
                     .set(
-                        input_asset.2, // merge_key
+                        input_asset.2 .0, // merge_key
                         input_asset.0.contract_address.to_hash_out().into(),
                         input_asset.0.variable_index.to_hash_out().into(),
                         HashOut::ZERO.into(),
@@ -687,7 +1228,7 @@ impl ServiceBuilder {
 
         // If too many input_assets are required, the transmission will fail.
         if purge_input_witness.len() > ROLLUP_CONSTANTS.n_diffs {
-            anyhow::bail!("too many fragments of assets");
+            return Err(TooManyFragments.into());
         }
 
         // If too many output_assets are required, the transmission will fail.
@@ -710,8 +1251,7 @@ impl ServiceBuilder {
                 nonce,
                 old_user_asset_root,
             )
-            .await
-            .unwrap();
+            .await?;
         // dbg!(transaction.diff_root);
 
         // Delete merge transactions included in the send API.
@@ -746,48 +1286,62 @@ impl ServiceBuilder {
             purge_output_inclusion_witnesses.push(purge_output_inclusion_witness);
             assets_list.push(assets);
         }
+        // The transaction has already been sent, so record it even if broadcasting fails.
+        let created_at = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|duration| duration.as_secs())
+            .ok();
+        let sent_transaction = SentTransaction {
+            removed_assets,
+            proposed_block_number: None,
+            created_at,
+            total_amounts,
+            recipients,
+            status: TransactionStatus::Pending,
+            outputs,
+        };
+        let tx_hash = TxHash(transaction.tx_hash);
+        user_state
+            .sent_transactions
+            .insert(tx_hash, sent_transaction);
+
         if broadcast {
             self.broadcast_transaction(
                 user_address,
-                transaction.tx_hash,
+                tx_hash,
                 nonce,
                 purge_output_inclusion_witnesses,
                 assets_list,
             )
-            .await
-            .unwrap();
+            .await?;
         }
 
-        user_state
-            .sent_transactions
-            .insert(transaction.tx_hash, (removed_assets, None));
-
-        Ok(transaction.tx_hash)
+        Ok(tx_hash)
     }
 
     /// purge_output_inclusion_witnesses` is the inclusion proof for the receiver_address of the tx_diff_tree.
     pub async fn broadcast_transaction(
         &self,
         user_address: Address<F>,
-        tx_hash: WrappedHashOut<F>,
+        tx_hash: TxHash<F>,
         nonce: WrappedHashOut<F>,
         purge_output_inclusion_witnesses: Vec<SmtInclusionProof<F>>,
         assets: Vec<Vec<Asset<F>>>,
     ) -> anyhow::Result<()> {
         if purge_output_inclusion_witnesses.is_empty() {
-            println!("no purging transaction given");
+            progress!("no purging transaction given");
             return Ok(());
         }
 
         let payload = RequestTxBroadcastBody {
             signer_address: user_address,
-            tx_hash,
+            tx_hash: tx_hash.0,
             nonce,
             purge_output_inclusion_witnesses,
             assets,
         };
 
-        let body = serde_json::to_string(&payload).expect("fail to encode");
+        let body = serde_json::to_string(&payload)?;
 
         let api_path = "/tx/broadcast";
         #[cfg(feature = "verbose")]
@@ -795,32 +1349,34 @@ impl ServiceBuilder {
             println!("request {api_path}");
             Instant::now()
         };
-        let resp = Client::new()
+        let request = self
+            .client()
             .post(self.aggregator_api_url(api_path))
             .body(body)
-            .header(CONTENT_TYPE, "application/json")
-            .send()
-            .await
-            .expect("fail to post");
+            .header(CONTENT_TYPE, "application/json");
+        let resp = self.send(api_path, request).await?;
         #[cfg(feature = "verbose")]
         {
             let end = start.elapsed();
-            println!("respond: {}.{:03} sec", end.as_secs(), end.subsec_millis());
+            println!("respond: {}", format_duration(end));
         }
         if resp.status() != 200 {
             #[cfg(feature = "verbose")]
             dbg!(&resp);
+            let status = resp.status().as_u16();
             let error_message = resp.text().await?;
-            anyhow::bail!("unexpected response from {api_path}: {error_message}");
+            let error =
+                AggregatorError::new(api_path, status, &error_message).with_tx_hash(tx_hash);
+
+            return Err(error.into());
         }
 
-        let resp = resp
-            .json::<ResponseTxBroadcastBody>()
-            .await
-            .expect("fail to parse JSON");
+        let resp = self
+            .read_response::<ResponseTxBroadcastBody>(api_path, resp)
+            .await?;
 
         if resp.ok {
-            println!("broadcast transaction successfully");
+            progress!("broadcast transaction successfully");
         } else {
             anyhow::bail!("fail to broadcast transaction");
         }
@@ -837,29 +1393,28 @@ impl ServiceBuilder {
             println!("request {api_path}");
             Instant::now()
         };
-        let resp = Client::new()
+        let request = self
+            .client()
             .post(self.aggregator_api_url(api_path))
             .body(body)
-            .header(CONTENT_TYPE, "application/json")
-            .send()
-            .await
-            .expect("fail to post");
+            .header(CONTENT_TYPE, "application/json");
+        let resp = self.send(api_path, request).await?;
         #[cfg(feature = "verbose")]
         {
             let end = start.elapsed();
-            println!("respond: {}.{:03} sec", end.as_secs(), end.subsec_millis());
+            println!("respond: {}", format_duration(end));
         }
         if resp.status() != 200 {
             #[cfg(feature = "verbose")]
             dbg!(&resp);
+            let status = resp.status().as_u16();
             let error_message = resp.text().await?;
-            anyhow::bail!("unexpected response from {api_path}: {error_message}");
+            return Err(AggregatorError::new(api_path, status, &error_message).into());
         }
 
-        let resp = resp
-            .json::<ResponseBlockProposeBody>()
-            .await
-            .expect("fail to parse JSON");
+        let resp = self
+            .read_response::<ResponseBlockProposeBody>(api_path, resp)
+            .await?;
 
         Ok(*resp.new_world_state_root)
     }
@@ -873,55 +1428,51 @@ impl ServiceBuilder {
             println!("request {api_path}");
             Instant::now()
         };
-        let resp = Client::new()
+        let request = self
+            .client()
             .post(self.aggregator_api_url(api_path))
             .body(body)
-            .header(CONTENT_TYPE, "application/json")
-            .send()
-            .await
-            .expect("fail to post");
+            .header(CONTENT_TYPE, "application/json");
+        let resp = self.send(api_path, request).await?;
         #[cfg(feature = "verbose")]
         {
             let end = start.elapsed();
-            println!("respond: {}.{:03} sec", end.as_secs(), end.subsec_millis());
+            println!("respond: {}", format_duration(end));
         }
         if resp.status() != 200 {
             #[cfg(feature = "verbose")]
             dbg!(&resp);
+            let status = resp.status().as_u16();
             let error_message = resp.text().await?;
-            anyhow::bail!("unexpected response from {api_path}: {error_message}");
+            return Err(AggregatorError::new(api_path, status, &error_message).into());
         }
 
-        let resp = resp
-            .json::<ResponseBlockApproveBody>()
-            .await
-            .expect("fail to parse JSON");
+        let resp = self
+            .read_response::<ResponseBlockApproveBody>(api_path, resp)
+            .await?;
 
         Ok(resp.new_block)
     }
 
     pub async fn verify_block(&self, block_number: Option<u32>) -> anyhow::Result<()> {
-        let latest_block = self.get_latest_block().await.unwrap();
+        let latest_block = self.get_latest_block().await?;
         let block_number = block_number.unwrap_or(latest_block.header.block_number);
-        println!("block number: {block_number}");
-        let block_details = self.get_block_details(block_number).await.unwrap();
-
-        let config = CircuitConfig::standard_recursion_config();
-        let simple_signature_circuit = make_simple_signature_circuit(config.clone());
-        let merge_and_purge_circuit = make_user_proof_circuit(config.clone(), ROLLUP_CONSTANTS);
-        let block_circuit = make_block_proof_circuit::<F, C, D>(
-            config,
-            ROLLUP_CONSTANTS,
-            &merge_and_purge_circuit,
-            &simple_signature_circuit,
-        );
+        progress!("block number: {block_number}");
+        let mut block_details = if block_number <= latest_block.header.block_number {
+            self.get_cached_block_details(block_number).await?
+        } else {
+            self.get_block_details(block_number).await?
+        };
+
+        // The circuit is built while the trees below are reconstructed.
+        let block_circuit_builder = std::thread::spawn(block_circuit);
 
+        // Each leaf is dropped as soon as it is inserted, instead of being kept with the proofs.
         let nodes_db = NodeDataMemory::default();
         let mut deposit_tree =
             LayeredLayeredPoseidonSparseMerkleTree::new(nodes_db.clone(), RootDataTmp::default());
-        let deposit_process_proofs = block_details
-            .deposit_list
-            .iter()
+        let deposit_process_proofs = std::mem::take(&mut block_details.deposit_list)
+            .into_iter()
             .map(|leaf| {
                 deposit_tree
                     .set(
@@ -935,9 +1486,8 @@ impl ServiceBuilder {
             .collect::<Vec<_>>();
         let mut scroll_flag_tree =
             LayeredLayeredPoseidonSparseMerkleTree::new(nodes_db.clone(), RootDataTmp::default());
-        let scroll_process_proofs = block_details
-            .scroll_flag_list
-            .iter()
+        let scroll_process_proofs = std::mem::take(&mut block_details.scroll_flag_list)
+            .into_iter()
             .map(|leaf| {
                 scroll_flag_tree
                     .set(
@@ -951,9 +1501,8 @@ impl ServiceBuilder {
             .collect::<Vec<_>>();
         let mut polygon_flag_tree =
             LayeredLayeredPoseidonSparseMerkleTree::new(nodes_db, RootDataTmp::default());
-        let polygon_process_proofs = block_details
-            .polygon_flag_list
-            .iter()
+        let polygon_process_proofs = std::mem::take(&mut block_details.polygon_flag_list)
+            .into_iter()
             .map(|leaf| {
                 polygon_flag_tree
                     .set(
@@ -979,17 +1528,19 @@ impl ServiceBuilder {
             block_headers_proof_siblings: block_details.block_headers_proof_siblings,
             prev_block_header: block_details.prev_block_header,
         };
-        println!("start proving: block_proof");
+        let block_circuit = block_circuit_builder
+            .join()
+            .map_err(|_| anyhow::anyhow!("fail to build the block circuit"))?;
+        let prover = prover();
+        progress!("start proving: block_proof ({})", prover.name());
         let start = Instant::now();
-        let block_proof = block_circuit
-            .set_witness_and_prove(
-                &inputs,
-                &block_details.default_user_tx_proof,
-                &block_details.default_simple_signature_proof,
-            )
-            .unwrap();
+        let block_proof = prover.prove_block(
+            &inputs,
+            &block_details.default_user_tx_proof,
+            &block_details.default_simple_signature_proof,
+        )?;
         let end = start.elapsed();
-        println!("prove: {}.{:03} sec", end.as_secs(), end.subsec_millis());
+        progress!("prove: {}", format_duration(end));
 
         block_circuit.verify(block_proof)
     }
@@ -1004,24 +1555,27 @@ impl ServiceBuilder {
             println!("request {api_path}");
             Instant::now()
         };
-        let resp = Client::new()
+        let request = self
+            .client()
             .get(self.aggregator_api_url(api_path))
-            .query(&query)
-            .send()
-            .await?;
+            .query(&query);
+        let resp = self.send(api_path, request).await?;
         #[cfg(feature = "verbose")]
         {
             let end = start.elapsed();
-            println!("respond: {}.{:03} sec", end.as_secs(), end.subsec_millis());
+            println!("respond: {}", format_duration(end));
         }
         if resp.status() != 200 {
             #[cfg(feature = "verbose")]
             dbg!(&resp);
+            let status = resp.status().as_u16();
             let error_message = resp.text().await?;
-            anyhow::bail!("unexpected response from {api_path}: {error_message}");
+            return Err(AggregatorError::new(api_path, status, &error_message).into());
         }
 
-        let resp = resp.json::<ResponseLatestBlockQuery>().await?;
+        let resp = self
+            .read_response::<ResponseLatestBlockQuery>(api_path, resp)
+            .await?;
 
         Ok(resp.block)
     }
@@ -1050,29 +1604,181 @@ impl ServiceBuilder {
             println!("request {api_path}");
             Instant::now()
         };
-        let resp = Client::new()
+        let request = self
+            .client()
             .get(self.aggregator_api_url(api_path))
-            .query(&query)
-            .send()
-            .await?;
+            .query(&query);
+        let resp = self.send(api_path, request).await?;
         #[cfg(feature = "verbose")]
         {
             let end = start.elapsed();
-            println!("respond: {}.{:03} sec", end.as_secs(), end.subsec_millis());
+            println!("respond: {}", format_duration(end));
         }
         if resp.status() != 200 {
             #[cfg(feature = "verbose")]
             dbg!(&resp);
+            let status = resp.status().as_u16();
             let error_message = resp.text().await?;
-            anyhow::bail!("unexpected response from {api_path}: {error_message}");
+            return Err(AggregatorError::new(api_path, status, &error_message).into());
         }
 
-        let resp = resp.json::<ResponseBlockQuery>().await?;
+        let resp = self
+            .read_large_response::<ResponseBlockQuery>(api_path, resp)
+            .await?;
         let latest_block_number = until.unwrap_or(resp.latest_block_number);
+        self.cache_blocks(&resp.blocks)?;
 
         Ok((resp.blocks, latest_block_number))
     }
 
+    /// Remember the blocks to verify received asset proofs.
+    /// Fails if a block differs from the one with the same number fetched before,
+    /// including the blocks cached on disk by the previous commands.
+    fn cache_blocks(&self, blocks: &[BlockInfo<F>]) -> anyhow::Result<()> {
+        let mut block_cache = self.block_cache.lock().unwrap();
+        for block in blocks {
+            let block_number = block.header.block_number;
+            if !block_cache.contains_key(&block_number) {
+                let stored_block = self
+                    .block_store
+                    .as_ref()
+                    .and_then(|block_store| block_store.read_block(block_number));
+                if let Some(stored_block) = stored_block {
+                    block_cache.insert(block_number, stored_block);
+                }
+            }
+
+            if let Some(cached_block) = block_cache.get(&block_number) {
+                if get_block_hash(&cached_block.header) != get_block_hash(&block.header) {
+                    anyhow::bail!(
+                        "block {block_number} differs from the one the aggregator returned before"
+                    );
+                }
+            } else {
+                block_cache.insert(block_number, block.clone());
+                if let Some(block_store) = &self.block_store {
+                    if let Err(err) = block_store.write_block(block) {
+                        eprintln!("WARNING: fail to cache block {block_number}: {err:#}");
+                    }
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Returns the block fetched before by this command or cached on disk, without asking the aggregator.
+    fn read_cached_block(&self, block_number: u32) -> Option<BlockInfo<F>> {
+        if let Some(block) = self.block_cache.lock().unwrap().get(&block_number) {
+            return Some(block.clone());
+        }
+        let block = self
+            .block_store
+            .as_ref()
+            .and_then(|block_store| block_store.read_block(block_number))?;
+        self.block_cache
+            .lock()
+            .unwrap()
+            .insert(block_number, block.clone());
+
+        Some(block)
+    }
+
+    /// Returns the block from the local cache, or fetches it if it is not cached.
+    pub async fn get_cached_block(&self, block_number: u32) -> anyhow::Result<BlockInfo<F>> {
+        if let Some(block) = self.read_cached_block(block_number) {
+            return Ok(block);
+        }
+
+        self.get_blocks(Some(block_number.saturating_sub(1)), Some(block_number))
+            .await?;
+
+        self.block_cache
+            .lock()
+            .unwrap()
+            .get(&block_number)
+            .cloned()
+            .ok_or_else(|| anyhow::anyhow!("block {block_number} was not found"))
+    }
+
+    /// Returns the blocks from `from` to `to`, or to the latest block if `to` is omitted, in ascending order.
+    /// The cached blocks are used as they are, and only the missing ranges are fetched.
+    pub async fn get_cached_blocks(
+        &self,
+        from: u32,
+        to: Option<u32>,
+    ) -> anyhow::Result<Vec<BlockInfo<F>>> {
+        let latest_block_number = self.get_latest_block().await?.header.block_number;
+        let to = to.map_or(latest_block_number, |to| to.min(latest_block_number));
+
+        let mut blocks = BTreeMap::new();
+        for block_number in from..=to {
+            if let Some(block) = self.read_cached_block(block_number) {
+                blocks.insert(block_number, block);
+            }
+        }
+        let missing_ranges =
+            missing_block_ranges(from, to, |block_number| blocks.contains_key(&block_number));
+        for range in missing_ranges {
+            let (fetched_blocks, _) = self
+                .get_blocks(Some(range.start().saturating_sub(1)), Some(*range.end()))
+                .await?;
+            for block in fetched_blocks {
+                if range.contains(&block.header.block_number) {
+                    blocks.insert(block.header.block_number, block);
+                }
+            }
+        }
+
+        Ok(blocks.into_values().collect())
+    }
+
+    /// Drop the received asset proofs which do not match the blocks, reporting them.
+    pub async fn verify_received_asset_proofs(
+        &self,
+        user_address: Address<F>,
+        received_asset_proofs: Vec<ReceivedAssetProof<F>>,
+    ) -> Vec<ReceivedAssetProof<F>> {
+        let mut verified_proofs = vec![];
+        for witness in received_asset_proofs {
+            let block_number = witness.diff_tree_inclusion_proof.0.block_number;
+            let result = match self.get_cached_block(block_number).await {
+                Ok(block) => verify_received_asset_proof(user_address, &witness, &block),
+                Err(err) => Err(err),
+            };
+            match result {
+                Ok(()) => verified_proofs.push(witness),
+                Err(err) => eprintln!(
+                    "WARNING: rejected the assets received in {} (block {block_number}): {err:#}",
+                    witness.diff_tree_inclusion_proof.1.value
+                ),
+            }
+        }
+
+        verified_proofs
+    }
+
+    /// Returns the details of the approved block from the disk cache, or fetches and caches them.
+    pub async fn get_cached_block_details(
+        &self,
+        block_number: u32,
+    ) -> anyhow::Result<BlockDetails> {
+        let block_store = match &self.block_store {
+            Some(block_store) => block_store,
+            None => return self.get_block_details(block_number).await,
+        };
+        if let Some(block_details) = block_store.read_block_details(block_number) {
+            return Ok(block_details);
+        }
+
+        let block_details = self.get_block_details(block_number).await?;
+        if let Err(err) = block_store.write_block_details(block_number, &block_details) {
+            eprintln!("WARNING: fail to cache the details of block {block_number}: {err:#}");
+        }
+
+        Ok(block_details)
+    }
+
     pub async fn get_block_details(&self, block_number: u32) -> anyhow::Result<BlockDetails> {
         let query = RequestBlockDetailQuery { block_number };
         // let query = vec![("block_number", block_number.to_string())];
@@ -1083,28 +1789,35 @@ impl ServiceBuilder {
             println!("request {api_path}");
             Instant::now()
         };
-        let resp = Client::new()
+        let request = self
+            .client()
             .get(self.aggregator_api_url(api_path))
             .query(&query)
-            .send()
-            .await?;
+            .header(ACCEPT, self.wire_format.accept());
+        let resp = self.send(api_path, request).await?;
         #[cfg(feature = "verbose")]
         {
             let end = start.elapsed();
-            println!("respond: {}.{:03} sec", end.as_secs(), end.subsec_millis());
+            println!("respond: {}", format_duration(end));
         }
         if resp.status() != 200 {
             #[cfg(feature = "verbose")]
             dbg!(&resp);
+            let status = resp.status().as_u16();
             let error_message = resp.text().await?;
-            anyhow::bail!("unexpected response from {api_path}: {error_message}");
+            return Err(AggregatorError::new(api_path, status, &error_message).into());
         }
 
-        let resp = resp.json::<ResponseBlockDetailQuery>().await?;
+        let resp = self
+            .read_large_response::<ResponseBlockDetailQuery>(api_path, resp)
+            .await?;
 
         Ok(resp.block_details)
     }
 
+    /// Sign the proposal blocks including the pending transactions.
+    /// If `target_tx_hashes` is given, only the given transactions are signed.
+    /// `signer` is the one spawned before the proposal, if any. Otherwise, it is spawned here.
     pub async fn sign_proposed_block<
         D: NodeData<WrappedHashOut<F>, WrappedHashOut<F>, WrappedHashOut<F>>,
         R: RootData<WrappedHashOut<F>>,
@@ -1112,26 +1825,39 @@ impl ServiceBuilder {
         &self,
         user_state: &mut UserState<D, R>,
         user_address: Address<F>,
-    ) {
-        let pending_transactions = user_state
-            .sent_transactions
-            .iter_mut()
-            .filter(|(_, (_, proposed_block_number))| proposed_block_number.is_none());
-        for (tx_hash, (_, proposed_block_number)) in pending_transactions {
-            let (_tx_inclusion_witness, user_asset_inclusion_witness) = self
-                .get_transaction_inclusion_witness(user_address, *tx_hash)
+        target_tx_hashes: Option<&[TxHash<F>]>,
+        signer: Option<Signer>,
+    ) -> anyhow::Result<()> {
+        let account = user_state.account;
+        let mut signer = signer;
+        let pending_transactions =
+            user_state
+                .sent_transactions
+                .iter_mut()
+                .filter(|(tx_hash, sent_transaction)| {
+                    sent_transaction.status == TransactionStatus::Pending
+                        && sent_transaction.proposed_block_number.is_none()
+                        && target_tx_hashes
+                            .map(|target_tx_hashes| target_tx_hashes.contains(tx_hash))
+                            .unwrap_or(true)
+                });
+        for (tx_hash, sent_transaction) in pending_transactions {
+            let proposed_world_state_root = self
+                .get_signing_message(user_address, *tx_hash)
                 .await
+                .with_context(|| format!("fail to get the proposal block including {tx_hash}"))?;
+
+            let latest_block = self.get_latest_block().await?;
+            // The signature circuit may have been built while the block was proposed,
+            // but the message to sign is known only after the proposal.
+            let received_signature = signer
+                .get_or_insert_with(|| spawn_signer(account))
+                .process(*proposed_world_state_root)
                 .unwrap();
-
-            let latest_block = self.get_latest_block().await.unwrap();
-            let proposed_world_state_root = user_asset_inclusion_witness.root;
-            let received_signature =
-                sign_to_message(user_state.account, *proposed_world_state_root).await;
             self.send_received_signature(received_signature, *tx_hash)
-                .await
-                .unwrap();
+                .await?;
 
-            *proposed_block_number = Some(latest_block.header.block_number + 1);
+            sent_transaction.proposed_block_number = Some(latest_block.header.block_number + 1);
 
             // let validation_error = format!(
             //     "{}: {}",
@@ -1142,17 +1868,32 @@ impl ServiceBuilder {
             //     dbg!(err);
             // }
         }
+
+        Ok(())
+    }
+
+    /// Returns the proposed world state root, which the sender signs to approve the transaction.
+    pub async fn get_signing_message(
+        &self,
+        user_address: Address<F>,
+        tx_hash: TxHash<F>,
+    ) -> anyhow::Result<WrappedHashOut<F>> {
+        let (_tx_inclusion_witness, user_asset_inclusion_witness) = self
+            .get_transaction_inclusion_witness(user_address, tx_hash)
+            .await?;
+
+        Ok(user_asset_inclusion_witness.root)
     }
 
     /// Returns `()`
     pub async fn get_transaction_inclusion_witness(
         &self,
         user_address: Address<F>,
-        tx_hash: WrappedHashOut<F>,
+        tx_hash: TxHash<F>,
     ) -> anyhow::Result<(MerkleProof<F>, SmtInclusionProof<F>)> {
         let query = RequestTxReceiptQuery {
             user_address,
-            tx_hash,
+            tx_hash: tx_hash.0,
         };
         // let query = vec![
         //     ("user_address", format!("{}", user_address)),
@@ -1165,24 +1906,27 @@ impl ServiceBuilder {
             println!("request {api_path}");
             Instant::now()
         };
-        let resp = Client::new()
+        let request = self
+            .client()
             .get(self.aggregator_api_url(api_path))
-            .query(&query)
-            .send()
-            .await?;
+            .query(&query);
+        let resp = self.send(api_path, request).await?;
         #[cfg(feature = "verbose")]
         {
             let end = start.elapsed();
-            println!("respond: {}.{:03} sec", end.as_secs(), end.subsec_millis());
+            println!("respond: {}", format_duration(end));
         }
         if resp.status() != 200 {
             #[cfg(feature = "verbose")]
             dbg!(&resp);
+            let status = resp.status().as_u16();
             let error_message = resp.text().await?;
-            anyhow::bail!("unexpected response from {api_path}: {error_message}");
+            return Err(AggregatorError::new(api_path, status, &error_message).into());
         }
 
-        let resp = resp.json::<ResponseTxReceiptQuery>().await?;
+        let resp = self
+            .read_response::<ResponseTxReceiptQuery>(api_path, resp)
+            .await?;
 
         Ok((resp.tx_inclusion_witness, resp.user_asset_inclusion_witness))
     }
@@ -1190,14 +1934,14 @@ impl ServiceBuilder {
     pub async fn send_received_signature(
         &self,
         received_signature: SimpleSignatureProofWithPublicInputs<F, C, D>,
-        tx_hash: WrappedHashOut<F>,
+        tx_hash: TxHash<F>,
     ) -> anyhow::Result<()> {
         let payload = RequestSignedDiffSendBody {
-            tx_hash,
+            tx_hash: tx_hash.0,
             received_signature,
         };
 
-        let body = serde_json::to_string(&payload).expect("fail to encode");
+        let body = serde_json::to_string(&payload)?;
 
         let api_path = "/signed-diff/send";
         #[cfg(feature = "verbose")]
@@ -1205,32 +1949,31 @@ impl ServiceBuilder {
             println!("request {api_path}");
             Instant::now()
         };
-        let resp = Client::new()
+        let request = self
+            .client()
             .post(self.aggregator_api_url(api_path))
             .body(body)
-            .header(CONTENT_TYPE, "application/json")
-            .send()
-            .await
-            .expect("fail to post");
+            .header(CONTENT_TYPE, "application/json");
+        let resp = self.send(api_path, request).await?;
         #[cfg(feature = "verbose")]
         {
             let end = start.elapsed();
-            println!("respond: {}.{:03} sec", end.as_secs(), end.subsec_millis());
+            println!("respond: {}", format_duration(end));
         }
         if resp.status() != 200 {
             #[cfg(feature = "verbose")]
             dbg!(&resp);
+            let status = resp.status().as_u16();
             let error_message = resp.text().await?;
-            anyhow::bail!("unexpected response from {api_path}: {error_message}");
+            return Err(AggregatorError::new(api_path, status, &error_message).into());
         }
 
-        let resp = resp
-            .json::<ResponseSignedDiffSendBody>()
-            .await
-            .expect("fail to parse JSON");
+        let resp = self
+            .read_response::<ResponseSignedDiffSendBody>(api_path, resp)
+            .await?;
 
         if resp.ok {
-            println!("send received signature successfully");
+            progress!("send received signature successfully");
         } else {
             anyhow::bail!("fail to send received signature");
         }
@@ -1264,36 +2007,86 @@ impl ServiceBuilder {
             println!("request {api_path}");
             Instant::now()
         };
-        let resp = Client::new()
+        let request = self
+            .client()
             .get(self.aggregator_api_url(api_path))
             .query(&query)
-            .send()
-            .await?;
+            .header(ACCEPT, self.wire_format.accept());
+        let resp = self.send(api_path, request).await?;
         #[cfg(feature = "verbose")]
         {
             let end = start.elapsed();
-            println!("respond: {}.{:03} sec", end.as_secs(), end.subsec_millis());
+            println!("respond: {}", format_duration(end));
         }
         if resp.status() != 200 {
             #[cfg(feature = "verbose")]
             dbg!(&resp);
+            let status = resp.status().as_u16();
             let error_message = resp.text().await?;
-            anyhow::bail!("unexpected response from {api_path}: {error_message}");
+            return Err(AggregatorError::new(api_path, status, &error_message).into());
         }
 
-        let resp = resp.json::<ResponseAssetReceivedQuery>().await?;
+        let resp = self
+            .read_large_response::<ResponseAssetReceivedQuery>(api_path, resp)
+            .await?;
         let latest_block_number = until.unwrap_or(resp.latest_block_number);
 
         Ok((resp.proofs, latest_block_number))
     }
 
+    /// Long-poll the assets received after the block `since`.
+    /// Aggregators which do not support long polling respond immediately.
+    /// Returns `(raw_merge_witnesses, latest_block_number, poll_interval)`,
+    /// where `poll_interval` is given by the `Retry-After` header.
+    pub async fn poll_received_assets(
+        &self,
+        user_address: Address<F>,
+        since: u32,
+        wait: Duration,
+    ) -> anyhow::Result<(Vec<ReceivedAssetProof<F>>, u32, Option<Duration>)> {
+        let query = RequestAssetReceivedLongPollQuery {
+            user_address,
+            since,
+            wait: wait.as_secs(),
+        };
+
+        let api_path = "/asset/received";
+        let mut request = self
+            .client()
+            .get(self.aggregator_api_url(api_path))
+            .query(&query)
+            .header(ACCEPT, self.wire_format.accept());
+        // The request is held by the aggregator for `wait` at most.
+        if let Some(timeout) = self.timeout {
+            request = request.timeout(timeout + wait);
+        }
+        let resp = self.send(api_path, request).await?;
+        if resp.status() != 200 {
+            let status = resp.status().as_u16();
+            let error_message = resp.text().await?;
+            return Err(AggregatorError::new(api_path, status, &error_message).into());
+        }
+
+        let poll_interval = resp
+            .headers()
+            .get(RETRY_AFTER)
+            .and_then(|retry_after| retry_after.to_str().ok())
+            .and_then(|retry_after| retry_after.trim().parse::<u64>().ok())
+            .map(Duration::from_secs);
+        let resp = self
+            .read_large_response::<ResponseAssetReceivedQuery>(api_path, resp)
+            .await?;
+
+        Ok((resp.proofs, resp.latest_block_number, poll_interval))
+    }
+
     pub async fn get_transaction_confirmation_witness(
         &self,
-        tx_hash: WrappedHashOut<F>,
+        tx_hash: TxHash<F>,
         taker_address: Address<F>,
     ) -> anyhow::Result<Bytes> {
         let query = RequestTxConfirmationWitnessQuery {
-            tx_hash,
+            tx_hash: tx_hash.0,
             recipient: taker_address,
         };
         // let query = vec![
@@ -1307,28 +2100,31 @@ impl ServiceBuilder {
             println!("request {api_path}");
             Instant::now()
         };
-        let resp = Client::new()
+        let request = self
+            .client()
             .get(self.aggregator_api_url(api_path))
-            .query(&query)
-            .send()
-            .await?;
+            .query(&query);
+        let resp = self.send(api_path, request).await?;
         #[cfg(feature = "verbose")]
         {
             let end = start.elapsed();
-            println!("respond: {}.{:03} sec", end.as_secs(), end.subsec_millis());
+            println!("respond: {}", format_duration(end));
         }
         if resp.status() != 200 {
             #[cfg(feature = "verbose")]
             dbg!(&resp);
+            let status = resp.status().as_u16();
             let error_message = resp.text().await?;
-            anyhow::bail!("unexpected response from {api_path}: {error_message}");
+            return Err(AggregatorError::new(api_path, status, &error_message).into());
         }
 
-        let resp = resp.json::<ResponseTxConfirmationWitnessQuery>().await?;
+        let resp = self
+            .read_response::<ResponseTxConfirmationWitnessQuery>(api_path, resp)
+            .await?;
 
-        #[cfg(feature = "verbose")]
-        dbg!(&resp.witness);
-        let witness_bytes = hex::decode(&resp.witness[2..]).unwrap();
+        self.debug_dump("transaction_confirmation_witness", &resp.witness);
+        // The witness was checked to be 0x-prefixed hex by `read_response`.
+        let witness_bytes = hex::decode(&resp.witness[2..])?;
         let witness = Bytes::from(witness_bytes);
 
         // TODO: Currently, there is no rigorous verification that the money transfer has been executed on the other party's network.
@@ -1369,24 +2165,27 @@ impl ServiceBuilder {
             println!("request {api_path}");
             Instant::now()
         };
-        let resp = Client::new()
+        let request = self
+            .client()
             .get(self.aggregator_api_url(api_path))
-            .query(&query)
-            .send()
-            .await?;
+            .query(&query);
+        let resp = self.send(api_path, request).await?;
         #[cfg(feature = "verbose")]
         {
             let end = start.elapsed();
-            println!("respond: {}.{:03} sec", end.as_secs(), end.subsec_millis());
+            println!("respond: {}", format_duration(end));
         }
         if resp.status() != 200 {
             #[cfg(feature = "verbose")]
             dbg!(&resp);
+            let status = resp.status().as_u16();
             let error_message = resp.text().await?;
-            anyhow::bail!("unexpected response from {api_path}: {error_message}");
+            return Err(AggregatorError::new(api_path, status, &error_message).into());
         }
 
-        let resp = resp.json::<ResponseUserAssetProofBody>().await?;
+        let resp = self
+            .read_response::<ResponseUserAssetProofBody>(api_path, resp)
+            .await?;
 
         Ok(resp.proof)
     }
@@ -1406,21 +2205,22 @@ impl ServiceBuilder {
             println!("request {api_path}");
             Instant::now()
         };
-        let resp = Client::new()
+        let request = self
+            .client()
             .get(self.aggregator_api_url(api_path))
-            .query(&query)
-            .send()
-            .await?;
+            .query(&query);
+        let resp = self.send(api_path, request).await?;
         #[cfg(feature = "verbose")]
         {
             let end = start.elapsed();
-            println!("respond: {}.{:03} sec", end.as_secs(), end.subsec_millis());
+            println!("respond: {}", format_duration(end));
         }
         if resp.status() != 200 {
             #[cfg(feature = "verbose")]
             dbg!(&resp);
+            let status = resp.status().as_u16();
             let error_message = resp.text().await?;
-            anyhow::bail!("unexpected response from {api_path}: {error_message}");
+            return Err(AggregatorError::new(api_path, status, &error_message).into());
         }
 
         let ResponseTransactionProofQuery {
@@ -1428,36 +2228,206 @@ impl ServiceBuilder {
             transaction_proof,
             block_header,
             witness,
-        } = resp.json::<ResponseTransactionProofQuery>().await?;
+        } = self
+            .read_response::<ResponseTransactionProofQuery>(api_path, resp)
+            .await?;
 
         Ok((tx_details, transaction_proof, block_header, witness))
     }
 }
 
+/// Build the user transaction, simple signature and block circuits,
+/// and display the time taken for each of them.
+/// Circuits are not persisted, so this measures the setup cost of proving on this machine.
+pub fn warm_up_circuits() -> Duration {
+    let total_start = Instant::now();
+
+    println!("start building: user_tx_circuit");
+    let start = Instant::now();
+    user_tx_circuit();
+    println!("build: {}", format_duration(start.elapsed()));
+
+    println!("start building: simple_signature_circuit");
+    let start = Instant::now();
+    simple_signature_circuit();
+    println!("build: {}", format_duration(start.elapsed()));
+
+    println!("start building: block_circuit");
+    let start = Instant::now();
+    block_circuit();
+    println!("build: {}", format_duration(start.elapsed()));
+
+    total_start.elapsed()
+}
+
+/// Signs messages with the simple signature circuit on another thread.
+pub type Signer = Worker<HashOut<F>, SimpleSignatureProofWithPublicInputs<F, C, D>>;
+
+/// Start building the simple signature circuit on another thread.
+/// The messages sent to the returned signer are signed after the circuit is built.
+/// Only the private key is kept by the thread, and it is zeroized when the signer is dropped.
+pub fn spawn_signer(sender_account: Account<F>) -> Signer {
+    let private_key = SecretKey::new(sender_account.private_key);
+    Worker::spawn(
+        simple_signature_circuit,
+        move |simple_signature_circuit, message| {
+            progress!("start proving: received_signature");
+            let start = Instant::now();
+            let received_signature = prover()
+                .prove_signature(private_key.expose(), message)
+                .unwrap();
+            let end = start.elapsed();
+            progress!("prove: {}", format_duration(end));
+
+            match simple_signature_circuit.verify(received_signature.clone()) {
+                Ok(()) => {}
+                Err(x) => println!("{}", x),
+            }
+
+            received_signature
+        },
+    )
+}
+
 pub async fn sign_to_message(
     sender_account: Account<F>,
     message: HashOut<F>,
 ) -> SimpleSignatureProofWithPublicInputs<F, C, D> {
-    let config = CircuitConfig::standard_recursion_config();
-    let simple_signature_circuit = make_simple_signature_circuit(config);
+    spawn_signer(sender_account).process(message).unwrap()
+}
 
-    let mut pw = PartialWitness::new();
-    simple_signature_circuit
-        .targets
-        .set_witness(&mut pw, sender_account.private_key, message);
+fn calc_merge_key(witness: &ReceivedAssetProof<F>) -> MergeKey<F> {
+    let tx_hash = witness.diff_tree_inclusion_proof.1.value;
+    if witness.is_deposit {
+        let block_hash = get_block_hash(&witness.diff_tree_inclusion_proof.0);
 
-    println!("start proving: received_signature");
-    let start = Instant::now();
-    let received_signature = simple_signature_circuit.prove(pw).unwrap();
-    let end = start.elapsed();
-    println!("prove: {}.{:03} sec", end.as_secs(), end.subsec_millis());
+        MergeKey(PoseidonHash::two_to_one(*tx_hash, block_hash).into())
+    } else {
+        MergeKey(tx_hash)
+    }
+}
+
+/// Returns the sender of the transaction included in the block.
+fn find_sender(block: &BlockInfo<F>, tx_hash: WrappedHashOut<F>) -> Option<Address<F>> {
+    block
+        .transactions
+        .iter()
+        .position(|v| *v == tx_hash)
+        .and_then(|index| block.address_list.get(index))
+        .map(|v| v.sender_address)
+}
+
+fn is_canceled_transaction(witness: &ReceivedAssetProof<F>) -> bool {
+    let is_valid_confirmed_block_number =
+        witness.latest_account_tree_inclusion_proof.value.to_u32()
+            == witness.diff_tree_inclusion_proof.0.block_number;
+
+    !witness.is_deposit && !is_valid_confirmed_block_number
+}
+
+fn is_already_merged<
+    D: NodeData<WrappedHashOut<F>, WrappedHashOut<F>, WrappedHashOut<F>> + Clone,
+    R: RootData<WrappedHashOut<F>> + Clone,
+>(
+    user_state: &UserState<D, R>,
+    merge_key: MergeKey<F>,
+) -> bool {
+    let asset_tree = PoseidonSparseMerkleTree::new(
+        user_state.asset_tree.nodes_db.clone(),
+        user_state.asset_tree.roots_db.clone(),
+    );
+    let old_asset_root_with_merge_key = asset_tree.get(&merge_key.0).unwrap();
+
+    old_asset_root_with_merge_key != Default::default()
+}
+
+/// An incoming transfer or deposit reported by `account notify`.
+#[derive(Clone, Debug, Serialize)]
+pub struct ReceivedAssetNotification {
+    pub merge_key: MergeKey<F>,
+    pub is_deposit: bool,
+    pub block_number: u32,
+    pub assets: Vec<Asset<F>>,
+}
+
+/// Returns the received assets except for the transactions canceled by their senders.
+pub fn get_received_asset_notifications(
+    received_asset_witness: &[ReceivedAssetProof<F>],
+) -> Vec<ReceivedAssetNotification> {
+    received_asset_witness
+        .iter()
+        .filter(|witness| !is_canceled_transaction(witness))
+        .map(|witness| ReceivedAssetNotification {
+            merge_key: calc_merge_key(witness),
+            is_deposit: witness.is_deposit,
+            block_number: witness.diff_tree_inclusion_proof.0.block_number,
+            assets: witness.assets.clone(),
+        })
+        .collect()
+}
+
+/// The assets held by a user including the received assets which have not been merged yet.
+#[derive(Clone, Debug, Default)]
+pub struct UnmergedAssetsSummary {
+    pub assets: Assets<F>,
+    /// merge keys which appear more than once in `rest_received_assets`
+    pub duplicated_merge_keys: Vec<MergeKey<F>>,
+    /// merge keys in `rest_received_assets` which were already merged into the asset tree
+    pub already_merged_merge_keys: Vec<MergeKey<F>>,
+    /// transactions in `rest_received_assets` which were canceled by the sender
+    pub canceled_tx_hashes: Vec<TxHash<F>>,
+}
+
+/// Calculate the assets after merging `rest_received_assets` without changing `user_state`.
+/// Unlike `calc_merge_witnesses`, neither the assets nor the asset tree are updated.
+pub fn calc_unmerged_assets<
+    D: NodeData<WrappedHashOut<F>, WrappedHashOut<F>, WrappedHashOut<F>> + Clone,
+    R: RootData<WrappedHashOut<F>> + Clone,
+>(
+    user_state: &UserState<D, R>,
+) -> UnmergedAssetsSummary {
+    calc_partially_merged_assets(user_state, user_state.rest_received_assets.len())
+}
+
+/// The same as `calc_unmerged_assets`, but only the first `n_merged` received assets are counted.
+pub fn calc_partially_merged_assets<
+    D: NodeData<WrappedHashOut<F>, WrappedHashOut<F>, WrappedHashOut<F>> + Clone,
+    R: RootData<WrappedHashOut<F>> + Clone,
+>(
+    user_state: &UserState<D, R>,
+    n_merged: usize,
+) -> UnmergedAssetsSummary {
+    let mut summary = UnmergedAssetsSummary {
+        assets: user_state.assets.clone(),
+        ..Default::default()
+    };
+    let mut merge_keys = HashSet::new();
+    for witness in user_state.rest_received_assets.iter().take(n_merged) {
+        let merge_key = calc_merge_key(witness);
+
+        if is_canceled_transaction(witness) {
+            summary
+                .canceled_tx_hashes
+                .push(TxHash(witness.diff_tree_inclusion_proof.1.value));
+            continue;
+        }
+
+        if !merge_keys.insert(merge_key) {
+            summary.duplicated_merge_keys.push(merge_key);
+            continue;
+        }
+
+        if is_already_merged(user_state, merge_key) {
+            summary.already_merged_merge_keys.push(merge_key);
+            continue;
+        }
 
-    match simple_signature_circuit.verify(received_signature.clone()) {
-        Ok(()) => {}
-        Err(x) => println!("{}", x),
+        for asset in witness.assets.iter() {
+            summary.assets.add(asset.kind, asset.amount, merge_key);
+        }
     }
 
-    received_signature
+    summary
 }
 
 pub async fn calc_merge_witnesses<
@@ -1472,36 +2442,18 @@ pub async fn calc_merge_witnesses<
         // let pseudo_tx_hash = HashOut::ZERO;
         let tx_hash = witness.diff_tree_inclusion_proof.1.value;
         let asset_root = witness.diff_tree_inclusion_proof.2.value;
-
-        let block_hash = get_block_hash(&witness.diff_tree_inclusion_proof.0);
-        let merge_key = if witness.is_deposit {
-            PoseidonHash::two_to_one(*tx_hash, block_hash).into()
-        } else {
-            tx_hash
-        };
+        let merge_key = calc_merge_key(&witness);
 
         // Transactions cancelled by the sender cannot be accepted.
-        {
-            let is_valid_confirmed_block_number =
-                witness.latest_account_tree_inclusion_proof.value.to_u32()
-                    == witness.diff_tree_inclusion_proof.0.block_number;
-            if !witness.is_deposit && !is_valid_confirmed_block_number {
-                println!("The following transaction was canceled: {}", tx_hash);
-                continue;
-            }
+        if is_canceled_transaction(&witness) {
+            progress!("The following transaction was canceled: {}", tx_hash);
+            continue;
         }
 
         // The same transaction cannot be merged twice.
-        {
-            let asset_tree = PoseidonSparseMerkleTree::new(
-                user_state.asset_tree.nodes_db.clone(),
-                user_state.asset_tree.roots_db.clone(),
-            );
-            let old_asset_root_with_merge_key = asset_tree.get(&merge_key).unwrap();
-            if old_asset_root_with_merge_key != Default::default() {
-                println!("The following transaction has already merged: {}", tx_hash);
-                continue;
-            }
+        if is_already_merged(user_state, merge_key) {
+            progress!("The following transaction has already merged: {}", tx_hash);
+            continue;
         }
 
         for asset in witness.assets {
@@ -1509,7 +2461,7 @@ pub async fn calc_merge_witnesses<
             user_state
                 .asset_tree
                 .set(
-                    merge_key,
+                    merge_key.0,
                     asset.kind.contract_address.to_hash_out().into(),
                     asset.kind.variable_index.to_hash_out().into(),
                     HashOut::from_partial(&[F::from_canonical_u64(asset.amount)]).into(),
@@ -1519,7 +2471,7 @@ pub async fn calc_merge_witnesses<
 
         // Verify that asset_root is calculated from witness.assets.
         assert_eq!(
-            user_state.asset_tree.get_asset_root(&merge_key).unwrap(),
+            user_state.asset_tree.get_asset_root(&merge_key.0).unwrap(),
             asset_root
         ); // XXX
 
@@ -1530,19 +2482,19 @@ pub async fn calc_merge_witnesses<
                 RootDataTmp::from(user_state.asset_tree.get_root().unwrap()),
             );
             let asset_root_with_merge_key = asset_tree
-                .set(merge_key, Default::default())
+                .set(merge_key.0, Default::default())
                 .unwrap()
                 .old_value;
 
             if cfg!(debug_assertion) {
                 assert_eq!(
                     *asset_root_with_merge_key,
-                    PoseidonHash::two_to_one(*asset_root, *merge_key)
+                    PoseidonHash::two_to_one(*asset_root, *merge_key.0)
                 );
             }
 
             asset_tree
-                .set(merge_key, asset_root_with_merge_key)
+                .set(merge_key.0, asset_root_with_merge_key)
                 .unwrap()
         };
         // dbg!(&merge_process_proof);
@@ -1559,3 +2511,80 @@ pub async fn calc_merge_witnesses<
 
     merge_witnesses
 }
+
+/// Returns the runs of consecutive block numbers from `from` to `to` which are not cached.
+fn missing_block_ranges(
+    from: u32,
+    to: u32,
+    is_cached: impl Fn(u32) -> bool,
+) -> Vec<RangeInclusive<u32>> {
+    let mut ranges = vec![];
+    let mut start = None;
+    for block_number in from..=to {
+        if is_cached(block_number) {
+            if let Some(start) = start.take() {
+                ranges.push(start..=block_number - 1);
+            }
+        } else if start.is_none() {
+            start = Some(block_number);
+        }
+    }
+    if let Some(start) = start {
+        ranges.push(start..=to);
+    }
+
+    ranges
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::test_utils::MockAggregator;
+
+    fn assert_send_sync<T: Send + Sync>(_: &T) {}
+
+    fn assert_send<T: Send>(_: &T) {}
+
+    #[tokio::test]
+    async fn test_concurrent_requests() {
+        let aggregator = MockAggregator::spawn_health_check_only();
+        let service = ServiceBuilder::new(&aggregator.url);
+        let cloned_service = service.clone();
+        assert_send_sync(&service);
+        assert_send(&service.supports(Capability::TransactionProof));
+
+        let (supported, cloned_supported, other_supported) = tokio::join!(
+            service.supports(Capability::TransactionProof),
+            cloned_service.supports(Capability::TransactionProof),
+            cloned_service.supports(Capability::ReceivedAssets),
+        );
+        assert!(!supported.unwrap());
+        assert!(!cloned_supported.unwrap());
+        assert!(!other_supported.unwrap());
+
+        // The answers are cached and shared by the clones.
+        let num_requests_before = aggregator.requests().len();
+        assert!(!service.supports(Capability::ReceivedAssets).await.unwrap());
+        let error = cloned_service
+            .ensure_capability(Capability::TransactionProof)
+            .await
+            .unwrap_err();
+        assert!(error
+            .to_string()
+            .contains("the aggregator v0.5.0 does not support transaction proofs"));
+        assert_eq!(aggregator.requests().len(), num_requests_before);
+    }
+
+    #[test]
+    fn test_missing_block_ranges() {
+        let cached = [3, 4, 7];
+        let is_cached = |block_number: u32| cached.contains(&block_number);
+        assert_eq!(
+            missing_block_ranges(1, 9, is_cached),
+            vec![1..=2, 5..=6, 8..=9]
+        );
+        assert_eq!(missing_block_ranges(3, 4, is_cached), vec![]);
+        assert_eq!(missing_block_ranges(4, 5, is_cached), vec![5..=5]);
+        assert_eq!(missing_block_ranges(5, 4, is_cached), vec![]);
+    }
+}
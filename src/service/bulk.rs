@@ -0,0 +1,224 @@
+use std::{
+    fs::File,
+    io::Write,
+    path::{Path, PathBuf},
+};
+
+use anyhow::Context;
+use intmax_rollup_interface::intmax_zkp_core::{
+    plonky2::plonk::config::{GenericConfig, PoseidonGoldilocksConfig},
+    transaction::asset::ContributedAsset,
+    zkdsa::account::Address,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::utils::{
+    key_management::types::TxHash,
+    loader::{read_json_file, LocalFileKind},
+};
+
+const D: usize = 2;
+type C = PoseidonGoldilocksConfig;
+type F = <C as GenericConfig<D>>::F;
+
+/// What happened to the assets for a recipient of `tx bulk-mint` or `tx bulk-transfer`.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "status", rename_all = "snake_case")]
+pub enum RecipientOutcome {
+    /// `tx_hash` is `None` if the assets were minted to the sender itself.
+    Delivered { tx_hash: Option<TxHash<F>> },
+    /// The transaction was sent, but the block including it has not been seen yet.
+    Pending { tx_hash: TxHash<F> },
+    /// `need_deposit` is `false` if the tokens have already been minted to the sender,
+    /// so that resuming does not mint them twice.
+    Failed { reason: String, need_deposit: bool },
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct RecipientReport {
+    pub asset: ContributedAsset<F>,
+    #[serde(flatten)]
+    pub outcome: RecipientOutcome,
+}
+
+/// The per-recipient outcome of `tx bulk-mint` or `tx bulk-transfer`,
+/// which is written into a file so that the failed recipients can be resumed.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct BulkReport {
+    pub sender_address: Address<F>,
+    pub recipients: Vec<RecipientReport>,
+}
+
+impl BulkReport {
+    pub fn read_from_file(report_file_path: &Path) -> anyhow::Result<Self> {
+        read_json_file(LocalFileKind::BulkReport, report_file_path, None)
+    }
+
+    pub fn write_to_file(&self, report_file_path: &Path) -> anyhow::Result<()> {
+        let mut file = File::create(report_file_path)
+            .with_context(|| format!("fail to create {}", report_file_path.display()))?;
+        write!(file, "{}", serde_json::to_string_pretty(self)?)?;
+        file.flush()?;
+
+        Ok(())
+    }
+
+    /// Returns the failed assets grouped by whether they still need to be minted.
+    pub fn failed_assets(&self) -> (Vec<ContributedAsset<F>>, Vec<ContributedAsset<F>>) {
+        let mut deposit_list = vec![];
+        let mut transfer_list = vec![];
+        for recipient in self.recipients.iter() {
+            if let RecipientOutcome::Failed { need_deposit, .. } = recipient.outcome {
+                if need_deposit {
+                    deposit_list.push(recipient.asset.clone());
+                } else {
+                    transfer_list.push(recipient.asset.clone());
+                }
+            }
+        }
+
+        (deposit_list, transfer_list)
+    }
+
+    /// Replace the outcomes of the same recipients and tokens with the given ones.
+    pub fn update(&mut self, recipients: Vec<RecipientReport>) {
+        for new_recipient in recipients {
+            let old_recipient = self.recipients.iter_mut().find(|recipient| {
+                recipient.asset.receiver_address == new_recipient.asset.receiver_address
+                    && recipient.asset.kind == new_recipient.asset.kind
+            });
+            if let Some(old_recipient) = old_recipient {
+                *old_recipient = new_recipient;
+            } else {
+                self.recipients.push(new_recipient);
+            }
+        }
+    }
+
+    /// The report is written next to the CSV file by default, e.g., `list.report.json` for `list.csv`.
+    pub fn default_file_path(csv_path: &Path) -> PathBuf {
+        csv_path.with_extension("report.json")
+    }
+
+    /// Write the report and display it. Fails if some recipients failed.
+    pub fn save(&self, report_file_path: &Path) -> anyhow::Result<()> {
+        self.write_to_file(report_file_path)?;
+        self.print();
+        println!("the report was written into {}", report_file_path.display());
+
+        let n_failed = self.count_failed();
+        if n_failed != 0 {
+            anyhow::bail!(
+                "{n_failed} of {} recipients failed. Resume them with `tx bulk-status --report {} --resume`",
+                self.recipients.len(),
+                report_file_path.display()
+            );
+        }
+
+        Ok(())
+    }
+
+    pub fn count_failed(&self) -> usize {
+        self.recipients
+            .iter()
+            .filter(|recipient| matches!(recipient.outcome, RecipientOutcome::Failed { .. }))
+            .count()
+    }
+
+    pub fn print(&self) {
+        let separator =
+            "--------------------------------------------------------------------------------------";
+        println!("{separator}");
+        println!("  Recipient          | Token Address      | Token ID | Amount   | Status");
+        println!("{separator}");
+        for recipient in self.recipients.iter() {
+            let status = match &recipient.outcome {
+                RecipientOutcome::Delivered {
+                    tx_hash: Some(tx_hash),
+                } => {
+                    format!("delivered ({tx_hash})")
+                }
+                RecipientOutcome::Delivered { tx_hash: None } => "delivered".to_string(),
+                RecipientOutcome::Pending { tx_hash } => format!("pending ({tx_hash})"),
+                RecipientOutcome::Failed { reason, .. } => format!("failed: {reason}"),
+            };
+            println!(
+                "  {} | {} | {:<8} | {:<8} | {status}",
+                recipient.asset.receiver_address,
+                recipient.asset.kind.contract_address,
+                recipient.asset.kind.variable_index.to_string(),
+                recipient.asset.amount,
+            );
+        }
+        println!("{separator}");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use intmax_rollup_interface::intmax_zkp_core::{
+        sparse_merkle_tree::goldilocks_poseidon::WrappedHashOut, transaction::asset::TokenKind,
+    };
+
+    use super::*;
+
+    #[test]
+    fn test_bulk_report() {
+        let sender_address = Address::from_str("0x714bdc6f38947e6d").unwrap();
+        let make_asset = |receiver_address: &str| ContributedAsset {
+            receiver_address: Address::from_str(receiver_address).unwrap(),
+            kind: TokenKind {
+                contract_address: sender_address,
+                variable_index: 0u8.into(),
+            },
+            amount: 10,
+        };
+        let tx_hash = TxHash(WrappedHashOut::rand());
+
+        let mut report = BulkReport {
+            sender_address,
+            recipients: vec![
+                RecipientReport {
+                    asset: make_asset("0x0000000000000001"),
+                    outcome: RecipientOutcome::Delivered {
+                        tx_hash: Some(tx_hash),
+                    },
+                },
+                RecipientReport {
+                    asset: make_asset("0x0000000000000002"),
+                    outcome: RecipientOutcome::Failed {
+                        reason: "timeout".to_string(),
+                        need_deposit: false,
+                    },
+                },
+            ],
+        };
+
+        let encoded_report = serde_json::to_value(&report).unwrap();
+        assert_eq!(encoded_report["recipients"][1]["status"], "failed");
+        let decoded_report: BulkReport = serde_json::from_value(encoded_report.clone()).unwrap();
+        assert_eq!(
+            serde_json::to_value(&decoded_report).unwrap(),
+            encoded_report
+        );
+
+        // Only the failed recipients are resumed.
+        let (deposit_list, transfer_list) = report.failed_assets();
+        assert!(deposit_list.is_empty());
+        assert_eq!(transfer_list.len(), 1);
+        assert_eq!(
+            transfer_list[0].receiver_address,
+            Address::from_str("0x0000000000000002").unwrap()
+        );
+        assert_eq!(report.count_failed(), 1);
+
+        report.update(vec![RecipientReport {
+            asset: make_asset("0x0000000000000002"),
+            outcome: RecipientOutcome::Pending { tx_hash },
+        }]);
+        assert_eq!(report.recipients.len(), 2);
+        assert_eq!(report.count_failed(), 0);
+    }
+}
@@ -0,0 +1,136 @@
+//! The optional features of the aggregator, which some commands depend on.
+//! An aggregator may list them in `features` of the health check response.
+//! Otherwise, the endpoint is probed without parameters: a missing endpoint responds 404,
+//! while an existing one rejects the request with another status.
+//! The answers are cached in `ServiceBuilder`, which is bound to one aggregator.
+
+use std::{collections::HashMap, fmt::Display};
+
+use serde::{Deserialize, Serialize};
+
+use super::response::ValidateResponse;
+use crate::utils::version::Version;
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum Capability {
+    /// `account notify`
+    ReceivedAssets,
+    /// `account transaction-proof` and the `io` commands unlocking offers
+    TransactionProof,
+    TransactionConfirmationWitness,
+}
+
+impl Capability {
+    /// The name listed in `features` of the health check response.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::ReceivedAssets => "asset-received",
+            Self::TransactionProof => "transaction-proof",
+            Self::TransactionConfirmationWitness => "transaction-confirmation-witness",
+        }
+    }
+
+    pub fn api_path(&self) -> &'static str {
+        match self {
+            Self::ReceivedAssets => "/asset/received",
+            Self::TransactionProof => "/account/transaction-proof",
+            Self::TransactionConfirmationWitness => "/tx/confirmation/witness",
+        }
+    }
+
+    /// The first aggregator version which provides the capability.
+    pub fn min_aggregator_version(&self) -> Version {
+        let patch = match self {
+            Self::ReceivedAssets => 2,
+            Self::TransactionProof | Self::TransactionConfirmationWitness => 1,
+        };
+
+        Version {
+            major: 0,
+            minor: 5,
+            patch,
+        }
+    }
+}
+
+impl Display for Capability {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::ReceivedAssets => write!(f, "polling received assets"),
+            Self::TransactionProof => write!(f, "transaction proofs"),
+            Self::TransactionConfirmationWitness => write!(f, "transaction confirmation witnesses"),
+        }
+    }
+}
+
+/// The part of the health check response describing the aggregator.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AdvertisedCapabilities {
+    pub version: String,
+    /// `None` if the aggregator does not list its features.
+    #[serde(default)]
+    pub features: Option<Vec<String>>,
+}
+
+impl ValidateResponse for AdvertisedCapabilities {}
+
+impl AdvertisedCapabilities {
+    /// Returns `None` if it is not advertised, in which case the endpoint needs to be probed.
+    pub fn supports(&self, capability: Capability) -> Option<bool> {
+        self.features
+            .as_ref()
+            .map(|features| features.iter().any(|feature| feature == capability.name()))
+    }
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct CapabilityCache {
+    pub advertised: Option<AdvertisedCapabilities>,
+    pub probed: HashMap<Capability, bool>,
+}
+
+/// Whether the endpoint exists, judging from the status of the probe.
+pub fn is_supported_by_probe(status: u16) -> bool {
+    status != 404
+}
+
+pub fn unsupported_capability_error(
+    capability: Capability,
+    aggregator_version: &str,
+) -> anyhow::Error {
+    anyhow::anyhow!(
+        "the aggregator {aggregator_version} does not support {capability} (requires aggregator >= {}). Use another aggregator with `intmax config aggregator-url`, or ask its operator to update it.",
+        capability.min_aggregator_version()
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_advertised_capabilities() {
+        let advertised: AdvertisedCapabilities =
+            serde_json::from_str(r#"{"name":"intmax rollup aggregator","version":"v0.5.0"}"#)
+                .unwrap();
+        assert_eq!(advertised.supports(Capability::TransactionProof), None);
+
+        let advertised: AdvertisedCapabilities = serde_json::from_str(
+            r#"{"version":"v0.5.2","features":["asset-received","transaction-proof"]}"#,
+        )
+        .unwrap();
+        assert_eq!(advertised.supports(Capability::ReceivedAssets), Some(true));
+        assert_eq!(
+            advertised.supports(Capability::TransactionConfirmationWitness),
+            Some(false)
+        );
+
+        assert!(!is_supported_by_probe(404));
+        assert!(is_supported_by_probe(400));
+
+        let error = unsupported_capability_error(Capability::TransactionProof, "v0.5.0");
+        assert!(error
+            .to_string()
+            .contains("does not support transaction proofs (requires aggregator >= v0.5.1)"));
+    }
+}
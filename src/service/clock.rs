@@ -0,0 +1,71 @@
+use crate::utils::key_management::stats::parse_date;
+
+/// The difference from the clock of the aggregator over which a warning is displayed, in seconds.
+/// Proposal blocks must be signed within a short window, which a skewed clock may miss.
+pub const MAX_CLOCK_SKEW: u64 = 30;
+
+const MONTH_NAMES: [&str; 12] = [
+    "Jan", "Feb", "Mar", "Apr", "May", "Jun", "Jul", "Aug", "Sep", "Oct", "Nov", "Dec",
+];
+
+/// Parse the `Date` header of an HTTP response, e.g., `Sun, 06 Nov 1994 08:49:37 GMT`, as UNIX time in seconds.
+pub fn parse_http_date(value: &str) -> Option<u64> {
+    let parts = value.split_whitespace().collect::<Vec<_>>();
+    if parts.len() != 6 || parts[5] != "GMT" {
+        return None;
+    }
+
+    let day = parts[1].parse::<u32>().ok()?;
+    let month = MONTH_NAMES.iter().position(|name| *name == parts[2])? + 1;
+    let year = parts[3].parse::<u32>().ok()?;
+    let date = parse_date(&format!("{year:04}-{month:02}-{day:02}")).ok()?;
+
+    let time = parts[4]
+        .split(':')
+        .map(|part| part.parse::<u64>().ok())
+        .collect::<Option<Vec<_>>>()?;
+    if time.len() != 3 || time[0] > 23 || time[1] > 59 || time[2] > 60 {
+        return None;
+    }
+
+    Some(date + time[0] * 3600 + time[1] * 60 + time[2])
+}
+
+/// Returns the warning if the local clock differs from the clock of the aggregator by more than `MAX_CLOCK_SKEW`.
+pub fn clock_skew_warning(local_time: u64, server_time: u64) -> Option<String> {
+    let (skew, direction) = if local_time >= server_time {
+        (local_time - server_time, "ahead of")
+    } else {
+        (server_time - local_time, "behind")
+    };
+    if skew <= MAX_CLOCK_SKEW {
+        return None;
+    }
+
+    Some(format!(
+        "the local clock is {skew} seconds {direction} the aggregator. Proposal blocks may not be signed in time. Synchronize the clock, e.g., with NTP"
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_clock_skew() {
+        assert_eq!(
+            parse_http_date("Sun, 06 Nov 1994 08:49:37 GMT"),
+            Some(784111777)
+        );
+        assert_eq!(parse_http_date("Sun, 06 Foo 1994 08:49:37 GMT"), None);
+        assert_eq!(parse_http_date("Sunday, 06-Nov-94 08:49:37 GMT"), None);
+
+        assert_eq!(clock_skew_warning(1000, 1000 + MAX_CLOCK_SKEW), None);
+        assert!(clock_skew_warning(1000, 1001 + MAX_CLOCK_SKEW)
+            .unwrap()
+            .contains("behind"));
+        assert!(clock_skew_warning(1001 + MAX_CLOCK_SKEW, 1000)
+            .unwrap()
+            .contains("ahead of"));
+    }
+}
@@ -0,0 +1,189 @@
+use std::{
+    fs::{File, OpenOptions},
+    io::{BufReader, Read, Seek, SeekFrom, Write},
+    path::PathBuf,
+    str::FromStr,
+};
+
+use anyhow::Context;
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+
+pub const JSON_CONTENT_TYPE: &str = "application/json";
+pub const MESSAGE_PACK_CONTENT_TYPE: &str = "application/msgpack";
+
+/// The encoding of the payloads of heavy endpoints, e.g., `/tx/send`, `/block/detail` and `/asset/received`.
+/// Other endpoints always use JSON.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum WireFormat {
+    #[default]
+    Json,
+    /// MessagePack is used only if the aggregator supports it. Otherwise, JSON is used.
+    MsgPack,
+}
+
+impl FromStr for WireFormat {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "json" => Ok(Self::Json),
+            "msgpack" => Ok(Self::MsgPack),
+            _ => anyhow::bail!("unknown wire format: {s}"),
+        }
+    }
+}
+
+impl WireFormat {
+    pub fn content_type(&self) -> &'static str {
+        match self {
+            Self::Json => JSON_CONTENT_TYPE,
+            Self::MsgPack => MESSAGE_PACK_CONTENT_TYPE,
+        }
+    }
+
+    /// The `Accept` header, which prefers this format and falls back to JSON.
+    pub fn accept(&self) -> &'static str {
+        match self {
+            Self::Json => JSON_CONTENT_TYPE,
+            Self::MsgPack => "application/msgpack, application/json;q=0.9",
+        }
+    }
+
+    /// Returns the format of the response body from its `Content-Type` header.
+    /// If it is missing or unknown, the body is regarded as JSON.
+    pub fn from_content_type(content_type: Option<&str>) -> Self {
+        let mime_type = content_type
+            .and_then(|content_type| content_type.split(';').next())
+            .map(|mime_type| mime_type.trim());
+        match mime_type {
+            Some(MESSAGE_PACK_CONTENT_TYPE) | Some("application/x-msgpack") => Self::MsgPack,
+            _ => Self::Json,
+        }
+    }
+
+    pub fn encode<T: Serialize>(&self, value: &T) -> anyhow::Result<Vec<u8>> {
+        let encoded_value = match self {
+            Self::Json => serde_json::to_vec(value)?,
+            Self::MsgPack => rmp_serde::to_vec_named(value)?,
+        };
+
+        Ok(encoded_value)
+    }
+
+    pub fn decode<T: DeserializeOwned>(&self, encoded_value: &[u8]) -> anyhow::Result<T> {
+        let value = match self {
+            Self::Json => serde_json::from_slice(encoded_value)?,
+            Self::MsgPack => rmp_serde::from_slice(encoded_value)?,
+        };
+
+        Ok(value)
+    }
+
+    /// Decode a value from the reader without reading all of it into a buffer first.
+    /// The decoded value itself is still built in memory as a whole.
+    pub fn decode_from_reader<T: DeserializeOwned, R: Read>(&self, reader: R) -> anyhow::Result<T> {
+        let value = match self {
+            Self::Json => serde_json::from_reader(reader)?,
+            Self::MsgPack => rmp_serde::from_read(reader)?,
+        };
+
+        Ok(value)
+    }
+}
+
+/// A response body written into a temporary file chunk by chunk as it arrives,
+/// so that a large body, e.g., of `/block/detail`, is decoded without buffering the raw body in memory.
+/// This does not stream the lists in the body: the decoded response is held in memory as a whole.
+/// The file is removed when this is dropped.
+pub struct SpooledBody {
+    path: PathBuf,
+    file: File,
+    size: usize,
+}
+
+impl SpooledBody {
+    pub fn new() -> anyhow::Result<Self> {
+        let path = std::env::temp_dir().join(format!(
+            "intmax-body-{}-{:016x}",
+            std::process::id(),
+            rand::random::<u64>()
+        ));
+        let file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create_new(true)
+            .open(&path)
+            .with_context(|| format!("fail to create {}", path.display()))?;
+
+        Ok(Self {
+            path,
+            file,
+            size: 0,
+        })
+    }
+
+    pub fn write_chunk(&mut self, chunk: &[u8]) -> anyhow::Result<()> {
+        self.file.write_all(chunk)?;
+        self.size += chunk.len();
+
+        Ok(())
+    }
+
+    /// the number of bytes written so far
+    pub fn size(&self) -> usize {
+        self.size
+    }
+
+    pub fn decode<T: DeserializeOwned>(mut self, wire_format: WireFormat) -> anyhow::Result<T> {
+        self.file.flush()?;
+        self.file.seek(SeekFrom::Start(0))?;
+
+        wire_format.decode_from_reader(BufReader::new(&self.file))
+    }
+}
+
+impl Drop for SpooledBody {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_file(&self.path);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_wire_format() {
+        assert_eq!(
+            WireFormat::from_content_type(Some("application/msgpack")),
+            WireFormat::MsgPack
+        );
+        assert_eq!(
+            WireFormat::from_content_type(Some("application/json; charset=utf-8")),
+            WireFormat::Json
+        );
+        assert_eq!(WireFormat::from_content_type(None), WireFormat::Json);
+
+        let value = serde_json::json!({ "tx_hash": "0x01", "block_number": 3 });
+        for wire_format in [WireFormat::Json, WireFormat::MsgPack] {
+            let encoded_value = wire_format.encode(&value).unwrap();
+            let decoded_value: serde_json::Value = wire_format.decode(&encoded_value).unwrap();
+            assert_eq!(decoded_value, value);
+            let decoded_value: serde_json::Value =
+                wire_format.decode_from_reader(&encoded_value[..]).unwrap();
+            assert_eq!(decoded_value, value);
+
+            let mut body = SpooledBody::new().unwrap();
+            let path = body.path.clone();
+            for chunk in encoded_value.chunks(5) {
+                body.write_chunk(chunk).unwrap();
+            }
+            assert_eq!(body.size(), encoded_value.len());
+            let decoded_value: serde_json::Value = body.decode(wire_format).unwrap();
+            assert_eq!(decoded_value, value);
+            // The temporary file is removed.
+            assert!(!path.exists());
+        }
+    }
+}
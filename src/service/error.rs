@@ -0,0 +1,216 @@
+use intmax_rollup_interface::intmax_zkp_core::plonky2::plonk::config::{
+    GenericConfig, PoseidonGoldilocksConfig,
+};
+use serde::Deserialize;
+
+use crate::utils::key_management::types::TxHash;
+
+const D: usize = 2;
+type C = PoseidonGoldilocksConfig;
+type F = <C as GenericConfig<D>>::F;
+
+/// The error body returned by the aggregator, e.g., `{"code": "...", "message": "..."}`.
+#[derive(Clone, Debug, Deserialize)]
+struct RawAggregatorError {
+    #[serde(default)]
+    code: Option<serde_json::Value>,
+    #[serde(default, alias = "error")]
+    message: Option<String>,
+}
+
+/// The rejection of a request by the aggregator.
+#[derive(Clone)]
+pub struct AggregatorError {
+    /// the API path at which the request was rejected
+    pub api_path: String,
+    pub status: u16,
+    pub code: Option<String>,
+    pub message: String,
+    pub tx_hash: Option<TxHash<F>>,
+}
+
+impl AggregatorError {
+    /// Parse the response body. If it is not structured, the whole body is used as the message.
+    pub fn new(api_path: &str, status: u16, body: &str) -> Self {
+        let (code, message) = match serde_json::from_str::<RawAggregatorError>(body) {
+            Ok(RawAggregatorError {
+                code,
+                message: Some(message),
+            }) => {
+                let code = code.map(|code| match code {
+                    serde_json::Value::String(code) => code,
+                    code => code.to_string(),
+                });
+
+                (code, message)
+            }
+            _ => (None, body.to_string()),
+        };
+
+        Self {
+            api_path: api_path.to_string(),
+            status,
+            code,
+            message,
+            tx_hash: None,
+        }
+    }
+
+    pub fn with_tx_hash(mut self, tx_hash: TxHash<F>) -> Self {
+        self.tx_hash = Some(tx_hash);
+
+        self
+    }
+
+    /// Returns what the user can do to resolve the error, if known.
+    pub fn hint(&self) -> Option<&'static str> {
+        let code = self.code.clone().unwrap_or_default().to_lowercase();
+        let message = self.message.to_lowercase();
+        let contains = |pattern: &str| code.contains(pattern) || message.contains(pattern);
+
+        if (502..=504).contains(&self.status) {
+            Some("The aggregator is temporarily unavailable. Check whether the request was applied, e.g., with `account assets`, before running the command again.")
+        } else if self.status == 413 || contains("too large") || contains("too many") {
+            Some("The transaction is too large. Reduce the number of recipients and token kinds, or merge your assets with `tx merge` before sending.")
+        } else if contains("signature")
+            && (contains("window")
+                || contains("closed")
+                || contains("deadline")
+                || contains("expired"))
+        {
+            Some("The signing window of the proposal block was closed. The transaction will be reverted, so send it again.")
+        } else if contains("already") {
+            Some("The transaction was already accepted. Check your assets with `account assets` before sending it again.")
+        } else if contains("proof") || contains("verif") {
+            Some("The aggregator rejected the proof. Synchronizing this CLI to the latest version may solve the problem.")
+        } else {
+            None
+        }
+    }
+}
+
+impl std::fmt::Display for AggregatorError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "unexpected response from {}: {}",
+            self.api_path, self.message
+        )?;
+
+        let mut details = vec![format!("status: {}", self.status)];
+        if let Some(code) = &self.code {
+            details.push(format!("code: {code}"));
+        }
+        if let Some(tx_hash) = &self.tx_hash {
+            details.push(format!("tx hash: {tx_hash}"));
+        }
+        write!(f, " ({})", details.join(", "))?;
+
+        if let Some(hint) = self.hint() {
+            write!(f, "\nhint: {hint}")?;
+        }
+
+        Ok(())
+    }
+}
+
+// `main` reports errors with `Debug`, so it is formatted the same as `Display`.
+impl std::fmt::Debug for AggregatorError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        std::fmt::Display::fmt(self, f)
+    }
+}
+
+impl std::error::Error for AggregatorError {}
+
+/// The aggregator responded with a body which cannot be decoded or breaks the invariants of the response.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct InvalidResponse {
+    /// the API path which responded the body
+    pub api_path: String,
+    pub reason: String,
+}
+
+impl InvalidResponse {
+    pub fn new(api_path: &str, reason: impl Into<String>) -> Self {
+        Self {
+            api_path: api_path.to_string(),
+            reason: reason.into(),
+        }
+    }
+}
+
+impl std::fmt::Display for InvalidResponse {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "invalid response from {}: {}",
+            self.api_path, self.reason
+        )
+    }
+}
+
+impl std::error::Error for InvalidResponse {}
+
+/// Nothing had to be done, e.g., there were no received assets to merge.
+/// The command exits with `NothingToDo::EXIT_CODE` so that scripts can tell it from a failure.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct NothingToDo;
+
+impl NothingToDo {
+    pub const EXIT_CODE: i32 = 3;
+}
+
+impl std::fmt::Display for NothingToDo {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "nothing to do")
+    }
+}
+
+impl std::error::Error for NothingToDo {}
+
+/// The assets to send are split into more fragments than a transaction can consume.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct TooManyFragments;
+
+impl std::fmt::Display for TooManyFragments {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "too many fragments of assets")
+    }
+}
+
+impl std::error::Error for TooManyFragments {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_aggregator_error() {
+        let error = AggregatorError::new(
+            "/tx/send",
+            400,
+            r#"{"code":"TX_TOO_LARGE","message":"too many diffs"}"#,
+        );
+        assert_eq!(error.code, Some("TX_TOO_LARGE".to_string()));
+        assert_eq!(error.message, "too many diffs");
+        assert!(error.hint().is_some());
+
+        let error = AggregatorError::new("/tx/send", 400, "Validation error: invalid nonce");
+        assert_eq!(error.code, None);
+        assert_eq!(error.message, "Validation error: invalid nonce");
+        assert!(error
+            .to_string()
+            .starts_with("unexpected response from /tx/send: Validation error: invalid nonce"));
+    }
+
+    #[test]
+    fn test_nothing_to_do() {
+        let error = anyhow::Error::from(NothingToDo).context("fail to merge");
+        assert!(error.is::<NothingToDo>());
+        assert_eq!(error.root_cause().to_string(), "nothing to do");
+
+        let error = anyhow::anyhow!("nothing to do");
+        assert!(!error.is::<NothingToDo>());
+    }
+}
@@ -0,0 +1,243 @@
+//! The documented errors displayed by `intmax explain <code>`.
+
+use super::error::{AggregatorError, InvalidResponse, NothingToDo, TooManyFragments};
+use crate::utils::loader::{LocalFileError, LocalFileKind};
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct ErrorCode {
+    pub code: &'static str,
+    pub summary: &'static str,
+    pub description: &'static str,
+    pub causes: &'static [&'static str],
+    /// commands or actions which resolve the error, in the order to try them
+    pub recovery: &'static [&'static str],
+}
+
+pub const EAGG_502: ErrorCode = ErrorCode {
+    code: "EAGG_502",
+    summary: "The aggregator is temporarily unavailable.",
+    description: "The aggregator or the proxy in front of it responded with 502, 503 or 504. The request may or may not have been processed.",
+    causes: &[
+        "The aggregator is restarting or overloaded.",
+        "A heavy request, e.g., /block/approve, took longer than the timeout of the proxy.",
+    ],
+    recovery: &[
+        "Wait a minute, then run the same command again.",
+        "intmax account assets   # check whether the transaction was applied before sending it again",
+        "intmax config aggregator-url   # check that the aggregator URL is correct",
+    ],
+};
+
+pub const EAGG_REJECTED: ErrorCode = ErrorCode {
+    code: "EAGG_REJECTED",
+    summary: "The aggregator rejected the request.",
+    description: "The aggregator responded with an error. The hint printed with the error describes the known cases.",
+    causes: &[
+        "This CLI is older than the aggregator.",
+        "The transaction was already accepted, or the signing window of its block was closed.",
+    ],
+    recovery: &[
+        "intmax --version   # check the aggregator versions supported by this CLI",
+        "intmax account assets",
+        "Send the transaction again if it was reverted.",
+    ],
+};
+
+pub const EAGG_INVALID_RESPONSE: ErrorCode = ErrorCode {
+    code: "EAGG_INVALID_RESPONSE",
+    summary: "The aggregator responded with a malformed body.",
+    description: "The response of the aggregator could not be decoded, or it was inconsistent, e.g., the blocks were out of order. Nothing was written to the wallet.",
+    causes: &[
+        "This CLI does not support the version of the aggregator.",
+        "A proxy in front of the aggregator responded with an error page.",
+    ],
+    recovery: &[
+        "intmax --version   # check the aggregator versions supported by this CLI",
+        "intmax config aggregator-url   # check that the aggregator URL is correct",
+        "Run the command again with `--debug-dump <dir>` and report the error.",
+    ],
+};
+
+pub const ETX_TOO_LARGE: ErrorCode = ErrorCode {
+    code: "ETX_TOO_LARGE",
+    summary: "The transaction is too large.",
+    description:
+        "A transaction can include a limited number of recipients, token kinds and input assets.",
+    causes: &["Too many recipients or token kinds are sent in one transaction."],
+    recovery: &[
+        "Split the recipients into several transactions, e.g., with `tx bulk-transfer`.",
+        "intmax tx merge",
+    ],
+};
+
+pub const EFRAGMENTS: ErrorCode = ErrorCode {
+    code: "EFRAGMENTS",
+    summary: "Too many asset fragments are needed for the transfer.",
+    description: "The assets to send are split into more received fragments than one transaction can consume.",
+    causes: &[
+        "Many small transfers were received and not merged.",
+        "The merge policy is `manual` or `lazy`.",
+    ],
+    recovery: &[
+        "intmax tx merge --dry-run   # see how many fragments are merged",
+        "intmax tx merge",
+        "intmax account merge-policy aggressive",
+    ],
+};
+
+pub const EWALLET_CORRUPT: ErrorCode = ErrorCode {
+    code: "EWALLET_CORRUPT",
+    summary: "The wallet file cannot be read.",
+    description: "The wallet file is broken or was edited by hand. It is never overwritten automatically, since it holds your private keys.",
+    causes: &[
+        "The disk became full or the machine stopped while the wallet was written.",
+        "The file was edited by hand or by another program.",
+    ],
+    recovery: &[
+        "Fix the line shown in the error, or restore the file from a backup.",
+        "intmax account reset --backup-first   # keep the broken file in an encrypted backup and start over",
+    ],
+};
+
+pub const ELOCAL_FILE: ErrorCode = ErrorCode {
+    code: "ELOCAL_FILE",
+    summary: "A local file written by this CLI cannot be read.",
+    description: "The config, nickname, signer or report file is broken or was edited by hand.",
+    causes: &["The file was edited by hand or by another program."],
+    recovery: &[
+        "Follow the remediation shown in the error.",
+        "intmax config edit   # for the config file",
+    ],
+};
+
+pub const ENOTHING_TO_DO: ErrorCode = ErrorCode {
+    code: "ENOTHING_TO_DO",
+    summary: "There was nothing to do.",
+    description: "The command did not change anything, e.g., there were no received assets to merge. It exits with status 3 instead of 1.",
+    causes: &["The work was already done."],
+    recovery: &["Nothing is needed. Scripts can treat the exit status 3 as success."],
+};
+
+pub const ERROR_CODES: &[ErrorCode] = &[
+    EAGG_502,
+    EAGG_REJECTED,
+    EAGG_INVALID_RESPONSE,
+    ETX_TOO_LARGE,
+    EFRAGMENTS,
+    EWALLET_CORRUPT,
+    ELOCAL_FILE,
+    ENOTHING_TO_DO,
+];
+
+/// Find the error code case-insensitively.
+pub fn find_error_code(code: &str) -> Option<&'static ErrorCode> {
+    ERROR_CODES
+        .iter()
+        .find(|error_code| error_code.code.eq_ignore_ascii_case(code))
+}
+
+/// Returns the code of a known error.
+pub fn classify_error(error: &anyhow::Error) -> Option<&'static ErrorCode> {
+    let code = error.chain().find_map(|cause| {
+        if let Some(error) = cause.downcast_ref::<AggregatorError>() {
+            return Some(match error.status {
+                502..=504 => "EAGG_502",
+                413 => "ETX_TOO_LARGE",
+                _ => "EAGG_REJECTED",
+            });
+        }
+        if cause.is::<InvalidResponse>() {
+            return Some("EAGG_INVALID_RESPONSE");
+        }
+        if let Some(error) = cause.downcast_ref::<LocalFileError>() {
+            return Some(if error.kind == LocalFileKind::Wallet {
+                "EWALLET_CORRUPT"
+            } else {
+                "ELOCAL_FILE"
+            });
+        }
+        if cause.is::<TooManyFragments>() {
+            return Some("EFRAGMENTS");
+        }
+        if cause.is::<NothingToDo>() {
+            return Some("ENOTHING_TO_DO");
+        }
+
+        None
+    })?;
+
+    find_error_code(code)
+}
+
+impl std::fmt::Display for ErrorCode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "{}: {}", self.code, self.summary)?;
+        writeln!(f)?;
+        writeln!(f, "{}", self.description)?;
+        writeln!(f)?;
+        writeln!(f, "Common causes:")?;
+        for cause in self.causes {
+            writeln!(f, "  - {cause}")?;
+        }
+        writeln!(f)?;
+        write!(f, "To recover:")?;
+        for step in self.recovery {
+            write!(f, "\n  - {step}")?;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use anyhow::Context;
+
+    use super::*;
+
+    #[test]
+    fn test_classify_error() {
+        assert_eq!(find_error_code("eagg_502"), Some(&EAGG_502));
+        assert_eq!(find_error_code("EUNKNOWN"), None);
+
+        let error = anyhow::Error::from(AggregatorError::new("/block/approve", 502, "Bad Gateway"))
+            .context("fail to approve the block");
+        assert_eq!(classify_error(&error), Some(&EAGG_502));
+
+        let error = anyhow::Error::from(AggregatorError::new("/tx/send", 400, "invalid nonce"));
+        assert_eq!(classify_error(&error), Some(&EAGG_REJECTED));
+
+        let error = anyhow::Error::from(InvalidResponse::new(
+            "/block",
+            "the blocks are out of order",
+        ));
+        assert_eq!(classify_error(&error), Some(&EAGG_INVALID_RESPONSE));
+
+        let error = Err::<(), _>(TooManyFragments)
+            .context("fail to send")
+            .unwrap_err();
+        assert_eq!(classify_error(&error), Some(&EFRAGMENTS));
+
+        let error = crate::utils::loader::decode_json_file::<serde_json::Value>(
+            LocalFileKind::Wallet,
+            Path::new("wallet"),
+            "{",
+            None,
+        )
+        .unwrap_err();
+        assert_eq!(
+            classify_error(&anyhow::Error::from(error)),
+            Some(&EWALLET_CORRUPT)
+        );
+
+        assert_eq!(classify_error(&anyhow::anyhow!("something else")), None);
+
+        // Every code is found by its name.
+        for error_code in ERROR_CODES {
+            assert_eq!(find_error_code(error_code.code), Some(error_code));
+            assert!(error_code.to_string().starts_with(error_code.code));
+        }
+    }
+}
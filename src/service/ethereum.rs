@@ -1,4 +1,10 @@
-use intmax_interoperability_plugin::ethers::types::U256;
+use std::time::{Duration, Instant};
+
+use anyhow::Context;
+use intmax_interoperability_plugin::ethers::{
+    providers::{Http, Middleware, Provider},
+    types::{U256, U64},
+};
 use intmax_rollup_interface::constants::{
     ContractConfig, POLYGON_ZKEVM_TEST_NETWORK_CONFIG, SCROLL_ALPHA_NETWORK_CONFIG,
 };
@@ -6,6 +12,7 @@ use reqwest::header::{HeaderMap, CONTENT_TYPE};
 use serde::{Deserialize, Serialize};
 
 use super::interoperability::NetworkName;
+use crate::utils::config::RpcConfig;
 
 pub fn get_network_config(network_name: NetworkName) -> ContractConfig<'static> {
     match network_name {
@@ -14,6 +21,91 @@ pub fn get_network_config(network_name: NetworkName) -> ContractConfig<'static>
     }
 }
 
+/// Returns the RPC endpoint configured in the profile.
+pub fn get_rpc_url(rpc_config: &RpcConfig, network_name: NetworkName) -> Option<&str> {
+    match network_name {
+        NetworkName::ScrollAlpha => rpc_config.scroll.as_deref(),
+        NetworkName::PolygonZkEvmTest => rpc_config.polygon.as_deref(),
+    }
+}
+
+/// Returns the network config whose RPC endpoint is replaced with `rpc_url` if it is given.
+pub fn get_network_config_with_rpc_url(
+    network_name: NetworkName,
+    rpc_url: Option<&str>,
+) -> ContractConfig<'static> {
+    let mut network_config = get_network_config(network_name);
+    if let Some(rpc_url) = rpc_url {
+        // The config is used until the command exits, so leaking the URL is harmless.
+        network_config.rpc_url = Box::leak(rpc_url.to_string().into_boxed_str());
+    }
+
+    network_config
+}
+
+#[derive(Copy, Clone, Debug)]
+pub struct RpcStatus {
+    pub chain_id: U256,
+    /// the round-trip time of the chain ID request
+    pub latency: Duration,
+    pub block_number: U64,
+    pub gas_price: U256,
+}
+
+/// Query the chain ID, the latest block number and the gas price of the RPC endpoint.
+pub async fn get_rpc_status(network_config: &ContractConfig<'static>) -> anyhow::Result<RpcStatus> {
+    let provider = Provider::<Http>::try_from(network_config.rpc_url)
+        .with_context(|| format!("invalid RPC URL: {}", network_config.rpc_url))?;
+    let start = Instant::now();
+    let chain_id = provider
+        .get_chainid()
+        .await
+        .with_context(|| format!("RPC endpoint {} is unreachable", network_config.rpc_url))?;
+    let latency = start.elapsed();
+    let block_number = provider.get_block_number().await?;
+    let gas_price = provider.get_gas_price().await?;
+
+    Ok(RpcStatus {
+        chain_id,
+        latency,
+        block_number,
+        gas_price,
+    })
+}
+
+/// Fails if the RPC endpoint is unreachable or belongs to another network,
+/// which is checked before sending any contract call.
+pub async fn check_rpc_endpoint(
+    network_name: NetworkName,
+    network_config: &ContractConfig<'static>,
+) -> anyhow::Result<RpcStatus> {
+    let status = get_rpc_status(network_config).await?;
+    if status.chain_id != network_config.chain_id.into() {
+        anyhow::bail!(
+            "RPC endpoint {} is on chain {}, but {network_name} is chain {}",
+            network_config.rpc_url,
+            status.chain_id,
+            network_config.chain_id
+        );
+    }
+
+    Ok(status)
+}
+
+/// Returns the network config with the RPC endpoint given by `--rpc-url` or the profile,
+/// after checking that the endpoint serves the network.
+pub async fn resolve_network_config(
+    network_name: NetworkName,
+    rpc_url: Option<&str>,
+    rpc_config: &RpcConfig,
+) -> anyhow::Result<ContractConfig<'static>> {
+    let rpc_url = rpc_url.or_else(|| get_rpc_url(rpc_config, network_name));
+    let network_config = get_network_config_with_rpc_url(network_name, rpc_url);
+    check_rpc_endpoint(network_name, &network_config).await?;
+
+    Ok(network_config)
+}
+
 #[derive(Copy, Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
 #[serde(
     from = "SerializableGasStationInfo",
@@ -117,6 +209,103 @@ pub async fn fetch_polygon_zkevm_test_gas_price() -> anyhow::Result<GasStationIn
     Ok(resp)
 }
 
+/// How long a fetched gas price is used before it is fetched again.
+pub const GAS_PRICE_REFRESH_INTERVAL: Duration = Duration::from_secs(60);
+
+/// The gas prices of the transactions sent by a command.
+/// The price is fetched again once it is older than the refresh interval,
+/// so commands sending transactions for a long time, e.g., `io repair`, follow the price of the network.
+#[derive(Clone, Debug)]
+pub struct GasPolicy {
+    refresh_interval: Duration,
+    fetched_price: Option<(Instant, U256)>,
+}
+
+impl Default for GasPolicy {
+    fn default() -> Self {
+        Self::new(GAS_PRICE_REFRESH_INTERVAL)
+    }
+}
+
+impl GasPolicy {
+    pub fn new(refresh_interval: Duration) -> Self {
+        Self {
+            refresh_interval,
+            fetched_price: None,
+        }
+    }
+
+    /// Returns the fetched price if it is not older than the refresh interval.
+    fn cached_price(&self, now: Instant) -> Option<U256> {
+        let (fetched_at, gas_price) = self.fetched_price?;
+        if now.saturating_duration_since(fetched_at) < self.refresh_interval {
+            Some(gas_price)
+        } else {
+            None
+        }
+    }
+
+    async fn fetch_gas_price(network_config: &ContractConfig<'static>) -> anyhow::Result<U256> {
+        // The gas station is more reliable than `eth_gasPrice` on Polygon zkEVM testnet.
+        if network_config.chain_id == POLYGON_ZKEVM_TEST_NETWORK_CONFIG.chain_id {
+            return Ok(fetch_polygon_zkevm_test_gas_price().await?.standard);
+        }
+
+        let provider = Provider::<Http>::try_from(network_config.rpc_url)
+            .with_context(|| format!("invalid RPC URL: {}", network_config.rpc_url))?;
+
+        Ok(provider.get_gas_price().await?)
+    }
+
+    /// Returns the gas price of the transaction `action`, which must not exceed `max_gas_price`.
+    /// The price is displayed, since it may differ between the transactions of a command.
+    pub async fn gas_price(
+        &mut self,
+        network_config: &ContractConfig<'static>,
+        max_gas_price: Option<U256>,
+        action: &str,
+    ) -> anyhow::Result<U256> {
+        let gas_price = if let Some(gas_price) = self.cached_price(Instant::now()) {
+            gas_price
+        } else {
+            let gas_price = Self::fetch_gas_price(network_config)
+                .await
+                .context("fail to fetch the gas price")?;
+            self.fetched_price = Some((Instant::now(), gas_price));
+
+            gas_price
+        };
+
+        if let Some(max_gas_price) = max_gas_price {
+            if gas_price > max_gas_price {
+                anyhow::bail!(
+                    "Gas prices are currently too high: {} Gwei (limit: {} Gwei)",
+                    wei_to_gwei(gas_price),
+                    wei_to_gwei(max_gas_price)
+                );
+            }
+        }
+        println!("{action}: gas price {} Gwei", wei_to_gwei(gas_price));
+
+        Ok(gas_price)
+    }
+}
+
+#[test]
+fn test_gas_policy_cache() {
+    let mut gas_policy = GasPolicy::new(Duration::from_secs(60));
+    let now = Instant::now();
+    assert_eq!(gas_policy.cached_price(now), None);
+
+    gas_policy.fetched_price = Some((now, 1_500_000_000u64.into()));
+    assert_eq!(
+        gas_policy.cached_price(now + Duration::from_secs(59)),
+        Some(1_500_000_000u64.into())
+    );
+    assert_eq!(gas_policy.cached_price(now + Duration::from_secs(60)), None);
+    assert_eq!(wei_to_gwei(gwei_to_wei(1.5)), 1.5);
+}
+
 #[tokio::test]
 async fn test_fetch_gas_price() {
     let _resp = fetch_polygon_zkevm_test_gas_price().await.unwrap();
@@ -0,0 +1,216 @@
+use std::{
+    collections::BTreeMap,
+    fs::File,
+    io::{BufWriter, Write},
+    path::{Path, PathBuf},
+    str::FromStr,
+};
+
+use intmax_rollup_interface::intmax_zkp_core::{
+    plonky2::plonk::config::{GenericConfig, PoseidonGoldilocksConfig},
+    rollup::block::BlockInfo,
+};
+use serde::Serialize;
+
+const D: usize = 2;
+type C = PoseidonGoldilocksConfig;
+type F = <C as GenericConfig<D>>::F;
+
+pub const BLOCKS_FILE_NAME: &str = "blocks";
+pub const TRANSACTIONS_FILE_NAME: &str = "transactions";
+pub const DEPOSITS_FILE_NAME: &str = "deposits";
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum ExportFormat {
+    Csv,
+}
+
+impl FromStr for ExportFormat {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "csv" => Ok(Self::Csv),
+            "parquet" => anyhow::bail!("parquet format is not supported yet; use csv"),
+            _ => anyhow::bail!("unknown format: {s}"),
+        }
+    }
+}
+
+impl ExportFormat {
+    pub fn extension(&self) -> &'static str {
+        match self {
+            Self::Csv => "csv",
+        }
+    }
+}
+
+/// A table whose columns are the union of the flattened fields of all rows.
+#[derive(Clone, Debug, Default)]
+struct Table {
+    columns: Vec<String>,
+    rows: Vec<BTreeMap<String, String>>,
+}
+
+impl Table {
+    fn push<T: Serialize>(&mut self, block_number: u32, value: &T) -> anyhow::Result<()> {
+        let mut row = BTreeMap::new();
+        row.insert("block_number".to_string(), block_number.to_string());
+        flatten_json_value("", &serde_json::to_value(value)?, &mut row);
+        for column in row.keys() {
+            if !self.columns.contains(column) {
+                self.columns.push(column.clone());
+            }
+        }
+        self.rows.push(row);
+
+        Ok(())
+    }
+
+    fn write_csv(&self, file_path: &Path) -> anyhow::Result<()> {
+        let mut file = BufWriter::new(File::create(file_path)?);
+        let header = self
+            .columns
+            .iter()
+            .map(|column| escape_csv_field(column))
+            .collect::<Vec<_>>();
+        writeln!(file, "{}", header.join(","))?;
+        for row in self.rows.iter() {
+            let record = self
+                .columns
+                .iter()
+                .map(|column| escape_csv_field(row.get(column).map(|v| v.as_str()).unwrap_or("")))
+                .collect::<Vec<_>>();
+            writeln!(file, "{}", record.join(","))?;
+        }
+        file.flush()?;
+
+        Ok(())
+    }
+}
+
+/// Nested objects are flattened into `parent.child` columns.
+/// Arrays are written as JSON strings.
+fn flatten_json_value(prefix: &str, value: &serde_json::Value, row: &mut BTreeMap<String, String>) {
+    let column = if prefix.is_empty() { "value" } else { prefix };
+    match value {
+        serde_json::Value::Object(fields) => {
+            for (key, value) in fields {
+                let key = if prefix.is_empty() {
+                    key.clone()
+                } else {
+                    format!("{prefix}.{key}")
+                };
+                flatten_json_value(&key, value, row);
+            }
+        }
+        serde_json::Value::String(value) => {
+            row.insert(column.to_string(), value.clone());
+        }
+        serde_json::Value::Null => {
+            row.insert(column.to_string(), String::new());
+        }
+        value => {
+            row.insert(column.to_string(), value.to_string());
+        }
+    }
+}
+
+pub(crate) fn escape_csv_field(field: &str) -> String {
+    if field.contains(&[',', '"', '\n', '\r'][..]) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
+
+#[derive(Serialize)]
+struct TransactionRow {
+    index: usize,
+    tx_hash: String,
+    sender_address: String,
+    is_valid: bool,
+}
+
+#[derive(Serialize)]
+struct IndexedRow<T: Serialize> {
+    index: usize,
+    #[serde(flatten)]
+    value: T,
+}
+
+/// Write the block headers, the transaction lists and the deposit lists of the given blocks
+/// into `output_dir`. Returns the paths of the written files.
+pub fn export_blocks(
+    blocks: &[BlockInfo<F>],
+    output_dir: &Path,
+    format: ExportFormat,
+) -> anyhow::Result<Vec<PathBuf>> {
+    let mut block_table = Table::default();
+    let mut transaction_table = Table::default();
+    let mut deposit_table = Table::default();
+    for block in blocks {
+        let block_number = block.header.block_number;
+        block_table.push(block_number, &block.header)?;
+
+        for (index, (sender, tx_hash)) in block
+            .address_list
+            .iter()
+            .zip(block.transactions.iter())
+            .enumerate()
+        {
+            let row = TransactionRow {
+                index,
+                tx_hash: tx_hash.to_string(),
+                sender_address: sender.sender_address.to_string(),
+                is_valid: sender.is_valid,
+            };
+            transaction_table.push(block_number, &row)?;
+        }
+
+        for (index, deposit) in block.deposit_list.iter().enumerate() {
+            let row = IndexedRow {
+                index,
+                value: deposit,
+            };
+            deposit_table.push(block_number, &row)?;
+        }
+    }
+
+    std::fs::create_dir_all(output_dir)?;
+    let mut written_files = vec![];
+    for (file_name, table) in [
+        (BLOCKS_FILE_NAME, block_table),
+        (TRANSACTIONS_FILE_NAME, transaction_table),
+        (DEPOSITS_FILE_NAME, deposit_table),
+    ] {
+        let mut file_path = output_dir.join(file_name);
+        file_path.set_extension(format.extension());
+        match format {
+            ExportFormat::Csv => table.write_csv(&file_path)?,
+        }
+        written_files.push(file_path);
+    }
+
+    Ok(written_files)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_flatten_json_value() {
+        let value = serde_json::json!({
+            "block_number": 3,
+            "header": { "tx_hash": "0x01", "digest": null },
+            "list": [1, 2],
+        });
+        let mut row = BTreeMap::new();
+        flatten_json_value("", &value, &mut row);
+        assert_eq!(row["block_number"], "3");
+        assert_eq!(row["header.tx_hash"], "0x01");
+        assert_eq!(row["header.digest"], "");
+        assert_eq!(escape_csv_field(&row["list"]), "\"[1,2]\"");
+    }
+}
@@ -0,0 +1,58 @@
+use std::{fs::File, io::Write, path::Path};
+
+use anyhow::Context;
+use intmax_rollup_interface::intmax_zkp_core::{
+    plonky2::plonk::config::{GenericConfig, PoseidonGoldilocksConfig},
+    sparse_merkle_tree::goldilocks_poseidon::WrappedHashOut,
+    zkdsa::{account::Address, circuits::SimpleSignatureProofWithPublicInputs},
+};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+
+use crate::utils::key_management::types::TxHash;
+
+const D: usize = 2;
+type C = PoseidonGoldilocksConfig;
+type F = <C as GenericConfig<D>>::F;
+
+/// A pending transaction whose proposal block has not been signed yet,
+/// which is written by `tx export-unsigned` and signed by `account sign-root` on another machine.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct UnsignedTransaction {
+    pub user_address: Address<F>,
+    pub tx_hash: TxHash<F>,
+    /// the proposed world state root including the transaction
+    pub message: WrappedHashOut<F>,
+    /// UNIX time in seconds after which the transaction expires and the signature is refused
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub expires_at: Option<u64>,
+}
+
+/// The signature written by `account sign-root`, which is imported by `tx import-signature`.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct SignedRoot {
+    pub user_address: Address<F>,
+    pub message: WrappedHashOut<F>,
+    pub signature: SimpleSignatureProofWithPublicInputs<F, C, D>,
+}
+
+/// Write the value in JSON into the file, or stdout if `output_path` is `None`.
+pub fn write_json<T: Serialize>(value: &T, output_path: Option<&Path>) -> anyhow::Result<()> {
+    let encoded_value = serde_json::to_string_pretty(value)?;
+    if let Some(output_path) = output_path {
+        let mut file = File::create(output_path)
+            .with_context(|| format!("fail to create {}", output_path.display()))?;
+        writeln!(file, "{encoded_value}")?;
+        file.flush()?;
+    } else {
+        println!("{encoded_value}");
+    }
+
+    Ok(())
+}
+
+pub fn read_json<T: DeserializeOwned>(input_path: &Path) -> anyhow::Result<T> {
+    let file =
+        File::open(input_path).with_context(|| format!("fail to open {}", input_path.display()))?;
+
+    serde_json::from_reader(file).with_context(|| format!("invalid file: {}", input_path.display()))
+}
@@ -0,0 +1,118 @@
+use std::time::{Duration, Instant};
+
+use intmax_interoperability_plugin::ethers::{
+    providers::{Http, Middleware, Provider},
+    types::{H160, U256},
+    utils::format_ether,
+};
+use intmax_rollup_interface::constants::ContractConfig;
+use reqwest::header::CONTENT_TYPE;
+use serde::Serialize;
+
+use super::interoperability::NetworkName;
+use crate::{progress, utils::config::FaucetConfig};
+
+const FAUCET_BALANCE_POLLING_INTERVAL: Duration = Duration::from_secs(5);
+
+const FAUCET_BALANCE_TIMEOUT: Duration = Duration::from_secs(180);
+
+#[derive(Clone, Debug, Serialize)]
+struct RequestFaucetBody {
+    address: H160,
+}
+
+/// Returns the web page where test ETH can be requested manually.
+pub fn get_faucet_page(network_name: NetworkName) -> &'static str {
+    match network_name {
+        NetworkName::ScrollAlpha => "https://scroll.io/alpha/faucet",
+        NetworkName::PolygonZkEvmTest => "https://faucet.polygon.technology/",
+    }
+}
+
+/// Returns the faucet endpoint configured in the profile.
+pub fn get_faucet_url(faucet_config: &FaucetConfig, network_name: NetworkName) -> Option<&str> {
+    match network_name {
+        NetworkName::ScrollAlpha => faucet_config.scroll.as_deref(),
+        NetworkName::PolygonZkEvmTest => faucet_config.polygon.as_deref(),
+    }
+}
+
+pub async fn get_balance(
+    network_config: &ContractConfig<'static>,
+    address: H160,
+) -> anyhow::Result<U256> {
+    let provider = Provider::<Http>::try_from(network_config.rpc_url)?;
+    let balance = provider.get_balance(address, None).await?;
+
+    Ok(balance)
+}
+
+/// Returns false if the faucet refused the request because of its rate limit.
+pub async fn request_faucet(faucet_url: &str, address: H160) -> anyhow::Result<bool> {
+    let body = serde_json::to_string(&RequestFaucetBody { address })?;
+    let resp = reqwest::Client::new()
+        .post(faucet_url)
+        .body(body)
+        .header(CONTENT_TYPE, "application/json")
+        .send()
+        .await?;
+    if resp.status() == 429 {
+        return Ok(false);
+    }
+    if !resp.status().is_success() {
+        let error_message = resp.text().await?;
+        anyhow::bail!("unexpected response from {faucet_url}: {error_message}");
+    }
+
+    Ok(true)
+}
+
+fn print_faucet_instructions(network_name: NetworkName, address: H160) {
+    println!("Request test ETH for the following address manually:");
+    println!("  Network | {network_name}");
+    println!("  Address | {address:?}");
+    println!("  Faucet  | {}", get_faucet_page(network_name));
+}
+
+/// Request test ETH to the faucet and wait until the balance of `address` increases.
+/// Returns the new balance.
+pub async fn fund_from_faucet(
+    network_name: NetworkName,
+    network_config: &ContractConfig<'static>,
+    faucet_url: Option<&str>,
+    address: H160,
+) -> anyhow::Result<U256> {
+    let old_balance = get_balance(network_config, address).await?;
+    progress!("balance: {} ETH", format_ether(old_balance));
+
+    let faucet_url = if let Some(faucet_url) = faucet_url {
+        faucet_url
+    } else {
+        print_faucet_instructions(network_name, address);
+        anyhow::bail!("faucet endpoint is not configured; rerun after your balance is funded");
+    };
+
+    if !request_faucet(faucet_url, address).await? {
+        print_faucet_instructions(network_name, address);
+        anyhow::bail!("the faucet is rate-limited; rerun after your balance is funded");
+    }
+
+    progress!("waiting for the balance to increase");
+    let start = Instant::now();
+    loop {
+        let balance = get_balance(network_config, address).await?;
+        if balance > old_balance {
+            progress!("balance: {} ETH", format_ether(balance));
+
+            return Ok(balance);
+        }
+
+        if start.elapsed() > FAUCET_BALANCE_TIMEOUT {
+            anyhow::bail!(
+                "timed out waiting for the faucet; check the balance of {address:?} later"
+            );
+        }
+
+        tokio::time::sleep(FAUCET_BALANCE_POLLING_INTERVAL).await;
+    }
+}
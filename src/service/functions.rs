@@ -1,5 +1,10 @@
-use std::{collections::HashMap, str::FromStr};
+use std::{
+    collections::BTreeMap,
+    str::FromStr,
+    time::{Duration, Instant},
+};
 
+use anyhow::Context;
 use intmax_interoperability_plugin::{
     contracts::verifier::verifier_contract,
     ethers::types::{Bytes, H256},
@@ -9,45 +14,77 @@ use intmax_rollup_interface::{
     intmax_zkp_core::{
         merkle_tree::tree::MerkleProof,
         plonky2::{
-            field::types::PrimeField64,
-            hash::hash_types::HashOut,
-            plonk::config::{GenericConfig, PoseidonGoldilocksConfig},
+            field::types::{Field, PrimeField64},
+            hash::{hash_types::HashOut, poseidon::PoseidonHash},
+            plonk::config::{GenericConfig, Hasher, PoseidonGoldilocksConfig},
         },
+        rollup::block::BlockInfo,
         sparse_merkle_tree::{
-            goldilocks_poseidon::{PoseidonNodeHash, WrappedHashOut},
+            goldilocks_poseidon::{
+                LayeredPoseidonSparseMerkleTree, NodeDataMemory, PoseidonNodeHash, RootDataTmp,
+                WrappedHashOut,
+            },
             node_data::Node,
             node_hash::NodeHash,
             proof::SparseMerkleInclusionProof,
         },
-        transaction::asset::{ContributedAsset, TokenKind},
+        transaction::{
+            asset::{Asset, ContributedAsset, ReceivedAssetProof},
+            block_header::get_block_hash,
+        },
         zkdsa::account::Address,
     },
 };
+use num_bigint::BigUint;
 
+#[cfg(feature = "verbose")]
+use crate::utils::debug_format::DebugFormat;
 use crate::{
+    progress,
     service::interoperability::verify_asset_inclusion_proof,
     utils::{
-        key_management::{memory::WalletOnMemory, types::Wallet},
-        nickname::NicknameTable,
+        hex_string::{is_address_literal, parse_intmax_address},
+        key_management::{
+            memory::WalletOnMemory,
+            types::{Assets, MergePolicy, TransactionStatus, TxHash, Wallet},
+        },
+        nickname::{
+            NicknameTable, ReservedNicknameTable, TOKEN_ADDRESS_MINE, TOKEN_ADDRESS_RECEIVER,
+        },
+        proof_format::{encode_token_address, AssetInclusionProof},
+        prover::ProvingTimes,
+        qr::resolve_qr_image_argument,
     },
 };
 
-use super::builder::ServiceBuilder;
+use super::{
+    builder::{
+        calc_partially_merged_assets, calc_unmerged_assets, spawn_signer, ServiceBuilder,
+        UnmergedAssetsSummary,
+    },
+    bulk::{RecipientOutcome, RecipientReport},
+    error::{AggregatorError, NothingToDo},
+    planner::{compose_transfers, TransferIntent, TransferLimits},
+};
 
 const D: usize = 2;
 type C = PoseidonGoldilocksConfig;
 type F = <C as GenericConfig<D>>::F;
 
+const WAIT_FOR_APPROVAL_INTERVAL: Duration = Duration::from_secs(5);
+const WAIT_FOR_APPROVAL_TIMEOUT: Duration = Duration::from_secs(600);
+
 pub fn parse_address(
     wallet: &WalletOnMemory,
     nickname_table: &NicknameTable,
     user_address: Option<String>,
 ) -> anyhow::Result<Address<F>> {
     if let Some(user_address) = user_address {
+        let user_address = resolve_qr_image_argument(user_address)?;
         let user_address = if user_address.is_empty() {
             anyhow::bail!("empty user address");
-        } else if user_address.starts_with("0x") {
-            Address::from_str(&user_address)?
+        } else if is_address_literal(&user_address) {
+            parse_intmax_address("user address", &user_address)?
         } else if let Some(user_address) = nickname_table.nickname_to_address.get(&user_address) {
             *user_address
         } else {
@@ -62,6 +99,88 @@ pub fn parse_address(
     }
 }
 
+/// Parse the recipient given as an address, a reserved nickname (e.g., a network for the bridge) or a nickname.
+pub fn parse_recipient(
+    nickname_table: &NicknameTable,
+    receiver_address: &str,
+) -> anyhow::Result<Address<F>> {
+    let reserved_nickname_table = ReservedNicknameTable::new();
+    let receiver_address = if receiver_address.is_empty() {
+        anyhow::bail!("empty recipient");
+    } else if is_address_literal(receiver_address) {
+        parse_intmax_address("recipient", receiver_address)?
+    } else if let Some(receiver_address) = reserved_nickname_table
+        .nickname_to_address
+        .get(receiver_address)
+    {
+        *receiver_address
+    } else if let Some(receiver_address) = nickname_table.nickname_to_address.get(receiver_address)
+    {
+        *receiver_address
+    } else {
+        anyhow::bail!("unregistered nickname: recipient");
+    };
+
+    Ok(receiver_address)
+}
+
+/// The token sent when `--token-address` is omitted, which differs for each command.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum DefaultTokenAddress {
+    /// the token issued by the sender
+    Mine,
+    /// the token issued by the recipient
+    Receiver,
+}
+
+/// Parse `--token-address` given as an address, a nickname, `mine` or `receiver`,
+/// and display the resolved token address so that it is clear which default was applied.
+pub fn resolve_token_address(
+    nickname_table: &NicknameTable,
+    token_address: Option<String>,
+    default: DefaultTokenAddress,
+    user_address: Address<F>,
+    receiver_address: Option<Address<F>>,
+) -> anyhow::Result<Address<F>> {
+    let keyword = match token_address.as_deref() {
+        None => Some(default),
+        Some(TOKEN_ADDRESS_MINE) => Some(DefaultTokenAddress::Mine),
+        Some(TOKEN_ADDRESS_RECEIVER) => Some(DefaultTokenAddress::Receiver),
+        Some(_) => None,
+    };
+    let (contract_address, description) = match keyword {
+        Some(DefaultTokenAddress::Mine) => (user_address, " (your own token)"),
+        Some(DefaultTokenAddress::Receiver) => (
+            receiver_address.context("`receiver` cannot be given as the token address here")?,
+            " (the token of the recipient)",
+        ),
+        None => {
+            let token_address = token_address.unwrap();
+            let contract_address = if token_address.is_empty() {
+                anyhow::bail!("empty token address");
+            } else if is_address_literal(&token_address) {
+                parse_intmax_address("token address", &token_address)?
+            } else if let Some(contract_address) =
+                nickname_table.nickname_to_address.get(&token_address)
+            {
+                *contract_address
+            } else {
+                anyhow::bail!("unregistered nickname: token address");
+            };
+
+            (contract_address, "")
+        }
+    };
+    let by_default = if token_address.is_none() {
+        ", by default"
+    } else {
+        ""
+    };
+    println!("Token address: {contract_address}{description}{by_default}");
+
+    Ok(contract_address)
+}
+
 // This function merges received assets for a user until the number of unmerged assets is less than `num_unmerged`.
 // During each iteration, `N_MERGES` is subtracted from `user_state.rest_received_assets`.
 pub async fn merge(
@@ -78,7 +197,7 @@ pub async fn merge(
 
         if user_state.rest_received_assets.len() <= num_unmerged {
             #[cfg(feature = "verbose")]
-            println!("the number of unmerged differences is sufficiently small");
+            progress!("the number of unmerged differences is sufficiently small");
             break;
         }
 
@@ -89,20 +208,312 @@ pub async fn merge(
 
         wallet.backup()?;
 
-        service.resolve_server_health_issue().await.unwrap();
-        service.trigger_propose_block().await.unwrap();
-        service.trigger_approve_block().await.unwrap();
+        service.resolve_server_health_issue().await?;
+        service.trigger_propose_block().await?;
+        service.trigger_approve_block().await?;
+    }
+
+    Ok(())
+}
+
+/// The outcome of `merge` estimated without proving anything.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct MergeSimulation {
+    /// the number of received assets which have not been merged yet
+    pub n_unmerged: usize,
+    /// the number of merge proofs generated, one for each merged received asset
+    pub n_merge_proofs: usize,
+    /// the number of transactions proved, each of which merges up to `N_MERGES` received assets
+    pub n_iterations: usize,
+    /// the number of asset fragments held before merging
+    pub n_fragments_before: usize,
+    /// the number of asset fragments held after merging
+    pub n_fragments_after: usize,
+    /// `None` if no proving time has been measured on this machine
+    pub proving_time: Option<Duration>,
+}
+
+/// Returns the number of iterations and merged received assets
+/// until `n_unmerged` received assets are reduced to `num_unmerged` or less.
+fn calc_merge_iterations(
+    n_unmerged: usize,
+    num_unmerged: usize,
+    n_merges: usize,
+) -> (usize, usize) {
+    if n_unmerged <= num_unmerged {
+        return (0, 0);
+    }
+
+    let n_iterations = (n_unmerged - num_unmerged + n_merges - 1) / n_merges;
+
+    (n_iterations, n_unmerged.min(n_iterations * n_merges))
+}
+
+/// Estimate what `merge` does with `num_unmerged` from the wallet and the proving times measured recently.
+pub fn simulate_merge(
+    wallet: &WalletOnMemory,
+    user_address: Address<F>,
+    num_unmerged: usize,
+    proving_times: &ProvingTimes,
+) -> MergeSimulation {
+    let user_state = wallet
+        .data
+        .get(&user_address)
+        .expect("user address was not found in wallet");
+    let n_unmerged = user_state.rest_received_assets.len();
+    let (n_iterations, n_merged) =
+        calc_merge_iterations(n_unmerged, num_unmerged, ROLLUP_CONSTANTS.n_merges);
+
+    MergeSimulation {
+        n_unmerged,
+        n_merge_proofs: n_merged,
+        n_iterations,
+        n_fragments_before: user_state.assets.0.len(),
+        n_fragments_after: calc_partially_merged_assets(user_state, n_merged)
+            .assets
+            .0
+            .len(),
+        proving_time: proving_times
+            .average()
+            .map(|proving_time| proving_time * n_iterations as u32),
+    }
+}
+
+/// Merge the received assets before sending `purge_diffs` according to the merge policy of the wallet.
+/// Up to `N_MERGES` unmerged differences are included in the transaction with purge.
+pub async fn merge_before_sending(
+    service: &ServiceBuilder,
+    wallet: &mut WalletOnMemory,
+    user_address: Address<F>,
+    purge_diffs: &[ContributedAsset<F>],
+) -> anyhow::Result<()> {
+    let n_merges = ROLLUP_CONSTANTS.n_merges;
+    let merge_policy = wallet.merge_policy;
+    let check_sendable = |wallet: &WalletOnMemory| {
+        let user_state = wallet
+            .data
+            .get(&user_address)
+            .expect("user address was not found in wallet");
+        let n_unmerged = user_state.rest_received_assets.len();
+        let assets = calc_partially_merged_assets(user_state, n_merges).assets;
+
+        (n_unmerged, ensure_assets_held(&assets, purge_diffs).is_ok())
+    };
+
+    let (n_unmerged, sendable) = check_sendable(wallet);
+    if n_unmerged <= n_merges {
+        return Ok(());
+    }
+
+    match merge_policy {
+        MergePolicy::Aggressive => {
+            progress!(
+                "merging {} of {n_unmerged} received assets before sending (merge policy: {merge_policy})",
+                n_unmerged - n_merges
+            );
+
+            // Repeat merging until there are `N_MERGES` unmerged differences remaining.
+            merge(service, wallet, user_address, n_merges).await?;
+        }
+        MergePolicy::Lazy => {
+            if sendable {
+                return Ok(());
+            }
+
+            progress!(
+                "the assets to send are not available without merging; merging the received assets before sending (merge policy: {merge_policy})"
+            );
+
+            // Merge one transaction at a time until the assets to send are available.
+            let mut n_unmerged = n_unmerged;
+            while n_unmerged > n_merges {
+                merge(service, wallet, user_address, n_unmerged - 1).await?;
+
+                let (new_n_unmerged, sendable) = check_sendable(wallet);
+                if sendable || new_n_unmerged >= n_unmerged {
+                    break;
+                }
+                n_unmerged = new_n_unmerged;
+            }
+        }
+        MergePolicy::Manual => {
+            if !sendable {
+                anyhow::bail!(
+                    "the assets to send are not available without merging {} of {n_unmerged} received assets; run `tx merge` first (merge policy: {merge_policy})",
+                    n_unmerged - n_merges
+                );
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Warn about the received assets which cannot be merged, since they may be double-merged.
+pub fn warn_unmerged_assets_summary(summary: &UnmergedAssetsSummary) {
+    for merge_key in summary.duplicated_merge_keys.iter() {
+        eprintln!(
+            "WARNING: the following received transaction is duplicated in unmerged assets: {merge_key}"
+        );
+    }
+    for merge_key in summary.already_merged_merge_keys.iter() {
+        eprintln!("WARNING: the following received transaction has already merged: {merge_key}");
+    }
+    #[cfg(feature = "verbose")]
+    for tx_hash in summary.canceled_tx_hashes.iter() {
+        progress!("The following transaction was canceled: {tx_hash}");
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum RegistrationStatus {
+    Registered,
+    Unregistered,
+    /// The aggregator could not tell the registration status.
+    Unknown,
+}
+
+impl std::fmt::Display for RegistrationStatus {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Registered => write!(f, "registered"),
+            Self::Unregistered => write!(f, "NOT REGISTERED"),
+            Self::Unknown => write!(f, "unknown"),
+        }
+    }
+}
+
+/// Query the aggregator whether each recipient has been registered.
+/// The addresses of reserved nicknames (e.g., networks for the bridge) are skipped.
+pub async fn check_recipients_registration(
+    service: &ServiceBuilder,
+    recipients: &[Address<F>],
+) -> Vec<(Address<F>, RegistrationStatus)> {
+    let reserved_nickname_table = ReservedNicknameTable::new();
+    let mut recipients = recipients
+        .iter()
+        .filter(|recipient| {
+            !reserved_nickname_table
+                .address_to_nickname
+                .contains_key(recipient)
+        })
+        .copied()
+        .collect::<Vec<_>>();
+    recipients.sort_by_key(|recipient| recipient.to_string());
+    recipients.dedup();
+
+    let mut result = vec![];
+    for recipient in recipients {
+        let status = match service.is_registered_account(recipient).await {
+            Ok(Some(true)) => RegistrationStatus::Registered,
+            Ok(Some(false)) => RegistrationStatus::Unregistered,
+            Ok(None) => RegistrationStatus::Unknown,
+            Err(_err) => {
+                #[cfg(feature = "verbose")]
+                eprintln!(
+                    "fail to check the registration of {}: {_err}",
+                    recipient.debug_format()
+                );
+
+                RegistrationStatus::Unknown
+            }
+        };
+        result.push((recipient, status));
+    }
+
+    result
+}
+
+/// Assets sent to an unregistered address cannot be used until the recipient registers.
+/// Fails unless every recipient is registered or `allow_unregistered` is set.
+pub fn ensure_recipients_registered(
+    statuses: &[(Address<F>, RegistrationStatus)],
+    allow_unregistered: bool,
+) -> anyhow::Result<()> {
+    let unregistered_recipients = statuses
+        .iter()
+        .filter(|(_, status)| *status != RegistrationStatus::Registered)
+        .map(|(recipient, status)| format!("{recipient} ({status})"))
+        .collect::<Vec<_>>();
+    if unregistered_recipients.is_empty() {
+        return Ok(());
+    }
+
+    if !allow_unregistered {
+        anyhow::bail!(
+            "the following recipients may not be registered: {}. Assets sent to them are stranded until they register. Use --allow-unregistered to send anyway.",
+            unregistered_recipients.join(", ")
+        );
     }
 
+    eprintln!(
+        "WARNING: the following recipients may not be registered: {}",
+        unregistered_recipients.join(", ")
+    );
+
     Ok(())
 }
 
+/// Check that the assets including the unmerged ones are enough for `purge_diffs`.
+/// The error lists the held token kinds to help to find a mistyped token address or token ID.
+pub fn ensure_assets_held(
+    assets: &Assets<F>,
+    purge_diffs: &[ContributedAsset<F>],
+) -> anyhow::Result<()> {
+    let total_amount_map = assets.calc_total_amount();
+
+    let mut required_amount_map: BTreeMap<(String, String), BigUint> = BTreeMap::new();
+    for purge_diff in purge_diffs {
+        let encoded_token_kind = (
+            purge_diff.kind.contract_address.to_string(),
+            purge_diff.kind.variable_index.to_string(),
+        );
+        *required_amount_map.entry(encoded_token_kind).or_default() += purge_diff.amount;
+    }
+
+    for ((contract_address, variable_index), required_amount) in required_amount_map {
+        let held_amount = total_amount_map
+            .get(&(contract_address.clone(), variable_index.clone()))
+            .cloned()
+            .unwrap_or_default();
+        if held_amount == BigUint::default() {
+            let mut message = format!(
+                "you do not hold the token {contract_address} with token ID {variable_index}"
+            );
+            if total_amount_map.is_empty() {
+                message += "; no assets are held";
+            } else {
+                message += "; the tokens you hold are:";
+                for ((contract_address, variable_index), amount) in total_amount_map.iter() {
+                    message += &format!(
+                        "\n  Token Address | {contract_address}, Token ID | {variable_index}, Amount | {amount}"
+                    );
+                }
+            }
+
+            anyhow::bail!(message);
+        }
+
+        if held_amount < required_amount {
+            anyhow::bail!(
+                "insufficient balance of the token {contract_address} with token ID {variable_index}: {required_amount} is required, but {held_amount} is held"
+            );
+        }
+    }
+
+    Ok(())
+}
+
+/// If `wait` is true, this function returns after the block including the transaction is approved.
+/// If `sign` is false, the proposal block is left unsigned, so that it is signed by an external signer.
 pub async fn transfer(
     service: &ServiceBuilder,
     wallet: &mut WalletOnMemory,
     user_address: Address<F>,
     purge_diffs: &[ContributedAsset<F>],
-) -> anyhow::Result<Option<WrappedHashOut<F>>> {
+    wait: bool,
+    sign: bool,
+) -> anyhow::Result<Option<TxHash<F>>> {
     {
         let user_state = wallet
             .data
@@ -114,11 +525,12 @@ pub async fn transfer(
             .await;
 
         wallet.backup()?;
+
+        // Fail before merging if a token to send is not held.
+        ensure_assets_held(&calc_unmerged_assets(user_state).assets, purge_diffs)?;
     }
 
-    // Repeat merging until there are `N_MERGES` unmerged differences remaining.
-    // The remaining differences are included in the transaction with purge.
-    merge(service, wallet, user_address, ROLLUP_CONSTANTS.n_merges).await?;
+    merge_before_sending(service, wallet, user_address, purge_diffs).await?;
 
     let tx_hash = {
         let user_state = wallet
@@ -132,12 +544,21 @@ pub async fn transfer(
         let tx_hash = match result {
             Ok(tx_hash) => Some(tx_hash),
             Err(err) => {
-                if err.to_string() == "nothing to do" {
+                if err.is::<NothingToDo>() {
                     #[cfg(feature = "verbose")]
                     println!("nothing to do");
 
                     None
                 } else {
+                    // The transaction has already been sent if only broadcasting failed.
+                    let is_broadcast_error = matches!(
+                        err.downcast_ref::<AggregatorError>(),
+                        Some(error) if error.api_path == "/tx/broadcast"
+                    );
+                    if is_broadcast_error {
+                        wallet.backup()?;
+                    }
+
                     return Err(err);
                 }
             }
@@ -148,8 +569,28 @@ pub async fn transfer(
         tx_hash
     };
 
-    service.resolve_server_health_issue().await.unwrap();
-    service.trigger_propose_block().await.unwrap();
+    // Build the signature circuit while the aggregator proposes a block.
+    let signer = tx_hash.filter(|_| sign).map(|_| {
+        let user_state = wallet
+            .data
+            .get(&user_address)
+            .expect("user address was not found in wallet");
+
+        spawn_signer(user_state.account)
+    });
+
+    service.resolve_server_health_issue().await?;
+    service.trigger_propose_block().await?;
+
+    if !sign {
+        if let Some(tx_hash) = tx_hash {
+            progress!(
+                "The transaction is waiting for your signature. Export it with `tx export-unsigned --tx-hash {tx_hash}`"
+            );
+        }
+
+        return Ok(tx_hash);
+    }
 
     {
         let user_state = wallet
@@ -157,60 +598,263 @@ pub async fn transfer(
             .get_mut(&user_address)
             .expect("user address was not found in wallet");
 
-        service.sign_proposed_block(user_state, user_address).await;
+        service
+            .sign_proposed_block(user_state, user_address, None, signer)
+            .await?;
 
         wallet.backup()?;
     }
 
-    service.trigger_approve_block().await.unwrap();
+    service.trigger_approve_block().await?;
+
+    if wait {
+        if let Some(tx_hash) = tx_hash {
+            wait_for_approval(service, wallet, user_address, tx_hash).await?;
+        }
+    }
 
     Ok(tx_hash)
 }
 
+/// Send a transaction from each account in `transfers`, sharing one proposal block.
+/// All the merges are done before any transfer is sent,
+/// since a merge proposes a block which would revert the transfers not signed yet.
+/// If a transfer fails, the transfers sent before it are still signed.
+pub async fn transfer_from_accounts(
+    service: &ServiceBuilder,
+    wallet: &mut WalletOnMemory,
+    transfers: &[(Address<F>, Vec<ContributedAsset<F>>)],
+    wait: bool,
+) -> anyhow::Result<Vec<(Address<F>, TxHash<F>)>> {
+    // Fail before merging if a token to send is not held by any of the senders.
+    for (user_address, purge_diffs) in transfers {
+        let user_state = wallet
+            .data
+            .get_mut(user_address)
+            .expect("user address was not found in wallet");
+
+        service
+            .sync_sent_transaction(user_state, *user_address)
+            .await;
+
+        ensure_assets_held(&calc_unmerged_assets(user_state).assets, purge_diffs)
+            .with_context(|| format!("cannot send from {user_address}"))?;
+    }
+
+    wallet.backup()?;
+
+    for (user_address, purge_diffs) in transfers {
+        merge_before_sending(service, wallet, *user_address, purge_diffs).await?;
+    }
+
+    let mut sent_transactions = vec![];
+    let mut signers = vec![];
+    let mut error = None;
+    for (user_address, purge_diffs) in transfers {
+        let user_state = wallet
+            .data
+            .get_mut(user_address)
+            .expect("user address was not found in wallet");
+
+        let result = service
+            .merge_and_purge_asset(user_state, *user_address, purge_diffs, true)
+            .await;
+        let account = user_state.account;
+
+        wallet.backup()?;
+
+        match result {
+            Ok(tx_hash) => {
+                // Build the signature circuit while the other transactions are sent.
+                signers.push(spawn_signer(account));
+                sent_transactions.push((*user_address, tx_hash));
+            }
+            Err(err) => {
+                error = Some(err.context(format!("fail to send from {user_address}")));
+                break;
+            }
+        }
+    }
+
+    if !sent_transactions.is_empty() {
+        service.resolve_server_health_issue().await?;
+        service.trigger_propose_block().await?;
+
+        for ((user_address, _), signer) in sent_transactions.iter().zip(signers) {
+            let user_state = wallet
+                .data
+                .get_mut(user_address)
+                .expect("user address was not found in wallet");
+
+            service
+                .sign_proposed_block(user_state, *user_address, None, Some(signer))
+                .await?;
+        }
+
+        wallet.backup()?;
+
+        service.trigger_approve_block().await?;
+    }
+
+    if let Some(error) = error {
+        return Err(error);
+    }
+
+    if wait {
+        for (user_address, tx_hash) in sent_transactions.iter() {
+            wait_for_approval(service, wallet, *user_address, *tx_hash).await?;
+        }
+    }
+
+    Ok(sent_transactions)
+}
+
+/// Poll the blocks until the block including the transaction appears,
+/// and record whether the transaction was approved or reverted.
+pub async fn wait_for_approval(
+    service: &ServiceBuilder,
+    wallet: &mut WalletOnMemory,
+    user_address: Address<F>,
+    tx_hash: TxHash<F>,
+) -> anyhow::Result<TransactionStatus> {
+    let since = {
+        let user_state = wallet
+            .data
+            .get(&user_address)
+            .expect("user address was not found in wallet");
+        let proposed_block_number = user_state
+            .sent_transactions
+            .get(&tx_hash)
+            .and_then(|sent_transaction| sent_transaction.proposed_block_number);
+
+        proposed_block_number
+            .map(|block_number| block_number.saturating_sub(1))
+            .unwrap_or(user_state.last_seen_block_number)
+    };
+
+    progress!("waiting for approval: {tx_hash}");
+    let start = Instant::now();
+    let (status, block_number) = loop {
+        let (blocks, _) = service.get_blocks(Some(since), None).await?;
+        let result = blocks.iter().find_map(|block| {
+            block
+                .transactions
+                .iter()
+                .position(|v| *v == tx_hash.0)
+                .map(|index| (block, index))
+        });
+        if let Some((block, index)) = result {
+            let is_valid = block
+                .address_list
+                .get(index)
+                .map(|v| v.sender_address == user_address && v.is_valid)
+                .unwrap_or(false);
+            let status = if is_valid {
+                TransactionStatus::Approved
+            } else {
+                TransactionStatus::Reverted
+            };
+
+            break (status, block.header.block_number);
+        }
+
+        if start.elapsed() > WAIT_FOR_APPROVAL_TIMEOUT {
+            anyhow::bail!(
+                "timed out waiting for the approval of the transaction: {tx_hash}. Check it later with `block sign --wait`"
+            );
+        }
+
+        tokio::time::sleep(WAIT_FOR_APPROVAL_INTERVAL).await;
+    };
+
+    {
+        let user_state = wallet
+            .data
+            .get_mut(&user_address)
+            .expect("user address was not found in wallet");
+        if let Some(sent_transaction) = user_state.sent_transactions.get_mut(&tx_hash) {
+            sent_transaction.proposed_block_number = Some(block_number);
+            sent_transaction.status = status;
+        }
+
+        wallet.backup()?;
+    }
+
+    println!("transaction {tx_hash} was {status} in block {block_number}");
+    if status == TransactionStatus::Reverted {
+        anyhow::bail!("transaction was reverted: {tx_hash}");
+    }
+
+    Ok(status)
+}
+
+/// Mint and/or send the assets to each recipient. The assets are split into as few transactions
+/// as the limits allow, which are sent one after another.
+/// Returns an error only if nothing was sent.
+/// Otherwise, the failures are reported per recipient.
 pub async fn bulk_mint(
     service: &ServiceBuilder,
     wallet: &mut WalletOnMemory,
     user_address: Address<F>,
     distribution_list: Vec<ContributedAsset<F>>,
     need_deposit: bool,
-) -> anyhow::Result<()> {
-    // {
-    //     let user_state = wallet
-    //         .data
-    //         .get_mut(&user_address)
-    //         .expect("user address was not found in wallet");
-
-    //     service.sync_sent_transaction(user_state, user_address);
-
-    //     backup_wallet(wallet)?;
-    // }
-
-    // Organize by destination and token.
-    let mut distribution_map: HashMap<(Address<F>, TokenKind<F>), u64> = HashMap::new();
-    for asset in distribution_list.iter() {
-        if let Some(v) = distribution_map.get_mut(&(asset.receiver_address, asset.kind)) {
-            *v += asset.amount;
-        } else {
-            distribution_map.insert((asset.receiver_address, asset.kind), asset.amount);
-        }
+) -> anyhow::Result<Vec<RecipientReport>> {
+    if distribution_list.is_empty() {
+        anyhow::bail!("asset list is empty");
     }
 
-    let distribution_list = distribution_map
-        .iter()
-        .map(|(k, v)| ContributedAsset {
-            receiver_address: k.0,
-            kind: k.1,
-            amount: *v,
+    let intents = distribution_list
+        .into_iter()
+        .map(|output| TransferIntent {
+            sender_address: user_address,
+            output,
         })
         .collect::<Vec<_>>();
+    let limits = if need_deposit {
+        TransferLimits::mint()
+    } else {
+        TransferLimits::transfer()
+    };
+    let planned_txs = compose_transfers(intents, &limits)?;
 
-    if distribution_list.is_empty() {
-        anyhow::bail!("asset list is empty");
+    let mut recipients = vec![];
+    for (i, planned_tx) in planned_txs.into_iter().enumerate() {
+        progress!(
+            "transaction {}: {} recipients and token kinds",
+            i + 1,
+            planned_tx.outputs.len()
+        );
+        let mut planned_recipients = mint_and_send(
+            service,
+            wallet,
+            user_address,
+            planned_tx.outputs,
+            need_deposit,
+        )
+        .await?;
+        recipients.append(&mut planned_recipients);
     }
 
-    if distribution_list.len() > ROLLUP_CONSTANTS.n_diffs.min(ROLLUP_CONSTANTS.n_merges) {
-        anyhow::bail!("too many destinations and token kinds");
-    }
+    Ok(recipients)
+}
+
+/// Mint and/or send the assets in one transaction.
+async fn mint_and_send(
+    service: &ServiceBuilder,
+    wallet: &mut WalletOnMemory,
+    user_address: Address<F>,
+    distribution_list: Vec<ContributedAsset<F>>,
+    need_deposit: bool,
+) -> anyhow::Result<Vec<RecipientReport>> {
+    let report_all = |outcome: RecipientOutcome| {
+        distribution_list
+            .iter()
+            .map(|asset| RecipientReport {
+                asset: asset.clone(),
+                outcome: outcome.clone(),
+            })
+            .collect::<Vec<_>>()
+    };
 
     if need_deposit {
         let mut deposit_list = distribution_list.clone();
@@ -225,21 +869,87 @@ pub async fn bulk_mint(
             .iter_mut()
             .for_each(|v| v.receiver_address = user_address);
 
-        service.deposit_assets(user_address, deposit_list).await?;
+        let result = async {
+            service.deposit_assets(user_address, deposit_list).await?;
 
-        service.resolve_server_health_issue().await.unwrap();
-        service.trigger_propose_block().await.unwrap();
-        service.trigger_approve_block().await.unwrap();
+            service.resolve_server_health_issue().await?;
+            service.trigger_propose_block().await?;
+            service.trigger_approve_block().await
+        }
+        .await;
+        if let Err(err) = result {
+            return Ok(report_all(RecipientOutcome::Failed {
+                reason: format!("fail to mint: {err:#}"),
+                need_deposit: true,
+            }));
+        }
     }
 
-    let purge_diffs = distribution_list
+    let (self_distribution_list, purge_diffs): (Vec<_>, Vec<_>) = distribution_list
+        .iter()
+        .cloned()
+        .partition(|v| v.receiver_address == user_address);
+
+    // The assets for the sender itself have already been delivered by minting.
+    let mut recipients = self_distribution_list
         .into_iter()
-        .filter(|v| v.receiver_address != user_address)
+        .map(|asset| RecipientReport {
+            asset,
+            outcome: RecipientOutcome::Delivered { tx_hash: None },
+        })
         .collect::<Vec<_>>();
+    if purge_diffs.is_empty() {
+        return Ok(recipients);
+    }
 
-    transfer(service, wallet, user_address, &purge_diffs).await?;
+    let outcome = match transfer(service, wallet, user_address, &purge_diffs, false, true).await {
+        Ok(Some(tx_hash)) => confirm_delivery(service, wallet, user_address, tx_hash).await,
+        Ok(None) => RecipientOutcome::Failed {
+            reason: "nothing to send".to_string(),
+            need_deposit: false,
+        },
+        Err(err) => RecipientOutcome::Failed {
+            reason: format!("{err:#}"),
+            need_deposit: false,
+        },
+    };
+    recipients.extend(purge_diffs.into_iter().map(|asset| RecipientReport {
+        asset,
+        outcome: outcome.clone(),
+    }));
 
-    Ok(())
+    Ok(recipients)
+}
+
+/// Wait for the approval of the transaction and returns the outcome for its recipients.
+pub async fn confirm_delivery(
+    service: &ServiceBuilder,
+    wallet: &mut WalletOnMemory,
+    user_address: Address<F>,
+    tx_hash: TxHash<F>,
+) -> RecipientOutcome {
+    match wait_for_approval(service, wallet, user_address, tx_hash).await {
+        Ok(_) => RecipientOutcome::Delivered {
+            tx_hash: Some(tx_hash),
+        },
+        Err(err) => {
+            let status = wallet
+                .data
+                .get(&user_address)
+                .and_then(|user_state| user_state.sent_transactions.get(&tx_hash))
+                .map(|sent_transaction| sent_transaction.status);
+            if status == Some(TransactionStatus::Reverted) {
+                RecipientOutcome::Failed {
+                    reason: format!("{err:#}"),
+                    need_deposit: false,
+                }
+            } else {
+                eprintln!("{err:#}");
+
+                RecipientOutcome::Pending { tx_hash }
+            }
+        }
+    }
 }
 
 pub fn smt_proof_to_merkle_proof(
@@ -270,16 +980,191 @@ pub fn smt_proof_to_merkle_proof(
     })
 }
 
+/// Returns the root calculated from the value and the siblings of the Merkle proof.
+/// The first sibling is at the leaf level.
+pub fn calc_merkle_root(proof: &MerkleProof<F>) -> WrappedHashOut<F> {
+    let mut node = proof.value;
+    for (i, sibling) in proof.siblings.iter().enumerate() {
+        node = if (proof.index >> i) & 1 == 0 {
+            PoseidonNodeHash::calc_node_hash(Node::Internal(node, *sibling))
+        } else {
+            PoseidonNodeHash::calc_node_hash(Node::Internal(*sibling, node))
+        };
+    }
+
+    node
+}
+
+/// Check a received asset proof given by the aggregator against the block it refers to,
+/// which is fetched separately and cached locally.
+pub fn verify_received_asset_proof(
+    user_address: Address<F>,
+    witness: &ReceivedAssetProof<F>,
+    block: &BlockInfo<F>,
+) -> anyhow::Result<()> {
+    let (block_header, digest_inclusion_proof, diff_tree_inclusion_proof) =
+        &witness.diff_tree_inclusion_proof;
+    let block_number = block.header.block_number;
+    if get_block_hash(block_header) != get_block_hash(&block.header) {
+        anyhow::bail!("the block header does not match block {block_number}");
+    }
+
+    let (commitment, digest) = if witness.is_deposit {
+        (
+            ReceivedAssetsCommitment::Deposit,
+            block.header.deposit_digest,
+        )
+    } else {
+        let commitment = ReceivedAssetsCommitment::Transaction {
+            nonce: witness.nonce,
+            digest_inclusion_proof,
+            transactions: &block.transactions,
+        };
+
+        (commitment, block.header.transactions_digest)
+    };
+
+    verify_received_assets(
+        user_address,
+        &witness.assets,
+        commitment,
+        diff_tree_inclusion_proof,
+        digest.into(),
+        block_number,
+    )
+}
+
+/// How the diff tree including the received assets is committed to by the block.
+enum ReceivedAssetsCommitment<'a> {
+    /// The deposit digest of the block is the root of the deposit tree itself.
+    Deposit,
+    /// The transaction hash commits to the diff tree, and it is included in the transactions digest.
+    Transaction {
+        nonce: WrappedHashOut<F>,
+        digest_inclusion_proof: &'a MerkleProof<F>,
+        transactions: &'a [WrappedHashOut<F>],
+    },
+}
+
+/// Check that `assets` are given to the user in the diff tree committed to by `digest` of the block.
+fn verify_received_assets(
+    user_address: Address<F>,
+    assets: &[Asset<F>],
+    commitment: ReceivedAssetsCommitment<'_>,
+    diff_tree_inclusion_proof: &SparseMerkleInclusionProof<
+        WrappedHashOut<F>,
+        WrappedHashOut<F>,
+        WrappedHashOut<F>,
+    >,
+    digest: WrappedHashOut<F>,
+    block_number: u32,
+) -> anyhow::Result<()> {
+    match commitment {
+        ReceivedAssetsCommitment::Deposit => {
+            // The other proof in the witness is not needed, since the deposit tree is not a leaf of another tree.
+            if diff_tree_inclusion_proof.root != digest {
+                anyhow::bail!("the deposit tree does not match block {block_number}");
+            }
+        }
+        ReceivedAssetsCommitment::Transaction {
+            nonce,
+            digest_inclusion_proof,
+            transactions,
+        } => {
+            if digest_inclusion_proof.root != digest
+                || calc_merkle_root(digest_inclusion_proof) != digest_inclusion_proof.root
+            {
+                anyhow::bail!("invalid inclusion proof for block {block_number}");
+            }
+
+            if !transactions.contains(&digest_inclusion_proof.value) {
+                anyhow::bail!("the transaction was not found in block {block_number}");
+            }
+
+            let tx_hash = calc_tx_hash(diff_tree_inclusion_proof.root, nonce);
+            if tx_hash != digest_inclusion_proof.value {
+                anyhow::bail!(
+                    "the diff tree does not match the transaction {} in block {block_number}",
+                    digest_inclusion_proof.value
+                );
+            }
+        }
+    }
+
+    verify_diff_tree_leaf(user_address, assets, diff_tree_inclusion_proof)
+}
+
+/// The hash of a transaction, which commits to the tree of the assets sent to each recipient.
+pub fn calc_tx_hash(diff_root: WrappedHashOut<F>, nonce: WrappedHashOut<F>) -> WrappedHashOut<F> {
+    PoseidonHash::two_to_one(*diff_root, *nonce).into()
+}
+
+/// The root of the tree of `assets` given to one recipient, which is the leaf of the diff tree.
+pub fn calc_asset_root(assets: &[Asset<F>]) -> WrappedHashOut<F> {
+    let mut asset_tree =
+        LayeredPoseidonSparseMerkleTree::new(NodeDataMemory::default(), RootDataTmp::default());
+    for asset in assets {
+        asset_tree
+            .set(
+                asset.kind.contract_address.to_hash_out().into(),
+                asset.kind.variable_index.to_hash_out().into(),
+                HashOut::from_partial(&[F::from_canonical_u64(asset.amount)]).into(),
+            )
+            .unwrap();
+    }
+
+    asset_tree.get_root().unwrap()
+}
+
+/// Check that the leaf of the diff tree is the root of `assets` given to this user,
+/// and that it is included in the diff tree.
+pub fn verify_diff_tree_leaf(
+    user_address: Address<F>,
+    assets: &[Asset<F>],
+    diff_tree_inclusion_proof: &SparseMerkleInclusionProof<
+        WrappedHashOut<F>,
+        WrappedHashOut<F>,
+        WrappedHashOut<F>,
+    >,
+) -> anyhow::Result<()> {
+    if *diff_tree_inclusion_proof.key != user_address.to_hash_out() {
+        anyhow::bail!("the assets are not for {user_address}");
+    }
+
+    // A token kind given twice would be counted twice, though the tree has it once.
+    let mut token_kinds = assets.iter().map(|asset| asset.kind).collect::<Vec<_>>();
+    token_kinds.sort_by_key(|kind| {
+        (
+            kind.contract_address.to_string(),
+            kind.variable_index.to_string(),
+        )
+    });
+    token_kinds.dedup();
+    if token_kinds.len() != assets.len() {
+        anyhow::bail!("the assets include a token kind more than once");
+    }
+
+    if calc_asset_root(assets) != diff_tree_inclusion_proof.value {
+        anyhow::bail!("the assets differ from the ones included in the diff tree");
+    }
+
+    let diff_tree_inclusion_proof = smt_proof_to_merkle_proof(diff_tree_inclusion_proof)?;
+    if calc_merkle_root(&diff_tree_inclusion_proof) != diff_tree_inclusion_proof.root {
+        anyhow::bail!("invalid inclusion proof of the assets");
+    }
+
+    Ok(())
+}
+
 pub async fn create_transaction_proof(
     service: &ServiceBuilder,
     network_config: Option<ContractConfig<'static>>,
     tx_hash: HashOut<F>,
     receiver_address: Address<F>,
-) -> anyhow::Result<Bytes> {
+) -> anyhow::Result<AssetInclusionProof> {
     let (tx_details, _transaction_proof, _block_header, witness) = service
         .get_transaction_proof(tx_hash, receiver_address)
-        .await
-        .unwrap();
+        .await?;
 
     // NOTICE: When exiting, only one type of token can be transferred at a time.
     if tx_details.assets.len() != 1 {
@@ -287,27 +1172,326 @@ pub async fn create_transaction_proof(
     }
     let target_asset = &tx_details.assets[0];
     let recipient = H256::from_str(&tx_details.inclusion_witness.key.to_string()[2..]).unwrap();
-    let asset = verifier_contract::Asset {
-        token_address: H256::from_str(
-            &WrappedHashOut::from(target_asset.kind.contract_address.to_hash_out()).to_string()
-                [2..],
-        )
-        .unwrap()
-        .into(),
+    #[cfg(feature = "verbose")]
+    progress!(
+        "recipient: {}",
+        tx_details.inclusion_witness.key.debug_format()
+    );
+    service.debug_dump("transaction_witness", &witness);
+    let proof = AssetInclusionProof {
+        tx_hash: tx_hash.into(),
+        recipient,
+        token_address: encode_token_address(target_asset.kind.contract_address),
         token_id: target_asset.kind.variable_index.0.into(),
         amount: target_asset.amount.into(),
+        witness: Bytes::from_str(&witness[2..]).unwrap(),
     };
-    #[cfg(feature = "verbose")]
-    dbg!(recipient);
-    let witness = Bytes::from_str(&witness[2..]).unwrap();
     if let Some(network_config) = network_config {
-        let ok =
-            verify_asset_inclusion_proof(&network_config, vec![asset], recipient, witness.clone())
-                .await;
-        if !ok {
-            anyhow::bail!("invalid witness");
+        verify_inclusion_proof(&network_config, &proof).await?;
+    }
+
+    Ok(proof)
+}
+
+/// Check the witness of the proof with the verifier contract.
+pub async fn verify_inclusion_proof(
+    network_config: &ContractConfig<'static>,
+    proof: &AssetInclusionProof,
+) -> anyhow::Result<()> {
+    let asset = verifier_contract::Asset {
+        token_address: proof.token_address,
+        token_id: proof.token_id,
+        amount: proof.amount,
+    };
+    let ok = verify_asset_inclusion_proof(
+        network_config,
+        vec![asset],
+        proof.recipient,
+        proof.witness.clone(),
+    )
+    .await;
+    if !ok {
+        anyhow::bail!("invalid witness");
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use intmax_rollup_interface::intmax_zkp_core::{
+        sparse_merkle_tree::goldilocks_poseidon::{
+            LayeredLayeredPoseidonSparseMerkleTree, PoseidonSparseMerkleTree,
+        },
+        transaction::asset::TokenKind,
+    };
+
+    use super::*;
+    use crate::utils::key_management::types::MergeKey;
+
+    #[test]
+    fn test_calc_merkle_root() {
+        let leaves = (0..4).map(|_| WrappedHashOut::rand()).collect::<Vec<_>>();
+        let hash = |left, right| PoseidonNodeHash::calc_node_hash(Node::Internal(left, right));
+        let root = hash(hash(leaves[0], leaves[1]), hash(leaves[2], leaves[3]));
+
+        let proof = MerkleProof {
+            root,
+            index: 2,
+            value: leaves[2],
+            siblings: vec![leaves[3], hash(leaves[0], leaves[1])],
+        };
+        assert_eq!(calc_merkle_root(&proof), root);
+
+        let proof = MerkleProof { index: 3, ..proof };
+        assert_ne!(calc_merkle_root(&proof), root);
+    }
+
+    #[test]
+    fn test_verify_diff_tree_leaf() {
+        let user_address = Address::<F>::from_str("0x714bdc6f38947e6d").unwrap();
+        let other_address = Address::<F>::from_str("0x0000000000000001").unwrap();
+        let asset = Asset {
+            kind: TokenKind {
+                contract_address: other_address,
+                variable_index: 0u8.into(),
+            },
+            amount: 100,
+        };
+
+        let proof = make_diff_tree_proof(&[user_address, other_address], &asset, user_address);
+        verify_diff_tree_leaf(user_address, &[asset.clone()], &proof).unwrap();
+
+        // The aggregator cannot pair the proof with other assets.
+        let tampered_asset = Asset {
+            amount: 1000,
+            ..asset.clone()
+        };
+        assert!(verify_diff_tree_leaf(user_address, &[tampered_asset], &proof).is_err());
+        assert!(
+            verify_diff_tree_leaf(user_address, &[asset.clone(), asset.clone()], &proof).is_err()
+        );
+        assert!(verify_diff_tree_leaf(user_address, &[], &proof).is_err());
+        assert!(verify_diff_tree_leaf(other_address, &[asset], &proof).is_err());
+
+        // The transaction hash commits to the diff tree.
+        let nonce = WrappedHashOut::rand();
+        assert_ne!(
+            calc_tx_hash(proof.root, nonce),
+            calc_tx_hash(WrappedHashOut::rand(), nonce)
+        );
+    }
+
+    /// Returns the inclusion proof of `user_address` in the diff tree giving `asset` to each of `receivers`.
+    fn make_diff_tree_proof(
+        receivers: &[Address<F>],
+        asset: &Asset<F>,
+        user_address: Address<F>,
+    ) -> SparseMerkleInclusionProof<WrappedHashOut<F>, WrappedHashOut<F>, WrappedHashOut<F>> {
+        let mut diff_tree = LayeredLayeredPoseidonSparseMerkleTree::new(
+            NodeDataMemory::default(),
+            RootDataTmp::default(),
+        );
+        for receiver_address in receivers {
+            diff_tree
+                .set(
+                    receiver_address.to_hash_out().into(),
+                    asset.kind.contract_address.to_hash_out().into(),
+                    asset.kind.variable_index.to_hash_out().into(),
+                    HashOut::from_partial(&[F::from_canonical_u64(asset.amount)]).into(),
+                )
+                .unwrap();
         }
+        let diff_tree: PoseidonSparseMerkleTree<_, _> = diff_tree.into();
+
+        diff_tree.find(&user_address.to_hash_out().into()).unwrap()
     }
 
-    Ok(witness)
+    #[test]
+    fn test_verify_received_assets() {
+        let user_address = Address::<F>::from_str("0x714bdc6f38947e6d").unwrap();
+        let other_address = Address::<F>::from_str("0x0000000000000001").unwrap();
+        let asset = Asset {
+            kind: TokenKind {
+                contract_address: other_address,
+                variable_index: 0u8.into(),
+            },
+            amount: 100,
+        };
+        let tampered_assets = [Asset {
+            amount: 1000,
+            ..asset.clone()
+        }];
+        let diff_tree_proof =
+            make_diff_tree_proof(&[user_address, other_address], &asset, user_address);
+
+        // A transaction included in the block with two others and the default one.
+        let nonce = WrappedHashOut::rand();
+        let tx_hash = calc_tx_hash(diff_tree_proof.root, nonce);
+        let transactions = vec![WrappedHashOut::rand(), WrappedHashOut::rand(), tx_hash];
+        let default_tx_hash = WrappedHashOut::rand();
+        let hash = |left, right| PoseidonNodeHash::calc_node_hash(Node::Internal(left, right));
+        let transactions_digest = hash(
+            hash(transactions[0], transactions[1]),
+            hash(transactions[2], default_tx_hash),
+        );
+        let digest_inclusion_proof = MerkleProof {
+            root: transactions_digest,
+            index: 2,
+            value: tx_hash,
+            siblings: vec![default_tx_hash, hash(transactions[0], transactions[1])],
+        };
+        verify_received_assets(
+            user_address,
+            &[asset.clone()],
+            ReceivedAssetsCommitment::Transaction {
+                nonce,
+                digest_inclusion_proof: &digest_inclusion_proof,
+                transactions: &transactions,
+            },
+            &diff_tree_proof,
+            transactions_digest,
+            1,
+        )
+        .unwrap();
+
+        assert!(verify_received_assets(
+            user_address,
+            &tampered_assets,
+            ReceivedAssetsCommitment::Transaction {
+                nonce,
+                digest_inclusion_proof: &digest_inclusion_proof,
+                transactions: &transactions
+            },
+            &diff_tree_proof,
+            transactions_digest,
+            1,
+        )
+        .is_err());
+        let error = verify_received_assets(
+            user_address,
+            &[asset.clone()],
+            ReceivedAssetsCommitment::Transaction {
+                nonce: WrappedHashOut::rand(),
+                digest_inclusion_proof: &digest_inclusion_proof,
+                transactions: &transactions,
+            },
+            &diff_tree_proof,
+            transactions_digest,
+            1,
+        )
+        .unwrap_err();
+        assert!(error.to_string().contains("does not match the transaction"));
+        let error = verify_received_assets(
+            user_address,
+            &[asset.clone()],
+            ReceivedAssetsCommitment::Transaction {
+                nonce,
+                digest_inclusion_proof: &digest_inclusion_proof,
+                transactions: &transactions[..2],
+            },
+            &diff_tree_proof,
+            transactions_digest,
+            1,
+        )
+        .unwrap_err();
+        assert!(error.to_string().contains("was not found"));
+        assert!(verify_received_assets(
+            user_address,
+            &[asset.clone()],
+            ReceivedAssetsCommitment::Transaction {
+                nonce,
+                digest_inclusion_proof: &digest_inclusion_proof,
+                transactions: &transactions
+            },
+            &diff_tree_proof,
+            WrappedHashOut::rand(),
+            1,
+        )
+        .is_err());
+
+        // The deposit digest is the root of the deposit tree.
+        let deposit_proof =
+            make_diff_tree_proof(&[other_address, user_address], &asset, user_address);
+        verify_received_assets(
+            user_address,
+            &[asset.clone()],
+            ReceivedAssetsCommitment::Deposit,
+            &deposit_proof,
+            deposit_proof.root,
+            1,
+        )
+        .unwrap();
+
+        assert!(verify_received_assets(
+            user_address,
+            &tampered_assets,
+            ReceivedAssetsCommitment::Deposit,
+            &deposit_proof,
+            deposit_proof.root,
+            1,
+        )
+        .is_err());
+        let error = verify_received_assets(
+            user_address,
+            &[asset],
+            ReceivedAssetsCommitment::Deposit,
+            &deposit_proof,
+            transactions_digest,
+            1,
+        )
+        .unwrap_err();
+        assert!(error
+            .to_string()
+            .contains("the deposit tree does not match"));
+    }
+
+    #[test]
+    fn test_ensure_assets_held() {
+        let user_address = Address::<F>::from_str("0x714bdc6f38947e6d").unwrap();
+        let other_address = Address::<F>::from_str("0x0000000000000001").unwrap();
+        let kind = TokenKind {
+            contract_address: user_address,
+            variable_index: 0u8.into(),
+        };
+        let mut assets = Assets::default();
+        assets.add(kind, 10, MergeKey(WrappedHashOut::rand()));
+        assets.add(kind, 5, MergeKey(WrappedHashOut::rand()));
+
+        let purge_diff = |kind, amount| ContributedAsset {
+            receiver_address: other_address,
+            kind,
+            amount,
+        };
+
+        ensure_assets_held(&assets, &[purge_diff(kind, 15)]).unwrap();
+
+        let error =
+            ensure_assets_held(&assets, &[purge_diff(kind, 10), purge_diff(kind, 6)]).unwrap_err();
+        assert!(error.to_string().starts_with("insufficient balance"));
+
+        // A mistyped token address
+        let other_kind = TokenKind {
+            contract_address: other_address,
+            variable_index: 0u8.into(),
+        };
+        let error = ensure_assets_held(&assets, &[purge_diff(other_kind, 1)]).unwrap_err();
+        assert!(error.to_string().contains(&user_address.to_string()));
+
+        // A mistyped token ID
+        let other_kind = TokenKind {
+            contract_address: user_address,
+            variable_index: 1u8.into(),
+        };
+        assert!(ensure_assets_held(&assets, &[purge_diff(other_kind, 1)]).is_err());
+    }
+
+    #[test]
+    fn test_calc_merge_iterations() {
+        assert_eq!(calc_merge_iterations(0, 0, 8), (0, 0));
+        assert_eq!(calc_merge_iterations(8, 0, 8), (1, 8));
+        assert_eq!(calc_merge_iterations(9, 0, 8), (2, 9));
+        assert_eq!(calc_merge_iterations(20, 8, 8), (2, 16));
+        assert_eq!(calc_merge_iterations(5, 8, 8), (0, 0));
+    }
 }
@@ -0,0 +1,66 @@
+use intmax_rollup_interface::intmax_zkp_core::{
+    merkle_tree::tree::MerkleProof,
+    plonky2::plonk::config::{GenericConfig, PoseidonGoldilocksConfig},
+    sparse_merkle_tree::goldilocks_poseidon::WrappedHashOut,
+    transaction::block_header::{get_block_hash, BlockHeader},
+};
+use serde::{Deserialize, Serialize};
+
+use super::functions::calc_merkle_root;
+
+const D: usize = 2;
+type C = PoseidonGoldilocksConfig;
+type F = <C as GenericConfig<D>>::F;
+
+/// The proof that a block extends the header chain, which is written by `block header-proof`.
+/// `headers_digest_proof` shows that the hash of `prev_block_header` is included in
+/// the block headers digest of `block_header`, so it can be checked without re-proving the block.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct BlockHeaderProof {
+    pub block_header: BlockHeader<F>,
+    pub prev_block_header: BlockHeader<F>,
+    pub headers_digest_proof: MerkleProof<F>,
+}
+
+impl BlockHeaderProof {
+    pub fn new(
+        block_header: BlockHeader<F>,
+        prev_block_header: BlockHeader<F>,
+        block_headers_proof_siblings: Vec<WrappedHashOut<F>>,
+    ) -> Self {
+        let headers_digest_proof = MerkleProof {
+            root: block_header.block_headers_digest.into(),
+            index: prev_block_header.block_number as usize,
+            value: get_block_hash(&prev_block_header).into(),
+            siblings: block_headers_proof_siblings,
+        };
+
+        Self {
+            block_header,
+            prev_block_header,
+            headers_digest_proof,
+        }
+    }
+
+    pub fn verify(&self) -> anyhow::Result<()> {
+        let block_number = self.block_header.block_number;
+        if self.prev_block_header.block_number + 1 != block_number {
+            anyhow::bail!("the prior header does not precede block {block_number}");
+        }
+
+        let prev_block_hash = get_block_hash(&self.prev_block_header);
+        if self.block_header.prev_block_hash != prev_block_hash {
+            anyhow::bail!("block {block_number} does not follow the prior header");
+        }
+
+        let proof = &self.headers_digest_proof;
+        if *proof.value != prev_block_hash
+            || *proof.root != self.block_header.block_headers_digest
+            || calc_merkle_root(proof) != proof.root
+        {
+            anyhow::bail!("invalid block headers digest proof for block {block_number}");
+        }
+
+        Ok(())
+    }
+}
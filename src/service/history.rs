@@ -0,0 +1,313 @@
+//! `tx history`, which lists the transfers sent from and received by an account.
+//! The sent transfers are taken from the sent transactions, including the pending and reverted ones,
+//! and the received transfers from the history counted on sync, since the merged proofs are not kept.
+//! The same records are used to reconstruct the balance at a past block for `account assets --at-block`.
+
+use std::collections::{BTreeMap, HashMap};
+
+use intmax_rollup_interface::intmax_zkp_core::{
+    plonky2::plonk::config::{GenericConfig, PoseidonGoldilocksConfig},
+    transaction::asset::TokenKind,
+    zkdsa::account::Address,
+};
+
+use num_bigint::BigUint;
+
+use crate::utils::key_management::{
+    stats::{TransferDirection, TransferRecord, TransferStats},
+    types::{SentTransaction, TransactionStatus, TxHash},
+};
+
+const D: usize = 2;
+type C = PoseidonGoldilocksConfig;
+type F = <C as GenericConfig<D>>::F;
+
+/// A line of `tx history`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct HistoryEntry {
+    /// UNIX time in seconds when the transaction was sent or the assets were found on sync.
+    /// `None` for the transactions sent by v2.2.1-alpha or earlier.
+    pub timestamp: Option<u64>,
+    pub direction: TransferDirection,
+    /// the transaction hash, or the merge key of a deposit
+    pub tx_hash: String,
+    /// `None` for deposits, or if the recipient of the sent transaction was not recorded
+    pub counterparty: Option<Address<F>>,
+    pub kind: TokenKind<F>,
+    pub amount: u64,
+    pub block_number: Option<u32>,
+    /// `None` for received transfers, which are always approved
+    pub status: Option<TransactionStatus>,
+}
+
+/// Returns the transfers in chronological order.
+pub fn build_tx_history(
+    sent_transactions: &HashMap<TxHash<F>, SentTransaction>,
+    history: &[TransferRecord],
+) -> Vec<HistoryEntry> {
+    let mut entries = vec![];
+    for (tx_hash, sent_transaction) in sent_transactions.iter() {
+        for (recipient, kind, amount) in sent_transaction.outputs_by_recipient() {
+            entries.push(HistoryEntry {
+                timestamp: sent_transaction.created_at,
+                direction: TransferDirection::Sent,
+                tx_hash: tx_hash.to_string(),
+                counterparty: recipient,
+                kind,
+                amount,
+                block_number: sent_transaction.proposed_block_number,
+                status: Some(sent_transaction.status),
+            });
+        }
+    }
+
+    for record in history
+        .iter()
+        .filter(|record| record.direction == TransferDirection::Received)
+    {
+        entries.push(HistoryEntry {
+            timestamp: Some(record.timestamp),
+            direction: TransferDirection::Received,
+            tx_hash: record.reference.clone(),
+            counterparty: record.counterparty,
+            kind: record.kind,
+            amount: record.amount,
+            block_number: record.block_number,
+            status: None,
+        });
+    }
+
+    // The hash breaks ties so that the order does not depend on the map.
+    entries.sort_by(|a, b| {
+        (a.timestamp, a.block_number, &a.tx_hash).cmp(&(b.timestamp, b.block_number, &b.tx_hash))
+    });
+
+    entries
+}
+
+/// The balance of an account at a past block.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct AssetsAtBlock {
+    /// the total amount keyed by the token address and the token id, the same as `Assets::calc_total_amount`
+    pub total_amount_map: BTreeMap<(String, String), BigUint>,
+    /// the number of transfers whose block is unknown, which are assumed to be included at or before the block
+    pub undated_transfers: usize,
+}
+
+/// Reconstruct the balance at `block_number` from the current one by undoing the transfers after it.
+/// The received assets included after the block are subtracted,
+/// and the sent transactions which were not approved by the block are added back.
+/// Reverted transactions are skipped since their assets were already restored.
+pub fn calc_assets_at_block(
+    current_total_amount_map: &BTreeMap<(String, String), BigUint>,
+    sent_transactions: &HashMap<TxHash<F>, SentTransaction>,
+    stats: &TransferStats,
+    block_number: u32,
+) -> anyhow::Result<AssetsAtBlock> {
+    let key = |kind: &TokenKind<F>| {
+        (
+            kind.contract_address.to_string(),
+            kind.variable_index.to_string(),
+        )
+    };
+
+    let mut total_amount_map = current_total_amount_map.clone();
+    let mut undated_transfers = 0;
+    for sent_transaction in sent_transactions.values() {
+        match (
+            sent_transaction.status,
+            sent_transaction.proposed_block_number,
+        ) {
+            (TransactionStatus::Reverted, _) => continue,
+            (TransactionStatus::Approved, None) => {
+                undated_transfers += 1;
+                continue;
+            }
+            (_, Some(proposed_block_number)) if proposed_block_number <= block_number => continue,
+            _ => {}
+        }
+
+        for (kind, amount) in sent_transaction.total_amounts.iter() {
+            *total_amount_map.entry(key(kind)).or_default() += *amount;
+        }
+    }
+
+    let mut received_amount_map: BTreeMap<(String, String), BigUint> = BTreeMap::new();
+    let mut recorded_received_transfers = 0;
+    for record in stats
+        .history
+        .iter()
+        .filter(|record| record.direction == TransferDirection::Received)
+    {
+        recorded_received_transfers += 1;
+        match record.block_number {
+            Some(received_block_number) if received_block_number > block_number => {
+                *received_amount_map.entry(key(&record.kind)).or_default() += record.amount;
+            }
+            Some(_) => {}
+            None => undated_transfers += 1,
+        }
+    }
+
+    // The transfers counted before the history was introduced are not in it.
+    let counted_received_transfers: u64 = stats
+        .totals
+        .iter()
+        .filter(|total| total.direction == TransferDirection::Received)
+        .map(|total| total.count)
+        .sum();
+    undated_transfers +=
+        (counted_received_transfers as usize).saturating_sub(recorded_received_transfers);
+
+    for ((contract_address, variable_index), received_amount) in received_amount_map {
+        let total_amount = total_amount_map
+            .entry((contract_address.clone(), variable_index.clone()))
+            .or_default();
+        if *total_amount < received_amount {
+            anyhow::bail!(
+                "the cached history of token {contract_address} (id: {variable_index}) does not match the current balance"
+            );
+        }
+        *total_amount -= received_amount;
+    }
+    total_amount_map.retain(|_, total_amount| *total_amount != BigUint::default());
+
+    Ok(AssetsAtBlock {
+        total_amount_map,
+        undated_transfers,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use intmax_rollup_interface::intmax_zkp_core::{
+        sparse_merkle_tree::goldilocks_poseidon::WrappedHashOut, transaction::asset::Asset,
+    };
+
+    use super::*;
+    use crate::utils::key_management::types::MergeKey;
+
+    #[test]
+    fn test_build_tx_history() {
+        let counterparty = Address::from_str("0x0000000000000001").unwrap();
+        let kind = TokenKind {
+            contract_address: counterparty,
+            variable_index: 0u8.into(),
+        };
+        let tx_hash = TxHash(WrappedHashOut::rand());
+        let sent_transactions = HashMap::from([(
+            tx_hash,
+            SentTransaction {
+                removed_assets: vec![],
+                proposed_block_number: Some(3),
+                created_at: Some(200),
+                total_amounts: vec![(kind, 10)],
+                recipients: vec![counterparty],
+                status: TransactionStatus::Pending,
+                outputs: vec![],
+            },
+        )]);
+        let record = |timestamp, direction, block_number| TransferRecord {
+            timestamp,
+            direction,
+            kind,
+            counterparty: None,
+            amount: 5,
+            reference: "0x01".to_string(),
+            block_number,
+        };
+        let history = vec![
+            record(300, TransferDirection::Received, Some(4)),
+            // The sent transfers are taken from the sent transactions.
+            record(250, TransferDirection::Sent, None),
+            record(100, TransferDirection::Received, None),
+        ];
+
+        let entries = build_tx_history(&sent_transactions, &history);
+        assert_eq!(
+            entries
+                .iter()
+                .map(|entry| (entry.direction, entry.timestamp, entry.block_number))
+                .collect::<Vec<_>>(),
+            vec![
+                (TransferDirection::Received, Some(100), None),
+                (TransferDirection::Sent, Some(200), Some(3)),
+                (TransferDirection::Received, Some(300), Some(4)),
+            ]
+        );
+        assert_eq!(entries[1].counterparty, Some(counterparty));
+        assert_eq!(entries[1].tx_hash, tx_hash.to_string());
+        assert_eq!(entries[1].status, Some(TransactionStatus::Pending));
+    }
+
+    #[test]
+    fn test_calc_assets_at_block() {
+        let contract_address = Address::from_str("0x0000000000000001").unwrap();
+        let kind = TokenKind {
+            contract_address,
+            variable_index: 0u8.into(),
+        };
+        let token = (
+            contract_address.to_string(),
+            kind.variable_index.to_string(),
+        );
+        let sent_transaction = |proposed_block_number, status| SentTransaction {
+            removed_assets: vec![],
+            proposed_block_number,
+            created_at: Some(200),
+            total_amounts: vec![(kind, 10)],
+            recipients: vec![],
+            status,
+            outputs: vec![],
+        };
+        let sent_transactions = HashMap::from([
+            (
+                TxHash(WrappedHashOut::rand()),
+                sent_transaction(Some(3), TransactionStatus::Approved),
+            ),
+            (
+                TxHash(WrappedHashOut::rand()),
+                sent_transaction(Some(6), TransactionStatus::Approved),
+            ),
+            (
+                TxHash(WrappedHashOut::rand()),
+                sent_transaction(Some(4), TransactionStatus::Reverted),
+            ),
+            (
+                TxHash(WrappedHashOut::rand()),
+                sent_transaction(None, TransactionStatus::Pending),
+            ),
+        ]);
+        let mut stats = TransferStats::default();
+        for (block_number, amount) in [(2, 40), (5, 7)] {
+            stats.record_received(
+                MergeKey(WrappedHashOut::rand()),
+                100,
+                None,
+                block_number,
+                &[Asset { kind, amount }],
+            );
+        }
+
+        // 40 - 10 + 7 - 10 - 10 (pending)
+        let current_total_amount_map = BTreeMap::from([(token.clone(), BigUint::from(17u8))]);
+        let assets =
+            calc_assets_at_block(&current_total_amount_map, &sent_transactions, &stats, 4).unwrap();
+        assert_eq!(
+            assets.total_amount_map,
+            BTreeMap::from([(token.clone(), BigUint::from(30u8))])
+        );
+        assert_eq!(assets.undated_transfers, 0);
+
+        let assets =
+            calc_assets_at_block(&current_total_amount_map, &sent_transactions, &stats, 1).unwrap();
+        assert!(assets.total_amount_map.is_empty());
+
+        let current_total_amount_map = BTreeMap::from([(token, BigUint::from(1u8))]);
+        assert!(
+            calc_assets_at_block(&current_total_amount_map, &HashMap::new(), &stats, 1).is_err()
+        );
+    }
+}
@@ -1,4 +1,4 @@
-use std::{str::FromStr, sync::Arc, time::Duration};
+use std::{collections::HashMap, str::FromStr, sync::Arc, time::Duration};
 
 use intmax_interoperability_plugin::{
     contracts::{
@@ -14,10 +14,10 @@ use intmax_interoperability_plugin::{
         abi::AbiEncode,
         core::types::U256,
         prelude::{builders::ContractCall, k256::ecdsa::SigningKey, SignerMiddleware},
-        providers::{Http, Provider},
+        providers::{Http, Middleware, Provider},
         signers::{LocalWallet, Signer},
-        types::{Bytes, TransactionReceipt, H160, H256},
-        utils::secret_key_to_address,
+        types::{Bytes, Filter, TransactionReceipt, H160, H256},
+        utils::{keccak256, secret_key_to_address},
     },
 };
 use intmax_rollup_interface::{
@@ -34,7 +34,18 @@ use intmax_rollup_interface::{
     },
 };
 
-use crate::service::ethereum::{fetch_polygon_zkevm_test_gas_price, wei_to_gwei};
+use crate::{
+    progress,
+    service::{
+        ethereum::GasPolicy,
+        offer_events::{OfferEvent, OfferEventKind},
+    },
+    utils::hex_string::parse_private_key_env,
+};
+
+const REGISTER_EVENT_SIGNATURE: &str =
+    "Register(uint256,address,bytes32,uint256,uint256,address,bytes32,address,uint256)";
+const ACTIVATE_EVENT_SIGNATURE: &str = "Activate(uint256,bytes32)";
 
 #[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
 pub enum NetworkName {
@@ -77,6 +88,13 @@ impl std::str::FromStr for NetworkName {
     }
 }
 
+/// Encode an intmax address as `bytes32` of the offer manager contract.
+pub fn encode_intmax_account<F: RichField>(address: Address<F>) -> [u8; 32] {
+    let mut address_bytes = address.to_hash_out().to_bytes();
+    address_bytes.reverse();
+    address_bytes.try_into().unwrap()
+}
+
 #[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
 pub struct MakerTransferInfo<F: RichField> {
     pub address: H160,
@@ -91,9 +109,7 @@ impl<F: RichField> MakerTransferInfo<F> {
     }
 
     pub fn intmax_account(&self) -> [u8; 32] {
-        let mut address_bytes = self.intmax_account.to_hash_out().to_bytes();
-        address_bytes.reverse();
-        address_bytes.try_into().unwrap()
+        encode_intmax_account(self.intmax_account)
     }
 
     pub fn asset_id(&self) -> U256 {
@@ -123,9 +139,7 @@ impl<F: RichField> TakerTransferInfo<F> {
     }
 
     pub fn intmax_account(&self) -> [u8; 32] {
-        let mut address_bytes = self.intmax_account.to_hash_out().to_bytes();
-        address_bytes.reverse();
-        address_bytes.try_into().unwrap()
+        encode_intmax_account(self.intmax_account)
     }
 
     pub fn token_address(&self) -> H160 {
@@ -147,16 +161,17 @@ pub fn display_tx_hash(network_config: &ContractConfig<'static>, tx_hash: H256)
 
 pub async fn register_transfer<F: RichField>(
     network_config: &ContractConfig<'static>,
-    secret_key: String,
+    secret_key: &str,
     sending_transfer_info: MakerTransferInfo<F>,
     receiving_transfer_info: TakerTransferInfo<F>,
+    gas_policy: &mut GasPolicy,
     max_gas_price: Option<U256>,
     witness: Bytes,
 ) -> anyhow::Result<U256> {
     let provider = Provider::<Http>::try_from(network_config.rpc_url)
         .unwrap()
         .interval(Duration::from_millis(10u64));
-    let signer_key = SigningKey::from_bytes(&hex::decode(secret_key).unwrap()).unwrap();
+    let signer_key = parse_private_key_env(secret_key)?;
     let my_account = secret_key_to_address(&signer_key);
     let wallet = LocalWallet::new_with_signer(signer_key, my_account, network_config.chain_id);
     let client = SignerMiddleware::new(provider, wallet);
@@ -179,21 +194,11 @@ pub async fn register_transfer<F: RichField>(
         receiving_transfer_info.amount(),
         witness,
     );
-    println!("start register()");
-    let tx = if network_config.rpc_url == "https://rpc.public.zkevm-test.net" {
-        let gas_price = fetch_polygon_zkevm_test_gas_price().await.unwrap();
-        if let Some(max_gas_price) = max_gas_price {
-            if gas_price.standard > max_gas_price {
-                anyhow::bail!(
-                    "Gas prices are currently too high: {} Gwei",
-                    wei_to_gwei(gas_price.standard)
-                );
-            }
-        }
-        tx.gas_price(gas_price.standard)
-    } else {
-        tx
-    };
+    let gas_price = gas_policy
+        .gas_price(network_config, max_gas_price, "register()")
+        .await?;
+    let tx = tx.gas_price(gas_price);
+    progress!("start register()");
     let pending_tx = tx.send().await.unwrap(); // before confirmation
     let tx_hash = pending_tx.tx_hash();
     println!(
@@ -202,7 +207,7 @@ pub async fn register_transfer<F: RichField>(
     );
 
     let tx_receipt: Option<TransactionReceipt> = pending_tx.await.unwrap();
-    println!("end register()");
+    progress!("end register()");
 
     let block_number = tx_receipt
         .clone()
@@ -220,13 +225,15 @@ pub async fn register_transfer<F: RichField>(
 
 pub async fn activate_offer(
     network_config: &ContractConfig<'static>,
-    secret_key: String,
+    secret_key: &str,
     offer_id: U256,
+    gas_policy: &mut GasPolicy,
+    max_gas_price: Option<U256>,
 ) -> anyhow::Result<bool> {
     let provider = Provider::<Http>::try_from(network_config.rpc_url)
         .unwrap()
         .interval(Duration::from_millis(10u64));
-    let signer_key = SigningKey::from_bytes(&hex::decode(secret_key).unwrap()).unwrap();
+    let signer_key = parse_private_key_env(secret_key)?;
     let my_account = secret_key_to_address(&signer_key);
     let wallet = LocalWallet::new_with_signer(signer_key, my_account, network_config.chain_id);
     let client = SignerMiddleware::new(provider, wallet);
@@ -272,7 +279,11 @@ pub async fn activate_offer(
         let token_contract = Erc20Interface::new(taker_token_address, client.clone());
         let approve_tx = token_contract.approve(offer_manager_contract_address, taker_amount);
 
-        println!("start approve()");
+        let gas_price = gas_policy
+            .gas_price(network_config, max_gas_price, "approve()")
+            .await?;
+        let approve_tx = approve_tx.gas_price(gas_price);
+        progress!("start approve()");
         let pending_tx = approve_tx.send().await.unwrap(); // before confirmation
         let tx_hash = pending_tx.tx_hash();
         println!(
@@ -280,7 +291,7 @@ pub async fn activate_offer(
             display_tx_hash(network_config, tx_hash)
         );
         let tx_receipt: Option<TransactionReceipt> = pending_tx.await.unwrap();
-        println!("end approve()");
+        progress!("end approve()");
 
         let block_number = tx_receipt
             .expect("transaction receipt was not found")
@@ -290,7 +301,11 @@ pub async fn activate_offer(
 
         tx
     };
-    println!("start activate()");
+    let gas_price = gas_policy
+        .gas_price(network_config, max_gas_price, "activate()")
+        .await?;
+    let tx = tx.gas_price(gas_price);
+    progress!("start activate()");
     let pending_tx = tx.send().await.unwrap(); // before confirmation
     let tx_hash = pending_tx.tx_hash();
     println!(
@@ -298,7 +313,7 @@ pub async fn activate_offer(
         display_tx_hash(network_config, tx_hash)
     );
     let tx_receipt: Option<TransactionReceipt> = pending_tx.await.unwrap();
-    println!("end activate()");
+    progress!("end activate()");
 
     let block_number = tx_receipt
         .clone()
@@ -411,6 +426,101 @@ pub async fn verify_asset_inclusion_proof(
         .unwrap()
 }
 
+/// Fetch the `Register` and `Activate` events of the offer manager emitted from `from_block` to `to_block`
+/// (the latest block if omitted) in the order they were emitted.
+pub async fn get_offer_events(
+    network_config: &ContractConfig<'static>,
+    from_block: u64,
+    to_block: Option<u64>,
+) -> anyhow::Result<Vec<OfferEvent>> {
+    let rng = &mut rand::thread_rng();
+    let provider = Provider::<Http>::try_from(network_config.rpc_url)
+        .unwrap()
+        .interval(Duration::from_millis(10u64));
+    let wallet = LocalWallet::new(rng).with_chain_id(network_config.chain_id);
+    let client = SignerMiddleware::new(provider, wallet);
+    let client = Arc::new(client);
+
+    let offer_manager_contract_address: H160 = network_config
+        .offer_manager_contract_address
+        .parse()
+        .unwrap();
+    let register_topic = H256::from(keccak256(REGISTER_EVENT_SIGNATURE));
+    let activate_topic = H256::from(keccak256(ACTIVATE_EVENT_SIGNATURE));
+    let mut filter = Filter::new()
+        .address(offer_manager_contract_address)
+        .topic0(vec![register_topic, activate_topic])
+        .from_block(from_block);
+    if let Some(to_block) = to_block {
+        filter = filter.to_block(to_block);
+    }
+    let logs = client
+        .get_logs(&filter)
+        .await
+        .map_err(|err| anyhow::anyhow!("fail to fetch the offer events: {err}"))?;
+    if logs.is_empty() {
+        return Ok(vec![]);
+    }
+
+    // The offer is decoded from its `Register` event, which may be emitted before `from_block`.
+    let mut topic1 = logs
+        .iter()
+        .filter_map(|log| log.topics.get(1).copied())
+        .collect::<Vec<_>>();
+    topic1.sort();
+    topic1.dedup();
+    let contract = OfferManagerContractWrapper::new(offer_manager_contract_address, client);
+    let offers = contract
+        .get_register_events(Some(topic1), None)
+        .await
+        .map_err(|err| anyhow::anyhow!("fail to decode the register events: {err}"))?
+        .into_iter()
+        .map(|offer| (offer.offer_id, offer))
+        .collect::<HashMap<_, _>>();
+
+    let mut events = vec![];
+    for log in logs {
+        let event = if log.topics[0] == register_topic {
+            OfferEventKind::Register
+        } else {
+            OfferEventKind::Activate
+        };
+        let offer_id = U256::from_big_endian(
+            log.topics
+                .get(1)
+                .ok_or_else(|| anyhow::anyhow!("the offer ID of the {event} event is missing"))?
+                .as_bytes(),
+        );
+        let offer = offers.get(&offer_id).ok_or_else(|| {
+            anyhow::anyhow!("the register event of offer {offer_id} was not found")
+        })?;
+        events.push(OfferEvent {
+            block_number: log.block_number.unwrap_or_default().as_u64(),
+            transaction_hash: log.transaction_hash.unwrap_or_default(),
+            event,
+            offer_id,
+            maker: offer.maker,
+            maker_intmax_address: offer.maker_intmax_address.into(),
+            maker_asset_id: offer.maker_asset_id,
+            maker_amount: offer.maker_amount,
+            taker: offer.taker,
+            // The taker on intmax is given when the offer is activated.
+            taker_intmax_address: if event == OfferEventKind::Activate {
+                log.topics
+                    .get(2)
+                    .copied()
+                    .unwrap_or_else(|| offer.taker_intmax_address.into())
+            } else {
+                offer.taker_intmax_address.into()
+            },
+            taker_token_address: offer.taker_token_address,
+            taker_amount: offer.taker_amount,
+        });
+    }
+
+    Ok(events)
+}
+
 pub async fn get_offer(
     network_config: &ContractConfig<'static>,
     offer_id: U256,
@@ -471,14 +581,16 @@ pub async fn get_offer(
 
 pub async fn lock_offer<F: RichField>(
     network_config: &ContractConfig<'static>,
-    secret_key: String,
+    secret_key: &str,
     sending_transfer_info: TakerTransferInfo<F>,
     receiving_transfer_info: MakerTransferInfo<F>,
-) -> U256 {
+    gas_policy: &mut GasPolicy,
+    max_gas_price: Option<U256>,
+) -> anyhow::Result<U256> {
     let provider = Provider::<Http>::try_from(network_config.rpc_url)
         .unwrap()
         .interval(Duration::from_millis(10u64));
-    let signer_key = SigningKey::from_bytes(&hex::decode(secret_key).unwrap()).unwrap();
+    let signer_key = parse_private_key_env(secret_key)?;
     let my_account = secret_key_to_address(&signer_key);
     let wallet = LocalWallet::new_with_signer(signer_key, my_account, network_config.chain_id);
     let client = SignerMiddleware::new(provider, wallet);
@@ -517,7 +629,11 @@ pub async fn lock_offer<F: RichField>(
         let approve_tx =
             token_contract.approve(reverse_offer_manager_contract_address, taker_amount);
 
-        println!("start approve()");
+        let gas_price = gas_policy
+            .gas_price(network_config, max_gas_price, "approve()")
+            .await?;
+        let approve_tx = approve_tx.gas_price(gas_price);
+        progress!("start approve()");
         let pending_tx = approve_tx.send().await.unwrap(); // before confirmation
         let tx_hash = pending_tx.tx_hash();
         println!(
@@ -525,7 +641,7 @@ pub async fn lock_offer<F: RichField>(
             display_tx_hash(network_config, tx_hash)
         );
         let tx_receipt: Option<TransactionReceipt> = pending_tx.await.unwrap();
-        println!("end approve()");
+        progress!("end approve()");
 
         let block_number = tx_receipt
             .expect("transaction receipt was not found")
@@ -536,7 +652,11 @@ pub async fn lock_offer<F: RichField>(
         tx
     };
 
-    println!("start register()");
+    let gas_price = gas_policy
+        .gas_price(network_config, max_gas_price, "register()")
+        .await?;
+    let tx = tx.gas_price(gas_price);
+    progress!("start register()");
     let pending_tx = tx.send().await.unwrap(); // before confirmation
     let tx_hash = pending_tx.tx_hash();
     println!(
@@ -544,7 +664,7 @@ pub async fn lock_offer<F: RichField>(
         display_tx_hash(network_config, tx_hash)
     );
     let tx_receipt: Option<TransactionReceipt> = pending_tx.await.unwrap();
-    println!("end register()");
+    progress!("end register()");
 
     let block_number = tx_receipt
         .clone()
@@ -557,18 +677,20 @@ pub async fn lock_offer<F: RichField>(
     let is_locked = contract.is_registered(offer_id).await.unwrap();
     assert!(is_locked);
 
-    offer_id
+    Ok(offer_id)
 }
 
 pub async fn unlock_offer(
     network_config: &ContractConfig<'static>,
-    secret_key: String,
+    secret_key: &str,
     offer_id: U256,
     witness: Bytes,
+    gas_policy: &mut GasPolicy,
+    max_gas_price: Option<U256>,
 ) -> anyhow::Result<bool> {
     let provider =
         Provider::<Http>::try_from(network_config.rpc_url)?.interval(Duration::from_millis(10u64));
-    let signer_key = SigningKey::from_bytes(&hex::decode(secret_key.clone()).unwrap()).unwrap();
+    let signer_key = parse_private_key_env(secret_key)?;
     let my_account = secret_key_to_address(&signer_key);
     let wallet = LocalWallet::new_with_signer(signer_key, my_account, network_config.chain_id);
     let client = SignerMiddleware::new(provider, wallet);
@@ -592,16 +714,21 @@ pub async fn unlock_offer(
         return Ok(true);
     }
 
-    // dbg!(offer_id, &witness);
-    // contract
-    //     .check_witness(offer_id, witness.clone())
-    //     .call()
-    //     .await?;
+    // Transactions with invalid witnesses are reverted on-chain, so verify them before sending.
+    contract
+        .check_witness(offer_id, witness.clone())
+        .call()
+        .await
+        .map_err(|err| anyhow::anyhow!("witness verification failed: {err}"))?;
 
     let tx = contract.activate(offer_id, witness);
 
     // send token and activate flag on scroll
-    println!("start activate()");
+    let gas_price = gas_policy
+        .gas_price(network_config, max_gas_price, "activate()")
+        .await?;
+    let tx = tx.gas_price(gas_price);
+    progress!("start activate()");
     let pending_tx = tx.send().await.unwrap(); // before confirmation
     let tx_hash = pending_tx.tx_hash();
     println!(
@@ -609,7 +736,7 @@ pub async fn unlock_offer(
         display_tx_hash(network_config, tx_hash)
     );
     let tx_receipt: Option<TransactionReceipt> = pending_tx.await.unwrap();
-    println!("end activate()");
+    progress!("end activate()");
 
     let block_number = tx_receipt
         .clone()
@@ -623,6 +750,35 @@ pub async fn unlock_offer(
     Ok(is_unlocked)
 }
 
+/// Verify the confirmation witness against the offer without sending a transaction.
+pub async fn check_unlock_witness(
+    network_config: &ContractConfig<'static>,
+    offer_id: U256,
+    witness: Bytes,
+) -> anyhow::Result<()> {
+    let rng = &mut rand::thread_rng();
+    let provider =
+        Provider::<Http>::try_from(network_config.rpc_url)?.interval(Duration::from_millis(10u64));
+    let wallet = LocalWallet::new(rng).with_chain_id(network_config.chain_id);
+    let client = SignerMiddleware::new(provider, wallet);
+    let client = Arc::new(client);
+
+    let reverse_offer_manager_contract_address = network_config
+        .reverse_offer_manager_contract_address
+        .parse()
+        .unwrap();
+    let contract =
+        OfferManagerReverseContractWrapper::new(reverse_offer_manager_contract_address, client);
+
+    contract
+        .check_witness(offer_id, witness)
+        .call()
+        .await
+        .map_err(|err| anyhow::anyhow!("witness verification failed: {err}"))?;
+
+    Ok(())
+}
+
 pub async fn is_token_allowed(
     network_config: &ContractConfig<'static>,
     token_address: H160,
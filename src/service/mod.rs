@@ -1,7 +1,35 @@
 mod airdrop;
-pub use airdrop::read_distribution_from_csv;
+pub use airdrop::{
+    calc_distribution_total_amount, distribution_template, read_distribution_file,
+    validate_distribution_token_addresses,
+};
+pub mod batch;
+pub mod block_stats;
+pub mod block_view;
 pub mod builder;
+pub mod bulk;
+pub mod capability;
+pub mod clock;
+pub mod codec;
+pub mod error;
+pub mod error_code;
 pub mod ethereum;
+pub mod export;
+pub mod external_signer;
+pub mod faucet;
 pub mod functions;
+pub mod header_proof;
+pub mod history;
 pub mod interoperability;
+pub mod offer_events;
+pub mod planner;
+pub mod policy;
+pub mod privacy;
 pub mod prompt;
+pub mod proof_batch;
+pub mod receipt;
+pub mod register_intent;
+pub mod replay;
+pub mod response;
+pub mod retry;
+pub mod statement;
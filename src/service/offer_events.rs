@@ -0,0 +1,152 @@
+use std::str::FromStr;
+
+use intmax_interoperability_plugin::ethers::types::{H160, H256, U256};
+use serde::Serialize;
+
+use super::export::escape_csv_field;
+
+pub const OFFER_EVENTS_CSV_HEADER: &str = "block_number,transaction_hash,event,offer_id,maker,maker_intmax_address,maker_asset_id,maker_amount,taker,taker_intmax_address,taker_token_address,taker_amount";
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum OfferEventKind {
+    Register,
+    Activate,
+}
+
+impl std::fmt::Display for OfferEventKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Register => write!(f, "register"),
+            Self::Activate => write!(f, "activate"),
+        }
+    }
+}
+
+/// A `Register` or `Activate` event of the offer manager contract.
+/// The fields of the offer are taken from its `Register` event, so that both kinds of events can be filtered alike.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct OfferEvent {
+    pub block_number: u64,
+    pub transaction_hash: H256,
+    pub event: OfferEventKind,
+    pub offer_id: U256,
+    pub maker: H160,
+    pub maker_intmax_address: H256,
+    pub maker_asset_id: U256,
+    pub maker_amount: U256,
+    pub taker: H160,
+    pub taker_intmax_address: H256,
+    pub taker_token_address: H160,
+    pub taker_amount: U256,
+}
+
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct OfferEventFilter {
+    pub maker: Option<H160>,
+    pub taker: Option<H160>,
+    /// matches either the maker or the taker on intmax
+    pub intmax_address: Option<H256>,
+}
+
+impl OfferEventFilter {
+    pub fn matches(&self, event: &OfferEvent) -> bool {
+        self.maker.map_or(true, |maker| event.maker == maker)
+            && self.taker.map_or(true, |taker| event.taker == taker)
+            && self.intmax_address.map_or(true, |intmax_address| {
+                event.maker_intmax_address == intmax_address
+                    || event.taker_intmax_address == intmax_address
+            })
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum OfferEventsFormat {
+    Json,
+    Csv,
+}
+
+impl FromStr for OfferEventsFormat {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "json" => Ok(Self::Json),
+            "csv" => Ok(Self::Csv),
+            _ => anyhow::bail!("unknown format: {s}"),
+        }
+    }
+}
+
+/// The amounts and IDs are written in decimal, and the addresses in hex.
+pub fn offer_events_to_csv(events: &[OfferEvent]) -> String {
+    let mut csv = format!("{OFFER_EVENTS_CSV_HEADER}\n");
+    for event in events {
+        let record = [
+            event.block_number.to_string(),
+            format!("{:?}", event.transaction_hash),
+            event.event.to_string(),
+            event.offer_id.to_string(),
+            format!("{:?}", event.maker),
+            format!("{:?}", event.maker_intmax_address),
+            event.maker_asset_id.to_string(),
+            event.maker_amount.to_string(),
+            format!("{:?}", event.taker),
+            format!("{:?}", event.taker_intmax_address),
+            format!("{:?}", event.taker_token_address),
+            event.taker_amount.to_string(),
+        ]
+        .iter()
+        .map(|field| escape_csv_field(field))
+        .collect::<Vec<_>>();
+        csv += &format!("{}\n", record.join(","));
+    }
+
+    csv
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_offer_events() {
+        let event = OfferEvent {
+            block_number: 100,
+            transaction_hash: H256::repeat_byte(1),
+            event: OfferEventKind::Register,
+            offer_id: 3.into(),
+            maker: H160::repeat_byte(2),
+            maker_intmax_address: H256::repeat_byte(3),
+            maker_asset_id: 0.into(),
+            maker_amount: 10.into(),
+            taker: H160::repeat_byte(4),
+            taker_intmax_address: H256::repeat_byte(5),
+            taker_token_address: H160::zero(),
+            taker_amount: 1_000_000_000u64.into(),
+        };
+
+        assert!(OfferEventFilter::default().matches(&event));
+        let filter = OfferEventFilter {
+            maker: Some(H160::repeat_byte(2)),
+            intmax_address: Some(H256::repeat_byte(5)),
+            ..Default::default()
+        };
+        assert!(filter.matches(&event));
+        let filter = OfferEventFilter {
+            taker: Some(H160::repeat_byte(2)),
+            ..Default::default()
+        };
+        assert!(!filter.matches(&event));
+
+        let csv = offer_events_to_csv(&[event]);
+        let lines = csv.lines().collect::<Vec<_>>();
+        assert_eq!(lines[0], OFFER_EVENTS_CSV_HEADER);
+        assert_eq!(
+            lines[1].split(',').count(),
+            OFFER_EVENTS_CSV_HEADER.split(',').count()
+        );
+        assert!(lines[1].starts_with("100,0x0101"));
+        assert!(lines[1].ends_with(",0x0000000000000000000000000000000000000000,1000000000"));
+    }
+}
@@ -0,0 +1,412 @@
+//! Packing of independent transfers into as few transactions as the limits of the rollup allow,
+//! which is shared by `tx bulk-mint`, `tx bulk-transfer` and `tx batch-send`,
+//! and the choice of the leaves each transaction consumes.
+
+use intmax_rollup_interface::{
+    constants::ROLLUP_CONSTANTS,
+    intmax_zkp_core::{
+        plonky2::plonk::config::{GenericConfig, PoseidonGoldilocksConfig},
+        transaction::asset::{ContributedAsset, TokenKind},
+        zkdsa::account::Address,
+    },
+};
+
+use rand::Rng;
+
+use super::privacy::select_private_inputs;
+
+const D: usize = 2;
+type C = PoseidonGoldilocksConfig;
+type F = <C as GenericConfig<D>>::F;
+
+/// An output to send from an account in the wallet.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TransferIntent {
+    pub sender_address: Address<F>,
+    pub output: ContributedAsset<F>,
+}
+
+/// The outputs sent in one transaction.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PlannedTx {
+    pub sender_address: Address<F>,
+    pub outputs: Vec<ContributedAsset<F>>,
+}
+
+impl PlannedTx {
+    fn token_kinds(&self) -> Vec<TokenKind<F>> {
+        let mut kinds = vec![];
+        for output in self.outputs.iter() {
+            if !kinds.contains(&output.kind) {
+                kinds.push(output.kind);
+            }
+        }
+
+        kinds
+    }
+
+    /// The leaves of the diff tree: one for each output and at most one change for each token kind.
+    fn n_leaves(&self) -> usize {
+        self.outputs.len() + self.token_kinds().len()
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct TransferLimits {
+    /// the leaves of the diff tree of a transaction, including the change returned to the sender
+    pub max_leaves: usize,
+    pub max_outputs: usize,
+}
+
+impl TransferLimits {
+    pub fn transfer() -> Self {
+        Self {
+            max_leaves: ROLLUP_CONSTANTS.n_diffs,
+            max_outputs: ROLLUP_CONSTANTS.n_diffs,
+        }
+    }
+
+    /// The tokens minted for the outputs are received by the sender and merged before they are sent,
+    /// so the outputs are also limited by `N_MERGES`.
+    pub fn mint() -> Self {
+        Self {
+            max_leaves: ROLLUP_CONSTANTS.n_diffs,
+            max_outputs: ROLLUP_CONSTANTS.n_diffs.min(ROLLUP_CONSTANTS.n_merges),
+        }
+    }
+
+    fn fits(&self, planned_tx: &PlannedTx) -> bool {
+        planned_tx.outputs.len() <= self.max_outputs && planned_tx.n_leaves() <= self.max_leaves
+    }
+}
+
+/// Pack the outputs of each sender into the fewest transactions within `limits`.
+/// The outputs to the same recipient and token kind are summed up, since a transaction has one leaf for them.
+/// Each output is put into the first transaction of the sender with room for it, in the order given.
+/// Fails if the sum of the outputs to a recipient overflows.
+pub fn compose_transfers(
+    intents: Vec<TransferIntent>,
+    limits: &TransferLimits,
+) -> anyhow::Result<Vec<PlannedTx>> {
+    let mut planned_txs: Vec<PlannedTx> = vec![];
+    let mut aggregated_intents: Vec<TransferIntent> = vec![];
+    for intent in intents {
+        if let Some(aggregated_intent) = aggregated_intents.iter_mut().find(|aggregated_intent| {
+            aggregated_intent.sender_address == intent.sender_address
+                && aggregated_intent.output.receiver_address == intent.output.receiver_address
+                && aggregated_intent.output.kind == intent.output.kind
+        }) {
+            aggregated_intent.output.amount = aggregated_intent
+                .output
+                .amount
+                .checked_add(intent.output.amount)
+                .ok_or_else(|| {
+                    anyhow::anyhow!(
+                        "the total amount sent from {} to {} overflows",
+                        intent.sender_address,
+                        intent.output.receiver_address
+                    )
+                })?;
+        } else {
+            aggregated_intents.push(intent);
+        }
+    }
+
+    for intent in aggregated_intents {
+        let mut placed = false;
+        for planned_tx in planned_txs
+            .iter_mut()
+            .filter(|planned_tx| planned_tx.sender_address == intent.sender_address)
+        {
+            planned_tx.outputs.push(intent.output.clone());
+            if limits.fits(planned_tx) {
+                placed = true;
+                break;
+            }
+            planned_tx.outputs.pop();
+        }
+
+        if !placed {
+            planned_txs.push(PlannedTx {
+                sender_address: intent.sender_address,
+                outputs: vec![intent.output],
+            });
+        }
+    }
+
+    Ok(planned_txs)
+}
+
+/// The leaves consumed to send the outputs of one token kind, and the change returned to the sender.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct InputSelection<A> {
+    pub inputs: Vec<A>,
+    pub change: u64,
+}
+
+/// Choose the leaves of one token kind consumed to send `output_amount`.
+/// By default, a leaf of exactly `output_amount` is used alone, and otherwise the largest leaves are consumed first.
+/// With `privacy_rng`, the leaves are chosen by `select_private_inputs` within `max_inputs`.
+/// The choice depends only on the order of `assets` and the state of `privacy_rng`,
+/// so the caller sorts `assets` to make it reproducible.
+pub fn select_inputs<A, R: Rng>(
+    mut assets: Vec<A>,
+    amount_of: impl Fn(&A) -> u64,
+    output_amount: u64,
+    max_inputs: usize,
+    privacy_rng: Option<&mut R>,
+) -> anyhow::Result<InputSelection<A>> {
+    let inputs = if let Some(rng) = privacy_rng {
+        select_private_inputs(assets, &amount_of, output_amount, max_inputs, rng)
+    } else {
+        // The leaf with the largest amount is processed first.
+        // However, if there is a leaf with the same value as output_amount, it is given priority.
+        assets.sort_by_key(|asset| {
+            let amount = amount_of(asset);
+            std::cmp::Reverse((amount == output_amount, amount))
+        });
+
+        let mut input_amount = 0;
+        let mut n_inputs = 0;
+        for asset in assets.iter() {
+            if output_amount <= input_amount {
+                break;
+            }
+            input_amount += amount_of(asset);
+            n_inputs += 1;
+        }
+        assets.truncate(n_inputs);
+
+        assets
+    };
+
+    let input_amount = inputs.iter().map(&amount_of).sum::<u64>();
+    if output_amount > input_amount {
+        anyhow::bail!("output asset amount is too much");
+    }
+
+    // The difference between input (what you own) and output (what you give to others) is given to yourself.
+    Ok(InputSelection {
+        inputs,
+        change: input_amount - output_amount,
+    })
+}
+
+/// Split the planned transactions into rounds sent one after another.
+/// Each round has at most one transaction of each sender, so that the round is sent in one block.
+pub fn into_rounds(planned_txs: Vec<PlannedTx>) -> Vec<Vec<PlannedTx>> {
+    let mut rounds: Vec<Vec<PlannedTx>> = vec![];
+    for planned_tx in planned_txs {
+        let round = rounds.iter_mut().find(|round| {
+            round
+                .iter()
+                .all(|other| other.sender_address != planned_tx.sender_address)
+        });
+        if let Some(round) = round {
+            round.push(planned_tx);
+        } else {
+            rounds.push(vec![planned_tx]);
+        }
+    }
+
+    rounds
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{collections::HashMap, str::FromStr};
+
+    use proptest::{collection::vec, prelude::*};
+    use rand::{rngs::StdRng, SeedableRng};
+
+    use super::*;
+
+    fn address(i: u64) -> Address<F> {
+        Address::from_str(&format!("0x{i:016x}")).unwrap()
+    }
+
+    fn intent(sender: u64, receiver: u64, token_id: u8, amount: u64) -> TransferIntent {
+        TransferIntent {
+            sender_address: address(sender),
+            output: ContributedAsset {
+                receiver_address: address(receiver),
+                kind: TokenKind {
+                    contract_address: address(sender),
+                    variable_index: token_id.into(),
+                },
+                amount,
+            },
+        }
+    }
+
+    #[test]
+    fn test_compose_transfers() {
+        let limits = TransferLimits {
+            max_leaves: 4,
+            max_outputs: 4,
+        };
+
+        // The outputs to the same recipient and token kind are summed up.
+        let planned_txs =
+            compose_transfers(vec![intent(1, 2, 0, 10), intent(1, 2, 0, 5)], &limits).unwrap();
+        assert_eq!(planned_txs.len(), 1);
+        assert_eq!(planned_txs[0].outputs[0].amount, 15);
+
+        // 3 outputs of one token kind take 4 leaves with the change.
+        let intents = (2..6).map(|i| intent(1, i, 0, 1)).collect::<Vec<_>>();
+        let planned_txs = compose_transfers(intents, &limits).unwrap();
+        assert_eq!(
+            planned_txs
+                .iter()
+                .map(|planned_tx| planned_tx.outputs.len())
+                .collect::<Vec<_>>(),
+            vec![3, 1]
+        );
+
+        // A later output fills the room left in an earlier transaction.
+        let intents = vec![
+            intent(1, 2, 0, 1),
+            intent(1, 3, 1, 1),
+            intent(1, 4, 2, 1),
+            intent(1, 5, 0, 1),
+        ];
+        let planned_txs = compose_transfers(intents, &limits).unwrap();
+        assert_eq!(planned_txs.len(), 2);
+        assert_eq!(planned_txs[1].outputs.len(), 2);
+
+        let limits = TransferLimits {
+            max_leaves: 4,
+            max_outputs: 1,
+        };
+        let intents = vec![intent(1, 2, 0, 1), intent(1, 3, 0, 1), intent(2, 3, 0, 1)];
+        let rounds = into_rounds(compose_transfers(intents, &limits).unwrap());
+        assert_eq!(
+            rounds.iter().map(|round| round.len()).collect::<Vec<_>>(),
+            vec![2, 1]
+        );
+
+        let error = compose_transfers(vec![intent(1, 2, 0, u64::MAX), intent(1, 2, 0, 1)], &limits)
+            .unwrap_err();
+        assert!(error.to_string().contains("overflows"));
+    }
+
+    // A leaf is `(id, amount)`, so that the same leaf is found even if another one has the same amount.
+    fn select(
+        amounts: &[u64],
+        output_amount: u64,
+        max_inputs: usize,
+        seed: Option<u64>,
+    ) -> anyhow::Result<InputSelection<(usize, u64)>> {
+        let assets = amounts.iter().copied().enumerate().collect::<Vec<_>>();
+        let mut rng = seed.map(StdRng::seed_from_u64);
+        select_inputs(
+            assets,
+            |asset| asset.1,
+            output_amount,
+            max_inputs,
+            rng.as_mut(),
+        )
+    }
+
+    proptest! {
+        #[test]
+        fn test_select_inputs(
+            amounts in vec(1u64..1_000_000, 0..16),
+            output_amount in 1u64..4_000_000,
+            max_inputs in 1usize..16,
+            seed in proptest::option::of(any::<u64>()),
+        ) {
+            let total_amount = amounts.iter().sum::<u64>();
+            let selection = match select(&amounts, output_amount, max_inputs, seed) {
+                Ok(selection) => selection,
+                Err(_) => {
+                    prop_assert!(total_amount < output_amount);
+                    return Ok(());
+                }
+            };
+
+            // The inputs cover the output and the change is exact.
+            let input_amount = selection.inputs.iter().map(|asset| asset.1).sum::<u64>();
+            prop_assert!(input_amount >= output_amount);
+            prop_assert_eq!(input_amount - output_amount, selection.change);
+
+            // Each input is a leaf of the wallet, consumed only once.
+            let mut ids = selection.inputs.iter().map(|asset| asset.0).collect::<Vec<_>>();
+            ids.sort_unstable();
+            ids.dedup();
+            prop_assert_eq!(ids.len(), selection.inputs.len());
+            for (id, amount) in selection.inputs.iter() {
+                prop_assert_eq!(amounts[*id], *amount);
+            }
+
+            // A leaf of the exact amount is used alone.
+            if amounts.contains(&output_amount) {
+                prop_assert_eq!(selection.inputs.len(), 1);
+                prop_assert_eq!(selection.change, 0);
+            }
+
+            // No more leaves are consumed than needed, except one decoy within `max_inputs` in the privacy mode.
+            let (needed_inputs, _) = selection.inputs.split_at(selection.inputs.len() - 1);
+            if needed_inputs.iter().map(|asset| asset.1).sum::<u64>() >= output_amount {
+                prop_assert!(seed.is_some());
+                prop_assert!(selection.inputs.len() <= max_inputs);
+            }
+
+            // The same leaves and seed give the same choice.
+            prop_assert_eq!(
+                select(&amounts, output_amount, max_inputs, seed).unwrap(),
+                selection
+            );
+        }
+
+        #[test]
+        fn test_compose_transfers_invariants(
+            transfers in vec((1u64..4, 4u64..8, 0u8..3, prop_oneof![1u64..1000, u64::MAX - 1000..=u64::MAX]), 0..32),
+            max_leaves in 2usize..8,
+            max_outputs in 1usize..8,
+        ) {
+            let limits = TransferLimits { max_leaves, max_outputs };
+            let intents = transfers
+                .iter()
+                .map(|(sender, receiver, token_id, amount)| intent(*sender, *receiver, *token_id, *amount))
+                .collect::<Vec<_>>();
+            // The amount sent from each sender to each recipient is kept for every token kind.
+            let mut sent_amounts = HashMap::new();
+            for intent in intents.iter() {
+                *sent_amounts
+                    .entry((intent.sender_address, intent.output.receiver_address, intent.output.kind))
+                    .or_insert(0u128) += intent.output.amount as u128;
+            }
+            let planned_txs = match compose_transfers(intents.clone(), &limits) {
+                Ok(planned_txs) => planned_txs,
+                Err(_) => {
+                    prop_assert!(sent_amounts.values().any(|amount| *amount > u64::MAX as u128));
+                    return Ok(());
+                }
+            };
+
+            // Every transaction fits in the limits.
+            for planned_tx in planned_txs.iter() {
+                prop_assert!(limits.fits(planned_tx));
+            }
+
+            let mut planned_amounts = HashMap::new();
+            for planned_tx in planned_txs.iter() {
+                for output in planned_tx.outputs.iter() {
+                    *planned_amounts
+                        .entry((planned_tx.sender_address, output.receiver_address, output.kind))
+                        .or_insert(0u128) += output.amount as u128;
+                }
+            }
+            prop_assert_eq!(planned_amounts, sent_amounts);
+
+            // A round never has two transactions of one sender.
+            for round in into_rounds(planned_txs) {
+                let mut senders = round.iter().map(|planned_tx| planned_tx.sender_address.to_string()).collect::<Vec<_>>();
+                senders.sort();
+                senders.dedup();
+                prop_assert_eq!(senders.len(), round.len());
+            }
+        }
+    }
+}
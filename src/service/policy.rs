@@ -0,0 +1,236 @@
+//! The policy endpoint, which approves or rejects each transfer before it is proved,
+//! so that an organization can enforce its own approval workflow without forking this CLI.
+//!
+//! The CLI POSTs a `PolicyRequest` to `policy.url` and proves the transaction only if the response approves it.
+//! If `policy.signer` is set, an approval must be signed by that Ethereum address with `personal_sign`
+//! over the keccak256 hash of the exact request body, so that a proxy in between cannot forge it.
+//! The request has a random id, so that an approval of one request is not valid for another.
+
+use std::{
+    str::FromStr,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use anyhow::Context;
+use intmax_interoperability_plugin::ethers::{
+    types::{Signature, H160},
+    utils::keccak256,
+};
+use intmax_rollup_interface::intmax_zkp_core::{
+    plonky2::plonk::config::{GenericConfig, PoseidonGoldilocksConfig},
+    sparse_merkle_tree::goldilocks_poseidon::WrappedHashOut,
+    transaction::asset::ContributedAsset,
+    zkdsa::account::Address,
+};
+use reqwest::header::CONTENT_TYPE;
+use serde::{Deserialize, Serialize};
+
+const D: usize = 2;
+type C = PoseidonGoldilocksConfig;
+type F = <C as GenericConfig<D>>::F;
+
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct PolicyConfig {
+    /// the endpoint approving each transfer before it is proved
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub url: Option<String>,
+    /// the Ethereum address which must sign the approvals
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub signer: Option<H160>,
+}
+
+/// An output of the transfer described to the policy endpoint.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PolicyOutput {
+    pub recipient: String,
+    pub token_address: String,
+    pub token_id: String,
+    pub amount: u64,
+}
+
+/// The body POSTed to the policy endpoint.
+/// The fields are encoded in this order without spaces, and the outputs are sorted,
+/// so that the same transfer is always described by the same bytes except for `request_id` and `timestamp`.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PolicyRequest {
+    pub request_id: String,
+    /// UNIX time in seconds
+    pub timestamp: u64,
+    pub sender: String,
+    pub outputs: Vec<PolicyOutput>,
+}
+
+impl PolicyRequest {
+    pub fn new(user_address: Address<F>, purge_diffs: &[ContributedAsset<F>]) -> Self {
+        let mut outputs = purge_diffs
+            .iter()
+            .map(|asset| PolicyOutput {
+                recipient: asset.receiver_address.to_string(),
+                token_address: asset.kind.contract_address.to_string(),
+                token_id: asset.kind.variable_index.to_string(),
+                amount: asset.amount,
+            })
+            .collect::<Vec<_>>();
+        outputs.sort_by(|a, b| {
+            (&a.recipient, &a.token_address, &a.token_id, a.amount).cmp(&(
+                &b.recipient,
+                &b.token_address,
+                &b.token_id,
+                b.amount,
+            ))
+        });
+
+        Self {
+            request_id: WrappedHashOut::<F>::rand().to_string(),
+            timestamp: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|duration| duration.as_secs())
+                .unwrap_or_default(),
+            sender: user_address.to_string(),
+            outputs,
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PolicyResponse {
+    pub approved: bool,
+    /// why the transfer was rejected
+    #[serde(default)]
+    pub reason: Option<String>,
+    /// the signature over the keccak256 hash of the request body, required if `policy.signer` is set
+    #[serde(default)]
+    pub signature: Option<String>,
+}
+
+impl PolicyResponse {
+    /// Fails unless the transfer was approved with a valid signature of `signer`.
+    pub fn ensure_approved(&self, request_body: &[u8], signer: Option<H160>) -> anyhow::Result<()> {
+        if !self.approved {
+            anyhow::bail!(
+                "the transfer was rejected by the policy endpoint: {}",
+                self.reason.as_deref().unwrap_or("no reason was given")
+            );
+        }
+
+        if let Some(signer) = signer {
+            let signature = self
+                .signature
+                .as_deref()
+                .context("the approval of the policy endpoint is not signed")?;
+            let signature = Signature::from_str(signature)
+                .context("the approval of the policy endpoint has an invalid signature")?;
+            signature
+                .verify(keccak256(request_body).to_vec(), signer)
+                .with_context(|| {
+                    format!("the approval of the policy endpoint is not signed by {signer:?}")
+                })?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Ask the policy endpoint to approve the transfer. Nothing is asked if `policy.url` is not set.
+pub async fn request_policy_approval(
+    policy: &PolicyConfig,
+    user_address: Address<F>,
+    purge_diffs: &[ContributedAsset<F>],
+) -> anyhow::Result<()> {
+    let policy_url = if let Some(policy_url) = &policy.url {
+        policy_url
+    } else {
+        return Ok(());
+    };
+
+    let body = serde_json::to_vec(&PolicyRequest::new(user_address, purge_diffs))?;
+    let resp = reqwest::Client::new()
+        .post(policy_url)
+        .body(body.clone())
+        .header(CONTENT_TYPE, "application/json")
+        .send()
+        .await
+        .with_context(|| format!("fail to request the policy endpoint {policy_url}"))?;
+    if !resp.status().is_success() {
+        let error_message = resp.text().await?;
+        anyhow::bail!("unexpected response from {policy_url}: {error_message}");
+    }
+    let response: PolicyResponse = resp
+        .json()
+        .await
+        .with_context(|| format!("invalid response from {policy_url}"))?;
+
+    response.ensure_approved(&body, policy.signer)
+}
+
+#[cfg(test)]
+mod tests {
+    use intmax_interoperability_plugin::ethers::{
+        prelude::k256::ecdsa::SigningKey,
+        signers::{LocalWallet, Signer},
+        utils::hash_message,
+    };
+    use intmax_rollup_interface::intmax_zkp_core::transaction::asset::TokenKind;
+
+    use super::*;
+
+    #[test]
+    fn test_policy_approval() {
+        let sender = Address::from_str("0x0000000000000001").unwrap();
+        let kind = TokenKind {
+            contract_address: sender,
+            variable_index: 0u8.into(),
+        };
+        let asset = |recipient: &str, amount| ContributedAsset {
+            receiver_address: Address::from_str(recipient).unwrap(),
+            kind,
+            amount,
+        };
+        let request = PolicyRequest::new(
+            sender,
+            &[
+                asset("0x0000000000000003", 5),
+                asset("0x0000000000000002", 7),
+            ],
+        );
+        assert_eq!(
+            request
+                .outputs
+                .iter()
+                .map(|output| output.amount)
+                .collect::<Vec<_>>(),
+            vec![7, 5]
+        );
+        let body = serde_json::to_vec(&request).unwrap();
+
+        let wallet = LocalWallet::from(SigningKey::from_bytes(&[1u8; 32]).unwrap());
+        let signature = wallet.sign_hash(hash_message(keccak256(&body)));
+        let response = PolicyResponse {
+            approved: true,
+            reason: None,
+            signature: Some(signature.to_string()),
+        };
+        response.ensure_approved(&body, None).unwrap();
+        response
+            .ensure_approved(&body, Some(wallet.address()))
+            .unwrap();
+        assert!(response
+            .ensure_approved(&body, Some(H160::repeat_byte(1)))
+            .is_err());
+        assert!(response
+            .ensure_approved(b"another request", Some(wallet.address()))
+            .is_err());
+
+        let response = PolicyResponse {
+            approved: false,
+            reason: Some("over the daily limit".to_string()),
+            signature: None,
+        };
+        assert!(response
+            .ensure_approved(&body, None)
+            .unwrap_err()
+            .to_string()
+            .contains("over the daily limit"));
+    }
+}
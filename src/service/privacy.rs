@@ -0,0 +1,97 @@
+//! The privacy mode, which makes the change of a transfer harder to link to the assets it was made from.
+//!
+//! By default, the largest leaves are consumed first and the exact remainder returns to the sender,
+//! so the change amount tells which leaves were spent. In the privacy mode,
+//! - the consumed leaves are chosen in random order, and a decoy leaf of the same token may be added,
+//!   so the change is split off from a random set of leaves rather than the largest one, and
+//! - the received assets are merged in random order instead of the order they were received.
+//!
+//! A transaction holds one leaf per recipient and token kind, so the change of a token always returns in one leaf.
+//! The cost is that more leaves are consumed by each transfer, which reaches the limit of `N_DIFFS` inputs sooner,
+//! and that smaller fragments are kept in the wallet instead of being consolidated.
+
+use rand::{seq::SliceRandom, Rng};
+use serde::{Deserialize, Serialize};
+
+/// The probability to add a decoy leaf to the leaves consumed for a token.
+const DECOY_PROBABILITY: f64 = 0.5;
+
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct PrivacyConfig {
+    /// randomize the leaves consumed by transfers and the order of merging
+    #[serde(default)]
+    pub enabled: bool,
+}
+
+/// Choose the leaves consumed to send `output_amount` in random order.
+/// A leaf of exactly `output_amount` is used alone, since it leaves no change at all.
+/// Otherwise, a decoy leaf may be added if the selection stays within `max_inputs` leaves.
+/// The caller checks that the chosen leaves are enough.
+pub fn select_private_inputs<A>(
+    mut assets: Vec<A>,
+    amount_of: impl Fn(&A) -> u64,
+    output_amount: u64,
+    max_inputs: usize,
+    rng: &mut impl Rng,
+) -> Vec<A> {
+    if let Some(index) = assets
+        .iter()
+        .position(|asset| amount_of(asset) == output_amount)
+    {
+        return vec![assets.swap_remove(index)];
+    }
+
+    assets.shuffle(rng);
+    let mut input_amount = 0;
+    let mut n_inputs = 0;
+    for asset in assets.iter() {
+        if input_amount >= output_amount {
+            break;
+        }
+
+        input_amount += amount_of(asset);
+        n_inputs += 1;
+    }
+    if n_inputs < assets.len() && n_inputs < max_inputs && rng.gen_bool(DECOY_PROBABILITY) {
+        n_inputs += 1;
+    }
+    assets.truncate(n_inputs);
+
+    assets
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::{rngs::StdRng, SeedableRng};
+
+    use super::*;
+
+    #[test]
+    fn test_select_private_inputs() {
+        let mut rng = StdRng::seed_from_u64(0);
+        let amounts = vec![1u64, 2, 3, 5, 8, 13];
+
+        assert_eq!(
+            select_private_inputs(amounts.clone(), |amount| *amount, 5, 8, &mut rng),
+            vec![5]
+        );
+
+        let mut n_decoys = 0;
+        for _ in 0..100 {
+            let inputs = select_private_inputs(amounts.clone(), |amount| *amount, 4, 3, &mut rng);
+            let total_amount: u64 = inputs.iter().sum();
+            assert!(total_amount >= 4);
+            assert!(inputs.len() <= 3);
+            // The last leaf is a decoy if the others are already enough.
+            if inputs.iter().take(inputs.len() - 1).sum::<u64>() >= 4 {
+                n_decoys += 1;
+            }
+        }
+        assert!(n_decoys > 0);
+
+        // Not enough leaves are left to the caller.
+        let inputs = select_private_inputs(amounts.clone(), |amount| *amount, 100, 8, &mut rng);
+        assert_eq!(inputs.len(), amounts.len());
+    }
+}
@@ -1,13 +1,34 @@
-use dialoguer::{theme::ColorfulTheme, Select};
+use std::str::FromStr;
+
+use dialoguer::{Input, Select};
 use intmax_interoperability_plugin::ethers::types::H160;
-use intmax_rollup_interface::constants::ContractConfig;
+use intmax_rollup_interface::{
+    constants::ContractConfig,
+    intmax_zkp_core::{
+        plonky2::plonk::config::{GenericConfig, PoseidonGoldilocksConfig},
+        transaction::asset::ContributedAsset,
+        zkdsa::account::Address,
+    },
+};
+
+use super::{
+    calc_distribution_total_amount,
+    functions::RegistrationStatus,
+    interoperability::{get_token_allow_list, get_token_metadata, TokenMetadata},
+};
+use crate::utils::{nickname::NicknameTable, terminal::TerminalOptions};
 
-use super::interoperability::{get_token_allow_list, get_token_metadata, TokenMetadata};
+const D: usize = 2;
+type C = PoseidonGoldilocksConfig;
+type F = <C as GenericConfig<D>>::F;
 
 pub async fn select_payment_method(
     network_config: &ContractConfig<'static>,
     is_reverse_offer: bool,
+    terminal: &TerminalOptions,
 ) -> anyhow::Result<Option<TokenMetadata>> {
+    terminal.ensure_interactive("specify the payment method with --taker-token")?;
+
     let allow_list = get_token_allow_list(network_config, is_reverse_offer).await?;
 
     let mut allow_list_with_metadata = vec![];
@@ -17,7 +38,7 @@ pub async fn select_payment_method(
         allow_list_with_metadata.push(metadata);
     }
 
-    let selection = Select::with_theme(&ColorfulTheme::default())
+    let selection = Select::with_theme(&*terminal.theme())
         .with_prompt("Which token is the payment method?")
         .items(
             &allow_list_with_metadata
@@ -36,3 +57,121 @@ pub async fn select_payment_method(
 
     Ok(selection.map(|index| allow_list_with_metadata[index].clone()))
 }
+
+/// Display the total amount to be sent for each token kind and ask whether to continue.
+pub fn confirm_distribution(
+    distribution: &[ContributedAsset<F>],
+    recipient_statuses: &[(Address<F>, RegistrationStatus)],
+    nickname_table: &NicknameTable,
+    terminal: &TerminalOptions,
+) -> anyhow::Result<bool> {
+    let total_amount_map = calc_distribution_total_amount(distribution);
+
+    let separator =
+        "--------------------------------------------------------------------------------------";
+    println!("{}", separator);
+    for ((contract_address, variable_index), total_amount) in total_amount_map {
+        let decoded_contract_address = Address::<F>::from_str(&contract_address)?;
+        if let Some(contract_nickname) = nickname_table
+            .address_to_nickname
+            .get(&decoded_contract_address)
+        {
+            println!(
+                "  Token Address | {} [{}]",
+                decoded_contract_address, contract_nickname
+            );
+        } else {
+            println!("  Token Address | {}", decoded_contract_address);
+        }
+        println!("  Token ID      | {}", variable_index);
+        println!("  Total Amount  | {}", total_amount);
+        println!("{}", separator);
+    }
+    for (recipient, status) in recipient_statuses {
+        if *status == RegistrationStatus::Registered {
+            continue;
+        }
+
+        println!("  Recipient     | {} ({})", recipient, status);
+    }
+    if !recipient_statuses.is_empty() {
+        let n_registered_recipients = recipient_statuses
+            .iter()
+            .filter(|(_, status)| *status == RegistrationStatus::Registered)
+            .count();
+        println!(
+            "  Registered    | {} / {} recipients",
+            n_registered_recipients,
+            recipient_statuses.len()
+        );
+        println!("{}", separator);
+    }
+
+    terminal.confirm("Do you want to send the above tokens?")
+}
+
+/// Returns true if `input` is `keyword` or the aggregator host.
+pub fn is_confirmation_text(input: &str, keyword: &str, aggregator_host: &str) -> bool {
+    let input = input.trim();
+
+    input == keyword || input.trim_end_matches('/') == aggregator_host.trim_end_matches('/')
+}
+
+/// Require typing `keyword` or the aggregator host before a destructive operation.
+/// In non-interactive mode, the text must be given as `confirmation`. `--yes` does not skip it.
+pub fn confirm_by_typing(
+    description: &str,
+    keyword: &str,
+    aggregator_host: &str,
+    confirmation: Option<&str>,
+    terminal: &TerminalOptions,
+) -> anyhow::Result<bool> {
+    let input = if let Some(confirmation) = confirmation {
+        confirmation.to_string()
+    } else {
+        terminal.ensure_interactive(&format!("confirm the operation with --confirm {keyword}"))?;
+        eprintln!("{description}");
+        Input::<String>::with_theme(&*terminal.theme())
+            .with_prompt(format!(
+                "Type {keyword} or {} to continue",
+                aggregator_host.trim_end_matches('/')
+            ))
+            .allow_empty(true)
+            .interact_text()?
+    };
+
+    Ok(is_confirmation_text(&input, keyword, aggregator_host))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_is_confirmation_text() {
+        let aggregator_host = "alpha.testnet.intmax.io/";
+        assert!(is_confirmation_text("RESET", "RESET", aggregator_host));
+        assert!(is_confirmation_text(" RESET\n", "RESET", aggregator_host));
+        assert!(is_confirmation_text(
+            "alpha.testnet.intmax.io",
+            "RESET",
+            aggregator_host
+        ));
+        assert!(!is_confirmation_text("reset", "RESET", aggregator_host));
+        assert!(!is_confirmation_text("y", "RESET", aggregator_host));
+        assert!(!is_confirmation_text("", "RESET", aggregator_host));
+    }
+
+    #[test]
+    fn test_confirm_by_typing() {
+        let aggregator_host = "alpha.testnet.intmax.io/";
+        let terminal = TerminalOptions {
+            interactive: false,
+            assume_yes: true,
+            ..Default::default()
+        };
+        assert!(confirm_by_typing("", "RESET", aggregator_host, None, &terminal).is_err());
+        assert!(confirm_by_typing("", "RESET", aggregator_host, Some("RESET"), &terminal).unwrap());
+        assert!(!confirm_by_typing("", "RESET", aggregator_host, Some("yes"), &terminal).unwrap());
+    }
+}
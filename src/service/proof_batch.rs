@@ -0,0 +1,180 @@
+//! `proof verify-batch`, which checks every proof file in a directory, e.g., the proofs submitted by users.
+
+use std::path::{Path, PathBuf};
+
+use anyhow::Context;
+use intmax_rollup_interface::constants::ContractConfig;
+use rayon::prelude::*;
+use serde::Serialize;
+
+use super::functions::verify_inclusion_proof;
+use crate::utils::proof_format::{decode_proof_artifact, ProofArtifact};
+
+/// The result of verifying one proof file.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct ProofFileResult {
+    /// the path relative to the directory
+    pub file: String,
+    /// `None` if the type could not be detected
+    #[serde(rename = "type")]
+    pub proof_type: Option<String>,
+    pub valid: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reason: Option<String>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct ProofBatchReport {
+    pub total: usize,
+    pub passed: usize,
+    pub failed: usize,
+    pub results: Vec<ProofFileResult>,
+}
+
+impl ProofBatchReport {
+    pub fn new(results: Vec<ProofFileResult>) -> Self {
+        let passed = results.iter().filter(|result| result.valid).count();
+
+        Self {
+            total: results.len(),
+            passed,
+            failed: results.len() - passed,
+            results,
+        }
+    }
+}
+
+impl std::fmt::Display for ProofBatchReport {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for result in self.results.iter() {
+            let proof_type = result.proof_type.as_deref().unwrap_or("-");
+            if result.valid {
+                writeln!(f, "PASS  {proof_type:<9}  {}", result.file)?;
+            } else {
+                writeln!(
+                    f,
+                    "FAIL  {proof_type:<9}  {}: {}",
+                    result.file,
+                    result.reason.as_deref().unwrap_or_default()
+                )?;
+            }
+        }
+        write!(
+            f,
+            "{} proofs: {} passed, {} failed",
+            self.total, self.passed, self.failed
+        )
+    }
+}
+
+/// Returns the regular files in `dir` except hidden ones, sorted by name. Subdirectories are not searched.
+pub fn list_proof_files(dir: &Path) -> anyhow::Result<Vec<PathBuf>> {
+    let mut files = vec![];
+    for entry in
+        std::fs::read_dir(dir).with_context(|| format!("fail to read {}", dir.display()))?
+    {
+        let entry = entry?;
+        if !entry.file_type()?.is_file() || entry.file_name().to_string_lossy().starts_with('.') {
+            continue;
+        }
+
+        files.push(entry.path());
+    }
+    files.sort();
+
+    Ok(files)
+}
+
+/// Verify a proof of any type. An inclusion proof needs the network of the verifier contract.
+pub async fn verify_proof_artifact(
+    proof: &ProofArtifact,
+    network_config: Option<&ContractConfig<'static>>,
+) -> anyhow::Result<()> {
+    match proof {
+        ProofArtifact::Inclusion(proof) => {
+            let network_config = network_config.context(
+                "an inclusion proof is verified by the verifier contract. Choose the network with --network",
+            )?;
+            verify_inclusion_proof(network_config, proof).await
+        }
+        ProofArtifact::Header(proof) => proof.verify(),
+    }
+}
+
+/// Verify the proof files in parallel. A file which cannot be read or decoded is reported as failed.
+pub fn verify_proof_files(
+    dir: &Path,
+    files: &[PathBuf],
+    network_config: Option<&ContractConfig<'static>>,
+) -> ProofBatchReport {
+    // The inclusion proofs are checked with the contract from the threads of rayon.
+    let handle = tokio::runtime::Handle::current();
+    let results = files
+        .par_iter()
+        .map(|file| {
+            let file_name = file
+                .strip_prefix(dir)
+                .unwrap_or(file)
+                .to_string_lossy()
+                .to_string();
+            let proof = std::fs::read_to_string(file)
+                .context("fail to read")
+                .and_then(|encoded_proof| decode_proof_artifact(&encoded_proof));
+            let proof = match proof {
+                Ok(proof) => proof,
+                Err(err) => {
+                    return ProofFileResult {
+                        file: file_name,
+                        proof_type: None,
+                        valid: false,
+                        reason: Some(format!("{err:#}")),
+                    };
+                }
+            };
+
+            let result = handle.block_on(verify_proof_artifact(&proof, network_config));
+
+            ProofFileResult {
+                file: file_name,
+                proof_type: Some(proof.type_name().to_string()),
+                valid: result.is_ok(),
+                reason: result.err().map(|err| format!("{err:#}")),
+            }
+        })
+        .collect::<Vec<_>>();
+
+    ProofBatchReport::new(results)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::test_utils::temp_dir;
+
+    #[test]
+    fn test_proof_batch_report() {
+        let temp_dir = temp_dir();
+        let dir = temp_dir.path().join("proof-batch");
+        std::fs::create_dir_all(dir.join("nested")).unwrap();
+        std::fs::write(dir.join("b.json"), "{}").unwrap();
+        std::fs::write(dir.join("a.json"), "not a proof").unwrap();
+        std::fs::write(dir.join(".hidden"), "").unwrap();
+
+        let files = list_proof_files(&dir).unwrap();
+        assert_eq!(files, vec![dir.join("a.json"), dir.join("b.json")]);
+
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .build()
+            .unwrap();
+        let report = runtime.block_on(async { verify_proof_files(&dir, &files, None) });
+        assert_eq!(report.total, 2);
+        assert_eq!(report.failed, 2);
+        assert_eq!(report.results[0].file, "a.json");
+        assert!(report.results[0]
+            .reason
+            .as_ref()
+            .unwrap()
+            .contains("not JSON"));
+        assert!(report.to_string().ends_with("2 proofs: 0 passed, 2 failed"));
+    }
+}
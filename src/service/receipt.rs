@@ -0,0 +1,282 @@
+//! `tx verify-receipt`, which checks the inclusion proof given by someone claiming to have paid you,
+//! e.g., a buyer on a marketplace, instead of trusting a screenshot of their wallet.
+//! The proof must send the claimed asset to your address in a transaction included in an approved block.
+
+use anyhow::Context;
+use intmax_interoperability_plugin::ethers::types::U256;
+use intmax_rollup_interface::{
+    constants::ContractConfig,
+    intmax_zkp_core::{
+        merkle_tree::tree::MerkleProof,
+        plonky2::plonk::config::{GenericConfig, PoseidonGoldilocksConfig},
+        rollup::{block::BlockInfo, gadgets::deposit_block::VariableIndex},
+        sparse_merkle_tree::goldilocks_poseidon::WrappedHashOut,
+        transaction::block_header::{get_block_hash, BlockHeader},
+        zkdsa::account::Address,
+    },
+};
+
+use super::{
+    builder::ServiceBuilder,
+    functions::{calc_merkle_root, verify_diff_tree_leaf, verify_inclusion_proof},
+};
+use crate::utils::proof_format::{encode_recipient, encode_token_address, AssetInclusionProof};
+
+const D: usize = 2;
+type C = PoseidonGoldilocksConfig;
+type F = <C as GenericConfig<D>>::F;
+
+/// What the payer claims to have sent. The omitted fields are not checked.
+#[derive(Clone, Debug)]
+pub struct PaymentClaim {
+    pub recipient: Address<F>,
+    pub token_address: Option<Address<F>>,
+    pub token_id: Option<VariableIndex<F>>,
+    pub amount: Option<u64>,
+}
+
+/// Check that the proof sends the claimed asset to the recipient.
+pub fn check_payment_claim(
+    proof: &AssetInclusionProof,
+    claim: &PaymentClaim,
+) -> anyhow::Result<()> {
+    if proof.recipient != encode_recipient(claim.recipient) {
+        anyhow::bail!("the receipt is not for {}", claim.recipient);
+    }
+    if let Some(token_address) = claim.token_address {
+        if proof.token_address != encode_token_address(token_address) {
+            anyhow::bail!("the receipt does not send the token {token_address}");
+        }
+    }
+    if let Some(token_id) = claim.token_id {
+        if proof.token_id != U256::from(token_id.0) {
+            anyhow::bail!(
+                "the receipt sends the token id {}, not {token_id}",
+                proof.token_id
+            );
+        }
+    }
+    if let Some(amount) = claim.amount {
+        if proof.amount != U256::from(amount) {
+            anyhow::bail!("the receipt sends {}, not {amount}", proof.amount);
+        }
+    }
+
+    Ok(())
+}
+
+/// Check that the transaction proof given by the aggregator includes the transaction in `block_header`,
+/// and that the header is the one of the approved block.
+pub fn check_transaction_inclusion(
+    proof: &AssetInclusionProof,
+    transaction_proof: &MerkleProof<F>,
+    block_header: &BlockHeader<F>,
+    approved_block: &BlockInfo<F>,
+) -> anyhow::Result<()> {
+    let block_number = block_header.block_number;
+    if get_block_hash(block_header) != get_block_hash(&approved_block.header) {
+        anyhow::bail!("the block header does not match the approved block {block_number}");
+    }
+
+    if transaction_proof.value != proof.tx_hash
+        || *transaction_proof.root != block_header.transactions_digest
+        || calc_merkle_root(transaction_proof) != transaction_proof.root
+    {
+        anyhow::bail!(
+            "invalid inclusion proof of {} for block {block_number}",
+            proof.tx_hash
+        );
+    }
+
+    let validity = approved_block
+        .address_list
+        .iter()
+        .map(|sender| sender.is_valid)
+        .collect::<Vec<_>>();
+    check_transaction_validity(
+        proof.tx_hash,
+        &approved_block.transactions,
+        &validity,
+        block_number,
+    )
+}
+
+/// Check that the transaction is in the block and was signed by its sender.
+/// A transaction which was not signed stays in the block, but it is cancelled and its assets are refunded.
+pub fn check_transaction_validity(
+    tx_hash: WrappedHashOut<F>,
+    transactions: &[WrappedHashOut<F>],
+    validity: &[bool],
+    block_number: u32,
+) -> anyhow::Result<()> {
+    let index = transactions
+        .iter()
+        .position(|v| *v == tx_hash)
+        .ok_or_else(|| {
+            anyhow::anyhow!("the transaction {tx_hash} was not found in block {block_number}")
+        })?;
+    if !validity.get(index).copied().unwrap_or(false) {
+        anyhow::bail!("the transaction {tx_hash} was cancelled in block {block_number}");
+    }
+
+    Ok(())
+}
+
+/// Check that the witness in the proof is the one the aggregator has for the transaction.
+pub fn check_witness(proof: &AssetInclusionProof, witness: &str) -> anyhow::Result<()> {
+    let witness = hex::decode(witness.trim_start_matches("0x"))
+        .context("the aggregator returned a malformed witness")?;
+    if proof.witness.as_ref() != witness.as_slice() {
+        anyhow::bail!("the witness in the receipt differs from the one of the transaction");
+    }
+
+    Ok(())
+}
+
+/// The outcome of `verify_receipt`.
+#[derive(Clone, Debug)]
+pub struct VerifiedReceipt {
+    pub block_number: u32,
+    /// true if the witness was also checked with the verifier contract.
+    /// Otherwise, the link between the diff tree and the transaction hash is not checked,
+    /// since it is encoded in the witness only the contract decodes.
+    pub verified_on_chain: bool,
+}
+
+/// Verify the receipt against the claim and the approved block including the transaction.
+/// With `network_config`, the witness is also checked with the verifier contract,
+/// which does not rely on the aggregator.
+pub async fn verify_receipt(
+    service: &ServiceBuilder,
+    network_config: Option<&ContractConfig<'static>>,
+    proof: &AssetInclusionProof,
+    claim: &PaymentClaim,
+) -> anyhow::Result<VerifiedReceipt> {
+    check_payment_claim(proof, claim)?;
+
+    let (tx_details, transaction_proof, block_header, witness) = service
+        .get_transaction_proof(*proof.tx_hash, claim.recipient)
+        .await?;
+
+    // The asset in the receipt is the one the aggregator has for this transaction.
+    if tx_details.assets.len() != 1 {
+        anyhow::bail!("the transaction does not send one kind of asset");
+    }
+    let asset = &tx_details.assets[0];
+    let token_address = encode_token_address(asset.kind.contract_address);
+    if token_address != proof.token_address
+        || U256::from(asset.kind.variable_index.0) != proof.token_id
+        || U256::from(asset.amount) != proof.amount
+    {
+        anyhow::bail!("the receipt does not match the asset sent by the transaction");
+    }
+
+    // The assets are the ones given to the recipient in the diff tree of the transaction.
+    check_witness(proof, &witness)?;
+    verify_diff_tree_leaf(
+        claim.recipient,
+        &tx_details.assets,
+        &tx_details.inclusion_witness,
+    )?;
+
+    let approved_block = service.get_cached_block(block_header.block_number).await?;
+    check_transaction_inclusion(proof, &transaction_proof, &block_header, &approved_block)?;
+
+    if let Some(network_config) = network_config {
+        verify_inclusion_proof(network_config, proof).await?;
+    }
+
+    Ok(VerifiedReceipt {
+        block_number: block_header.block_number,
+        verified_on_chain: network_config.is_some(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use intmax_interoperability_plugin::ethers::types::Bytes;
+
+    use super::*;
+
+    #[test]
+    fn test_check_payment_claim() {
+        let seller = Address::<F>::from_str("0x714bdc6f38947e6d").unwrap();
+        let token_address = Address::<F>::from_str("0x0000000000000001").unwrap();
+        let proof = AssetInclusionProof {
+            tx_hash: WrappedHashOut::rand(),
+            recipient: encode_recipient(seller),
+            token_address: encode_token_address(token_address),
+            token_id: 0.into(),
+            amount: 100.into(),
+            witness: Bytes::from(vec![0xab, 0xcd]),
+        };
+        let claim = PaymentClaim {
+            recipient: seller,
+            token_address: Some(token_address),
+            token_id: Some(0u8.into()),
+            amount: Some(100),
+        };
+        check_payment_claim(&proof, &claim).unwrap();
+        check_payment_claim(
+            &proof,
+            &PaymentClaim {
+                token_address: None,
+                token_id: None,
+                amount: None,
+                ..claim.clone()
+            },
+        )
+        .unwrap();
+
+        let error = check_payment_claim(
+            &proof,
+            &PaymentClaim {
+                recipient: token_address,
+                ..claim.clone()
+            },
+        )
+        .unwrap_err();
+        assert!(error.to_string().contains("is not for"));
+        let error = check_payment_claim(
+            &proof,
+            &PaymentClaim {
+                token_address: Some(seller),
+                ..claim.clone()
+            },
+        )
+        .unwrap_err();
+        assert!(error.to_string().contains("does not send the token"));
+        let error = check_payment_claim(
+            &proof,
+            &PaymentClaim {
+                amount: Some(1000),
+                ..claim
+            },
+        )
+        .unwrap_err();
+        assert!(error.to_string().contains("not 1000"));
+
+        check_witness(&proof, "0xabcd").unwrap();
+        let error = check_witness(&proof, "0xabce").unwrap_err();
+        assert!(error.to_string().contains("differs"));
+        assert!(check_witness(&proof, "0xzz").is_err());
+    }
+
+    #[test]
+    fn test_check_transaction_validity() {
+        let transactions = vec![WrappedHashOut::rand(), WrappedHashOut::rand()];
+        check_transaction_validity(transactions[0], &transactions, &[true, false], 1).unwrap();
+
+        // The sender did not sign, so the payment was cancelled and refunded.
+        let error = check_transaction_validity(transactions[1], &transactions, &[true, false], 1)
+            .unwrap_err();
+        assert!(error.to_string().contains("was cancelled"));
+
+        let error =
+            check_transaction_validity(WrappedHashOut::rand(), &transactions, &[true, true], 1)
+                .unwrap_err();
+        assert!(error.to_string().contains("was not found"));
+    }
+}
@@ -0,0 +1,606 @@
+use std::{
+    fs::File,
+    io::Write,
+    path::Path,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use anyhow::Context;
+use intmax_interoperability_plugin::{
+    contracts::offer_manager_base::Offer,
+    ethers::{
+        types::{H160, U256},
+        utils::secret_key_to_address,
+    },
+};
+use intmax_rollup_interface::{
+    constants::ContractConfig,
+    intmax_zkp_core::{
+        plonky2::{
+            field::types::Field,
+            plonk::config::{GenericConfig, PoseidonGoldilocksConfig},
+        },
+        transaction::asset::TokenKind,
+        zkdsa::account::Address,
+    },
+};
+use serde::{Deserialize, Serialize};
+
+use super::{
+    builder::ServiceBuilder,
+    ethereum::{gwei_to_wei, GasPolicy},
+    functions::create_transaction_proof,
+    interoperability::{
+        get_offer, register_transfer, MakerTransferInfo, NetworkName, TakerTransferInfo,
+    },
+};
+use crate::{
+    progress,
+    utils::{
+        debug_format::write_debug_dump,
+        hex_string::parse_private_key_env,
+        key_management::{
+            memory::WalletOnMemory,
+            types::{TransactionStatus, TxHash},
+        },
+        loader::{read_json_file, LocalFileKind},
+    },
+};
+
+const D: usize = 2;
+type C = PoseidonGoldilocksConfig;
+type F = <C as GenericConfig<D>>::F;
+
+pub const REGISTER_INTENTS_FILE_NAME: &str = "register_intents.json";
+
+/// The intmax account which receives the assets locked by `io register` on each network.
+pub fn get_temporary_receiver_address(network_name: NetworkName) -> Address<F> {
+    match network_name {
+        NetworkName::ScrollAlpha => Address(F::from_canonical_u64(1)),
+        NetworkName::PolygonZkEvmTest => Address(F::from_canonical_u64(2)),
+    }
+}
+
+/// `io register` sends the assets on intmax first and then registers the offer on the network.
+/// The progress is recorded before each step, so that an interrupted registration can be completed later.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "status", rename_all = "snake_case")]
+pub enum RegisterIntentStatus {
+    /// The intmax transfer may not have been sent yet.
+    Prepared,
+    /// The intmax transfer was sent, but the offer has not been registered yet.
+    Transferred { tx_hash: TxHash<F> },
+    Registered {
+        tx_hash: Option<TxHash<F>>,
+        offer_id: U256,
+    },
+    /// The intmax transfer was never sent, so nothing has to be done.
+    Canceled,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct RegisterIntent {
+    /// `NetworkName` in the form of `SCROLL_ALPHA`
+    pub network_name: String,
+    pub user_address: Address<F>,
+    pub receiver_address: Address<F>,
+    pub kind: TokenKind<F>,
+    pub maker_amount: u64,
+    pub taker_token_address: H160,
+    pub taker_amount: U256,
+    /// upper limit of acceptable gas price in Gwei
+    pub max_gas_price: Option<f64>,
+    /// UNIX time in seconds
+    pub created_at: u64,
+    #[serde(flatten)]
+    pub status: RegisterIntentStatus,
+}
+
+impl RegisterIntent {
+    #[allow(clippy::too_many_arguments)]
+    pub fn new(
+        network_name: NetworkName,
+        user_address: Address<F>,
+        receiver_address: Address<F>,
+        kind: TokenKind<F>,
+        maker_amount: u64,
+        taker_token_address: H160,
+        taker_amount: U256,
+        max_gas_price: Option<f64>,
+    ) -> anyhow::Result<Self> {
+        Ok(Self {
+            network_name: network_name.to_string(),
+            user_address,
+            receiver_address,
+            kind,
+            maker_amount,
+            taker_token_address,
+            taker_amount,
+            max_gas_price,
+            created_at: SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs(),
+            status: RegisterIntentStatus::Prepared,
+        })
+    }
+
+    pub fn is_pending(&self) -> bool {
+        matches!(
+            self.status,
+            RegisterIntentStatus::Prepared | RegisterIntentStatus::Transferred { .. }
+        )
+    }
+
+    pub fn tx_hash(&self) -> Option<TxHash<F>> {
+        match self.status {
+            RegisterIntentStatus::Transferred { tx_hash } => Some(tx_hash),
+            RegisterIntentStatus::Registered { tx_hash, .. } => tx_hash,
+            _ => None,
+        }
+    }
+
+    pub fn maker_transfer_info(&self, maker_address: H160) -> MakerTransferInfo<F> {
+        MakerTransferInfo {
+            address: maker_address,
+            intmax_account: self.user_address,
+            kind: self.kind,
+            amount: self.maker_amount,
+        }
+    }
+
+    /// Returns true if the offer registered on the network was made by this intent.
+    pub fn matches_offer(&self, offer: &Offer) -> bool {
+        let maker_transfer_info = self.maker_transfer_info(offer.maker);
+
+        offer.maker_intmax_address == maker_transfer_info.intmax_account()
+            && offer.maker_asset_id == maker_transfer_info.asset_id()
+            && offer.maker_amount == self.maker_amount.into()
+            && offer.taker_token_address == self.taker_token_address
+            && offer.taker_amount == self.taker_amount
+    }
+
+    /// Compare the offer registered on the network with this intent and the intmax transfer `tx_hash`,
+    /// and returns the differences.
+    pub fn verify_offer(
+        &self,
+        wallet: &WalletOnMemory,
+        maker_address: H160,
+        offer: &Offer,
+        tx_hash: TxHash<F>,
+    ) -> anyhow::Result<Vec<OfferMismatch>> {
+        let maker_transfer_info = self.maker_transfer_info(maker_address);
+        let taker_transfer_info = TakerTransferInfo {
+            address: H160::default(),
+            intmax_account: self.receiver_address,
+            token_address: self.taker_token_address,
+            amount: self.taker_amount,
+        };
+
+        let mut mismatches = vec![];
+        let mut compare = |field: &str, expected: String, actual: String| {
+            if expected != actual {
+                mismatches.push(OfferMismatch {
+                    field: field.to_string(),
+                    expected,
+                    actual,
+                });
+            }
+        };
+        compare(
+            "maker",
+            format!("{:?}", maker_address),
+            format!("{:?}", offer.maker),
+        );
+        compare(
+            "maker_intmax_address",
+            hex::encode(maker_transfer_info.intmax_account()),
+            hex::encode(offer.maker_intmax_address),
+        );
+        compare(
+            "maker_asset_id",
+            maker_transfer_info.asset_id().to_string(),
+            offer.maker_asset_id.to_string(),
+        );
+        compare(
+            "maker_amount",
+            self.maker_amount.to_string(),
+            offer.maker_amount.to_string(),
+        );
+        compare(
+            "taker_intmax_address",
+            hex::encode(taker_transfer_info.intmax_account()),
+            hex::encode(offer.taker_intmax_address),
+        );
+        compare(
+            "taker_token_address",
+            format!("{:?}", self.taker_token_address),
+            format!("{:?}", offer.taker_token_address),
+        );
+        compare(
+            "taker_amount",
+            self.taker_amount.to_string(),
+            offer.taker_amount.to_string(),
+        );
+
+        // The transfer actually performed on intmax must have locked the offered asset.
+        let network_name: NetworkName = self.network_name.parse()?;
+        let expected_output = (
+            get_temporary_receiver_address(network_name),
+            self.kind,
+            self.maker_amount,
+        );
+        let sent_transaction = wallet
+            .data
+            .get(&self.user_address)
+            .and_then(|user_state| user_state.sent_transactions.get(&tx_hash));
+        let actual_output = match sent_transaction {
+            Some(sent_transaction) if sent_transaction.status == TransactionStatus::Reverted => {
+                format!("{tx_hash} was reverted")
+            }
+            Some(sent_transaction) if sent_transaction.outputs.contains(&expected_output) => {
+                format!(
+                    "{tx_hash} sent {} to {}",
+                    self.maker_amount, expected_output.0
+                )
+            }
+            Some(_) => format!("{tx_hash} did not send the offered asset"),
+            None => format!("{tx_hash} was not found in the wallet"),
+        };
+        compare(
+            "intmax_transfer",
+            format!(
+                "{tx_hash} sent {} to {}",
+                self.maker_amount, expected_output.0
+            ),
+            actual_output,
+        );
+
+        Ok(mismatches)
+    }
+
+    /// Find the intmax transfer of a prepared intent in the transactions sent by the user.
+    pub fn find_transfer(&self, wallet: &WalletOnMemory) -> anyhow::Result<Option<TxHash<F>>> {
+        let network_name: NetworkName = self.network_name.parse()?;
+        let temporary_receiver_address = get_temporary_receiver_address(network_name);
+        let user_state = wallet
+            .data
+            .get(&self.user_address)
+            .context("user address was not found in wallet")?;
+        let tx_hash = user_state
+            .sent_transactions
+            .iter()
+            .find(|(_, sent_transaction)| {
+                sent_transaction.status != TransactionStatus::Reverted
+                    && sent_transaction.created_at.unwrap_or_default() >= self.created_at
+                    && sent_transaction.outputs.contains(&(
+                        temporary_receiver_address,
+                        self.kind,
+                        self.maker_amount,
+                    ))
+            })
+            .map(|(tx_hash, _)| *tx_hash);
+
+        Ok(tx_hash)
+    }
+
+    /// Register the offer for the intmax transfer `tx_hash`, and returns the offer ID.
+    pub async fn register(
+        &self,
+        service: &ServiceBuilder,
+        network_config: &ContractConfig<'static>,
+        secret_key: &str,
+        tx_hash: TxHash<F>,
+        gas_policy: &mut GasPolicy,
+    ) -> anyhow::Result<U256> {
+        let network_name: NetworkName = self.network_name.parse()?;
+        let witness = create_transaction_proof(
+            service,
+            Some(network_config.clone()),
+            *tx_hash.0,
+            get_temporary_receiver_address(network_name),
+        )
+        .await?;
+
+        let signer_key = parse_private_key_env(secret_key)?;
+        let my_account = secret_key_to_address(&signer_key);
+        let receiving_transfer_info = TakerTransferInfo {
+            address: H160::default(), // anyone can activate
+            intmax_account: self.receiver_address,
+            token_address: self.taker_token_address,
+            amount: self.taker_amount,
+        };
+
+        register_transfer(
+            network_config,
+            secret_key,
+            self.maker_transfer_info(my_account),
+            receiving_transfer_info,
+            gas_policy,
+            self.max_gas_price.map(gwei_to_wei),
+            witness,
+        )
+        .await
+    }
+}
+
+/// A field of the offer registered on the network which differs from the intmax transfer made for it.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct OfferMismatch {
+    pub field: String,
+    pub expected: String,
+    pub actual: String,
+}
+
+#[derive(Clone, Debug, Serialize)]
+struct OfferMismatchReport<'a> {
+    offer_id: U256,
+    intent: &'a RegisterIntent,
+    offer_found: bool,
+    mismatches: &'a [OfferMismatch],
+}
+
+/// Read the offer `offer_id` back from the network and check that it matches the intmax transfer `tx_hash`.
+/// If it does not, a report is written into `report_dir` and an error is returned.
+pub async fn verify_registered_offer(
+    wallet: &WalletOnMemory,
+    network_config: &ContractConfig<'static>,
+    secret_key: &str,
+    intent: &RegisterIntent,
+    offer_id: U256,
+    tx_hash: TxHash<F>,
+    report_dir: &Path,
+) -> anyhow::Result<()> {
+    let maker_address = secret_key_to_address(&parse_private_key_env(secret_key)?);
+    let offer = get_offer(network_config, offer_id, false).await;
+    let mismatches = if let Some(offer) = &offer {
+        intent.verify_offer(wallet, maker_address, offer, tx_hash)?
+    } else {
+        vec![]
+    };
+    if offer.is_some() && mismatches.is_empty() {
+        progress!("verified the offer {offer_id} against the intmax transfer {tx_hash}");
+
+        return Ok(());
+    }
+
+    let report = OfferMismatchReport {
+        offer_id,
+        intent,
+        offer_found: offer.is_some(),
+        mismatches: &mismatches,
+    };
+    let report_file_path =
+        write_debug_dump(report_dir, &format!("offer-mismatch-{offer_id}"), &report)?;
+    if offer.is_none() {
+        eprintln!("WARNING: the offer {offer_id} was not found on the network");
+    }
+    for mismatch in mismatches.iter() {
+        eprintln!(
+            "WARNING: {} of the offer {offer_id} is {}, but {} is expected",
+            mismatch.field, mismatch.actual, mismatch.expected
+        );
+    }
+
+    anyhow::bail!(
+        "the offer {offer_id} does not match the intmax transfer {tx_hash}. See {}",
+        report_file_path.display()
+    );
+}
+
+/// The registrations made by `io register`, which are kept next to the wallet.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct RegisterIntents {
+    pub intents: Vec<RegisterIntent>,
+}
+
+impl RegisterIntents {
+    /// Returns no intents if the file does not exist.
+    pub fn read_from_file(file_path: &Path) -> anyhow::Result<Self> {
+        if !file_path.exists() {
+            return Ok(Self::default());
+        }
+
+        read_json_file(LocalFileKind::RegisterIntents, file_path, None)
+    }
+
+    pub fn write_to_file(&self, file_path: &Path) -> anyhow::Result<()> {
+        let mut file = File::create(file_path)
+            .with_context(|| format!("fail to create {}", file_path.display()))?;
+        write!(file, "{}", serde_json::to_string_pretty(self)?)?;
+        file.flush()?;
+
+        Ok(())
+    }
+
+    /// Returns the indices of the unfinished intents of the user on the network.
+    pub fn pending(&self, user_address: Address<F>, network_name: NetworkName) -> Vec<usize> {
+        self.intents
+            .iter()
+            .enumerate()
+            .filter(|(_, intent)| {
+                intent.is_pending()
+                    && intent.user_address == user_address
+                    && intent.network_name == network_name.to_string()
+            })
+            .map(|(i, _)| i)
+            .collect()
+    }
+}
+
+/// Drive the unfinished registrations of the user on the network to the end.
+/// A prepared intent whose intmax transfer was never sent is canceled.
+#[allow(clippy::too_many_arguments)]
+pub async fn resume_register_intents(
+    service: &ServiceBuilder,
+    wallet: &WalletOnMemory,
+    intents_file_path: &Path,
+    network_name: NetworkName,
+    network_config: &ContractConfig<'static>,
+    secret_key: &str,
+    user_address: Address<F>,
+    report_dir: &Path,
+) -> anyhow::Result<()> {
+    let mut intents = RegisterIntents::read_from_file(intents_file_path)?;
+    // Proving takes minutes for each registration, so the gas price is refreshed between them.
+    let mut gas_policy = GasPolicy::default();
+    for i in intents.pending(user_address, network_name) {
+        let tx_hash = if let Some(tx_hash) = intents.intents[i].tx_hash() {
+            tx_hash
+        } else if let Some(tx_hash) = intents.intents[i].find_transfer(wallet)? {
+            intents.intents[i].status = RegisterIntentStatus::Transferred { tx_hash };
+            intents.write_to_file(intents_file_path)?;
+
+            tx_hash
+        } else {
+            intents.intents[i].status = RegisterIntentStatus::Canceled;
+            intents.write_to_file(intents_file_path)?;
+            progress!(
+                "canceled the registration prepared at {}, whose intmax transfer was not sent",
+                intents.intents[i].created_at
+            );
+
+            continue;
+        };
+
+        progress!("completing the registration for the intmax transfer {tx_hash}");
+        let offer_id = intents.intents[i]
+            .register(
+                service,
+                network_config,
+                secret_key,
+                tx_hash,
+                &mut gas_policy,
+            )
+            .await
+            .with_context(|| {
+                format!("fail to register the offer for the intmax transfer {tx_hash}")
+            })?;
+        intents.intents[i].status = RegisterIntentStatus::Registered {
+            tx_hash: Some(tx_hash),
+            offer_id,
+        };
+        intents.write_to_file(intents_file_path)?;
+        println!("offer_id: {}", offer_id);
+
+        verify_registered_offer(
+            wallet,
+            network_config,
+            secret_key,
+            &intents.intents[i],
+            offer_id,
+            tx_hash,
+            report_dir,
+        )
+        .await?;
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use intmax_rollup_interface::intmax_zkp_core::sparse_merkle_tree::goldilocks_poseidon::WrappedHashOut;
+
+    use super::*;
+    use crate::utils::key_management::types::Wallet;
+
+    #[test]
+    fn test_register_intents() {
+        let user_address = Address::from_str("0x714bdc6f38947e6d").unwrap();
+        let mut intent = RegisterIntent::new(
+            NetworkName::ScrollAlpha,
+            user_address,
+            Address::from_str("0x0000000000000003").unwrap(),
+            TokenKind {
+                contract_address: user_address,
+                variable_index: 0u8.into(),
+            },
+            10,
+            H160::default(),
+            100u64.into(),
+            None,
+        )
+        .unwrap();
+        let tx_hash = TxHash(WrappedHashOut::rand());
+        intent.status = RegisterIntentStatus::Transferred { tx_hash };
+
+        let intents = RegisterIntents {
+            intents: vec![intent],
+        };
+        let encoded_intents = serde_json::to_value(&intents).unwrap();
+        assert_eq!(encoded_intents["intents"][0]["status"], "transferred");
+        let mut decoded_intents: RegisterIntents = serde_json::from_value(encoded_intents).unwrap();
+        assert_eq!(decoded_intents.intents[0].tx_hash(), Some(tx_hash));
+
+        assert_eq!(
+            decoded_intents.pending(user_address, NetworkName::ScrollAlpha),
+            vec![0]
+        );
+        assert!(decoded_intents
+            .pending(user_address, NetworkName::PolygonZkEvmTest)
+            .is_empty());
+
+        decoded_intents.intents[0].status = RegisterIntentStatus::Registered {
+            tx_hash: Some(tx_hash),
+            offer_id: 1u64.into(),
+        };
+        assert!(decoded_intents
+            .pending(user_address, NetworkName::ScrollAlpha)
+            .is_empty());
+    }
+
+    #[test]
+    fn test_verify_offer() {
+        let user_address = Address::from_str("0x714bdc6f38947e6d").unwrap();
+        let receiver_address = Address::from_str("0x0000000000000003").unwrap();
+        let intent = RegisterIntent::new(
+            NetworkName::ScrollAlpha,
+            user_address,
+            receiver_address,
+            TokenKind {
+                contract_address: user_address,
+                variable_index: 0u8.into(),
+            },
+            10,
+            H160::default(),
+            100u64.into(),
+            None,
+        )
+        .unwrap();
+        let maker_address = H160::repeat_byte(1);
+        let maker_transfer_info = intent.maker_transfer_info(maker_address);
+        let mut offer = Offer {
+            maker: maker_address,
+            maker_intmax_address: maker_transfer_info.intmax_account(),
+            maker_asset_id: maker_transfer_info.asset_id(),
+            maker_amount: 10u64.into(),
+            taker: H160::default(),
+            taker_intmax_address: TakerTransferInfo {
+                intmax_account: receiver_address,
+                ..Default::default()
+            }
+            .intmax_account(),
+            taker_token_address: H160::default(),
+            taker_amount: 100u64.into(),
+            is_activated: false,
+        };
+        assert!(intent.matches_offer(&offer));
+
+        // The transfer is not in the empty wallet.
+        let wallet = WalletOnMemory::new("wallet".into(), String::new());
+        let tx_hash = TxHash(WrappedHashOut::rand());
+        let mismatches = intent
+            .verify_offer(&wallet, maker_address, &offer, tx_hash)
+            .unwrap();
+        assert_eq!(mismatches.len(), 1);
+        assert_eq!(mismatches[0].field, "intmax_transfer");
+
+        offer.maker_amount = 9u64.into();
+        let mismatches = intent
+            .verify_offer(&wallet, maker_address, &offer, tx_hash)
+            .unwrap();
+        assert_eq!(mismatches[0].field, "maker_amount");
+        assert_eq!(mismatches[0].expected, "10");
+        assert_eq!(mismatches[0].actual, "9");
+    }
+}
@@ -0,0 +1,294 @@
+//! `dev replay-block`, which rebuilds the witnesses of a block the way `block verify` does
+//! and compares each intermediate digest with the block header, without proving the block.
+//! When a block proof does not verify, the first diverging digest tells which part of the witness is wrong.
+
+use intmax_rollup_interface::{
+    constants::ROLLUP_CONSTANTS,
+    interface::BlockDetails,
+    intmax_zkp_core::{
+        merkle_tree::tree::MerkleProof,
+        plonky2::{
+            hash::hash_types::HashOut,
+            plonk::config::{GenericConfig, PoseidonGoldilocksConfig},
+        },
+        rollup::block::BlockInfo,
+        sparse_merkle_tree::{
+            goldilocks_poseidon::{
+                LayeredLayeredPoseidonSparseMerkleTree, NodeDataMemory, PoseidonNodeHash,
+                RootDataTmp, WrappedHashOut,
+            },
+            node_data::Node,
+            node_hash::NodeHash,
+        },
+        transaction::block_header::get_block_hash,
+    },
+};
+
+use super::functions::calc_merkle_root;
+
+const D: usize = 2;
+type C = PoseidonGoldilocksConfig;
+type F = <C as GenericConfig<D>>::F;
+
+/// A value derived from the witnesses and the value it must equal.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ReplayCheck {
+    pub name: String,
+    pub expected: String,
+    pub replayed: String,
+}
+
+impl ReplayCheck {
+    fn new(name: impl Into<String>, expected: impl ToString, replayed: impl ToString) -> Self {
+        Self {
+            name: name.into(),
+            expected: expected.to_string(),
+            replayed: replayed.to_string(),
+        }
+    }
+
+    pub fn matches(&self) -> bool {
+        self.expected == self.replayed
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct BlockReplay {
+    pub block_number: u32,
+    /// in the order the block circuit depends on them
+    pub checks: Vec<ReplayCheck>,
+}
+
+impl BlockReplay {
+    pub fn first_divergence(&self) -> Option<&ReplayCheck> {
+        self.checks.iter().find(|check| !check.matches())
+    }
+}
+
+impl std::fmt::Display for BlockReplay {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for check in self.checks.iter() {
+            if check.matches() {
+                writeln!(f, "OK        {}", check.name)?;
+            } else {
+                writeln!(f, "MISMATCH  {}", check.name)?;
+                writeln!(f, "          expected: {}", check.expected)?;
+                writeln!(f, "          replayed: {}", check.replayed)?;
+            }
+        }
+
+        match self.first_divergence() {
+            Some(check) => write!(
+                f,
+                "block {} first diverges at: {}",
+                self.block_number, check.name
+            ),
+            None => write!(
+                f,
+                "all {} checks of block {} match",
+                self.checks.len(),
+                self.block_number
+            ),
+        }
+    }
+}
+
+/// Returns the root of the Merkle tree of depth `log_n_leaves` with `leaves` on the left,
+/// and `default_leaf` in the rest.
+pub fn calc_merkle_root_of_leaves(
+    leaves: &[WrappedHashOut<F>],
+    default_leaf: WrappedHashOut<F>,
+    log_n_leaves: usize,
+) -> WrappedHashOut<F> {
+    let mut nodes = leaves.to_vec();
+    nodes.resize(1 << log_n_leaves, default_leaf);
+    while nodes.len() > 1 {
+        nodes = nodes
+            .chunks(2)
+            .map(|pair| PoseidonNodeHash::calc_node_hash(Node::Internal(pair[0], pair[1])))
+            .collect();
+    }
+
+    nodes[0]
+}
+
+/// Follow the process proofs from `start_root`. Proofs which change nothing are padding and skipped.
+/// Returns the check of the first proof not starting from the previous root, or the check of the final root.
+fn replay_root_chain(
+    name: &str,
+    start_root: WrappedHashOut<F>,
+    roots: impl Iterator<Item = (WrappedHashOut<F>, WrappedHashOut<F>)>,
+    expected_root: HashOut<F>,
+) -> ReplayCheck {
+    let mut root = start_root;
+    for (i, (old_root, new_root)) in roots.enumerate() {
+        if old_root == new_root {
+            continue;
+        }
+
+        if old_root != root {
+            return ReplayCheck::new(format!("{name} (old root of proof {i})"), root, old_root);
+        }
+        root = new_root;
+    }
+
+    ReplayCheck::new(name, WrappedHashOut::from(expected_root), root)
+}
+
+/// Rebuild the witnesses of `block` from its details and compare them with its header.
+pub fn replay_block(block: &BlockInfo<F>, block_details: &BlockDetails) -> BlockReplay {
+    let header = &block.header;
+    let prev_header = &block_details.prev_block_header;
+    let mut checks = vec![];
+
+    checks.push(ReplayCheck::new(
+        "block number of the details",
+        header.block_number,
+        block_details.block_number,
+    ));
+    checks.push(ReplayCheck::new(
+        "block number of the prior header",
+        header.block_number,
+        prev_header.block_number + 1,
+    ));
+    let prev_block_hash = get_block_hash(prev_header);
+    checks.push(ReplayCheck::new(
+        "prev block hash",
+        WrappedHashOut::from(header.prev_block_hash),
+        WrappedHashOut::from(prev_block_hash),
+    ));
+    let headers_digest_proof = MerkleProof {
+        root: header.block_headers_digest.into(),
+        index: prev_header.block_number as usize,
+        value: prev_block_hash.into(),
+        siblings: block_details.block_headers_proof_siblings.clone(),
+    };
+    checks.push(ReplayCheck::new(
+        "block headers digest",
+        WrappedHashOut::from(header.block_headers_digest),
+        calc_merkle_root(&headers_digest_proof),
+    ));
+
+    let tx_hashes = block_details
+        .user_tx_proofs
+        .iter()
+        .map(|user_tx_proof| user_tx_proof.public_inputs.tx_hash)
+        .collect::<Vec<_>>();
+    let format_tx_hashes = |tx_hashes: &[WrappedHashOut<F>]| {
+        tx_hashes
+            .iter()
+            .map(|tx_hash| tx_hash.to_string())
+            .collect::<Vec<_>>()
+            .join(", ")
+    };
+    checks.push(ReplayCheck::new(
+        "transaction hashes",
+        format_tx_hashes(&block.transactions),
+        format_tx_hashes(&tx_hashes),
+    ));
+    checks.push(ReplayCheck::new(
+        "transactions digest",
+        WrappedHashOut::from(header.transactions_digest),
+        calc_merkle_root_of_leaves(
+            &tx_hashes,
+            block_details.default_user_tx_proof.public_inputs.tx_hash,
+            ROLLUP_CONSTANTS.log_n_txs,
+        ),
+    ));
+
+    let mut deposit_tree = LayeredLayeredPoseidonSparseMerkleTree::new(
+        NodeDataMemory::default(),
+        RootDataTmp::default(),
+    );
+    for leaf in block_details.deposit_list.iter() {
+        deposit_tree
+            .set(
+                leaf.receiver_address.to_hash_out().into(),
+                leaf.contract_address.to_hash_out().into(),
+                leaf.variable_index.to_hash_out().into(),
+                HashOut::from_partial(&[leaf.amount]).into(),
+            )
+            .unwrap();
+    }
+    checks.push(ReplayCheck::new(
+        "deposit digest",
+        WrappedHashOut::from(header.deposit_digest),
+        deposit_tree.get_root().unwrap(),
+    ));
+
+    checks.push(replay_root_chain(
+        "proposed world state digest",
+        prev_header.approved_world_state_digest.into(),
+        block_details
+            .world_state_process_proofs
+            .iter()
+            .map(|proof| (proof.old_root, proof.new_root)),
+        header.proposed_world_state_digest,
+    ));
+    checks.push(replay_root_chain(
+        "approved world state digest",
+        header.proposed_world_state_digest.into(),
+        block_details
+            .world_state_revert_proofs
+            .iter()
+            .map(|proof| (proof.old_root, proof.new_root)),
+        header.approved_world_state_digest,
+    ));
+    checks.push(replay_root_chain(
+        "latest account digest",
+        prev_header.latest_account_digest.into(),
+        block_details
+            .latest_account_process_proofs
+            .iter()
+            .map(|proof| (proof.old_root, proof.new_root)),
+        header.latest_account_digest,
+    ));
+
+    BlockReplay {
+        block_number: header.block_number,
+        checks,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_replay_checks() {
+        let leaves = (0..3).map(|_| WrappedHashOut::rand()).collect::<Vec<_>>();
+        let default_leaf = WrappedHashOut::rand();
+        let root = calc_merkle_root_of_leaves(&leaves, default_leaf, 2);
+        let hash = |left, right| PoseidonNodeHash::calc_node_hash(Node::Internal(left, right));
+        let proof = MerkleProof {
+            root,
+            index: 1,
+            value: leaves[1],
+            siblings: vec![leaves[0], hash(leaves[2], default_leaf)],
+        };
+        assert_eq!(calc_merkle_root(&proof), root);
+
+        let roots = (0..3).map(|_| WrappedHashOut::rand()).collect::<Vec<_>>();
+        let chain = vec![
+            (roots[0], roots[1]),
+            (roots[2], roots[2]),
+            (roots[1], roots[2]),
+        ];
+        let check = replay_root_chain("chain", roots[0], chain.clone().into_iter(), *roots[2]);
+        assert!(check.matches());
+
+        let check = replay_root_chain("chain", roots[1], chain.into_iter(), *roots[2]);
+        assert_eq!(check.name, "chain (old root of proof 0)");
+
+        let replay = BlockReplay {
+            block_number: 5,
+            checks: vec![
+                ReplayCheck::new("a", 1, 1),
+                ReplayCheck::new("b", 1, 2),
+                ReplayCheck::new("c", 1, 3),
+            ],
+        };
+        assert_eq!(replay.first_divergence().unwrap().name, "b");
+        assert!(replay.to_string().ends_with("block 5 first diverges at: b"));
+    }
+}
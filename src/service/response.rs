@@ -0,0 +1,227 @@
+//! The checks applied to every response of the aggregator after it is decoded,
+//! so that a malformed response is reported as an error instead of a panic later.
+
+use intmax_rollup_interface::interface::*;
+use serde::de::DeserializeOwned;
+
+use super::{codec::WireFormat, error::InvalidResponse};
+
+/// The maximum number of blocks or proofs in one response.
+pub const MAX_RESPONSE_ITEMS: usize = 100_000;
+
+/// The invariants of a response, which are checked after it is decoded.
+pub trait ValidateResponse {
+    /// Returns the reason if the response is malformed.
+    fn validate(&self) -> Result<(), String> {
+        Ok(())
+    }
+}
+
+/// Convert a decoding error or a broken invariant into `InvalidResponse`.
+pub fn check_response<T: ValidateResponse>(
+    api_path: &str,
+    decoded_value: anyhow::Result<T>,
+) -> Result<T, InvalidResponse> {
+    let value = decoded_value
+        .map_err(|err| InvalidResponse::new(api_path, format!("fail to decode: {err}")))?;
+    value
+        .validate()
+        .map_err(|reason| InvalidResponse::new(api_path, reason))?;
+
+    Ok(value)
+}
+
+pub fn decode_response<T: DeserializeOwned + ValidateResponse>(
+    api_path: &str,
+    wire_format: WireFormat,
+    body: &[u8],
+) -> Result<T, InvalidResponse> {
+    check_response(api_path, wire_format.decode(body))
+}
+
+fn validate_item_count(name: &str, count: usize) -> Result<(), String> {
+    if count > MAX_RESPONSE_ITEMS {
+        return Err(format!(
+            "too many {name}: {count} (at most {MAX_RESPONSE_ITEMS})"
+        ));
+    }
+
+    Ok(())
+}
+
+/// The blocks must be in ascending order without duplicates and not after the latest block.
+fn validate_block_numbers(block_numbers: &[u32], latest_block_number: u32) -> Result<(), String> {
+    validate_item_count("blocks", block_numbers.len())?;
+    for pair in block_numbers.windows(2) {
+        if pair[0] >= pair[1] {
+            return Err(format!(
+                "block {} is returned after block {}",
+                pair[1], pair[0]
+            ));
+        }
+    }
+    if let Some(last_block_number) = block_numbers.last() {
+        if *last_block_number > latest_block_number {
+            return Err(format!(
+                "block {last_block_number} is after the latest block {latest_block_number}"
+            ));
+        }
+    }
+
+    Ok(())
+}
+
+/// The witness must be a non-empty 0x-prefixed hex string.
+fn validate_hex_witness(witness: &str) -> Result<(), String> {
+    let digits = witness
+        .strip_prefix("0x")
+        .ok_or_else(|| "the witness is not 0x-prefixed".to_string())?;
+    if digits.is_empty() || digits.len() % 2 != 0 {
+        return Err(format!(
+            "the witness has an invalid length: {}",
+            witness.len()
+        ));
+    }
+    if !digits.chars().all(|c| c.is_ascii_hexdigit()) {
+        return Err("the witness is not a hex string".to_string());
+    }
+
+    Ok(())
+}
+
+impl ValidateResponse for ResponseBlockQuery {
+    fn validate(&self) -> Result<(), String> {
+        let block_numbers = self
+            .blocks
+            .iter()
+            .map(|block| block.header.block_number)
+            .collect::<Vec<_>>();
+
+        validate_block_numbers(&block_numbers, self.latest_block_number)
+    }
+}
+
+impl ValidateResponse for ResponseAssetReceivedQuery {
+    fn validate(&self) -> Result<(), String> {
+        validate_item_count("received asset proofs", self.proofs.len())?;
+        for proof in self.proofs.iter() {
+            let block_number = proof.diff_tree_inclusion_proof.0.block_number;
+            if block_number > self.latest_block_number {
+                return Err(format!(
+                    "an asset is received in block {block_number} after the latest block {}",
+                    self.latest_block_number
+                ));
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl ValidateResponse for ResponseTxConfirmationWitnessQuery {
+    fn validate(&self) -> Result<(), String> {
+        validate_hex_witness(&self.witness)
+    }
+}
+
+impl ValidateResponse for ResponseTransactionProofQuery {
+    fn validate(&self) -> Result<(), String> {
+        validate_hex_witness(&self.witness)?;
+        if self.transaction_proof.siblings.is_empty() {
+            return Err("the transaction proof is empty".to_string());
+        }
+
+        Ok(())
+    }
+}
+
+impl ValidateResponse for ResponseAccountRegisterBody {}
+impl ValidateResponse for ResponseDepositAddBody {}
+impl ValidateResponse for ResponseTxSendBody {}
+impl ValidateResponse for ResponseCheckHealth {}
+impl ValidateResponse for ResponseTxBroadcastBody {}
+impl ValidateResponse for ResponseBlockProposeBody {}
+impl ValidateResponse for ResponseBlockApproveBody {}
+impl ValidateResponse for ResponseLatestBlockQuery {}
+impl ValidateResponse for ResponseBlockDetailQuery {}
+impl ValidateResponse for ResponseTxReceiptQuery {}
+impl ValidateResponse for ResponseSignedDiffSendBody {}
+impl ValidateResponse for ResponseUserAssetProofBody {}
+
+#[cfg(test)]
+mod tests {
+    use rand::{rngs::StdRng, Rng, SeedableRng};
+
+    use super::*;
+
+    #[test]
+    fn test_validate_response() {
+        assert!(validate_block_numbers(&[], 0).is_ok());
+        assert!(validate_block_numbers(&[1, 2, 5], 5).is_ok());
+        assert!(validate_block_numbers(&[1, 1], 5).is_err());
+        assert!(validate_block_numbers(&[2, 1], 5).is_err());
+        assert!(validate_block_numbers(&[1, 6], 5).is_err());
+
+        assert!(validate_hex_witness("0xabcd").is_ok());
+        assert!(validate_hex_witness("").is_err());
+        assert!(validate_hex_witness("0x").is_err());
+        assert!(validate_hex_witness("0xabc").is_err());
+        assert!(validate_hex_witness("abcd").is_err());
+        assert!(validate_hex_witness("0xzz").is_err());
+        assert!(validate_hex_witness("0xé0").is_err());
+    }
+
+    /// Returns `body` with random bytes replaced, inserted or removed, or truncated.
+    fn corrupt(rng: &mut StdRng, body: &[u8]) -> Vec<u8> {
+        let mut body = body.to_vec();
+        for _ in 0..rng.gen_range(1..4) {
+            let index = rng.gen_range(0..=body.len());
+            match rng.gen_range(0..4) {
+                0 if index < body.len() => body[index] = rng.gen(),
+                1 => body.insert(index, rng.gen()),
+                2 if index < body.len() => {
+                    body.remove(index);
+                }
+                _ => body.truncate(index),
+            }
+        }
+
+        body
+    }
+
+    #[test]
+    fn test_decode_corrupted_response() {
+        let mut rng = StdRng::seed_from_u64(0);
+        let bodies: [&[u8]; 3] = [
+            br#"{"blocks":[],"latest_block_number":3}"#,
+            br#"{"proofs":[],"latest_block_number":3}"#,
+            br#"{"witness":"0xabcd"}"#,
+        ];
+        for body in bodies {
+            for _ in 0..1000 {
+                let body = corrupt(&mut rng, body);
+                // Only the absence of panics is checked.
+                let _ = decode_response::<ResponseBlockQuery>("/block", WireFormat::Json, &body);
+                let _ = decode_response::<ResponseAssetReceivedQuery>(
+                    "/asset/received",
+                    WireFormat::Json,
+                    &body,
+                );
+                let _ = decode_response::<ResponseTxConfirmationWitnessQuery>(
+                    "/tx/witness",
+                    WireFormat::Json,
+                    &body,
+                );
+                let _ = decode_response::<ResponseTransactionProofQuery>(
+                    "/account/transaction-proof",
+                    WireFormat::MsgPack,
+                    &body,
+                );
+            }
+        }
+
+        let error = decode_response::<ResponseBlockQuery>("/block", WireFormat::Json, b"<html>")
+            .unwrap_err();
+        assert_eq!(error.api_path, "/block");
+    }
+}
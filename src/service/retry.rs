@@ -0,0 +1,218 @@
+//! Retrying the requests to the aggregator which failed temporarily,
+//! e.g., with 502 from the load balancer while the aggregator is restarting.
+//! A request is retried only if it never reached the aggregator or the aggregator responded 5xx,
+//! since 4xx means the request itself was rejected and repeating it gives the same result.
+//! Only the idempotent requests, i.e., the queries with GET, are retried.
+//! A POST such as `/tx/send` may have been processed even if it responded 5xx,
+//! so it is sent once and its failure is reported to the user.
+
+use std::time::Duration;
+
+use anyhow::Context;
+use rand::Rng;
+use reqwest::{RequestBuilder, Response};
+
+use crate::service::clock::parse_http_date;
+
+/// The number of attempts including the first one if `http.max_attempts` is not set.
+pub const DEFAULT_MAX_ATTEMPTS: u32 = 3;
+
+/// The delay before the first retry if `http.retry_backoff` is not set.
+pub const DEFAULT_RETRY_BACKOFF: Duration = Duration::from_millis(500);
+
+/// No delay exceeds this, even if the aggregator asks for a longer one with `Retry-After`.
+const MAX_RETRY_BACKOFF: Duration = Duration::from_secs(30);
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct RetryPolicy {
+    /// the number of attempts including the first one. `1` disables retrying.
+    pub max_attempts: u32,
+    /// the delay before the first retry, which is doubled at each retry
+    pub backoff: Duration,
+    /// If true, each delay is randomized between its half and the whole,
+    /// so that the clients failed at once do not retry at once.
+    pub jitter: bool,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_attempts: DEFAULT_MAX_ATTEMPTS,
+            backoff: DEFAULT_RETRY_BACKOFF,
+            jitter: true,
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// Returns the delay before the `retry`-th retry, counted from 1.
+    /// `retry_after` given by the aggregator is respected if it is longer.
+    pub fn delay(&self, retry: u32, retry_after: Option<Duration>) -> Duration {
+        let exponent = retry.saturating_sub(1).min(16);
+        let mut delay = self
+            .backoff
+            .saturating_mul(1 << exponent)
+            .min(MAX_RETRY_BACKOFF);
+        if self.jitter {
+            let half = delay / 2;
+            delay = half + half.mul_f64(rand::thread_rng().gen::<f64>());
+        }
+        if let Some(retry_after) = retry_after {
+            delay = delay.max(retry_after);
+        }
+
+        delay.min(MAX_RETRY_BACKOFF)
+    }
+}
+
+/// 501 and 505 are excluded, since they do not go away by retrying.
+pub fn is_retryable_status(status: u16) -> bool {
+    (500..600).contains(&status) && status != 501 && status != 505
+}
+
+/// `Retry-After` is either seconds or an HTTP date.
+fn parse_retry_after(resp: &Response) -> Option<Duration> {
+    let retry_after = resp
+        .headers()
+        .get(reqwest::header::RETRY_AFTER)?
+        .to_str()
+        .ok()?
+        .trim();
+    if let Ok(seconds) = retry_after.parse::<u64>() {
+        return Some(Duration::from_secs(seconds));
+    }
+
+    let retry_at = parse_http_date(retry_after)?;
+    let now = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .ok()?
+        .as_secs();
+
+    Some(Duration::from_secs(retry_at.saturating_sub(now)))
+}
+
+/// Returns true if the method of the request is idempotent, e.g., GET.
+/// A request whose body cannot be cloned is taken as non-idempotent.
+fn is_idempotent(request: &RequestBuilder) -> bool {
+    request
+        .try_clone()
+        .and_then(|request| request.build().ok())
+        .map_or(false, |request| request.method().is_idempotent())
+}
+
+/// Send the request, retrying it with exponential backoff while it fails temporarily.
+/// A non-idempotent request is sent only once.
+/// The last response is returned as is even if it is 5xx, so that the caller reports it.
+pub async fn send_with_retry(
+    retry_policy: &RetryPolicy,
+    api_path: &str,
+    request: RequestBuilder,
+) -> anyhow::Result<Response> {
+    if !is_idempotent(&request) {
+        return request.send().await.with_context(|| {
+            format!(
+                "fail to request {api_path}. Check the aggregator URL and your network connection"
+            )
+        });
+    }
+
+    let mut retry = 0;
+    loop {
+        let attempt = request
+            .try_clone()
+            .with_context(|| format!("the request to {api_path} cannot be retried"))?;
+        let can_retry = retry + 1 < retry_policy.max_attempts;
+        let (reason, retry_after) = match attempt.send().await {
+            Ok(resp) if can_retry && is_retryable_status(resp.status().as_u16()) => (
+                format!("responded {}", resp.status()),
+                parse_retry_after(&resp),
+            ),
+            Ok(resp) => return Ok(resp),
+            Err(err) if can_retry && err.is_connect() => (format!("is unreachable: {err}"), None),
+            Err(err) => {
+                return Err(err).with_context(|| {
+                    format!("fail to request {api_path}. Check the aggregator URL and your network connection")
+                });
+            }
+        };
+
+        retry += 1;
+        let delay = retry_policy.delay(retry, retry_after);
+        eprintln!(
+            "WARNING: {api_path} {reason}. Retrying in {}.{:03} sec ({retry}/{})",
+            delay.as_secs(),
+            delay.subsec_millis(),
+            retry_policy.max_attempts - 1
+        );
+        tokio::time::sleep(delay).await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::test_utils::MockAggregator;
+
+    #[test]
+    fn test_retry_delay() {
+        let retry_policy = RetryPolicy {
+            max_attempts: 5,
+            backoff: Duration::from_millis(500),
+            jitter: false,
+        };
+        assert_eq!(retry_policy.delay(1, None), Duration::from_millis(500));
+        assert_eq!(retry_policy.delay(3, None), Duration::from_secs(2));
+        assert_eq!(retry_policy.delay(100, None), MAX_RETRY_BACKOFF);
+        assert_eq!(
+            retry_policy.delay(1, Some(Duration::from_secs(3))),
+            Duration::from_secs(3)
+        );
+        assert_eq!(
+            retry_policy.delay(1, Some(Duration::from_secs(3600))),
+            MAX_RETRY_BACKOFF
+        );
+
+        let retry_policy = RetryPolicy {
+            jitter: true,
+            ..retry_policy
+        };
+        for _ in 0..100 {
+            let delay = retry_policy.delay(2, None);
+            assert!(delay >= Duration::from_millis(500) && delay <= Duration::from_secs(1));
+        }
+
+        assert!(is_retryable_status(502));
+        assert!(is_retryable_status(500));
+        assert!(!is_retryable_status(501));
+        assert!(!is_retryable_status(400));
+    }
+
+    #[tokio::test]
+    async fn test_send_with_retry() {
+        let aggregator = MockAggregator::spawn(|_, _| (502, "bad gateway".to_string()));
+        let retry_policy = RetryPolicy {
+            max_attempts: 3,
+            backoff: Duration::from_millis(1),
+            jitter: false,
+        };
+        let client = reqwest::Client::new();
+
+        // The query is retried up to `max_attempts` times.
+        let request = client.get(format!("{}/block", aggregator.url));
+        let resp = send_with_retry(&retry_policy, "/block", request)
+            .await
+            .unwrap();
+        assert_eq!(resp.status().as_u16(), 502);
+        assert_eq!(aggregator.count("GET /block"), 3);
+
+        // The transaction may have been accepted, so it is not sent again.
+        let request = client
+            .post(format!("{}/tx/send", aggregator.url))
+            .json(&serde_json::json!({ "tx": "0x00" }));
+        let resp = send_with_retry(&retry_policy, "/tx/send", request)
+            .await
+            .unwrap();
+        assert_eq!(resp.status().as_u16(), 502);
+        assert_eq!(aggregator.count("POST /tx/send"), 1);
+    }
+}
@@ -0,0 +1,253 @@
+use std::collections::BTreeMap;
+
+use intmax_rollup_interface::intmax_zkp_core::{
+    plonky2::plonk::config::{GenericConfig, PoseidonGoldilocksConfig},
+    transaction::asset::TokenKind,
+    zkdsa::account::Address,
+};
+
+use super::export::escape_csv_field;
+use crate::utils::key_management::{
+    stats::{format_date, TransferDirection, TransferRecord, SECONDS_PER_DAY},
+    types::Assets,
+};
+
+const D: usize = 2;
+type C = PoseidonGoldilocksConfig;
+type F = <C as GenericConfig<D>>::F;
+
+pub const STATEMENT_CSV_HEADER: &str =
+    "date,type,token_address,token_id,counterparty,amount,balance,reference";
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum StatementRowKind {
+    Opening,
+    Transfer(TransferDirection),
+    Closing,
+}
+
+impl std::fmt::Display for StatementRowKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Opening => write!(f, "opening"),
+            Self::Transfer(direction) => write!(f, "{direction}"),
+            Self::Closing => write!(f, "closing"),
+        }
+    }
+}
+
+/// A line of `account statement`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct StatementRow {
+    /// `YYYY-MM-DD` in UTC
+    pub date: String,
+    pub kind: StatementRowKind,
+    pub token: TokenKind<F>,
+    pub counterparty: Option<Address<F>>,
+    /// negative for outflows, and zero for the opening and closing lines
+    pub amount: i128,
+    /// the running balance of the token after this line
+    pub balance: i128,
+    pub reference: String,
+}
+
+fn signed_amount(record: &TransferRecord) -> i128 {
+    match record.direction {
+        TransferDirection::Sent => -(record.amount as i128),
+        TransferDirection::Received => record.amount as i128,
+    }
+}
+
+/// Returns the lines for each token which was held or transferred in `[from, to]`,
+/// where `from` and `to` are the UNIX times at the beginning of the days.
+///
+/// The balances are derived backwards from the current assets,
+/// so they are correct even if the history was recorded after the account was created.
+pub fn build_statement(
+    history: &[TransferRecord],
+    assets: &Assets<F>,
+    user_address: Address<F>,
+    from: u64,
+    to: u64,
+) -> anyhow::Result<Vec<StatementRow>> {
+    if from > to {
+        anyhow::bail!("--from must not be after --to");
+    }
+    let end = to + SECONDS_PER_DAY;
+
+    // Transfers to yourself, e.g., merges, do not change the balances.
+    let history = history
+        .iter()
+        .filter(|record| record.counterparty != Some(user_address))
+        .collect::<Vec<_>>();
+
+    let mut current_balances: BTreeMap<(String, String), (TokenKind<F>, i128)> = BTreeMap::new();
+    for (kind, amount, _) in assets.0.iter() {
+        let key = (
+            kind.contract_address.to_string(),
+            kind.variable_index.to_string(),
+        );
+        current_balances.entry(key).or_insert((*kind, 0)).1 += *amount as i128;
+    }
+    for record in history.iter() {
+        let key = (
+            record.kind.contract_address.to_string(),
+            record.kind.variable_index.to_string(),
+        );
+        current_balances.entry(key).or_insert((record.kind, 0));
+    }
+
+    let mut rows = vec![];
+    for (kind, current_balance) in current_balances.into_values() {
+        let records = history
+            .iter()
+            .filter(|record| record.kind == kind)
+            .collect::<Vec<_>>();
+        let net_after_period: i128 = records
+            .iter()
+            .filter(|record| record.timestamp >= end)
+            .map(|record| signed_amount(record))
+            .sum();
+        let mut records_in_period = records
+            .into_iter()
+            .filter(|record| record.timestamp >= from && record.timestamp < end)
+            .collect::<Vec<_>>();
+        records_in_period.sort_by_key(|record| record.timestamp);
+        let net_in_period: i128 = records_in_period
+            .iter()
+            .map(|record| signed_amount(record))
+            .sum();
+
+        let closing_balance = current_balance - net_after_period;
+        let opening_balance = closing_balance - net_in_period;
+        if opening_balance == 0 && records_in_period.is_empty() {
+            continue;
+        }
+
+        rows.push(StatementRow {
+            date: format_date(from),
+            kind: StatementRowKind::Opening,
+            token: kind,
+            counterparty: None,
+            amount: 0,
+            balance: opening_balance,
+            reference: String::new(),
+        });
+        let mut balance = opening_balance;
+        for record in records_in_period {
+            balance += signed_amount(record);
+            rows.push(StatementRow {
+                date: format_date(record.timestamp),
+                kind: StatementRowKind::Transfer(record.direction),
+                token: kind,
+                counterparty: record.counterparty,
+                amount: signed_amount(record),
+                balance,
+                reference: record.reference.clone(),
+            });
+        }
+        rows.push(StatementRow {
+            date: format_date(to),
+            kind: StatementRowKind::Closing,
+            token: kind,
+            counterparty: None,
+            amount: 0,
+            balance: closing_balance,
+            reference: String::new(),
+        });
+    }
+
+    Ok(rows)
+}
+
+pub fn statement_to_csv(rows: &[StatementRow]) -> String {
+    let mut csv = format!("{STATEMENT_CSV_HEADER}\n");
+    for row in rows {
+        let record = [
+            row.date.clone(),
+            row.kind.to_string(),
+            row.token.contract_address.to_string(),
+            row.token.variable_index.to_string(),
+            row.counterparty
+                .map(|address| address.to_string())
+                .unwrap_or_default(),
+            row.amount.to_string(),
+            row.balance.to_string(),
+            row.reference.clone(),
+        ]
+        .iter()
+        .map(|field| escape_csv_field(field))
+        .collect::<Vec<_>>();
+        csv += &format!("{}\n", record.join(","));
+    }
+
+    csv
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{collections::HashSet, str::FromStr};
+
+    use intmax_rollup_interface::intmax_zkp_core::sparse_merkle_tree::goldilocks_poseidon::WrappedHashOut;
+
+    use super::*;
+    use crate::utils::key_management::{stats::parse_date, types::MergeKey};
+
+    #[test]
+    fn test_build_statement() {
+        let user_address = Address::from_str("0x714bdc6f38947e6d").unwrap();
+        let counterparty = Address::from_str("0x0000000000000001").unwrap();
+        let kind = TokenKind {
+            contract_address: counterparty,
+            variable_index: 0u8.into(),
+        };
+        let record = |date: &str, direction, counterparty, amount| TransferRecord {
+            timestamp: parse_date(date).unwrap() + 60,
+            direction,
+            kind,
+            counterparty: Some(counterparty),
+            amount,
+            reference: String::new(),
+            block_number: None,
+        };
+        let history = vec![
+            record("2023-12-31", TransferDirection::Received, counterparty, 100),
+            record("2024-01-10", TransferDirection::Sent, counterparty, 30),
+            record("2024-01-10", TransferDirection::Sent, user_address, 70),
+            record("2024-03-31", TransferDirection::Received, counterparty, 5),
+            record("2024-04-01", TransferDirection::Received, counterparty, 1),
+        ];
+        let assets = Assets(HashSet::from([(
+            kind,
+            76,
+            MergeKey(WrappedHashOut::rand()),
+        )]));
+
+        let rows = build_statement(
+            &history,
+            &assets,
+            user_address,
+            parse_date("2024-01-01").unwrap(),
+            parse_date("2024-03-31").unwrap(),
+        )
+        .unwrap();
+        let balances = rows
+            .iter()
+            .map(|row| (row.kind.to_string(), row.amount, row.balance))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            balances,
+            vec![
+                ("opening".to_string(), 0, 100),
+                ("sent".to_string(), -30, 70),
+                ("received".to_string(), 5, 75),
+                ("closing".to_string(), 0, 75),
+            ]
+        );
+        assert_eq!(rows[3].date, "2024-03-31");
+
+        let csv = statement_to_csv(&rows);
+        assert!(csv.starts_with(STATEMENT_CSV_HEADER));
+        assert_eq!(csv.lines().count(), 5);
+    }
+}
@@ -0,0 +1,348 @@
+use std::{
+    collections::BTreeSet,
+    fs::OpenOptions,
+    io::Write,
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex},
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+};
+
+use anyhow::Context;
+use intmax_interoperability_plugin::ethers::utils::keccak256;
+use serde::{Deserialize, Serialize};
+
+pub const AUDIT_LOG_FILE_NAME: &str = "audit.log";
+
+/// How long to wait for another command appending to the audit log.
+const AUDIT_LOCK_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// A lock file older than this was left by a command which crashed, and is removed.
+const STALE_AUDIT_LOCK_AGE: Duration = Duration::from_secs(60);
+
+/// `prev_hash` of the first entry.
+pub const GENESIS_AUDIT_HASH: &str =
+    "0x0000000000000000000000000000000000000000000000000000000000000000";
+
+/// A line of the audit log.
+/// Each entry includes the hash of the previous entry,
+/// so editing or removing an entry breaks the chain after it.
+/// Removing the last entries, or rewriting the whole chain, is not detected,
+/// since the log has nothing to compare with outside itself.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AuditEntry {
+    pub index: u64,
+    /// UNIX time in seconds
+    pub timestamp: u64,
+    pub command: String,
+    pub arguments: String,
+    pub aggregator_url: Option<String>,
+    /// The transactions sent on intmax by the command
+    pub tx_hashes: Vec<String>,
+    /// The error message if the command failed
+    pub error: Option<String>,
+    pub prev_hash: String,
+    pub hash: String,
+}
+
+impl AuditEntry {
+    /// Keccak-256 hash of the JSON encoding of the entry with `hash` left empty.
+    pub fn calc_hash(&self) -> String {
+        let mut entry = self.clone();
+        entry.hash = String::new();
+        let encoded_entry = serde_json::to_vec(&entry).expect("fail to encode audit entry");
+
+        format!("0x{}", hex::encode(keccak256(encoded_entry)))
+    }
+}
+
+/// Read all entries of the audit log. A missing file is regarded as empty.
+pub fn read_audit_log(audit_log_path: &Path) -> anyhow::Result<Vec<AuditEntry>> {
+    let encoded_audit_log = match std::fs::read_to_string(audit_log_path) {
+        Ok(encoded_audit_log) => encoded_audit_log,
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(vec![]),
+        Err(err) => {
+            return Err(err).with_context(|| format!("fail to read {}", audit_log_path.display()))
+        }
+    };
+
+    encoded_audit_log
+        .lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty())
+        .map(|(i, line)| {
+            serde_json::from_str(line)
+                .with_context(|| format!("invalid audit log entry at line {}", i + 1))
+        })
+        .collect()
+}
+
+/// Check that the entries are numbered in order and chained by their hashes.
+/// The truncated or entirely rewritten log passes this check.
+pub fn verify_audit_log(entries: &[AuditEntry]) -> anyhow::Result<()> {
+    let mut prev_hash = GENESIS_AUDIT_HASH;
+    for (i, entry) in entries.iter().enumerate() {
+        if entry.index != i as u64 {
+            anyhow::bail!(
+                "audit log is broken at entry {i}: index {} was found",
+                entry.index
+            );
+        }
+
+        if entry.prev_hash != prev_hash {
+            anyhow::bail!(
+                "audit log is broken at entry {i}: the previous entry was modified or removed"
+            );
+        }
+
+        if entry.hash != entry.calc_hash() {
+            anyhow::bail!("audit log is broken at entry {i}: the entry was modified");
+        }
+
+        prev_hash = &entry.hash;
+    }
+
+    Ok(())
+}
+
+/// A lock file held while an entry is appended, so that two commands sharing the wallet
+/// do not chain their entries to the same last entry.
+struct AuditLogLock {
+    lock_file_path: PathBuf,
+}
+
+impl AuditLogLock {
+    fn acquire(audit_log_path: &Path) -> anyhow::Result<Self> {
+        let lock_file_path = audit_log_path.with_extension("log.lock");
+        let start = Instant::now();
+        loop {
+            let result = OpenOptions::new()
+                .write(true)
+                .create_new(true)
+                .open(&lock_file_path);
+            match result {
+                Ok(_) => return Ok(Self { lock_file_path }),
+                Err(err) if err.kind() == std::io::ErrorKind::AlreadyExists => {
+                    let is_stale = std::fs::metadata(&lock_file_path)
+                        .and_then(|metadata| metadata.modified())
+                        .ok()
+                        .and_then(|modified| modified.elapsed().ok())
+                        .map_or(false, |age| age > STALE_AUDIT_LOCK_AGE);
+                    if is_stale {
+                        let _ = std::fs::remove_file(&lock_file_path);
+                        continue;
+                    }
+                    if start.elapsed() > AUDIT_LOCK_TIMEOUT {
+                        anyhow::bail!(
+                            "fail to lock the audit log: {} exists. Remove it if no other command is running",
+                            lock_file_path.display()
+                        );
+                    }
+
+                    std::thread::sleep(Duration::from_millis(50));
+                }
+                Err(err) => {
+                    return Err(err)
+                        .with_context(|| format!("fail to create {}", lock_file_path.display()))
+                }
+            }
+        }
+    }
+}
+
+impl Drop for AuditLogLock {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_file(&self.lock_file_path);
+    }
+}
+
+/// Append an entry chained to the last entry of the audit log.
+/// The log is locked from reading the last entry to appending the new one.
+pub fn append_audit_entry(
+    audit_log_path: &Path,
+    command: String,
+    arguments: String,
+    aggregator_url: Option<String>,
+    tx_hashes: Vec<String>,
+    error: Option<String>,
+) -> anyhow::Result<AuditEntry> {
+    if let Some(audit_log_dir) = audit_log_path.parent() {
+        std::fs::create_dir_all(audit_log_dir)?;
+    }
+    let _lock = AuditLogLock::acquire(audit_log_path)?;
+
+    let entries = read_audit_log(audit_log_path)?;
+    let (index, prev_hash) = if let Some(last_entry) = entries.last() {
+        (last_entry.index + 1, last_entry.hash.clone())
+    } else {
+        (0, GENESIS_AUDIT_HASH.to_string())
+    };
+
+    let mut entry = AuditEntry {
+        index,
+        timestamp: SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs(),
+        command,
+        arguments,
+        aggregator_url,
+        tx_hashes,
+        error,
+        prev_hash,
+        hash: String::new(),
+    };
+    entry.hash = entry.calc_hash();
+
+    let mut file = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(audit_log_path)
+        .with_context(|| format!("fail to open {}", audit_log_path.display()))?;
+    writeln!(file, "{}", serde_json::to_string(&entry)?)?;
+    file.flush()?;
+
+    Ok(entry)
+}
+
+/// Collects what a state-changing command did while it runs,
+/// and appends it to the audit log when it finishes.
+#[derive(Debug, Default)]
+pub struct AuditRecorder {
+    /// The name and the arguments of the command. `None` if it does not change any state.
+    operation: Option<(String, String)>,
+    audit_log_path: Option<PathBuf>,
+    aggregator_url: Option<String>,
+    /// the transactions written into the wallet by the command, which are given by the wallet
+    sent_tx_hashes: Arc<Mutex<BTreeSet<String>>>,
+}
+
+impl AuditRecorder {
+    pub fn new(operation: Option<(String, String)>) -> Self {
+        Self {
+            operation,
+            ..Default::default()
+        }
+    }
+
+    pub fn set_audit_log_path(&mut self, audit_log_path: PathBuf) {
+        self.audit_log_path = Some(audit_log_path);
+    }
+
+    pub fn set_aggregator_url(&mut self, aggregator_url: &str) {
+        self.aggregator_url = Some(aggregator_url.to_string());
+    }
+
+    /// The set to which the wallet reports the transactions sent by the command.
+    /// `None` if the command is not recorded.
+    pub fn sent_tx_hash_sink(&self) -> Option<Arc<Mutex<BTreeSet<String>>>> {
+        self.operation
+            .is_some()
+            .then(|| self.sent_tx_hashes.clone())
+    }
+
+    pub fn finish(self, result: &anyhow::Result<()>) -> anyhow::Result<()> {
+        let (command, arguments) = if let Some(operation) = self.operation {
+            operation
+        } else {
+            return Ok(());
+        };
+        let audit_log_path = if let Some(audit_log_path) = self.audit_log_path {
+            audit_log_path
+        } else {
+            return Ok(());
+        };
+
+        let tx_hashes = self
+            .sent_tx_hashes
+            .lock()
+            .unwrap()
+            .iter()
+            .cloned()
+            .collect();
+        let error = result.as_ref().err().map(|err| format!("{err:#}"));
+
+        append_audit_entry(
+            &audit_log_path,
+            command,
+            arguments,
+            self.aggregator_url,
+            tx_hashes,
+            error,
+        )
+        .context("fail to write audit log")?;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::test_utils::temp_dir;
+
+    #[test]
+    fn test_audit_log() {
+        let temp_dir = temp_dir();
+        let audit_log_path = temp_dir.path().join(AUDIT_LOG_FILE_NAME);
+        for i in 0..3 {
+            append_audit_entry(
+                &audit_log_path,
+                "tx send".to_string(),
+                format!("amount: {i}"),
+                Some("localhost:8080".to_string()),
+                vec![format!("0x{i:064x}")],
+                None,
+            )
+            .unwrap();
+        }
+
+        let entries = read_audit_log(&audit_log_path).unwrap();
+        assert_eq!(entries.len(), 3);
+        assert_eq!(entries[0].prev_hash, GENESIS_AUDIT_HASH);
+        assert_eq!(entries[2].prev_hash, entries[1].hash);
+        verify_audit_log(&entries).unwrap();
+
+        // Modify an entry.
+        let mut modified_entries = entries.clone();
+        modified_entries[1].arguments = "amount: 100".to_string();
+        assert!(verify_audit_log(&modified_entries).is_err());
+
+        // Modify an entry and recompute its hash.
+        modified_entries[1].hash = modified_entries[1].calc_hash();
+        assert!(verify_audit_log(&modified_entries).is_err());
+
+        // Remove an entry.
+        let mut removed_entries = entries.clone();
+        removed_entries.remove(1);
+        assert!(verify_audit_log(&removed_entries).is_err());
+    }
+
+    #[test]
+    fn test_concurrent_audit_entries() {
+        let temp_dir = temp_dir();
+        let audit_log_path = temp_dir.path().join(AUDIT_LOG_FILE_NAME);
+        let handles = (0..4)
+            .map(|i| {
+                let audit_log_path = audit_log_path.clone();
+                std::thread::spawn(move || {
+                    for j in 0..5 {
+                        append_audit_entry(
+                            &audit_log_path,
+                            "tx send".to_string(),
+                            format!("amount: {i}{j}"),
+                            None,
+                            vec![],
+                            None,
+                        )
+                        .unwrap();
+                    }
+                })
+            })
+            .collect::<Vec<_>>();
+        for handle in handles {
+            handle.join().unwrap();
+        }
+
+        let entries = read_audit_log(&audit_log_path).unwrap();
+        assert_eq!(entries.len(), 20);
+        verify_audit_log(&entries).unwrap();
+        assert!(!audit_log_path.with_extension("log.lock").exists());
+    }
+}
@@ -0,0 +1,145 @@
+use std::{
+    collections::BTreeMap,
+    path::{Path, PathBuf},
+};
+
+use anyhow::Context;
+use dialoguer::Password;
+use serde::{Deserialize, Serialize};
+
+use super::terminal::TerminalOptions;
+
+pub const BACKUP_DIR_NAME: &str = "backups";
+
+/// The password of encrypted backups used in non-interactive mode.
+pub const BACKUP_PASSWORD_ENV: &str = "INTMAX_BACKUP_PASSWORD";
+
+/// The contents of the files included in a backup, keyed by their file names.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BackupArchive {
+    pub files: BTreeMap<String, String>,
+}
+
+impl BackupArchive {
+    /// Read the given files. Missing files are skipped.
+    pub fn read_from_files(file_paths: &[PathBuf]) -> anyhow::Result<Self> {
+        let mut files = BTreeMap::new();
+        for file_path in file_paths {
+            let file_name = file_path
+                .file_name()
+                .with_context(|| format!("invalid file path: {}", file_path.display()))?
+                .to_string_lossy()
+                .to_string();
+            match std::fs::read_to_string(file_path) {
+                Ok(content) => {
+                    files.insert(file_name, content);
+                }
+                Err(err) if err.kind() == std::io::ErrorKind::NotFound => {}
+                Err(err) => {
+                    return Err(err)
+                        .with_context(|| format!("fail to read {}", file_path.display()))
+                }
+            }
+        }
+
+        Ok(Self { files })
+    }
+}
+
+/// Encrypt the archive with `password` in the Ethereum keystore format
+/// and write it into `backup_dir`. Returns the path of the written file.
+pub fn write_encrypted_backup(
+    backup_dir: &Path,
+    file_name: &str,
+    archive: &BackupArchive,
+    password: &str,
+) -> anyhow::Result<PathBuf> {
+    std::fs::create_dir_all(backup_dir)?;
+    let encoded_archive = zeroize::Zeroizing::new(serde_json::to_vec(archive)?);
+    eth_keystore::encrypt_key(
+        backup_dir,
+        &mut rand::thread_rng(),
+        &*encoded_archive,
+        password,
+        Some(file_name),
+    )?;
+
+    Ok(backup_dir.join(file_name))
+}
+
+pub fn read_encrypted_backup(
+    backup_file_path: &Path,
+    password: &str,
+) -> anyhow::Result<BackupArchive> {
+    let encoded_archive = zeroize::Zeroizing::new(
+        eth_keystore::decrypt_key(backup_file_path, password)
+            .with_context(|| format!("fail to decrypt {}", backup_file_path.display()))?,
+    );
+
+    Ok(serde_json::from_slice(&encoded_archive)?)
+}
+
+/// Read the backup password from `INTMAX_BACKUP_PASSWORD`, or prompt it twice.
+pub fn read_backup_password(
+    terminal: &TerminalOptions,
+) -> anyhow::Result<zeroize::Zeroizing<String>> {
+    if let Ok(password) = std::env::var(BACKUP_PASSWORD_ENV) {
+        if password.is_empty() {
+            anyhow::bail!("{BACKUP_PASSWORD_ENV} is empty");
+        }
+
+        return Ok(zeroize::Zeroizing::new(password));
+    }
+
+    terminal.ensure_interactive(&format!(
+        "set the backup password with {BACKUP_PASSWORD_ENV}"
+    ))?;
+    let password = Password::with_theme(&*terminal.theme())
+        .with_prompt("Backup password")
+        .with_confirmation("Confirm backup password", "Passwords do not match")
+        .interact()?;
+
+    Ok(zeroize::Zeroizing::new(password))
+}
+
+/// Read the password of an existing backup from `INTMAX_BACKUP_PASSWORD`, or prompt it once.
+pub fn read_existing_backup_password(
+    terminal: &TerminalOptions,
+) -> anyhow::Result<zeroize::Zeroizing<String>> {
+    if let Ok(password) = std::env::var(BACKUP_PASSWORD_ENV) {
+        return Ok(zeroize::Zeroizing::new(password));
+    }
+
+    terminal.ensure_interactive(&format!(
+        "set the backup password with {BACKUP_PASSWORD_ENV}"
+    ))?;
+    let password = Password::with_theme(&*terminal.theme())
+        .with_prompt("Backup password")
+        .interact()?;
+
+    Ok(zeroize::Zeroizing::new(password))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::test_utils::temp_dir;
+
+    #[test]
+    fn test_encrypted_backup() {
+        let temp_dir = temp_dir();
+        let backup_dir = temp_dir.path().join("backups");
+        let mut archive = BackupArchive::default();
+        archive
+            .files
+            .insert("wallet".to_string(), r#"{"data":[]}"#.to_string());
+
+        let backup_file_path =
+            write_encrypted_backup(&backup_dir, "test.json", &archive, "password").unwrap();
+        assert_eq!(
+            read_encrypted_backup(&backup_file_path, "password").unwrap(),
+            archive
+        );
+        assert!(read_encrypted_backup(&backup_file_path, "wrong password").is_err());
+    }
+}
@@ -0,0 +1,179 @@
+//! Bech32 encoding (BIP-173) of intmax addresses, e.g., `intmax1w99acmecj3lx6sfqt4w`.
+//! The checksum detects mistyped or truncated addresses, which raw hex strings cannot.
+
+use intmax_rollup_interface::intmax_zkp_core::{
+    plonky2::plonk::config::{GenericConfig, PoseidonGoldilocksConfig},
+    zkdsa::account::Address,
+};
+
+use super::hex_string::{decode_hex, INTMAX_ADDRESS_LEN};
+
+const D: usize = 2;
+type C = PoseidonGoldilocksConfig;
+type F = <C as GenericConfig<D>>::F;
+
+/// The human-readable part of bech32-encoded intmax addresses.
+pub const INTMAX_ADDRESS_HRP: &str = "intmax";
+
+const CHARSET: &[u8; 32] = b"qpzry9x8gf2tvdw0s3jn54khce6mua7l";
+
+const GENERATOR: [u32; 5] = [0x3b6a57b2, 0x26508e6d, 0x1ea119fa, 0x3d4233dd, 0x2a1462b3];
+
+const CHECKSUM_LEN: usize = 6;
+
+fn polymod(values: &[u8]) -> u32 {
+    let mut checksum = 1u32;
+    for value in values {
+        let top = checksum >> 25;
+        checksum = ((checksum & 0x1ffffff) << 5) ^ *value as u32;
+        for (i, generator) in GENERATOR.iter().enumerate() {
+            if (top >> i) & 1 == 1 {
+                checksum ^= generator;
+            }
+        }
+    }
+
+    checksum
+}
+
+fn expand_hrp(hrp: &str) -> Vec<u8> {
+    let mut result = hrp.bytes().map(|c| c >> 5).collect::<Vec<_>>();
+    result.push(0);
+    result.extend(hrp.bytes().map(|c| c & 31));
+
+    result
+}
+
+/// Regroup `from_bits`-bit values into `to_bits`-bit values.
+fn convert_bits(data: &[u8], from_bits: u32, to_bits: u32, pad: bool) -> anyhow::Result<Vec<u8>> {
+    let mut accumulator = 0u32;
+    let mut bits = 0u32;
+    let mut result = vec![];
+    let max_value = (1u32 << to_bits) - 1;
+    for value in data {
+        accumulator = (accumulator << from_bits) | *value as u32;
+        bits += from_bits;
+        while bits >= to_bits {
+            bits -= to_bits;
+            result.push(((accumulator >> bits) & max_value) as u8);
+        }
+    }
+
+    if pad {
+        if bits > 0 {
+            result.push(((accumulator << (to_bits - bits)) & max_value) as u8);
+        }
+    } else if bits >= from_bits || (accumulator << (to_bits - bits)) & max_value != 0 {
+        anyhow::bail!("invalid padding");
+    }
+
+    Ok(result)
+}
+
+pub fn encode_bech32(hrp: &str, data: &[u8]) -> String {
+    let data = convert_bits(data, 8, 5, true).expect("padding never fails");
+    let mut values = expand_hrp(hrp);
+    values.extend(&data);
+    values.extend([0; CHECKSUM_LEN]);
+    let checksum = polymod(&values) ^ 1;
+
+    let mut result = format!("{hrp}1");
+    for value in data {
+        result.push(CHARSET[value as usize] as char);
+    }
+    for i in 0..CHECKSUM_LEN {
+        let value = (checksum >> (5 * (CHECKSUM_LEN - 1 - i))) & 31;
+        result.push(CHARSET[value as usize] as char);
+    }
+
+    result
+}
+
+/// Decode a bech32 string whose human-readable part is `hrp`.
+pub fn decode_bech32(hrp: &str, value: &str) -> anyhow::Result<Vec<u8>> {
+    if value.chars().any(|c| c.is_ascii_lowercase())
+        && value.chars().any(|c| c.is_ascii_uppercase())
+    {
+        anyhow::bail!("mixed case");
+    }
+
+    let value = value.to_ascii_lowercase();
+    let (given_hrp, encoded_data) = value
+        .rsplit_once('1')
+        .ok_or_else(|| anyhow::anyhow!("separator was not found"))?;
+    if given_hrp != hrp {
+        anyhow::bail!("prefix must be {hrp}1");
+    }
+    if encoded_data.len() < CHECKSUM_LEN {
+        anyhow::bail!("too short");
+    }
+
+    let data = encoded_data
+        .bytes()
+        .map(|c| {
+            CHARSET
+                .iter()
+                .position(|v| *v == c)
+                .map(|v| v as u8)
+                .ok_or_else(|| anyhow::anyhow!("invalid character {:?}", c as char))
+        })
+        .collect::<anyhow::Result<Vec<_>>>()?;
+    let mut values = expand_hrp(hrp);
+    values.extend(&data);
+    if polymod(&values) != 1 {
+        anyhow::bail!("invalid checksum");
+    }
+
+    convert_bits(&data[..data.len() - CHECKSUM_LEN], 5, 8, false)
+}
+
+/// Returns `true` if the value looks like a bech32-encoded intmax address.
+pub fn is_bech32_address(value: &str) -> bool {
+    value
+        .to_ascii_lowercase()
+        .starts_with(&format!("{INTMAX_ADDRESS_HRP}1"))
+}
+
+pub fn encode_intmax_address(address: &Address<F>) -> String {
+    let bytes = decode_hex("address", &address.to_string(), INTMAX_ADDRESS_LEN)
+        .expect("intmax address is always 8 bytes");
+
+    encode_bech32(INTMAX_ADDRESS_HRP, &bytes)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use super::*;
+
+    #[test]
+    fn test_bech32() {
+        // test vectors of BIP-173
+        assert_eq!(decode_bech32("a", "A12UEL5L").unwrap(), Vec::<u8>::new());
+        assert_eq!(encode_bech32("a", &[]), "a12uel5l");
+        assert!(decode_bech32("a", "A12uEL5L").is_err());
+        assert!(decode_bech32("a", "a12uel5m").is_err());
+
+        let address = Address::<F>::from_str("0x714bdc6f38947e6d").unwrap();
+        let encoded_address = encode_intmax_address(&address);
+        assert_eq!(encoded_address, "intmax1w99acmecj3lx6sfqt4w");
+        assert!(is_bech32_address(&encoded_address));
+        assert!(is_bech32_address(&encoded_address.to_uppercase()));
+        assert_eq!(
+            decode_bech32(INTMAX_ADDRESS_HRP, &encoded_address).unwrap(),
+            hex::decode("714bdc6f38947e6d").unwrap()
+        );
+
+        // A mistyped character is detected.
+        let mut mistyped_address = encoded_address.into_bytes();
+        let last = mistyped_address.len() - 1;
+        mistyped_address[last] = if mistyped_address[last] == b'q' {
+            b'p'
+        } else {
+            b'q'
+        };
+        let mistyped_address = String::from_utf8(mistyped_address).unwrap();
+        assert!(decode_bech32(INTMAX_ADDRESS_HRP, &mistyped_address).is_err());
+    }
+}
@@ -0,0 +1,530 @@
+use std::{
+    collections::BTreeMap,
+    fs::File,
+    io::Write,
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use anyhow::Context;
+use dialoguer::Confirm;
+use serde::{Deserialize, Serialize};
+
+use super::{
+    aggregator_url::canonicalize_aggregator_url,
+    loader::{decode_json_file, decode_toml_file, LocalFileKind},
+    migration::CONFIG_SCHEMA,
+    prover::ProverConfig,
+    terminal::TerminalOptions,
+};
+use crate::service::{
+    codec::WireFormat,
+    policy::PolicyConfig,
+    privacy::PrivacyConfig,
+    retry::{RetryPolicy, DEFAULT_MAX_ATTEMPTS, DEFAULT_RETRY_BACKOFF},
+};
+
+pub const DEFAULT_AGGREGATOR_URL: &str = "http://localhost:8080";
+pub const DEFAULT_PROFILE_NAME: &str = "default";
+
+const CONFIG_FILE_HEADER: &str = r#"# intmax CLI configuration
+#
+# schema_version = <version of this file format, which is managed by this CLI>
+#
+# default_profile = "<profile name used when --profile is omitted>"
+#
+# [profiles.<name>]
+# aggregator_url = "<URL of the aggregator>"
+#
+# [profiles.<name>.http]
+# timeout = <request timeout in seconds>
+# wire_format = "<json or msgpack: encoding of large payloads, falling back to JSON if unsupported>"
+# max_attempts = <the number of attempts of a request failed with 5xx or a connection error (default: 3, 1 to disable retrying)>
+# retry_backoff = <milliseconds before the first retry, doubled at each retry (default: 500)>
+# retry_jitter = <false to retry exactly after the backoff instead of a random part of it (default: true)>
+#
+# [profiles.<name>.hooks]
+# post_send = "<shell command executed after a transaction is sent (INTMAX_TX_HASH is set)>"
+#
+# [profiles.<name>.limits]
+# max_amount = <upper limit of the amount sent by `tx send`>
+# max_gas_price = <upper limit of acceptable gas price in Gwei for `io` transactions (`io register` takes --max-gas-price)>
+# wallet_size_warning = <wallet size in MiB above which a warning is displayed on load (default: 50)>
+# duplicate_window = <seconds in which `tx send` refuses to repeat the same transfer (default: 300, 0 to disable)>
+# pending_ttl = <seconds after which a transaction not yet approved expires and is no longer signed (default: never)>
+#
+# [profiles.<name>.prover]
+# threads = <the number of threads used for proving>
+# memory_budget = <upper limit of memory used for proving in MiB>
+#
+# [profiles.<name>.faucet]
+# scroll = "<URL of the faucet endpoint of Scroll Alpha>"
+# polygon = "<URL of the faucet endpoint of Polygon zkEVM testnet>"
+#
+# [profiles.<name>.rpc]
+# scroll = "<URL of the RPC endpoint of Scroll Alpha>"
+# polygon = "<URL of the RPC endpoint of Polygon zkEVM testnet>"
+#
+# [profiles.<name>.privacy]
+# enabled = <true to randomize the leaves consumed by transfers and the order of merging (default: false)>
+#
+# [profiles.<name>.policy]
+# url = "<URL of the endpoint which approves each transfer before it is proved>"
+# signer = "<Ethereum address which must sign the approvals>"
+#
+# NOTICE: Comments are not kept when this file is rewritten by `intmax config` commands.
+
+"#;
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Config {
+    #[serde(default)]
+    pub schema_version: u32,
+    pub default_profile: String,
+    pub profiles: BTreeMap<String, Profile>,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Profile {
+    pub aggregator_url: String,
+    #[serde(default)]
+    pub http: HttpConfig,
+    #[serde(default)]
+    pub hooks: HooksConfig,
+    #[serde(default)]
+    pub limits: LimitsConfig,
+    #[serde(default)]
+    pub prover: ProverConfig,
+    #[serde(default)]
+    pub faucet: FaucetConfig,
+    #[serde(default)]
+    pub rpc: RpcConfig,
+    #[serde(default)]
+    pub privacy: PrivacyConfig,
+    #[serde(default)]
+    pub policy: PolicyConfig,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct HttpConfig {
+    /// request timeout in seconds
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub timeout: Option<u64>,
+    /// encoding of the payloads of heavy endpoints
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub wire_format: Option<WireFormat>,
+    /// the number of attempts of a request including the first one
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_attempts: Option<u32>,
+    /// the delay before the first retry in milliseconds
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub retry_backoff: Option<u64>,
+    /// whether the delays before retries are randomized
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub retry_jitter: Option<bool>,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct HooksConfig {
+    /// shell command executed after a transaction is sent
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub post_send: Option<String>,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct LimitsConfig {
+    /// upper limit of the amount sent by `tx send`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_amount: Option<u64>,
+    /// upper limit of acceptable gas price in Gwei of `io` transactions
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_gas_price: Option<f64>,
+    /// wallet size in MiB above which a warning is displayed on load
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub wallet_size_warning: Option<u64>,
+    /// seconds in which `tx send` refuses to repeat the same transfer without `--allow-duplicate`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub duplicate_window: Option<u64>,
+    /// seconds after which a transaction which has not been approved expires
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pending_ttl: Option<u64>,
+}
+
+/// The faucet endpoints which accept `{"address": "0x..."}` and send test ETH.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct FaucetConfig {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub scroll: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub polygon: Option<String>,
+}
+
+/// The RPC endpoints used instead of the built-in ones.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct RpcConfig {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub scroll: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub polygon: Option<String>,
+}
+
+/// The JSON config file written by v2.2.1-alpha or earlier.
+#[derive(Clone, Debug, Deserialize)]
+struct LegacyConfig {
+    aggregator_url: String,
+}
+
+impl Profile {
+    pub fn new(aggregator_url: &str) -> Self {
+        Self {
+            aggregator_url: aggregator_url.to_string(),
+            http: Default::default(),
+            hooks: Default::default(),
+            limits: Default::default(),
+            prover: Default::default(),
+            faucet: Default::default(),
+            rpc: Default::default(),
+            privacy: Default::default(),
+            policy: Default::default(),
+        }
+    }
+
+    pub fn validate(&self, name: &str) -> anyhow::Result<()> {
+        // A plain HTTP URL in the file was written with --allow-insecure or by hand.
+        canonicalize_aggregator_url(&self.aggregator_url, true)
+            .map_err(|err| anyhow::anyhow!("profile `{name}`: {err}"))?;
+
+        if self.http.timeout == Some(0) {
+            anyhow::bail!("profile `{name}`: http.timeout must be a positive integer");
+        }
+
+        if self.http.max_attempts == Some(0) {
+            anyhow::bail!("profile `{name}`: http.max_attempts must be a positive integer");
+        }
+
+        if let Some(max_amount) = self.limits.max_amount {
+            if max_amount == 0 || max_amount >= 1u64 << 56 {
+                anyhow::bail!(
+                    "profile `{name}`: limits.max_amount must be a positive integer less than 2^56"
+                );
+            }
+        }
+
+        if let Some(max_gas_price) = self.limits.max_gas_price {
+            if max_gas_price.is_nan() || max_gas_price <= 0.0 {
+                anyhow::bail!("profile `{name}`: limits.max_gas_price must be a positive number");
+            }
+        }
+
+        if self.limits.wallet_size_warning == Some(0) {
+            anyhow::bail!(
+                "profile `{name}`: limits.wallet_size_warning must be a positive integer"
+            );
+        }
+
+        if self.limits.pending_ttl == Some(0) {
+            anyhow::bail!("profile `{name}`: limits.pending_ttl must be a positive integer");
+        }
+
+        self.prover
+            .validate()
+            .map_err(|err| anyhow::anyhow!("profile `{name}`: {err}"))?;
+
+        for (key, url) in [
+            ("faucet.scroll", &self.faucet.scroll),
+            ("faucet.polygon", &self.faucet.polygon),
+            ("rpc.scroll", &self.rpc.scroll),
+            ("rpc.polygon", &self.rpc.polygon),
+            ("policy.url", &self.policy.url),
+        ] {
+            if let Some(url) = url {
+                if !url.starts_with("http://") && !url.starts_with("https://") {
+                    anyhow::bail!("profile `{name}`: {key} must start with http:// or https://");
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl HttpConfig {
+    /// The unset fields are filled with the defaults.
+    pub fn retry_policy(&self) -> RetryPolicy {
+        RetryPolicy {
+            max_attempts: self.max_attempts.unwrap_or(DEFAULT_MAX_ATTEMPTS),
+            backoff: self
+                .retry_backoff
+                .map(Duration::from_millis)
+                .unwrap_or(DEFAULT_RETRY_BACKOFF),
+            jitter: self.retry_jitter.unwrap_or(true),
+        }
+    }
+}
+
+impl HooksConfig {
+    /// Run `post_send` hook with `INTMAX_TX_HASH` environment variable if it is configured.
+    pub fn run_post_send(&self, tx_hash: String) -> anyhow::Result<()> {
+        if let Some(post_send) = &self.post_send {
+            let status = std::process::Command::new("sh")
+                .arg("-c")
+                .arg(post_send)
+                .env("INTMAX_TX_HASH", tx_hash)
+                .status()
+                .context("fail to run post_send hook")?;
+            if !status.success() {
+                anyhow::bail!("post_send hook exited with {status}");
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self::new(DEFAULT_AGGREGATOR_URL)
+    }
+}
+
+impl Config {
+    pub fn new(aggregator_url: &str) -> Self {
+        let mut profiles = BTreeMap::new();
+        profiles.insert(
+            DEFAULT_PROFILE_NAME.to_string(),
+            Profile::new(aggregator_url),
+        );
+
+        Self {
+            schema_version: CONFIG_SCHEMA.current_version(),
+            default_profile: DEFAULT_PROFILE_NAME.to_string(),
+            profiles,
+        }
+    }
+
+    pub fn validate(&self) -> anyhow::Result<()> {
+        if !self.profiles.contains_key(&self.default_profile) {
+            anyhow::bail!(
+                "default_profile `{}` is not defined in profiles",
+                self.default_profile
+            );
+        }
+
+        for (name, profile) in self.profiles.iter() {
+            profile.validate(name)?;
+        }
+
+        Ok(())
+    }
+
+    pub fn from_toml(encoded_config: &str) -> anyhow::Result<Self> {
+        let mut raw: serde_json::Value = toml::from_str(encoded_config)?;
+        CONFIG_SCHEMA.migrate(&mut raw)?;
+        let config: Self = serde_json::from_value(raw)?;
+        config.validate()?;
+
+        Ok(config)
+    }
+
+    pub fn to_toml(&self) -> anyhow::Result<String> {
+        Ok(CONFIG_FILE_HEADER.to_string() + &toml::to_string(self)?)
+    }
+
+    /// Returns the profile with the given name, or the default profile if omitted.
+    pub fn get_profile(&self, profile_name: Option<&str>) -> anyhow::Result<&Profile> {
+        let profile_name = profile_name.unwrap_or(&self.default_profile);
+
+        self.profiles
+            .get(profile_name)
+            .with_context(|| format!("profile `{profile_name}` was not found"))
+    }
+
+    pub fn get_profile_mut(&mut self, profile_name: Option<&str>) -> anyhow::Result<&mut Profile> {
+        let profile_name = profile_name.unwrap_or(&self.default_profile).to_string();
+
+        self.profiles
+            .get_mut(&profile_name)
+            .with_context(|| format!("profile `{profile_name}` was not found"))
+    }
+
+    /// Read the TOML config file.
+    /// If it does not exist, the legacy JSON config file is migrated if any.
+    pub fn read_from_file(
+        config_file_path: &Path,
+        legacy_config_file_path: &Path,
+    ) -> anyhow::Result<Self> {
+        if let Some(encoded_config) = read_optional_file(config_file_path)? {
+            let config: Self = decode_toml_file(
+                LocalFileKind::Config,
+                config_file_path,
+                &encoded_config,
+                Some(&CONFIG_SCHEMA),
+            )?;
+            config
+                .validate()
+                .with_context(|| format!("invalid config file: {}", config_file_path.display()))?;
+
+            return Ok(config);
+        }
+
+        if let Some(encoded_legacy_config) = read_optional_file(legacy_config_file_path)? {
+            let legacy_config: LegacyConfig = decode_json_file(
+                LocalFileKind::Config,
+                legacy_config_file_path,
+                &encoded_legacy_config,
+                None,
+            )?;
+            let config = Self::new(&legacy_config.aggregator_url);
+            config.write_to_file(config_file_path)?;
+
+            let mut backup_file_path = legacy_config_file_path.to_path_buf();
+            backup_file_path.set_extension("json.bak");
+            std::fs::rename(legacy_config_file_path, &backup_file_path)?;
+            println!(
+                "migrate config file: {} -> {}",
+                legacy_config_file_path.display(),
+                config_file_path.display()
+            );
+
+            return Ok(config);
+        }
+
+        Ok(Self::default())
+    }
+
+    pub fn write_to_file(&self, config_file_path: &Path) -> anyhow::Result<()> {
+        let encoded_config = self.to_toml()?;
+        let mut file = File::create(config_file_path)?;
+        write!(file, "{}", encoded_config)?;
+        file.flush()?;
+
+        Ok(())
+    }
+}
+
+/// Open the config file with `$VISUAL` or `$EDITOR` and validate it on save.
+/// The config file is not changed until the edited file passes validation.
+pub fn edit_config_file(
+    config_file_path: &Path,
+    legacy_config_file_path: &Path,
+    terminal: &TerminalOptions,
+) -> anyhow::Result<()> {
+    terminal.ensure_interactive("`config edit` opens an editor")?;
+
+    let encoded_config = if let Ok(encoded_config) = std::fs::read_to_string(config_file_path) {
+        encoded_config
+    } else {
+        Config::read_from_file(config_file_path, legacy_config_file_path)?.to_toml()?
+    };
+
+    let mut editing_file_path = PathBuf::from(config_file_path);
+    editing_file_path.set_extension("toml.edit");
+    let mut file = File::create(&editing_file_path)?;
+    write!(file, "{}", encoded_config)?;
+    file.flush()?;
+
+    let editor = std::env::var("VISUAL")
+        .or_else(|_| std::env::var("EDITOR"))
+        .unwrap_or_else(|_| "vi".to_string());
+    let mut editor_args = editor.split_whitespace();
+    let editor_program = editor_args.next().context("empty $EDITOR")?;
+    let editor_args = editor_args.collect::<Vec<_>>();
+
+    loop {
+        let status = std::process::Command::new(editor_program)
+            .args(&editor_args)
+            .arg(&editing_file_path)
+            .status()
+            .with_context(|| format!("fail to launch editor: {editor}"))?;
+        if !status.success() {
+            std::fs::remove_file(&editing_file_path)?;
+            anyhow::bail!("editor exited with {status}");
+        }
+
+        let edited_config = std::fs::read_to_string(&editing_file_path)?;
+        match Config::from_toml(&edited_config) {
+            Ok(_) => {
+                std::fs::rename(&editing_file_path, config_file_path)?;
+                println!("Config file was saved");
+
+                return Ok(());
+            }
+            Err(error) => {
+                eprintln!("invalid config: {error}");
+                let response = Confirm::new()
+                    .with_prompt("Do you want to edit it again?")
+                    .interact()?;
+                if !response {
+                    std::fs::remove_file(&editing_file_path)?;
+                    eprintln!("Config file was not changed");
+
+                    return Ok(());
+                }
+            }
+        }
+    }
+}
+
+/// Returns `None` only if the file does not exist. The other errors are not hidden,
+/// so that an unreadable config file is not replaced by the default one.
+fn read_optional_file(file_path: &Path) -> anyhow::Result<Option<String>> {
+    match std::fs::read_to_string(file_path) {
+        Ok(encoded_file) => Ok(Some(encoded_file)),
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => Ok(None),
+        Err(err) => Err(err).with_context(|| format!("fail to read {}", file_path.display())),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::test_utils::temp_dir;
+
+    #[test]
+    fn test_config_toml() {
+        let mut config = Config::new("https://alpha.testnet.intmax.io/");
+        let profile = config.get_profile_mut(None).unwrap();
+        profile.http.timeout = Some(30);
+        profile.limits.max_gas_price = Some(1.5);
+        profile.privacy.enabled = true;
+
+        let encoded_config = config.to_toml().unwrap();
+        let decoded_config = Config::from_toml(&encoded_config).unwrap();
+        assert_eq!(decoded_config, config);
+
+        let invalid_config = r#"
+default_profile = "default"
+
+[profiles.default]
+aggregator_url = "localhost:8080"
+"#;
+        assert!(Config::from_toml(invalid_config).is_err());
+
+        let unknown_field_config = r#"
+default_profile = "default"
+
+[profiles.default]
+aggregator_url = "http://localhost:8080"
+aggregator = "http://localhost:8080"
+"#;
+        assert!(Config::from_toml(unknown_field_config).is_err());
+    }
+
+    #[test]
+    fn test_read_config_file() {
+        let missing_file_path = Path::new("/nonexistent/intmax/config.toml");
+        let config = Config::read_from_file(missing_file_path, missing_file_path).unwrap();
+        assert_eq!(config, Config::default());
+
+        // A directory cannot be read as a file, which must not be taken for a missing config.
+        let temp_dir = temp_dir();
+        assert!(Config::read_from_file(temp_dir.path(), missing_file_path).is_err());
+    }
+}
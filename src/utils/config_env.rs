@@ -0,0 +1,244 @@
+//! `INTMAX_*` environment variables overriding the config file.
+//! The settings are resolved in the following order of precedence:
+//! command-line flags, environment variables, the profile in the config file.
+
+use std::{path::PathBuf, str::FromStr};
+
+use anyhow::Context;
+
+use super::config::Profile;
+use crate::service::codec::WireFormat;
+
+/// The directory including the config file, the wallets and the audit log. [default: ~/.intmax]
+pub const DATA_DIR_ENV: &str = "INTMAX_DATA_DIR";
+pub const PROFILE_ENV: &str = "INTMAX_PROFILE";
+pub const AGGREGATOR_URL_ENV: &str = "INTMAX_AGGREGATOR_URL";
+pub const HTTP_TIMEOUT_ENV: &str = "INTMAX_HTTP_TIMEOUT";
+pub const WIRE_FORMAT_ENV: &str = "INTMAX_WIRE_FORMAT";
+pub const POST_SEND_HOOK_ENV: &str = "INTMAX_POST_SEND_HOOK";
+pub const MAX_AMOUNT_ENV: &str = "INTMAX_MAX_AMOUNT";
+pub const MAX_GAS_PRICE_ENV: &str = "INTMAX_MAX_GAS_PRICE";
+pub const PROVE_THREADS_ENV: &str = "INTMAX_PROVE_THREADS";
+pub const PROVE_MEMORY_BUDGET_ENV: &str = "INTMAX_PROVE_MEMORY_BUDGET";
+pub const FAUCET_SCROLL_ENV: &str = "INTMAX_FAUCET_SCROLL";
+pub const FAUCET_POLYGON_ENV: &str = "INTMAX_FAUCET_POLYGON";
+pub const RPC_SCROLL_ENV: &str = "INTMAX_RPC_SCROLL";
+pub const RPC_POLYGON_ENV: &str = "INTMAX_RPC_POLYGON";
+pub const PRIVACY_ENV: &str = "INTMAX_PRIVACY";
+pub const NON_INTERACTIVE_ENV: &str = "INTMAX_NON_INTERACTIVE";
+/// Set by most CI services. It enables the non-interactive mode unless `INTMAX_NON_INTERACTIVE` is given.
+pub const CI_ENV: &str = "CI";
+/// `quiet` (the same as `--quiet`), `info` or `debug` (the same as `--stats`)
+pub const LOG_LEVEL_ENV: &str = "INTMAX_LOG_LEVEL";
+
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub enum LogLevel {
+    Quiet,
+    #[default]
+    Info,
+    Debug,
+}
+
+impl FromStr for LogLevel {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "quiet" => Ok(Self::Quiet),
+            "info" => Ok(Self::Info),
+            "debug" => Ok(Self::Debug),
+            _ => anyhow::bail!("unknown log level: {s}"),
+        }
+    }
+}
+
+/// The settings given by `INTMAX_*` environment variables. `None` if not set.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct EnvConfig {
+    pub data_dir: Option<PathBuf>,
+    pub profile: Option<String>,
+    pub aggregator_url: Option<String>,
+    pub http_timeout: Option<u64>,
+    pub wire_format: Option<WireFormat>,
+    pub post_send_hook: Option<String>,
+    pub max_amount: Option<u64>,
+    pub max_gas_price: Option<f64>,
+    pub prove_threads: Option<usize>,
+    pub prove_memory_budget: Option<u64>,
+    pub faucet_scroll: Option<String>,
+    pub faucet_polygon: Option<String>,
+    pub rpc_scroll: Option<String>,
+    pub rpc_polygon: Option<String>,
+    pub privacy: Option<bool>,
+    pub non_interactive: Option<bool>,
+    pub log_level: Option<LogLevel>,
+}
+
+fn parse_var<T: FromStr>(name: &str, value: Option<String>) -> anyhow::Result<Option<T>>
+where
+    T::Err: std::fmt::Display,
+{
+    value
+        .map(|value| {
+            value
+                .trim()
+                .parse()
+                .map_err(|err| anyhow::anyhow!("invalid {name}: {err}"))
+        })
+        .transpose()
+}
+
+fn parse_bool_var(name: &str, value: Option<String>) -> anyhow::Result<Option<bool>> {
+    value
+        .map(|value| match value.trim().to_lowercase().as_str() {
+            "1" | "true" | "yes" => Ok(true),
+            "" | "0" | "false" | "no" => Ok(false),
+            _ => anyhow::bail!("invalid {name}: expected true or false"),
+        })
+        .transpose()
+}
+
+impl EnvConfig {
+    pub fn from_env() -> anyhow::Result<Self> {
+        Self::from_vars(|name| std::env::var(name).ok())
+    }
+
+    /// Read the settings with `get_var`, which returns the value of the environment variable if set.
+    pub fn from_vars(get_var: impl Fn(&str) -> Option<String>) -> anyhow::Result<Self> {
+        // Empty values are regarded as unset, except for flags.
+        let get_value = |name: &str| get_var(name).filter(|value| !value.is_empty());
+
+        Ok(Self {
+            data_dir: get_value(DATA_DIR_ENV).map(PathBuf::from),
+            profile: get_value(PROFILE_ENV),
+            aggregator_url: get_value(AGGREGATOR_URL_ENV),
+            http_timeout: parse_var(HTTP_TIMEOUT_ENV, get_value(HTTP_TIMEOUT_ENV))?,
+            wire_format: parse_var(WIRE_FORMAT_ENV, get_value(WIRE_FORMAT_ENV))?,
+            post_send_hook: get_value(POST_SEND_HOOK_ENV),
+            max_amount: parse_var(MAX_AMOUNT_ENV, get_value(MAX_AMOUNT_ENV))?,
+            max_gas_price: parse_var(MAX_GAS_PRICE_ENV, get_value(MAX_GAS_PRICE_ENV))?,
+            prove_threads: parse_var(PROVE_THREADS_ENV, get_value(PROVE_THREADS_ENV))?,
+            prove_memory_budget: parse_var(
+                PROVE_MEMORY_BUDGET_ENV,
+                get_value(PROVE_MEMORY_BUDGET_ENV),
+            )?,
+            faucet_scroll: get_value(FAUCET_SCROLL_ENV),
+            faucet_polygon: get_value(FAUCET_POLYGON_ENV),
+            rpc_scroll: get_value(RPC_SCROLL_ENV),
+            rpc_polygon: get_value(RPC_POLYGON_ENV),
+            privacy: parse_bool_var(PRIVACY_ENV, get_var(PRIVACY_ENV))?,
+            non_interactive: parse_bool_var(NON_INTERACTIVE_ENV, get_var(NON_INTERACTIVE_ENV))?
+                .or_else(|| {
+                    get_value(CI_ENV)
+                        .filter(|ci| !matches!(ci.to_lowercase().as_str(), "0" | "false" | "no"))
+                        .map(|_| true)
+                }),
+            log_level: parse_var(LOG_LEVEL_ENV, get_value(LOG_LEVEL_ENV))?,
+        })
+    }
+
+    /// Returns `INTMAX_DATA_DIR`, or `~/.intmax` if it is not set.
+    pub fn data_dir(&self) -> anyhow::Result<PathBuf> {
+        if let Some(data_dir) = &self.data_dir {
+            return Ok(data_dir.clone());
+        }
+
+        let home_dir = dirs::home_dir().context("fail to get home directory")?;
+
+        Ok(home_dir.join(".intmax"))
+    }
+
+    /// Overwrite the fields of the profile given by the environment variables.
+    /// The config file itself is not changed.
+    pub fn apply_to_profile(&self, profile: &mut Profile) {
+        if let Some(aggregator_url) = &self.aggregator_url {
+            profile.aggregator_url = aggregator_url.clone();
+        }
+        if let Some(http_timeout) = self.http_timeout {
+            profile.http.timeout = Some(http_timeout);
+        }
+        if let Some(wire_format) = self.wire_format {
+            profile.http.wire_format = Some(wire_format);
+        }
+        if let Some(post_send_hook) = &self.post_send_hook {
+            profile.hooks.post_send = Some(post_send_hook.clone());
+        }
+        if let Some(max_amount) = self.max_amount {
+            profile.limits.max_amount = Some(max_amount);
+        }
+        if let Some(max_gas_price) = self.max_gas_price {
+            profile.limits.max_gas_price = Some(max_gas_price);
+        }
+        if let Some(prove_threads) = self.prove_threads {
+            profile.prover.threads = Some(prove_threads);
+        }
+        if let Some(prove_memory_budget) = self.prove_memory_budget {
+            profile.prover.memory_budget = Some(prove_memory_budget);
+        }
+        if let Some(faucet_scroll) = &self.faucet_scroll {
+            profile.faucet.scroll = Some(faucet_scroll.clone());
+        }
+        if let Some(faucet_polygon) = &self.faucet_polygon {
+            profile.faucet.polygon = Some(faucet_polygon.clone());
+        }
+        if let Some(rpc_scroll) = &self.rpc_scroll {
+            profile.rpc.scroll = Some(rpc_scroll.clone());
+        }
+        if let Some(rpc_polygon) = &self.rpc_polygon {
+            profile.rpc.polygon = Some(rpc_polygon.clone());
+        }
+        if let Some(privacy) = self.privacy {
+            profile.privacy.enabled = privacy;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::*;
+
+    #[test]
+    fn test_env_config() {
+        let vars = HashMap::from([
+            (AGGREGATOR_URL_ENV, "https://alpha.testnet.intmax.io"),
+            (HTTP_TIMEOUT_ENV, "30"),
+            (WIRE_FORMAT_ENV, "msgpack"),
+            (PROVE_THREADS_ENV, ""),
+            (NON_INTERACTIVE_ENV, "1"),
+            (LOG_LEVEL_ENV, "QUIET"),
+        ]);
+        let env_config =
+            EnvConfig::from_vars(|name| vars.get(name).map(|value| value.to_string())).unwrap();
+        assert_eq!(env_config.http_timeout, Some(30));
+        assert_eq!(env_config.wire_format, Some(WireFormat::MsgPack));
+        assert_eq!(env_config.prove_threads, None);
+        assert_eq!(env_config.non_interactive, Some(true));
+        assert_eq!(env_config.log_level, Some(LogLevel::Quiet));
+
+        let ci_vars = HashMap::from([(CI_ENV, "true")]);
+        let env_config =
+            EnvConfig::from_vars(|name| ci_vars.get(name).map(|value| value.to_string())).unwrap();
+        assert_eq!(env_config.non_interactive, Some(true));
+        let ci_vars = HashMap::from([(CI_ENV, "true"), (NON_INTERACTIVE_ENV, "0")]);
+        let env_config =
+            EnvConfig::from_vars(|name| ci_vars.get(name).map(|value| value.to_string())).unwrap();
+        assert_eq!(env_config.non_interactive, Some(false));
+
+        // The environment variables take precedence over the config file.
+        let mut profile = Profile::new("http://localhost:8080");
+        profile.http.timeout = Some(10);
+        profile.prover.threads = Some(4);
+        env_config.apply_to_profile(&mut profile);
+        assert_eq!(profile.aggregator_url, "https://alpha.testnet.intmax.io");
+        assert_eq!(profile.http.timeout, Some(30));
+        assert_eq!(profile.prover.threads, Some(4));
+
+        let invalid_vars = HashMap::from([(HTTP_TIMEOUT_ENV, "30s")]);
+        let error =
+            EnvConfig::from_vars(|name| invalid_vars.get(name).map(|value| value.to_string()))
+                .unwrap_err();
+        assert!(error.to_string().starts_with("invalid INTMAX_HTTP_TIMEOUT"));
+    }
+}
@@ -0,0 +1,129 @@
+//! Consistent rendering of hashes, addresses and token IDs in debug output,
+//! and the files written by `--debug-dump` for bug reports.
+
+use std::{
+    fs::{create_dir_all, File},
+    io::Write,
+    path::{Path, PathBuf},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use anyhow::Context;
+use intmax_rollup_interface::intmax_zkp_core::{
+    plonky2::{
+        field::types::PrimeField64,
+        plonk::config::{GenericConfig, PoseidonGoldilocksConfig},
+    },
+    rollup::gadgets::deposit_block::VariableIndex,
+    sparse_merkle_tree::goldilocks_poseidon::WrappedHashOut,
+    zkdsa::account::Address,
+};
+use serde::Serialize;
+
+use super::{short_address::AddressRenderer, terminal::TerminalOptions};
+
+const D: usize = 2;
+type C = PoseidonGoldilocksConfig;
+type F = <C as GenericConfig<D>>::F;
+
+pub trait DebugFormat {
+    /// `0x`-prefixed hex string without truncation
+    fn to_full_hex(&self) -> String;
+
+    /// canonical values of the underlying field elements
+    fn to_field_elements(&self) -> Vec<u64>;
+
+    /// e.g., `0x714b...7e6d`
+    fn to_short_hex(&self) -> String {
+        let terminal = TerminalOptions {
+            ascii: true,
+            ..Default::default()
+        };
+
+        AddressRenderer::new(&terminal, vec![]).render(&self.to_full_hex())
+    }
+
+    /// e.g., `0x714b...7e6d (hex: 0x714bdc6f38947e6d, elements: [8163861119787892333])`
+    fn debug_format(&self) -> String {
+        format!(
+            "{} (hex: {}, elements: {:?})",
+            self.to_short_hex(),
+            self.to_full_hex(),
+            self.to_field_elements()
+        )
+    }
+}
+
+impl DebugFormat for WrappedHashOut<F> {
+    fn to_full_hex(&self) -> String {
+        self.to_string()
+    }
+
+    fn to_field_elements(&self) -> Vec<u64> {
+        self.elements
+            .iter()
+            .map(|element| element.to_canonical_u64())
+            .collect()
+    }
+}
+
+impl DebugFormat for Address<F> {
+    fn to_full_hex(&self) -> String {
+        self.to_string()
+    }
+
+    fn to_field_elements(&self) -> Vec<u64> {
+        vec![self.0.to_canonical_u64()]
+    }
+}
+
+impl DebugFormat for VariableIndex<F> {
+    fn to_full_hex(&self) -> String {
+        self.to_string()
+    }
+
+    fn to_field_elements(&self) -> Vec<u64> {
+        WrappedHashOut::from(self.to_hash_out()).to_field_elements()
+    }
+}
+
+/// Write `value` as JSON into `dir`, and returns the path of the file.
+/// The file name is prefixed with the time in milliseconds so that the dumps are kept in order.
+pub fn write_debug_dump(dir: &Path, name: &str, value: &impl Serialize) -> anyhow::Result<PathBuf> {
+    create_dir_all(dir).with_context(|| format!("fail to create {}", dir.display()))?;
+
+    let timestamp = SystemTime::now().duration_since(UNIX_EPOCH)?.as_millis();
+    let file_path = dir.join(format!("{timestamp}-{name}.json"));
+    let mut file = File::create(&file_path)
+        .with_context(|| format!("fail to create {}", file_path.display()))?;
+    write!(file, "{}", serde_json::to_string_pretty(value)?)?;
+    file.flush()?;
+
+    Ok(file_path)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use super::*;
+
+    #[test]
+    fn test_debug_format() {
+        let address = Address::<F>::from_str("0x714bdc6f38947e6d").unwrap();
+        assert_eq!(address.to_short_hex(), "0x714b...7e6d");
+        assert_eq!(address.to_field_elements(), vec![0x714bdc6f38947e6d]);
+        assert_eq!(
+            address.debug_format(),
+            "0x714b...7e6d (hex: 0x714bdc6f38947e6d, elements: [8163861119787892333])"
+        );
+
+        let variable_index: VariableIndex<F> = 1u8.into();
+        assert_eq!(variable_index.to_short_hex(), variable_index.to_full_hex());
+        assert_eq!(variable_index.to_field_elements().len(), 4);
+
+        let hash = WrappedHashOut::<F>::rand();
+        assert_eq!(hash.to_field_elements().len(), 4);
+        assert!(hash.to_full_hex().starts_with(&hash.to_short_hex()[..6]));
+    }
+}
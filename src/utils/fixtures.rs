@@ -0,0 +1,235 @@
+use std::path::{Path, PathBuf};
+
+use intmax_rollup_interface::intmax_zkp_core::{
+    plonky2::{
+        field::{goldilocks_field::GoldilocksField, types::Field},
+        hash::hash_types::HashOut,
+    },
+    sparse_merkle_tree::goldilocks_poseidon::{NodeDataMemory, RootDataMemory},
+    transaction::asset::TokenKind,
+    zkdsa::account::Account,
+};
+use serde_json::json;
+
+use super::{
+    config::Config,
+    key_management::{
+        memory::{SerializableUserState, UserState, WalletOnMemory},
+        types::{MergeKey, Wallet},
+    },
+    nickname::NicknameTable,
+};
+
+type F = GoldilocksField;
+
+const FIXTURE_PRIVATE_KEY: u64 = 1;
+const FIXTURE_AMOUNT: u64 = 100;
+const FIXTURE_NICKNAME: &str = "alice";
+const FIXTURE_AGGREGATOR_URL: &str = "https://alpha.testnet.intmax.io";
+
+/// The wallet files in each historical schema. Keys of the JSON objects are written literally,
+/// so that a renamed field of `SerializableUserState` does not change these fixtures.
+pub const WALLET_FIXTURES: [&str; 4] = [
+    // before `default_account`, `last_seen_block_number`, `rest_received_assets` and `sent_transactions`
+    "wallet.minimal",
+    // v2.2.1-alpha: each account has the nodes of its asset tree
+    "wallet.nodes-per-account",
+    // v1: the nodes are shared by all accounts
+    "wallet.shared-nodes",
+    // v2: each account is stored in its own file under `accounts`
+    "wallet.accounts",
+];
+
+pub const NICKNAME_FIXTURE: &str = "nickname";
+/// the nickname file before `schema_version` is introduced
+pub const LEGACY_NICKNAME_FIXTURE: &str = "nickname.legacy";
+pub const LEGACY_CONFIG_FIXTURE: &str = "config";
+pub const CONFIG_FIXTURE: &str = "config.toml";
+
+/// Returns the wallet containing one account which holds one asset.
+pub fn make_fixture_wallet(wallet_file_path: PathBuf) -> anyhow::Result<WalletOnMemory> {
+    let private_key = HashOut::from_partial(&[F::from_canonical_u64(FIXTURE_PRIVATE_KEY)]);
+    let account = Account::new(private_key);
+    let mut wallet = WalletOnMemory::new(wallet_file_path, String::new());
+    wallet.add_account(account)?;
+    wallet.set_default_account(Some(account.address));
+
+    let kind = TokenKind {
+        contract_address: account.address,
+        variable_index: 0u8.into(),
+    };
+    let merge_key = MergeKey(HashOut::from_partial(&[F::ONE]).into());
+    let user_state = wallet.data.get_mut(&account.address).unwrap();
+    user_state.assets.add(kind, FIXTURE_AMOUNT, merge_key);
+    user_state
+        .asset_tree
+        .set(
+            merge_key.0,
+            kind.contract_address.to_hash_out().into(),
+            kind.variable_index.to_hash_out().into(),
+            HashOut::from_partial(&[F::from_canonical_u64(FIXTURE_AMOUNT)]).into(),
+        )
+        .unwrap();
+
+    Ok(wallet)
+}
+
+fn encode_user_state(
+    user_state: &UserState<NodeDataMemory, RootDataMemory>,
+    with_optional_fields: bool,
+) -> anyhow::Result<serde_json::Value> {
+    let raw = SerializableUserState::from(user_state.clone());
+    let mut encoded_user_state = json!({
+        "account": raw.account,
+        "asset_tree_nodes": raw.asset_tree_nodes,
+        "asset_tree_root": raw.asset_tree_root,
+        "assets": raw.assets,
+    });
+    if with_optional_fields {
+        let encoded_user_state = encoded_user_state.as_object_mut().unwrap();
+        encoded_user_state.insert(
+            "last_seen_block_number".to_string(),
+            json!(raw.last_seen_block_number),
+        );
+        encoded_user_state.insert(
+            "rest_received_assets".to_string(),
+            serde_json::to_value(raw.rest_received_assets)?,
+        );
+        encoded_user_state.insert(
+            "sent_transactions".to_string(),
+            serde_json::to_value(raw.sent_transactions)?,
+        );
+    }
+
+    Ok(encoded_user_state)
+}
+
+/// Write the wallet, nickname and config files in each known schema into `output_dir`.
+pub fn generate_fixtures(output_dir: &Path) -> anyhow::Result<Vec<PathBuf>> {
+    std::fs::create_dir_all(output_dir)?;
+    let mut written_files = vec![];
+
+    let wallet = make_fixture_wallet(output_dir.join(WALLET_FIXTURES[3]))?;
+    let user_states = wallet.data.values().collect::<Vec<_>>();
+
+    let minimal_wallet = json!({
+        "data": user_states
+            .iter()
+            .map(|user_state| encode_user_state(user_state, false))
+            .collect::<anyhow::Result<Vec<_>>>()?,
+    });
+    let nodes_per_account_wallet = json!({
+        "data": user_states
+            .iter()
+            .map(|user_state| encode_user_state(user_state, true))
+            .collect::<anyhow::Result<Vec<_>>>()?,
+        "default_account": wallet.default_account,
+    });
+    let mut shared_nodes_wallet = serde_json::to_value(wallet.to_serializable())?;
+    let shared_nodes_wallet_object = shared_nodes_wallet.as_object_mut().unwrap();
+    shared_nodes_wallet_object.insert("schema_version".to_string(), json!(1));
+    shared_nodes_wallet_object.remove("accounts");
+    for (file_name, encoded_wallet) in WALLET_FIXTURES.iter().zip([
+        minimal_wallet,
+        nodes_per_account_wallet,
+        shared_nodes_wallet,
+    ]) {
+        let file_path = output_dir.join(file_name);
+        std::fs::write(&file_path, encoded_wallet.to_string())?;
+        written_files.push(file_path);
+    }
+
+    wallet.backup()?;
+    written_files.append(&mut wallet.storage_file_paths());
+
+    let mut nickname_table = NicknameTable::default();
+    nickname_table.insert(
+        wallet.default_account.unwrap(),
+        FIXTURE_NICKNAME.to_string(),
+    )?;
+    let file_path = output_dir.join(NICKNAME_FIXTURE);
+    std::fs::write(&file_path, serde_json::to_string(&nickname_table)?)?;
+    written_files.push(file_path);
+
+    let file_path = output_dir.join(LEGACY_NICKNAME_FIXTURE);
+    let legacy_nickname_table = json!([[wallet.default_account.unwrap(), FIXTURE_NICKNAME]]);
+    std::fs::write(&file_path, legacy_nickname_table.to_string())?;
+    written_files.push(file_path);
+
+    let file_path = output_dir.join(LEGACY_CONFIG_FIXTURE);
+    let legacy_config = json!({ "aggregator_url": FIXTURE_AGGREGATOR_URL });
+    std::fs::write(&file_path, legacy_config.to_string())?;
+    written_files.push(file_path);
+
+    let file_path = output_dir.join(CONFIG_FIXTURE);
+    Config::new(FIXTURE_AGGREGATOR_URL).write_to_file(&file_path)?;
+    written_files.push(file_path);
+
+    Ok(written_files)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::test_utils::temp_dir;
+
+    #[test]
+    fn test_load_fixtures() {
+        let temp_dir = temp_dir();
+        let output_dir = temp_dir.path().join("fixtures");
+        generate_fixtures(&output_dir).unwrap();
+
+        let expected_wallet = make_fixture_wallet(PathBuf::new()).unwrap();
+        let expected_address = expected_wallet.default_account.unwrap();
+        let expected_user_state = &expected_wallet.data[&expected_address];
+        for (i, file_name) in WALLET_FIXTURES.iter().enumerate() {
+            let wallet = WalletOnMemory::read_from_file(output_dir.join(file_name))
+                .unwrap_or_else(|err| panic!("fail to load {file_name}: {err}"));
+            let user_state = &wallet.data[&expected_address];
+            assert_eq!(
+                user_state.asset_tree.get_root().unwrap(),
+                expected_user_state.asset_tree.get_root().unwrap(),
+                "{file_name}"
+            );
+            assert_eq!(
+                user_state.assets.calc_total_amount(),
+                expected_user_state.assets.calc_total_amount(),
+                "{file_name}"
+            );
+            if i != 0 {
+                assert_eq!(
+                    wallet.default_account,
+                    Some(expected_address),
+                    "{file_name}"
+                );
+            }
+        }
+
+        for file_name in [NICKNAME_FIXTURE, LEGACY_NICKNAME_FIXTURE] {
+            let encoded_nickname_table =
+                std::fs::read_to_string(output_dir.join(file_name)).unwrap();
+            let nickname_table: NicknameTable = serde_json::from_str(&encoded_nickname_table)
+                .unwrap_or_else(|err| panic!("fail to load {file_name}: {err}"));
+            assert_eq!(
+                nickname_table.nickname_to_address.get(FIXTURE_NICKNAME),
+                Some(&expected_address),
+                "{file_name}"
+            );
+        }
+
+        // The legacy config is migrated into a new file.
+        let config = Config::read_from_file(
+            &output_dir.join("migrated.toml"),
+            &output_dir.join(LEGACY_CONFIG_FIXTURE),
+        )
+        .unwrap();
+        assert_eq!(config, Config::new(FIXTURE_AGGREGATOR_URL));
+
+        let config = Config::read_from_file(
+            &output_dir.join(CONFIG_FIXTURE),
+            &output_dir.join(LEGACY_CONFIG_FIXTURE),
+        )
+        .unwrap();
+        assert_eq!(config, Config::new(FIXTURE_AGGREGATOR_URL));
+    }
+}
@@ -0,0 +1,222 @@
+use std::str::FromStr;
+
+use anyhow::Context;
+use intmax_interoperability_plugin::ethers::{prelude::k256::ecdsa::SigningKey, types::H160};
+use intmax_rollup_interface::intmax_zkp_core::{
+    plonky2::plonk::config::{GenericConfig, PoseidonGoldilocksConfig},
+    sparse_merkle_tree::goldilocks_poseidon::WrappedHashOut,
+    zkdsa::account::Address,
+};
+
+use zeroize::Zeroizing;
+
+use super::bech32::{decode_bech32, is_bech32_address, INTMAX_ADDRESS_HRP};
+
+const D: usize = 2;
+type C = PoseidonGoldilocksConfig;
+type F = <C as GenericConfig<D>>::F;
+
+/// The length of an intmax address in bytes.
+pub const INTMAX_ADDRESS_LEN: usize = 8;
+
+/// The length of an Ethereum address in bytes.
+pub const ETHEREUM_ADDRESS_LEN: usize = 20;
+
+/// The length of a hash, e.g., a transaction hash or a private key, in bytes.
+pub const HASH_LEN: usize = 32;
+
+/// The length of an Ethereum secret key in bytes.
+pub const SECRET_KEY_LEN: usize = 32;
+
+/// Returns the hex digits without the 0x-prefix, if any.
+pub fn strip_0x_prefix(value: &str) -> &str {
+    value
+        .strip_prefix("0x")
+        .or_else(|| value.strip_prefix("0X"))
+        .unwrap_or(value)
+}
+
+/// Decode a hex string of exactly `expected_len` bytes. The 0x-prefix is optional.
+/// `name` is the parameter shown in error messages.
+/// The value itself is never included in them, since it may be a secret key.
+pub fn decode_hex(name: &str, value: &str, expected_len: usize) -> anyhow::Result<Vec<u8>> {
+    let digits = strip_0x_prefix(value);
+    if digits.is_empty() {
+        anyhow::bail!("{name} is empty");
+    }
+
+    let bytes = hex::decode(digits).map_err(|err| {
+        anyhow::anyhow!(
+            "{name} must be a {expected_len}-byte hex string (the 0x-prefix is optional): {err}"
+        )
+    })?;
+    if bytes.len() != expected_len {
+        anyhow::bail!(
+            "{name} must be a {expected_len}-byte hex string (the 0x-prefix is optional), but {} bytes were given",
+            bytes.len()
+        );
+    }
+
+    Ok(bytes)
+}
+
+/// Returns the lowercase hex string with the 0x-prefix,
+/// which is the form `FromStr` of intmax types expects.
+pub fn normalize_hex(name: &str, value: &str, expected_len: usize) -> anyhow::Result<String> {
+    let bytes = decode_hex(name, value, expected_len)?;
+
+    Ok(format!("0x{}", hex::encode(bytes)))
+}
+
+/// Returns `true` if the value is given as an address, i.e., a hex string with the 0x-prefix
+/// or a bech32 string with the `intmax1` prefix, rather than a nickname.
+pub fn is_address_literal(value: &str) -> bool {
+    value.starts_with("0x") || is_bech32_address(value)
+}
+
+/// Parse an intmax address given as a hex string or a bech32 string.
+pub fn parse_intmax_address(name: &str, value: &str) -> anyhow::Result<Address<F>> {
+    let encoded_address = if is_bech32_address(value) {
+        let bytes = decode_bech32(INTMAX_ADDRESS_HRP, value)
+            .map_err(|err| anyhow::anyhow!("invalid {name}: {err}"))?;
+        if bytes.len() != INTMAX_ADDRESS_LEN {
+            anyhow::bail!("{name} must be {INTMAX_ADDRESS_LEN} bytes");
+        }
+
+        format!("0x{}", hex::encode(bytes))
+    } else {
+        normalize_hex(name, value, INTMAX_ADDRESS_LEN)?
+    };
+
+    Address::from_str(&encoded_address).map_err(|_| anyhow::anyhow!("invalid {name}"))
+}
+
+pub fn parse_hash_out(name: &str, value: &str) -> anyhow::Result<WrappedHashOut<F>> {
+    let encoded_hash = normalize_hex(name, value, HASH_LEN)?;
+
+    WrappedHashOut::from_str(&encoded_hash).map_err(|_| anyhow::anyhow!("invalid {name}"))
+}
+
+pub fn parse_h160(name: &str, value: &str) -> anyhow::Result<H160> {
+    let bytes = decode_hex(name, value, ETHEREUM_ADDRESS_LEN)?;
+
+    Ok(H160::from_slice(&bytes))
+}
+
+pub fn parse_signing_key(name: &str, value: &str) -> anyhow::Result<SigningKey> {
+    let bytes = Zeroizing::new(decode_hex(name, value, SECRET_KEY_LEN)?);
+
+    SigningKey::from_bytes(&bytes).map_err(|_| anyhow::anyhow!("{name} is not a valid secret key"))
+}
+
+/// The environment variable holding the Ethereum secret key used by `io` and `bridge` commands.
+pub const PRIVATE_KEY_ENV: &str = "PRIVATE_KEY";
+
+/// Read the Ethereum secret key from `PRIVATE_KEY`.
+/// The returned copy is zeroized when it is dropped, so pass it by reference instead of cloning it.
+pub fn read_private_key_env() -> anyhow::Result<Zeroizing<String>> {
+    std::env::var(PRIVATE_KEY_ENV)
+        .map(Zeroizing::new)
+        .map_err(|_| anyhow::anyhow!("{PRIVATE_KEY_ENV} must be set in .env file"))
+}
+
+/// Parse an Ethereum secret key given by the `PRIVATE_KEY` environment variable.
+pub fn parse_private_key_env(secret_key: &str) -> anyhow::Result<SigningKey> {
+    parse_signing_key("PRIVATE_KEY", secret_key).context("set a valid PRIVATE_KEY in .env file")
+}
+
+#[cfg(test)]
+mod tests {
+    use intmax_interoperability_plugin::ethers::utils::secret_key_to_address;
+
+    use super::*;
+
+    #[test]
+    fn test_decode_hex() {
+        assert_eq!(strip_0x_prefix("0x0102"), "0102");
+        assert_eq!(strip_0x_prefix("0X0102"), "0102");
+        assert_eq!(strip_0x_prefix("0102"), "0102");
+
+        // The 0x-prefix is optional.
+        assert_eq!(decode_hex("value", "0x0102", 2).unwrap(), vec![1, 2]);
+        assert_eq!(decode_hex("value", "0102", 2).unwrap(), vec![1, 2]);
+        assert_eq!(decode_hex("value", "0xABcd", 2).unwrap(), vec![0xab, 0xcd]);
+
+        // The length is enforced.
+        let error = decode_hex("recipient", "0x010203", 2).unwrap_err();
+        assert!(error
+            .to_string()
+            .starts_with("recipient must be a 2-byte hex string"));
+        assert!(error.to_string().ends_with("but 3 bytes were given"));
+        assert!(decode_hex("value", "0x01", 2).is_err());
+
+        // Empty, odd-length and non-hex values are refused.
+        assert_eq!(
+            decode_hex("value", "0x", 2).unwrap_err().to_string(),
+            "value is empty"
+        );
+        assert!(decode_hex("value", "", 2).is_err());
+        assert!(decode_hex("value", "0x012", 2).is_err());
+        assert!(decode_hex("value", "0x01zz", 2).is_err());
+        assert!(decode_hex("value", "0x0x0102", 2).is_err());
+
+        // The value is not included in error messages.
+        let error = decode_hex("PRIVATE_KEY", "0xdeadbeef", 32).unwrap_err();
+        assert!(!error.to_string().contains("deadbeef"));
+        let error = decode_hex("PRIVATE_KEY", "deadbeefzz", 32).unwrap_err();
+        assert!(!error.to_string().contains("deadbeef"));
+
+        assert_eq!(
+            normalize_hex("value", "ABCD", 2).unwrap(),
+            "0xabcd".to_string()
+        );
+    }
+
+    #[test]
+    fn test_parse_typed_hex() {
+        let address = parse_intmax_address("recipient", "0x714bdc6f38947e6d").unwrap();
+        assert_eq!(
+            parse_intmax_address("recipient", "714bdc6f38947e6d").unwrap(),
+            address
+        );
+        assert_eq!(address, Address::from_str("0x714bdc6f38947e6d").unwrap());
+        assert!(parse_intmax_address("recipient", "0x714bdc6f38947e").is_err());
+        assert_eq!(
+            parse_intmax_address("recipient", "intmax1w99acmecj3lx6sfqt4w").unwrap(),
+            address
+        );
+        assert_eq!(
+            parse_intmax_address("recipient", "INTMAX1W99ACMECJ3LX6SFQT4W").unwrap(),
+            address
+        );
+        assert!(parse_intmax_address("recipient", "intmax1w99acmecj3lx6sfqt4q").is_err());
+        assert!(is_address_literal("intmax1w99acmecj3lx6sfqt4w"));
+        assert!(!is_address_literal("alice"));
+
+        let h160 = parse_h160("--receiver", "0x0000000000000000000000000000000000000001").unwrap();
+        assert_eq!(h160, H160::from_low_u64_be(1));
+        assert_eq!(
+            parse_h160("--receiver", "0000000000000000000000000000000000000001").unwrap(),
+            h160
+        );
+        assert!(parse_h160("--receiver", "0x714bdc6f38947e6d").is_err());
+
+        let hash = WrappedHashOut::<F>::rand();
+        let encoded_hash = hash.to_string();
+        assert_eq!(parse_hash_out("tx hash", &encoded_hash).unwrap(), hash);
+        assert_eq!(
+            parse_hash_out("tx hash", strip_0x_prefix(&encoded_hash)).unwrap(),
+            hash
+        );
+
+        let secret_key = "0101010101010101010101010101010101010101010101010101010101010101";
+        let signing_key = parse_signing_key("PRIVATE_KEY", secret_key).unwrap();
+        assert_eq!(
+            secret_key_to_address(
+                &parse_signing_key("PRIVATE_KEY", &format!("0x{secret_key}")).unwrap()
+            ),
+            secret_key_to_address(&signing_key)
+        );
+        assert!(parse_signing_key("PRIVATE_KEY", &"00".repeat(32)).is_err());
+    }
+}
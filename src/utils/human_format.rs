@@ -0,0 +1,175 @@
+//! Human-readable durations, sizes and counts for the stats and progress outputs.
+//! Numbers are grouped following the locale given by `LC_ALL`, `LC_NUMERIC` or `LANG`.
+//! There is no message catalog yet, so only the separators are localized, not the units.
+
+use std::time::Duration;
+
+/// The separators of numbers in a locale.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct NumberFormat {
+    pub grouping: char,
+    pub decimal: char,
+}
+
+impl Default for NumberFormat {
+    fn default() -> Self {
+        Self {
+            grouping: ',',
+            decimal: '.',
+        }
+    }
+}
+
+impl NumberFormat {
+    /// The format of a POSIX locale name, e.g., `de_DE.UTF-8`. Unknown languages use the default.
+    pub fn from_locale(locale: &str) -> Self {
+        let language = locale
+            .split(|c| c == '_' || c == '-' || c == '.' || c == '@')
+            .next()
+            .unwrap_or_default()
+            .to_lowercase();
+        match language.as_str() {
+            "de" | "es" | "it" | "nl" | "pt" | "id" | "tr" | "da" => Self {
+                grouping: '.',
+                decimal: ',',
+            },
+            "fr" | "ru" | "pl" | "cs" | "sv" | "fi" | "nb" | "uk" => Self {
+                grouping: ' ',
+                decimal: ',',
+            },
+            _ => Self::default(),
+        }
+    }
+
+    /// The format of the locale of the environment.
+    pub fn from_env() -> Self {
+        ["LC_ALL", "LC_NUMERIC", "LANG"]
+            .iter()
+            .filter_map(|name| std::env::var(name).ok())
+            .find(|locale| !locale.is_empty())
+            .map(|locale| Self::from_locale(&locale))
+            .unwrap_or_default()
+    }
+
+    /// e.g., `1,234,567`
+    pub fn integer(&self, value: u64) -> String {
+        let digits = value.to_string();
+        let mut grouped = String::with_capacity(digits.len() + digits.len() / 3);
+        for (i, digit) in digits.chars().enumerate() {
+            if i != 0 && (digits.len() - i) % 3 == 0 {
+                grouped.push(self.grouping);
+            }
+            grouped.push(digit);
+        }
+
+        grouped
+    }
+
+    /// e.g., `1,234.5` with `precision` 1
+    pub fn decimal(&self, value: f64, precision: usize) -> String {
+        let rendered = format!("{:.precision$}", value.abs());
+        let (integer_part, fraction_part) = match rendered.split_once('.') {
+            Some((integer_part, fraction_part)) => (integer_part, Some(fraction_part)),
+            None => (rendered.as_str(), None),
+        };
+        let sign = if value < 0.0 && rendered.chars().any(|c| c != '0' && c != '.') {
+            "-"
+        } else {
+            ""
+        };
+        let integer_part = self.integer(integer_part.parse().unwrap_or_default());
+        match fraction_part {
+            Some(fraction_part) => format!("{sign}{integer_part}{}{fraction_part}", self.decimal),
+            None => format!("{sign}{integer_part}"),
+        }
+    }
+
+    /// e.g., `850 ms`, `12.3 s`, `4 min 05 s` or `2 h 03 min`
+    pub fn duration(&self, duration: Duration) -> String {
+        let seconds = duration.as_secs();
+        if seconds == 0 {
+            return format!("{} ms", duration.subsec_millis());
+        }
+        if seconds < 60 {
+            return format!("{} s", self.decimal(duration.as_secs_f64(), 1));
+        }
+        if seconds < 3600 {
+            return format!("{} min {:02} s", seconds / 60, seconds % 60);
+        }
+
+        format!(
+            "{} h {:02} min",
+            self.integer(seconds / 3600),
+            seconds % 3600 / 60
+        )
+    }
+
+    /// e.g., `512 B`, `1.5 KiB` or `12.0 MiB`
+    pub fn size(&self, bytes: u64) -> String {
+        const UNITS: [&str; 4] = ["KiB", "MiB", "GiB", "TiB"];
+        if bytes < 1024 {
+            return format!("{bytes} B");
+        }
+
+        let mut value = bytes as f64 / 1024.0;
+        let mut unit = UNITS[0];
+        for next_unit in UNITS.iter().skip(1) {
+            if value < 1024.0 {
+                break;
+            }
+            value /= 1024.0;
+            unit = next_unit;
+        }
+
+        format!("{} {unit}", self.decimal(value, 1))
+    }
+}
+
+/// Format a duration in the locale of the environment.
+pub fn format_duration(duration: Duration) -> String {
+    NumberFormat::from_env().duration(duration)
+}
+
+/// Format a duration given in seconds in the locale of the environment.
+pub fn format_seconds(seconds: f64) -> String {
+    format_duration(Duration::from_secs_f64(seconds.max(0.0)))
+}
+
+/// Format a number of bytes in the locale of the environment.
+pub fn format_size(bytes: u64) -> String {
+    NumberFormat::from_env().size(bytes)
+}
+
+/// Format a count with the digit grouping of the locale of the environment.
+pub fn format_count(value: u64) -> String {
+    NumberFormat::from_env().integer(value)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_number_format() {
+        let english = NumberFormat::from_locale("en_US.UTF-8");
+        assert_eq!(english, NumberFormat::default());
+        assert_eq!(english.integer(0), "0");
+        assert_eq!(english.integer(999), "999");
+        assert_eq!(english.integer(1234567), "1,234,567");
+        assert_eq!(english.decimal(1234.56, 1), "1,234.6");
+        assert_eq!(english.decimal(-0.01, 1), "0.0");
+        assert_eq!(english.duration(Duration::from_millis(850)), "850 ms");
+        assert_eq!(english.duration(Duration::from_millis(12345)), "12.3 s");
+        assert_eq!(english.duration(Duration::from_secs(245)), "4 min 05 s");
+        assert_eq!(english.duration(Duration::from_secs(7380)), "2 h 03 min");
+        assert_eq!(english.size(512), "512 B");
+        assert_eq!(english.size(1536), "1.5 KiB");
+        assert_eq!(english.size(12 * 1024 * 1024), "12.0 MiB");
+
+        let german = NumberFormat::from_locale("de_DE.UTF-8");
+        assert_eq!(german.integer(1234567), "1.234.567");
+        assert_eq!(german.size(1536), "1,5 KiB");
+        assert_eq!(NumberFormat::from_locale("fr").integer(1234), "1 234");
+        assert_eq!(NumberFormat::from_locale("C"), NumberFormat::default());
+    }
+}
@@ -0,0 +1,224 @@
+//! The account files, which have the private keys, are encrypted with the wallet password
+//! in the Ethereum keystore format (scrypt and AES-128-CTR with a MAC), the same as encrypted backups.
+//! The wallet file itself has no private keys and stays in plaintext,
+//! so that the wallet can tell whether a password is needed before reading the accounts.
+
+use std::{
+    fs::{File, OpenOptions},
+    path::{Path, PathBuf},
+};
+
+use anyhow::Context;
+use dialoguer::Password;
+use serde::Deserialize;
+use zeroize::Zeroizing;
+
+use crate::utils::terminal::TerminalOptions;
+
+/// The password of the wallet used in non-interactive mode.
+/// If it is set for a plaintext wallet, the wallet is encrypted with it.
+pub const WALLET_PASSWORD_ENV: &str = "INTMAX_WALLET_PASSWORD";
+
+/// The text encrypted into the password check of the wallet file.
+const PASSWORD_CHECK_TEXT: &str = "intmax wallet password check";
+
+/// The part of a keystore file which an account file does not have.
+#[derive(Deserialize)]
+struct EncryptedFileOnly {
+    #[allow(dead_code)]
+    crypto: serde::de::IgnoredAny,
+}
+
+/// Returns true if the content of a file is a keystore rather than a plaintext account.
+pub fn is_encrypted_file(encoded_value: &str) -> bool {
+    serde_json::from_str::<EncryptedFileOnly>(encoded_value).is_ok()
+}
+
+/// Returns the file next to `file_path` into which it is written before it is renamed into place.
+pub fn tmp_file_path(file_path: &Path) -> PathBuf {
+    file_path.with_extension("tmp")
+}
+
+/// Create a file which only the owner can read and write.
+pub fn create_private_file(file_path: &Path) -> anyhow::Result<File> {
+    // The mode is applied only when the file is created.
+    std::fs::remove_file(file_path).unwrap_or(());
+    let mut options = OpenOptions::new();
+    options.write(true).create_new(true);
+    #[cfg(unix)]
+    std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+
+    options
+        .open(file_path)
+        .with_context(|| format!("fail to create {}", file_path.display()))
+}
+
+/// Encrypt `encoded_value` with `password` and write it into `file_path`.
+/// The file is renamed into place after it is written, so an interrupted write leaves the old file.
+pub fn write_encrypted_file(
+    file_path: &Path,
+    encoded_value: Zeroizing<String>,
+    password: &str,
+) -> anyhow::Result<()> {
+    let dir_path = file_path
+        .parent()
+        .with_context(|| format!("invalid file path: {}", file_path.display()))?;
+    let tmp_file_path = tmp_file_path(file_path);
+    let tmp_file_name = tmp_file_path
+        .file_name()
+        .with_context(|| format!("invalid file path: {}", file_path.display()))?
+        .to_string_lossy();
+    // The keystore is written into the file created here, which only the owner can read.
+    drop(create_private_file(&tmp_file_path)?);
+    eth_keystore::encrypt_key(
+        dir_path,
+        &mut rand::thread_rng(),
+        encoded_value.as_bytes(),
+        password,
+        Some(&tmp_file_name),
+    )?;
+    File::open(&tmp_file_path)?.sync_all()?;
+    std::fs::rename(&tmp_file_path, file_path)
+        .with_context(|| format!("fail to write {}", file_path.display()))
+}
+
+/// Returns a keystore of a fixed text, which is kept in the wallet file
+/// to check the password even if no encrypted account file is left to try it with.
+pub fn make_password_check(dir_path: &Path, password: &str) -> anyhow::Result<String> {
+    let file_path = dir_path.join("password-check.json");
+    write_encrypted_file(
+        &file_path,
+        Zeroizing::new(PASSWORD_CHECK_TEXT.to_string()),
+        password,
+    )?;
+    let password_check = std::fs::read_to_string(&file_path);
+    std::fs::remove_file(&file_path).unwrap_or(());
+
+    Ok(password_check?)
+}
+
+/// Fails if `password` is not the one with which `password_check` was made.
+pub fn verify_password_check(
+    dir_path: &Path,
+    password_check: &str,
+    password: &str,
+) -> anyhow::Result<()> {
+    let file_path = dir_path.join("password-check.json");
+    std::fs::write(&file_path, password_check)?;
+    let decrypted_value = read_encrypted_file(&file_path, password);
+    std::fs::remove_file(&file_path).unwrap_or(());
+    if *decrypted_value? != PASSWORD_CHECK_TEXT {
+        anyhow::bail!("invalid password check");
+    }
+
+    Ok(())
+}
+
+/// Decrypt the file written by `write_encrypted_file`. It fails if the password is wrong.
+pub fn read_encrypted_file(file_path: &Path, password: &str) -> anyhow::Result<Zeroizing<String>> {
+    let decrypted_value = Zeroizing::new(
+        eth_keystore::decrypt_key(file_path, password)
+            .with_context(|| format!("fail to decrypt {}", file_path.display()))?,
+    );
+    let encoded_value = std::str::from_utf8(&decrypted_value)
+        .with_context(|| format!("{} is not a wallet file", file_path.display()))?;
+
+    Ok(Zeroizing::new(encoded_value.to_string()))
+}
+
+/// Read the password of an encrypted wallet from `INTMAX_WALLET_PASSWORD`, or prompt it.
+pub fn read_wallet_password(terminal: &TerminalOptions) -> anyhow::Result<Zeroizing<String>> {
+    if let Some(password) = read_wallet_password_env()? {
+        return Ok(password);
+    }
+
+    terminal.ensure_interactive(&format!(
+        "set the wallet password with {WALLET_PASSWORD_ENV}"
+    ))?;
+    let password = Password::with_theme(&*terminal.theme())
+        .with_prompt("Wallet password")
+        .interact()?;
+
+    Ok(Zeroizing::new(password))
+}
+
+/// Prompt a new password of the wallet twice.
+/// `INTMAX_WALLET_PASSWORD` is not read since it has the current password.
+pub fn prompt_new_wallet_password(terminal: &TerminalOptions) -> anyhow::Result<Zeroizing<String>> {
+    terminal.ensure_interactive("the new wallet password is only prompted")?;
+    let password = Password::with_theme(&*terminal.theme())
+        .with_prompt("New wallet password")
+        .with_confirmation("Confirm wallet password", "Passwords do not match")
+        .interact()?;
+
+    Ok(Zeroizing::new(password))
+}
+
+/// Returns the password of a wallet created now, or `None` to keep it unencrypted.
+/// In interactive mode, the password is prompted and an empty one skips the encryption.
+pub fn read_new_wallet_password(
+    terminal: &TerminalOptions,
+) -> anyhow::Result<Option<Zeroizing<String>>> {
+    if let Some(password) = read_wallet_password_env()? {
+        return Ok(Some(password));
+    }
+    if !terminal.interactive {
+        return Ok(None);
+    }
+
+    let password = Zeroizing::new(
+        Password::with_theme(&*terminal.theme())
+            .with_prompt("Wallet password (empty to keep the private keys unencrypted)")
+            .with_confirmation("Confirm wallet password", "Passwords do not match")
+            .allow_empty_password(true)
+            .interact()?,
+    );
+    if password.is_empty() {
+        return Ok(None);
+    }
+
+    Ok(Some(password))
+}
+
+pub fn read_wallet_password_env() -> anyhow::Result<Option<Zeroizing<String>>> {
+    if let Ok(password) = std::env::var(WALLET_PASSWORD_ENV) {
+        if password.is_empty() {
+            anyhow::bail!("{WALLET_PASSWORD_ENV} is empty");
+        }
+
+        return Ok(Some(Zeroizing::new(password)));
+    }
+
+    Ok(None)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::test_utils::temp_dir;
+
+    #[test]
+    fn test_encrypted_file() {
+        let temp_dir = temp_dir();
+        let dir_path = temp_dir.path().join("wallet-encryption");
+        std::fs::create_dir_all(&dir_path).unwrap();
+        let file_path = dir_path.join("0x0123456789abcdef.json");
+        let encoded_value = r#"{"account":{}}"#;
+        assert!(!is_encrypted_file(encoded_value));
+
+        write_encrypted_file(
+            &file_path,
+            Zeroizing::new(encoded_value.to_string()),
+            "password",
+        )
+        .unwrap();
+        assert!(is_encrypted_file(
+            &std::fs::read_to_string(&file_path).unwrap()
+        ));
+        assert_eq!(
+            *read_encrypted_file(&file_path, "password").unwrap(),
+            encoded_value
+        );
+        assert!(read_encrypted_file(&file_path, "wrong password").is_err());
+    }
+}
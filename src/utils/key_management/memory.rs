@@ -1,10 +1,12 @@
 use std::{
-    collections::HashMap,
-    fs::File,
-    io::{Read, Write},
-    path::PathBuf,
+    collections::{BTreeSet, HashMap, HashSet},
+    io::Write,
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex},
 };
 
+use anyhow::Context;
+
 use intmax_rollup_interface::intmax_zkp_core::{
     plonky2::field::goldilocks_field::GoldilocksField,
     sparse_merkle_tree::{
@@ -12,18 +14,63 @@ use intmax_rollup_interface::intmax_zkp_core::{
         node_data::{Node, NodeData},
         root_data::RootData,
     },
-    transaction::{
-        asset::{ReceivedAssetProof, TokenKind},
-        tree::user_asset::UserAssetTree,
-    },
+    transaction::{asset::ReceivedAssetProof, tree::user_asset::UserAssetTree},
     zkdsa::account::{Account, Address},
 };
-use serde::{Deserialize, Serialize};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use zeroize::Zeroizing;
+
+use crate::utils::{
+    loader::{decode_json_file, read_json_file, LocalFileKind},
+    migration::WALLET_SCHEMA,
+};
 
-use super::types::{Assets, Wallet};
+use super::{
+    encryption::{
+        create_private_file, is_encrypted_file, make_password_check, read_encrypted_file,
+        tmp_file_path, verify_password_check, write_encrypted_file, WALLET_PASSWORD_ENV,
+    },
+    recent_sends::RecentSends,
+    stats::TransferStats,
+    types::{
+        AssetRoot, Assets, MergePolicy, SentTransaction, SerializableSentTransaction, TxHash,
+        Wallet,
+    },
+};
 
 type F = GoldilocksField;
 
+/// The directory next to the wallet file, which has a file for each account.
+pub const ACCOUNTS_DIR_NAME: &str = "accounts";
+
+/// Returns the file of the account, e.g., `accounts/0x0123...json` next to the wallet file.
+pub fn account_file_path(wallet_file_path: &Path, address: &Address<F>) -> PathBuf {
+    wallet_file_path
+        .with_file_name(ACCOUNTS_DIR_NAME)
+        .join(format!("{address}.json"))
+}
+
+/// Returns the account files next to the wallet file, sorted by name.
+pub fn list_account_files(wallet_file_path: &Path) -> anyhow::Result<Vec<PathBuf>> {
+    let accounts_dir_path = wallet_file_path.with_file_name(ACCOUNTS_DIR_NAME);
+    if !accounts_dir_path.is_dir() {
+        return Ok(vec![]);
+    }
+
+    let mut account_files = vec![];
+    for entry in std::fs::read_dir(&accounts_dir_path)
+        .with_context(|| format!("fail to read {}", accounts_dir_path.display()))?
+    {
+        let entry = entry?;
+        if entry.file_type()?.is_file() && entry.file_name().to_string_lossy().ends_with(".json") {
+            account_files.push(entry.path());
+        }
+    }
+    account_files.sort();
+
+    Ok(account_files)
+}
+
 #[derive(Clone, Debug)]
 pub struct UserState<
     D: NodeData<GoldilocksHashOut, GoldilocksHashOut, GoldilocksHashOut>,
@@ -36,10 +83,14 @@ pub struct UserState<
 
     pub rest_received_assets: Vec<ReceivedAssetProof<GoldilocksField>>,
 
-    /// the set consisting of `(tx_hash, removed_assets, block_number)`.
-    #[allow(clippy::type_complexity)]
-    pub sent_transactions:
-        HashMap<WrappedHashOut<F>, (Vec<(TokenKind<F>, u64, WrappedHashOut<F>)>, Option<u32>)>,
+    /// the map from `tx_hash` to the record of the transaction.
+    pub sent_transactions: HashMap<TxHash<F>, SentTransaction>,
+
+    /// the totals of the transferred tokens, which are updated on sync
+    pub stats: TransferStats,
+
+    /// the transfers sent within the duplicate window of `tx send`
+    pub recent_sends: RecentSends,
     // HashSet<(
     //     WrappedHashOut<F>,
     //     Vec<(TokenKind<F>, u64, WrappedHashOut<F>)>,
@@ -51,11 +102,13 @@ pub struct UserState<
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct SerializableUserState {
     pub account: Account<F>,
+    /// Empty if the nodes are stored in `SerializableWalletOnMemory::nodes`.
+    #[serde(default)]
     pub asset_tree_nodes: Vec<(
         WrappedHashOut<F>,
         Node<WrappedHashOut<F>, WrappedHashOut<F>, WrappedHashOut<F>>,
     )>,
-    pub asset_tree_root: WrappedHashOut<F>,
+    pub asset_tree_root: AssetRoot<F>,
     pub assets: Assets<F>,
     #[serde(default)]
     pub last_seen_block_number: u32,
@@ -64,35 +117,135 @@ pub struct SerializableUserState {
     pub rest_received_assets: Vec<ReceivedAssetProof<GoldilocksField>>,
 
     #[serde(default)]
-    pub sent_transactions: Vec<(
-        WrappedHashOut<F>,
-        (Vec<(TokenKind<F>, u64, WrappedHashOut<F>)>, Option<u32>),
-    )>,
+    pub sent_transactions: Vec<(TxHash<F>, SerializableSentTransaction)>,
+
+    #[serde(default)]
+    pub stats: TransferStats,
+
+    #[serde(default)]
+    pub recent_sends: RecentSends,
 }
 
 impl From<SerializableUserState> for UserState<NodeDataMemory, RootDataMemory> {
     fn from(value: SerializableUserState) -> Self {
-        let mut asset_tree_nodes = NodeDataMemory::default();
+        value.into_user_state(NodeDataMemory::default())
+    }
+}
+
+impl SerializableUserState {
+    /// Restore the user state whose asset tree uses the given node store.
+    /// The nodes included in this user state are moved into the store.
+    pub fn into_user_state(
+        self,
+        nodes_db: NodeDataMemory,
+    ) -> UserState<NodeDataMemory, RootDataMemory> {
+        let mut asset_tree_nodes = nodes_db;
         asset_tree_nodes
-            .multi_insert(value.asset_tree_nodes)
+            .multi_insert(self.asset_tree_nodes)
             .unwrap();
-        let asset_tree = UserAssetTree::new(asset_tree_nodes, value.asset_tree_root.into());
+        let asset_tree = UserAssetTree::new(asset_tree_nodes, self.asset_tree_root.0.into());
         let mut sent_transactions = HashMap::new();
-        for (key, value) in value.sent_transactions {
-            sent_transactions.insert(key, value);
+        for (key, value) in self.sent_transactions {
+            sent_transactions.insert(key, value.into());
         }
 
+        UserState {
+            account: self.account,
+            asset_tree,
+            assets: self.assets,
+            last_seen_block_number: self.last_seen_block_number,
+            rest_received_assets: self.rest_received_assets,
+            sent_transactions,
+            stats: self.stats,
+            recent_sends: self.recent_sends,
+        }
+    }
+
+    /// Convert the user state without the nodes of its asset tree.
+    pub fn without_nodes(value: &UserState<NodeDataMemory, RootDataMemory>) -> Self {
+        let asset_tree_root = AssetRoot(value.asset_tree.get_root().unwrap());
+        let sent_transactions = sort_sent_transactions(
+            value
+                .sent_transactions
+                .clone()
+                .into_iter()
+                .map(|(key, value)| (key, value.into()))
+                .collect::<Vec<_>>(),
+        );
+
         Self {
             account: value.account,
-            asset_tree,
-            assets: value.assets,
+            asset_tree_nodes: vec![],
+            asset_tree_root,
+            assets: value.assets.clone(),
             last_seen_block_number: value.last_seen_block_number,
-            rest_received_assets: value.rest_received_assets,
+            rest_received_assets: value.rest_received_assets.clone(),
             sent_transactions,
+            stats: value.stats.clone(),
+            recent_sends: value.recent_sends.clone(),
         }
     }
 }
 
+#[allow(clippy::type_complexity)]
+fn sort_nodes(
+    mut nodes: Vec<(
+        WrappedHashOut<F>,
+        Node<WrappedHashOut<F>, WrappedHashOut<F>, WrappedHashOut<F>>,
+    )>,
+) -> Vec<(
+    WrappedHashOut<F>,
+    Node<WrappedHashOut<F>, WrappedHashOut<F>, WrappedHashOut<F>>,
+)> {
+    nodes.sort_by_cached_key(|(key, _)| key.to_string());
+
+    nodes
+}
+
+/// Returns the nodes reachable from `root`. The leaf of each layer of the asset tree
+/// is the root of the next layer, so the nodes of the inner layers are included.
+#[allow(clippy::type_complexity)]
+fn collect_reachable_nodes(
+    nodes_db: &NodeDataMemory,
+    root: WrappedHashOut<F>,
+) -> Vec<(
+    WrappedHashOut<F>,
+    Node<WrappedHashOut<F>, WrappedHashOut<F>, WrappedHashOut<F>>,
+)> {
+    let nodes = nodes_db.nodes.lock().unwrap();
+    let mut visited = HashSet::new();
+    let mut stack = vec![root];
+    let mut reachable_nodes = vec![];
+    while let Some(key) = stack.pop() {
+        if !visited.insert(key) {
+            continue;
+        }
+        let node = if let Some(node) = nodes.get(&key) {
+            node
+        } else {
+            continue;
+        };
+
+        if let Node::Internal(left, right) = node {
+            stack.push(*left);
+            stack.push(*right);
+        } else if let Node::Leaf(_, value) = node {
+            stack.push(*value);
+        }
+        reachable_nodes.push((key, node.clone()));
+    }
+
+    sort_nodes(reachable_nodes)
+}
+
+fn sort_sent_transactions(
+    mut sent_transactions: Vec<(TxHash<F>, SerializableSentTransaction)>,
+) -> Vec<(TxHash<F>, SerializableSentTransaction)> {
+    sent_transactions.sort_by_cached_key(|(tx_hash, _)| tx_hash.to_string());
+
+    sent_transactions
+}
+
 impl<'de> Deserialize<'de> for UserState<NodeDataMemory, RootDataMemory> {
     fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
         let raw = SerializableUserState::deserialize(deserializer)?;
@@ -103,16 +256,24 @@ impl<'de> Deserialize<'de> for UserState<NodeDataMemory, RootDataMemory> {
 
 impl From<UserState<NodeDataMemory, RootDataMemory>> for SerializableUserState {
     fn from(value: UserState<NodeDataMemory, RootDataMemory>) -> Self {
-        let asset_tree_root = value.asset_tree.get_root().unwrap();
+        let asset_tree_root = AssetRoot(value.asset_tree.get_root().unwrap());
         let asset_tree_nodes = value.asset_tree.nodes_db.clone();
-        let asset_tree_nodes = asset_tree_nodes
-            .nodes
-            .lock()
-            .unwrap()
-            .clone()
-            .into_iter()
-            .collect::<Vec<_>>();
-        let sent_transactions = value.sent_transactions.into_iter().collect::<Vec<_>>();
+        let asset_tree_nodes = sort_nodes(
+            asset_tree_nodes
+                .nodes
+                .lock()
+                .unwrap()
+                .clone()
+                .into_iter()
+                .collect::<Vec<_>>(),
+        );
+        let sent_transactions = sort_sent_transactions(
+            value
+                .sent_transactions
+                .into_iter()
+                .map(|(key, value)| (key, value.into()))
+                .collect::<Vec<_>>(),
+        );
 
         Self {
             account: value.account,
@@ -122,6 +283,8 @@ impl From<UserState<NodeDataMemory, RootDataMemory>> for SerializableUserState {
             last_seen_block_number: value.last_seen_block_number,
             rest_received_assets: value.rest_received_assets,
             sent_transactions,
+            stats: value.stats,
+            recent_sends: value.recent_sends,
         }
     }
 }
@@ -134,74 +297,512 @@ impl Serialize for UserState<NodeDataMemory, RootDataMemory> {
     }
 }
 
+/// The part of an account file read by `WalletOnMemory::sent_tx_hashes`.
+#[derive(Deserialize)]
+struct SentTransactionsOnly {
+    #[serde(default)]
+    sent_transactions: Vec<(TxHash<F>, serde::de::IgnoredAny)>,
+}
+
 #[derive(Clone)]
 pub struct WalletOnMemory {
     pub data: HashMap<Address<F>, UserState<NodeDataMemory, RootDataMemory>>,
     pub default_account: Option<Address<F>>,
+    /// the policy applied to all accounts in this wallet
+    pub merge_policy: MergePolicy,
     pub wallet_file_path: PathBuf,
+
+    /// The node store shared by the asset trees of all accounts.
+    /// Since the nodes are addressed by their hashes, the same node is stored only once.
+    pub nodes_db: NodeDataMemory,
+
+    /// The accounts whose files have not been read yet, which are not in `data`.
+    pub unloaded_accounts: HashSet<Address<F>>,
+
+    /// If true, the account files are written encrypted with `password`.
+    pub encrypted: bool,
+    /// `None` until the encrypted wallet is unlocked.
+    password: Option<Zeroizing<String>>,
+    /// The keystore of a fixed text encrypted with `password`, which `unlock` checks the password with.
+    /// `None` if the wallet is not encrypted or was encrypted by an older version.
+    password_check: Option<String>,
+
+    /// The transactions which the loaded accounts had sent when they were read.
+    known_tx_hashes: HashSet<TxHash<F>>,
+    /// Receives the transactions sent since the accounts were read, each time the wallet is written.
+    sent_tx_hash_sink: Option<Arc<Mutex<BTreeSet<String>>>>,
 }
 
+#[allow(clippy::type_complexity)]
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct SerializableWalletOnMemory {
-    pub data: Vec<UserState<NodeDataMemory, RootDataMemory>>,
+    #[serde(default)]
+    pub schema_version: u32,
+    /// The accounts embedded in the wallet file, which are written by older versions.
+    pub data: Vec<SerializableUserState>,
     #[serde(default)]
     pub default_account: Option<Address<F>>,
+    #[serde(default)]
+    pub merge_policy: MergePolicy,
+    #[serde(default)]
+    pub nodes: Vec<(
+        WrappedHashOut<F>,
+        Node<WrappedHashOut<F>, WrappedHashOut<F>, WrappedHashOut<F>>,
+    )>,
+    /// The accounts stored in their own files under `ACCOUNTS_DIR_NAME`.
+    #[serde(default)]
+    pub accounts: Vec<Address<F>>,
+    /// whether the account files are encrypted with the wallet password
+    #[serde(default)]
+    pub encrypted: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub password_check: Option<String>,
 }
 
 impl WalletOnMemory {
+    /// Read the wallet with all accounts.
     pub fn read_from_file(wallet_file_path: PathBuf) -> anyhow::Result<Self> {
-        let mut file = File::open(wallet_file_path.clone())?;
-        let mut encoded_wallet = String::new();
-        file.read_to_string(&mut encoded_wallet)?;
-        let raw: SerializableWalletOnMemory = serde_json::from_str(&encoded_wallet)?;
+        let mut wallet = Self::read_index_from_file(wallet_file_path)?;
+        wallet.load_all_accounts()?;
+
+        Ok(wallet)
+    }
+
+    /// Read the wallet file without the accounts stored in their own files,
+    /// which are read later with `load_account` only if a command needs them.
+    pub fn read_index_from_file(wallet_file_path: PathBuf) -> anyhow::Result<Self> {
+        let raw: SerializableWalletOnMemory = read_json_file(
+            LocalFileKind::Wallet,
+            &wallet_file_path,
+            Some(&WALLET_SCHEMA),
+        )?;
+
+        let mut nodes_db = NodeDataMemory::default();
+        nodes_db.multi_insert(raw.nodes).unwrap();
 
+        // The wallet written by older versions has the nodes for each account,
+        // which are merged into the shared node store here.
         let mut result = HashMap::new();
         for value in raw.data.into_iter() {
+            let value = value.into_user_state(nodes_db.clone());
             result.insert(value.account.address, value);
         }
+        let unloaded_accounts = raw
+            .accounts
+            .into_iter()
+            .filter(|address| !result.contains_key(address))
+            .collect();
+        let known_tx_hashes = result
+            .values()
+            .flat_map(|user_state| user_state.sent_transactions.keys().copied())
+            .collect();
 
         Ok(Self {
             data: result,
             default_account: raw.default_account,
+            merge_policy: raw.merge_policy,
             wallet_file_path,
+            nodes_db,
+            unloaded_accounts,
+            encrypted: raw.encrypted,
+            password: None,
+            password_check: raw.password_check,
+            known_tx_hashes,
+            sent_tx_hash_sink: None,
         })
     }
+
+    /// Report the transactions sent since the accounts were read into `sink` when the wallet is written,
+    /// e.g., for the audit log.
+    pub fn set_sent_tx_hash_sink(&mut self, sink: Option<Arc<Mutex<BTreeSet<String>>>>) {
+        self.sent_tx_hash_sink = sink;
+    }
+
+    /// Returns the transactions sent from the loaded accounts since they were read or added.
+    pub fn newly_sent_tx_hashes(&self) -> Vec<TxHash<F>> {
+        self.data
+            .values()
+            .flat_map(|user_state| user_state.sent_transactions.keys())
+            .filter(|tx_hash| !self.known_tx_hashes.contains(tx_hash))
+            .copied()
+            .collect()
+    }
+
+    /// Read the file of the account if it has not been read yet.
+    /// An address which is not in this wallet is ignored.
+    pub fn load_account(&mut self, address: &Address<F>) -> anyhow::Result<()> {
+        if !self.unloaded_accounts.contains(address) {
+            return Ok(());
+        }
+
+        let account_file_path = account_file_path(&self.wallet_file_path, address);
+        let raw: SerializableUserState = self.read_account_file(address)?;
+        if raw.account.address != *address {
+            anyhow::bail!(
+                "{} has the account {}, but {address} is expected",
+                account_file_path.display(),
+                raw.account.address
+            );
+        }
+
+        self.unloaded_accounts.remove(address);
+        let user_state = raw.into_user_state(self.nodes_db.clone());
+        self.known_tx_hashes
+            .extend(user_state.sent_transactions.keys().copied());
+        self.data.insert(*address, user_state);
+
+        Ok(())
+    }
+
+    pub fn load_all_accounts(&mut self) -> anyhow::Result<()> {
+        let mut addresses = self.unloaded_accounts.iter().copied().collect::<Vec<_>>();
+        addresses.sort_by_cached_key(|address| address.to_string());
+        for address in addresses {
+            self.load_account(&address)?;
+        }
+
+        Ok(())
+    }
+
+    /// Returns true if this wallet has the account, whether it is loaded or not.
+    pub fn contains_account(&self, address: &Address<F>) -> bool {
+        self.data.contains_key(address) || self.unloaded_accounts.contains(address)
+    }
+
+    /// Returns the hashes of the transactions sent from all accounts.
+    /// Only the sent transactions are decoded from the files of unloaded accounts.
+    pub fn sent_tx_hashes(&self) -> anyhow::Result<Vec<TxHash<F>>> {
+        let mut tx_hashes = self
+            .data
+            .values()
+            .flat_map(|user_state| user_state.sent_transactions.keys().copied())
+            .collect::<Vec<_>>();
+        for address in self.unloaded_accounts.iter() {
+            let raw: SentTransactionsOnly = self.read_account_file(address)?;
+            tx_hashes.extend(
+                raw.sent_transactions
+                    .into_iter()
+                    .map(|(tx_hash, _)| tx_hash),
+            );
+        }
+
+        Ok(tx_hashes)
+    }
+
+    /// Read the file of the account, which is decrypted if it was written with the wallet password.
+    /// Files written before the wallet was encrypted are read as they are.
+    fn read_account_file<T: DeserializeOwned>(&self, address: &Address<F>) -> anyhow::Result<T> {
+        let account_file_path = account_file_path(&self.wallet_file_path, address);
+        let mut encoded_value =
+            Zeroizing::new(std::fs::read_to_string(&account_file_path).map_err(|err| {
+                anyhow::anyhow!("fail to read {}: {err}", account_file_path.display())
+            })?);
+        if is_encrypted_file(&encoded_value) {
+            let password = self.password.as_ref().with_context(|| {
+                format!("the wallet is locked. Set the wallet password with {WALLET_PASSWORD_ENV}")
+            })?;
+            encoded_value = read_encrypted_file(&account_file_path, password)?;
+        }
+
+        Ok(decode_json_file(
+            LocalFileKind::Wallet,
+            &account_file_path,
+            &encoded_value,
+            None,
+        )?)
+    }
+
+    /// Returns true if the accounts cannot be read or written until the wallet is unlocked.
+    pub fn is_locked(&self) -> bool {
+        self.encrypted && self.password.is_none()
+    }
+
+    /// Encrypt the account files with `password` on the next `backup`, or decrypt them with `None`.
+    /// Only the loaded accounts are rewritten, so load all accounts in advance.
+    pub fn set_password(&mut self, password: Option<Zeroizing<String>>) {
+        self.encrypted = password.is_some();
+        self.password = password;
+        self.password_check = None;
+    }
+
+    fn wallet_dir_path(&self) -> PathBuf {
+        let mut wallet_dir_path = self.wallet_file_path.clone();
+        wallet_dir_path.pop();
+
+        wallet_dir_path
+    }
+
+    /// Returns the wallet file and the files of all accounts.
+    pub fn storage_file_paths(&self) -> Vec<PathBuf> {
+        let mut addresses = self
+            .data
+            .keys()
+            .chain(self.unloaded_accounts.iter())
+            .collect::<Vec<_>>();
+        addresses.sort_by_cached_key(|address| address.to_string());
+
+        std::iter::once(self.wallet_file_path.clone())
+            .chain(
+                addresses
+                    .into_iter()
+                    .map(|address| account_file_path(&self.wallet_file_path, address)),
+            )
+            .collect()
+    }
+
+    /// Returns the total size of the wallet file and the account files in bytes.
+    pub fn storage_size(&self) -> u64 {
+        self.storage_file_paths()
+            .iter()
+            .filter_map(|file_path| std::fs::metadata(file_path).ok())
+            .map(|metadata| metadata.len())
+            .sum()
+    }
 }
 
 impl WalletOnMemory {
+    /// Encode the loaded accounts into one document. The accounts, the nodes and the other collections are sorted,
+    /// so that the same wallet is always encoded into the same bytes.
+    pub fn to_serializable(&self) -> SerializableWalletOnMemory {
+        let nodes = sort_nodes(
+            self.nodes_db
+                .nodes
+                .lock()
+                .unwrap()
+                .clone()
+                .into_iter()
+                .collect::<Vec<_>>(),
+        );
+        let mut data = self
+            .data
+            .values()
+            .map(SerializableUserState::without_nodes)
+            .collect::<Vec<_>>();
+        data.sort_by_cached_key(|user_state| user_state.account.address.to_string());
+
+        SerializableWalletOnMemory {
+            schema_version: WALLET_SCHEMA.current_version(),
+            data,
+            default_account: self.default_account,
+            merge_policy: self.merge_policy,
+            nodes,
+            accounts: vec![],
+            encrypted: false,
+            password_check: None,
+        }
+    }
+
+    /// Move the accounts of `other` which are not in this wallet, with the nodes of their asset trees.
+    /// Returns the addresses of the accounts left in `other` since this wallet already has them.
+    pub fn merge(&mut self, other: &WalletOnMemory) -> Vec<Address<F>> {
+        let nodes = other
+            .nodes_db
+            .nodes
+            .lock()
+            .unwrap()
+            .clone()
+            .into_iter()
+            .collect::<Vec<_>>();
+        self.nodes_db.multi_insert(nodes).unwrap();
+
+        let mut skipped_accounts = vec![];
+        for (address, user_state) in other.data.iter() {
+            if self.contains_account(address) {
+                skipped_accounts.push(*address);
+                continue;
+            }
+
+            let user_state = SerializableUserState::without_nodes(user_state)
+                .into_user_state(self.nodes_db.clone());
+            self.known_tx_hashes
+                .extend(user_state.sent_transactions.keys().copied());
+            self.data.insert(*address, user_state);
+        }
+        if self.default_account.is_none() {
+            self.default_account = other.default_account;
+        }
+        skipped_accounts.sort_by_cached_key(|address| address.to_string());
+
+        skipped_accounts
+    }
+
+    /// Encode one loaded account with the nodes of its asset tree, so that it can be imported into another wallet.
+    pub fn export_account(&self, address: &Address<F>) -> anyhow::Result<SerializableUserState> {
+        let user_state = self
+            .data
+            .get(address)
+            .with_context(|| format!("{address} is not in your wallet"))?;
+        let mut raw = SerializableUserState::without_nodes(user_state);
+        raw.asset_tree_nodes = collect_reachable_nodes(&self.nodes_db, raw.asset_tree_root.0);
+
+        Ok(raw)
+    }
+
+    /// Add the account written by `export_account`. An account which this wallet already has is never overwritten.
+    pub fn import_account(&mut self, raw: SerializableUserState) -> anyhow::Result<Address<F>> {
+        let address = raw.account.address;
+        if Account::new(raw.account.private_key).address != address {
+            anyhow::bail!("the private key of {address} does not match its address");
+        }
+        if self.contains_account(&address) {
+            anyhow::bail!("{address} is already in your wallet");
+        }
+
+        let user_state = raw.into_user_state(self.nodes_db.clone());
+        self.known_tx_hashes
+            .extend(user_state.sent_transactions.keys().copied());
+        self.data.insert(address, user_state);
+
+        Ok(address)
+    }
+
+    /// Write each loaded account into its own file with the nodes of its asset tree,
+    /// and the wallet file listing all accounts. The files of unloaded accounts are kept as they are.
     pub fn backup(&self) -> anyhow::Result<()> {
+        if self.is_locked() {
+            anyhow::bail!(
+                "the wallet is locked. Set the wallet password with {WALLET_PASSWORD_ENV}"
+            );
+        }
+
+        let wallet_dir_path = self.wallet_dir_path();
+        std::fs::create_dir(wallet_dir_path.clone()).unwrap_or(());
+        std::fs::create_dir(wallet_dir_path.join(ACCOUNTS_DIR_NAME)).unwrap_or(());
+        let password_check = match (&self.password, &self.password_check) {
+            (Some(password), None) => Some(make_password_check(&wallet_dir_path, password)?),
+            (Some(_), Some(password_check)) => Some(password_check.clone()),
+            (None, _) => None,
+        };
+
+        for (address, user_state) in self.data.iter() {
+            let mut raw = SerializableUserState::without_nodes(user_state);
+            raw.asset_tree_nodes = collect_reachable_nodes(&self.nodes_db, raw.asset_tree_root.0);
+            let account_file_path = account_file_path(&self.wallet_file_path, address);
+            let encoded_value = Zeroizing::new(serde_json::to_string(&raw)?);
+            if let Some(password) = &self.password {
+                write_encrypted_file(&account_file_path, encoded_value, password)?;
+            } else {
+                write_private_file(&account_file_path, encoded_value)?;
+            }
+        }
+
+        let mut accounts = self
+            .data
+            .keys()
+            .chain(self.unloaded_accounts.iter())
+            .copied()
+            .collect::<Vec<_>>();
+        accounts.sort_by_cached_key(|address| address.to_string());
         let raw = SerializableWalletOnMemory {
-            data: self.data.values().cloned().collect::<Vec<_>>(),
+            schema_version: WALLET_SCHEMA.current_version(),
+            data: vec![],
             default_account: self.default_account,
+            merge_policy: self.merge_policy,
+            nodes: vec![],
+            accounts,
+            encrypted: self.encrypted,
+            password_check,
         };
+        write_private_file(
+            &self.wallet_file_path,
+            Zeroizing::new(serde_json::to_string(&raw)?),
+        )?;
 
-        let mut wallet_dir_path = self.wallet_file_path.clone();
-        wallet_dir_path.pop();
-        let encoded_wallet = serde_json::to_string(&raw).unwrap();
-        std::fs::create_dir(wallet_dir_path.clone()).unwrap_or(());
-        let mut file = File::create(self.wallet_file_path.clone())?;
-        write!(file, "{}", encoded_wallet)?;
-        file.flush()?;
+        // The files of the removed accounts.
+        let account_file_paths = self.storage_file_paths();
+        for file_path in list_account_files(&self.wallet_file_path)? {
+            if !account_file_paths.contains(&file_path) {
+                std::fs::remove_file(&file_path)
+                    .with_context(|| format!("fail to remove {}", file_path.display()))?;
+            }
+        }
+
+        if let Some(sink) = &self.sent_tx_hash_sink {
+            sink.lock().unwrap().extend(
+                self.newly_sent_tx_hashes()
+                    .iter()
+                    .map(|tx_hash| tx_hash.to_string()),
+            );
+        }
 
         Ok(())
     }
 }
 
+/// Write a file which includes private keys. Only the owner can read it,
+/// and it is renamed into place after it is written, so an interrupted write never breaks the only copy of a key.
+fn write_private_file(file_path: &Path, encoded_value: Zeroizing<String>) -> anyhow::Result<()> {
+    let tmp_file_path = tmp_file_path(file_path);
+    let mut file = create_private_file(&tmp_file_path)?;
+    write!(file, "{}", *encoded_value)?;
+    file.sync_all()?;
+    std::fs::rename(&tmp_file_path, file_path)
+        .with_context(|| format!("fail to write {}", file_path.display()))
+}
+
 impl Wallet for WalletOnMemory {
     type Seed = String;
     type Account = Account<F>;
     type Error = anyhow::Error;
 
-    fn new(wallet_file_path: PathBuf, _password: String) -> Self {
+    /// An empty password keeps the private keys unencrypted.
+    fn new(wallet_file_path: PathBuf, password: String) -> Self {
+        let password = Some(Zeroizing::new(password)).filter(|password| !password.is_empty());
+
         Self {
             data: HashMap::new(),
             default_account: None,
+            merge_policy: Default::default(),
             wallet_file_path,
+            nodes_db: NodeDataMemory::default(),
+            unloaded_accounts: HashSet::new(),
+            encrypted: password.is_some(),
+            password,
+            password_check: None,
+            known_tx_hashes: HashSet::new(),
+            sent_tx_hash_sink: None,
+        }
+    }
+
+    /// The password is checked with the password check in the wallet file.
+    /// A wallet encrypted by an older version has no password check, so one of the encrypted account files is tried instead.
+    /// A wallet which is not encrypted ignores the password.
+    fn unlock(&mut self, password: String) -> anyhow::Result<()> {
+        let password = Zeroizing::new(password);
+        if !self.encrypted {
+            return Ok(());
         }
+
+        if let Some(password_check) = &self.password_check {
+            verify_password_check(&self.wallet_dir_path(), password_check, &password)
+                .context("wrong wallet password")?;
+            self.password = Some(password);
+
+            return Ok(());
+        }
+
+        let mut addresses = self.unloaded_accounts.iter().collect::<Vec<_>>();
+        addresses.sort_by_cached_key(|address| address.to_string());
+        for address in addresses {
+            let account_file_path = account_file_path(&self.wallet_file_path, address);
+            let encoded_value = Zeroizing::new(std::fs::read_to_string(&account_file_path)?);
+            if is_encrypted_file(&encoded_value) {
+                read_encrypted_file(&account_file_path, &password)
+                    .context("wrong wallet password")?;
+                break;
+            }
+        }
+        self.password = Some(password);
+
+        Ok(())
     }
 
     fn add_account(&mut self, account: Account<F>) -> anyhow::Result<()> {
-        let asset_tree = UserAssetTree::new(NodeDataMemory::default(), RootDataMemory::default());
+        if self.unloaded_accounts.contains(&account.address) {
+            anyhow::bail!("designated address was already used");
+        }
+
+        let asset_tree = UserAssetTree::new(self.nodes_db.clone(), RootDataMemory::default());
         let old_account = self.data.insert(
             account.address,
             UserState {
@@ -211,6 +812,8 @@ impl Wallet for WalletOnMemory {
                 last_seen_block_number: 0,
                 rest_received_assets: Default::default(),
                 sent_transactions: Default::default(),
+                stats: Default::default(),
+                recent_sends: Default::default(),
             },
         );
         if old_account.is_some() {
@@ -228,3 +831,226 @@ impl Wallet for WalletOnMemory {
         self.default_account
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use intmax_rollup_interface::intmax_zkp_core::{
+        plonky2::{field::types::Field, hash::hash_types::HashOut},
+        transaction::asset::TokenKind,
+    };
+
+    use super::*;
+    use crate::utils::{
+        fixtures::make_fixture_wallet, key_management::types::MergeKey, test_utils::temp_dir,
+    };
+
+    #[test]
+    fn test_deterministic_encoding() {
+        let temp_dir = temp_dir();
+        let dir_path = temp_dir.path().join("wallet-encoding");
+        std::fs::create_dir_all(&dir_path).unwrap();
+        let wallet_file_path = dir_path.join("wallet");
+        let mut wallet = make_fixture_wallet(wallet_file_path.clone()).unwrap();
+        for i in 2..10 {
+            let account = Account::new(HashOut::from_partial(&[F::from_canonical_u64(i)]));
+            wallet.add_account(account).unwrap();
+            let user_state = wallet.data.get_mut(&account.address).unwrap();
+            for j in 0..10 {
+                let kind = TokenKind {
+                    contract_address: account.address,
+                    variable_index: (j as u8).into(),
+                };
+                user_state
+                    .assets
+                    .add(kind, j, MergeKey(WrappedHashOut::rand()));
+            }
+        }
+        wallet.backup().unwrap();
+        let read_files = |wallet: &WalletOnMemory| {
+            wallet
+                .storage_file_paths()
+                .iter()
+                .map(|file_path| std::fs::read(file_path).unwrap())
+                .collect::<Vec<_>>()
+        };
+        let encoded_wallet = read_files(&wallet);
+
+        // The collections restored from the file are in a different order of iteration.
+        let wallet = WalletOnMemory::read_from_file(wallet_file_path).unwrap();
+        wallet.backup().unwrap();
+        assert_eq!(read_files(&wallet), encoded_wallet);
+    }
+
+    #[test]
+    fn test_lazy_loading() {
+        let temp_dir = temp_dir();
+        let dir_path = temp_dir.path().join("wallet-lazy");
+        std::fs::create_dir_all(&dir_path).unwrap();
+        let wallet_file_path = dir_path.join("wallet");
+        let mut wallet = make_fixture_wallet(wallet_file_path.clone()).unwrap();
+        let address = wallet.default_account.unwrap();
+        let other_account = Account::new(HashOut::from_partial(&[F::from_canonical_u64(2)]));
+        wallet.add_account(other_account).unwrap();
+        wallet.backup().unwrap();
+        let asset_root = wallet.data[&address].asset_tree.get_root().unwrap();
+
+        let mut wallet = WalletOnMemory::read_index_from_file(wallet_file_path.clone()).unwrap();
+        assert!(wallet.data.is_empty());
+        assert!(wallet.contains_account(&other_account.address));
+        wallet.load_account(&address).unwrap();
+        assert_eq!(wallet.data.len(), 1);
+        let user_state = &wallet.data[&address];
+        assert_eq!(user_state.asset_tree.get_root().unwrap(), asset_root);
+        assert!(!user_state
+            .asset_tree
+            .nodes_db
+            .nodes
+            .lock()
+            .unwrap()
+            .is_empty());
+        assert!(wallet
+            .add_account(other_account)
+            .unwrap_err()
+            .to_string()
+            .contains("already used"));
+
+        // The accounts which were not loaded are kept.
+        wallet.backup().unwrap();
+        let wallet = WalletOnMemory::read_from_file(wallet_file_path).unwrap();
+        assert_eq!(wallet.data.len(), 2);
+        assert_eq!(
+            wallet.data[&address].asset_tree.get_root().unwrap(),
+            asset_root
+        );
+        assert_eq!(
+            list_account_files(&wallet.wallet_file_path).unwrap().len(),
+            2
+        );
+    }
+
+    #[test]
+    fn test_newly_sent_tx_hashes() {
+        let temp_dir = temp_dir();
+        let dir_path = temp_dir.path().join("wallet-sent");
+        std::fs::create_dir_all(&dir_path).unwrap();
+        let wallet_file_path = dir_path.join("wallet");
+        let mut wallet = make_fixture_wallet(wallet_file_path.clone()).unwrap();
+        let address = wallet.default_account.unwrap();
+        let old_tx_hash = TxHash(WrappedHashOut::rand());
+        wallet
+            .data
+            .get_mut(&address)
+            .unwrap()
+            .sent_transactions
+            .insert(old_tx_hash, Default::default());
+        wallet.backup().unwrap();
+
+        let mut wallet = WalletOnMemory::read_index_from_file(wallet_file_path).unwrap();
+        let sink = Arc::new(Mutex::new(BTreeSet::new()));
+        wallet.set_sent_tx_hash_sink(Some(sink.clone()));
+        wallet.load_account(&address).unwrap();
+        assert!(wallet.newly_sent_tx_hashes().is_empty());
+        let new_tx_hash = TxHash(WrappedHashOut::rand());
+        wallet
+            .data
+            .get_mut(&address)
+            .unwrap()
+            .sent_transactions
+            .insert(new_tx_hash, Default::default());
+        assert_eq!(wallet.newly_sent_tx_hashes(), vec![new_tx_hash]);
+
+        // The transactions are reported only when they are written.
+        assert!(sink.lock().unwrap().is_empty());
+        wallet.backup().unwrap();
+        assert_eq!(
+            *sink.lock().unwrap(),
+            BTreeSet::from([new_tx_hash.to_string()])
+        );
+    }
+
+    #[test]
+    fn test_encrypted_wallet() {
+        let temp_dir = temp_dir();
+        let dir_path = temp_dir.path().join("wallet-encrypted");
+        std::fs::create_dir_all(&dir_path).unwrap();
+        let wallet_file_path = dir_path.join("wallet");
+        let mut wallet = make_fixture_wallet(wallet_file_path.clone()).unwrap();
+        let address = wallet.default_account.unwrap();
+        wallet.backup().unwrap();
+        let account_file_path = account_file_path(&wallet_file_path, &address);
+        let plaintext = std::fs::read_to_string(&account_file_path).unwrap();
+        assert!(!is_encrypted_file(&plaintext));
+
+        // A plaintext wallet is migrated by setting a password.
+        wallet.set_password(Some(Zeroizing::new("password".to_string())));
+        wallet.backup().unwrap();
+        assert!(is_encrypted_file(
+            &std::fs::read_to_string(&account_file_path).unwrap()
+        ));
+
+        let mut wallet = WalletOnMemory::read_index_from_file(wallet_file_path.clone()).unwrap();
+        assert!(wallet.is_locked());
+        assert!(wallet.load_account(&address).is_err());
+        assert!(wallet.backup().is_err());
+        assert!(wallet.unlock("wrong password".to_string()).is_err());
+        wallet.unlock("password".to_string()).unwrap();
+        wallet.load_account(&address).unwrap();
+        assert!(wallet.data.contains_key(&address));
+
+        wallet.set_password(None);
+        wallet.backup().unwrap();
+        let wallet = WalletOnMemory::read_from_file(wallet_file_path).unwrap();
+        assert!(!wallet.encrypted);
+        assert!(wallet.data.contains_key(&address));
+
+        // The private files are replaced at once, and only the owner can read them.
+        assert!(!tmp_file_path(&account_file_path).exists());
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let metadata = std::fs::metadata(&account_file_path).unwrap();
+            assert_eq!(metadata.permissions().mode() & 0o777, 0o600);
+        }
+
+        // A wallet without any account file still checks the password.
+        let empty_wallet_file_path = dir_path.join("empty_wallet").join("wallet");
+        WalletOnMemory::new(empty_wallet_file_path.clone(), "password".to_string())
+            .backup()
+            .unwrap();
+        let mut wallet = WalletOnMemory::read_index_from_file(empty_wallet_file_path).unwrap();
+        assert!(wallet.unlock("wrong password".to_string()).is_err());
+        assert!(wallet.is_locked());
+        wallet.unlock("password".to_string()).unwrap();
+    }
+
+    #[test]
+    fn test_export_account() {
+        let temp_dir = temp_dir();
+        let dir_path = temp_dir.path().join("wallet-export");
+        let wallet = make_fixture_wallet(dir_path.join("wallet")).unwrap();
+        let address = wallet.default_account.unwrap();
+        let asset_root = wallet.data[&address].asset_tree.get_root().unwrap();
+        let encoded_account =
+            serde_json::to_string(&wallet.export_account(&address).unwrap()).unwrap();
+
+        let mut other_wallet = WalletOnMemory::new(dir_path.join("other_wallet"), String::new());
+        other_wallet
+            .import_account(serde_json::from_str(&encoded_account).unwrap())
+            .unwrap();
+        let user_state = &other_wallet.data[&address];
+        assert_eq!(user_state.asset_tree.get_root().unwrap(), asset_root);
+        assert_eq!(user_state.assets.0, wallet.data[&address].assets.0);
+        assert!(other_wallet
+            .import_account(serde_json::from_str(&encoded_account).unwrap())
+            .is_err());
+
+        let mut raw: SerializableUserState = serde_json::from_str(&encoded_account).unwrap();
+        raw.account.address =
+            Account::new(HashOut::from_partial(&[F::from_canonical_u64(2)])).address;
+        assert!(
+            WalletOnMemory::new(dir_path.join("another_wallet"), String::new())
+                .import_account(raw)
+                .is_err()
+        );
+    }
+}
@@ -0,0 +1,126 @@
+//! Accounts derived from a BIP39 mnemonic, so that all of them can be recreated from the phrase
+//! even if the wallet is lost.
+//!
+//! The phrase and its seed follow BIP39 (English wordlist, no passphrase).
+//! BIP32 does not apply to intmax keys, which are Goldilocks field elements rather than secp256k1 scalars,
+//! so the private key of the account at `index` is `keccak256(DERIVATION_DOMAIN || seed || index)`
+//! read as a hash of four field elements. Changing this breaks the recovery of existing accounts.
+
+use std::io::BufRead;
+
+use dialoguer::Password;
+use intmax_interoperability_plugin::ethers::{
+    signers::coins_bip39::{English, Mnemonic},
+    utils::keccak256,
+};
+use intmax_rollup_interface::intmax_zkp_core::{
+    plonky2::plonk::config::{GenericConfig, PoseidonGoldilocksConfig},
+    sparse_merkle_tree::goldilocks_poseidon::WrappedHashOut,
+};
+use zeroize::Zeroizing;
+
+use crate::utils::terminal::TerminalOptions;
+
+const D: usize = 2;
+type C = PoseidonGoldilocksConfig;
+type F = <C as GenericConfig<D>>::F;
+
+const DERIVATION_DOMAIN: &[u8] = b"intmax/account/v1";
+
+/// The number of words of a generated mnemonic.
+pub const MNEMONIC_WORD_COUNT: usize = 24;
+
+/// Generate a new mnemonic.
+pub fn generate_mnemonic() -> anyhow::Result<Zeroizing<String>> {
+    let mnemonic =
+        Mnemonic::<English>::new_with_count(&mut rand::thread_rng(), MNEMONIC_WORD_COUNT)
+            .map_err(|err| anyhow::anyhow!("fail to generate a mnemonic: {err}"))?;
+    let phrase = mnemonic
+        .to_phrase()
+        .map_err(|err| anyhow::anyhow!("fail to generate a mnemonic: {err}"))?;
+
+    Ok(Zeroizing::new(phrase))
+}
+
+/// Returns the BIP39 seed of the phrase. It fails if a word or the checksum is wrong.
+pub fn mnemonic_to_seed(phrase: &str) -> anyhow::Result<Zeroizing<[u8; 64]>> {
+    // Extra spaces and capitals are common when the phrase is typed by hand.
+    let phrase = Zeroizing::new(
+        phrase
+            .split_whitespace()
+            .map(|word| word.to_lowercase())
+            .collect::<Vec<_>>()
+            .join(" "),
+    );
+    // The phrase itself is not included in the error messages.
+    let mnemonic = Mnemonic::<English>::new_from_phrase(&phrase)
+        .map_err(|_| anyhow::anyhow!("invalid mnemonic: check the words and their order"))?;
+    let seed = mnemonic
+        .to_seed(None)
+        .map_err(|_| anyhow::anyhow!("invalid mnemonic: check the words and their order"))?;
+
+    Ok(Zeroizing::new(seed))
+}
+
+/// Derive the private key of the account at `index`.
+pub fn derive_private_key(seed: &[u8; 64], index: u32) -> WrappedHashOut<F> {
+    let mut preimage = Zeroizing::new(Vec::with_capacity(DERIVATION_DOMAIN.len() + 64 + 4));
+    preimage.extend_from_slice(DERIVATION_DOMAIN);
+    preimage.extend_from_slice(seed);
+    preimage.extend_from_slice(&index.to_be_bytes());
+    let hash = Zeroizing::new(keccak256(&*preimage));
+
+    WrappedHashOut::from_bytes(&*hash)
+}
+
+/// Prompt the mnemonic, or read it from the first line of stdin in non-interactive mode.
+pub fn read_mnemonic(terminal: &TerminalOptions) -> anyhow::Result<Zeroizing<String>> {
+    if terminal.interactive {
+        let phrase = Password::with_theme(&*terminal.theme())
+            .with_prompt("Mnemonic")
+            .interact()?;
+
+        return Ok(Zeroizing::new(phrase));
+    }
+
+    let mut phrase = Zeroizing::new(String::new());
+    std::io::stdin().lock().read_line(&mut phrase)?;
+    if phrase.trim().is_empty() {
+        anyhow::bail!("mnemonic was not given from stdin");
+    }
+
+    Ok(phrase)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_derive_private_key() {
+        let phrase = generate_mnemonic().unwrap();
+        assert_eq!(phrase.split(' ').count(), MNEMONIC_WORD_COUNT);
+
+        let seed = mnemonic_to_seed(&phrase).unwrap();
+        let retyped_phrase = format!("  {}\n", phrase.to_uppercase().replace(' ', "  "));
+        assert_eq!(*mnemonic_to_seed(&retyped_phrase).unwrap(), *seed);
+        assert_eq!(derive_private_key(&seed, 0), derive_private_key(&seed, 0));
+        assert_ne!(derive_private_key(&seed, 0), derive_private_key(&seed, 1));
+
+        // the test vector of BIP39
+        let seed = mnemonic_to_seed("abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon about").unwrap();
+        assert_eq!(hex::encode(&seed[..4]), "5eb00bbd");
+
+        // The keys of the existing accounts must not change.
+        assert_eq!(
+            derive_private_key(&seed, 0).to_string(),
+            "0x8f5a616aa24d1d7b5ff0766e51c297cad30e509e193cfebb9813b7dbe3fff7c6"
+        );
+        assert_eq!(
+            derive_private_key(&seed, 1).to_string(),
+            "0x5ede38a27e017b5bb290eab7814b9790ded2578149d1773eb76d2abd7a6e1420"
+        );
+
+        assert!(mnemonic_to_seed(&["abandon"; 12].join(" ")).is_err());
+    }
+}
@@ -1,2 +1,40 @@
+use std::io::BufRead;
+
+use anyhow::Context;
+use intmax_rollup_interface::intmax_zkp_core::{
+    plonky2::plonk::config::{GenericConfig, PoseidonGoldilocksConfig},
+    sparse_merkle_tree::goldilocks_poseidon::WrappedHashOut,
+};
+use zeroize::Zeroizing;
+
+use super::hex_string::parse_hash_out;
+
+pub mod encryption;
 pub mod memory;
+pub mod mnemonic;
+pub mod recent_sends;
+pub mod secret;
+pub mod stats;
+pub mod storage;
 pub mod types;
+
+const D: usize = 2;
+type C = PoseidonGoldilocksConfig;
+type F = <C as GenericConfig<D>>::F;
+
+/// Read a private key from the first line of stdin.
+/// The buffer holding the key is zeroized when it is dropped.
+pub fn read_private_key_from_stdin() -> anyhow::Result<WrappedHashOut<F>> {
+    let mut encoded_private_key = Zeroizing::new(String::new());
+    std::io::stdin()
+        .lock()
+        .read_line(&mut encoded_private_key)?;
+    let encoded_private_key = encoded_private_key.trim();
+    if encoded_private_key.is_empty() {
+        anyhow::bail!("private key was not given from stdin");
+    }
+
+    // The key itself is not included in the error message.
+    parse_hash_out("private key", encoded_private_key)
+        .context("invalid private key was given from stdin")
+}
@@ -0,0 +1,109 @@
+use intmax_rollup_interface::intmax_zkp_core::{
+    plonky2::field::goldilocks_field::GoldilocksField,
+    transaction::asset::{ContributedAsset, TokenKind},
+    zkdsa::account::Address,
+};
+use serde::{Deserialize, Serialize};
+
+use super::types::TxHash;
+
+type F = GoldilocksField;
+
+/// How long `tx send` refuses to repeat the same transfer without `--allow-duplicate` by default, in seconds.
+pub const DEFAULT_DUPLICATE_WINDOW: u64 = 5 * 60;
+
+/// A transfer recently sent by `tx send`.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SendFingerprint {
+    /// UNIX time in seconds
+    pub timestamp: u64,
+    pub receiver_address: Address<F>,
+    pub kind: TokenKind<F>,
+    pub amount: u64,
+    /// `None` if the transaction was not signed yet, or if it was sent with other transfers
+    #[serde(default)]
+    pub tx_hash: Option<TxHash<F>>,
+}
+
+impl SendFingerprint {
+    fn matches(&self, asset: &ContributedAsset<F>) -> bool {
+        self.receiver_address == asset.receiver_address
+            && self.kind == asset.kind
+            && self.amount == asset.amount
+    }
+}
+
+/// The transfers sent by a user within the duplicate window, which are used to detect a repeated command.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct RecentSends(pub Vec<SendFingerprint>);
+
+impl RecentSends {
+    /// Returns the latest transfer of `asset` sent within `window` seconds before `now`.
+    pub fn find_duplicate(
+        &self,
+        asset: &ContributedAsset<F>,
+        now: u64,
+        window: u64,
+    ) -> Option<&SendFingerprint> {
+        self.0
+            .iter()
+            .rev()
+            .find(|sent| sent.matches(asset) && sent.timestamp + window > now)
+    }
+
+    /// Remember the sent transfers and forget the ones older than `window` seconds.
+    pub fn record(
+        &mut self,
+        assets: &[ContributedAsset<F>],
+        tx_hash: Option<TxHash<F>>,
+        now: u64,
+        window: u64,
+    ) {
+        self.0.retain(|sent| sent.timestamp + window > now);
+        for asset in assets {
+            self.0.push(SendFingerprint {
+                timestamp: now,
+                receiver_address: asset.receiver_address,
+                kind: asset.kind,
+                amount: asset.amount,
+                tx_hash,
+            });
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use super::*;
+
+    #[test]
+    fn test_recent_sends() {
+        let asset = ContributedAsset {
+            receiver_address: Address::from_str("0x714bdc6f38947e6d").unwrap(),
+            kind: TokenKind {
+                contract_address: Address::from_str("0x0000000000000001").unwrap(),
+                variable_index: 0u8.into(),
+            },
+            amount: 100,
+        };
+        let other_asset = ContributedAsset {
+            amount: 99,
+            ..asset.clone()
+        };
+
+        let mut recent_sends = RecentSends::default();
+        recent_sends.record(std::slice::from_ref(&asset), None, 1000, 300);
+        assert!(recent_sends.find_duplicate(&asset, 1299, 300).is_some());
+        assert!(recent_sends.find_duplicate(&asset, 1300, 300).is_none());
+        assert!(recent_sends
+            .find_duplicate(&other_asset, 1000, 300)
+            .is_none());
+        assert!(recent_sends.find_duplicate(&asset, 1000, 0).is_none());
+
+        recent_sends.record(std::slice::from_ref(&other_asset), None, 1300, 300);
+        assert_eq!(recent_sends.0.len(), 1);
+    }
+}
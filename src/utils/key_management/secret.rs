@@ -0,0 +1,52 @@
+use intmax_rollup_interface::intmax_zkp_core::plonky2::{
+    field::{
+        goldilocks_field::GoldilocksField,
+        types::{Field, PrimeField64},
+    },
+    hash::hash_types::HashOut,
+};
+use zeroize::Zeroizing;
+
+type F = GoldilocksField;
+
+/// The private key of an intmax account, which is zeroized when it is dropped.
+/// Keep it instead of a copied `Account` in long-lived threads and structs.
+pub struct SecretKey(Zeroizing<[u64; 4]>);
+
+impl SecretKey {
+    pub fn new(private_key: HashOut<F>) -> Self {
+        Self(Zeroizing::new(
+            private_key
+                .elements
+                .map(|element| element.to_canonical_u64()),
+        ))
+    }
+
+    /// Returns a temporary copy of the key, which should not be stored.
+    pub fn expose(&self) -> HashOut<F> {
+        HashOut {
+            elements: (*self.0).map(F::from_canonical_u64),
+        }
+    }
+}
+
+impl std::fmt::Debug for SecretKey {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "SecretKey(<redacted>)")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use intmax_rollup_interface::intmax_zkp_core::sparse_merkle_tree::goldilocks_poseidon::WrappedHashOut;
+
+    use super::*;
+
+    #[test]
+    fn test_secret_key() {
+        let private_key = *WrappedHashOut::<F>::rand();
+        let secret_key = SecretKey::new(private_key);
+        assert_eq!(secret_key.expose(), private_key);
+        assert_eq!(format!("{secret_key:?}"), "SecretKey(<redacted>)");
+    }
+}
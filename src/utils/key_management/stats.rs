@@ -0,0 +1,343 @@
+use std::collections::HashSet;
+
+use intmax_rollup_interface::intmax_zkp_core::{
+    plonky2::field::goldilocks_field::GoldilocksField,
+    rollup::gadgets::deposit_block::VariableIndex,
+    transaction::asset::{Asset, TokenKind},
+    zkdsa::account::Address,
+};
+use serde::{Deserialize, Serialize};
+
+use super::types::{serialize_sorted_set, MergeKey, TxHash};
+
+type F = GoldilocksField;
+
+pub const SECONDS_PER_DAY: u64 = 24 * 60 * 60;
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TransferDirection {
+    Sent,
+    Received,
+}
+
+impl std::fmt::Display for TransferDirection {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Sent => write!(f, "sent"),
+            Self::Received => write!(f, "received"),
+        }
+    }
+}
+
+/// The cumulative amount of a token kind transferred with a counterparty in a month.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct TransferTotal {
+    /// `YYYY-MM` in UTC
+    pub month: String,
+    pub direction: TransferDirection,
+    pub kind: TokenKind<F>,
+    /// `None` for deposits, or if the recipients of the sent transaction were not recorded.
+    pub counterparty: Option<Address<F>>,
+    pub amount: u128,
+    /// the number of transfers
+    pub count: u64,
+}
+
+/// A transfer counted in the totals, which is kept for `account statement`.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TransferRecord {
+    /// UNIX time in seconds
+    pub timestamp: u64,
+    pub direction: TransferDirection,
+    pub kind: TokenKind<F>,
+    pub counterparty: Option<Address<F>>,
+    pub amount: u64,
+    /// the transaction hash of a sent transfer, or the merge key of received assets
+    pub reference: String,
+    /// the block including received assets. `None` for sent transfers and the records made before it was introduced.
+    #[serde(default)]
+    pub block_number: Option<u32>,
+}
+
+/// Per-token transfer totals of a user, which are updated when the wallet is synchronized.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct TransferStats {
+    #[serde(default)]
+    pub totals: Vec<TransferTotal>,
+    /// the transfers counted in `totals` since this field was introduced, in the order of counting
+    #[serde(default)]
+    pub history: Vec<TransferRecord>,
+    /// sent transactions which were already counted
+    #[serde(default, serialize_with = "serialize_sorted_set")]
+    pub counted_tx_hashes: HashSet<TxHash<F>>,
+    /// received transactions and deposits which were already counted
+    #[serde(default, serialize_with = "serialize_sorted_set")]
+    pub counted_merge_keys: HashSet<MergeKey<F>>,
+}
+
+impl TransferStats {
+    /// Count the outputs of an approved transaction. Nothing happens if it was already counted.
+    pub fn record_sent(
+        &mut self,
+        tx_hash: TxHash<F>,
+        timestamp: u64,
+        outputs: &[(Option<Address<F>>, TokenKind<F>, u64)],
+    ) {
+        if !self.counted_tx_hashes.insert(tx_hash) {
+            return;
+        }
+
+        for (recipient, kind, amount) in outputs {
+            self.add(
+                timestamp,
+                TransferDirection::Sent,
+                *kind,
+                *recipient,
+                *amount,
+                tx_hash.to_string(),
+                None,
+            );
+        }
+    }
+
+    /// Count the received assets. Nothing happens if they were already counted.
+    pub fn record_received(
+        &mut self,
+        merge_key: MergeKey<F>,
+        timestamp: u64,
+        sender: Option<Address<F>>,
+        block_number: u32,
+        assets: &[Asset<F>],
+    ) {
+        if !self.counted_merge_keys.insert(merge_key) {
+            return;
+        }
+
+        for asset in assets {
+            self.add(
+                timestamp,
+                TransferDirection::Received,
+                asset.kind,
+                sender,
+                asset.amount,
+                merge_key.to_string(),
+                Some(block_number),
+            );
+        }
+    }
+
+    fn add(
+        &mut self,
+        timestamp: u64,
+        direction: TransferDirection,
+        kind: TokenKind<F>,
+        counterparty: Option<Address<F>>,
+        amount: u64,
+        reference: String,
+        block_number: Option<u32>,
+    ) {
+        self.history.push(TransferRecord {
+            timestamp,
+            direction,
+            kind,
+            counterparty,
+            amount,
+            reference,
+            block_number,
+        });
+
+        let month = format_month(timestamp);
+        let total = self.totals.iter_mut().find(|total| {
+            total.month == month
+                && total.direction == direction
+                && total.kind == kind
+                && total.counterparty == counterparty
+        });
+        if let Some(total) = total {
+            total.amount += amount as u128;
+            total.count += 1;
+        } else {
+            self.totals.push(TransferTotal {
+                month,
+                direction,
+                kind,
+                counterparty,
+                amount: amount as u128,
+                count: 1,
+            });
+        }
+    }
+
+    /// Returns the totals of the given token sorted by month, direction and counterparty.
+    /// `variable_index` is ignored if it is `None`.
+    pub fn filter(
+        &self,
+        contract_address: Option<Address<F>>,
+        variable_index: Option<VariableIndex<F>>,
+    ) -> Vec<&TransferTotal> {
+        let mut totals = self
+            .totals
+            .iter()
+            .filter(|total| {
+                contract_address
+                    .map(|contract_address| total.kind.contract_address == contract_address)
+                    .unwrap_or(true)
+                    && variable_index
+                        .map(|variable_index| total.kind.variable_index == variable_index)
+                        .unwrap_or(true)
+            })
+            .collect::<Vec<_>>();
+        totals.sort_by_key(|total| {
+            (
+                total.month.clone(),
+                total.direction == TransferDirection::Received,
+                total.kind.contract_address.to_string(),
+                total.kind.variable_index.to_string(),
+                total.counterparty.map(|address| address.to_string()),
+            )
+        });
+
+        totals
+    }
+}
+
+/// Format UNIX time in seconds as `YYYY-MM` in UTC.
+pub fn format_month(timestamp: u64) -> String {
+    let (year, month, _) = civil_from_timestamp(timestamp);
+
+    format!("{year:04}-{month:02}")
+}
+
+/// Format UNIX time in seconds as `YYYY-MM-DD` in UTC.
+pub fn format_date(timestamp: u64) -> String {
+    let (year, month, day) = civil_from_timestamp(timestamp);
+
+    format!("{year:04}-{month:02}-{day:02}")
+}
+
+/// Parse `YYYY-MM-DD` as UNIX time in seconds at the beginning of the day in UTC.
+pub fn parse_date(date: &str) -> anyhow::Result<u64> {
+    let invalid_date = || anyhow::anyhow!("invalid date: {date} (expected YYYY-MM-DD)");
+    let parts = date
+        .split('-')
+        .map(|part| part.parse::<i64>())
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|_| invalid_date())?;
+    if parts.len() != 3 {
+        return Err(invalid_date());
+    }
+    let (year, month, day) = (parts[0], parts[1], parts[2]);
+    if year < 1970 || !(1..=12).contains(&month) || !(1..=31).contains(&day) {
+        return Err(invalid_date());
+    }
+
+    // See http://howardhinnant.github.io/date_algorithms.html#days_from_civil
+    let shifted_year = if month <= 2 { year - 1 } else { year };
+    let era = shifted_year / 400;
+    let year_of_era = shifted_year - era * 400;
+    let shifted_month = if month > 2 { month - 3 } else { month + 9 };
+    let day_of_year = (153 * shifted_month + 2) / 5 + day - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    let days = era * 146097 + day_of_era - 719468;
+
+    let timestamp = days as u64 * SECONDS_PER_DAY;
+    // Reject days which do not exist, e.g., 2023-02-30.
+    if format_date(timestamp) != date {
+        return Err(invalid_date());
+    }
+
+    Ok(timestamp)
+}
+
+/// Returns the year, month and day of UNIX time in seconds in UTC.
+fn civil_from_timestamp(timestamp: u64) -> (i64, i64, i64) {
+    // See http://howardhinnant.github.io/date_algorithms.html#civil_from_days
+    let days = (timestamp / SECONDS_PER_DAY) as i64 + 719468;
+    let era = days / 146097;
+    let day_of_era = days - era * 146097;
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let shifted_month = (5 * day_of_year + 2) / 153;
+    let month = if shifted_month < 10 {
+        shifted_month + 3
+    } else {
+        shifted_month - 9
+    };
+    let day = day_of_year - (153 * shifted_month + 2) / 5 + 1;
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+
+    (year, month, day)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use intmax_rollup_interface::intmax_zkp_core::sparse_merkle_tree::goldilocks_poseidon::WrappedHashOut;
+
+    use super::*;
+
+    #[test]
+    fn test_format_month() {
+        assert_eq!(format_month(0), "1970-01");
+        // 2000-02-29T23:59:59Z
+        assert_eq!(format_month(951868799), "2000-02");
+        // 2000-03-01T00:00:00Z
+        assert_eq!(format_month(951868800), "2000-03");
+        // 2023-12-31T23:59:59Z
+        assert_eq!(format_month(1704067199), "2023-12");
+        assert_eq!(format_month(1704067200), "2024-01");
+    }
+
+    #[test]
+    fn test_parse_date() {
+        assert_eq!(parse_date("1970-01-01").unwrap(), 0);
+        assert_eq!(parse_date("2000-03-01").unwrap(), 951868800);
+        assert_eq!(parse_date("2024-01-01").unwrap(), 1704067200);
+        assert_eq!(format_date(1704067199), "2023-12-31");
+        assert!(parse_date("2023-02-29").is_err());
+        assert!(parse_date("2024-1-1").is_err());
+        assert!(parse_date("2024-01").is_err());
+    }
+
+    #[test]
+    fn test_transfer_stats() {
+        let token_address = Address::from_str("0x714bdc6f38947e6d").unwrap();
+        let kind = TokenKind {
+            contract_address: token_address,
+            variable_index: 0u8.into(),
+        };
+        let counterparty = Address::from_str("0x0000000000000001").unwrap();
+        let tx_hash = TxHash(WrappedHashOut::rand());
+        let merge_key = MergeKey(WrappedHashOut::rand());
+
+        let mut stats = TransferStats::default();
+        stats.record_sent(tx_hash, 1704067199, &[(Some(counterparty), kind, 10)]);
+        // The same transaction is counted once.
+        stats.record_sent(tx_hash, 1704067199, &[(Some(counterparty), kind, 10)]);
+        stats.record_sent(
+            TxHash(WrappedHashOut::rand()),
+            1704067100,
+            &[(Some(counterparty), kind, 5)],
+        );
+        stats.record_received(merge_key, 1704067200, None, 7, &[Asset { kind, amount: 3 }]);
+        stats.record_received(merge_key, 1704067200, None, 7, &[Asset { kind, amount: 3 }]);
+
+        let totals = stats.filter(Some(token_address), None);
+        assert_eq!(totals.len(), 2);
+        assert_eq!(totals[0].month, "2023-12");
+        assert_eq!(totals[0].direction, TransferDirection::Sent);
+        assert_eq!(totals[0].amount, 15);
+        assert_eq!(totals[0].count, 2);
+        assert_eq!(totals[1].month, "2024-01");
+        assert_eq!(totals[1].direction, TransferDirection::Received);
+        assert_eq!(totals[1].amount, 3);
+        assert!(stats.filter(Some(counterparty), None).is_empty());
+        assert!(stats.filter(None, Some(1u8.into())).is_empty());
+        assert_eq!(stats.history.len(), 3);
+        assert_eq!(stats.history[0].reference, tx_hash.to_string());
+        assert_eq!(stats.history[2].block_number, Some(7));
+    }
+}
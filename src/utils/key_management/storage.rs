@@ -0,0 +1,180 @@
+use intmax_rollup_interface::intmax_zkp_core::{
+    plonky2::field::goldilocks_field::GoldilocksField, zkdsa::account::Address,
+};
+use serde::Serialize;
+
+use super::memory::{SerializableUserState, WalletOnMemory};
+use crate::utils::human_format::format_size;
+
+type F = GoldilocksField;
+
+/// The wallet size in MiB above which a warning is displayed on load.
+pub const DEFAULT_WALLET_SIZE_WARNING: u64 = 50;
+
+const BYTES_PER_MIB: u64 = 1 << 20;
+
+/// The bytes of an account in the wallet file.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct AccountStorageStats {
+    pub address: Address<F>,
+    /// the proofs of the received assets which have not been merged yet
+    pub received_asset_proofs: usize,
+    pub sent_transactions: usize,
+    /// the rest of the account, e.g., the assets and the transfer stats
+    pub others: usize,
+}
+
+impl AccountStorageStats {
+    pub fn total(&self) -> usize {
+        self.received_asset_proofs + self.sent_transactions + self.others
+    }
+}
+
+/// The breakdown of the wallet file, which is estimated by encoding each part again.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct WalletStorageStats {
+    /// the size of the wallet file and the account files on disk
+    pub file_size: u64,
+    /// the nodes of the asset trees, which are shared by all accounts
+    pub tree_nodes: usize,
+    pub n_tree_nodes: usize,
+    /// sorted by the total in descending order
+    pub accounts: Vec<AccountStorageStats>,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum StorageCategory {
+    TreeNodes,
+    ReceivedAssetProofs,
+    SentTransactions,
+    Others,
+}
+
+impl StorageCategory {
+    /// What the user can do to reduce the bytes of this category.
+    pub fn remediation(&self) -> &'static str {
+        match self {
+            Self::ReceivedAssetProofs => {
+                "run `intmax tx merge` for the accounts with many unmerged assets, which drops their proofs"
+            }
+            Self::TreeNodes | Self::SentTransactions | Self::Others => {
+                "resync: write a backup with `intmax account reset --backup-first`, and add only the accounts in use again with `intmax account add --private-key-stdin`, which keeps only the latest state"
+            }
+        }
+    }
+}
+
+impl std::fmt::Display for StorageCategory {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::TreeNodes => write!(f, "asset tree nodes"),
+            Self::ReceivedAssetProofs => write!(f, "received asset proofs"),
+            Self::SentTransactions => write!(f, "sent transactions"),
+            Self::Others => write!(f, "other account data"),
+        }
+    }
+}
+
+fn encoded_len(value: &impl Serialize) -> anyhow::Result<usize> {
+    Ok(serde_json::to_vec(value)?.len())
+}
+
+impl WalletStorageStats {
+    pub fn calculate(wallet: &WalletOnMemory) -> anyhow::Result<Self> {
+        let file_size = wallet.storage_size();
+
+        let nodes = wallet
+            .nodes_db
+            .nodes
+            .lock()
+            .unwrap()
+            .clone()
+            .into_iter()
+            .collect::<Vec<_>>();
+
+        let mut accounts = vec![];
+        for (address, user_state) in wallet.data.iter() {
+            let mut raw = SerializableUserState::without_nodes(user_state);
+            let received_asset_proofs = encoded_len(&raw.rest_received_assets)?;
+            let sent_transactions = encoded_len(&raw.sent_transactions)?;
+            raw.rest_received_assets.clear();
+            raw.sent_transactions.clear();
+            accounts.push(AccountStorageStats {
+                address: *address,
+                received_asset_proofs,
+                sent_transactions,
+                others: encoded_len(&raw)?,
+            });
+        }
+        accounts.sort_by_key(|account| std::cmp::Reverse(account.total()));
+
+        Ok(Self {
+            file_size,
+            tree_nodes: encoded_len(&nodes)?,
+            n_tree_nodes: nodes.len(),
+            accounts,
+        })
+    }
+
+    pub fn total(&self, category: StorageCategory) -> usize {
+        match category {
+            StorageCategory::TreeNodes => self.tree_nodes,
+            StorageCategory::ReceivedAssetProofs => self
+                .accounts
+                .iter()
+                .map(|account| account.received_asset_proofs)
+                .sum(),
+            StorageCategory::SentTransactions => self
+                .accounts
+                .iter()
+                .map(|account| account.sent_transactions)
+                .sum(),
+            StorageCategory::Others => self.accounts.iter().map(|account| account.others).sum(),
+        }
+    }
+
+    /// Returns the categories sorted by their bytes in descending order.
+    pub fn largest_categories(&self) -> Vec<(StorageCategory, usize)> {
+        let mut categories = [
+            StorageCategory::TreeNodes,
+            StorageCategory::ReceivedAssetProofs,
+            StorageCategory::SentTransactions,
+            StorageCategory::Others,
+        ]
+        .into_iter()
+        .map(|category| (category, self.total(category)))
+        .collect::<Vec<_>>();
+        categories.sort_by_key(|(_, bytes)| std::cmp::Reverse(*bytes));
+
+        categories
+    }
+}
+
+/// Warn if the wallet file is larger than `threshold` MiB,
+/// with the remediation for the categories which take the most bytes.
+pub fn warn_wallet_size(wallet: &WalletOnMemory, threshold: u64) -> anyhow::Result<()> {
+    let file_size = wallet.storage_size();
+    if file_size <= threshold * BYTES_PER_MIB {
+        return Ok(());
+    }
+
+    eprintln!(
+        "WARNING: the wallet file is {}, which exceeds {threshold} MiB. Loading and saving it become slow.",
+        format_size(file_size)
+    );
+    let stats = WalletStorageStats::calculate(wallet)?;
+    for (category, bytes) in stats.largest_categories().into_iter().take(2) {
+        if bytes == 0 {
+            continue;
+        }
+
+        eprintln!(
+            "  {category} take {}: {}",
+            format_size(bytes as u64),
+            category.remediation()
+        );
+    }
+    eprintln!("  See `intmax account storage-stats` for the details.");
+
+    Ok(())
+}
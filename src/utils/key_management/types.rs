@@ -2,26 +2,96 @@ use std::{
     collections::{BTreeMap, HashSet},
     fmt::Debug,
     path::PathBuf,
+    str::FromStr,
 };
 
 use intmax_rollup_interface::intmax_zkp_core::{
     plonky2::{field::goldilocks_field::GoldilocksField, hash::hash_types::RichField},
-    sparse_merkle_tree::goldilocks_poseidon::{GoldilocksHashOut, WrappedHashOut},
+    sparse_merkle_tree::goldilocks_poseidon::WrappedHashOut,
     transaction::asset::TokenKind,
     zkdsa::account::Address,
 };
 use num_bigint::BigUint;
 use serde::{Deserialize, Serialize};
 
+use crate::utils::hex_string::strip_0x_prefix;
+
+macro_rules! define_hash_newtype {
+    ($(#[$meta:meta])* $name:ident) => {
+        $(#[$meta])*
+        #[derive(Copy, Clone, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+        #[serde(
+            transparent,
+            bound(
+                serialize = "WrappedHashOut<F>: Serialize",
+                deserialize = "WrappedHashOut<F>: Deserialize<'de>"
+            )
+        )]
+        #[repr(transparent)]
+        pub struct $name<F: RichField>(pub WrappedHashOut<F>);
+
+        impl<F: RichField> From<WrappedHashOut<F>> for $name<F> {
+            fn from(value: WrappedHashOut<F>) -> Self {
+                Self(value)
+            }
+        }
+
+        impl<F: RichField> From<$name<F>> for WrappedHashOut<F> {
+            fn from(value: $name<F>) -> Self {
+                value.0
+            }
+        }
+
+        impl<F: RichField> std::fmt::Display for $name<F>
+        where
+            WrappedHashOut<F>: std::fmt::Display,
+        {
+            fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+                std::fmt::Display::fmt(&self.0, f)
+            }
+        }
+
+        impl<F: RichField> FromStr for $name<F>
+        where
+            WrappedHashOut<F>: FromStr,
+        {
+            type Err = <WrappedHashOut<F> as FromStr>::Err;
+
+            /// The 0x-prefix is optional.
+            fn from_str(s: &str) -> Result<Self, Self::Err> {
+                WrappedHashOut::from_str(&format!("0x{}", strip_0x_prefix(s))).map(Self)
+            }
+        }
+    };
+}
+
+define_hash_newtype!(
+    /// The hash of a transaction, which is used to look up `SentTransaction`.
+    TxHash
+);
+
+define_hash_newtype!(
+    /// The key of the asset tree under which the assets received in one transaction or deposit are stored.
+    /// It equals the hash of the transaction, or the hash of the deposit and the block hash.
+    MergeKey
+);
+
+define_hash_newtype!(
+    /// The root of the asset tree of a user.
+    AssetRoot
+);
+
 /// 受け取った token を merge key とともに保管する構造体
 /// `(token_kind, amount, merge_key)` の集合
 #[derive(Clone, Debug, Default)]
 #[repr(transparent)]
-pub struct Assets<F: RichField>(pub HashSet<(TokenKind<F>, u64, WrappedHashOut<F>)>);
+pub struct Assets<F: RichField>(pub HashSet<(TokenKind<F>, u64, MergeKey<F>)>);
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
 #[repr(transparent)]
-pub struct SerializableAssets(pub Vec<(TokenKind<GoldilocksField>, u64, GoldilocksHashOut)>);
+pub struct SerializableAssets(
+    pub Vec<(TokenKind<GoldilocksField>, u64, MergeKey<GoldilocksField>)>,
+);
 
 impl From<SerializableAssets> for Assets<GoldilocksField> {
     fn from(value: SerializableAssets) -> Self {
@@ -42,11 +112,34 @@ impl<'de> Deserialize<'de> for Assets<GoldilocksField> {
 }
 
 impl From<Assets<GoldilocksField>> for SerializableAssets {
+    /// The assets are sorted, so that the same assets are always encoded into the same bytes.
     fn from(value: Assets<GoldilocksField>) -> Self {
-        Self(value.0.into_iter().collect::<Vec<_>>())
+        let mut assets = value.0.into_iter().collect::<Vec<_>>();
+        assets.sort_by_cached_key(|(kind, amount, merge_key)| {
+            (
+                kind.contract_address.to_string(),
+                kind.variable_index.to_string(),
+                *amount,
+                merge_key.to_string(),
+            )
+        });
+
+        Self(assets)
     }
 }
 
+/// Serialize a hash set as the list sorted by the encoded elements.
+pub fn serialize_sorted_set<T, S>(set: &HashSet<T>, serializer: S) -> Result<S::Ok, S::Error>
+where
+    T: Serialize + ToString,
+    S: serde::Serializer,
+{
+    let mut values = set.iter().collect::<Vec<_>>();
+    values.sort_by_cached_key(|value| value.to_string());
+
+    values.serialize(serializer)
+}
+
 impl Serialize for Assets<GoldilocksField> {
     fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
         let raw = SerializableAssets::from(self.clone());
@@ -56,10 +149,9 @@ impl Serialize for Assets<GoldilocksField> {
 }
 
 impl<F: RichField> Assets<F> {
-    // TODO: tx_hash ではなく merge_key
-    pub fn add(&mut self, kind: TokenKind<F>, amount: u64, tx_hash: WrappedHashOut<F>) {
-        // NOTICE: どの kind と tx_hash の組み合わせに対しても要素は高々一つ
-        self.0.insert((kind, amount, tx_hash));
+    pub fn add(&mut self, kind: TokenKind<F>, amount: u64, merge_key: MergeKey<F>) {
+        // NOTICE: どの kind と merge_key の組み合わせに対しても要素は高々一つ
+        self.0.insert((kind, amount, merge_key));
     }
 
     pub fn filter(&self, kind: TokenKind<F>) -> Self {
@@ -96,6 +188,163 @@ impl<F: RichField> Assets<F> {
     }
 }
 
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TransactionStatus {
+    /// The transaction has been sent but the block including it has not been seen yet.
+    #[default]
+    Pending,
+    Approved,
+    /// The transaction was canceled, e.g., because the proposal block was not signed in time.
+    Reverted,
+    /// The transaction was pending longer than `limits.pending_ttl`, so it is no longer signed.
+    /// It becomes approved if the block including it is seen later.
+    Expired,
+}
+
+impl std::fmt::Display for TransactionStatus {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Pending => write!(f, "pending"),
+            Self::Approved => write!(f, "approved"),
+            Self::Reverted => write!(f, "reverted"),
+            Self::Expired => write!(f, "expired"),
+        }
+    }
+}
+
+/// When the received assets are merged before sending a transaction.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MergePolicy {
+    /// Merge until the rest can be included in the transaction.
+    #[default]
+    Aggressive,
+    /// Merge only if the assets to send are not available without merging.
+    Lazy,
+    /// Never merge automatically. Run `tx merge` instead.
+    Manual,
+}
+
+impl std::fmt::Display for MergePolicy {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Aggressive => write!(f, "aggressive"),
+            Self::Lazy => write!(f, "lazy"),
+            Self::Manual => write!(f, "manual"),
+        }
+    }
+}
+
+impl FromStr for MergePolicy {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "aggressive" => Ok(Self::Aggressive),
+            "lazy" => Ok(Self::Lazy),
+            "manual" => Ok(Self::Manual),
+            _ => anyhow::bail!("unknown merge policy: {s} (choose aggressive, lazy or manual)"),
+        }
+    }
+}
+
+/// The record of a transaction sent by a user.
+#[allow(clippy::type_complexity)]
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct SentTransaction {
+    /// `(token_kind, amount, merge_key)` removed from the sender's asset tree
+    pub removed_assets: Vec<(TokenKind<GoldilocksField>, u64, MergeKey<GoldilocksField>)>,
+    pub proposed_block_number: Option<u32>,
+    /// UNIX time in seconds. `None` if the transaction was sent by v2.2.1-alpha or earlier.
+    #[serde(default)]
+    pub created_at: Option<u64>,
+    /// the total amount sent for each token kind
+    #[serde(default)]
+    pub total_amounts: Vec<(TokenKind<GoldilocksField>, u64)>,
+    #[serde(default)]
+    pub recipients: Vec<Address<GoldilocksField>>,
+    #[serde(default)]
+    pub status: TransactionStatus,
+    /// the amount sent to each recipient for each token kind
+    #[serde(default)]
+    pub outputs: Vec<(Address<GoldilocksField>, TokenKind<GoldilocksField>, u64)>,
+}
+
+impl SentTransaction {
+    /// Returns `true` if the transaction is still pending `ttl` seconds after it was created.
+    /// The transactions sent by v2.2.1-alpha or earlier never expire, since their age is unknown.
+    pub fn is_stale(&self, now: u64, ttl: u64) -> bool {
+        self.status == TransactionStatus::Pending
+            && self
+                .created_at
+                .map(|created_at| created_at.saturating_add(ttl) <= now)
+                .unwrap_or(false)
+    }
+
+    /// Returns `(recipient, token_kind, amount)` sent by the transaction.
+    /// The recipient is unknown for the transactions recorded before `outputs` was introduced,
+    /// unless they had only one recipient.
+    pub fn outputs_by_recipient(
+        &self,
+    ) -> Vec<(
+        Option<Address<GoldilocksField>>,
+        TokenKind<GoldilocksField>,
+        u64,
+    )> {
+        if !self.outputs.is_empty() {
+            return self
+                .outputs
+                .iter()
+                .map(|(recipient, kind, amount)| (Some(*recipient), *kind, *amount))
+                .collect();
+        }
+
+        let recipient = if self.recipients.len() == 1 {
+            Some(self.recipients[0])
+        } else {
+            None
+        };
+
+        self.total_amounts
+            .iter()
+            .map(|(kind, amount)| (recipient, *kind, *amount))
+            .collect()
+    }
+}
+
+/// `SentTransaction` written by v2.2.1-alpha or earlier is a tuple of
+/// `(removed_assets, proposed_block_number)`.
+#[allow(clippy::type_complexity)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum SerializableSentTransaction {
+    Record(SentTransaction),
+    Legacy(
+        Vec<(TokenKind<GoldilocksField>, u64, MergeKey<GoldilocksField>)>,
+        Option<u32>,
+    ),
+}
+
+impl From<SerializableSentTransaction> for SentTransaction {
+    fn from(value: SerializableSentTransaction) -> Self {
+        match value {
+            SerializableSentTransaction::Record(value) => value,
+            SerializableSentTransaction::Legacy(removed_assets, proposed_block_number) => Self {
+                removed_assets,
+                proposed_block_number,
+                ..Default::default()
+            },
+        }
+    }
+}
+
+impl From<SentTransaction> for SerializableSentTransaction {
+    fn from(value: SentTransaction) -> Self {
+        Self::Record(value)
+    }
+}
+
 pub trait Wallet {
     /// the type of passwords for accessing this wallet
     type Seed;
@@ -109,6 +358,9 @@ pub trait Wallet {
     /// Initialize the wallet with seed.
     fn new(wallet_file_path: PathBuf, seed: Self::Seed) -> Self;
 
+    /// Unlock the wallet read from a file with seed, which is needed to read and write its accounts.
+    fn unlock(&mut self, seed: Self::Seed) -> Result<(), Self::Error>;
+
     /// Store a account in a wallet.
     /// Panic if the address of the account was already used.
     fn add_account(&mut self, account: Self::Account) -> Result<(), Self::Error>;
@@ -121,3 +373,74 @@ pub trait Wallet {
     /// Fetch your default account.
     fn get_default_account(&self) -> Option<Address<GoldilocksField>>;
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_hash_newtype_serde() {
+        let value = WrappedHashOut::<GoldilocksField>::rand();
+        let encoded_value = serde_json::to_string(&value).unwrap();
+
+        // The wallet file written before the newtypes were introduced can be read.
+        assert_eq!(
+            serde_json::to_string(&TxHash(value)).unwrap(),
+            encoded_value
+        );
+        let tx_hash: TxHash<GoldilocksField> = serde_json::from_str(&encoded_value).unwrap();
+        assert_eq!(tx_hash.0, value);
+        let merge_key: MergeKey<GoldilocksField> = serde_json::from_str(&encoded_value).unwrap();
+        assert_eq!(merge_key.to_string(), value.to_string());
+
+        // The 0x-prefix is optional.
+        let encoded_value = value.to_string();
+        let tx_hash: TxHash<GoldilocksField> = strip_0x_prefix(&encoded_value).parse().unwrap();
+        assert_eq!(tx_hash.0, value);
+    }
+
+    #[test]
+    fn test_merge_policy() {
+        for merge_policy in [
+            MergePolicy::Aggressive,
+            MergePolicy::Lazy,
+            MergePolicy::Manual,
+        ] {
+            assert_eq!(
+                merge_policy.to_string().parse::<MergePolicy>().unwrap(),
+                merge_policy
+            );
+            assert_eq!(
+                serde_json::to_string(&merge_policy).unwrap(),
+                format!("\"{merge_policy}\"")
+            );
+        }
+        assert!("eager".parse::<MergePolicy>().is_err());
+    }
+
+    #[test]
+    fn test_stale_transaction() {
+        let mut sent_transaction = SentTransaction {
+            removed_assets: vec![],
+            proposed_block_number: None,
+            created_at: Some(1000),
+            total_amounts: vec![],
+            recipients: vec![],
+            status: TransactionStatus::Pending,
+            outputs: vec![],
+        };
+        assert!(!sent_transaction.is_stale(1099, 100));
+        assert!(sent_transaction.is_stale(1100, 100));
+
+        sent_transaction.status = TransactionStatus::Reverted;
+        assert!(!sent_transaction.is_stale(1100, 100));
+
+        sent_transaction.status = TransactionStatus::Pending;
+        sent_transaction.created_at = None;
+        assert!(!sent_transaction.is_stale(u64::MAX, 100));
+        assert_eq!(
+            serde_json::to_string(&TransactionStatus::Expired).unwrap(),
+            "\"expired\""
+        );
+    }
+}
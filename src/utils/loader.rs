@@ -0,0 +1,267 @@
+//! Decoding of the files written by this CLI.
+//! A corrupt or hand-edited file is reported with its path, the broken line and what to do about it.
+
+use std::path::{Path, PathBuf};
+
+use serde::de::DeserializeOwned;
+use serde_json::Value;
+use zeroize::Zeroizing;
+
+use super::migration::Schema;
+
+/// The maximum number of characters of the broken line included in the error.
+const MAX_SNIPPET_LEN: usize = 80;
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum LocalFileKind {
+    Wallet,
+    Nickname,
+    Config,
+    RegisterIntents,
+    BulkReport,
+    Signers,
+}
+
+impl std::fmt::Display for LocalFileKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Wallet => write!(f, "wallet"),
+            Self::Nickname => write!(f, "nickname"),
+            Self::Config => write!(f, "config"),
+            Self::RegisterIntents => write!(f, "register intents"),
+            Self::BulkReport => write!(f, "bulk report"),
+            Self::Signers => write!(f, "signers"),
+        }
+    }
+}
+
+impl LocalFileKind {
+    /// What the user can do if the file cannot be decoded.
+    pub fn remediation(&self) -> &'static str {
+        match self {
+            Self::Wallet => {
+                "fix the file by hand or restore it from a backup. `intmax account reset --backup-first` keeps the broken file in an encrypted backup and creates an empty wallet"
+            }
+            Self::Nickname => {
+                "fix the file by hand or restore it from a backup. Removing the file only drops your nicknames"
+            }
+            Self::Config => "fix the file with `intmax config edit`",
+            Self::RegisterIntents => {
+                "fix the file by hand. Removing it stops resuming the unfinished registrations"
+            }
+            Self::BulkReport => "fix the file by hand, or give the CSV again without --resume",
+            Self::Signers => {
+                "fix the file by hand. The encrypted keys are kept in the files named after the signers in the same directory"
+            }
+        }
+    }
+}
+
+/// A local file which cannot be decoded.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct LocalFileError {
+    pub kind: LocalFileKind,
+    pub file_path: PathBuf,
+    /// 1-based line and column in the file, which are unknown if the file is syntactically valid
+    pub position: Option<(usize, usize)>,
+    /// the broken line
+    pub snippet: Option<String>,
+    pub message: String,
+}
+
+impl std::fmt::Display for LocalFileError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "invalid {} file: {}",
+            self.kind,
+            self.file_path.display()
+        )?;
+        if let Some((line, column)) = self.position {
+            write!(f, " (line {line}, column {column})")?;
+        }
+        write!(f, "\n  {}", self.message)?;
+        if let Some(snippet) = &self.snippet {
+            write!(f, "\n  near: {snippet}")?;
+        }
+        write!(f, "\n  To recover, {}.", self.kind.remediation())
+    }
+}
+
+impl std::error::Error for LocalFileError {}
+
+fn get_snippet(encoded_value: &str, line: usize) -> Option<String> {
+    let snippet = encoded_value.lines().nth(line.checked_sub(1)?)?.trim();
+    if snippet.chars().count() > MAX_SNIPPET_LEN {
+        Some(snippet.chars().take(MAX_SNIPPET_LEN).collect::<String>() + "...")
+    } else {
+        Some(snippet.to_string())
+    }
+}
+
+impl LocalFileError {
+    fn new(kind: LocalFileKind, file_path: &Path, message: String) -> Self {
+        Self {
+            kind,
+            file_path: file_path.to_path_buf(),
+            position: None,
+            snippet: None,
+            message,
+        }
+    }
+
+    fn from_json_error(
+        kind: LocalFileKind,
+        file_path: &Path,
+        encoded_value: &str,
+        err: serde_json::Error,
+    ) -> Self {
+        let mut result = Self::new(kind, file_path, err.to_string());
+        if err.line() != 0 {
+            result.position = Some((err.line(), err.column()));
+            result.snippet = get_snippet(encoded_value, err.line());
+        }
+
+        result
+    }
+
+    /// The position of a field with an invalid value is not known from the decoded JSON value,
+    /// so the value is decoded again from its formatted text to find the broken field.
+    fn from_invalid_value<T: DeserializeOwned>(
+        kind: LocalFileKind,
+        file_path: &Path,
+        raw: &Value,
+        err: serde_json::Error,
+    ) -> Self {
+        let mut result = Self::new(kind, file_path, err.to_string());
+        if let Ok(formatted_value) = serde_json::to_string_pretty(raw) {
+            if let Err(err) = serde_json::from_str::<T>(&formatted_value) {
+                result.snippet = get_snippet(&formatted_value, err.line());
+            }
+        }
+
+        result
+    }
+}
+
+/// Decode `raw` after upgrading it to the current schema version.
+fn decode_value<T: DeserializeOwned>(
+    kind: LocalFileKind,
+    file_path: &Path,
+    mut raw: Value,
+    schema: Option<&Schema>,
+) -> Result<T, LocalFileError> {
+    if let Some(schema) = schema {
+        schema
+            .migrate(&mut raw)
+            .map_err(|err| LocalFileError::new(kind, file_path, format!("{err:#}")))?;
+    }
+
+    T::deserialize(&raw)
+        .map_err(|err| LocalFileError::from_invalid_value::<T>(kind, file_path, &raw, err))
+}
+
+/// Decode the JSON file read from `file_path`.
+pub fn decode_json_file<T: DeserializeOwned>(
+    kind: LocalFileKind,
+    file_path: &Path,
+    encoded_value: &str,
+    schema: Option<&Schema>,
+) -> Result<T, LocalFileError> {
+    let raw: Value = serde_json::from_str(encoded_value)
+        .map_err(|err| LocalFileError::from_json_error(kind, file_path, encoded_value, err))?;
+
+    decode_value(kind, file_path, raw, schema)
+}
+
+/// Decode the TOML file read from `file_path`.
+pub fn decode_toml_file<T: DeserializeOwned>(
+    kind: LocalFileKind,
+    file_path: &Path,
+    encoded_value: &str,
+    schema: Option<&Schema>,
+) -> Result<T, LocalFileError> {
+    let raw: Value = toml::from_str(encoded_value).map_err(|err| {
+        let mut result = LocalFileError::new(kind, file_path, err.to_string());
+        if let Some((line, column)) = err.line_col() {
+            result.position = Some((line + 1, column + 1));
+            result.snippet = get_snippet(encoded_value, line + 1);
+        }
+
+        result
+    })?;
+
+    decode_value(kind, file_path, raw, schema)
+}
+
+/// Read and decode the JSON file at `file_path`.
+pub fn read_json_file<T: DeserializeOwned>(
+    kind: LocalFileKind,
+    file_path: &Path,
+    schema: Option<&Schema>,
+) -> anyhow::Result<T> {
+    // The wallet file includes the private keys.
+    let encoded_value = Zeroizing::new(
+        std::fs::read_to_string(file_path)
+            .map_err(|err| anyhow::anyhow!("fail to read {}: {err}", file_path.display()))?,
+    );
+
+    Ok(decode_json_file(kind, file_path, &encoded_value, schema)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use serde::Deserialize;
+
+    use super::*;
+
+    #[derive(Debug, Deserialize)]
+    #[allow(dead_code)]
+    struct Example {
+        name: String,
+        amount: u64,
+    }
+
+    #[test]
+    fn test_decode_json_file() {
+        let file_path = Path::new("example.json");
+
+        let err = decode_json_file::<Example>(
+            LocalFileKind::BulkReport,
+            file_path,
+            "{\n  \"name\": \"a\",\n  \"amount\": 1,,\n}",
+            None,
+        )
+        .unwrap_err();
+        assert_eq!(err.position.map(|(line, _)| line), Some(3));
+        assert_eq!(err.snippet.as_deref(), Some("\"amount\": 1,,"));
+
+        let err = decode_json_file::<Example>(
+            LocalFileKind::BulkReport,
+            file_path,
+            r#"{"amount": "1"}"#,
+            None,
+        )
+        .unwrap_err();
+        assert_eq!(err.position, None);
+        assert_eq!(err.snippet.as_deref(), Some("\"amount\": \"1\""));
+        assert!(err.to_string().contains("example.json"));
+
+        let err = decode_toml_file::<Example>(
+            LocalFileKind::Config,
+            file_path,
+            "name = \"a\"\namount = =\n",
+            None,
+        )
+        .unwrap_err();
+        assert_eq!(err.position.map(|(line, _)| line), Some(2));
+
+        assert!(decode_json_file::<Example>(
+            LocalFileKind::BulkReport,
+            file_path,
+            r#"{"name": "a", "amount": 1}"#,
+            None
+        )
+        .is_ok());
+    }
+}
@@ -0,0 +1,188 @@
+use anyhow::Context;
+use serde_json::{json, Value};
+
+pub const SCHEMA_VERSION_KEY: &str = "schema_version";
+
+/// Upgrade a file from the schema version at its index to the next version.
+type Migration = fn(&mut Value) -> anyhow::Result<()>;
+
+/// The history of the schema of a file written by this CLI.
+/// Files without `schema_version` are regarded as version 0.
+pub struct Schema {
+    pub name: &'static str,
+    migrations: &'static [Migration],
+}
+
+pub const WALLET_SCHEMA: Schema = Schema {
+    name: "wallet",
+    migrations: &[migrate_wallet_from_v0, migrate_wallet_from_v1],
+};
+
+pub const NICKNAME_SCHEMA: Schema = Schema {
+    name: "nickname",
+    migrations: &[migrate_nickname_table_from_v0],
+};
+
+pub const CONFIG_SCHEMA: Schema = Schema {
+    name: "config",
+    migrations: &[migrate_config_from_v0],
+};
+
+impl Schema {
+    pub fn current_version(&self) -> u32 {
+        self.migrations.len() as u32
+    }
+
+    /// Returns the schema version of the given file.
+    pub fn get_version(&self, value: &Value) -> anyhow::Result<u32> {
+        match value.get(SCHEMA_VERSION_KEY) {
+            Some(version) => version
+                .as_u64()
+                .map(|version| version as u32)
+                .with_context(|| format!("invalid {SCHEMA_VERSION_KEY} of {} file", self.name)),
+            None => Ok(0),
+        }
+    }
+
+    /// Upgrade the file step by step to the current schema version.
+    /// Files written by a newer version of this CLI are refused.
+    pub fn migrate(&self, value: &mut Value) -> anyhow::Result<()> {
+        let version = self.get_version(value)?;
+        let current_version = self.current_version();
+        if version > current_version {
+            anyhow::bail!(
+                "{} file was written by a newer version of this CLI (schema version {version}, but up to {current_version} is supported). Update this CLI by following https://github.com/InternetMaximalism/intmax-rollup-cli#update .",
+                self.name
+            );
+        }
+
+        for (i, migration) in self.migrations[version as usize..].iter().enumerate() {
+            let from_version = version as usize + i;
+            migration(value).with_context(|| {
+                format!(
+                    "fail to migrate {} file from schema version {from_version}",
+                    self.name
+                )
+            })?;
+        }
+
+        value
+            .as_object_mut()
+            .with_context(|| format!("{} file must be a JSON object", self.name))?
+            .insert(SCHEMA_VERSION_KEY.to_string(), json!(current_version));
+
+        Ok(())
+    }
+}
+
+/// v0 has the optional fields added after the first release
+/// and the sent transactions written as `[removed_assets, proposed_block_number]`.
+fn migrate_wallet_from_v0(wallet: &mut Value) -> anyhow::Result<()> {
+    let wallet = wallet.as_object_mut().context("not a JSON object")?;
+    wallet.entry("default_account").or_insert(Value::Null);
+    wallet.entry("nodes").or_insert_with(|| json!([]));
+
+    let user_states = wallet
+        .get_mut("data")
+        .and_then(|data| data.as_array_mut())
+        .context("`data` must be an array")?;
+    for user_state in user_states {
+        let user_state = user_state
+            .as_object_mut()
+            .context("user state must be a JSON object")?;
+        user_state
+            .entry("asset_tree_nodes")
+            .or_insert_with(|| json!([]));
+        user_state
+            .entry("last_seen_block_number")
+            .or_insert_with(|| json!(0));
+        user_state
+            .entry("rest_received_assets")
+            .or_insert_with(|| json!([]));
+
+        let sent_transactions = user_state
+            .entry("sent_transactions")
+            .or_insert_with(|| json!([]))
+            .as_array_mut()
+            .context("`sent_transactions` must be an array")?;
+        for sent_transaction in sent_transactions {
+            let record = sent_transaction
+                .get_mut(1)
+                .context("sent transaction must be a pair of tx hash and record")?;
+            if let Value::Array(legacy_record) = record {
+                if legacy_record.len() != 2 {
+                    anyhow::bail!("invalid sent transaction");
+                }
+
+                let new_record = json!({
+                    "removed_assets": legacy_record[0],
+                    "proposed_block_number": legacy_record[1],
+                });
+                *record = new_record;
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// v1 has every account in `data`. Since v2, the accounts may be stored in their own files,
+/// which are listed in `accounts`.
+fn migrate_wallet_from_v1(wallet: &mut Value) -> anyhow::Result<()> {
+    let wallet = wallet.as_object_mut().context("not a JSON object")?;
+    wallet.entry("accounts").or_insert_with(|| json!([]));
+
+    Ok(())
+}
+
+/// v0 is an array of `[address, nickname]`.
+fn migrate_nickname_table_from_v0(nickname_table: &mut Value) -> anyhow::Result<()> {
+    if nickname_table.is_array() {
+        *nickname_table = json!({ "nicknames": nickname_table.take() });
+    }
+
+    Ok(())
+}
+
+/// v0 is the same as v1 except for `schema_version`.
+fn migrate_config_from_v0(_config: &mut Value) -> anyhow::Result<()> {
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_migrate() {
+        let mut wallet = json!({
+            "data": [{
+                "sent_transactions": [["0x01", [[], 3]]],
+            }],
+        });
+        WALLET_SCHEMA.migrate(&mut wallet).unwrap();
+        assert_eq!(
+            WALLET_SCHEMA.get_version(&wallet).unwrap(),
+            WALLET_SCHEMA.current_version()
+        );
+        assert_eq!(wallet["default_account"], Value::Null);
+        assert_eq!(wallet["accounts"], json!([]));
+        assert_eq!(wallet["data"][0]["last_seen_block_number"], json!(0));
+        assert_eq!(
+            wallet["data"][0]["sent_transactions"][0][1],
+            json!({ "removed_assets": [], "proposed_block_number": 3 })
+        );
+
+        // The migrated file is not changed any more.
+        let migrated_wallet = wallet.clone();
+        WALLET_SCHEMA.migrate(&mut wallet).unwrap();
+        assert_eq!(wallet, migrated_wallet);
+
+        let mut nickname_table = json!([["0x01", "alice"]]);
+        NICKNAME_SCHEMA.migrate(&mut nickname_table).unwrap();
+        assert_eq!(nickname_table["nicknames"], json!([["0x01", "alice"]]));
+
+        let mut config = json!({ "schema_version": CONFIG_SCHEMA.current_version() + 1 });
+        assert!(CONFIG_SCHEMA.migrate(&mut config).is_err());
+    }
+}
@@ -1,3 +1,26 @@
+pub mod aggregator_url;
+pub mod audit;
+pub mod backup;
+pub mod bech32;
+pub mod config;
+pub mod config_env;
+pub mod debug_format;
+#[cfg(any(test, feature = "dev"))]
+pub mod fixtures;
+pub mod hex_string;
+pub mod human_format;
 pub mod key_management;
+pub mod loader;
+pub mod migration;
 pub mod nickname;
+pub mod proof_format;
+pub mod prover;
+pub mod qr;
+pub mod short_address;
+pub mod signer;
+pub mod terminal;
+#[cfg(test)]
+pub mod test_utils;
 pub mod version;
+pub mod wallet_dir;
+pub mod worker;
@@ -9,8 +9,17 @@ use intmax_rollup_interface::intmax_zkp_core::{
 };
 use serde::{Deserialize, Serialize};
 
+use super::migration::NICKNAME_SCHEMA;
+
+pub mod store;
+
 type F = GoldilocksField;
 
+/// `--token-address mine` means the token issued by the sender.
+pub const TOKEN_ADDRESS_MINE: &str = "mine";
+/// `--token-address receiver` means the token issued by the recipient.
+pub const TOKEN_ADDRESS_RECEIVER: &str = "receiver";
+
 #[derive(Clone, Debug, Default)]
 pub struct NicknameTable {
     pub address_to_nickname: HashMap<Address<F>, String>,
@@ -18,14 +27,16 @@ pub struct NicknameTable {
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
-#[repr(transparent)]
-pub struct SerializableNicknameTable(#[serde(default)] pub Vec<(Address<F>, String)>);
+pub struct SerializableNicknameTable {
+    pub schema_version: u32,
+    pub nicknames: Vec<(Address<F>, String)>,
+}
 
 impl From<SerializableNicknameTable> for NicknameTable {
     fn from(value: SerializableNicknameTable) -> Self {
         let mut address_to_nickname = HashMap::new();
         let mut nickname_to_address = BTreeMap::new();
-        for (address, nickname) in value.0 {
+        for (address, nickname) in value.nicknames {
             address_to_nickname.insert(address, nickname.clone());
             nickname_to_address.insert(nickname.clone(), address);
         }
@@ -39,7 +50,11 @@ impl From<SerializableNicknameTable> for NicknameTable {
 
 impl<'de> Deserialize<'de> for NicknameTable {
     fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
-        let raw = SerializableNicknameTable::deserialize(deserializer)?;
+        let mut raw = serde_json::Value::deserialize(deserializer)?;
+        NICKNAME_SCHEMA
+            .migrate(&mut raw)
+            .map_err(serde::de::Error::custom)?;
+        let raw = SerializableNicknameTable::deserialize(raw).map_err(serde::de::Error::custom)?;
 
         Ok(raw.into())
     }
@@ -52,7 +67,10 @@ impl From<NicknameTable> for SerializableNicknameTable {
             nickname_list.push((address, nickname));
         }
 
-        Self(nickname_list)
+        Self {
+            schema_version: NICKNAME_SCHEMA.current_version(),
+            nicknames: nickname_list,
+        }
     }
 }
 
@@ -161,3 +179,38 @@ pub fn nickname_to_address(nickname_table: &NicknameTable, nickname: &str) -> Op
         nickname_table.nickname_to_address.get(nickname).copied()
     }
 }
+
+/// Returns the nickname corresponding to the given address.
+pub fn address_to_nickname(nickname_table: &NicknameTable, address: &Address<F>) -> Option<String> {
+    let reserved_nickname_table = ReservedNicknameTable::new();
+    if let Some(nickname) = reserved_nickname_table.address_to_nickname.get(address) {
+        Some(nickname.clone())
+    } else {
+        nickname_table.address_to_nickname.get(address).cloned()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_resolve_nickname() {
+        let address = Address::from_str("0x714bdc6f38947e6d").unwrap();
+        let mut nickname_table = NicknameTable::default();
+        nickname_table.insert(address, "alice".to_string()).unwrap();
+
+        assert_eq!(nickname_to_address(&nickname_table, "alice"), Some(address));
+        assert_eq!(
+            address_to_nickname(&nickname_table, &address),
+            Some("alice".to_string())
+        );
+        assert_eq!(nickname_to_address(&nickname_table, "bob"), None);
+
+        // The reserved nicknames are resolved in both directions.
+        let scroll_address = nickname_to_address(&nickname_table, "scroll").unwrap();
+        assert!(address_to_nickname(&nickname_table, &scroll_address)
+            .unwrap()
+            .starts_with("scroll"));
+    }
+}
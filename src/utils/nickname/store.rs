@@ -0,0 +1,160 @@
+use std::{
+    io::Write,
+    path::{Path, PathBuf},
+    time::SystemTime,
+};
+
+use anyhow::Context;
+use intmax_rollup_interface::intmax_zkp_core::{
+    plonky2::field::goldilocks_field::GoldilocksField, zkdsa::account::Address,
+};
+
+use super::{NicknameTable, SerializableNicknameTable};
+use crate::utils::{
+    loader::{read_json_file, LocalFileKind},
+    migration::NICKNAME_SCHEMA,
+};
+
+type F = GoldilocksField;
+
+pub const NICKNAME_FILE_NAME: &str = "nickname";
+
+/// The nickname file of a wallet, which is read once and written only if the table was changed.
+/// The table is looked up through `Deref`.
+#[derive(Debug)]
+pub struct NicknameStore {
+    file_path: PathBuf,
+    table: NicknameTable,
+    /// the modification time of the file when it was read or written, `None` if it did not exist
+    modified: Option<SystemTime>,
+    dirty: bool,
+}
+
+fn get_modified_time(file_path: &Path) -> anyhow::Result<Option<SystemTime>> {
+    match std::fs::metadata(file_path) {
+        Ok(metadata) => Ok(Some(metadata.modified()?)),
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => Ok(None),
+        Err(err) => Err(err).with_context(|| format!("fail to read {}", file_path.display())),
+    }
+}
+
+/// Replace the file at once, so that other commands never read a half-written table.
+fn write_nickname_file(file_path: &Path, table: &NicknameTable) -> anyhow::Result<()> {
+    if let Some(dir_path) = file_path.parent() {
+        std::fs::create_dir_all(dir_path)?;
+    }
+    let encoded_table = serde_json::to_string(table)?;
+    let tmp_file_path = file_path.with_extension("tmp");
+    let mut file = std::fs::File::create(&tmp_file_path)?;
+    write!(file, "{}", encoded_table)?;
+    file.flush()?;
+    std::fs::rename(&tmp_file_path, file_path)
+        .with_context(|| format!("fail to write {}", file_path.display()))
+}
+
+impl NicknameStore {
+    /// Read the nickname file. The table is empty if the file does not exist.
+    pub fn open(file_path: PathBuf) -> anyhow::Result<Self> {
+        let modified = get_modified_time(&file_path)?;
+        let table = if modified.is_some() {
+            let raw: SerializableNicknameTable =
+                read_json_file(LocalFileKind::Nickname, &file_path, Some(&NICKNAME_SCHEMA))?;
+
+            raw.into()
+        } else {
+            NicknameTable::default()
+        };
+
+        Ok(Self {
+            file_path,
+            table,
+            modified,
+            dirty: false,
+        })
+    }
+
+    /// Overwrite the nickname file with an empty table.
+    pub fn reset(file_path: PathBuf) -> anyhow::Result<Self> {
+        write_nickname_file(&file_path, &NicknameTable::default())?;
+
+        Self::open(file_path)
+    }
+
+    pub fn insert(&mut self, address: Address<F>, nickname: String) -> anyhow::Result<()> {
+        self.table.insert(address, nickname)?;
+        self.dirty = true;
+
+        Ok(())
+    }
+
+    pub fn remove(&mut self, nickname: String) -> anyhow::Result<()> {
+        self.table.remove(nickname)?;
+        self.dirty = true;
+
+        Ok(())
+    }
+
+    /// Write the changed table. Fails without writing if another command changed the file after it was read,
+    /// since the changes of the command would be lost.
+    pub fn flush(&mut self) -> anyhow::Result<()> {
+        if !self.dirty {
+            return Ok(());
+        }
+
+        if get_modified_time(&self.file_path)? != self.modified {
+            anyhow::bail!(
+                "{} was changed by another command. Run the command again",
+                self.file_path.display()
+            );
+        }
+
+        write_nickname_file(&self.file_path, &self.table)?;
+        self.modified = get_modified_time(&self.file_path)?;
+        self.dirty = false;
+
+        Ok(())
+    }
+}
+
+impl std::ops::Deref for NicknameStore {
+    type Target = NicknameTable;
+
+    fn deref(&self) -> &Self::Target {
+        &self.table
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use super::*;
+    use crate::utils::test_utils::temp_dir;
+
+    #[test]
+    fn test_nickname_store() {
+        let temp_dir = temp_dir();
+        let dir_path = temp_dir.path().join("nickname-store");
+        let file_path = dir_path.join(NICKNAME_FILE_NAME);
+        let address = Address::from_str("0x714bdc6f38947e6d").unwrap();
+
+        let mut store = NicknameStore::open(file_path.clone()).unwrap();
+        assert!(store.nickname_to_address.is_empty());
+        store.insert(address, "alice".to_string()).unwrap();
+        assert!(!file_path.exists());
+        store.flush().unwrap();
+
+        let mut other_store = NicknameStore::open(file_path.clone()).unwrap();
+        assert_eq!(other_store.nickname_to_address.get("alice"), Some(&address));
+
+        // The file written by another command is not overwritten.
+        std::thread::sleep(std::time::Duration::from_millis(10));
+        store.remove("alice".to_string()).unwrap();
+        store.flush().unwrap();
+        other_store.insert(address, "bob".to_string()).unwrap();
+        assert!(other_store.flush().is_err());
+
+        let store = NicknameStore::reset(file_path).unwrap();
+        assert!(store.nickname_to_address.is_empty());
+    }
+}
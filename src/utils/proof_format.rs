@@ -0,0 +1,168 @@
+//! The versioned JSON format of the proofs exported by this CLI, which can be checked by
+//! `intmax proof verify` or by third-party tools.
+//!
+//! ```json
+//! { "format": "intmax-proof", "version": 1, "type": "<proof type>", "proof": { ... } }
+//! ```
+//!
+//! - `inclusion`: the proof that a transaction sent an asset to a recipient, written by `account transaction-proof`.
+//!   `tx_hash` is a Poseidon hash in 32-byte hex, `recipient` and `token_address` are 32-byte and 20-byte hex,
+//!   `token_id` and `amount` are `uint256` in hex, and `witness` is the ABI-encoded witness
+//!   accepted by `verifyAssets` of the verifier contract.
+//! - `header`: the proof that a block extends the header chain, written by `block header-proof`.
+//!   Block headers and Merkle proofs are encoded as in the aggregator API.
+//! - `possession`: reserved for the possession proof of assets.
+//!
+//! All hex strings are `0x`-prefixed and in lower case.
+
+use std::str::FromStr;
+
+use anyhow::Context;
+use intmax_interoperability_plugin::ethers::types::{Bytes, H160, H256, U256};
+use intmax_rollup_interface::intmax_zkp_core::{
+    plonky2::plonk::config::{GenericConfig, PoseidonGoldilocksConfig},
+    sparse_merkle_tree::goldilocks_poseidon::WrappedHashOut,
+    zkdsa::account::Address,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::service::header_proof::BlockHeaderProof;
+
+const D: usize = 2;
+type C = PoseidonGoldilocksConfig;
+type F = <C as GenericConfig<D>>::F;
+
+pub const PROOF_FORMAT_NAME: &str = "intmax-proof";
+
+/// The latest version of the format. A file of a newer version is not read.
+pub const PROOF_FORMAT_VERSION: u32 = 1;
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct AssetInclusionProof {
+    pub tx_hash: WrappedHashOut<F>,
+    pub recipient: H256,
+    pub token_address: H160,
+    pub token_id: U256,
+    pub amount: U256,
+    pub witness: Bytes,
+}
+
+/// Returns `recipient` of an inclusion proof for the intmax address, i.e., the hash of the address.
+pub fn encode_recipient(address: Address<F>) -> H256 {
+    H256::from_str(&WrappedHashOut::from(address.to_hash_out()).to_string()[2..]).unwrap()
+}
+
+/// Returns `token_address` of an inclusion proof for the intmax token address,
+/// i.e., the last 20 bytes of the hash of the address.
+pub fn encode_token_address(contract_address: Address<F>) -> H160 {
+    encode_recipient(contract_address).into()
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(tag = "type", content = "proof", rename_all = "kebab-case")]
+pub enum ProofArtifact {
+    Inclusion(AssetInclusionProof),
+    Header(BlockHeaderProof),
+}
+
+impl ProofArtifact {
+    pub fn type_name(&self) -> &'static str {
+        match self {
+            Self::Inclusion(_) => "inclusion",
+            Self::Header(_) => "header",
+        }
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+struct ProofEnvelope {
+    format: String,
+    version: u32,
+    #[serde(flatten)]
+    proof: ProofArtifact,
+}
+
+/// Encode the proof with the envelope of the latest version.
+pub fn encode_proof_artifact(proof: &ProofArtifact) -> anyhow::Result<String> {
+    let envelope = ProofEnvelope {
+        format: PROOF_FORMAT_NAME.to_string(),
+        version: PROOF_FORMAT_VERSION,
+        proof: proof.clone(),
+    };
+
+    Ok(serde_json::to_string_pretty(&envelope)?)
+}
+
+/// Decode a proof file and detect its type.
+/// A header proof written by an older CLI without the envelope is also accepted.
+pub fn decode_proof_artifact(encoded_proof: &str) -> anyhow::Result<ProofArtifact> {
+    let value: serde_json::Value =
+        serde_json::from_str(encoded_proof).context("the proof file is not JSON")?;
+
+    if value.get("format").is_none() {
+        let proof = serde_json::from_value::<BlockHeaderProof>(value)
+            .context("unknown proof file: the format field is missing")?;
+
+        return Ok(ProofArtifact::Header(proof));
+    }
+    let format = &value["format"];
+    if format.as_str() != Some(PROOF_FORMAT_NAME) {
+        anyhow::bail!("unknown proof format: {format}");
+    }
+
+    let version = value
+        .get("version")
+        .and_then(|version| version.as_u64())
+        .context("the version of the proof file is missing")?;
+    if version == 0 || version > PROOF_FORMAT_VERSION as u64 {
+        anyhow::bail!(
+            "proof format version {version} is not supported. Update this CLI to read it"
+        );
+    }
+
+    match value.get("type").and_then(|proof_type| proof_type.as_str()) {
+        Some("possession") => anyhow::bail!("possession proofs are an upcoming feature"),
+        Some(_) => {}
+        None => anyhow::bail!("the type of the proof file is missing"),
+    }
+
+    let envelope: ProofEnvelope =
+        serde_json::from_value(value).context("the proof file is broken")?;
+
+    Ok(envelope.proof)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_proof_format() {
+        let proof = AssetInclusionProof {
+            tx_hash: WrappedHashOut::rand(),
+            recipient: H256::repeat_byte(1),
+            token_address: H160::repeat_byte(2),
+            token_id: 0.into(),
+            amount: 100.into(),
+            witness: Bytes::from(vec![0xab, 0xcd]),
+        };
+        let encoded_proof =
+            encode_proof_artifact(&ProofArtifact::Inclusion(proof.clone())).unwrap();
+        let value: serde_json::Value = serde_json::from_str(&encoded_proof).unwrap();
+        assert_eq!(value["format"], PROOF_FORMAT_NAME);
+        assert_eq!(value["version"], PROOF_FORMAT_VERSION);
+        assert_eq!(value["type"], "inclusion");
+        assert_eq!(value["proof"]["witness"], "0xabcd");
+
+        match decode_proof_artifact(&encoded_proof).unwrap() {
+            ProofArtifact::Inclusion(decoded_proof) => assert_eq!(decoded_proof, proof),
+            decoded_proof => panic!("unexpected proof type: {}", decoded_proof.type_name()),
+        }
+
+        let newer_proof = encoded_proof.replace("\"version\": 1", "\"version\": 2");
+        assert!(decode_proof_artifact(&newer_proof).is_err());
+        let possession_proof = encoded_proof.replace("\"inclusion\"", "\"possession\"");
+        assert!(decode_proof_artifact(&possession_proof).is_err());
+        assert!(decode_proof_artifact("{}").is_err());
+    }
+}
@@ -0,0 +1,236 @@
+use std::{fs::File, io::Write, path::Path, sync::Mutex, time::Duration};
+
+use anyhow::Context;
+use intmax_rollup_interface::constants::ROLLUP_CONSTANTS;
+use serde::{Deserialize, Serialize};
+
+pub const PROVING_TIMES_FILE_NAME: &str = "proving_times.json";
+
+/// The number of recent measurements used to estimate the proving time.
+const MAX_PROVING_TIMES: usize = 20;
+
+/// Rough memory usage of proving a user transaction, which is independent of the number of threads.
+const BASE_PROVING_MEMORY_MIB: u64 = 512;
+
+/// Rough memory usage of proving for each merge or diff of a user transaction.
+const PROVING_MEMORY_PER_WITNESS_MIB: u64 = 32;
+
+/// Rough memory usage of each proving thread.
+const PROVING_MEMORY_PER_THREAD_MIB: u64 = 256;
+
+/// The config applied when the first circuit is built, so that the commands which never prove are not affected.
+static PENDING_PROVER_CONFIG: Mutex<Option<ProverConfig>> = Mutex::new(None);
+
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ProverConfig {
+    /// the number of threads used for proving
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub threads: Option<usize>,
+    /// upper limit of memory used for proving in MiB
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub memory_budget: Option<u64>,
+    /// the prover service which generates the user transaction proofs. See `prover::backend`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub url: Option<String>,
+}
+
+/// Returns the estimated memory usage in MiB of proving a user transaction with `threads` threads.
+pub fn estimate_proving_memory(threads: usize) -> u64 {
+    let n_witnesses = (ROLLUP_CONSTANTS.n_merges + ROLLUP_CONSTANTS.n_diffs) as u64;
+
+    BASE_PROVING_MEMORY_MIB
+        + PROVING_MEMORY_PER_WITNESS_MIB * n_witnesses
+        + PROVING_MEMORY_PER_THREAD_MIB * threads as u64
+}
+
+impl ProverConfig {
+    pub fn validate(&self) -> anyhow::Result<()> {
+        if self.threads == Some(0) {
+            anyhow::bail!("the number of proving threads must be a positive integer");
+        }
+
+        if self.memory_budget == Some(0) {
+            anyhow::bail!("proving memory budget must be a positive integer");
+        }
+
+        if let Some(url) = &self.url {
+            if !url.starts_with("http://") && !url.starts_with("https://") {
+                anyhow::bail!("prover URL must start with http:// or https://");
+            }
+            if cfg!(not(feature = "remote-prover")) {
+                anyhow::bail!("prover.url requires the remote-prover feature. Build with `--features remote-prover`");
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Returns the number of proving threads which fits in the memory budget.
+    /// If the budget is too small for the requested threads, fewer threads are used.
+    /// The memory usage is a rough estimate, so a budget below it for one thread only warns.
+    pub fn calc_threads(&self) -> usize {
+        let max_threads = self.threads.unwrap_or_else(|| {
+            std::thread::available_parallelism()
+                .map(|n| n.get())
+                .unwrap_or(1)
+        });
+        let memory_budget = if let Some(memory_budget) = self.memory_budget {
+            memory_budget
+        } else {
+            return max_threads;
+        };
+
+        let required_memory = estimate_proving_memory(1);
+        if memory_budget < required_memory {
+            eprintln!(
+                "WARNING: proving memory budget of {memory_budget} MiB is below the rough estimate of {required_memory} MiB for the current rollup constants. Proving with one thread"
+            );
+
+            return 1;
+        }
+
+        let threads = (1..=max_threads)
+            .rev()
+            .find(|threads| estimate_proving_memory(*threads) <= memory_budget)
+            .unwrap_or(1);
+        if threads < max_threads {
+            eprintln!(
+                "WARNING: the number of proving threads is reduced to {threads} to fit in the memory budget of {memory_budget} MiB"
+            );
+        }
+
+        threads
+    }
+
+    /// Configure the thread pool used for proving when the first circuit is built.
+    pub fn apply_on_first_proof(self) {
+        *PENDING_PROVER_CONFIG.lock().unwrap() = Some(self);
+    }
+
+    /// Configure the thread pool used for proving.
+    /// The default pool is kept with a warning if it has already been built.
+    fn apply(&self) {
+        if self.threads.is_none() && self.memory_budget.is_none() {
+            return;
+        }
+
+        let threads = self.calc_threads();
+        if let Err(err) = rayon::ThreadPoolBuilder::new()
+            .num_threads(threads)
+            .build_global()
+        {
+            eprintln!("WARNING: fail to set the number of proving threads to {threads}: {err}");
+        }
+    }
+}
+
+/// Apply the config given by `ProverConfig::apply_on_first_proof` if it has not been applied yet.
+pub fn apply_pending_prover_config() {
+    if let Some(prover_config) = PENDING_PROVER_CONFIG.lock().unwrap().take() {
+        prover_config.apply();
+    }
+}
+
+/// The recent durations of proving a user transaction on this machine.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct ProvingTimes {
+    /// in seconds, oldest first
+    pub recent: Vec<f64>,
+}
+
+impl ProvingTimes {
+    /// Returns no measurements if the file does not exist.
+    pub fn read_from_file(file_path: &Path) -> anyhow::Result<Self> {
+        let file = match File::open(file_path) {
+            Ok(file) => file,
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(Self::default()),
+            Err(err) => {
+                return Err(err).with_context(|| format!("fail to open {}", file_path.display()))
+            }
+        };
+
+        serde_json::from_reader(file)
+            .with_context(|| format!("invalid proving times: {}", file_path.display()))
+    }
+
+    pub fn write_to_file(&self, file_path: &Path) -> anyhow::Result<()> {
+        let mut file = File::create(file_path)
+            .with_context(|| format!("fail to create {}", file_path.display()))?;
+        write!(file, "{}", serde_json::to_string(self)?)?;
+        file.flush()?;
+
+        Ok(())
+    }
+
+    pub fn push(&mut self, proving_time: Duration) {
+        self.recent.push(proving_time.as_secs_f64());
+        if self.recent.len() > MAX_PROVING_TIMES {
+            self.recent.drain(..self.recent.len() - MAX_PROVING_TIMES);
+        }
+    }
+
+    /// Returns `None` if nothing has been proved yet.
+    pub fn average(&self) -> Option<Duration> {
+        if self.recent.is_empty() {
+            return None;
+        }
+
+        let total: f64 = self.recent.iter().sum();
+
+        Some(Duration::from_secs_f64(total / self.recent.len() as f64))
+    }
+}
+
+/// Append the proving time to the file.
+pub fn record_proving_time(file_path: &Path, proving_time: Duration) -> anyhow::Result<()> {
+    let mut proving_times = ProvingTimes::read_from_file(file_path)?;
+    proving_times.push(proving_time);
+
+    proving_times.write_to_file(file_path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_calc_threads() {
+        let prover_config = ProverConfig {
+            threads: Some(4),
+            memory_budget: None,
+            url: None,
+        };
+        assert_eq!(prover_config.calc_threads(), 4);
+
+        let prover_config = ProverConfig {
+            threads: Some(4),
+            memory_budget: Some(estimate_proving_memory(2)),
+            url: None,
+        };
+        assert_eq!(prover_config.calc_threads(), 2);
+
+        let prover_config = ProverConfig {
+            threads: Some(4),
+            memory_budget: Some(estimate_proving_memory(1) - 1),
+            url: None,
+        };
+        assert_eq!(prover_config.calc_threads(), 1);
+    }
+
+    #[test]
+    fn test_proving_times() {
+        let mut proving_times = ProvingTimes::default();
+        assert_eq!(proving_times.average(), None);
+
+        proving_times.push(Duration::from_secs(2));
+        proving_times.push(Duration::from_secs(4));
+        assert_eq!(proving_times.average(), Some(Duration::from_secs(3)));
+
+        for _ in 0..MAX_PROVING_TIMES {
+            proving_times.push(Duration::from_secs(1));
+        }
+        assert_eq!(proving_times.recent.len(), MAX_PROVING_TIMES);
+        assert_eq!(proving_times.average(), Some(Duration::from_secs(1)));
+    }
+}
@@ -0,0 +1,133 @@
+use std::path::Path;
+
+use anyhow::Context;
+use intmax_rollup_interface::intmax_zkp_core::{
+    plonky2::field::goldilocks_field::GoldilocksField, transaction::asset::TokenKind,
+    zkdsa::account::Address,
+};
+use qrcode::{render::unicode, QrCode};
+
+use super::{hex_string::parse_intmax_address, terminal::TerminalOptions};
+
+type F = GoldilocksField;
+
+const PAYMENT_REQUEST_SCHEME: &str = "intmax:";
+const QR_IMAGE_EXTENSIONS: [&str; 3] = ["png", "jpg", "jpeg"];
+
+/// Encode the address and optionally the requested asset.
+/// e.g., `intmax:0x714bdc6f38947e6d?token=0x714bdc6f38947e6d&token_id=0x00&amount=10`
+pub fn encode_payment_request(
+    address: Address<F>,
+    requested_asset: Option<(TokenKind<F>, u64)>,
+) -> String {
+    let mut payload = format!("{PAYMENT_REQUEST_SCHEME}{address}");
+    if let Some((kind, amount)) = requested_asset {
+        payload += &format!(
+            "?token={}&token_id={}&amount={}",
+            kind.contract_address, kind.variable_index, amount
+        );
+    }
+
+    payload
+}
+
+/// Extract the address from a payment request or a plain address.
+pub fn decode_payment_request(payload: &str) -> anyhow::Result<Address<F>> {
+    let payload = payload.trim();
+    let payload = payload
+        .strip_prefix(PAYMENT_REQUEST_SCHEME)
+        .unwrap_or(payload);
+    let encoded_address = payload.split('?').next().unwrap_or_default();
+
+    parse_intmax_address("address", encoded_address)
+        .map_err(|_| anyhow::anyhow!("QR code does not contain an intmax address"))
+}
+
+/// Render the payload as a QR code with Unicode block characters,
+/// or with `#` if only ASCII characters are allowed.
+pub fn render_qr_code(payload: &str, terminal: &TerminalOptions) -> anyhow::Result<String> {
+    let code = QrCode::new(payload.as_bytes())?;
+    if terminal.ascii {
+        let rendered_code = code
+            .render::<char>()
+            .module_dimensions(2, 1)
+            .dark_color('#')
+            .light_color(' ')
+            .build();
+
+        return Ok(rendered_code);
+    }
+
+    let rendered_code = code
+        .render::<unicode::Dense1x2>()
+        .dark_color(unicode::Dense1x2::Light)
+        .light_color(unicode::Dense1x2::Dark)
+        .build();
+
+    Ok(rendered_code)
+}
+
+pub fn save_qr_code(payload: &str, image_path: &Path) -> anyhow::Result<()> {
+    let code = QrCode::new(payload.as_bytes())?;
+    let image = code.render::<image::Luma<u8>>().build();
+    image
+        .save(image_path)
+        .with_context(|| format!("fail to write {}", image_path.display()))?;
+
+    Ok(())
+}
+
+pub fn read_address_from_qr_image(image_path: &Path) -> anyhow::Result<Address<F>> {
+    let image = image::open(image_path)
+        .with_context(|| format!("fail to read {}", image_path.display()))?
+        .to_luma8();
+    let mut image = rqrr::PreparedImage::prepare(image);
+    let grids = image.detect_grids();
+    let grid = grids
+        .first()
+        .context("QR code was not found in the image")?;
+    let (_, payload) = grid.decode()?;
+
+    decode_payment_request(&payload)
+}
+
+/// If the given address argument is a path to a QR image, returns the address in it.
+/// Otherwise, the argument is returned as it is.
+pub fn resolve_qr_image_argument(address: String) -> anyhow::Result<String> {
+    let path = Path::new(&address);
+    let is_image = path
+        .extension()
+        .and_then(|extension| extension.to_str())
+        .map(|extension| QR_IMAGE_EXTENSIONS.contains(&extension.to_lowercase().as_str()))
+        .unwrap_or(false);
+    if !is_image || !path.is_file() {
+        return Ok(address);
+    }
+
+    Ok(read_address_from_qr_image(path)?.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use super::*;
+
+    #[test]
+    fn test_payment_request() {
+        let address = Address::<F>::from_str("0x714bdc6f38947e6d").unwrap();
+        let payload = encode_payment_request(address, None);
+        assert_eq!(decode_payment_request(&payload).unwrap(), address);
+        assert_eq!(
+            decode_payment_request("intmax:intmax1w99acmecj3lx6sfqt4w").unwrap(),
+            address
+        );
+
+        let kind = TokenKind {
+            contract_address: address,
+            variable_index: 1u8.into(),
+        };
+        let payload = encode_payment_request(address, Some((kind, 10)));
+        assert_eq!(decode_payment_request(&payload).unwrap(), address);
+    }
+}
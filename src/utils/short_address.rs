@@ -0,0 +1,111 @@
+//! Truncated addresses in tables, e.g., `0x714b…7e6d`.
+
+use super::terminal::TerminalOptions;
+
+/// the number of hex digits kept on each side of a truncated address
+const SHORT_ADDRESS_DIGITS: usize = 4;
+
+/// Renders hex addresses truncated for readability.
+/// If a known address shares the truncation, both are extended until they differ.
+#[derive(Clone, Debug)]
+pub struct AddressRenderer {
+    known_addresses: Vec<String>,
+    ellipsis: &'static str,
+    full_addresses: bool,
+}
+
+impl AddressRenderer {
+    /// `known_addresses` are the addresses which may appear in the same output,
+    /// e.g., the accounts in the wallet and the nicknamed addresses.
+    pub fn new(
+        terminal: &TerminalOptions,
+        known_addresses: impl IntoIterator<Item = String>,
+    ) -> Self {
+        let mut known_addresses = known_addresses
+            .into_iter()
+            .map(|address| address.to_lowercase())
+            .collect::<Vec<_>>();
+        known_addresses.sort();
+        known_addresses.dedup();
+
+        Self {
+            known_addresses,
+            ellipsis: if terminal.ascii { "..." } else { "…" },
+            full_addresses: terminal.full_addresses,
+        }
+    }
+
+    /// Returns the address as it is with `--full-addresses` or if it is not a hex string.
+    pub fn render(&self, address: &str) -> String {
+        if self.full_addresses {
+            return address.to_string();
+        }
+
+        let address = address.to_lowercase();
+        let digits = match address.strip_prefix("0x") {
+            Some(digits) if digits.chars().all(|c| c.is_ascii_hexdigit()) => digits,
+            _ => return address,
+        };
+
+        let mut n_digits = SHORT_ADDRESS_DIGITS;
+        while 2 * n_digits < digits.len() {
+            let prefix = &digits[..n_digits];
+            let suffix = &digits[digits.len() - n_digits..];
+            let collides = self.known_addresses.iter().any(|other| {
+                *other != address
+                    && other.len() == address.len()
+                    && other[2..].starts_with(prefix)
+                    && other.ends_with(suffix)
+            });
+            if !collides {
+                return format!("0x{prefix}{}{suffix}", self.ellipsis);
+            }
+
+            n_digits += 1;
+        }
+
+        address
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_render_address() {
+        let terminal = TerminalOptions::default();
+        let renderer = AddressRenderer::new(
+            &terminal,
+            [
+                "0x714bdc6f38947e6d".to_string(),
+                "0x714b0000000f7e6d".to_string(),
+                "0x0000000000000001".to_string(),
+            ],
+        );
+        assert_eq!(renderer.render("0x0000000000000001"), "0x0000…0001");
+        assert_eq!(renderer.render("0x1234567890abcdef"), "0x1234…cdef");
+
+        // The addresses sharing the truncation are extended.
+        assert_eq!(renderer.render("0x714bdc6f38947e6d"), "0x714bd…47e6d");
+        assert_eq!(renderer.render("0x714B0000000F7E6D"), "0x714b0…f7e6d");
+
+        // The address is not truncated if it is too short or not a hex string.
+        assert_eq!(renderer.render("0x12345678"), "0x12345678");
+        assert_eq!(renderer.render("alice"), "alice");
+
+        let terminal = TerminalOptions {
+            ascii: true,
+            ..Default::default()
+        };
+        let renderer = AddressRenderer::new(&terminal, vec![]);
+        assert_eq!(renderer.render("0x1234567890abcdef"), "0x1234...cdef");
+
+        let terminal = TerminalOptions {
+            full_addresses: true,
+            ..Default::default()
+        };
+        let renderer = AddressRenderer::new(&terminal, vec![]);
+        assert_eq!(renderer.render("0x1234567890abcdef"), "0x1234567890abcdef");
+    }
+}
@@ -0,0 +1,275 @@
+//! Named Ethereum secret keys used by `io` and `bridge` commands with `--signer <name>`.
+//! The keys are encrypted in the Ethereum keystore format into files only the owner can read,
+//! and only their addresses are kept in plain text.
+
+use std::{
+    collections::BTreeMap,
+    io::BufRead,
+    path::{Path, PathBuf},
+};
+
+use anyhow::Context;
+use dialoguer::Password;
+use intmax_interoperability_plugin::ethers::{
+    prelude::k256::ecdsa::SigningKey, types::H160, utils::secret_key_to_address,
+};
+use serde::{Deserialize, Serialize};
+use zeroize::Zeroizing;
+
+use super::{
+    hex_string::{parse_signing_key, read_private_key_env, PRIVATE_KEY_ENV},
+    key_management::encryption::write_encrypted_file,
+    loader::{read_json_file, LocalFileKind},
+    terminal::TerminalOptions,
+};
+
+pub const SIGNERS_DIR_NAME: &str = "signers";
+
+/// The file mapping the signer names to their addresses.
+pub const SIGNERS_FILE_NAME: &str = "signers.json";
+
+/// The password of the signers used in non-interactive mode.
+pub const SIGNER_PASSWORD_ENV: &str = "INTMAX_SIGNER_PASSWORD";
+
+const MAX_SIGNER_NAME_LEN: usize = 32;
+
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SignerTable {
+    pub signers: BTreeMap<String, H160>,
+}
+
+impl SignerTable {
+    /// Read the table in `signers_dir`. It is empty if no signer has been added.
+    pub fn read_from_dir(signers_dir: &Path) -> anyhow::Result<Self> {
+        let file_path = signers_dir.join(SIGNERS_FILE_NAME);
+        if !file_path.exists() {
+            return Ok(Self::default());
+        }
+
+        read_json_file(LocalFileKind::Signers, &file_path, None)
+    }
+
+    pub fn write_to_dir(&self, signers_dir: &Path) -> anyhow::Result<()> {
+        std::fs::create_dir_all(signers_dir)?;
+        let file_path = signers_dir.join(SIGNERS_FILE_NAME);
+        std::fs::write(&file_path, serde_json::to_string_pretty(self)?)
+            .with_context(|| format!("fail to write {}", file_path.display()))
+    }
+}
+
+fn validate_signer_name(name: &str) -> anyhow::Result<()> {
+    if name.is_empty() || name.len() > MAX_SIGNER_NAME_LEN {
+        anyhow::bail!("signer name must have 1 to {MAX_SIGNER_NAME_LEN} characters");
+    }
+    if !name
+        .chars()
+        .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+    {
+        anyhow::bail!("signer name must consist of ASCII letters, digits, '-' and '_'");
+    }
+    Ok(())
+}
+
+fn keystore_path(signers_dir: &Path, name: &str) -> PathBuf {
+    signers_dir.join(name)
+}
+
+/// Encrypt `signing_key` with `password` and store it as `name`. Returns the address of the key.
+pub fn add_signer(
+    signers_dir: &Path,
+    name: &str,
+    signing_key: &SigningKey,
+    password: &str,
+) -> anyhow::Result<H160> {
+    validate_signer_name(name)?;
+    let mut table = SignerTable::read_from_dir(signers_dir)?;
+    if table.signers.contains_key(name) {
+        anyhow::bail!("signer {name} already exists. Remove it first to replace it");
+    }
+
+    std::fs::create_dir_all(signers_dir)?;
+    let secret_key = Zeroizing::new(hex::encode(signing_key.to_bytes().as_slice()));
+    write_encrypted_file(&keystore_path(signers_dir, name), secret_key, password)?;
+
+    let address = secret_key_to_address(signing_key);
+    table.signers.insert(name.to_string(), address);
+    table.write_to_dir(signers_dir)?;
+
+    Ok(address)
+}
+
+/// Remove the signer and its encrypted key.
+pub fn remove_signer(signers_dir: &Path, name: &str) -> anyhow::Result<H160> {
+    let mut table = SignerTable::read_from_dir(signers_dir)?;
+    let address = table
+        .signers
+        .remove(name)
+        .with_context(|| format!("signer {name} was not found"))?;
+    match std::fs::remove_file(keystore_path(signers_dir, name)) {
+        Ok(()) => {}
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => {}
+        Err(err) => return Err(err).context("fail to remove the encrypted key"),
+    }
+    table.write_to_dir(signers_dir)?;
+
+    Ok(address)
+}
+
+/// Decrypt the secret key of the signer. It is returned in hex like `PRIVATE_KEY`.
+pub fn read_signer(
+    signers_dir: &Path,
+    name: &str,
+    password: &str,
+) -> anyhow::Result<(H160, Zeroizing<String>)> {
+    let table = SignerTable::read_from_dir(signers_dir)?;
+    let address = *table.signers.get(name).with_context(|| {
+        format!("signer {name} was not found. Add it with `intmax io signer add {name}`")
+    })?;
+    let secret_key = Zeroizing::new(
+        eth_keystore::decrypt_key(keystore_path(signers_dir, name), password)
+            .with_context(|| format!("fail to decrypt signer {name}"))?,
+    );
+    // The signers added before the key was kept in hex have the raw 32 bytes.
+    let secret_key = if secret_key.len() == 32 {
+        Zeroizing::new(hex::encode(&*secret_key))
+    } else {
+        Zeroizing::new(
+            std::str::from_utf8(&secret_key)
+                .with_context(|| format!("the key of signer {name} is not a secret key"))?
+                .to_string(),
+        )
+    };
+    let signing_key = parse_signing_key("signer", &secret_key)?;
+    if secret_key_to_address(&signing_key) != address {
+        anyhow::bail!("the key of signer {name} does not match {address:?} in {SIGNERS_FILE_NAME}");
+    }
+
+    Ok((address, secret_key))
+}
+
+/// Read the signer password from `INTMAX_SIGNER_PASSWORD`, or prompt it.
+/// A new password is asked twice.
+pub fn read_signer_password(
+    terminal: &TerminalOptions,
+    confirmation: bool,
+) -> anyhow::Result<Zeroizing<String>> {
+    if let Ok(password) = std::env::var(SIGNER_PASSWORD_ENV) {
+        if password.is_empty() {
+            anyhow::bail!("{SIGNER_PASSWORD_ENV} is empty");
+        }
+
+        return Ok(Zeroizing::new(password));
+    }
+
+    terminal.ensure_interactive(&format!(
+        "set the signer password with {SIGNER_PASSWORD_ENV}"
+    ))?;
+    let theme = terminal.theme();
+    let mut prompt = Password::with_theme(&*theme);
+    prompt.with_prompt("Signer password");
+    if confirmation {
+        prompt.with_confirmation("Confirm signer password", "Passwords do not match");
+    }
+
+    Ok(Zeroizing::new(prompt.interact()?))
+}
+
+/// Read an Ethereum secret key from the first line of stdin.
+pub fn read_signing_key_from_stdin() -> anyhow::Result<SigningKey> {
+    let mut secret_key = Zeroizing::new(String::new());
+    std::io::stdin().lock().read_line(&mut secret_key)?;
+    let secret_key = secret_key.trim();
+    if secret_key.is_empty() {
+        anyhow::bail!("secret key was not given from stdin");
+    }
+
+    // The key itself is not included in the error message.
+    parse_signing_key("secret key", secret_key).context("invalid secret key was given from stdin")
+}
+
+/// Returns the Ethereum secret key of the signer given by `--signer`, or `PRIVATE_KEY` if it is omitted,
+/// and displays the address signing the transactions.
+pub fn resolve_signer_secret_key(
+    signers_dir: &Path,
+    signer: Option<&str>,
+    terminal: &TerminalOptions,
+) -> anyhow::Result<Zeroizing<String>> {
+    let (address, secret_key, source) = if let Some(name) = signer {
+        let password = read_signer_password(terminal, false)?;
+        let (address, secret_key) = read_signer(signers_dir, name, &password)?;
+
+        (address, secret_key, name)
+    } else {
+        let secret_key = read_private_key_env()?;
+        let signing_key = parse_signing_key(PRIVATE_KEY_ENV, &secret_key)
+            .context("set a valid PRIVATE_KEY in .env file")?;
+
+        (
+            secret_key_to_address(&signing_key),
+            secret_key,
+            PRIVATE_KEY_ENV,
+        )
+    };
+    println!("Ethereum signer: {address:?} ({source})");
+
+    Ok(secret_key)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::test_utils::temp_dir;
+
+    #[test]
+    fn test_signer() {
+        let temp_dir = temp_dir();
+        let signers_dir = temp_dir.path().join("signers");
+        let signing_key = SigningKey::random(&mut rand::thread_rng());
+
+        let address = add_signer(&signers_dir, "alice", &signing_key, "password").unwrap();
+        assert_eq!(address, secret_key_to_address(&signing_key));
+        assert!(add_signer(&signers_dir, "alice", &signing_key, "password").is_err());
+        assert!(add_signer(&signers_dir, "../alice", &signing_key, "password").is_err());
+        assert_eq!(
+            SignerTable::read_from_dir(&signers_dir).unwrap().signers,
+            BTreeMap::from([("alice".to_string(), address)])
+        );
+
+        let (decrypted_address, secret_key) =
+            read_signer(&signers_dir, "alice", "password").unwrap();
+        assert_eq!(decrypted_address, address);
+        assert_eq!(*secret_key, hex::encode(signing_key.to_bytes().as_slice()));
+        assert!(read_signer(&signers_dir, "alice", "wrong password").is_err());
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let metadata = std::fs::metadata(keystore_path(&signers_dir, "alice")).unwrap();
+            assert_eq!(metadata.permissions().mode() & 0o777, 0o600);
+        }
+        assert!(!signers_dir.join("alice.tmp").exists());
+
+        // a signer encrypted as the raw bytes of the key
+        let legacy_signing_key = SigningKey::random(&mut rand::thread_rng());
+        eth_keystore::encrypt_key(
+            &signers_dir,
+            &mut rand::thread_rng(),
+            legacy_signing_key.to_bytes().as_slice(),
+            "password",
+            Some("bob"),
+        )
+        .unwrap();
+        let mut table = SignerTable::read_from_dir(&signers_dir).unwrap();
+        let legacy_address = secret_key_to_address(&legacy_signing_key);
+        table.signers.insert("bob".to_string(), legacy_address);
+        table.write_to_dir(&signers_dir).unwrap();
+        let (decrypted_address, secret_key) = read_signer(&signers_dir, "bob", "password").unwrap();
+        assert_eq!(decrypted_address, legacy_address);
+        assert_eq!(
+            *secret_key,
+            hex::encode(legacy_signing_key.to_bytes().as_slice())
+        );
+
+        assert_eq!(remove_signer(&signers_dir, "alice").unwrap(), address);
+        assert!(read_signer(&signers_dir, "alice", "password").is_err());
+    }
+}
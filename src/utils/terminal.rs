@@ -0,0 +1,126 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use dialoguer::{
+    theme::{ColorfulTheme, SimpleTheme, Theme},
+    Confirm,
+};
+
+static QUIET: AtomicBool = AtomicBool::new(false);
+
+/// Returns true if `--quiet` is given.
+pub fn is_quiet() -> bool {
+    QUIET.load(Ordering::Relaxed)
+}
+
+/// `println!` for progress messages, which are suppressed by `--quiet`.
+/// Use `println!` for the final results and `eprintln!` for warnings and errors.
+#[macro_export]
+macro_rules! progress {
+    ($($arg:tt)*) => {
+        if !$crate::utils::terminal::is_quiet() {
+            println!($($arg)*);
+        }
+    };
+}
+
+/// Output options shared by the renderers and prompts.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct TerminalOptions {
+    pub color: bool,
+    pub ascii: bool,
+    /// If false, every prompt fails instead of waiting for stdin.
+    pub interactive: bool,
+    /// If true, every confirmation is answered yes without prompting, except the typed ones.
+    pub assume_yes: bool,
+    /// If true, progress messages are suppressed.
+    pub quiet: bool,
+    /// If true, addresses in tables are not truncated.
+    pub full_addresses: bool,
+}
+
+impl Default for TerminalOptions {
+    fn default() -> Self {
+        Self {
+            color: true,
+            ascii: false,
+            interactive: true,
+            assume_yes: false,
+            quiet: false,
+            full_addresses: false,
+        }
+    }
+}
+
+impl TerminalOptions {
+    /// `NO_COLOR` disables colors and `TERM=dumb` disables both colors and non-ASCII characters
+    /// even if the flags are not given.
+    pub fn new(no_color: bool, ascii: bool, non_interactive: bool) -> Self {
+        let is_dumb_terminal = std::env::var("TERM")
+            .map(|term| term == "dumb")
+            .unwrap_or(false);
+        let is_no_color_set = std::env::var_os("NO_COLOR")
+            .map(|value| !value.is_empty())
+            .unwrap_or(false);
+
+        Self {
+            color: !(no_color || is_no_color_set || is_dumb_terminal),
+            ascii: ascii || is_dumb_terminal,
+            interactive: !non_interactive,
+            assume_yes: false,
+            quiet: false,
+            full_addresses: false,
+        }
+    }
+
+    pub fn with_assume_yes(mut self, assume_yes: bool) -> Self {
+        self.assume_yes = assume_yes;
+
+        self
+    }
+
+    pub fn with_full_addresses(mut self, full_addresses: bool) -> Self {
+        self.full_addresses = full_addresses;
+
+        self
+    }
+
+    /// Progress messages printed by `progress!` follow this setting.
+    pub fn with_quiet(mut self, quiet: bool) -> Self {
+        self.quiet = quiet;
+        QUIET.store(quiet, Ordering::Relaxed);
+
+        self
+    }
+
+    /// Fails in non-interactive mode. `hint` describes the flag used instead of the prompt.
+    pub fn ensure_interactive(&self, hint: &str) -> anyhow::Result<()> {
+        if !self.interactive {
+            anyhow::bail!("cannot prompt in non-interactive mode: {hint}");
+        }
+
+        Ok(())
+    }
+
+    /// Ask whether to continue. With `--yes`, returns true without prompting.
+    pub fn confirm(&self, prompt: &str) -> anyhow::Result<bool> {
+        if self.assume_yes {
+            return Ok(true);
+        }
+
+        self.ensure_interactive("skip the confirmation with --yes")?;
+        let response = Confirm::with_theme(&*self.theme())
+            .with_prompt(prompt)
+            .interact()?;
+
+        Ok(response)
+    }
+
+    /// Returns the theme of dialoguer prompts.
+    pub fn theme(&self) -> Box<dyn Theme> {
+        if self.color && !self.ascii {
+            Box::new(ColorfulTheme::default())
+        } else {
+            Box::new(SimpleTheme)
+        }
+    }
+}
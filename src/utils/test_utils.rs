@@ -0,0 +1,104 @@
+//! Helpers shared by the tests: temporary directories, which are removed even if a test panics,
+//! and a local HTTP server standing in for the aggregator.
+
+use std::{
+    io::{BufRead, BufReader, Read, Write},
+    net::TcpListener,
+    sync::{Arc, Mutex},
+};
+
+use tempfile::TempDir;
+
+/// Returns a new empty directory, which is removed when the returned value is dropped.
+pub fn temp_dir() -> TempDir {
+    tempfile::Builder::new()
+        .prefix("intmax-test-")
+        .tempdir()
+        .unwrap()
+}
+
+/// A local aggregator answering each request with `respond(method, path)`,
+/// where the path has no query. The requests are recorded as `"GET /block"`.
+pub struct MockAggregator {
+    pub url: String,
+    requests: Arc<Mutex<Vec<String>>>,
+}
+
+impl MockAggregator {
+    /// `respond` returns the status and the JSON body of the response.
+    pub fn spawn(respond: impl Fn(&str, &str) -> (u16, String) + Send + 'static) -> Self {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        let requests = Arc::new(Mutex::new(vec![]));
+        {
+            let requests = requests.clone();
+            std::thread::spawn(move || {
+                for stream in listener.incoming() {
+                    let mut stream = stream.unwrap();
+                    let mut reader = BufReader::new(&stream);
+                    let mut request_line = String::new();
+                    reader.read_line(&mut request_line).unwrap();
+                    let mut content_length = 0;
+                    loop {
+                        let mut header = String::new();
+                        reader.read_line(&mut header).unwrap();
+                        if header.trim().is_empty() {
+                            break;
+                        }
+                        if let Some((name, value)) = header.split_once(':') {
+                            if name.eq_ignore_ascii_case("content-length") {
+                                content_length = value.trim().parse().unwrap();
+                            }
+                        }
+                    }
+                    // The body is read, so that closing the connection does not reset it.
+                    let mut body = vec![0; content_length];
+                    reader.read_exact(&mut body).unwrap();
+
+                    let mut parts = request_line.split_whitespace();
+                    let method = parts.next().unwrap_or_default();
+                    let path = parts
+                        .next()
+                        .unwrap_or_default()
+                        .split('?')
+                        .next()
+                        .unwrap_or_default();
+                    requests.lock().unwrap().push(format!("{method} {path}"));
+
+                    let (status, body) = respond(method, path);
+                    write!(
+                        stream,
+                        "HTTP/1.1 {status} MOCK\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{body}",
+                        body.len()
+                    )
+                    .unwrap();
+                }
+            });
+        }
+
+        Self { url, requests }
+    }
+
+    /// Serve only the health check at `/`, and respond 404 to the other paths.
+    pub fn spawn_health_check_only() -> Self {
+        Self::spawn(|method, path| {
+            if method == "GET" && path == "/" {
+                (200, r#"{"name":"test","version":"v0.5.0"}"#.to_string())
+            } else {
+                (404, "not found".to_string())
+            }
+        })
+    }
+
+    pub fn requests(&self) -> Vec<String> {
+        self.requests.lock().unwrap().clone()
+    }
+
+    /// Returns how many times `request`, e.g., `"POST /tx/send"`, was received.
+    pub fn count(&self, request: &str) -> usize {
+        self.requests()
+            .iter()
+            .filter(|received| *received == request)
+            .count()
+    }
+}
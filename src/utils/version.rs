@@ -1,5 +1,84 @@
 use std::{fmt::Display, str::FromStr};
 
+use serde::Serialize;
+
+/// The oldest aggregator version this CLI can communicate with.
+pub const MIN_SUPPORTED_AGGREGATOR_VERSION: Version = Version {
+    major: 0,
+    minor: 5,
+    patch: 0,
+};
+
+/// The first aggregator version this CLI cannot communicate with.
+pub const MAX_UNSUPPORTED_AGGREGATOR_VERSION: Version = Version {
+    major: 0,
+    minor: 6,
+    patch: 0,
+};
+
+/// A Cargo feature of this CLI and the commands it provides.
+#[derive(Copy, Clone, Debug, Serialize)]
+pub struct FeatureInfo {
+    pub name: &'static str,
+    pub enabled: bool,
+    pub commands: &'static [&'static str],
+}
+
+pub const FEATURES: [FeatureInfo; 9] = [
+    FeatureInfo {
+        name: "parallel",
+        enabled: cfg!(feature = "parallel"),
+        commands: &[],
+    },
+    FeatureInfo {
+        name: "interoperability",
+        enabled: cfg!(feature = "interoperability"),
+        commands: &["io"],
+    },
+    FeatureInfo {
+        name: "bridge",
+        enabled: cfg!(feature = "bridge"),
+        commands: &["bridge"],
+    },
+    FeatureInfo {
+        name: "advanced",
+        enabled: cfg!(feature = "advanced"),
+        commands: &["block propose", "block approve", "block verify"],
+    },
+    FeatureInfo {
+        name: "verbose",
+        enabled: cfg!(feature = "verbose"),
+        commands: &[],
+    },
+    FeatureInfo {
+        name: "enable-polygon-zkevm",
+        enabled: cfg!(feature = "enable-polygon-zkevm"),
+        commands: &[],
+    },
+    FeatureInfo {
+        name: "dev",
+        enabled: cfg!(feature = "dev"),
+        commands: &["dev"],
+    },
+    FeatureInfo {
+        name: "custom-prover",
+        enabled: cfg!(feature = "custom-prover"),
+        commands: &[],
+    },
+    FeatureInfo {
+        name: "remote-prover",
+        enabled: cfg!(feature = "remote-prover"),
+        commands: &[],
+    },
+];
+
+/// The error for a command which is not compiled in this binary.
+pub fn feature_not_compiled_error(command: &str, feature: &str) -> anyhow::Error {
+    anyhow::anyhow!(
+        "`intmax {command}` is not compiled in this binary. Rebuild with `cargo build --release --features {feature}`, or run `intmax features` to list the compiled features."
+    )
+}
+
 #[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord)]
 pub struct Version {
     pub major: u64,
@@ -24,8 +103,9 @@ impl FromStr for Version {
         let mut version = version
             .trim_start_matches('v')
             .split('.')
-            .map(|v| v.parse().unwrap())
-            .collect::<Vec<_>>();
+            .map(|v| v.parse())
+            .collect::<Result<Vec<u64>, _>>()
+            .map_err(|_| anyhow::anyhow!("Given version has invalid format."))?;
         if version.is_empty() || version.len() > 3 {
             anyhow::bail!("Given version has invalid format.");
         }
@@ -40,6 +120,56 @@ impl FromStr for Version {
     }
 }
 
+/// Whether the aggregator of the given version is compatible with this CLI.
+/// Pre-release suffixes such as `-alpha` are ignored.
+pub fn is_supported_aggregator_version(version: &str) -> bool {
+    let version = version.split('-').next().unwrap_or_default();
+    match Version::from_str(version) {
+        Ok(version) => {
+            MIN_SUPPORTED_AGGREGATOR_VERSION <= version
+                && version < MAX_UNSUPPORTED_AGGREGATOR_VERSION
+        }
+        Err(_) => false,
+    }
+}
+
+/// The information attached to bug reports.
+#[derive(Clone, Debug, Serialize)]
+pub struct VersionInfo {
+    pub cli_version: &'static str,
+    pub git_commit: &'static str,
+    pub rollup_interface_version: &'static str,
+    pub supported_aggregator_versions: String,
+    pub features: Vec<&'static str>,
+}
+
+impl VersionInfo {
+    pub fn current() -> Self {
+        Self {
+            cli_version: env!("CARGO_PKG_VERSION"),
+            git_commit: env!("INTMAX_GIT_COMMIT"),
+            rollup_interface_version: env!("INTMAX_ROLLUP_INTERFACE_VERSION"),
+            supported_aggregator_versions: format!(
+                ">={MIN_SUPPORTED_AGGREGATOR_VERSION}, <{MAX_UNSUPPORTED_AGGREGATOR_VERSION}"
+            ),
+            features: FEATURES
+                .iter()
+                .filter(|feature| feature.enabled)
+                .map(|feature| feature.name)
+                .collect(),
+        }
+    }
+}
+
+impl Display for VersionInfo {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "intmax {} ({})", self.cli_version, self.git_commit)?;
+        writeln!(f, "rollup interface: {}", self.rollup_interface_version)?;
+        writeln!(f, "aggregator: {}", self.supported_aggregator_versions)?;
+        write!(f, "features: {}", self.features.join(", "))
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -57,5 +187,16 @@ mod tests {
         let version3_str = "v1";
         let version3: Version = version3_str.parse().unwrap();
         assert!(version2 < version3);
+
+        assert!("v0.5.x".parse::<Version>().is_err());
+    }
+
+    #[test]
+    fn test_is_supported_aggregator_version() {
+        assert!(is_supported_aggregator_version("v0.5.2"));
+        assert!(is_supported_aggregator_version("v0.5.0-alpha"));
+        assert!(!is_supported_aggregator_version("v0.4.3"));
+        assert!(!is_supported_aggregator_version("v0.6"));
+        assert!(!is_supported_aggregator_version("0.5.0"));
     }
 }
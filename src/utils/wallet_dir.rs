@@ -0,0 +1,262 @@
+//! The directory of the wallet and the nicknames of each aggregator, which is named after its URL.
+
+use std::path::{Path, PathBuf};
+
+use anyhow::Context;
+
+use super::{
+    key_management::{memory::WalletOnMemory, types::Wallet},
+    nickname::store::{NicknameStore, NICKNAME_FILE_NAME},
+};
+
+pub const WALLET_FILE_NAME: &str = "wallet";
+
+/// Returns the name of the wallet directory of the aggregator. The scheme, the default port and
+/// trailing slashes are removed and the host is lowercased, so that the same aggregator always shares one wallet.
+pub fn wallet_dir_name(aggregator_url: &str) -> String {
+    // A directory name has no scheme, so either default port is removed.
+    let (url, default_ports): (_, &[&str]) =
+        if let Some(url) = aggregator_url.strip_prefix("https://") {
+            (url, &[":443"])
+        } else if let Some(url) = aggregator_url.strip_prefix("http://") {
+            (url, &[":80"])
+        } else {
+            (aggregator_url, &[":443", ":80"])
+        };
+    let url = url.trim_end_matches('/');
+    let (host, path) = url.split_at(url.find('/').unwrap_or(url.len()));
+    let mut host = host.to_lowercase();
+    for port in default_ports {
+        if let Some(stripped_host) = host.strip_suffix(port) {
+            host = stripped_host.to_string();
+        }
+    }
+
+    host + path
+}
+
+/// The directory name derived by older versions, which only removed the scheme.
+fn legacy_wallet_dir_name(aggregator_url: &str) -> String {
+    aggregator_url
+        .trim_end_matches('/')
+        .split("://")
+        .last()
+        .unwrap()
+        .to_string()
+}
+
+/// Returns the wallet directory of the aggregator. The directory created by older versions for the same URL
+/// is used until it is migrated with `account migrate-data`.
+pub fn resolve_wallet_dir(intmax_dir: &Path, aggregator_url: &str) -> PathBuf {
+    let wallet_dir = intmax_dir.join(wallet_dir_name(aggregator_url));
+    let legacy_wallet_dir = intmax_dir.join(legacy_wallet_dir_name(aggregator_url));
+    if !wallet_dir.exists() && legacy_wallet_dir.join(WALLET_FILE_NAME).exists() {
+        return legacy_wallet_dir;
+    }
+
+    wallet_dir
+}
+
+/// Returns the directory named exactly after `aggregator_url` if it has a wallet, which is the case
+/// for the directories created by older versions, or the wallet directory otherwise.
+pub fn find_source_wallet_dir(intmax_dir: &Path, aggregator_url: &str) -> PathBuf {
+    let legacy_wallet_dir = intmax_dir.join(legacy_wallet_dir_name(aggregator_url));
+    if legacy_wallet_dir.join(WALLET_FILE_NAME).is_file() {
+        return legacy_wallet_dir;
+    }
+
+    intmax_dir.join(wallet_dir_name(aggregator_url))
+}
+
+/// Returns the other wallet directories whose names refer to the same aggregator as `wallet_dir`.
+pub fn find_duplicate_wallet_dirs(
+    intmax_dir: &Path,
+    wallet_dir: &Path,
+) -> anyhow::Result<Vec<PathBuf>> {
+    let dir_name = wallet_dir_name(&wallet_dir.strip_prefix(intmax_dir)?.to_string_lossy());
+    let mut duplicates = vec![];
+    for entry in std::fs::read_dir(intmax_dir)? {
+        let path = entry?.path();
+        if path == wallet_dir || !path.join(WALLET_FILE_NAME).is_file() {
+            continue;
+        }
+
+        let name = path.file_name().unwrap_or_default().to_string_lossy();
+        if wallet_dir_name(&name) == dir_name {
+            duplicates.push(path);
+        }
+    }
+    duplicates.sort();
+
+    Ok(duplicates)
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct MigrationReport {
+    pub moved_accounts: usize,
+    /// the accounts which the destination already has
+    pub skipped_accounts: Vec<String>,
+    pub moved_nicknames: usize,
+    /// the nicknames whose name or address is already used in the destination
+    pub skipped_nicknames: Vec<String>,
+    pub copied_files: Vec<String>,
+    /// the other files which the destination already has
+    pub skipped_files: Vec<String>,
+}
+
+/// Merge the wallet, the nicknames and the other files in `from_dir` into `to_dir`.
+/// The accounts and the nicknames which `to_dir` already has are never overwritten.
+/// `from_dir` is removed only if `remove_source` is true and everything was merged.
+/// Encrypted wallets are unlocked with `password`, and a new destination is encrypted if the source is.
+pub fn migrate_wallet_dir(
+    from_dir: &Path,
+    to_dir: &Path,
+    remove_source: bool,
+    password: Option<&str>,
+) -> anyhow::Result<MigrationReport> {
+    if !from_dir.join(WALLET_FILE_NAME).is_file() {
+        anyhow::bail!("no wallet was found in {}", from_dir.display());
+    }
+    if from_dir == to_dir {
+        anyhow::bail!("the source and the destination are the same directory");
+    }
+    std::fs::create_dir_all(to_dir)?;
+
+    let mut report = MigrationReport::default();
+
+    let read_wallet = |wallet_file_path: PathBuf| -> anyhow::Result<WalletOnMemory> {
+        let mut wallet = WalletOnMemory::read_index_from_file(wallet_file_path)?;
+        if let Some(password) = password {
+            wallet.unlock(password.to_string())?;
+        }
+        wallet.load_all_accounts()?;
+
+        Ok(wallet)
+    };
+    let source_wallet = read_wallet(from_dir.join(WALLET_FILE_NAME))?;
+    let wallet_file_path = to_dir.join(WALLET_FILE_NAME);
+    let mut wallet = if wallet_file_path.exists() {
+        read_wallet(wallet_file_path)?
+    } else {
+        let password = password
+            .filter(|_| source_wallet.encrypted)
+            .unwrap_or_default();
+        WalletOnMemory::new(wallet_file_path, password.to_string())
+    };
+    let skipped_accounts = wallet.merge(&source_wallet);
+    report.moved_accounts = source_wallet.data.len() - skipped_accounts.len();
+    report.skipped_accounts = skipped_accounts
+        .iter()
+        .map(|address| address.to_string())
+        .collect();
+    wallet.backup()?;
+
+    let source_nicknames = NicknameStore::open(from_dir.join(NICKNAME_FILE_NAME))?;
+    let mut nicknames = NicknameStore::open(to_dir.join(NICKNAME_FILE_NAME))?;
+    for (nickname, address) in source_nicknames.nickname_to_address.iter() {
+        if nicknames.nickname_to_address.contains_key(nickname)
+            || nicknames.address_to_nickname.contains_key(address)
+        {
+            report.skipped_nicknames.push(nickname.clone());
+            continue;
+        }
+
+        nicknames.insert(*address, nickname.clone())?;
+        report.moved_nicknames += 1;
+    }
+    nicknames.flush()?;
+
+    for entry in std::fs::read_dir(from_dir)? {
+        let entry = entry?;
+        let file_name = entry.file_name().to_string_lossy().to_string();
+        if file_name == WALLET_FILE_NAME
+            || file_name == NICKNAME_FILE_NAME
+            || !entry.file_type()?.is_file()
+        {
+            continue;
+        }
+
+        let to_path = to_dir.join(&file_name);
+        if to_path.exists() {
+            report.skipped_files.push(file_name);
+        } else {
+            std::fs::copy(entry.path(), &to_path)
+                .with_context(|| format!("fail to copy {}", entry.path().display()))?;
+            report.copied_files.push(file_name);
+        }
+    }
+    report.skipped_files.sort();
+    report.copied_files.sort();
+
+    if remove_source {
+        if !report.skipped_accounts.is_empty()
+            || !report.skipped_nicknames.is_empty()
+            || !report.skipped_files.is_empty()
+        {
+            anyhow::bail!(
+                "{} was not removed since some of its data was not merged",
+                from_dir.display()
+            );
+        }
+
+        std::fs::remove_dir_all(from_dir)
+            .with_context(|| format!("fail to remove {}", from_dir.display()))?;
+    }
+
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::fixtures::make_fixture_wallet;
+    use crate::utils::test_utils::temp_dir;
+
+    #[test]
+    fn test_wallet_dir_name() {
+        assert_eq!(wallet_dir_name("http://localhost:8080"), "localhost:8080");
+        assert_eq!(wallet_dir_name("http://localhost:8080/"), "localhost:8080");
+        assert_eq!(wallet_dir_name("http://LocalHost:8080//"), "localhost:8080");
+        assert_eq!(
+            wallet_dir_name("https://alpha.testnet.intmax.io:443/"),
+            "alpha.testnet.intmax.io"
+        );
+        assert_eq!(wallet_dir_name("http://example.com:443"), "example.com:443");
+        assert_eq!(
+            wallet_dir_name("https://example.com/API/"),
+            "example.com/API"
+        );
+        assert_eq!(wallet_dir_name("Example.com:80"), "example.com");
+    }
+
+    #[test]
+    fn test_migrate_wallet_dir() {
+        let temp_dir = temp_dir();
+        let intmax_dir = temp_dir.path().join("wallet-dir");
+        let from_dir = intmax_dir.join("LOCALHOST:8080");
+        let to_dir = intmax_dir.join("localhost:8080");
+        std::fs::create_dir_all(&from_dir).unwrap();
+        let wallet = make_fixture_wallet(from_dir.join(WALLET_FILE_NAME)).unwrap();
+        wallet.backup().unwrap();
+        std::fs::write(from_dir.join("register_intents.json"), "[]").unwrap();
+
+        assert_eq!(
+            find_duplicate_wallet_dirs(&intmax_dir, &to_dir).unwrap(),
+            vec![from_dir.clone()]
+        );
+
+        let report = migrate_wallet_dir(&from_dir, &to_dir, false, None).unwrap();
+        assert_eq!(report.moved_accounts, 1);
+        assert_eq!(report.copied_files, vec!["register_intents.json"]);
+        let migrated_wallet =
+            WalletOnMemory::read_from_file(to_dir.join(WALLET_FILE_NAME)).unwrap();
+        assert_eq!(migrated_wallet.default_account, wallet.default_account);
+
+        // The accounts already in the destination are kept.
+        let report = migrate_wallet_dir(&from_dir, &to_dir, false, None).unwrap();
+        assert_eq!(report.moved_accounts, 0);
+        assert_eq!(report.skipped_accounts.len(), 1);
+        assert!(migrate_wallet_dir(&from_dir, &to_dir, true, None).is_err());
+        assert!(from_dir.exists());
+    }
+}
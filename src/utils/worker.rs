@@ -0,0 +1,93 @@
+use std::{
+    sync::mpsc::{channel, Receiver, Sender},
+    thread,
+};
+
+/// A thread which runs `setup` as soon as it is spawned,
+/// and then processes the inputs sent to it one by one in order.
+/// This lets an expensive setup, e.g., building a circuit, overlap with
+/// requests made until the input is known.
+pub struct Worker<I, O> {
+    input_sender: Sender<I>,
+    output_receiver: Receiver<O>,
+}
+
+impl<I: Send + 'static, O: Send + 'static> Worker<I, O> {
+    pub fn spawn<S>(
+        setup: impl FnOnce() -> S + Send + 'static,
+        process: impl Fn(&S, I) -> O + Send + 'static,
+    ) -> Self {
+        let (input_sender, input_receiver) = channel::<I>();
+        let (output_sender, output_receiver) = channel();
+        thread::spawn(move || {
+            let state = setup();
+            // Stop when the worker is dropped.
+            for input in input_receiver {
+                if output_sender.send(process(&state, input)).is_err() {
+                    break;
+                }
+            }
+        });
+
+        Self {
+            input_sender,
+            output_receiver,
+        }
+    }
+
+    /// Blocks until the setup and the processing of `input` finish.
+    /// Fails if the thread panicked.
+    pub fn process(&self, input: I) -> anyhow::Result<O> {
+        self.input_sender
+            .send(input)
+            .map_err(|_| anyhow::anyhow!("worker thread stopped"))?;
+
+        self.output_receiver
+            .recv()
+            .map_err(|_| anyhow::anyhow!("worker thread stopped"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+
+    use super::*;
+
+    #[test]
+    fn test_worker() {
+        let events = Arc::new(Mutex::new(vec![]));
+        let (proposed_sender, proposed_receiver) = channel::<()>();
+
+        let setup_events = events.clone();
+        let process_events = events.clone();
+        let worker = Worker::spawn(
+            move || {
+                // The setup runs while the main thread is proposing.
+                proposed_receiver.recv().unwrap();
+                setup_events.lock().unwrap().push("setup".to_string());
+            },
+            move |_, message: u32| {
+                process_events
+                    .lock()
+                    .unwrap()
+                    .push(format!("sign {message}"));
+                message * 2
+            },
+        );
+
+        events.lock().unwrap().push("propose".to_string());
+        proposed_sender.send(()).unwrap();
+
+        // Each message is signed after it is given, in the given order.
+        assert_eq!(worker.process(1).unwrap(), 2);
+        assert_eq!(worker.process(2).unwrap(), 4);
+        assert_eq!(
+            *events.lock().unwrap(),
+            vec!["propose", "setup", "sign 1", "sign 2"]
+        );
+
+        let worker = Worker::spawn(|| (), |_, _: u32| -> u32 { panic!("fail to prove") });
+        assert!(worker.process(1).is_err());
+    }
+}